@@ -51,6 +51,17 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error("Function call exceeded the execution time limit of the node")]
+    ContractExecutionTimeout {
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("View calls to contract {contract_account_id} are disabled on this node")]
+    ContractBlacklisted {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("The state of account {requested_account_id} is too large")]
     TooLargeContractState {
         requested_account_id: near_primitives::types::AccountId,
@@ -0,0 +1,152 @@
+use near_primitives::types::{Gas, ShardId};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::metrics;
+
+/// Gas burnt per nanosecond assumed by the gas costs: 1 Tgas per millisecond.
+const GAS_PER_NANOSECOND: f64 = 1_000_000.0;
+/// Chunks which burnt less gas than this are not measured, as the time to apply them is
+/// dominated by the fixed costs, which aren't charged in gas.
+const MIN_MEASURED_GAS: Gas = 10_000_000_000_000;
+/// Weight of the latest chunk in the moving average of the ratio.
+const SMOOTHING: f64 = 0.05;
+/// Number of measured chunks of a shard before its drift is reported.
+const MIN_SAMPLES: u64 = 50;
+/// The average ratio above which applying chunks is considered too slow for the gas they
+/// burn, i.e. gas is underpriced and blocks may take longer than the block time.
+const SLOW_RATIO: f64 = 1.5;
+/// The average ratio below which applying chunks is considered too fast for the gas they
+/// burn, i.e. gas is overpriced and throughput is lost.
+const FAST_RATIO: f64 = 0.25;
+
+// Monitors of all the shards tracked by the node. Chunks are applied on different
+// threads, so the measurements are collected here rather than in the `Chain`.
+static APPLY_TIME_MONITOR: Lazy<Mutex<ApplyTimeMonitor>> =
+    Lazy::new(|| Mutex::new(ApplyTimeMonitor::default()));
+
+/// Records how long it took to apply a chunk of the shard which burnt `gas_burnt`.
+pub(crate) fn record(shard_id: ShardId, gas_burnt: Gas, elapsed: Duration) {
+    APPLY_TIME_MONITOR.lock().unwrap().record(shard_id, gas_burnt, elapsed);
+}
+
+/// Whether the time to apply chunks is in line with the gas they burn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Drift {
+    Normal,
+    Slow,
+    Fast,
+}
+
+#[derive(Debug, Default)]
+struct ShardApplyTime {
+    /// Moving average of the ratio of the time to apply a chunk to the time assumed for the
+    /// gas it burnt.
+    ratio: f64,
+    samples: u64,
+    drift: Option<Drift>,
+}
+
+/// Compares the time to apply chunks with the time assumed by the gas costs, which are
+/// calibrated so that 1 Tgas takes 1 ms to execute.
+///
+/// The ratio between the two is exported per shard, and a warning is logged when it
+/// stays out of the expected range for a while, which is an early sign that the gas
+/// costs don't match the hardware of the node or the workload of the shard.
+#[derive(Debug, Default)]
+pub(crate) struct ApplyTimeMonitor {
+    shards: HashMap<ShardId, ShardApplyTime>,
+}
+
+impl ApplyTimeMonitor {
+    /// Records the time to apply a chunk.  Returns the new drift of the shard, if it has
+    /// changed.
+    pub(crate) fn record(
+        &mut self,
+        shard_id: ShardId,
+        gas_burnt: Gas,
+        elapsed: Duration,
+    ) -> Option<Drift> {
+        if gas_burnt < MIN_MEASURED_GAS {
+            return None;
+        }
+        let ratio = elapsed.as_nanos() as f64 * GAS_PER_NANOSECOND / gas_burnt as f64;
+        let shard = self.shards.entry(shard_id).or_default();
+        shard.ratio = if shard.samples == 0 {
+            ratio
+        } else {
+            shard.ratio + SMOOTHING * (ratio - shard.ratio)
+        };
+        shard.samples += 1;
+        metrics::CHUNK_APPLY_TIME_PER_GAS_RATIO
+            .with_label_values(&[&shard_id.to_string()])
+            .set(shard.ratio);
+        if shard.samples < MIN_SAMPLES {
+            return None;
+        }
+        let drift = if shard.ratio > SLOW_RATIO {
+            Drift::Slow
+        } else if shard.ratio < FAST_RATIO {
+            Drift::Fast
+        } else {
+            Drift::Normal
+        };
+        if shard.drift == Some(drift) {
+            return None;
+        }
+        match drift {
+            Drift::Slow => warn!(
+                target: "chain", shard_id, ratio = shard.ratio,
+                "Applying chunks takes longer than their gas allows, gas may be underpriced for this node or workload"),
+            Drift::Fast => warn!(
+                target: "chain", shard_id, ratio = shard.ratio,
+                "Applying chunks takes much less time than their gas allows, gas may be overpriced for this node or workload"),
+            Drift::Normal if shard.drift.is_some() => info!(
+                target: "chain", shard_id, ratio = shard.ratio,
+                "Time to apply chunks is back in line with their gas"),
+            Drift::Normal => {}
+        }
+        shard.drift = Some(drift);
+        Some(drift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TGAS: Gas = 1_000_000_000_000;
+
+    #[test]
+    fn test_apply_time_drift() {
+        let mut monitor = ApplyTimeMonitor::default();
+        let ms = Duration::from_millis;
+        // Small chunks aren't measured.
+        assert_eq!(None, monitor.record(0, TGAS, ms(100)));
+        for _ in 1..MIN_SAMPLES {
+            assert_eq!(None, monitor.record(0, 100 * TGAS, ms(100)));
+        }
+        assert_eq!(Some(Drift::Normal), monitor.record(0, 100 * TGAS, ms(100)));
+        assert_eq!(None, monitor.record(0, 100 * TGAS, ms(100)));
+
+        // A single slow chunk isn't a drift.
+        assert_eq!(None, monitor.record(0, 100 * TGAS, ms(500)));
+        assert_eq!(Some(Drift::Slow), next_drift(&mut monitor, 0, ms(300)));
+        // Other shards are monitored separately.
+        assert_eq!(None, monitor.record(1, 100 * TGAS, ms(10)));
+        assert_eq!(Some(Drift::Normal), next_drift(&mut monitor, 0, ms(10)));
+        assert_eq!(Some(Drift::Fast), next_drift(&mut monitor, 0, ms(10)));
+    }
+
+    /// Records chunks of 100 Tgas until the drift of the shard changes.
+    fn next_drift(
+        monitor: &mut ApplyTimeMonitor,
+        shard_id: ShardId,
+        elapsed: Duration,
+    ) -> Option<Drift> {
+        (0..200).find_map(|_| monitor.record(shard_id, 100 * TGAS, elapsed))
+    }
+}
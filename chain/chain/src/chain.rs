@@ -34,8 +34,11 @@ use near_primitives::state_part::PartId;
 use near_primitives::syncing::{
     get_num_state_parts, ReceiptProofResponse, RootProof, ShardStateSyncResponseHeader,
     ShardStateSyncResponseHeaderV1, ShardStateSyncResponseHeaderV2, StateHeaderKey, StatePartKey,
+    STATE_PART_MEMORY_LIMIT,
+};
+use near_primitives::transaction::{
+    ExecutionOutcomeWithIdAndProof, OutcomeLocation, SignedTransaction,
 };
-use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
     AccountId, Balance, BlockExtra, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash,
@@ -50,6 +53,7 @@ use near_primitives::views::{
 };
 use near_store::{DBCol, ShardTries, StoreUpdate};
 
+use crate::apply_time_monitor;
 use crate::block_processing_utils::{
     BlockPreprocessInfo, BlockProcessingArtifact, BlocksInProcessing, DoneApplyChunkCallback,
 };
@@ -85,6 +89,10 @@ pub const MAX_ORPHAN_SIZE: usize = 1024;
 /// Maximum age of orhpan to store in the chain.
 const MAX_ORPHAN_AGE_SECS: u64 = 300;
 
+/// Maximum number of orphans at the same height, so that the forks at a single height
+/// can't crowd the orphans at the other heights out of the pool.
+pub const MAX_ORPHANS_PER_HEIGHT: usize = 16;
+
 // Number of orphan ancestors should be checked to request chunks
 // Orphans for which we will request for missing chunks must satisfy,
 // its NUM_ORPHAN_ANCESTORS_CHECK'th ancestor has been accepted
@@ -108,6 +116,11 @@ const ACCEPTABLE_TIME_DIFFERENCE: i64 = 12 * 10;
 /// Over this block height delta in advance if we are not chunk producer - route tx to upcoming validators.
 pub const TX_ROUTING_HEIGHT_HORIZON: BlockHeightDelta = 4;
 
+/// Memory budget for the state parts generated in one go when a state part is requested.
+/// Parts which follow the requested one are generated in parallel with it, as the peers
+/// syncing the state are going to request them next.
+const STATE_PARTS_MEMORY_BUDGET: u64 = 64 << 20;
+
 /// Private constant for 1 NEAR (copy from near/config.rs) used for reporting.
 const NEAR_BASE: Balance = 1_000_000_000_000_000_000_000_000;
 
@@ -149,13 +162,52 @@ impl Orphan {
     }
 }
 
+/// Why an orphan has been evicted from the pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum OrphanEvictionReason {
+    /// The orphan has been in the pool for longer than MAX_ORPHAN_AGE_SECS.
+    Expired,
+    /// There were already MAX_ORPHANS_PER_HEIGHT orphans at its height.
+    HeightCap,
+    /// The pool exceeded MAX_ORPHAN_SIZE and the orphan was among the highest ones.
+    PoolFull,
+}
+
+impl OrphanEvictionReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            OrphanEvictionReason::Expired => "expired",
+            OrphanEvictionReason::HeightCap => "height_cap",
+            OrphanEvictionReason::PoolFull => "pool_full",
+        }
+    }
+}
+
+/// An orphan in the pool and the block it is waiting for.
+#[derive(Debug)]
+pub struct OrphanStatus {
+    pub block_hash: CryptoHash,
+    pub height: BlockHeight,
+    pub prev_hash: CryptoHash,
+    /// The first ancestor of the orphan which is neither accepted nor in the pool, i.e. the
+    /// block which has to be accepted before the orphan can be processed.
+    pub missing_ancestor: CryptoHash,
+    /// When the orphan has been added to the pool.
+    pub added: Instant,
+    pub requested_missing_chunks: bool,
+}
+
 /// OrphanBlockPool stores information of all orphans that are waiting to be processed
 /// A block is added to the orphan pool when process_block failed because the block is an orphan
 /// A block is removed from the pool if
 /// 1) it is ready to be processed
 /// or
-/// 2) size of the pool exceeds MAX_ORPHAN_SIZE and the orphan was added a long time ago
-///    or the height is high
+/// 2) it has been in the pool for longer than MAX_ORPHAN_AGE_SECS
+/// or
+/// 3) there are already MAX_ORPHANS_PER_HEIGHT orphans at its height, in which case it isn't
+///    added at all
+/// or
+/// 4) size of the pool exceeds MAX_ORPHAN_SIZE and the height is high
 pub struct OrphanBlockPool {
     /// A map from block hash to a orphan block
     orphans: HashMap<CryptoHash, Orphan>,
@@ -196,9 +248,17 @@ impl OrphanBlockPool {
     /// Add a block to the orphan pool
     /// `requested_missing_chunks`: whether missing chunks has been requested for the orphan
     fn add(&mut self, orphan: Orphan, requested_missing_chunks: bool) {
+        self.evict_expired();
+
         let block_hash = *orphan.block.hash();
-        let height_hashes = self.height_idx.entry(orphan.block.header().height()).or_default();
-        height_hashes.push(*orphan.block.hash());
+        let height = orphan.block.header().height();
+        if self.height_idx.get(&height).map_or(0, Vec::len) >= MAX_ORPHANS_PER_HEIGHT {
+            debug!(target: "chain", ?block_hash, height, "Orphan pool: too many orphans at the height, dropping the orphan");
+            self.record_eviction(OrphanEvictionReason::HeightCap, 1);
+            return;
+        }
+        let height_hashes = self.height_idx.entry(height).or_default();
+        height_hashes.push(block_hash);
         let prev_hash_entries =
             self.prev_hash_idx.entry(*orphan.block.header().prev_hash()).or_default();
         prev_hash_entries.push(block_hash);
@@ -208,39 +268,65 @@ impl OrphanBlockPool {
         }
 
         if self.orphans.len() > MAX_ORPHAN_SIZE {
-            let old_len = self.orphans.len();
-
-            let mut removed_hashes: HashSet<CryptoHash> = HashSet::default();
-            self.orphans.retain(|_, ref mut x| {
-                let keep = x.added.elapsed() < TimeDuration::from_secs(MAX_ORPHAN_AGE_SECS);
-                if !keep {
-                    removed_hashes.insert(*x.block.hash());
-                }
-                keep
-            });
             let mut heights = self.height_idx.keys().cloned().collect::<Vec<u64>>();
             heights.sort_unstable();
             for h in heights.iter().rev() {
-                if let Some(hash) = self.height_idx.remove(h) {
-                    for h in hash {
-                        let _ = self.orphans.remove(&h);
-                        removed_hashes.insert(h);
-                    }
+                let hashes = self.height_idx.get(h).cloned().unwrap_or_default();
+                for hash in &hashes {
+                    self.remove(hash);
                 }
+                self.record_eviction(OrphanEvictionReason::PoolFull, hashes.len());
                 if self.orphans.len() < MAX_ORPHAN_SIZE {
                     break;
                 }
             }
-            self.height_idx.retain(|_, ref mut xs| xs.iter().any(|x| !removed_hashes.contains(x)));
-            self.prev_hash_idx
-                .retain(|_, ref mut xs| xs.iter().any(|x| !removed_hashes.contains(x)));
-            self.orphans_requested_missing_chunks.retain(|x| !removed_hashes.contains(x));
-
-            self.evicted += old_len - self.orphans.len();
         }
         metrics::NUM_ORPHANS.set(self.orphans.len() as i64);
     }
 
+    /// Removes the orphans which have been in the pool for longer than MAX_ORPHAN_AGE_SECS.
+    fn evict_expired(&mut self) {
+        let max_age = TimeDuration::from_secs(MAX_ORPHAN_AGE_SECS);
+        let expired: Vec<CryptoHash> = self
+            .orphans
+            .values()
+            .filter(|orphan| orphan.added.elapsed() >= max_age)
+            .map(|orphan| *orphan.block.hash())
+            .collect();
+        for hash in &expired {
+            self.remove(hash);
+        }
+        self.record_eviction(OrphanEvictionReason::Expired, expired.len());
+    }
+
+    /// Removes an orphan from the pool and its indices.
+    fn remove(&mut self, hash: &CryptoHash) -> Option<Orphan> {
+        let orphan = self.orphans.remove(hash)?;
+        self.orphans_requested_missing_chunks.remove(hash);
+        let height = orphan.block.header().height();
+        if let Some(hashes) = self.height_idx.get_mut(&height) {
+            hashes.retain(|x| x != hash);
+            if hashes.is_empty() {
+                self.height_idx.remove(&height);
+            }
+        }
+        if let Some(hashes) = self.prev_hash_idx.get_mut(orphan.prev_hash()) {
+            hashes.retain(|x| x != hash);
+            if hashes.is_empty() {
+                self.prev_hash_idx.remove(orphan.prev_hash());
+            }
+        }
+        Some(orphan)
+    }
+
+    fn record_eviction(&mut self, reason: OrphanEvictionReason, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.evicted += count;
+        metrics::ORPHANS_EVICTED_TOTAL.with_label_values(&[reason.as_str()]).inc_by(count as u64);
+    }
+
     pub fn contains(&self, hash: &CryptoHash) -> bool {
         self.orphans.contains_key(hash)
     }
@@ -262,18 +348,11 @@ impl OrphanBlockPool {
     /// This function is called when `prev_hash` is accepted, thus its children can be removed
     /// from the orphan pool and be processed.
     pub fn remove_by_prev_hash(&mut self, prev_hash: CryptoHash) -> Option<Vec<Orphan>> {
-        let mut removed_hashes: HashSet<CryptoHash> = HashSet::default();
-        let ret = self.prev_hash_idx.remove(&prev_hash).map(|hs| {
-            hs.iter()
-                .filter_map(|h| {
-                    removed_hashes.insert(*h);
-                    self.orphans_requested_missing_chunks.remove(h);
-                    self.orphans.remove(h)
-                })
-                .collect()
-        });
-
-        self.height_idx.retain(|_, ref mut xs| xs.iter().any(|x| !removed_hashes.contains(x)));
+        let ret = self
+            .prev_hash_idx
+            .get(&prev_hash)
+            .cloned()
+            .map(|hs| hs.iter().filter_map(|h| self.remove(h)).collect());
 
         metrics::NUM_ORPHANS.set(self.orphans.len() as i64);
         ret
@@ -313,6 +392,44 @@ impl OrphanBlockPool {
         res
     }
 
+    /// Returns the orphans in the pool ordered by height, along with the blocks they are
+    /// waiting for.
+    pub fn list_orphans(&self) -> Vec<OrphanStatus> {
+        let mut res: Vec<OrphanStatus> = self
+            .orphans
+            .values()
+            .map(|orphan| {
+                let block_hash = *orphan.block.hash();
+                OrphanStatus {
+                    block_hash,
+                    height: orphan.block.header().height(),
+                    prev_hash: *orphan.prev_hash(),
+                    missing_ancestor: self.missing_ancestor(orphan),
+                    added: orphan.added,
+                    requested_missing_chunks: self
+                        .orphans_requested_missing_chunks
+                        .contains(&block_hash),
+                }
+            })
+            .collect();
+        res.sort_by_key(|orphan| (orphan.height, orphan.block_hash));
+        res
+    }
+
+    /// Walks up the ancestors of the orphan which are orphans themselves, to the first one
+    /// which isn't in the pool.
+    fn missing_ancestor(&self, orphan: &Orphan) -> CryptoHash {
+        let mut hash = *orphan.prev_hash();
+        // The orphans can't form a loop, but the walk is bounded nevertheless.
+        for _ in 0..self.orphans.len() {
+            match self.orphans.get(&hash) {
+                Some(parent) => hash = *parent.prev_hash(),
+                None => break,
+            }
+        }
+        hash
+    }
+
     pub fn list_orphans_by_height(&self) -> Vec<BlockStatusView> {
         let mut rtn = Vec::new();
         for (height, orphans) in &self.height_idx {
@@ -960,6 +1077,23 @@ impl Chain {
         chain_store_update.commit()
     }
 
+    /// Removes the locations of the transactions and receipts executed more than
+    /// `num_blocks_to_keep` blocks before the head.  The outcomes are kept until they are
+    /// garbage collected.
+    ///
+    /// `gc_height_limit` limits how many heights will the function process.
+    pub fn clear_outcome_locations(
+        &mut self,
+        num_blocks_to_keep: NumBlocks,
+        gc_height_limit: BlockHeightDelta,
+    ) -> Result<(), Error> {
+        let head = self.store.head()?;
+        let stop_height = head.height.saturating_sub(num_blocks_to_keep);
+        let mut chain_store_update = self.store.store_update();
+        chain_store_update.clear_outcome_locations(stop_height, gc_height_limit)?;
+        chain_store_update.commit()
+    }
+
     pub fn clear_forks_data(
         &mut self,
         tries: ShardTries,
@@ -2698,25 +2832,38 @@ impl Chain {
         if part_id >= num_parts {
             return Err(Error::InvalidStateRequest("part_id out of bound".to_string()));
         }
-        let state_part = self
+        // Generate the requested part together with the next parts which aren't cached yet,
+        // as many as there are threads to spare and fit into the memory budget.
+        let max_parts = (STATE_PARTS_MEMORY_BUDGET / STATE_PART_MEMORY_LIMIT.as_u64())
+            .min(std::cmp::max(rayon::current_num_threads() / 2, 1) as u64)
+            .max(1);
+        let mut part_ids = vec![PartId::new(part_id, num_parts)];
+        for next_part_id in part_id + 1..num_parts {
+            if part_ids.len() as u64 >= max_parts {
+                break;
+            }
+            let next_key = StatePartKey(sync_hash, shard_id, next_part_id).try_to_vec()?;
+            if !self.store.store().exists(DBCol::StateParts, &next_key)? {
+                part_ids.push(PartId::new(next_part_id, num_parts));
+            }
+        }
+        let state_parts = self
             .runtime_adapter
-            .obtain_state_part(
-                shard_id,
-                &sync_prev_hash,
-                &state_root,
-                PartId::new(part_id, num_parts),
-            )
-            .log_storage_error("obtain_state_part fail")?;
+            .obtain_state_parts(shard_id, &sync_prev_hash, &state_root, &part_ids)
+            .log_storage_error("obtain_state_parts fail")?;
 
         // Before saving State Part data, we need to make sure we can calculate and save State Header
         self.get_state_response_header(shard_id, sync_hash)?;
 
         // Saving the part data
         let mut store_update = self.store.store().store_update();
-        store_update.set(DBCol::StateParts, &key, &state_part);
+        for (part_id, state_part) in part_ids.iter().zip(state_parts.iter()) {
+            let key = StatePartKey(sync_hash, shard_id, part_id.idx).try_to_vec()?;
+            store_update.set(DBCol::StateParts, &key, state_part);
+        }
         store_update.commit()?;
 
-        Ok(state_part)
+        Ok(state_parts.into_iter().next().expect("the requested part is always generated"))
     }
 
     pub fn set_state_header(
@@ -3573,6 +3720,7 @@ impl Chain {
                             shard_id)
                         .entered();
                         let _timer = CryptoHashTimer::new(chunk.chunk_hash().0);
+                        let apply_start = Clock::instant();
                         match runtime_adapter.apply_transactions(
                             shard_id,
                             chunk_inner.prev_state_root(),
@@ -3592,6 +3740,11 @@ impl Chain {
                             state_patch,
                         ) {
                             Ok(apply_result) => {
+                                apply_time_monitor::record(
+                                    shard_id,
+                                    apply_result.total_gas_burnt,
+                                    apply_start.elapsed(),
+                                );
                                 let apply_split_result_or_state_changes =
                                     if will_shard_layout_change {
                                         Some(ChainUpdate::apply_split_state_changes(
@@ -3938,6 +4091,30 @@ impl Chain {
         self.store.get_chunk(chunk_hash)
     }
 
+    /// Gets a block by hash, falling back to its pinned copy if the block was garbage
+    /// collected.
+    pub fn get_block_or_pinned(&self, hash: &CryptoHash) -> Result<Block, Error> {
+        match self.store.get_block(hash) {
+            Err(Error::DBNotFoundErr(err)) => match self.store.get_pinned_block(hash)? {
+                Some(pinned) => Ok(pinned.block),
+                None => Err(Error::DBNotFoundErr(err)),
+            },
+            result => result,
+        }
+    }
+
+    /// Gets a chunk from hash, falling back to the copies of the pinned blocks if the chunk
+    /// was garbage collected.
+    pub fn get_chunk_or_pinned(&self, chunk_hash: &ChunkHash) -> Result<Arc<ShardChunk>, Error> {
+        match self.store.get_chunk(chunk_hash) {
+            Err(Error::ChunkMissing(_)) => match self.store.get_pinned_chunk(chunk_hash)? {
+                Some(chunk) => Ok(Arc::new(chunk)),
+                None => Err(Error::ChunkMissing(chunk_hash.clone())),
+            },
+            result => result,
+        }
+    }
+
     /// Gets a chunk from header.
     #[inline]
     pub fn get_chunk_clone_from_header(
@@ -4119,6 +4296,11 @@ impl Chain {
         self.orphans.len_evicted()
     }
 
+    /// Returns the orphans currently in the orphan pool, with the blocks they are waiting for.
+    pub fn orphans_status(&self) -> Vec<OrphanStatus> {
+        self.orphans.list_orphans()
+    }
+
     /// Check if hash is for a known orphan.
     #[inline]
     pub fn is_orphan(&self, hash: &CryptoHash) -> bool {
@@ -4154,6 +4336,9 @@ impl Chain {
         &self,
         id: &CryptoHash,
     ) -> Result<ExecutionOutcomeWithIdAndProof, Error> {
+        if let Some((_, outcome)) = self.get_located_outcome(id)? {
+            return Ok(outcome);
+        }
         let outcomes = self.store.get_outcomes_by_id(id)?;
         outcomes
             .into_iter()
@@ -4167,6 +4352,36 @@ impl Chain {
             .ok_or_else(|| Error::DBNotFoundErr(format!("EXECUTION OUTCOME: {}", id)))
     }
 
+    /// Returns the height and shard in which the transaction or receipt with the given id was
+    /// executed on the canonical chain, if this node has applied the chunk and the location
+    /// hasn't been removed yet.
+    pub fn get_outcome_location(&self, id: &CryptoHash) -> Result<Option<OutcomeLocation>, Error> {
+        Ok(self.get_located_outcome(id)?.map(|(location, _)| location))
+    }
+
+    /// Returns the indexed location of the transaction or receipt with the given id and its
+    /// outcome there, if the block at that height is still the one which executed it.
+    fn get_located_outcome(
+        &self,
+        id: &CryptoHash,
+    ) -> Result<Option<(OutcomeLocation, ExecutionOutcomeWithIdAndProof)>, Error> {
+        let location = match self.store.get_outcome_location(id)? {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+        let block_hash = match self.store.get_block_hash_by_height(location.height) {
+            Ok(block_hash) => block_hash,
+            Err(Error::DBNotFoundErr(_)) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let outcome = self
+            .store
+            .get_outcomes_by_id(id)?
+            .into_iter()
+            .find(|outcome| outcome.block_hash == block_hash);
+        Ok(outcome.map(|outcome| (location, outcome)))
+    }
+
     /// Retrieve the up to `max_headers_returned` headers on the main chain
     /// `hashes`: a list of block "locators". `hashes` should be ordered from older blocks to
     ///           more recent blocks. This function will find the first block in `hashes`
@@ -4,10 +4,11 @@ pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThreshold
 pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
 pub use near_chain_primitives::{self, Error};
 pub use near_primitives::receipt::ReceiptResult;
-pub use store::{ChainStore, ChainStoreAccess, ChainStoreUpdate};
+pub use store::{ChainStore, ChainStoreAccess, ChainStoreUpdate, PinnedBlock};
 pub use store_validator::{ErrorMessage, StoreValidator};
 pub use types::{Block, BlockHeader, BlockStatus, ChainGenesis, Provenance, RuntimeAdapter};
 
+mod apply_time_monitor;
 mod block_processing_utils;
 pub mod blocks_delay_tracker;
 pub mod chain;
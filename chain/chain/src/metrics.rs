@@ -1,6 +1,7 @@
 use near_metrics::{
-    exponential_buckets, try_create_histogram, try_create_histogram_vec, try_create_int_counter,
-    try_create_int_gauge, Histogram, HistogramVec, IntCounter, IntGauge,
+    exponential_buckets, try_create_gauge_vec, try_create_histogram, try_create_histogram_vec,
+    try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge, GaugeVec, Histogram,
+    HistogramVec, IntCounter, IntCounterVec, IntGauge,
 };
 use once_cell::sync::Lazy;
 
@@ -39,6 +40,14 @@ pub static VALIDATOR_ACTIVE_TOTAL: Lazy<IntGauge> = Lazy::new(|| {
 });
 pub static NUM_ORPHANS: Lazy<IntGauge> =
     Lazy::new(|| try_create_int_gauge("near_num_orphans", "Number of orphan blocks.").unwrap());
+pub static ORPHANS_EVICTED_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_orphans_evicted_total",
+        "Number of orphan blocks evicted from the orphan pool, by the reason of the eviction",
+        &["reason"],
+    )
+    .unwrap()
+});
 pub static HEADER_HEAD_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_header_head_height", "Height of the header head").unwrap()
 });
@@ -79,3 +88,11 @@ pub static BLOCK_MISSING_CHUNKS_DELAY: Lazy<Histogram> = Lazy::new(|| {
     )
     .unwrap()
 });
+pub static CHUNK_APPLY_TIME_PER_GAS_RATIO: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_chunk_apply_time_per_gas_ratio",
+        "Moving average of the time to apply a chunk divided by the time assumed for the gas it burnt (1 Tgas per ms)",
+        &["shard_id"],
+    )
+    .unwrap()
+});
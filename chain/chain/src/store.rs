@@ -1,3 +1,4 @@
+use std::cmp::max;
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::io;
@@ -22,7 +23,7 @@ use near_primitives::syncing::{
     StatePartKey,
 };
 use near_primitives::transaction::{
-    ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof, SignedTransaction,
+    ExecutionOutcomeWithId, ExecutionOutcomeWithIdAndProof, OutcomeLocation, SignedTransaction,
 };
 use near_primitives::trie_key::{trie_key_parsers, TrieKey};
 use near_primitives::types::chunk_extra::ChunkExtra;
@@ -36,7 +37,7 @@ use near_primitives::views::LightClientBlockView;
 use near_store::{
     DBCol, KeyForStateChanges, ShardTries, Store, StoreUpdate, WrappedTrieChanges, CHUNK_TAIL_KEY,
     FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY, LARGEST_TARGET_HEIGHT_KEY,
-    LATEST_KNOWN_KEY, TAIL_KEY,
+    LATEST_KNOWN_KEY, OUTCOME_LOCATIONS_TAIL_KEY, TAIL_KEY,
 };
 
 use crate::types::{Block, BlockHeader, LatestKnown};
@@ -62,6 +63,15 @@ pub enum GCMode {
     StateSync { clear_block_info: bool },
 }
 
+/// Copy of a block pinned by the node operator, which is kept after the block is garbage
+/// collected, e.g. while it is under investigation.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PinnedBlock {
+    pub block: Block,
+    /// Chunks included in the block which this node had when the block was pinned.
+    pub chunks: Vec<ShardChunk>,
+}
+
 fn get_height_shard_id(height: BlockHeight, shard_id: ShardId) -> Vec<u8> {
     let mut res = Vec::with_capacity(40);
     res.extend_from_slice(&height.to_le_bytes());
@@ -577,6 +587,93 @@ impl ChainStore {
         Ok(self.store.get_ser(DBCol::TransactionResult, id.as_ref())?.unwrap_or_else(|| vec![]))
     }
 
+    /// Returns the height and shard in which the transaction or receipt with the given id
+    /// was executed, if this node has applied the chunk.
+    pub fn get_outcome_location(&self, id: &CryptoHash) -> Result<Option<OutcomeLocation>, Error> {
+        Ok(self.store.get_ser(DBCol::OutcomeLocations, id.as_ref())?)
+    }
+
+    /// Height from which the outcome locations are kept, see `clear_outcome_locations`.
+    fn outcome_locations_tail(&self) -> Result<BlockHeight, Error> {
+        self.store
+            .get_ser(DBCol::BlockMisc, OUTCOME_LOCATIONS_TAIL_KEY)
+            .map(|option| option.unwrap_or_else(|| self.genesis_height))
+            .map_err(|e| e.into())
+    }
+
+    /// Returns the copy of the block with the given hash, if it is pinned.
+    pub fn get_pinned_block(&self, hash: &CryptoHash) -> Result<Option<PinnedBlock>, Error> {
+        Ok(self.store.get_ser(DBCol::PinnedBlocks, hash.as_ref())?)
+    }
+
+    /// Returns the copy of the chunk with the given hash, if it is included in a pinned block.
+    pub fn get_pinned_chunk(&self, chunk_hash: &ChunkHash) -> Result<Option<ShardChunk>, Error> {
+        let block_hash: CryptoHash =
+            match self.store.get_ser(DBCol::PinnedChunks, chunk_hash.as_ref())? {
+                Some(block_hash) => block_hash,
+                None => return Ok(None),
+            };
+        Ok(self.get_pinned_block(&block_hash)?.and_then(|pinned| {
+            pinned.chunks.into_iter().find(|chunk| &chunk.chunk_hash() == chunk_hash)
+        }))
+    }
+
+    /// Returns the copies of all the pinned blocks together with their sizes in bytes.
+    pub fn get_pinned_blocks(&self) -> Result<Vec<(PinnedBlock, u64)>, Error> {
+        self.store
+            .iter(DBCol::PinnedBlocks)
+            .map(|item| {
+                let (_, value) = item?;
+                Ok((PinnedBlock::try_from_slice(&value)?, value.len() as u64))
+            })
+            .collect()
+    }
+
+    /// Copies the block with the given hash and its chunks which this node has, for pinning.
+    pub fn make_pinned_block(&self, hash: &CryptoHash) -> Result<PinnedBlock, Error> {
+        let block = self.get_block(hash)?;
+        let mut chunks = vec![];
+        for chunk_header in block.chunks().iter() {
+            if chunk_header.height_included() != block.header().height() {
+                continue;
+            }
+            if let Ok(chunk) = self.get_chunk(&chunk_header.chunk_hash()) {
+                chunks.push(ShardChunk::clone(&chunk));
+            }
+        }
+        Ok(PinnedBlock { block, chunks })
+    }
+
+    /// Pins the blocks, so that their copies are kept once they are garbage collected.
+    pub fn save_pinned_blocks(&self, blocks: &[PinnedBlock]) -> Result<(), Error> {
+        let mut store_update = self.store.store_update();
+        for pinned in blocks {
+            store_update.set_ser(DBCol::PinnedBlocks, pinned.block.hash().as_ref(), pinned)?;
+            for chunk in &pinned.chunks {
+                store_update.set_ser(
+                    DBCol::PinnedChunks,
+                    chunk.chunk_hash().as_ref(),
+                    pinned.block.hash(),
+                )?;
+            }
+        }
+        store_update.commit().map_err(|err| err.into())
+    }
+
+    /// Unpins the blocks with the given hashes.
+    pub fn remove_pinned_blocks(&self, hashes: &[CryptoHash]) -> Result<(), Error> {
+        let mut store_update = self.store.store_update();
+        for hash in hashes {
+            if let Some(pinned) = self.get_pinned_block(hash)? {
+                for chunk in &pinned.chunks {
+                    store_update.delete(DBCol::PinnedChunks, chunk.chunk_hash().as_ref());
+                }
+            }
+            store_update.delete(DBCol::PinnedBlocks, hash.as_ref());
+        }
+        store_update.commit().map_err(|err| err.into())
+    }
+
     /// Returns a vector of Outcome ids for given block and shard id
     pub fn get_outcomes_by_block_hash_and_shard_id(
         &self,
@@ -2022,6 +2119,50 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// Removes the locations of the transactions and receipts executed below `stop_height`,
+    /// keeping the outcomes themselves.  Locations of outcomes which were executed again
+    /// at a higher height (on a fork) are kept.
+    ///
+    /// `gc_height_limit` indicates limit of how many non-empty heights to process.
+    pub fn clear_outcome_locations(
+        &mut self,
+        stop_height: BlockHeight,
+        gc_height_limit: BlockHeightDelta,
+    ) -> Result<(), Error> {
+        // Below the tail the locations are removed together with the outcomes.
+        let mut height = max(self.chain_store.outcome_locations_tail()?, self.tail()?);
+        let mut remaining = gc_height_limit;
+        while height < stop_height && remaining > 0 {
+            let block_hash = match self.get_block_hash_by_height(height) {
+                Ok(block_hash) => block_hash,
+                Err(Error::DBNotFoundErr(_)) => {
+                    height += 1;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            remaining -= 1;
+            let block = self.get_block(&block_hash)?;
+            for chunk_header in block.chunks().iter().filter(|h| h.height_included() == height) {
+                let outcome_ids = self.chain_store.get_outcomes_by_block_hash_and_shard_id(
+                    &block_hash,
+                    chunk_header.shard_id(),
+                )?;
+                for outcome_id in outcome_ids {
+                    let location = self.chain_store.get_outcome_location(&outcome_id)?;
+                    if location.map_or(false, |location| location.height == height) {
+                        self.gc_col(DBCol::OutcomeLocations, outcome_id.as_bytes());
+                    }
+                }
+            }
+            height += 1;
+        }
+        let mut store_update = self.store().store_update();
+        store_update.set_ser(DBCol::BlockMisc, OUTCOME_LOCATIONS_TAIL_KEY, &height)?;
+        self.merge(store_update);
+        Ok(())
+    }
+
     fn get_shard_uids_to_gc(
         &mut self,
         runtime_adapter: &dyn RuntimeAdapter,
@@ -2293,6 +2434,9 @@ impl<'a> ChainStoreUpdate<'a> {
                 outcomes_with_id.retain(|outcome| &outcome.block_hash != block_hash);
                 if outcomes_with_id.is_empty() {
                     self.gc_col(DBCol::TransactionResult, outcome_id.as_bytes());
+                    if self.chain_store.get_outcome_location(&outcome_id)?.is_some() {
+                        self.gc_col(DBCol::OutcomeLocations, outcome_id.as_bytes());
+                    }
                 } else {
                     store_update.set_ser(
                         DBCol::TransactionResult,
@@ -2404,6 +2548,9 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::OutcomeIds => {
                 store_update.delete(col, key);
             }
+            DBCol::OutcomeLocations => {
+                store_update.delete(col, key);
+            }
             DBCol::StateDlInfos => {
                 store_update.delete(col, key);
             }
@@ -2428,6 +2575,10 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::PeerComponent
             | DBCol::LastComponentNonce
             | DBCol::ComponentEdges
+            | DBCol::PeerAccessRules
+            | DBCol::PinnedBlocks
+            | DBCol::RoutedMessages
+            | DBCol::PinnedChunks
             | DBCol::EpochInfo
             | DBCol::EpochStart
             | DBCol::EpochValidatorInfo
@@ -2748,6 +2899,39 @@ impl<'a> ChainStoreUpdate<'a> {
                 &get_block_shard_id(block_hash, *shard_id),
                 &ids,
             )?;
+            // Only the outcomes on the canonical chain are indexed, see
+            // `DBCol::OutcomeLocations`.
+            let height = self.get_block_height(block_hash)?;
+            if self.get_block_hash_by_height(height).ok() != Some(*block_hash) {
+                continue;
+            }
+            let location = OutcomeLocation { height, shard_id: *shard_id };
+            for id in ids {
+                store_update.set_ser(DBCol::OutcomeLocations, id.as_ref(), &location)?;
+            }
+        }
+        // Blocks which became canonical may have been applied while they were on a fork, so
+        // their outcomes are indexed now.
+        for (height, block_hash) in self.chain_store_cache_update.height_to_hashes.iter() {
+            let block_hash = match block_hash {
+                Some(block_hash) => block_hash,
+                None => continue,
+            };
+            let num_shards = self.get_block_header(block_hash)?.chunk_mask().len() as ShardId;
+            for shard_id in 0..num_shards {
+                if self.chain_store_cache_update.outcome_ids.contains_key(&(*block_hash, shard_id))
+                {
+                    // Indexed above.
+                    continue;
+                }
+                let location = OutcomeLocation { height: *height, shard_id };
+                for id in self
+                    .chain_store
+                    .get_outcomes_by_block_hash_and_shard_id(block_hash, shard_id)?
+                {
+                    store_update.set_ser(DBCol::OutcomeLocations, id.as_ref(), &location)?;
+                }
+            }
         }
         for (receipt_id, shard_id) in self.chain_store_cache_update.receipt_id_to_shard_id.iter() {
             let data = shard_id.try_to_vec()?;
@@ -3019,6 +3203,7 @@ mod tests {
     use near_primitives::epoch_manager::block_info::BlockInfo;
     use near_primitives::errors::InvalidTxError;
     use near_primitives::hash::hash;
+    use near_primitives::transaction::{ExecutionOutcomeWithId, OutcomeLocation};
     use near_primitives::types::{BlockHeight, EpochId, GCCount, NumBlocks};
     use near_primitives::utils::index_to_bytes;
     use near_primitives::validator_signer::InMemoryValidatorSigner;
@@ -3557,6 +3742,124 @@ mod tests {
             assert!(!store_validator.is_failed());
         }
     }
+    #[test]
+    fn test_clear_outcome_locations() {
+        let mut chain = get_chain();
+        let runtime_adapter = chain.runtime_adapter.clone();
+        let genesis = chain.get_block_by_height(0).unwrap();
+        let signer = Arc::new(InMemoryValidatorSigner::from_seed(
+            "test1".parse().unwrap(),
+            KeyType::ED25519,
+            "test1",
+        ));
+        let mut prev_block = genesis.clone();
+        let mut blocks = vec![prev_block.clone()];
+        for i in 1..10 {
+            add_block(
+                &mut chain,
+                runtime_adapter.clone(),
+                &mut prev_block,
+                &mut blocks,
+                signer.clone(),
+                i,
+            );
+        }
+        let outcome = |id| ExecutionOutcomeWithId { id, ..Default::default() };
+        let (id1, id2) = (hash(&[1]), hash(&[2]));
+        let mut store_update = chain.mut_store().store_update();
+        store_update.save_outcomes_with_proofs(
+            genesis.hash(),
+            0,
+            vec![outcome(id1), outcome(id2)],
+            vec![vec![], vec![]],
+        );
+        store_update.commit().unwrap();
+        // Executed again at a higher height, e.g. on a fork.
+        let mut store_update = chain.mut_store().store_update();
+        store_update.save_outcomes_with_proofs(
+            blocks[3].hash(),
+            0,
+            vec![outcome(id2)],
+            vec![vec![]],
+        );
+        store_update.commit().unwrap();
+        assert_eq!(
+            Some(OutcomeLocation { height: 0, shard_id: 0 }),
+            chain.get_outcome_location(&id1).unwrap()
+        );
+        assert_eq!(
+            Some(OutcomeLocation { height: 3, shard_id: 0 }),
+            chain.get_outcome_location(&id2).unwrap()
+        );
+
+        chain.clear_outcome_locations(5, 100).unwrap();
+        assert_eq!(None, chain.get_outcome_location(&id1).unwrap());
+        assert_eq!(
+            Some(OutcomeLocation { height: 3, shard_id: 0 }),
+            chain.get_outcome_location(&id2).unwrap()
+        );
+        // The outcomes themselves are kept.
+        assert_eq!(1, chain.store().get_outcomes_by_id(&id1).unwrap().len());
+        assert_eq!(4, chain.store().outcome_locations_tail().unwrap());
+    }
+
+    #[test]
+    fn test_outcome_locations_on_forks() {
+        let mut chain = get_chain();
+        let runtime_adapter = chain.runtime_adapter.clone();
+        let genesis = chain.get_block_by_height(0).unwrap();
+        let signer = Arc::new(InMemoryValidatorSigner::from_seed(
+            "test1".parse().unwrap(),
+            KeyType::ED25519,
+            "test1",
+        ));
+        let mut prev_block = genesis.clone();
+        let mut blocks = vec![prev_block.clone()];
+        for i in 1..5 {
+            add_block(
+                &mut chain,
+                runtime_adapter.clone(),
+                &mut prev_block,
+                &mut blocks,
+                signer.clone(),
+                i,
+            );
+        }
+        let fork = Block::empty_with_height(&blocks[2], 4, &*signer);
+        let mut store_update = chain.mut_store().store_update();
+        store_update.save_block_header(fork.header().clone()).unwrap();
+        store_update.commit().unwrap();
+
+        let id = hash(&[1]);
+        for block_hash in [blocks[3].hash(), fork.hash()] {
+            let mut store_update = chain.mut_store().store_update();
+            store_update.save_outcomes_with_proofs(
+                block_hash,
+                0,
+                vec![ExecutionOutcomeWithId { id, ..Default::default() }],
+                vec![vec![]],
+            );
+            store_update.commit().unwrap();
+        }
+        // The outcome on the fork isn't indexed.
+        assert_eq!(
+            Some(OutcomeLocation { height: 3, shard_id: 0 }),
+            chain.get_outcome_location(&id).unwrap()
+        );
+        assert_eq!(blocks[3].hash(), &chain.get_execution_outcome(&id).unwrap().block_hash);
+
+        // Until the fork becomes canonical.
+        let mut store_update = chain.mut_store().store_update();
+        store_update.chain_store_cache_update.height_to_hashes.insert(3, None);
+        store_update.chain_store_cache_update.height_to_hashes.insert(4, Some(*fork.hash()));
+        store_update.commit().unwrap();
+        assert_eq!(
+            Some(OutcomeLocation { height: 4, shard_id: 0 }),
+            chain.get_outcome_location(&id).unwrap()
+        );
+        assert_eq!(fork.hash(), &chain.get_execution_outcome(&id).unwrap().block_hash);
+    }
+
     #[test]
     fn test_fork_chunk_tail_updates() {
         let mut chain = get_chain();
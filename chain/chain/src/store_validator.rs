@@ -16,7 +16,7 @@ use near_primitives::epoch_manager::AGGREGATOR_KEY;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::{ChunkHash, ShardChunk, StateSyncInfo};
 use near_primitives::syncing::{ShardStateSyncResponseHeader, StateHeaderKey, StatePartKey};
-use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
+use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, OutcomeLocation};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{AccountId, BlockHeight, EpochId, GCCount};
 use near_primitives::utils::get_block_shard_id_rev;
@@ -272,6 +272,17 @@ impl StoreValidator {
                         col,
                     );
                 }
+                DBCol::OutcomeLocations => {
+                    let outcome_id = CryptoHash::try_from_slice(key_ref)?;
+                    let location = OutcomeLocation::try_from_slice(value_ref)?;
+                    // Outcome which can be indexed by Outcome id exists
+                    self.check(
+                        &validate::outcome_location_outcome_exists,
+                        &outcome_id,
+                        &location,
+                        col,
+                    );
+                }
                 DBCol::StateDlInfos => {
                     let block_hash = CryptoHash::try_from(key_ref)?;
                     let state_sync_info = StateSyncInfo::try_from_slice(value_ref)?;
@@ -12,7 +12,9 @@ use near_primitives::sharding::{ChunkHash, ShardChunk, StateSyncInfo};
 use near_primitives::syncing::{
     get_num_state_parts, ShardStateSyncResponseHeader, StateHeaderKey, StatePartKey,
 };
-use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
+use near_primitives::transaction::{
+    ExecutionOutcomeWithIdAndProof, OutcomeLocation, SignedTransaction,
+};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{BlockHeight, EpochId};
 use near_primitives::utils::{get_block_shard_id, index_to_bytes};
@@ -726,6 +728,25 @@ pub(crate) fn outcome_indexed_by_block_hash(
     Ok(())
 }
 
+pub(crate) fn outcome_location_outcome_exists(
+    sv: &mut StoreValidator,
+    outcome_id: &CryptoHash,
+    _location: &OutcomeLocation,
+) -> Result<(), StoreValidatorError> {
+    let outcomes = unwrap_or_err_db!(
+        sv.store.get_ser::<Vec<ExecutionOutcomeWithIdAndProof>>(
+            DBCol::TransactionResult,
+            outcome_id.as_ref()
+        ),
+        "Can't get TransactionResult from storage with Outcome id {:?}",
+        outcome_id
+    );
+    if outcomes.is_empty() {
+        err!("No outcomes stored for Outcome id {:?}", outcome_id);
+    }
+    Ok(())
+}
+
 pub(crate) fn state_sync_info_valid(
     _sv: &mut StoreValidator,
     block_hash: &CryptoHash,
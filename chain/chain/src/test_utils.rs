@@ -55,7 +55,7 @@ use crate::types::{
 };
 use crate::{BlockHeader, DoomslugThresholdMode, RuntimeAdapter};
 use crate::{BlockProcessingArtifact, Doomslug, Provenance};
-use near_primitives::epoch_manager::ShardConfig;
+use near_primitives::epoch_manager::{ProtocolUpgradeVotes, ShardConfig};
 use near_primitives::time::Clock;
 use near_primitives::utils::MaybeValidated;
 
@@ -1007,6 +1007,7 @@ impl RuntimeAdapter for KeyValueRuntime {
                         public_key: PublicKey::empty(KeyType::ED25519),
                         access_key: AccessKey::full_access().into(),
                     }],
+                    next_public_key: None,
                 }),
                 block_height,
                 block_hash: *block_hash,
@@ -1213,6 +1214,17 @@ impl RuntimeAdapter for KeyValueRuntime {
         Ok(PROTOCOL_VERSION)
     }
 
+    fn get_protocol_upgrade_votes(
+        &self,
+        _last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolUpgradeVotes, Error> {
+        Ok(ProtocolUpgradeVotes {
+            votes: HashMap::new(),
+            total_block_producer_stake: 0,
+            upgrade_stake_threshold: 0,
+        })
+    }
+
     fn get_validator_info(
         &self,
         _epoch_id: ValidatorInfoIdentifier,
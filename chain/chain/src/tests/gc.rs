@@ -10,6 +10,7 @@ use near_crypto::KeyType;
 use near_primitives::block::Block;
 use near_primitives::merkle::PartialMerkleTree;
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::sharding::{ShardChunk, ShardChunkV2};
 use near_primitives::types::{NumBlocks, NumShards, StateRoot};
 use near_primitives::validator_signer::InMemoryValidatorSigner;
 use near_store::test_utils::{create_test_store, gen_changes};
@@ -724,3 +725,57 @@ fn test_fork_far_away_from_epoch_end() {
         );
     }
 }
+
+#[test]
+fn test_pinned_block_kept_after_gc() {
+    let mut chain = get_chain(1);
+    let tries = chain.runtime_adapter.get_tries();
+    let genesis = chain.get_block_by_height(0).unwrap();
+    let mut states = vec![(genesis.clone(), vec![Trie::empty_root()], vec![Vec::new()])];
+    do_fork(
+        genesis,
+        vec![Trie::empty_root()],
+        tries.clone(),
+        &mut chain,
+        100,
+        &mut states,
+        1,
+        false,
+    );
+
+    let pinned = states[3].0.clone();
+    let unpinned = states[4].0.clone();
+    let mut pinned_block = chain.store().make_pinned_block(pinned.hash()).unwrap();
+    // The test runtime does not save chunks, so pin empty ones matching the chunk headers.
+    pinned_block.chunks = pinned
+        .chunks()
+        .iter()
+        .map(|header| {
+            ShardChunk::V2(ShardChunkV2 {
+                chunk_hash: header.chunk_hash(),
+                header: header.clone(),
+                transactions: vec![],
+                receipts: vec![],
+            })
+        })
+        .collect();
+    let pinned_chunks: Vec<_> =
+        pinned_block.chunks.iter().map(|chunk| chunk.chunk_hash()).collect();
+    chain.store().save_pinned_blocks(&[pinned_block]).unwrap();
+    chain.clear_data(tries, &GCConfig { gc_blocks_limit: 100, ..GCConfig::default() }).unwrap();
+
+    assert!(!chain.block_exists(pinned.hash()).unwrap());
+    assert!(!chain.block_exists(unpinned.hash()).unwrap());
+    assert_eq!(pinned, chain.get_block_or_pinned(pinned.hash()).unwrap());
+    assert!(chain.get_block_or_pinned(unpinned.hash()).is_err());
+    assert_eq!(1, chain.store().get_pinned_blocks().unwrap().len());
+    for chunk_hash in &pinned_chunks {
+        assert_eq!(chunk_hash, &chain.get_chunk_or_pinned(chunk_hash).unwrap().chunk_hash());
+    }
+
+    chain.store().remove_pinned_blocks(&[*pinned.hash()]).unwrap();
+    assert!(chain.get_block_or_pinned(pinned.hash()).is_err());
+    for chunk_hash in &pinned_chunks {
+        assert!(chain.get_chunk_or_pinned(chunk_hash).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::chain::MAX_ORPHANS_PER_HEIGHT;
 use crate::near_chain_primitives::error::BlockKnownError;
 use crate::test_utils::{setup, wait_for_all_blocks_in_processing};
 use crate::{Block, BlockProcessingArtifact, ChainStoreAccess, Error};
@@ -88,6 +89,7 @@ fn build_chain_with_orhpans() {
     }
     let last_block = &blocks[blocks.len() - 1];
     let block = Block::produce(
+        PROTOCOL_VERSION,
         PROTOCOL_VERSION,
         PROTOCOL_VERSION,
         last_block.header(),
@@ -133,6 +135,37 @@ fn build_chain_with_orhpans() {
     );
 }
 
+#[test]
+fn orphan_pool_limits() {
+    init_test_logger();
+    let (mut chain, _, signer) = setup();
+    let genesis = chain.get_block(&chain.genesis().hash().clone()).unwrap();
+    // Blocks which never reach the chain, so that their descendants stay orphans.
+    let mut missing = vec![Block::empty(&genesis, &*signer)];
+    for i in 1..=MAX_ORPHANS_PER_HEIGHT {
+        missing.push(Block::empty(&missing[i - 1], &*signer));
+    }
+    let orphans: Vec<Block> =
+        missing.iter().map(|prev| Block::empty_with_height(prev, 100, &*signer)).collect();
+    for orphan in orphans.iter().cloned() {
+        assert_matches!(chain.process_block_test(&None, orphan).unwrap_err(), Error::Orphan);
+    }
+    // The last orphan at the height is dropped.
+    assert_eq!(chain.orphans_len(), MAX_ORPHANS_PER_HEIGHT);
+    assert_eq!(chain.orphans_evicted_len(), 1);
+    assert!(!chain.is_orphan(orphans[MAX_ORPHANS_PER_HEIGHT].hash()));
+
+    let child = Block::empty_with_height(&orphans[0], 101, &*signer);
+    assert_matches!(chain.process_block_test(&None, child.clone()).unwrap_err(), Error::Orphan);
+    let status = chain.orphans_status();
+    assert_eq!(status.len(), MAX_ORPHANS_PER_HEIGHT + 1);
+    let last = status.last().unwrap();
+    assert_eq!(last.block_hash, *child.hash());
+    assert_eq!(last.prev_hash, *orphans[0].hash());
+    // The child waits for the missing parent of its orphan parent.
+    assert_eq!(last.missing_ancestor, *missing[0].hash());
+}
+
 #[test]
 fn build_chain_with_skips_and_forks() {
     init_test_logger();
@@ -17,7 +17,7 @@ use near_primitives::challenge::{ChallengesResult, SlashedValidator};
 use near_primitives::checked_feature;
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
-use near_primitives::epoch_manager::ShardConfig;
+use near_primitives::epoch_manager::{ProtocolUpgradeVotes, ShardConfig};
 use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath};
@@ -569,6 +569,12 @@ pub trait RuntimeAdapter: Send + Sync {
     /// Epoch active protocol version.
     fn get_epoch_protocol_version(&self, epoch_id: &EpochId) -> Result<ProtocolVersion, Error>;
 
+    /// Protocol version votes cast in the epoch of the given block, up to and including it.
+    fn get_protocol_upgrade_votes(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolUpgradeVotes, Error>;
+
     /// Epoch Manager init procedure that is necessary after Epoch Sync.
     fn epoch_sync_init_epoch_manager(
         &self,
@@ -705,6 +711,22 @@ pub trait RuntimeAdapter: Send + Sync {
         part_id: PartId,
     ) -> Result<Vec<u8>, Error>;
 
+    /// Get several parts of the state from given state root, in the order of `part_ids`.
+    /// Implementations may generate the parts concurrently; the default one obtains them
+    /// one by one.
+    fn obtain_state_parts(
+        &self,
+        shard_id: ShardId,
+        block_hash: &CryptoHash,
+        state_root: &StateRoot,
+        part_ids: &[PartId],
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        part_ids
+            .iter()
+            .map(|part_id| self.obtain_state_part(shard_id, block_hash, state_root, *part_id))
+            .collect()
+    }
+
     /// Validate state part that expected to be given state root with provided data.
     /// Returns false if the resulting part doesn't match the expected one.
     fn validate_state_part(&self, state_root: &StateRoot, part_id: PartId, data: &Vec<u8>) -> bool;
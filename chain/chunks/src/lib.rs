@@ -174,6 +174,9 @@ struct ChunkRequestInfo {
     shard_id: ShardId,
     added: Instant,
     last_requested: Instant,
+    // time the missing parts were requested from another node, if they were since the last
+    // request
+    hedged: Option<Instant>,
 }
 
 struct RequestPool {
@@ -181,6 +184,9 @@ struct RequestPool {
     switch_to_others_duration: Duration,
     switch_to_full_fetch_duration: Duration,
     max_duration: Duration,
+    /// Time after which unanswered requests are hedged, see
+    /// `ShardsManager::send_hedged_chunk_requests`. None if hedging is disabled.
+    hedge_delay: Option<Duration>,
     requests: HashMap<ChunkHash, ChunkRequestInfo>,
 }
 
@@ -190,12 +196,14 @@ impl RequestPool {
         switch_to_others_duration: Duration,
         switch_to_full_fetch_duration: Duration,
         max_duration: Duration,
+        hedge_delay: Option<Duration>,
     ) -> Self {
         Self {
             retry_duration,
             switch_to_others_duration,
             switch_to_full_fetch_duration,
             max_duration,
+            hedge_delay,
             requests: HashMap::default(),
         }
     }
@@ -218,6 +226,15 @@ impl RequestPool {
         self.requests.remove(chunk_hash);
     }
 
+    /// Removes the request of a chunk which was completed.
+    pub fn complete(&mut self, chunk_hash: &ChunkHash) {
+        let hedged = self.requests.remove(chunk_hash).and_then(|request| request.hedged);
+        if let Some(hedged) = hedged {
+            metrics::CHUNK_REQUEST_HEDGES.with_label_values(&["completed"]).inc();
+            metrics::CHUNK_REQUEST_HEDGE_COMPLETION_TIME.observe(hedged.elapsed().as_secs_f64());
+        }
+    }
+
     pub fn fetch(&mut self) -> Vec<(ChunkHash, ChunkRequestInfo)> {
         let mut removed_requests = HashSet::<ChunkHash>::default();
         let mut requests = Vec::new();
@@ -229,6 +246,9 @@ impl RequestPool {
             }
             if chunk_request.last_requested.elapsed() > self.retry_duration {
                 chunk_request.last_requested = Clock::instant();
+                if chunk_request.hedged.take().is_some() {
+                    metrics::CHUNK_REQUEST_HEDGES.with_label_values(&["retried"]).inc();
+                }
                 requests.push((chunk_hash.clone(), chunk_request.clone()));
             }
         }
@@ -237,6 +257,36 @@ impl RequestPool {
         }
         requests
     }
+
+    /// Returns the requests which weren't answered within the hedge delay and weren't hedged
+    /// since they were last sent, marking them as hedged.
+    pub fn fetch_hedges(&mut self) -> Vec<(ChunkHash, ChunkRequestInfo)> {
+        let hedge_delay = match self.hedge_delay {
+            Some(hedge_delay) => hedge_delay,
+            None => return vec![],
+        };
+        let mut requests = Vec::new();
+        for (chunk_hash, chunk_request) in self.requests.iter_mut() {
+            if chunk_request.hedged.is_none()
+                && chunk_request.last_requested.elapsed() > hedge_delay
+            {
+                chunk_request.hedged = Some(Clock::instant());
+                requests.push((chunk_hash.clone(), chunk_request.clone()));
+            }
+        }
+        requests
+    }
+}
+
+/// Splits the parts to request into at most `num_groups` groups of about the same size, none
+/// of them empty unless `part_ords` is, in which case a single empty group is returned.
+fn split_part_ords(part_ords: Vec<u64>, num_groups: usize) -> Vec<Vec<u64>> {
+    let num_groups = num_groups.clamp(1, cmp::max(part_ords.len(), 1));
+    let mut groups = vec![vec![]; num_groups];
+    for (i, part_ord) in part_ords.into_iter().enumerate() {
+        groups[i % num_groups].push(part_ord);
+    }
+    groups
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -480,6 +530,9 @@ pub struct ShardsManager {
     chunk_forwards_cache: lru::LruCache<ChunkHash, HashMap<u64, PartialEncodedChunkPart>>,
 
     seals_mgr: SealsManager,
+    /// Maximum number of nodes tracking the shard which the parts requested from the shard
+    /// representative target are spread over.
+    chunk_request_fan_out: usize,
     /// Useful to make tests deterministic and reproducible,
     /// while keeping the security of randomization of transactions in pool
     rng_seed: RngSeed,
@@ -490,6 +543,8 @@ impl ShardsManager {
         me: Option<AccountId>,
         runtime_adapter: Arc<dyn RuntimeAdapter>,
         network_adapter: Arc<dyn PeerManagerAdapter>,
+        chunk_request_fan_out: usize,
+        chunk_request_hedge_delay: Option<Duration>,
         rng_seed: RngSeed,
     ) -> Self {
         TransactionPool::init_metrics();
@@ -504,9 +559,11 @@ impl ShardsManager {
                 Duration::from_millis(CHUNK_REQUEST_SWITCH_TO_OTHERS_MS),
                 Duration::from_millis(CHUNK_REQUEST_SWITCH_TO_FULL_FETCH_MS),
                 Duration::from_millis(CHUNK_REQUEST_RETRY_MAX_MS),
+                chunk_request_hedge_delay,
             ),
             chunk_forwards_cache: lru::LruCache::new(CHUNK_FORWARD_CACHE_SIZE),
             seals_mgr: SealsManager::new(me, runtime_adapter),
+            chunk_request_fan_out,
             rng_seed,
         }
     }
@@ -553,8 +610,6 @@ impl ShardsManager {
         .entered();
         let mut bp_to_parts = HashMap::<_, Vec<u64>>::new();
 
-        let cache_entry = self.encoded_chunks.get(chunk_hash);
-
         let request_full = force_request_full
             || self.cares_about_shard_this_or_next_epoch(
                 self.me.as_ref(),
@@ -584,6 +639,8 @@ impl ShardsManager {
         // from the target account or any eligible peer of the node (See comments in
         // AccountIdOrPeerTrackingShard for when target account is used or peer is used)
 
+        let part_ords =
+            self.get_missing_part_ords(height, ancestor_hash, shard_id, chunk_hash, request_full)?;
         let me = self.me.as_ref();
         // A account that is either the original chunk producer or a random block producer tracking
         // the shard
@@ -596,41 +653,21 @@ impl ShardsManager {
             self.get_random_target_tracking_shard(ancestor_hash, shard_id)?
         };
 
-        let seal = self.seals_mgr.get_seal(chunk_hash, ancestor_hash, height, shard_id)?;
-
-        for part_ord in 0..self.runtime_adapter.num_total_parts() {
-            let part_ord = part_ord as u64;
-            if cache_entry.map_or(false, |cache_entry| cache_entry.parts.contains_key(&part_ord)) {
-                continue;
-            }
-
-            let need_to_fetch_part = if request_full || seal.contains_part_ord(&part_ord) {
-                true
+        for part_ord in part_ords {
+            let fetch_from = if request_from_archival {
+                shard_representative_target.clone()
             } else {
-                if let Some(me) = me {
-                    &self.runtime_adapter.get_part_owner(ancestor_hash, part_ord)? == me
-                } else {
-                    false
-                }
-            };
+                let part_owner = self.runtime_adapter.get_part_owner(ancestor_hash, part_ord)?;
 
-            if need_to_fetch_part {
-                let fetch_from = if request_from_archival {
+                if Some(&part_owner) == me {
+                    // If missing own part, request it from the chunk producer / node tracking shard
                     shard_representative_target.clone()
                 } else {
-                    let part_owner =
-                        self.runtime_adapter.get_part_owner(ancestor_hash, part_ord)?;
-
-                    if Some(&part_owner) == me {
-                        // If missing own part, request it from the chunk producer / node tracking shard
-                        shard_representative_target.clone()
-                    } else {
-                        Some(part_owner)
-                    }
-                };
+                    Some(part_owner)
+                }
+            };
 
-                bp_to_parts.entry(fetch_from).or_default().push(part_ord);
-            }
+            bp_to_parts.entry(fetch_from).or_default().push(part_ord);
         }
 
         let shards_to_fetch_receipts =
@@ -645,16 +682,43 @@ impl ShardsManager {
             bp_to_parts.entry(shard_representative_target.clone()).or_default();
         }
 
-        let no_account_id = me.is_none();
-        debug!(target: "chunks", "Will send {} requests to fetch chunk parts.", bp_to_parts.len());
+        // Each request is a target account, the parts to request from it and whether to request
+        // the receipts from it.
+        let mut requests = vec![];
+        if let Some(part_ords) = bp_to_parts.remove(&shard_representative_target) {
+            // The shard representative target can serve all the parts, and so can any other node
+            // tracking the shard. Spread the parts over several of them, so that a single slow
+            // node doesn't stall the chunk. Receipts are requested from the representative only.
+            let other_targets = if self.chunk_request_fan_out > 1 && part_ords.len() > 1 {
+                self.get_random_targets_tracking_shard(
+                    ancestor_hash,
+                    shard_id,
+                    self.chunk_request_fan_out - 1,
+                    shard_representative_target.as_ref(),
+                )?
+            } else {
+                vec![]
+            };
+            let mut groups = split_part_ords(part_ords, 1 + other_targets.len()).into_iter();
+            requests.push((shard_representative_target.clone(), groups.next().unwrap(), true));
+            for (target_account, part_ords) in other_targets.into_iter().zip(groups) {
+                requests.push((Some(target_account), part_ords, false));
+            }
+        }
         for (target_account, part_ords) in bp_to_parts {
+            requests.push((target_account, part_ords, false));
+        }
+
+        let no_account_id = me.is_none();
+        debug!(target: "chunks", "Will send {} requests to fetch chunk parts.", requests.len());
+        for (target_account, part_ords, request_receipts) in requests {
             // extra check that we are not sending request to ourselves.
             if no_account_id || me != target_account.as_ref() {
                 let parts_count = part_ords.len();
                 let request = PartialEncodedChunkRequestMsg {
                     chunk_hash: chunk_hash.clone(),
                     part_ords,
-                    tracking_shards: if target_account == shard_representative_target {
+                    tracking_shards: if request_receipts {
                         shards_to_fetch_receipts.clone()
                     } else {
                         HashSet::new()
@@ -686,12 +750,114 @@ impl ShardsManager {
         Ok(())
     }
 
+    /// Parts of the chunk which we don't have yet and need: all of them if `request_full`,
+    /// otherwise the parts we own and the parts in the seal.
+    fn get_missing_part_ords(
+        &mut self,
+        height: BlockHeight,
+        ancestor_hash: &CryptoHash,
+        shard_id: ShardId,
+        chunk_hash: &ChunkHash,
+        request_full: bool,
+    ) -> Result<Vec<u64>, near_chain::Error> {
+        let cache_entry = self.encoded_chunks.get(chunk_hash);
+        let seal = self.seals_mgr.get_seal(chunk_hash, ancestor_hash, height, shard_id)?;
+        let mut part_ords = vec![];
+        for part_ord in 0..self.runtime_adapter.num_total_parts() {
+            let part_ord = part_ord as u64;
+            if cache_entry.map_or(false, |cache_entry| cache_entry.parts.contains_key(&part_ord)) {
+                continue;
+            }
+
+            let need_to_fetch_part = if request_full || seal.contains_part_ord(&part_ord) {
+                true
+            } else {
+                if let Some(me) = &self.me {
+                    &self.runtime_adapter.get_part_owner(ancestor_hash, part_ord)? == me
+                } else {
+                    false
+                }
+            };
+            if need_to_fetch_part {
+                part_ords.push(part_ord);
+            }
+        }
+        Ok(part_ords)
+    }
+
+    /// Requests the parts still missing for the chunks whose requests weren't answered
+    /// within the hedge delay from another node tracking the shard, so that a single slow
+    /// node doesn't stall the chunk until the requests are retried. Whichever of the
+    /// responses comes second is dropped, see `remove_known_parts_and_receipts`.
+    pub fn send_hedged_chunk_requests(&mut self) {
+        for (chunk_hash, chunk_request) in self.requested_partial_encoded_chunks.fetch_hedges() {
+            if let Err(err) = self.request_hedged_partial_encoded_chunk(&chunk_hash, &chunk_request)
+            {
+                error!(target: "chunks", "Error during hedging partial encoded chunk request: {}", err);
+            }
+        }
+    }
+
+    fn request_hedged_partial_encoded_chunk(
+        &mut self,
+        chunk_hash: &ChunkHash,
+        chunk_request: &ChunkRequestInfo,
+    ) -> Result<(), near_chain::Error> {
+        let ChunkRequestInfo { height, ancestor_hash, shard_id, .. } = *chunk_request;
+        let request_full = self.cares_about_shard_this_or_next_epoch(
+            self.me.as_ref(),
+            &ancestor_hash,
+            shard_id,
+            true,
+        );
+        let part_ords =
+            self.get_missing_part_ords(height, &ancestor_hash, shard_id, chunk_hash, request_full)?;
+        if part_ords.is_empty() {
+            metrics::CHUNK_REQUEST_HEDGES.with_label_values(&["cancelled"]).inc();
+            return Ok(());
+        }
+        let target_account = self.get_random_target_tracking_shard(&ancestor_hash, shard_id)?;
+        debug!(target: "chunks", ?chunk_hash, parts = part_ords.len(), ?target_account, "Hedging chunk part request");
+        let request = PartialEncodedChunkRequestMsg {
+            chunk_hash: chunk_hash.clone(),
+            part_ords,
+            tracking_shards: HashSet::new(),
+        };
+        let target = AccountIdOrPeerTrackingShard {
+            account_id: target_account,
+            prefer_peer: rand::thread_rng().gen::<bool>(),
+            shard_id,
+            only_archival: false,
+            min_height: height.saturating_sub(CHUNK_REQUEST_PEER_HORIZON),
+        };
+        self.peer_manager_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::PartialEncodedChunkRequest {
+                target,
+                request,
+                create_time: Clock::instant().into(),
+            },
+        ));
+        Ok(())
+    }
+
     /// Get a random shard block producer that is not me.
     fn get_random_target_tracking_shard(
         &self,
         parent_hash: &CryptoHash,
         shard_id: ShardId,
     ) -> Result<Option<AccountId>, near_chain::Error> {
+        Ok(self.get_random_targets_tracking_shard(parent_hash, shard_id, 1, None)?.pop())
+    }
+
+    /// Get up to `count` distinct random shard block producers that are neither me nor
+    /// `exclude`.
+    fn get_random_targets_tracking_shard(
+        &self,
+        parent_hash: &CryptoHash,
+        shard_id: ShardId,
+        count: usize,
+        exclude: Option<&AccountId>,
+    ) -> Result<Vec<AccountId>, near_chain::Error> {
         let epoch_id = self.runtime_adapter.get_epoch_id_from_prev_block(parent_hash).unwrap();
         let block_producers = self
             .runtime_adapter
@@ -707,6 +873,7 @@ impl ShardsManager {
                         false,
                     )
                     && self.me.as_ref() != Some(&account_id)
+                    && exclude != Some(&account_id)
                 {
                     Some(account_id)
                 } else {
@@ -714,7 +881,7 @@ impl ShardsManager {
                 }
             });
 
-        Ok(block_producers.choose(&mut rand::thread_rng()))
+        Ok(block_producers.choose_multiple(&mut rand::thread_rng(), count))
     }
 
     fn get_tracking_shards(&self, parent_hash: &CryptoHash) -> HashSet<ShardId> {
@@ -810,6 +977,7 @@ impl ShardsManager {
                 shard_id,
                 last_requested: Clock::instant(),
                 added: Clock::instant(),
+                hedged: None,
             },
         );
 
@@ -1433,6 +1601,21 @@ impl ShardsManager {
         Ok(header)
     }
 
+    /// Drops the parts and receipts of a response which are already in the cache, e.g.
+    /// because the chunk has been requested from several targets, so that they aren't
+    /// validated again.
+    pub fn remove_known_parts_and_receipts(&self, response: &mut PartialEncodedChunkResponseMsg) {
+        let entry = match self.encoded_chunks.get(&response.chunk_hash) {
+            Some(entry) => entry,
+            None => return,
+        };
+        let num_parts = response.parts.len();
+        response.parts.retain(|part| !entry.parts.contains_key(&part.part_ord));
+        response.receipts.retain(|receipt| !entry.receipts.contains_key(&receipt.1.to_shard_id));
+        metrics::PARTIAL_ENCODED_CHUNK_RESPONSE_DUPLICATE_PARTS
+            .inc_by((num_parts - response.parts.len()) as u64);
+    }
+
     pub fn insert_forwarded_chunk(&mut self, forward: PartialEncodedChunkForwardMsg) {
         let chunk_hash = forward.chunk_hash.clone();
         let num_total_parts = self.runtime_adapter.num_total_parts() as u64;
@@ -1864,7 +2047,7 @@ impl ShardsManager {
     fn complete_chunk(&mut self, chunk_hash: &ChunkHash) {
         self.encoded_chunks.mark_entry_complete(chunk_hash);
         self.encoded_chunks.remove_from_cache_if_outside_horizon(chunk_hash);
-        self.requested_partial_encoded_chunks.remove(chunk_hash);
+        self.requested_partial_encoded_chunks.complete(chunk_hash);
     }
 
     /// Send the parts of the partial_encoded_chunk that are owned by `self.me` to the
@@ -2111,7 +2294,7 @@ impl ShardsManager {
             store_update.save_chunk(shard_chunk);
             store_update.commit()?;
 
-            self.requested_partial_encoded_chunks.remove(&chunk_hash);
+            self.requested_partial_encoded_chunks.complete(&chunk_hash);
 
             return Ok(());
         } else {
@@ -2276,6 +2459,8 @@ mod test {
             Some("test".parse().unwrap()),
             runtime_adapter,
             network_adapter.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         let added = Clock::instant();
@@ -2288,6 +2473,7 @@ mod test {
                 shard_id: 0,
                 added: added,
                 last_requested: added,
+                hedged: None,
             },
         );
         std::thread::sleep(Duration::from_millis(2 * CHUNK_REQUEST_RETRY_MS));
@@ -2311,6 +2497,49 @@ mod test {
         };
     }
 
+    /// requests which aren't answered within the hedge delay should be hedged once
+    #[test]
+    fn test_hedge_chunk_requests() {
+        let runtime_adapter = Arc::new(KeyValueRuntime::new(create_test_store(), 5));
+        let network_adapter = Arc::new(MockPeerManagerAdapter::default());
+        let mut shards_manager = ShardsManager::new(
+            Some("test".parse().unwrap()),
+            runtime_adapter,
+            network_adapter.clone(),
+            1,
+            Some(Duration::from_millis(10)),
+            TEST_SEED,
+        );
+        let added = Clock::instant();
+        shards_manager.requested_partial_encoded_chunks.insert(
+            ChunkHash(hash(&[1])),
+            ChunkRequestInfo {
+                height: 0,
+                ancestor_hash: Default::default(),
+                prev_block_hash: Default::default(),
+                shard_id: 0,
+                added: added,
+                last_requested: added,
+                hedged: None,
+            },
+        );
+        shards_manager.send_hedged_chunk_requests();
+        assert!(network_adapter.requests.read().unwrap().is_empty());
+
+        std::thread::sleep(Duration::from_millis(20));
+        shards_manager.send_hedged_chunk_requests();
+        shards_manager.send_hedged_chunk_requests();
+        let requests = network_adapter.requests.read().unwrap();
+        assert_eq!(requests.len(), 1);
+        let msg = requests[0].as_network_requests_ref();
+        if let NetworkRequests::PartialEncodedChunkRequest { request, .. } = msg {
+            assert!(!request.part_ords.is_empty());
+            assert!(request.tracking_shards.is_empty());
+        } else {
+            panic!("unexpected request {:?}", msg);
+        }
+    }
+
     #[test]
     #[cfg_attr(not(feature = "expensive_tests"), ignore)]
     fn test_seal_removal() {
@@ -2333,6 +2562,8 @@ mod test {
             Some("test".parse().unwrap()),
             runtime_adapter.clone(),
             network_adapter,
+            1,
+            None,
             TEST_SEED,
         );
         let signer =
@@ -2369,6 +2600,7 @@ mod test {
                 shard_id: header.shard_id(),
                 last_requested: Clock::instant(),
                 added: Clock::instant(),
+                hedged: None,
             },
         );
         shards_manager
@@ -2500,6 +2732,8 @@ mod test {
             Some(fixture.mock_shard_tracker.clone()),
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         // process chunk part 0
@@ -2571,6 +2805,8 @@ mod test {
             Some(fixture.mock_shard_tracker.clone()),
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
 
@@ -2597,6 +2833,8 @@ mod test {
             Some(fixture.mock_chunk_part_owner.clone()),
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         let partial_encoded_chunk = fixture.make_partial_encoded_chunk(&fixture.mock_part_ords);
@@ -2663,6 +2901,8 @@ mod test {
             account_id,
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         shards_manager.request_chunks(
@@ -2718,6 +2958,8 @@ mod test {
             Some(fixture.mock_chunk_part_owner.clone()),
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         let partial_encoded_chunk = fixture.make_partial_encoded_chunk(&fixture.mock_part_ords);
@@ -2800,6 +3042,8 @@ mod test {
             Some(fixture.mock_shard_tracker.clone()),
             fixture.mock_runtime.clone(),
             fixture.mock_network.clone(),
+            1,
+            None,
             TEST_SEED,
         );
         let (most_parts, other_parts) = {
@@ -2877,4 +3121,13 @@ mod test {
         assert_ne!(seed256, seed1000000);
         assert_ne!(seed1000, seed1000000);
     }
+
+    #[test]
+    fn test_split_part_ords() {
+        assert_eq!(split_part_ords(vec![], 3), vec![Vec::<u64>::new()]);
+        assert_eq!(split_part_ords(vec![1, 2, 3], 1), vec![vec![1, 2, 3]]);
+        assert_eq!(split_part_ords(vec![1, 2, 3, 4, 5], 2), vec![vec![1, 3, 5], vec![2, 4]]);
+        assert_eq!(split_part_ords(vec![1, 2], 3), vec![vec![1], vec![2]]);
+        assert_eq!(split_part_ords(vec![1, 2], 0), vec![vec![1, 2]]);
+    }
 }
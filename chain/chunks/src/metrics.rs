@@ -32,3 +32,34 @@ pub static DISTRIBUTE_ENCODED_CHUNK_TIME: Lazy<near_metrics::HistogramVec> = Laz
     )
     .unwrap()
 });
+
+pub static PARTIAL_ENCODED_CHUNK_RESPONSE_DUPLICATE_PARTS: Lazy<near_metrics::IntCounter> =
+    Lazy::new(|| {
+        near_metrics::try_create_int_counter(
+            "near_partial_encoded_chunk_response_duplicate_parts",
+            "Number of chunk parts received in responses to chunk requests which were already known",
+        )
+        .unwrap()
+    });
+
+pub static CHUNK_REQUEST_HEDGES: Lazy<near_metrics::IntCounterVec> = Lazy::new(|| {
+    near_metrics::try_create_int_counter_vec(
+        "near_chunk_request_hedges_total",
+        concat!(
+            "Number of chunk requests not answered within the hedge delay, by outcome: ",
+            "‘cancelled’ if no parts were missing anymore, so no hedged request was sent, ",
+            "‘completed’ if the chunk completed before the requests were retried and ",
+            "‘retried’ otherwise",
+        ),
+        &["outcome"],
+    )
+    .unwrap()
+});
+
+pub static CHUNK_REQUEST_HEDGE_COMPLETION_TIME: Lazy<near_metrics::Histogram> = Lazy::new(|| {
+    near_metrics::try_create_histogram(
+        "near_chunk_request_hedge_completion_time",
+        "Time from sending a hedged chunk request to the chunk completing",
+    )
+    .unwrap()
+});
@@ -10,7 +10,7 @@ use near_primitives::{
     block_header::ApprovalInner,
     hash::CryptoHash,
     sharding::ChunkHash,
-    types::{AccountId, BlockHeight},
+    types::{AccountId, Balance, BlockHeight, EpochHeight, ProtocolVersion},
     views::ValidatorInfo,
 };
 use serde::{Deserialize, Serialize};
@@ -140,6 +140,61 @@ pub struct ValidatorStatus {
     pub production: HashMap<BlockHeight, ProductionAtHeight>,
 }
 
+// Stake voting for a given protocol version.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtocolVersionVote {
+    pub protocol_version: ProtocolVersion,
+    pub stake: Balance,
+}
+
+// Protocol version upgrade which is expected to happen, given the votes so far.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProjectedProtocolUpgrade {
+    pub protocol_version: ProtocolVersion,
+    // Ordinal of the epoch which is going to use the new protocol version.
+    pub epoch_height: EpochHeight,
+    // Expected height of the first block of that epoch.
+    pub start_height: BlockHeight,
+}
+
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ProtocolUpgradeView {
+    pub epoch_height: EpochHeight,
+    pub protocol_version: ProtocolVersion,
+    pub next_epoch_protocol_version: ProtocolVersion,
+    // Latest protocol version supported by this binary.
+    pub binary_protocol_version: ProtocolVersion,
+    // Protocol version this node announces in the blocks it produces.
+    pub voted_protocol_version: ProtocolVersion,
+    // Time after which this node votes for `binary_protocol_version` (None - votes immediately).
+    pub voting_start: Option<DateTime<chrono::Utc>>,
+    // Votes of the block producers of the current epoch (in descending order of stake).
+    // They decide the protocol version of the epoch after next.
+    pub votes: Vec<ProtocolVersionVote>,
+    pub total_block_producer_stake: Balance,
+    // Stake which must be exceeded for the upgrade to happen.
+    pub upgrade_stake_threshold: Balance,
+    pub projected_upgrade: Option<ProjectedProtocolUpgrade>,
+}
+
+// Orphan block in the orphan pool, and the block it is waiting for.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DebugOrphanStatus {
+    pub block_hash: CryptoHash,
+    pub block_height: BlockHeight,
+    pub prev_hash: CryptoHash,
+    // First ancestor which is neither accepted nor an orphan itself, i.e. the block which has to
+    // arrive before the orphan can be processed.
+    pub missing_ancestor: CryptoHash,
+    // Time since the block was added to the orphan pool.
+    pub in_pool_ms: u64,
+    pub requested_missing_chunks: bool,
+}
+
 // Different debug requests that can be sent by HTML pages, via GET.
 pub enum DebugStatus {
     // Request for the current sync status
@@ -152,6 +207,10 @@ pub enum DebugStatus {
     BlockStatus,
     // Consensus related information.
     ValidatorStatus,
+    // Protocol version voting and the upcoming upgrades.
+    ProtocolUpgrade,
+    // Blocks in the orphan pool.
+    Orphans,
 }
 
 impl Message for DebugStatus {
@@ -168,4 +227,8 @@ pub enum DebugStatusResponse {
     BlockStatus(Vec<DebugBlockStatus>),
     // Detailed information about the validator (approvals, block & chunk production etc.)
     ValidatorStatus(ValidatorStatus),
+    // Protocol version voting of the current epoch.
+    ProtocolUpgrade(ProtocolUpgradeView),
+    // Blocks in the orphan pool, ordered by height.
+    Orphans(Vec<DebugOrphanStatus>),
 }
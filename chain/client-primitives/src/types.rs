@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
@@ -13,16 +14,16 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::sharding::ChunkHash;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, NumBlocks,
+    ShardId, TransactionOrReceiptId,
 };
 use near_primitives::utils::generate_random_string;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    FinalExecutionOutcomeViewEnum, GasPriceView, LightClientBlockLiteView, LightClientBlockView,
-    QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView, StateChangesRequestView,
-    StateChangesView,
+    FinalExecutionOutcomeViewEnum, GasPriceEstimateView, GasPriceView, LightClientBlockLiteView,
+    LightClientBlockView, QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView,
+    StateChangesRequestView, StateChangesView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
 use serde::Serialize;
@@ -84,6 +85,8 @@ pub enum ShardSyncStatus {
 pub struct ShardSyncDownload {
     pub downloads: Vec<DownloadStatus>,
     pub status: ShardSyncStatus,
+    /// Targets chosen for the last round of state requests, best ranked first.
+    pub targets: Vec<AccountOrPeerIdOrHash>,
 }
 
 /// Various status sync can be in, whether it's fast sync or archival.
@@ -323,6 +326,19 @@ pub enum QueryError {
         block_height: near_primitives::types::BlockHeight,
         block_hash: near_primitives::hash::CryptoHash,
     },
+    #[error(
+        "Function call exceeded the execution time limit of the node at block #{block_height}"
+    )]
+    ContractExecutionTimeout {
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
+    #[error("View calls to contract {contract_account_id} are disabled on this node")]
+    ContractBlacklisted {
+        contract_account_id: near_primitives::types::AccountId,
+        block_height: near_primitives::types::BlockHeight,
+        block_hash: near_primitives::hash::CryptoHash,
+    },
     #[error("The node reached its limits. Try again later. More details: {error_message}")]
     InternalError { error_message: String },
     #[error(
@@ -443,6 +459,18 @@ impl Message for GetGasPrice {
     type Result = Result<GasPriceView, GetGasPriceError>;
 }
 
+/// Estimates the gas price to attach to a transaction, from the recent blocks.
+pub struct GetGasPriceEstimate {
+    /// Number of blocks before the head to report the gas prices of.
+    pub history: NumBlocks,
+    /// Number of blocks the transaction should be included within.
+    pub target_blocks: NumBlocks,
+}
+
+impl Message for GetGasPriceEstimate {
+    type Result = Result<GasPriceEstimateView, GetGasPriceError>;
+}
+
 #[derive(thiserror::Error, Debug)]
 pub enum GetGasPriceError {
     #[error("Internal error: {error_message}")]
@@ -802,6 +830,76 @@ impl From<near_chain_primitives::Error> for GetProtocolConfigError {
     }
 }
 
+/// Blocks to pin or unpin: the blocks with the given hashes and all the known blocks at the
+/// heights in the range, including the forks.
+#[derive(Debug, Clone, Default)]
+pub struct BlockSelection {
+    pub block_hashes: Vec<CryptoHash>,
+    pub heights: Option<RangeInclusive<BlockHeight>>,
+}
+
+/// Pins the blocks, so that their copies are kept once they are garbage collected and can
+/// still be fetched with `GetBlock` and `GetChunk`. Either all the blocks are pinned or none.
+pub struct PinBlocks(pub BlockSelection);
+
+impl Message for PinBlocks {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+}
+
+/// Unpins the blocks, dropping their copies.
+pub struct UnpinBlocks(pub BlockSelection);
+
+impl Message for UnpinBlocks {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+}
+
+pub struct GetPinnedBlocks;
+
+impl Message for GetPinnedBlocks {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedBlockInfo {
+    pub block_hash: CryptoHash,
+    pub height: BlockHeight,
+    /// Size of the copy of the block and its chunks in bytes.
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedBlocksInfo {
+    /// Pinned blocks ordered by height.
+    pub blocks: Vec<PinnedBlockInfo>,
+    pub total_size: u64,
+    pub size_limit: u64,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PinBlocksError {
+    #[error("Block either has never been observed on the node or has been garbage collected: {error_message}")]
+    UnknownBlock { error_message: String },
+    #[error("Pinned blocks would take {size} bytes, over the limit of {size_limit} bytes")]
+    SizeLimitExceeded { size: u64, size_limit: u64 },
+    #[error(
+        "Height range {start_height}..={end_height} is empty or longer than {max_length} heights"
+    )]
+    InvalidHeightRange { start_height: BlockHeight, end_height: BlockHeight, max_length: u64 },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<near_chain_primitives::Error> for PinBlocksError {
+    fn from(error: near_chain_primitives::Error) -> Self {
+        match error {
+            near_chain_primitives::Error::DBNotFoundErr(error_message) => {
+                Self::UnknownBlock { error_message }
+            }
+            _ => Self::InternalError { error_message: error.to_string() },
+        }
+    }
+}
+
 #[cfg(feature = "sandbox")]
 #[derive(Debug)]
 pub enum SandboxMessage {
@@ -29,6 +29,7 @@ use near_primitives::block::{Approval, ApprovalInner, ApprovalMessage, Block, Bl
 use near_primitives::challenge::{Challenge, ChallengeBody};
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{merklize, MerklePath, PartialMerkleTree};
+use near_primitives::network::PeerId;
 use near_primitives::receipt::Receipt;
 use near_primitives::sharding::{
     ChunkHash, EncodedShardChunk, PartialEncodedChunk, PartialEncodedChunkV2, ReedSolomonWrapper,
@@ -54,7 +55,7 @@ use near_network_primitives::types::{
 use near_o11y::log_assert;
 use near_primitives::block_header::ApprovalType;
 use near_primitives::epoch_manager::RngSeed;
-use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::version::{get_protocol_version, PROTOCOL_VERSION};
 
 const NUM_REBROADCAST_BLOCKS: usize = 30;
 
@@ -174,6 +175,8 @@ impl Client {
             validator_signer.as_ref().map(|x| x.validator_id().clone()),
             runtime_adapter.clone(),
             network_adapter.clone(),
+            config.chunk_request_fan_out,
+            config.chunk_request_hedge_delay,
             rng_seed,
         );
         let sync_status = SyncStatus::AwaitingPeers;
@@ -564,10 +567,15 @@ impl Client {
             self.runtime_adapter.get_epoch_protocol_version(&epoch_id)?;
         let next_epoch_protocol_version =
             self.runtime_adapter.get_epoch_protocol_version(&next_epoch_id)?;
+        let latest_protocol_version = get_protocol_version(
+            next_epoch_protocol_version,
+            self.config.protocol_upgrade_voting_start,
+        );
 
         let block = Block::produce(
             this_epoch_protocol_version,
             next_epoch_protocol_version,
+            latest_protocol_version,
             prev_header,
             next_height,
             block_ordinal,
@@ -908,10 +916,11 @@ impl Client {
 
     pub fn process_partial_encoded_chunk_response(
         &mut self,
-        response: PartialEncodedChunkResponseMsg,
+        mut response: PartialEncodedChunkResponseMsg,
         apply_chunks_done_callback: DoneApplyChunkCallback,
     ) -> Result<(), Error> {
         let header = self.shards_mgr.get_partial_encoded_chunk_header(&response.chunk_hash)?;
+        self.shards_mgr.remove_known_parts_and_receipts(&mut response);
         let partial_chunk = PartialEncodedChunk::new(header, response.parts, response.receipts);
         // We already know the header signature is valid because we read it from the
         // shard manager.
@@ -1222,6 +1231,16 @@ impl Client {
                     self.chain.clear_data(tries, &self.config.gc)
                 };
                 log_assert!(result.is_ok(), "Can't clear old data, {:?}", result);
+
+                if let Some(num_blocks_to_keep) =
+                    self.config.gc.outcome_locations_num_blocks_to_keep
+                {
+                    let result = self.chain.clear_outcome_locations(
+                        num_blocks_to_keep,
+                        self.config.gc.gc_blocks_limit,
+                    );
+                    log_assert!(result.is_ok(), "Can't clear outcome locations, {:?}", result);
+                }
             }
 
             if self.runtime_adapter.is_next_block_epoch_start(block.hash()).unwrap_or(false) {
@@ -1820,6 +1839,7 @@ impl Client {
     pub fn run_catchup(
         &mut self,
         highest_height_peers: &Vec<FullPeerInfo>,
+        peer_scores: &HashMap<PeerId, i64>,
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
         block_catch_up_task_scheduler: &dyn Fn(BlockCatchUpRequest),
         state_split_scheduler: &dyn Fn(StateSplitRequest),
@@ -1852,6 +1872,7 @@ impl Client {
                                     ShardSyncDownload {
                                         downloads: vec![],
                                         status: ShardSyncStatus::StateSplitScheduling,
+                                        targets: vec![],
                                     },
                                 ))
                             } else {
@@ -1889,6 +1910,7 @@ impl Client {
                 &mut self.chain,
                 &self.runtime_adapter,
                 highest_height_peers,
+                peer_scores,
                 state_sync_info.shards.iter().map(|tuple| tuple.0).collect(),
                 state_parts_task_scheduler,
                 state_split_scheduler,
@@ -1,6 +1,7 @@
 //! Client actor orchestrates Client and facilitates network connection.
 
 use crate::client::{Client, EPOCH_START_INFO_BLOCKS};
+use crate::finality_stall::{self, FinalityStall};
 use crate::info::{
     display_sync_status, get_validator_epoch_stats, InfoHelper, ValidatorInfoHelper,
 };
@@ -49,7 +50,10 @@ use near_primitives::unwrap_or_return;
 use near_primitives::utils::{from_timestamp, MaybeValidated};
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{DetailedDebugStatus, ValidatorInfo};
+use near_primitives::views::{
+    DetailedDebugStatus, EpochReadinessView, NetworkAdvisoryView, ProtocolDeprecationView,
+    ValidatorInfo,
+};
 use near_store::DBCol;
 use near_telemetry::TelemetryActor;
 use rand::seq::SliceRandom;
@@ -85,6 +89,10 @@ pub struct ClientActor {
     last_validator_announce_time: Option<Instant>,
     /// Info helper.
     info_helper: InfoHelper,
+    /// Readiness checklist for the upcoming epoch switch, see `epoch_readiness`.
+    epoch_readiness: Option<EpochReadinessView>,
+    /// Alarm for the final head not advancing, see `finality_stall`.
+    finality_stall: FinalityStall,
 
     /// Last time handle_block_production method was called
     block_production_next_attempt: DateTime<Utc>,
@@ -95,6 +103,7 @@ pub struct ClientActor {
     block_production_started: bool,
     doomslug_timer_next_attempt: DateTime<Utc>,
     chunk_request_retry_next_attempt: DateTime<Utc>,
+    chunk_request_hedge_next_attempt: DateTime<Utc>,
     sync_started: bool,
     state_parts_task_scheduler: Box<dyn Fn(ApplyStatePartsRequest)>,
     block_catch_up_scheduler: Box<dyn Fn(BlockCatchUpRequest)>,
@@ -159,6 +168,7 @@ impl ClientActor {
             info!(target: "client", "Starting validator node: {}", vs.validator_id());
         }
         let info_helper = InfoHelper::new(telemetry_actor, &config, validator_signer.clone());
+        let finality_stall = FinalityStall::new(config.finality_stall_threshold);
         let client = Client::new(
             config,
             chain_genesis,
@@ -185,14 +195,20 @@ impl ClientActor {
                 sent_bytes_per_sec: 0,
                 known_producers: vec![],
                 peer_counter: 0,
+                peer_scores: Default::default(),
+                protocol_deprecation: None,
+                advisories: vec![],
             },
             last_validator_announce_time: None,
             info_helper,
+            epoch_readiness: None,
+            finality_stall,
             block_production_next_attempt: now,
             log_summary_timer_next_attempt: now,
             block_production_started: false,
             doomslug_timer_next_attempt: now,
             chunk_request_retry_next_attempt: now,
+            chunk_request_hedge_next_attempt: now,
             sync_started: false,
             state_parts_task_scheduler: create_sync_job_scheduler::<ApplyStatePartsRequest>(
                 sync_jobs_actor_addr.clone(),
@@ -764,6 +780,23 @@ impl Handler<Status> for ClientActor {
             },
             validator_account_id,
             detailed_debug_status,
+            preflight_report: self.client.config.preflight_report.clone(),
+            db_recovery: self.client.config.db_recovery_report.clone(),
+            protocol_deprecation: self.network_info.protocol_deprecation.map(|deprecation| {
+                ProtocolDeprecationView {
+                    protocol_version: deprecation.protocol_version,
+                    epoch_height: deprecation.epoch_height,
+                }
+            }),
+            network_advisories: (self.network_info.advisories.iter())
+                .map(|advisory| NetworkAdvisoryView {
+                    signer: advisory.signer.clone(),
+                    nonce: advisory.nonce,
+                    timestamp: advisory.timestamp,
+                    message: advisory.message.clone(),
+                })
+                .collect(),
+            epoch_readiness: self.epoch_readiness.clone(),
         })
     }
 }
@@ -1101,6 +1134,23 @@ impl ClientActor {
             },
             "resend_chunk_requests",
         );
+        if let Some(hedge_delay) = self.client.config.chunk_request_hedge_delay {
+            // Hedges are sent between one and two hedge delays after the request.
+            self.chunk_request_hedge_next_attempt = self.run_timer(
+                hedge_delay,
+                self.chunk_request_hedge_next_attempt,
+                ctx,
+                |act, _ctx| act.client.shards_mgr.send_hedged_chunk_requests(),
+                "send_hedged_chunk_requests",
+            );
+            delay = core::cmp::min(
+                delay,
+                self.chunk_request_hedge_next_attempt
+                    .signed_duration_since(now)
+                    .to_std()
+                    .unwrap_or(delay),
+            );
+        }
         timer.observe_duration();
         core::cmp::min(
             delay,
@@ -1521,6 +1571,7 @@ impl ClientActor {
         let _d = delay_detector::DelayDetector::new(|| "client catchup".into());
         if let Err(err) = self.client.run_catchup(
             &self.network_info.highest_height_peers,
+            &self.network_info.peer_scores,
             &self.state_parts_task_scheduler,
             &self.block_catch_up_scheduler,
             &self.state_split_scheduler,
@@ -1672,6 +1723,7 @@ impl ClientActor {
                     &mut self.client.chain,
                     &self.client.runtime_adapter,
                     &self.network_info.highest_height_peers,
+                    &self.network_info.peer_scores,
                     shards_to_sync,
                     &self.state_parts_task_scheduler,
                     &self.state_split_scheduler,
@@ -1729,6 +1781,8 @@ impl ClientActor {
         let _span = tracing::debug_span!(target: "client", "log_summary").entered();
         let _d = delay_detector::DelayDetector::new(|| "client log summary".into());
         let is_syncing = self.client.sync_status.is_syncing();
+        self.update_epoch_readiness(is_syncing);
+        self.check_finality_stall(is_syncing);
         let head = unwrap_or_return!(self.client.chain.head());
         let validator_info = if !is_syncing {
             let validators = unwrap_or_return!(self
@@ -1793,6 +1847,80 @@ impl ClientActor {
         );
         debug!(target: "stats", "{}", self.client.detailed_upcoming_blocks_info_as_printable().unwrap_or(String::from("Upcoming block info failed.")));
     }
+
+    /// Evaluates the readiness checklist for the upcoming epoch switch and logs the problems.
+    /// The checklist isn't evaluated while syncing, since the head is behind the chain then.
+    fn update_epoch_readiness(&mut self, is_syncing: bool) {
+        let readiness = if is_syncing {
+            None
+        } else {
+            match crate::epoch_readiness::evaluate(&self.client) {
+                Ok(readiness) => readiness,
+                Err(err) => {
+                    debug!(target: "client", ?err, "Failed to evaluate the epoch switch readiness");
+                    None
+                }
+            }
+        };
+        if let Some(readiness) = &readiness {
+            crate::epoch_readiness::log_changes(self.epoch_readiness.as_ref(), readiness);
+        }
+        self.epoch_readiness = readiness;
+    }
+
+    /// Raises the alarm if the final head doesn't keep up with the head, and writes the
+    /// diagnostic bundle when it does.  Finality isn't checked while syncing, since the blocks
+    /// are then processed well after they were produced.
+    fn check_finality_stall(&mut self, is_syncing: bool) {
+        if is_syncing {
+            return;
+        }
+        let (head, final_head) = match (self.client.chain.head(), self.client.chain.final_head()) {
+            (Ok(head), Ok(final_head)) => (head, final_head),
+            _ => return,
+        };
+        match self.finality_stall.update(head.height, final_head.height) {
+            finality_stall::Transition::None => {}
+            finality_stall::Transition::Stalled => {
+                metrics::FINALITY_STALLED.set(1);
+                metrics::FINALITY_STALLS_TOTAL.inc();
+                error!(
+                    target: "client",
+                    head_height = head.height,
+                    final_height = final_head.height,
+                    "Finality stalled: the final head didn't advance for {} blocks",
+                    head.height - final_head.height,
+                );
+                let dir = match &self.client.config.diagnostics_dir {
+                    Some(dir) => dir,
+                    None => return,
+                };
+                let last_blocks = self.get_last_blocks_info().unwrap_or_default();
+                match finality_stall::write_bundle(
+                    dir,
+                    &self.client,
+                    &self.network_info,
+                    last_blocks,
+                ) {
+                    Ok(path) => {
+                        info!(target: "client", path = %path.display(), "Wrote the finality stall diagnostic bundle")
+                    }
+                    Err(err) => {
+                        warn!(target: "client", ?err, "Failed to write the finality stall diagnostic bundle")
+                    }
+                }
+            }
+            finality_stall::Transition::Resumed => {
+                metrics::FINALITY_STALLED.set(0);
+                info!(
+                    target: "client",
+                    final_height = final_head.height,
+                    "Finality resumed after stalling at height {}",
+                    self.finality_stall.stalled_at().unwrap_or_default(),
+                );
+            }
+        }
+    }
 }
 
 impl Drop for ClientActor {
@@ -7,7 +7,8 @@ use borsh::BorshSerialize;
 use near_chain::crypto_hash_timer::CryptoHashTimer;
 use near_chain::{near_chain_primitives, ChainStoreAccess};
 use near_client_primitives::debug::{
-    BlockProduction, ChunkProduction, DebugStatus, DebugStatusResponse, ProductionAtHeight,
+    BlockProduction, ChunkProduction, DebugOrphanStatus, DebugStatus, DebugStatusResponse,
+    ProductionAtHeight, ProjectedProtocolUpgrade, ProtocolUpgradeView, ProtocolVersionVote,
     ValidatorStatus,
 };
 use near_client_primitives::types::Error;
@@ -18,6 +19,7 @@ use near_client_primitives::{
 use near_performance_metrics_macros::perf;
 use near_primitives::syncing::get_num_state_parts;
 use near_primitives::types::{AccountId, BlockHeight};
+use near_primitives::version::{get_protocol_version, PROTOCOL_VERSION};
 use near_primitives::{
     hash::CryptoHash,
     syncing::{ShardStateSyncResponseHeader, StateHeaderKey},
@@ -60,6 +62,10 @@ impl Handler<DebugStatus> for ClientActor {
             DebugStatus::ValidatorStatus => {
                 Ok(DebugStatusResponse::ValidatorStatus(self.get_validator_status()?))
             }
+            DebugStatus::ProtocolUpgrade => {
+                Ok(DebugStatusResponse::ProtocolUpgrade(self.get_protocol_upgrade_view()?))
+            }
+            DebugStatus::Orphans => Ok(DebugStatusResponse::Orphans(self.get_orphans())),
         }
     }
 }
@@ -255,8 +261,8 @@ impl ClientActor {
         Ok(epochs_info)
     }
 
-    fn get_last_blocks_info(
-        &mut self,
+    pub(crate) fn get_last_blocks_info(
+        &self,
     ) -> Result<Vec<DebugBlockStatus>, near_chain_primitives::Error> {
         let head = self.client.chain.head()?;
 
@@ -340,6 +346,77 @@ impl ClientActor {
         Ok(blocks_debug)
     }
 
+    /// Returns the protocol version votes of the current epoch, together with the upgrade they
+    /// are going to result in.
+    fn get_orphans(&self) -> Vec<DebugOrphanStatus> {
+        self.client
+            .chain
+            .orphans_status()
+            .into_iter()
+            .map(|orphan| DebugOrphanStatus {
+                block_hash: orphan.block_hash,
+                block_height: orphan.height,
+                prev_hash: orphan.prev_hash,
+                missing_ancestor: orphan.missing_ancestor,
+                in_pool_ms: orphan.added.elapsed().as_millis() as u64,
+                requested_missing_chunks: orphan.requested_missing_chunks,
+            })
+            .collect()
+    }
+
+    fn get_protocol_upgrade_view(
+        &self,
+    ) -> Result<ProtocolUpgradeView, near_chain_primitives::Error> {
+        let head = self.client.chain.head()?;
+        let runtime_adapter = &self.client.runtime_adapter;
+        let epoch_height =
+            runtime_adapter.get_epoch_height_from_prev_block(&head.prev_block_hash)?;
+        let epoch_start_height = runtime_adapter.get_epoch_start_height(&head.last_block_hash)?;
+        let epoch_length = self.client.config.epoch_length;
+        let protocol_version = runtime_adapter.get_epoch_protocol_version(&head.epoch_id)?;
+        let next_epoch_protocol_version =
+            runtime_adapter.get_epoch_protocol_version(&head.next_epoch_id)?;
+        let voting_start = self.client.config.protocol_upgrade_voting_start;
+        let votes = runtime_adapter.get_protocol_upgrade_votes(&head.last_block_hash)?;
+
+        // Votes of this epoch decide the protocol version of the epoch after next.
+        let projected_upgrade = if next_epoch_protocol_version > protocol_version {
+            Some(ProjectedProtocolUpgrade {
+                protocol_version: next_epoch_protocol_version,
+                epoch_height: epoch_height + 1,
+                start_height: epoch_start_height + epoch_length,
+            })
+        } else {
+            votes.winner().filter(|&version| version > next_epoch_protocol_version).map(|version| {
+                ProjectedProtocolUpgrade {
+                    protocol_version: version,
+                    epoch_height: epoch_height + 2,
+                    start_height: epoch_start_height + 2 * epoch_length,
+                }
+            })
+        };
+
+        let mut stake_votes: Vec<_> = votes
+            .votes
+            .iter()
+            .map(|(&protocol_version, &stake)| ProtocolVersionVote { protocol_version, stake })
+            .collect();
+        stake_votes.sort_by(|a, b| b.stake.cmp(&a.stake));
+
+        Ok(ProtocolUpgradeView {
+            epoch_height,
+            protocol_version,
+            next_epoch_protocol_version,
+            binary_protocol_version: PROTOCOL_VERSION,
+            voted_protocol_version: get_protocol_version(next_epoch_protocol_version, voting_start),
+            voting_start,
+            votes: stake_votes,
+            total_block_producer_stake: votes.total_block_producer_stake,
+            upgrade_stake_threshold: votes.upgrade_stake_threshold,
+            projected_upgrade,
+        })
+    }
+
     /// Returns debugging information about the validator - including things like which approvals were received, which blocks/chunks will be
     /// produced and some detailed timing information.
     fn get_validator_status(&mut self) -> Result<ValidatorStatus, near_chain_primitives::Error> {
@@ -0,0 +1,250 @@
+//! Readiness checklist of the node for the upcoming epoch switch.
+//!
+//! During the last `READINESS_HORIZON` blocks of an epoch the client checks whether it will
+//! be able to follow the chain and do its job in the next epoch: whether the state of the
+//! shards it starts tracking has been caught up, whether its validator key matches the one
+//! the next epoch expects, whether it supports the protocol version of the next epoch and
+//! whether there is enough free disk space.  Problems are logged as warnings and reported in
+//! the `status` RPC, so that the validators learn about them before they start missing chunks.
+use crate::client::Client;
+use near_chain::Error;
+use near_crypto::PublicKey;
+use near_primitives::sharding::ShardInfo;
+use near_primitives::types::{AccountId, BlockHeightDelta, ProtocolVersion, ShardId};
+use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::{EpochReadinessView, PreflightCheckStatus, PreflightCheckView};
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Number of the last blocks of an epoch during which the checklist is evaluated.
+const READINESS_HORIZON: BlockHeightDelta = 300;
+/// Free disk space below which the node is unlikely to get through the next epoch.
+const MIN_FREE_DISK_SPACE: u64 = 10 << 30;
+/// Free disk space below which a warning is reported.
+const LOW_FREE_DISK_SPACE: u64 = 50 << 30;
+
+/// Evaluates the checklist if the head is within `READINESS_HORIZON` blocks of the expected
+/// end of its epoch, returns None otherwise.
+pub(crate) fn evaluate(client: &Client) -> Result<Option<EpochReadinessView>, Error> {
+    let head = client.chain.head()?;
+    let epoch_start_height =
+        client.runtime_adapter.get_epoch_start_height(&head.last_block_hash)?;
+    let next_epoch_start_height = epoch_start_height + client.config.epoch_length;
+    if next_epoch_start_height.saturating_sub(head.height) > READINESS_HORIZON {
+        return Ok(None);
+    }
+
+    let catchup_shards: Vec<ShardId> = (client.chain.store().iterate_state_sync_infos()?)
+        .into_iter()
+        .flat_map(|(_, info)| info.shards.into_iter().map(|ShardInfo(shard_id, _)| shard_id))
+        .collect();
+    let validator_key = match &client.validator_signer {
+        Some(signer) => {
+            let next_epoch_key = match client.runtime_adapter.get_validator_by_account_id(
+                &head.next_epoch_id,
+                &head.last_block_hash,
+                signer.validator_id(),
+            ) {
+                Ok((stake, _)) => Some(stake.public_key().clone()),
+                Err(Error::NotAValidator) => None,
+                Err(err) => return Err(err),
+            };
+            check_validator_key(signer.validator_id(), &signer.public_key(), next_epoch_key)
+        }
+        None => check("validator_key", PreflightCheckStatus::Ok, "Not a validator".to_string()),
+    };
+    let next_protocol_version =
+        client.runtime_adapter.get_epoch_protocol_version(&head.next_epoch_id)?;
+    let checks = vec![
+        check_next_epoch_state(&catchup_shards),
+        validator_key,
+        check_protocol_version(next_protocol_version),
+        check_disk_space(client.config.store_path.as_deref()),
+    ];
+    Ok(Some(EpochReadinessView {
+        next_epoch_id: head.next_epoch_id,
+        next_epoch_start_height,
+        checks,
+    }))
+}
+
+/// Logs the checks which didn't pass, and the ones which passed after failing before,
+/// unless their status is the same as in the previous evaluation.
+pub(crate) fn log_changes(prev: Option<&EpochReadinessView>, readiness: &EpochReadinessView) {
+    for check in &readiness.checks {
+        let prev_status = prev
+            .filter(|prev| prev.next_epoch_id == readiness.next_epoch_id)
+            .and_then(|prev| prev.checks.iter().find(|c| c.name == check.name))
+            .map(|c| c.status);
+        match (prev_status, check.status) {
+            (Some(prev_status), status) if prev_status == status => {}
+            (None, PreflightCheckStatus::Ok) => {}
+            (Some(_), PreflightCheckStatus::Ok) => {
+                info!(target: "client", name = %check.name, "Epoch switch readiness: {}", check.message);
+            }
+            _ => {
+                warn!(
+                    target: "client",
+                    name = %check.name,
+                    next_epoch_start_height = readiness.next_epoch_start_height,
+                    "Epoch switch readiness: {}",
+                    check.message,
+                );
+            }
+        }
+    }
+}
+
+fn check(name: &str, status: PreflightCheckStatus, message: String) -> PreflightCheckView {
+    PreflightCheckView { name: name.to_string(), status, message }
+}
+
+/// Checks that the state of the shards tracked from the next epoch on has been caught up.
+fn check_next_epoch_state(catchup_shards: &[ShardId]) -> PreflightCheckView {
+    const NAME: &str = "next_epoch_state";
+    if catchup_shards.is_empty() {
+        return check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            "State of the next epoch is ready".to_string(),
+        );
+    }
+    check(
+        NAME,
+        PreflightCheckStatus::Error,
+        format!(
+            "State of shards {:?} tracked in the next epoch is still being caught up; \
+             check the state sync progress and the connectivity to the peers",
+            catchup_shards
+        ),
+    )
+}
+
+/// Checks that the validator key of the node is the one the next epoch expects.
+fn check_validator_key(
+    account_id: &AccountId,
+    key: &PublicKey,
+    next_epoch_key: Option<PublicKey>,
+) -> PreflightCheckView {
+    const NAME: &str = "validator_key";
+    match next_epoch_key {
+        None => check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("{} is not a validator in the next epoch", account_id),
+        ),
+        Some(next_epoch_key) if &next_epoch_key == key => check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("Validator key of {} matches the next epoch", account_id),
+        ),
+        Some(next_epoch_key) => check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "The next epoch expects {} to sign with {}, but validator_key.json has {}; \
+                 replace validator_key.json with the staked key and restart the node",
+                account_id, next_epoch_key, key
+            ),
+        ),
+    }
+}
+
+/// Checks that this binary supports the protocol version of the next epoch.
+fn check_protocol_version(next_protocol_version: ProtocolVersion) -> PreflightCheckView {
+    const NAME: &str = "protocol_version";
+    if next_protocol_version <= PROTOCOL_VERSION {
+        return check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("Protocol version {} of the next epoch is supported", next_protocol_version),
+        );
+    }
+    check(
+        NAME,
+        PreflightCheckStatus::Error,
+        format!(
+            "The next epoch switches to protocol version {}, while this binary supports up to {}; \
+             upgrade neard before the epoch switch",
+            next_protocol_version, PROTOCOL_VERSION
+        ),
+    )
+}
+
+/// Checks the free space on the disk holding the database.
+fn check_disk_space(store_path: Option<&Path>) -> PreflightCheckView {
+    const NAME: &str = "disk_space";
+    let store_path = match store_path {
+        Some(store_path) => store_path,
+        None => {
+            return check(
+                NAME,
+                PreflightCheckStatus::Ok,
+                "Free disk space isn't checked".to_string(),
+            )
+        }
+    };
+    match fs2::available_space(store_path) {
+        Ok(available) => check_free_disk_space(available),
+        Err(err) => check(
+            NAME,
+            PreflightCheckStatus::Warning,
+            format!("Unable to check free disk space at {}: {err}", store_path.display()),
+        ),
+    }
+}
+
+fn check_free_disk_space(available: u64) -> PreflightCheckView {
+    const NAME: &str = "disk_space";
+    let status = if available < MIN_FREE_DISK_SPACE {
+        PreflightCheckStatus::Error
+    } else if available < LOW_FREE_DISK_SPACE {
+        PreflightCheckStatus::Warning
+    } else {
+        PreflightCheckStatus::Ok
+    };
+    let gib = available as f64 / (1u64 << 30) as f64;
+    let message = match status {
+        PreflightCheckStatus::Ok => format!("{:.1} GiB of free disk space", gib),
+        _ => format!(
+            "Only {:.1} GiB of free disk space left; free up space or grow the disk \
+             before the epoch switch",
+            gib
+        ),
+    };
+    check(NAME, status, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+
+    #[test]
+    fn test_checks() {
+        assert_eq!(check_next_epoch_state(&[]).status, PreflightCheckStatus::Ok);
+        assert_eq!(check_next_epoch_state(&[1, 2]).status, PreflightCheckStatus::Error);
+
+        let account_id: AccountId = "test.near".parse().unwrap();
+        let key = SecretKey::from_seed(KeyType::ED25519, "key").public_key();
+        let other_key = SecretKey::from_seed(KeyType::ED25519, "other").public_key();
+        for (next_epoch_key, status) in [
+            (None, PreflightCheckStatus::Ok),
+            (Some(key.clone()), PreflightCheckStatus::Ok),
+            (Some(other_key), PreflightCheckStatus::Error),
+        ] {
+            assert_eq!(check_validator_key(&account_id, &key, next_epoch_key).status, status);
+        }
+
+        assert_eq!(check_protocol_version(PROTOCOL_VERSION).status, PreflightCheckStatus::Ok);
+        assert_eq!(
+            check_protocol_version(PROTOCOL_VERSION + 1).status,
+            PreflightCheckStatus::Error
+        );
+
+        assert_eq!(check_free_disk_space(100 << 30).status, PreflightCheckStatus::Ok);
+        assert_eq!(check_free_disk_space(20 << 30).status, PreflightCheckStatus::Warning);
+        assert_eq!(check_free_disk_space(1 << 30).status, PreflightCheckStatus::Error);
+        assert_eq!(check_disk_space(None).status, PreflightCheckStatus::Ok);
+    }
+}
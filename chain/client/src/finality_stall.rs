@@ -0,0 +1,140 @@
+//! Alarm for a stalled finality.
+//!
+//! The final head normally trails the head by two blocks.  If it stops advancing while the
+//! head keeps growing, e.g. because the block producers don't receive enough approvals from
+//! each other, the client raises an alarm once the head gets `finality_stall_threshold`
+//! blocks ahead of the final head: it sets the `near_finality_stalled` metric, logs an error
+//! and writes a diagnostic bundle with the approval stats, the peer table, the sync status
+//! and the last processed blocks to a file, so that the stall can be analysed after the fact.
+use crate::client::Client;
+use near_chain::Error;
+use near_client_primitives::debug::DebugBlockStatus;
+use near_network::types::NetworkInfo;
+use near_primitives::types::{BlockHeight, BlockHeightDelta};
+use near_primitives::views::NetworkInfoView;
+use std::path::{Path, PathBuf};
+
+/// Number of the heights after the final head whose approvals are included in the bundle.
+const APPROVAL_HEIGHTS: BlockHeightDelta = 10;
+
+/// Change of the alarm after an update.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Transition {
+    None,
+    /// The head got the threshold ahead of the final head.
+    Stalled,
+    /// The final head caught up to within the threshold of the head again.
+    Resumed,
+}
+
+/// Tracks whether finality is stalled.
+pub(crate) struct FinalityStall {
+    threshold: Option<BlockHeightDelta>,
+    /// Height of the final head when the alarm was raised, None while it isn't raised.
+    stalled_at: Option<BlockHeight>,
+}
+
+impl FinalityStall {
+    pub(crate) fn new(threshold: Option<BlockHeightDelta>) -> Self {
+        Self { threshold, stalled_at: None }
+    }
+
+    /// Height of the final head when the alarm was raised, None while it isn't raised.
+    pub(crate) fn stalled_at(&self) -> Option<BlockHeight> {
+        self.stalled_at
+    }
+
+    /// Updates the alarm with the current heights of the head and of the final head.
+    /// The alarm is raised once per stall, it stays raised while the final head crawls
+    /// forward but is still the threshold behind.
+    pub(crate) fn update(
+        &mut self,
+        head_height: BlockHeight,
+        final_height: BlockHeight,
+    ) -> Transition {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return Transition::None,
+        };
+        let stalled = head_height.saturating_sub(final_height) >= threshold;
+        match (self.stalled_at, stalled) {
+            (None, true) => {
+                self.stalled_at = Some(final_height);
+                Transition::Stalled
+            }
+            (Some(_), false) => {
+                self.stalled_at = None;
+                Transition::Resumed
+            }
+            _ => Transition::None,
+        }
+    }
+}
+
+/// Collects the diagnostic bundle of a finality stall and writes it as JSON to a new file in
+/// `dir`.  Returns the path of the file.
+pub(crate) fn write_bundle(
+    dir: &Path,
+    client: &Client,
+    network_info: &NetworkInfo,
+    last_blocks: Vec<DebugBlockStatus>,
+) -> Result<PathBuf, Error> {
+    let head = client.chain.head()?;
+    let final_head = client.chain.final_head()?;
+    let approvals: Vec<_> = (final_head.height + 1..=head.height + 1)
+        .rev()
+        .take(APPROVAL_HEIGHTS as usize)
+        .map(|height| {
+            serde_json::json!({
+                "height": height,
+                "status": client.doomslug.approval_status_at_height(&height),
+            })
+        })
+        .collect();
+    let network_info: NetworkInfoView = network_info.clone().into();
+    let bundle = serde_json::json!({
+        "timestamp": chrono::Utc::now(),
+        "head": { "height": head.height, "hash": head.last_block_hash },
+        "final_head": { "height": final_head.height, "hash": final_head.last_block_hash },
+        "largest_target_height": client.doomslug.get_largest_target_height(),
+        "approvals": approvals,
+        "approval_history": client.doomslug.get_approval_history(),
+        "peers": network_info,
+        "sync_status": client.sync_status,
+        "last_blocks": last_blocks,
+    });
+
+    let path = dir.join(format!(
+        "finality-stall-{}-{}.json",
+        final_head.height,
+        chrono::Utc::now().timestamp()
+    ));
+    let write = || -> std::io::Result<()> {
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, serde_json::to_vec_pretty(&bundle)?)
+    };
+    write().map_err(|err| Error::Other(format!("{}: {}", path.display(), err)))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finality_stall() {
+        let mut stall = FinalityStall::new(Some(10));
+        assert_eq!(stall.update(100, 98), Transition::None);
+        assert_eq!(stall.update(107, 98), Transition::None);
+        assert_eq!(stall.update(108, 98), Transition::Stalled);
+        assert_eq!(stall.stalled_at(), Some(98));
+        // Raised only once per stall, even if the final head crawls forward.
+        assert_eq!(stall.update(120, 99), Transition::None);
+        assert_eq!(stall.update(121, 119), Transition::Resumed);
+        assert_eq!(stall.stalled_at(), None);
+        assert_eq!(stall.update(140, 119), Transition::Stalled);
+
+        let mut disabled = FinalityStall::new(None);
+        assert_eq!(disabled.update(1000, 0), Transition::None);
+    }
+}
@@ -1,10 +1,11 @@
 pub use near_client_primitives::types::{
     Error, GetBlock, GetBlockHash, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree,
     GetChunk, GetExecutionOutcome, GetExecutionOutcomeResponse, GetExecutionOutcomesForBlock,
-    GetGasPrice, GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt,
-    GetStateChanges, GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    GetGasPrice, GetGasPriceEstimate, GetNetworkInfo, GetNextLightClientBlock, GetPinnedBlocks,
+    GetProtocolConfig, GetReceipt, GetStateChanges, GetStateChangesInBlock,
+    GetStateChangesWithCauseInBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetValidatorInfo, GetValidatorOrdered, PinBlocks, Query, QueryError, Status, StatusResponse,
+    SyncStatus, TxStatus, TxStatusError, UnpinBlocks,
 };
 
 pub use near_client_primitives::debug::DebugStatus;
@@ -17,6 +18,8 @@ pub mod adversarial;
 mod client;
 mod client_actor;
 pub mod debug;
+mod epoch_readiness;
+mod finality_stall;
 mod info;
 mod metrics;
 mod rocksdb_metrics;
@@ -27,6 +27,22 @@ pub(crate) static IS_VALIDATOR: Lazy<IntGauge> = Lazy::new(|| {
         .unwrap()
 });
 
+pub(crate) static FINALITY_STALLED: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_finality_stalled",
+        "Bool to denote if the final head is stalled behind the head",
+    )
+    .unwrap()
+});
+
+pub(crate) static FINALITY_STALLS_TOTAL: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_finality_stalls_total",
+        "Total number of times finality stalled since starting this node",
+    )
+    .unwrap()
+});
+
 pub(crate) static RECEIVED_BYTES_PER_SECOND: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_received_bytes_per_second",
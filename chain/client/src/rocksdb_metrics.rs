@@ -114,16 +114,22 @@ impl RocksDBMetrics {
     }
 }
 
+/// Replaces the characters which are not allowed in prometheus metric names.
+/// RocksDB statistics use dots, while RocksDB properties use both dots and hyphens.
+fn normalize_stat_name(stat_name: &str) -> String {
+    stat_name.replace(['.', '-'], "_")
+}
+
 fn get_prometheus_metric_name(stat_name: &str) -> String {
-    format!("near_{}", stat_name.replace('.', "_"))
+    format!("near_{}", normalize_stat_name(stat_name))
 }
 
 fn get_metric_name_summary_count_gauge(stat_name: &str) -> String {
-    format!("near_{}_count", stat_name.replace('.', "_"))
+    format!("near_{}_count", normalize_stat_name(stat_name))
 }
 
 fn get_metric_name_summary_sum_gauge(stat_name: &str) -> String {
-    format!("near_{}_sum", stat_name.replace('.', "_"))
+    format!("near_{}_sum", normalize_stat_name(stat_name))
 }
 
 fn get_stats_summary_count_key(stat_name: &str) -> String {
@@ -133,3 +139,28 @@ fn get_stats_summary_count_key(stat_name: &str) -> String {
 fn get_stats_summary_sum_key(stat_name: &str) -> String {
     format!("{}.sum", stat_name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_properties_with_hyphens() {
+        let stats = StoreStatistics {
+            data: vec![
+                ("rocksdb.is-write-stopped".to_string(), vec![StatsValue::Count(1)]),
+                (
+                    "rocksdb.estimate-pending-compaction-bytes".to_string(),
+                    vec![StatsValue::Count(1024)],
+                ),
+                ("rocksdb.num.running.flushes".to_string(), vec![StatsValue::Count(2)]),
+            ],
+        };
+        let mut metrics = RocksDBMetrics::default();
+        metrics.export_stats_as_metrics(stats).unwrap();
+        assert_eq!(1, metrics.int_gauges["rocksdb.is-write-stopped"].get());
+        assert_eq!(1024, metrics.int_gauges["rocksdb.estimate-pending-compaction-bytes"].get());
+        // Statistics after the properties with hyphens are exported too.
+        assert_eq!(2, metrics.int_gauges["rocksdb.num.running.flushes"].get());
+    }
+}
@@ -10,7 +10,7 @@ use ansi_term::Color::{Purple, Yellow};
 use chrono::{DateTime, Duration};
 use futures::{future, FutureExt};
 use rand::seq::{IteratorRandom, SliceRandom};
-use rand::{thread_rng, Rng};
+use rand::thread_rng;
 use tracing::{debug, error, info, warn};
 
 use near_chain::{Chain, RuntimeAdapter};
@@ -50,6 +50,12 @@ pub const MAX_STATE_PART_REQUEST: u64 = 16;
 /// Number of state parts already requested stored as pending.
 /// This number should not exceed MAX_STATE_PART_REQUEST times (number of peers in the network).
 pub const MAX_PENDING_PART: u64 = MAX_STATE_PART_REQUEST * 10000;
+/// Number of state sync targets for which we remember how many requests to them failed.
+const MAX_TRACKED_TARGET_FAILURES: usize = 10000;
+/// Rank bonus of archival peers, which are sure to still have the state at the sync hash.
+const ARCHIVAL_TARGET_BONUS: i64 = 100;
+/// Rank penalty for every state part request to the target which timed out.
+const FAILED_REQUEST_PENALTY: i64 = 50;
 
 pub const NS_PER_SECOND: u128 = 1_000_000_000;
 
@@ -632,6 +638,9 @@ pub struct StateSync {
     last_part_id_requested: HashMap<(AccountOrPeerIdOrHash, ShardId), PendingRequestStatus>,
     /// Map from which part we requested to whom.
     requested_target: lru::LruCache<(u64, CryptoHash), AccountOrPeerIdOrHash>,
+    /// Number of timed out requests per target, decreased when the target answers all
+    /// the requests sent to it.
+    target_failures: lru::LruCache<AccountOrPeerIdOrHash, u64>,
 
     timeout: Duration,
 
@@ -650,6 +659,7 @@ impl StateSync {
             last_time_block_requested: None,
             last_part_id_requested: Default::default(),
             requested_target: lru::LruCache::new(MAX_PENDING_PART as usize),
+            target_failures: lru::LruCache::new(MAX_TRACKED_TARGET_FAILURES),
             timeout: Duration::from_std(timeout).unwrap(),
             state_parts_apply_results: HashMap::new(),
             split_state_roots: HashMap::new(),
@@ -692,6 +702,7 @@ impl StateSync {
         chain: &mut Chain,
         runtime_adapter: &Arc<dyn RuntimeAdapter>,
         highest_height_peers: &Vec<FullPeerInfo>,
+        peer_scores: &HashMap<PeerId, i64>,
         tracking_shards: Vec<ShardId>,
         now: DateTime<Utc>,
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
@@ -713,6 +724,7 @@ impl StateSync {
                 1
             ],
             status: ShardSyncStatus::StateDownloadHeader,
+            targets: vec![],
         };
 
         let prev_hash = *chain.get_block_header(&sync_hash)?.prev_hash();
@@ -754,6 +766,7 @@ impl StateSync {
                                 state_num_parts as usize
                             ],
                             status: ShardSyncStatus::StateDownloadParts,
+                            targets: vec![],
                         };
                         need_shard = true;
                     } else {
@@ -794,6 +807,7 @@ impl StateSync {
                         *shard_sync_download = ShardSyncDownload {
                             downloads: vec![],
                             status: ShardSyncStatus::StateDownloadScheduling,
+                            targets: vec![],
                         };
                     }
                 }
@@ -812,6 +826,7 @@ impl StateSync {
                             *shard_sync_download = ShardSyncDownload {
                                 downloads: vec![],
                                 status: ShardSyncStatus::StateDownloadApplying,
+                                targets: vec![],
                             }
                         }
                         Err(e) => {
@@ -833,6 +848,7 @@ impl StateSync {
                                 *shard_sync_download = ShardSyncDownload {
                                     downloads: vec![],
                                     status: ShardSyncStatus::StateDownloadComplete,
+                                    targets: vec![],
                                 }
                             }
                             Err(e) => {
@@ -864,11 +880,13 @@ impl StateSync {
                         *shard_sync_download = ShardSyncDownload {
                             downloads: vec![],
                             status: ShardSyncStatus::StateSplitScheduling,
+                            targets: vec![],
                         }
                     } else {
                         *shard_sync_download = ShardSyncDownload {
                             downloads: vec![],
                             status: ShardSyncStatus::StateSyncDone,
+                            targets: vec![],
                         };
                         this_done = true;
                     }
@@ -884,6 +902,7 @@ impl StateSync {
                     *shard_sync_download = ShardSyncDownload {
                         downloads: vec![],
                         status: ShardSyncStatus::StateSplitApplying,
+                        targets: vec![],
                     };
                 }
                 ShardSyncStatus::StateSplitApplying => {
@@ -895,6 +914,7 @@ impl StateSync {
                         *shard_sync_download = ShardSyncDownload {
                             downloads: vec![],
                             status: ShardSyncStatus::StateSyncDone,
+                            targets: vec![],
                         };
                         this_done = true;
                     }
@@ -944,6 +964,7 @@ impl StateSync {
                     sync_hash,
                     shard_sync_download.clone(),
                     highest_height_peers,
+                    peer_scores,
                 )?;
             }
         }
@@ -1020,11 +1041,14 @@ impl StateSync {
                 },
             ) {
                 self.last_part_id_requested.remove(&(target.clone(), shard_id));
+                if let Some(failures) = self.target_failures.get_mut(target) {
+                    *failures = failures.saturating_sub(1);
+                }
             }
         }
     }
 
-    /// Find possible targets to download state from.
+    /// Find possible targets to download state from, best ranked first.
     /// Candidates are validators at current epoch and peers at highest height.
     /// Only select candidates that we have no pending request currently ongoing.
     fn possible_targets(
@@ -1035,14 +1059,31 @@ impl StateSync {
         runtime_adapter: &Arc<dyn RuntimeAdapter>,
         sync_hash: CryptoHash,
         highest_height_peers: &Vec<FullPeerInfo>,
+        peer_scores: &HashMap<PeerId, i64>,
     ) -> Result<Vec<AccountOrPeerIdOrHash>, Error> {
         // Remove candidates from pending list if request expired due to timeout
-        self.last_part_id_requested.retain(|_, request| !request.expired());
+        let target_failures = &mut self.target_failures;
+        self.last_part_id_requested.retain(|(target, _), request| {
+            if !request.expired() {
+                return true;
+            }
+            if let Some(failures) = target_failures.get_mut(target) {
+                *failures += 1;
+            } else {
+                target_failures.put(target.clone(), 1);
+            }
+            false
+        });
 
         let prev_block_hash = *chain.get_block_header(&sync_hash)?.prev_hash();
         let epoch_hash = runtime_adapter.get_epoch_id_from_prev_block(&prev_block_hash)?;
 
-        Ok(runtime_adapter
+        let archival_peers = highest_height_peers
+            .iter()
+            .filter(|peer| peer.chain_info.archival)
+            .map(|peer| peer.peer_info.id.clone())
+            .collect();
+        let targets = runtime_adapter
             .get_epoch_block_producers_ordered(&epoch_hash, &sync_hash)?
             .iter()
             .filter_map(|(validator_stake, _slashed)| {
@@ -1072,7 +1113,8 @@ impl StateSync {
             .filter(|candidate| {
                 !self.last_part_id_requested.contains_key(&(candidate.clone(), shard_id))
             })
-            .collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        Ok(rank_targets(targets, &archival_peers, peer_scores, &self.target_failures))
     }
 
     /// Returns new ShardSyncDownload if successful, otherwise returns given shard_sync_download
@@ -1085,6 +1127,7 @@ impl StateSync {
         sync_hash: CryptoHash,
         shard_sync_download: ShardSyncDownload,
         highest_height_peers: &Vec<FullPeerInfo>,
+        peer_scores: &HashMap<PeerId, i64>,
     ) -> Result<ShardSyncDownload, near_chain::Error> {
         let possible_targets = self.possible_targets(
             me,
@@ -1093,6 +1136,7 @@ impl StateSync {
             runtime_adapter,
             sync_hash,
             highest_height_peers,
+            peer_scores,
         )?;
 
         if possible_targets.is_empty() {
@@ -1101,10 +1145,11 @@ impl StateSync {
 
         // Downloading strategy starts here
         let mut new_shard_sync_download = shard_sync_download.clone();
+        new_shard_sync_download.targets = possible_targets.clone();
 
         match shard_sync_download.status {
             ShardSyncStatus::StateDownloadHeader => {
-                let target = possible_targets.choose(&mut thread_rng()).cloned().unwrap();
+                let target = possible_targets[0].clone();
                 assert!(new_shard_sync_download.downloads[0].run_me.load(Ordering::SeqCst));
                 new_shard_sync_download.downloads[0].run_me.store(false, Ordering::SeqCst);
                 new_shard_sync_download.downloads[0].state_requests_count += 1;
@@ -1128,19 +1173,20 @@ impl StateSync {
                 );
             }
             ShardSyncStatus::StateDownloadParts => {
-                let possible_targets_sampler =
-                    SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+                let num_requests = possible_targets.len() * MAX_STATE_PART_REQUEST as usize;
+                let possible_targets_cycle =
+                    possible_targets.iter().cycle().take(num_requests).cloned();
 
                 // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
                 // Parts are ordered such that its index match its part_id.
-                // Finally, for every part that needs to be requested it is selected one peer (target) randomly
-                // to request the part from
+                // Targets are assigned to the parts round-robin starting with the best ranked one,
+                // so that the worse ranked targets are only used when there are enough parts.
                 for ((part_id, download), target) in new_shard_sync_download
                     .downloads
                     .iter_mut()
                     .enumerate()
                     .filter(|(_, download)| download.run_me.load(Ordering::SeqCst))
-                    .zip(possible_targets_sampler)
+                    .zip(possible_targets_cycle)
                 {
                     self.sent_request_part(target.clone(), part_id as u64, shard_id, sync_hash);
                     download.run_me.store(false, Ordering::SeqCst);
@@ -1185,6 +1231,7 @@ impl StateSync {
         chain: &mut Chain,
         runtime_adapter: &Arc<dyn RuntimeAdapter>,
         highest_height_peers: &Vec<FullPeerInfo>,
+        peer_scores: &HashMap<PeerId, i64>,
         tracking_shards: Vec<ShardId>,
         state_parts_task_scheduler: &dyn Fn(ApplyStatePartsRequest),
         state_split_scheduler: &dyn Fn(StateSplitRequest),
@@ -1214,6 +1261,7 @@ impl StateSync {
             chain,
             runtime_adapter,
             highest_height_peers,
+            peer_scores,
             tracking_shards,
             now,
             state_parts_task_scheduler,
@@ -1233,66 +1281,32 @@ impl StateSync {
     }
 }
 
-/// Create an abstract collection of elements to be shuffled.
-/// Each element will appear in the shuffled output exactly `limit` times.
-/// Use it as an iterator to access the shuffled collection.
-///
-/// ```rust,ignore
-/// let sampler = SamplerLimited::new(vec![1, 2, 3], 2);
+/// Orders state sync targets from the most to the least preferred one.
 ///
-/// let res = sampler.collect::<Vec<_>>();
-///
-/// assert!(res.len() == 6);
-/// assert!(res.iter().filter(|v| v == 1).count() == 2);
-/// assert!(res.iter().filter(|v| v == 2).count() == 2);
-/// assert!(res.iter().filter(|v| v == 3).count() == 2);
-/// ```
-///
-/// Out of the 90 possible values of `res` in the code above on of them is:
-///
-/// ```
-/// vec![1, 2, 1, 3, 3, 2];
-/// ```
-struct SamplerLimited<T> {
-    data: Vec<T>,
-    limit: Vec<u64>,
-}
-
-impl<T> SamplerLimited<T> {
-    fn new(data: Vec<T>, limit: u64) -> Self {
-        if limit == 0 {
-            Self { data: vec![], limit: vec![] }
-        } else {
-            let len = data.len();
-            Self { data, limit: vec![limit; len] }
-        }
-    }
-}
-
-impl<T: Clone> Iterator for SamplerLimited<T> {
-    type Item = T;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.limit.is_empty() {
-            None
-        } else {
-            let len = self.limit.len();
-            let ix = thread_rng().gen_range(0, len);
-            self.limit[ix] -= 1;
-
-            if self.limit[ix] == 0 {
-                if ix + 1 != len {
-                    self.limit[ix] = self.limit[len - 1];
-                    self.data.swap(ix, len - 1);
-                }
-
-                self.limit.pop();
-                self.data.pop()
-            } else {
-                Some(self.data[ix].clone())
+/// Peers are ranked by their score, which accounts for their response latency and
+/// misbehavior, get a bonus if they are archival and a penalty for every request to them
+/// which timed out.  Validators we don't know the peers of are only ranked by failures.
+/// Targets with equal rank are shuffled, to spread the load between them.
+fn rank_targets(
+    mut targets: Vec<AccountOrPeerIdOrHash>,
+    archival_peers: &HashSet<PeerId>,
+    peer_scores: &HashMap<PeerId, i64>,
+    target_failures: &lru::LruCache<AccountOrPeerIdOrHash, u64>,
+) -> Vec<AccountOrPeerIdOrHash> {
+    targets.shuffle(&mut thread_rng());
+    targets.sort_by_key(|target| {
+        let mut rank = 0;
+        if let AccountOrPeerIdOrHash::PeerId(peer_id) = target {
+            rank += peer_scores.get(peer_id).copied().unwrap_or(0);
+            if archival_peers.contains(peer_id) {
+                rank += ARCHIVAL_TARGET_BONUS;
             }
         }
-    }
+        let failures = target_failures.peek(target).copied().unwrap_or(0);
+        rank -= FAILED_REQUEST_PENALTY.saturating_mul(failures as i64);
+        std::cmp::Reverse(rank)
+    });
+    targets
 }
 
 #[cfg(test)]
@@ -1339,6 +1353,40 @@ mod test {
     }
 
     /// Starts two chains that fork of genesis and checks that they can sync heaaders to the longest.
+    #[test]
+    fn test_rank_state_sync_targets() {
+        let slow = PeerId::random();
+        let fast = PeerId::random();
+        let archival = PeerId::random();
+        let failing = PeerId::random();
+        let validator: AccountId = "test0".parse().unwrap();
+        let target = |peer_id: &PeerId| AccountOrPeerIdOrHash::PeerId(peer_id.clone());
+
+        let archival_peers = HashSet::from([archival.clone()]);
+        let peer_scores =
+            HashMap::from([(slow.clone(), -10), (fast.clone(), 200), (failing.clone(), 200)]);
+        let mut target_failures = lru::LruCache::new(10);
+        target_failures.put(target(&failing), 5);
+
+        let targets = vec![
+            target(&failing),
+            target(&slow),
+            AccountOrPeerIdOrHash::AccountId(validator.clone()),
+            target(&archival),
+            target(&fast),
+        ];
+        assert_eq!(
+            rank_targets(targets, &archival_peers, &peer_scores, &target_failures),
+            vec![
+                target(&fast),
+                target(&archival),
+                AccountOrPeerIdOrHash::AccountId(validator),
+                target(&slow),
+                target(&failing),
+            ]
+        );
+    }
+
     #[test]
     fn test_sync_headers_fork() {
         let mock_adapter = Arc::new(MockPeerManagerAdapter::default());
@@ -1490,6 +1538,7 @@ mod test {
                     )
                 };
             let block = Block::produce(
+                PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 last_block.header(),
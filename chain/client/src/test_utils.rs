@@ -646,6 +646,9 @@ pub fn setup_mock_all_validators(
                             received_bytes_per_sec: 0,
                             known_producers: vec![],
                             peer_counter: 0,
+                            peer_scores: Default::default(),
+                            protocol_deprecation: None,
+                            advisories: vec![],
                         };
                         client_addr.do_send(NetworkClientMessages::NetworkInfo(info));
                     }
@@ -1697,6 +1700,7 @@ pub fn create_chunk(
     let mut block_merkle_tree = PartialMerkleTree::clone(&block_merkle_tree);
     block_merkle_tree.insert(*last_block.hash());
     let block = Block::produce(
+        PROTOCOL_VERSION,
         PROTOCOL_VERSION,
         PROTOCOL_VERSION,
         last_block.header(),
@@ -1744,6 +1748,7 @@ pub fn run_catchup(
     loop {
         client.run_catchup(
             highest_height_peers,
+            &HashMap::new(),
             &f,
             &block_catch_up,
             &state_split,
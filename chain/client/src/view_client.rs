@@ -1,15 +1,16 @@
 //! Readonly view of the chain and state of the database.
 //! Useful for querying from RPC.
 
-use near_primitives::receipt::Receipt;
+use near_primitives::receipt::{DelayedReceiptIndices, Receipt};
 use near_primitives::time::Clock;
 use std::cmp::Ordering;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 
 use actix::{Actor, Addr, Handler, SyncArbiter, SyncContext};
+use borsh::{BorshDeserialize, BorshSerialize};
 use tracing::{debug, error, info, trace, warn};
 
 use near_chain::types::ValidatorInfoIdentifier;
@@ -17,22 +18,23 @@ use near_chain::{
     get_epoch_block_producers_view, Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode,
     RuntimeAdapter,
 };
-use near_chain_configs::{ClientConfig, ProtocolConfigView};
+use near_chain_configs::{ClientConfig, ProtocolConfigView, TrackedShardsPolicy};
 use near_client_primitives::types::{
     Error, GetBlock, GetBlockError, GetBlockHash, GetBlockProof, GetBlockProofError,
     GetBlockProofResponse, GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome,
     GetExecutionOutcomeError, GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError,
-    GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigError, GetReceipt,
-    GetReceiptError, GetStateChangesError, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
-    TxStatus, TxStatusError,
+    GetGasPriceEstimate, GetNextLightClientBlockError, GetProtocolConfig, GetProtocolConfigError,
+    GetReceipt, GetReceiptError, GetStateChangesError, GetStateChangesWithCauseInBlock,
+    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, PinBlocks,
+    PinBlocksError, PinnedBlockInfo, PinnedBlocksInfo, Query, QueryError, TxStatus, TxStatusError,
+    UnpinBlocks,
 };
 use near_network::types::{NetworkRequests, PeerManagerAdapter, PeerManagerMessageRequest};
 #[cfg(feature = "test_features")]
 use near_network_primitives::types::NetworkAdversarialMessage;
 use near_network_primitives::types::{
-    NetworkViewClientMessages, NetworkViewClientResponses, ReasonForBan, StateResponseInfo,
-    StateResponseInfoV1, StateResponseInfoV2,
+    NetworkViewClientMessages, NetworkViewClientResponses, ProtocolDeprecation, ReasonForBan,
+    StateResponseInfo, StateResponseInfoV1, StateResponseInfoV2,
 };
 use near_performance_metrics_macros::{perf, perf_with_debug};
 use near_primitives::block::{Block, BlockHeader, GenesisId, Tip};
@@ -44,25 +46,30 @@ use near_primitives::syncing::{
     ShardStateSyncResponse, ShardStateSyncResponseHeader, ShardStateSyncResponseV1,
     ShardStateSyncResponseV2,
 };
+use near_primitives::trie_key::TrieKey;
 use near_primitives::types::{
     AccountId, BlockHeight, BlockId, BlockReference, EpochId, EpochReference, Finality,
-    MaybeBlockId, ShardId, TransactionOrReceiptId,
+    MaybeBlockId, NumBlocks, NumShards, ShardId, TransactionOrReceiptId,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
-    BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
-    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus, GasPriceView,
-    LightClientBlockView, QueryRequest, QueryResponse, ReceiptView, StateChangesKindsView,
-    StateChangesView,
+    BlockGasPriceView, BlockView, ChunkView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
+    FinalExecutionOutcomeView, FinalExecutionOutcomeViewEnum, FinalExecutionStatus,
+    GasPriceEstimateView, GasPriceView, LightClientBlockView, QueryRequest, QueryResponse,
+    ReceiptView, ShardCongestionView, StateChangesKindsView, StateChangesView,
 };
 
 use crate::{
-    sync, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
+    sync, GetChunk, GetExecutionOutcomeResponse, GetNextLightClientBlock, GetPinnedBlocks,
+    GetStateChanges, GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered,
 };
 
 /// Max number of queries that we keep.
 const QUERY_REQUEST_LIMIT: usize = 500;
+/// Max number of blocks to report the gas prices of in `GetGasPriceEstimate`.
+const MAX_GAS_PRICE_HISTORY: NumBlocks = 100;
+/// Max number of heights in the range of `PinBlocks`.
+const MAX_PIN_BLOCKS_HEIGHT_RANGE: NumBlocks = 1000;
 /// Waiting time between requests, in ms
 const REQUEST_WAIT_TIME: u64 = 1000;
 
@@ -316,6 +323,19 @@ impl ViewClientActor {
                     block_height,
                     block_hash,
                 },
+                near_chain::near_chain_primitives::error::QueryError::ContractExecutionTimeout {
+                    block_height,
+                    block_hash,
+                } => QueryError::ContractExecutionTimeout { block_height, block_hash },
+                near_chain::near_chain_primitives::error::QueryError::ContractBlacklisted {
+                    contract_account_id,
+                    block_height,
+                    block_hash,
+                } => QueryError::ContractBlacklisted {
+                    contract_account_id,
+                    block_height,
+                    block_hash,
+                },
                 near_chain::near_chain_primitives::error::QueryError::TooLargeContractState {
                     requested_account_id,
                     block_height,
@@ -335,6 +355,12 @@ impl ViewClientActor {
         epoch_id: &EpochId,
         last_block_hash: &CryptoHash,
     ) -> Result<(), TxStatusError> {
+        // The outcome is stored locally, it just may be not final yet.
+        let location =
+            self.chain.get_outcome_location(&receipt_id).map_err(TxStatusError::ChainError)?;
+        if location.is_some() {
+            return Ok(());
+        }
         if let Ok(dst_shard_id) = self.chain.get_shard_id_for_receipt_id(&receipt_id) {
             let shard_uid = self
                 .runtime_adapter
@@ -372,17 +398,30 @@ impl ViewClientActor {
         }
 
         let head = self.chain.head().map_err(|e| TxStatusError::ChainError(e))?;
-        let target_shard_id = self
-            .runtime_adapter
-            .account_id_to_shard_id(&signer_account_id, &head.epoch_id)
-            .map_err(|err| TxStatusError::InternalError(err.to_string()))?;
-        // Check if we are tracking this shard.
-        if self.runtime_adapter.cares_about_shard(
-            self.validator_account_id.as_ref(),
-            &head.prev_block_hash,
-            target_shard_id,
-            true,
-        ) {
+        // Transactions executed in the chunks we have applied are indexed with their shard,
+        // the others may still be pending in the shard of the signer, if we track it.
+        let location =
+            self.chain.get_outcome_location(&tx_hash).map_err(TxStatusError::ChainError)?;
+        let is_stored = match location {
+            Some(location) => {
+                debug!(target: "client", %tx_hash, ?location, "Transaction outcome is indexed");
+                true
+            }
+            None => {
+                let target_shard_id = self
+                    .runtime_adapter
+                    .account_id_to_shard_id(&signer_account_id, &head.epoch_id)
+                    .map_err(|err| TxStatusError::InternalError(err.to_string()))?;
+                // Check if we are tracking this shard.
+                self.runtime_adapter.cares_about_shard(
+                    self.validator_account_id.as_ref(),
+                    &head.prev_block_hash,
+                    target_shard_id,
+                    true,
+                )
+            }
+        };
+        if is_stored {
             match self.chain.get_final_transaction_result(&tx_hash) {
                 Ok(tx_result) => {
                     match &tx_result.status {
@@ -490,6 +529,65 @@ impl ViewClientActor {
         }
     }
 
+    /// Shards advertised to the peers as tracked by this node.
+    fn advertised_tracked_shards(&self, num_shards: Option<NumShards>) -> Vec<ShardId> {
+        let tracks_all_shards = match &self.config.tracked_shards_policy {
+            Some(TrackedShardsPolicy::AssignedPlusNext) => false,
+            Some(TrackedShardsPolicy::All) => true,
+            Some(TrackedShardsPolicy::Shards(shards)) => {
+                return (shards.iter().copied())
+                    .filter(|shard_id| num_shards.map_or(true, |n| *shard_id < n))
+                    .collect();
+            }
+            // runtime will track all shards if config tracked shards is not empty
+            // https://github.com/near/nearcore/issues/4930
+            None => !self.config.tracked_shards.is_empty(),
+        };
+        match num_shards {
+            Some(num_shards) if tracks_all_shards => (0..num_shards).collect(),
+            None if tracks_all_shards => self.config.tracked_shards.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// Switch to a newer protocol version at the start of the epoch after the one of `head`,
+    /// which peers still running an older version are warned about.
+    fn get_protocol_upgrade(&self, head: &Tip) -> Option<ProtocolDeprecation> {
+        let protocol_version =
+            self.runtime_adapter.get_epoch_protocol_version(&head.epoch_id).ok()?;
+        let next_protocol_version =
+            self.runtime_adapter.get_epoch_protocol_version(&head.next_epoch_id).ok()?;
+        if next_protocol_version <= protocol_version {
+            return None;
+        }
+        let epoch_height =
+            self.runtime_adapter.get_epoch_height_from_prev_block(&head.prev_block_hash).ok()?;
+        Some(ProtocolDeprecation {
+            protocol_version: next_protocol_version,
+            epoch_height: epoch_height + 1,
+        })
+    }
+
+    fn get_pinned_blocks_info(&self) -> Result<PinnedBlocksInfo, PinBlocksError> {
+        let mut blocks: Vec<_> = self
+            .chain
+            .store()
+            .get_pinned_blocks()?
+            .into_iter()
+            .map(|(pinned_block, size)| PinnedBlockInfo {
+                block_hash: *pinned_block.block.hash(),
+                height: pinned_block.block.header().height(),
+                size,
+            })
+            .collect();
+        blocks.sort_by_key(|block| (block.height, block.block_hash));
+        Ok(PinnedBlocksInfo {
+            total_size: blocks.iter().map(|block| block.size).sum(),
+            blocks,
+            size_limit: self.config.gc.pinned_blocks_size_limit,
+        })
+    }
+
     fn check_state_sync_request(&self) -> bool {
         let mut cache = self.state_request_cache.lock().expect(POISONED_LOCK_ERR);
         let now = Clock::instant();
@@ -529,17 +627,18 @@ impl Handler<GetBlock> for ViewClientActor {
         let block = match msg.0 {
             BlockReference::Finality(finality) => {
                 let block_hash = self.get_block_hash_by_finality(&finality)?;
-                self.chain.get_block(&block_hash)
+                self.chain.get_block_or_pinned(&block_hash)
             }
             BlockReference::BlockId(BlockId::Height(height)) => {
-                self.chain.get_block_by_height(height)
+                let block_hash = self.chain.get_block_hash_by_height(height)?;
+                self.chain.get_block_or_pinned(&block_hash)
             }
-            BlockReference::BlockId(BlockId::Hash(hash)) => self.chain.get_block(&hash),
+            BlockReference::BlockId(BlockId::Hash(hash)) => self.chain.get_block_or_pinned(&hash),
             BlockReference::SyncCheckpoint(sync_checkpoint) => {
                 if let Some(block_hash) =
                     self.get_block_hash_by_sync_checkpoint(&sync_checkpoint)?
                 {
-                    self.chain.get_block(&block_hash)
+                    self.chain.get_block_or_pinned(&block_hash)
                 } else {
                     return Err(GetBlockError::NotSyncedYet);
                 }
@@ -608,7 +707,7 @@ impl Handler<GetChunk> for ViewClientActor {
                 .ok_or_else(|| near_chain::Error::InvalidShardId(shard_id))?
                 .clone();
             let chunk_hash = chunk_header.chunk_hash();
-            let chunk = chain.get_chunk(&chunk_hash)?;
+            let chunk = chain.get_chunk_or_pinned(&chunk_hash)?;
             let res = ShardChunk::with_header(ShardChunk::clone(&chunk), chunk_header).ok_or(
                 near_chain::Error::Other(format!(
                     "Mismatched versions for chunk with hash {}",
@@ -620,15 +719,16 @@ impl Handler<GetChunk> for ViewClientActor {
 
         let chunk = match msg {
             GetChunk::ChunkHash(chunk_hash) => {
-                let chunk = self.chain.get_chunk(&chunk_hash)?;
+                let chunk = self.chain.get_chunk_or_pinned(&chunk_hash)?;
                 ShardChunk::clone(&chunk)
             }
             GetChunk::BlockHash(block_hash, shard_id) => {
-                let block = self.chain.get_block(&block_hash)?;
+                let block = self.chain.get_block_or_pinned(&block_hash)?;
                 get_chunk_from_block(block, shard_id, &self.chain)?
             }
             GetChunk::Height(height, shard_id) => {
-                let block = self.chain.get_block_by_height(height)?;
+                let block_hash = self.chain.get_block_hash_by_height(height)?;
+                let block = self.chain.get_block_or_pinned(&block_hash)?;
                 get_chunk_from_block(block, shard_id, &self.chain)?
             }
         };
@@ -1008,6 +1108,79 @@ impl Handler<GetProtocolConfig> for ViewClientActor {
     }
 }
 
+impl Handler<PinBlocks> for ViewClientActor {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+
+    #[perf]
+    fn handle(&mut self, msg: PinBlocks, _: &mut Self::Context) -> Self::Result {
+        let pinned = self.chain.store().get_pinned_blocks()?;
+        let mut block_hashes: HashSet<CryptoHash> = msg.0.block_hashes.into_iter().collect();
+        if let Some(heights) = msg.0.heights {
+            let length = heights.end().saturating_sub(*heights.start()).saturating_add(1);
+            if heights.is_empty() || length > MAX_PIN_BLOCKS_HEIGHT_RANGE {
+                return Err(PinBlocksError::InvalidHeightRange {
+                    start_height: *heights.start(),
+                    end_height: *heights.end(),
+                    max_length: MAX_PIN_BLOCKS_HEIGHT_RANGE,
+                });
+            }
+            // Only the heights from the tail to the head can have known blocks.
+            let start = std::cmp::max(*heights.start(), self.chain.tail()?);
+            let end = std::cmp::min(*heights.end(), self.chain.head()?.height);
+            for height in start..=end {
+                // Heights without known blocks are skipped.
+                if let Ok(hashes) = self.chain.store().get_all_block_hashes_by_height(height) {
+                    block_hashes.extend(hashes.values().flatten());
+                }
+            }
+        }
+        for (pinned_block, _) in &pinned {
+            block_hashes.remove(pinned_block.block.hash());
+        }
+
+        let mut size: u64 = pinned.iter().map(|(_, size)| size).sum();
+        let mut new_blocks = vec![];
+        for block_hash in block_hashes {
+            let pinned_block = self.chain.store().make_pinned_block(&block_hash)?;
+            size += pinned_block.try_to_vec().map_err(near_chain::Error::from)?.len() as u64;
+            new_blocks.push(pinned_block);
+        }
+        let size_limit = self.config.gc.pinned_blocks_size_limit;
+        if size > size_limit {
+            return Err(PinBlocksError::SizeLimitExceeded { size, size_limit });
+        }
+        self.chain.store().save_pinned_blocks(&new_blocks)?;
+        self.get_pinned_blocks_info()
+    }
+}
+
+impl Handler<UnpinBlocks> for ViewClientActor {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+
+    #[perf]
+    fn handle(&mut self, msg: UnpinBlocks, _: &mut Self::Context) -> Self::Result {
+        let mut block_hashes = msg.0.block_hashes;
+        if let Some(heights) = msg.0.heights {
+            for (pinned_block, _) in self.chain.store().get_pinned_blocks()? {
+                if heights.contains(&pinned_block.block.header().height()) {
+                    block_hashes.push(*pinned_block.block.hash());
+                }
+            }
+        }
+        self.chain.store().remove_pinned_blocks(&block_hashes)?;
+        self.get_pinned_blocks_info()
+    }
+}
+
+impl Handler<GetPinnedBlocks> for ViewClientActor {
+    type Result = Result<PinnedBlocksInfo, PinBlocksError>;
+
+    #[perf]
+    fn handle(&mut self, _msg: GetPinnedBlocks, _: &mut Self::Context) -> Self::Result {
+        self.get_pinned_blocks_info()
+    }
+}
+
 impl Handler<NetworkViewClientMessages> for ViewClientActor {
     type Result = NetworkViewClientResponses;
 
@@ -1130,41 +1303,44 @@ impl Handler<NetworkViewClientMessages> for ViewClientActor {
                 }
             }
             NetworkViewClientMessages::GetChainInfo => match self.chain.head() {
-                Ok(head) => {
-                    match self.runtime_adapter.num_shards(&head.epoch_id) {
-                        Ok(num_shards) => {
-                            // convert config tracked shards
-                            // runtime will track all shards if config tracked shards is not empty
-                            // https://github.com/near/nearcore/issues/4930
-                            let tracked_shards = if self.config.tracked_shards.is_empty() {
-                                vec![]
-                            } else {
-                                (0..num_shards).collect()
-                            };
-                            NetworkViewClientResponses::ChainInfo {
-                                genesis_id: GenesisId {
-                                    chain_id: self.config.chain_id.clone(),
-                                    hash: *self.chain.genesis().hash(),
-                                },
-                                height: self.get_height(&head),
-                                tracked_shards,
-                                archival: self.config.archive,
-                            }
+                Ok(head) => match self.runtime_adapter.num_shards(&head.epoch_id) {
+                    Ok(num_shards) => {
+                        let tracked_shards = self.advertised_tracked_shards(Some(num_shards));
+                        NetworkViewClientResponses::ChainInfo {
+                            genesis_id: GenesisId {
+                                chain_id: self.config.chain_id.clone(),
+                                hash: *self.chain.genesis().hash(),
+                            },
+                            height: self.get_height(&head),
+                            header_height: self
+                                .chain
+                                .header_head()
+                                .map_or(head.height, |header_head| header_head.height),
+                            tracked_shards,
+                            archival: self.config.archive,
+                            num_shards: Some(num_shards),
+                            protocol_upgrade: self.get_protocol_upgrade(&head),
                         }
-                        Err(err) => {
-                            error!(target: "view_client", "Cannot retrieve num shards: {}", err);
-                            NetworkViewClientResponses::ChainInfo {
-                                genesis_id: GenesisId {
-                                    chain_id: self.config.chain_id.clone(),
-                                    hash: *self.chain.genesis().hash(),
-                                },
-                                height: self.get_height(&head),
-                                tracked_shards: self.config.tracked_shards.clone(),
-                                archival: self.config.archive,
-                            }
+                    }
+                    Err(err) => {
+                        error!(target: "view_client", "Cannot retrieve num shards: {}", err);
+                        NetworkViewClientResponses::ChainInfo {
+                            genesis_id: GenesisId {
+                                chain_id: self.config.chain_id.clone(),
+                                hash: *self.chain.genesis().hash(),
+                            },
+                            height: self.get_height(&head),
+                            header_height: self
+                                .chain
+                                .header_head()
+                                .map_or(head.height, |header_head| header_head.height),
+                            tracked_shards: self.advertised_tracked_shards(None),
+                            archival: self.config.archive,
+                            num_shards: None,
+                            protocol_upgrade: self.get_protocol_upgrade(&head),
                         }
                     }
-                }
+                },
                 Err(err) => {
                     error!(target: "view_client", "Cannot retrieve chain head: {}", err);
                     NetworkViewClientResponses::ChainInfo {
@@ -1173,11 +1349,21 @@ impl Handler<NetworkViewClientMessages> for ViewClientActor {
                             hash: *self.chain.genesis().hash(),
                         },
                         height: self.chain.genesis().height(),
-                        tracked_shards: self.config.tracked_shards.clone(),
+                        header_height: self.chain.genesis().height(),
+                        tracked_shards: self.advertised_tracked_shards(None),
                         archival: self.config.archive,
+                        num_shards: None,
+                        protocol_upgrade: None,
                     }
                 }
             },
+            NetworkViewClientMessages::GetTier1Accounts => match self.get_tier1_accounts() {
+                Ok(accounts) => NetworkViewClientResponses::Tier1Accounts(accounts),
+                Err(err) => {
+                    error!(target: "view_client", "Cannot retrieve TIER1 accounts: {}", err);
+                    NetworkViewClientResponses::NoResponse
+                }
+            },
             NetworkViewClientMessages::StateRequestHeader { shard_id, sync_hash } => {
                 if !self.check_state_sync_request() {
                     return NetworkViewClientResponses::NoResponse;
@@ -1357,6 +1543,109 @@ impl Handler<GetGasPrice> for ViewClientActor {
     }
 }
 
+impl ViewClientActor {
+    /// Block and chunk producers of the current and the next epoch, which the network
+    /// maintains direct connections with.
+    fn get_tier1_accounts(&self) -> Result<Vec<AccountId>, near_chain::Error> {
+        let head = self.chain.head()?;
+        let mut accounts = HashSet::new();
+        for epoch_id in [&head.epoch_id, &head.next_epoch_id] {
+            let block_producers = self
+                .runtime_adapter
+                .get_epoch_block_producers_ordered(epoch_id, &head.last_block_hash)?;
+            accounts.extend(block_producers.into_iter().map(|(bp, _)| bp.take_account_id()));
+            let chunk_producers = self.runtime_adapter.get_epoch_chunk_producers(epoch_id)?;
+            accounts.extend(chunk_producers.into_iter().map(|cp| cp.take_account_id()));
+        }
+        let mut accounts: Vec<_> = accounts.into_iter().collect();
+        accounts.sort();
+        Ok(accounts)
+    }
+
+    /// Length of the delayed receipts queue of the shard after `block`, None if the shard's
+    /// state is not available.
+    fn delayed_receipts(&self, block: &Block, shard_id: ShardId) -> Option<u64> {
+        let epoch_id = block.header().epoch_id();
+        let shard_uid = self.runtime_adapter.shard_id_to_uid(shard_id, epoch_id).ok()?;
+        let chunk_extra = self.chain.get_chunk_extra(block.hash(), &shard_uid).ok()?;
+        let trie = self
+            .runtime_adapter
+            .get_view_trie_for_shard(shard_id, block.header().prev_hash())
+            .ok()?;
+        let indices = match trie
+            .get(chunk_extra.state_root(), &TrieKey::DelayedReceiptIndices.to_vec())
+            .ok()?
+        {
+            Some(bytes) => DelayedReceiptIndices::try_from_slice(&bytes).ok()?,
+            None => DelayedReceiptIndices::default(),
+        };
+        Some(indices.next_available_index - indices.first_index)
+    }
+}
+
+impl Handler<GetGasPriceEstimate> for ViewClientActor {
+    type Result = Result<GasPriceEstimateView, GetGasPriceError>;
+
+    #[perf]
+    fn handle(&mut self, msg: GetGasPriceEstimate, _ctx: &mut Self::Context) -> Self::Result {
+        let head = self.chain.head()?;
+        let block = self.chain.get_block(&head.last_block_hash)?;
+        let header = block.header();
+
+        let mut recent_gas_prices = vec![];
+        let mut prev_hash = *header.prev_hash();
+        for _ in 0..msg.history.min(MAX_GAS_PRICE_HISTORY) {
+            let prev_header = match self.chain.get_block_header(&prev_hash) {
+                Ok(prev_header) => prev_header,
+                // Garbage collected or before genesis.
+                Err(_) => break,
+            };
+            recent_gas_prices.push(BlockGasPriceView {
+                block_height: prev_header.height(),
+                gas_price: prev_header.gas_price(),
+            });
+            prev_hash = *prev_header.prev_hash();
+        }
+
+        let shards = block
+            .chunks()
+            .iter()
+            .map(|chunk| ShardCongestionView {
+                shard_id: chunk.shard_id(),
+                gas_used: chunk.gas_used(),
+                gas_limit: chunk.gas_limit(),
+                delayed_receipts: self.delayed_receipts(&block, chunk.shard_id()),
+            })
+            .collect();
+
+        // The gas price rises the fastest when all the chunks are full.
+        let protocol_version =
+            self.runtime_adapter.get_epoch_protocol_version(header.epoch_id())?;
+        let economics = &self.chain.block_economics_config;
+        let mut suggested_gas_price = header.gas_price();
+        for _ in 0..msg.target_blocks {
+            suggested_gas_price = Block::compute_new_gas_price(
+                suggested_gas_price,
+                1,
+                1,
+                economics.gas_price_adjustment_rate(protocol_version),
+                economics.min_gas_price(protocol_version),
+                economics.max_gas_price(protocol_version),
+            );
+        }
+
+        Ok(GasPriceEstimateView {
+            block_height: header.height(),
+            block_hash: *header.hash(),
+            gas_price: header.gas_price(),
+            recent_gas_prices,
+            shards,
+            target_blocks: msg.target_blocks,
+            suggested_gas_price,
+        })
+    }
+}
+
 /// Starts the View Client in a new arbiter (thread).
 pub fn start_view_client(
     validator_account_id: Option<AccountId>,
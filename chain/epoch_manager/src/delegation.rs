@@ -0,0 +1,26 @@
+//! Hooks for the metadata about the stake delegated to staking pools, e.g. collected by an
+//! indexer or read through a contract standard.  The metadata is only surfaced through the
+//! validators RPC and never affects the seat assignment or the rewards.
+//!
+//! There are deliberately no hooks in `RewardCalculator::calculate_reward` or in
+//! `proposals_to_epoch_info`: both are part of consensus, so their inputs must be the same
+//! on every node, which metadata from an indexer isn't.  Rewards and seats keep being
+//! computed from the total stake of the pools only.  `RewardCalculator::delegators_reward`
+//! only splits the computed reward for reporting.
+use near_primitives::types::{AccountId, Balance, EpochId};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelegationInfo {
+    /// Stake of the owner of the pool.
+    pub own_stake: Balance,
+    /// Stake of all the delegators of the pool.
+    pub delegated_stake: Balance,
+    pub num_delegators: u64,
+}
+
+pub trait DelegationInfoProvider: Send + Sync {
+    /// Delegation to `account_id` in the epoch.  None if the validator isn't a staking pool
+    /// or its delegation isn't known.
+    fn delegation_info(&self, epoch_id: &EpochId, account_id: &AccountId)
+        -> Option<DelegationInfo>;
+}
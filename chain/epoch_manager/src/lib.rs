@@ -9,7 +9,7 @@ use tracing::{debug, warn};
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_info::{EpochInfo, EpochSummary};
 use near_primitives::epoch_manager::{
-    AllEpochConfig, EpochConfig, ShardConfig, SlashState, AGGREGATOR_KEY,
+    AllEpochConfig, EpochConfig, ProtocolUpgradeVotes, ShardConfig, SlashState, AGGREGATOR_KEY,
 };
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
@@ -20,10 +20,12 @@ use near_primitives::types::{
 };
 use near_primitives::version::{ProtocolVersion, UPGRADABILITY_FIX_PROTOCOL_VERSION};
 use near_primitives::views::{
-    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorKickoutView,
+    CurrentEpochValidatorInfo, EpochValidatorInfo, NextEpochValidatorInfo, ValidatorDelegationView,
+    ValidatorKickoutView,
 };
 use near_store::{DBCol, Store, StoreUpdate};
 
+pub use crate::delegation::{DelegationInfo, DelegationInfoProvider};
 use crate::proposals::proposals_to_epoch_info;
 pub use crate::reward_calculator::RewardCalculator;
 use crate::types::EpochInfoAggregator;
@@ -34,6 +36,7 @@ use near_chain::types::ValidatorInfoIdentifier;
 use near_chain_configs::GenesisConfig;
 use near_primitives::shard_layout::ShardLayout;
 
+mod delegation;
 mod proposals;
 mod reward_calculator;
 #[cfg(feature = "protocol_feature_chunk_only_producers")]
@@ -79,6 +82,8 @@ pub struct EpochManager {
     epoch_info_aggregator: EpochInfoAggregator,
     /// Largest final height. Monotonically increasing.
     largest_final_height: BlockHeight,
+    /// Source of the delegation metadata reported in the validator info, if any.
+    delegation_info_provider: Option<Arc<dyn DelegationInfoProvider>>,
 
     /// Counts loop iterations inside of aggregate_epoch_info_upto method.
     /// Used for tests as a bit of white-box testing.
@@ -130,6 +135,7 @@ impl EpochManager {
             #[cfg(test)]
             epoch_info_aggregator_loop_counter: Default::default(),
             largest_final_height: 0,
+            delegation_info_provider: None,
         };
         let genesis_epoch_id = EpochId::default();
         if !epoch_manager.has_epoch_info(&genesis_epoch_id)? {
@@ -344,6 +350,48 @@ impl EpochManager {
         (validator_kickout, validator_block_chunk_stats)
     }
 
+    /// Protocol version of the epoch after next if no version gets enough votes.
+    fn default_next_version(
+        epoch_info: &EpochInfo,
+        next_epoch_info: &EpochInfo,
+    ) -> ProtocolVersion {
+        if epoch_info.protocol_version() >= UPGRADABILITY_FIX_PROTOCOL_VERSION {
+            next_epoch_info.protocol_version()
+        } else {
+            epoch_info.protocol_version()
+        }
+    }
+
+    /// Tallies the protocol version votes collected in `version_tracker`.
+    /// Implements https://github.com/nearprotocol/NEPs/pull/64/files#diff-45f773511fe4321b446c3c4226324873R76
+    fn protocol_upgrade_votes(
+        &self,
+        epoch_info: &EpochInfo,
+        next_epoch_info: &EpochInfo,
+        version_tracker: HashMap<ValidatorId, ProtocolVersion>,
+    ) -> ProtocolUpgradeVotes {
+        let mut votes = HashMap::new();
+        for (validator_id, version) in version_tracker {
+            let stake = epoch_info.validator_stake(validator_id);
+            *votes.entry(version).or_insert(0) += stake;
+        }
+        let total_block_producer_stake: u128 = epoch_info
+            .block_producers_settlement()
+            .iter()
+            .copied()
+            .collect::<HashSet<_>>()
+            .iter()
+            .map(|&id| epoch_info.validator_stake(id))
+            .sum();
+
+        let protocol_version = Self::default_next_version(epoch_info, next_epoch_info);
+        let config = self.config.for_protocol_version(protocol_version);
+        let upgrade_stake_threshold = (total_block_producer_stake
+            * *config.protocol_upgrade_stake_threshold.numer() as u128)
+            / *config.protocol_upgrade_stake_threshold.denom() as u128;
+        ProtocolUpgradeVotes { votes, total_block_producer_stake, upgrade_stake_threshold }
+    }
+
     fn collect_blocks_info(
         &mut self,
         last_block_info: &BlockInfo,
@@ -364,47 +412,10 @@ impl EpochManager {
         let mut proposals = vec![];
         let mut validator_kickout = HashMap::new();
 
-        // Next protocol version calculation.
-        // Implements https://github.com/nearprotocol/NEPs/pull/64/files#diff-45f773511fe4321b446c3c4226324873R76
-        let mut versions = HashMap::new();
-        for (validator_id, version) in version_tracker {
-            let stake = epoch_info.validator_stake(validator_id);
-            *versions.entry(version).or_insert(0) += stake;
-        }
-        let total_block_producer_stake: u128 = epoch_info
-            .block_producers_settlement()
-            .iter()
-            .copied()
-            .collect::<HashSet<_>>()
-            .iter()
-            .map(|&id| epoch_info.validator_stake(id))
-            .sum();
-
-        let protocol_version =
-            if epoch_info.protocol_version() >= UPGRADABILITY_FIX_PROTOCOL_VERSION {
-                next_epoch_info.protocol_version()
-            } else {
-                epoch_info.protocol_version()
-            };
-
-        let config = self.config.for_protocol_version(protocol_version);
-        // Note: non-deterministic iteration is fine here, there can be only one
-        // version with large enough stake.
-        let next_version = if let Some((version, stake)) =
-            versions.into_iter().max_by_key(|&(_version, stake)| stake)
-        {
-            if stake
-                > (total_block_producer_stake
-                    * *config.protocol_upgrade_stake_threshold.numer() as u128)
-                    / *config.protocol_upgrade_stake_threshold.denom() as u128
-            {
-                version
-            } else {
-                protocol_version
-            }
-        } else {
-            protocol_version
-        };
+        let next_version = self
+            .protocol_upgrade_votes(&epoch_info, &next_epoch_info, version_tracker)
+            .winner()
+            .unwrap_or_else(|| Self::default_next_version(&epoch_info, &next_epoch_info));
 
         // Gather slashed validators and add them to kick out first.
         let slashed_validators = last_block_info.slashed();
@@ -1017,6 +1028,43 @@ impl EpochManager {
         Ok(res)
     }
 
+    /// Returns the protocol version votes cast in the epoch of the given block, up to and
+    /// including that block.  They determine the protocol version of the epoch after next.
+    pub fn get_protocol_upgrade_votes(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolUpgradeVotes, EpochError> {
+        let epoch_info = self.get_epoch_info(&self.get_epoch_id(last_block_hash)?)?;
+        let next_epoch_info = self.get_epoch_info(&self.get_next_epoch_id(last_block_hash)?)?;
+        let EpochInfoAggregator { version_tracker, .. } =
+            self.get_epoch_info_aggregator_upto_last(last_block_hash)?;
+        Ok(self.protocol_upgrade_votes(&epoch_info, &next_epoch_info, version_tracker))
+    }
+
+    /// Sets the source of the delegation metadata reported by `get_validator_info`.
+    pub fn set_delegation_info_provider(&mut self, provider: Arc<dyn DelegationInfoProvider>) {
+        self.delegation_info_provider = Some(provider);
+    }
+
+    /// Delegation to the validator in the epoch, with the part of `reward` attributed to the
+    /// delegators if the reward is known.
+    fn get_delegation_view(
+        &self,
+        epoch_id: &EpochId,
+        account_id: &AccountId,
+        reward: Option<Balance>,
+    ) -> Option<ValidatorDelegationView> {
+        let info = self.delegation_info_provider.as_ref()?.delegation_info(epoch_id, account_id)?;
+        Some(ValidatorDelegationView {
+            own_stake: info.own_stake,
+            delegated_stake: info.delegated_stake,
+            num_delegators: info.num_delegators,
+            delegators_reward: reward.map(|reward| {
+                RewardCalculator::delegators_reward(reward, info.own_stake, info.delegated_stake)
+            }),
+        })
+    }
+
     /// Get validators for current epoch and next epoch.
     pub fn get_validator_info(
         &self,
@@ -1063,6 +1111,8 @@ impl EpochManager {
                             .collect::<Vec<ShardId>>();
                         shards.sort();
                         let (account_id, public_key, stake) = info.destructure();
+                        let reward = cur_epoch_info.validator_reward().get(&account_id).copied();
+                        let delegation = self.get_delegation_view(&epoch_id, &account_id, reward);
                         Ok(CurrentEpochValidatorInfo {
                             is_slashed: false, // currently there is no slashing
                             account_id,
@@ -1073,6 +1123,7 @@ impl EpochManager {
                             num_expected_blocks: validator_stats.block_stats.expected,
                             num_produced_chunks: validator_stats.chunk_stats.produced,
                             num_expected_chunks: validator_stats.chunk_stats.expected,
+                            delegation,
                         })
                     })
                     .collect::<Result<Vec<CurrentEpochValidatorInfo>, EpochError>>()?;
@@ -1109,6 +1160,8 @@ impl EpochManager {
                             .collect::<Vec<ShardId>>();
                         shards.sort();
                         let (account_id, public_key, stake) = info.destructure();
+                        let reward = cur_epoch_info.validator_reward().get(&account_id).copied();
+                        let delegation = self.get_delegation_view(&epoch_id, &account_id, reward);
                         Ok(CurrentEpochValidatorInfo {
                             is_slashed: false, // currently there is no slashing
                             account_id,
@@ -1119,6 +1172,7 @@ impl EpochManager {
                             num_expected_blocks: block_stats.expected,
                             num_produced_chunks: chunk_stats.produced,
                             num_expected_chunks: chunk_stats.expected,
+                            delegation,
                         })
                     })
                     .collect::<Result<Vec<CurrentEpochValidatorInfo>, EpochError>>()?;
@@ -1150,7 +1204,8 @@ impl EpochManager {
                     .collect::<Vec<ShardId>>();
                 shards.sort();
                 let (account_id, public_key, stake) = info.destructure();
-                NextEpochValidatorInfo { account_id, public_key, stake, shards }
+                let delegation = self.get_delegation_view(&next_epoch_id, &account_id, None);
+                NextEpochValidatorInfo { account_id, public_key, stake, shards, delegation }
             })
             .collect();
         let prev_epoch_kickout = next_epoch_info
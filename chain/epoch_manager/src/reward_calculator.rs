@@ -36,6 +36,20 @@ impl RewardCalculator {
             num_seconds_per_year: NUM_SECONDS_IN_A_YEAR,
         }
     }
+    /// Part of the `reward` of a staking pool attributed to the delegators, in proportion to
+    /// their stake.  Only used for reporting, see `DelegationInfoProvider`.
+    pub fn delegators_reward(
+        reward: Balance,
+        own_stake: Balance,
+        delegated_stake: Balance,
+    ) -> Balance {
+        let total_stake = own_stake + delegated_stake;
+        if total_stake == 0 {
+            return 0;
+        }
+        (U256::from(reward) * U256::from(delegated_stake) / U256::from(total_stake)).as_u128()
+    }
+
     /// Calculate validator reward for an epoch based on their block and chunk production stats.
     /// Returns map of validators with their rewards and amount of newly minted tokens including to protocol's treasury.
     /// See spec <https://nomicon.io/Economics/README.html#rewards-calculation>.
@@ -2120,6 +2120,33 @@ fn test_protocol_version_switch() {
     );
 }
 
+#[test]
+fn test_protocol_upgrade_votes() {
+    let store = create_test_store();
+    let config = epoch_config(10, 1, 1, 0, 90, 60, 0, None);
+    let amount_staked = 1_000_000;
+    let validators = vec![stake("test1".parse().unwrap(), amount_staked)];
+    let mut epoch_manager =
+        EpochManager::new(store, config, PROTOCOL_VERSION, default_reward_calculator(), validators)
+            .unwrap();
+    let h = hash_range(3);
+    record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+    record_block(&mut epoch_manager, h[0], h[1], 1, vec![]);
+    let votes = epoch_manager.get_protocol_upgrade_votes(&h[1]).unwrap();
+    assert_eq!(votes.votes, HashMap::from([(PROTOCOL_VERSION, amount_staked)]));
+    assert_eq!(votes.total_block_producer_stake, amount_staked);
+    assert_eq!(votes.upgrade_stake_threshold, amount_staked * 80 / 100);
+    assert_eq!(votes.winner(), Some(PROTOCOL_VERSION));
+
+    // Only the latest vote of the block producer counts.
+    let mut block_info2 = block_info(h[2], 2, 1, h[1], h[1], h[0], vec![], DEFAULT_TOTAL_SUPPLY);
+    set_block_info_protocol_version(&mut block_info2, PROTOCOL_VERSION + 1);
+    epoch_manager.record_block_info(block_info2, [0; 32]).unwrap();
+    let votes = epoch_manager.get_protocol_upgrade_votes(&h[2]).unwrap();
+    assert_eq!(votes.votes, HashMap::from([(PROTOCOL_VERSION + 1, amount_staked)]));
+    assert_eq!(votes.winner(), Some(PROTOCOL_VERSION + 1));
+}
+
 #[test]
 fn test_protocol_version_switch_with_shard_layout_change() {
     let store = create_test_store();
@@ -2443,3 +2470,58 @@ fn test_chunk_producers() {
     #[cfg(not(feature = "protocol_feature_chunk_only_producers"))]
     assert_eq!(vec!(String::from("test1"), String::from("test2")), chunk_producers);
 }
+
+struct TestDelegationInfoProvider;
+
+impl DelegationInfoProvider for TestDelegationInfoProvider {
+    fn delegation_info(
+        &self,
+        _epoch_id: &EpochId,
+        account_id: &AccountId,
+    ) -> Option<DelegationInfo> {
+        (account_id.as_ref() == "test1").then(|| DelegationInfo {
+            own_stake: 250_000,
+            delegated_stake: 750_000,
+            num_delegators: 3,
+        })
+    }
+}
+
+#[test]
+fn test_validator_info_delegation() {
+    let amount_staked = 1_000_000;
+    let validators =
+        vec![("test1".parse().unwrap(), amount_staked), ("test2".parse().unwrap(), amount_staked)];
+    let mut epoch_manager = setup_default_epoch_manager(validators, 2, 1, 10, 0, 90, 60);
+    epoch_manager.set_delegation_info_provider(Arc::new(TestDelegationInfoProvider));
+    let h = hash_range(10);
+    record_block(&mut epoch_manager, CryptoHash::default(), h[0], 0, vec![]);
+    for i in 1..4 {
+        record_block(&mut epoch_manager, h[i - 1], h[i], i as u64, vec![]);
+    }
+    let epoch_id = epoch_manager.get_epoch_id(&h[3]).unwrap();
+    let reward = epoch_manager
+        .get_epoch_info(&epoch_id)
+        .unwrap()
+        .validator_reward()
+        .get(&"test1".parse::<AccountId>().unwrap())
+        .copied();
+    let info = epoch_manager.get_validator_info(ValidatorInfoIdentifier::BlockHash(h[3])).unwrap();
+
+    let delegation = ValidatorDelegationView {
+        own_stake: 250_000,
+        delegated_stake: 750_000,
+        num_delegators: 3,
+        delegators_reward: reward.map(|reward| reward * 3 / 4),
+    };
+    for validator in &info.current_validators {
+        let expected = (validator.account_id.as_ref() == "test1").then(|| delegation.clone());
+        assert_eq!(validator.delegation, expected);
+    }
+    // The reward for the next epoch isn't known yet.
+    let delegation = ValidatorDelegationView { delegators_reward: None, ..delegation };
+    for validator in &info.next_validators {
+        let expected = (validator.account_id.as_ref() == "test1").then(|| delegation.clone());
+        assert_eq!(validator.delegation, expected);
+    }
+}
@@ -374,6 +374,7 @@ mod tests {
     use super::*;
     use near_crypto::{KeyType, PublicKey};
     use near_primitives::epoch_manager::epoch_info::{EpochInfo, EpochInfoV3};
+    use near_primitives::epoch_manager::sampling;
     use near_primitives::epoch_manager::ValidatorSelectionConfig;
     use near_primitives::shard_layout::ShardLayout;
     use near_primitives::types::validator_stake::ValidatorStake;
@@ -570,6 +571,57 @@ mod tests {
         assert!(diff < 100);
     }
 
+    #[test]
+    fn test_reproduce_producer_sampling() {
+        let num_shards = 2;
+        let epoch_config = create_epoch_config(
+            num_shards,
+            3,
+            0,
+            ValidatorSelectionConfig {
+                #[cfg(feature = "protocol_feature_chunk_only_producers")]
+                num_chunk_only_producer_seats: 0,
+                #[cfg(feature = "protocol_feature_chunk_only_producers")]
+                minimum_validators_per_shard: 1,
+                minimum_stake_ratio: Ratio::new(160, 1_000_000),
+            },
+        );
+        let prev_epoch_info = create_prev_epoch_info(7, &["test1", "test2", "test3"], &[]);
+        let proposals = create_proposals(&[("test1", 1000), ("test2", 2000), ("test3", 3000)]);
+        let epoch_info = proposals_to_epoch_info(
+            &epoch_config,
+            [7; 32],
+            &prev_epoch_info,
+            proposals,
+            Default::default(),
+            Default::default(),
+            0,
+            PROTOCOL_VERSION,
+            PROTOCOL_VERSION,
+        )
+        .unwrap();
+        let rng_seed = epoch_info.rng_seed().unwrap();
+        assert_eq!(rng_seed, [7; 32]);
+
+        // The producers sampled from the seed and the stakes of the settlements alone are the
+        // ones the epoch info assigns.
+        let stakes = |settlement: &[ValidatorId]| -> Vec<Balance> {
+            settlement.iter().map(|&id| epoch_info.get_validator(id).stake()).collect()
+        };
+        let bp_settlement = epoch_info.block_producers_settlement();
+        for h in 0..1000 {
+            let seed = sampling::block_producer_seed(&rng_seed, h);
+            let bp = bp_settlement[sampling::sample_producer(&stakes(bp_settlement), seed)];
+            assert_eq!(bp, epoch_info.sample_block_producer(h));
+            for shard_id in 0..num_shards {
+                let cp_settlement = &epoch_info.chunk_producers_settlement()[shard_id as usize];
+                let seed = sampling::chunk_producer_seed(&rng_seed, h, shard_id, PROTOCOL_VERSION);
+                let cp = cp_settlement[sampling::sample_producer(&stakes(cp_settlement), seed)];
+                assert_eq!(cp, epoch_info.sample_chunk_producer(h, shard_id));
+            }
+        }
+    }
+
     #[test]
     #[cfg(feature = "protocol_feature_chunk_only_producers")]
     fn test_chunk_producer_sampling() {
@@ -3,7 +3,7 @@
 use anyhow::Context;
 use tokio::sync::mpsc;
 
-use near_chain_configs::GenesisValidationMode;
+use near_chain_configs::{GenesisValidationMode, TrackedShardsPolicy};
 pub use near_primitives;
 use near_primitives::types::Gas;
 pub use nearcore::{get_default_home, init_configs, NearConfig};
@@ -101,9 +101,14 @@ impl Indexer {
                 .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
 
         assert!(
-            !&near_config.client_config.tracked_shards.is_empty(),
+            match &near_config.client_config.tracked_shards_policy {
+                Some(TrackedShardsPolicy::All) => true,
+                Some(TrackedShardsPolicy::Shards(shards)) => !shards.is_empty(),
+                Some(TrackedShardsPolicy::AssignedPlusNext) => false,
+                None => !near_config.client_config.tracked_shards.is_empty(),
+            },
             "Indexer should track at least one shard. \n\
-            Tip: You may want to update {} with `\"tracked_shards\": [0]`
+            Tip: You may want to update {} with `\"tracked_shards_policy\": \"all\"`
             ",
             indexer_config.home_dir.join("config.json").display()
         );
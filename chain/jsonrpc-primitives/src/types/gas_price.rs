@@ -1,4 +1,4 @@
-use near_primitives::types::MaybeBlockId;
+use near_primitives::types::{MaybeBlockId, NumBlocks};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -13,6 +13,30 @@ pub struct RpcGasPriceResponse {
     pub gas_price_view: near_primitives::views::GasPriceView,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcGasPriceEstimateRequest {
+    /// Number of blocks before the last one to report the gas prices of.
+    #[serde(default = "default_history")]
+    pub history: NumBlocks,
+    /// Number of blocks the transaction should be included within.
+    #[serde(default = "default_target_blocks")]
+    pub target_blocks: NumBlocks,
+}
+
+fn default_history() -> NumBlocks {
+    10
+}
+
+fn default_target_blocks() -> NumBlocks {
+    3
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcGasPriceEstimateResponse {
+    #[serde(flatten)]
+    pub estimate: near_primitives::views::GasPriceEstimateView,
+}
+
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcGasPriceError {
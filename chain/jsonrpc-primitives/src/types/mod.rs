@@ -4,7 +4,10 @@ pub mod chunks;
 pub mod config;
 pub mod gas_price;
 pub mod light_client;
+pub mod network_advisory;
 pub mod network_info;
+pub mod peer_access;
+pub mod pinned_blocks;
 pub mod query;
 pub mod receipts;
 pub mod sandbox;
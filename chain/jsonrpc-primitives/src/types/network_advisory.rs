@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Advisory signed by one of the advisory keys of the chain.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcBroadcastNetworkAdvisoryRequest {
+    pub signer: near_crypto::PublicKey,
+    pub chain_id: String,
+    pub nonce: u64,
+    /// Time of signing, as a unix timestamp in seconds.
+    pub timestamp: u64,
+    pub message: String,
+    pub signature: near_crypto::Signature,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcBroadcastNetworkAdvisoryResponse {}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcBroadcastNetworkAdvisoryError {
+    #[error("Advisory rejected: {reason}")]
+    Rejected { reason: String },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcBroadcastNetworkAdvisoryError> for crate::errors::RpcError {
+    fn from(error: RpcBroadcastNetworkAdvisoryError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcBroadcastNetworkAdvisoryError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
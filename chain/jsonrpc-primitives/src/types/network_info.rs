@@ -1,5 +1,6 @@
 use near_primitives::network::PeerId;
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, BlockHeight, ShardId};
+use near_primitives::version::ProtocolVersion;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 
@@ -28,6 +29,36 @@ pub struct RpcNetworkInfoResponse {
     pub known_producers: Vec<RpcKnownProducer>,
 }
 
+/// Details of the connection with a connected peer.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcPeerConnectionInfo {
+    pub id: PeerId,
+    pub addr: Option<SocketAddr>,
+    pub account_id: Option<AccountId>,
+    /// Who started the connection: "Inbound" (the peer) or "Outbound" (us).
+    pub peer_type: String,
+    /// Encoding of the messages, null until the first message is received.
+    pub encoding: Option<String>,
+    pub protocol_version: ProtocolVersion,
+    pub height: BlockHeight,
+    pub tracked_shards: Vec<ShardId>,
+    pub archival: bool,
+    pub received_bytes_per_sec: u64,
+    pub sent_bytes_per_sec: u64,
+    /// Averaged over the last minute.
+    pub received_messages_per_sec: f64,
+    /// Averaged over the last minute.
+    pub sent_messages_per_sec: f64,
+    pub connection_age_secs: u64,
+    /// UNIX timestamp in nanoseconds.
+    pub last_time_received_message: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpcNetworkPeerInfoResponse {
+    pub peers: Vec<RpcPeerConnectionInfo>,
+}
+
 #[derive(thiserror::Error, Debug, Serialize, Deserialize)]
 #[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum RpcNetworkInfoError {
@@ -0,0 +1,76 @@
+use near_primitives::network::PeerId;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Rule set by the node operator for a blacklist entry.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RpcPeerAccessRule {
+    /// Adds the entry to the blacklist.
+    Block,
+    /// Adds the entry to the allowlist.
+    Allow,
+    /// Removes the entry from both lists.
+    Unlisted,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcSetPeerAccessRuleRequest {
+    /// IP, IP:port, network in CIDR notation or peer id.
+    pub entry: String,
+    pub rule: RpcPeerAccessRule,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcPeerAccessListResponse {
+    pub blacklist: Vec<String>,
+    pub allowlist: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcBannedPeer {
+    pub id: PeerId,
+    pub addr: Option<SocketAddr>,
+    /// Reason for the ban, e.g. "InvalidSignature".
+    pub reason: String,
+    /// UNIX timestamp in nanoseconds.
+    pub banned_at: u64,
+    /// UNIX timestamp in nanoseconds.
+    pub expires_at: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcBannedPeersResponse {
+    pub peers: Vec<RpcBannedPeer>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcUnbanPeerRequest {
+    pub peer_id: PeerId,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcPeerAccessListError {
+    #[error("Invalid entry: {error_message}")]
+    InvalidEntry { error_message: String },
+    #[error("Peer {peer_id} is not banned")]
+    PeerNotBanned { peer_id: PeerId },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcPeerAccessListError> for crate::errors::RpcError {
+    fn from(error: RpcPeerAccessListError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcPeerAccessListError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
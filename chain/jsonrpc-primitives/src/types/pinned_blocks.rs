@@ -0,0 +1,64 @@
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::BlockHeight;
+use serde::{Deserialize, Serialize};
+
+/// Blocks with the given hashes and all the known blocks at heights
+/// `start_height..=end_height`, including the forks.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcPinBlocksRequest {
+    #[serde(default)]
+    pub block_hashes: Vec<CryptoHash>,
+    #[serde(default)]
+    pub heights: Option<RpcHeightRange>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct RpcHeightRange {
+    pub start_height: BlockHeight,
+    pub end_height: BlockHeight,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcPinnedBlock {
+    pub block_hash: CryptoHash,
+    pub height: BlockHeight,
+    /// Size of the copy of the block and its chunks in bytes.
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct RpcPinnedBlocksResponse {
+    pub blocks: Vec<RpcPinnedBlock>,
+    pub total_size: u64,
+    pub size_limit: u64,
+}
+
+#[derive(thiserror::Error, Debug, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcPinBlocksError {
+    #[error("Block either has never been observed on the node or has been garbage collected: {error_message}")]
+    UnknownBlock { error_message: String },
+    #[error("Pinned blocks would take {size} bytes, over the limit of {size_limit} bytes")]
+    SizeLimitExceeded { size: u64, size_limit: u64 },
+    #[error(
+        "Height range {start_height}..={end_height} is empty or longer than {max_length} heights"
+    )]
+    InvalidHeightRange { start_height: BlockHeight, end_height: BlockHeight, max_length: u64 },
+    #[error("Internal error: {error_message}")]
+    InternalError { error_message: String },
+}
+
+impl From<RpcPinBlocksError> for crate::errors::RpcError {
+    fn from(error: RpcPinBlocksError) -> Self {
+        let error_data = match serde_json::to_value(error) {
+            Ok(value) => value,
+            Err(err) => {
+                return Self::new_internal_error(
+                    None,
+                    format!("Failed to serialize RpcPinBlocksError: {:?}", err),
+                )
+            }
+        };
+        Self::new_internal_or_handler_error(Some(error_data.clone()), error_data)
+    }
+}
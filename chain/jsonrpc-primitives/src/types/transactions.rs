@@ -27,6 +27,9 @@ pub enum RpcTransactionError {
     InvalidTransaction {
         #[serde(skip_serializing)]
         context: near_primitives::errors::InvalidTxError,
+        /// The runtime limit exceeded by the transaction, if that is why it is invalid.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit_exceeded: Option<RpcTransactionLimitExceeded>,
     },
     #[error("Node doesn't track this shard. Cannot determine whether the transaction is valid")]
     DoesNotTrackShard,
@@ -40,6 +43,25 @@ pub enum RpcTransactionError {
     TimeoutError,
 }
 
+/// Runtime limit exceeded by an invalid transaction, reported so that clients can
+/// tell these apart without parsing the `InvalidTxError`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "name", content = "info", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RpcTransactionLimitExceeded {
+    TransactionSizeExceeded {
+        size: u64,
+        limit: u64,
+    },
+    TotalNumberOfActionsExceeded {
+        total_number_of_actions: u64,
+        limit: u64,
+    },
+    TotalPrepaidGasExceeded {
+        total_prepaid_gas: near_primitives::types::Gas,
+        limit: near_primitives::types::Gas,
+    },
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RpcTransactionResponse {
     #[serde(flatten)]
@@ -51,10 +73,39 @@ pub struct RpcBroadcastTxSyncResponse {
     pub transaction_hash: near_primitives::hash::CryptoHash,
 }
 
+impl RpcTransactionError {
+    /// Reports an invalid transaction, along with the runtime limit it exceeds.
+    pub fn from_invalid_tx(context: near_primitives::errors::InvalidTxError) -> Self {
+        use near_primitives::errors::{ActionsValidationError, InvalidTxError};
+        let limit_exceeded = match context {
+            InvalidTxError::TransactionSizeExceeded { size, limit } => {
+                Some(RpcTransactionLimitExceeded::TransactionSizeExceeded { size, limit })
+            }
+            InvalidTxError::ActionsValidation(
+                ActionsValidationError::TotalNumberOfActionsExceeded {
+                    total_number_of_actions,
+                    limit,
+                },
+            ) => Some(RpcTransactionLimitExceeded::TotalNumberOfActionsExceeded {
+                total_number_of_actions,
+                limit,
+            }),
+            InvalidTxError::ActionsValidation(
+                ActionsValidationError::TotalPrepaidGasExceeded { total_prepaid_gas, limit },
+            ) => Some(RpcTransactionLimitExceeded::TotalPrepaidGasExceeded {
+                total_prepaid_gas,
+                limit,
+            }),
+            _ => None,
+        };
+        Self::InvalidTransaction { context, limit_exceeded }
+    }
+}
+
 impl From<RpcTransactionError> for crate::errors::RpcError {
     fn from(error: RpcTransactionError) -> Self {
         let error_data = match &error {
-            RpcTransactionError::InvalidTransaction { context } => {
+            RpcTransactionError::InvalidTransaction { context, .. } => {
                 if let Ok(value) =
                     serde_json::to_value(crate::errors::ServerError::TxExecutionError(
                         near_primitives::errors::TxExecutionError::InvalidTxError(context.clone()),
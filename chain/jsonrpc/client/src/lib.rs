@@ -258,6 +258,14 @@ impl JsonRpcClient {
     ) -> RpcRequest<near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse> {
         call_method(&self.client, &self.server_addr, "EXPERIMENTAL_protocol_config", request)
     }
+
+    #[allow(non_snake_case)]
+    pub fn EXPERIMENTAL_gas_price_estimate(
+        &self,
+        request: near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateRequest,
+    ) -> RpcRequest<near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateResponse> {
+        call_method(&self.client, &self.server_addr, "EXPERIMENTAL_gas_price_estimate", request)
+    }
 }
 
 fn create_client() -> Client {
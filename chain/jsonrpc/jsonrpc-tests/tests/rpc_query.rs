@@ -7,6 +7,7 @@ use serde_json::json;
 use near_actix_test_utils::run_actix;
 use near_crypto::{KeyType, PublicKey, Signature};
 use near_jsonrpc::client::{new_client, ChunkId};
+use near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateRequest;
 use near_jsonrpc_primitives::types::query::QueryResponseKind;
 use near_jsonrpc_primitives::types::validator::RpcValidatorsOrderedRequest;
 use near_logger_utils::init_test_logger;
@@ -221,7 +222,12 @@ fn test_query_access_keys() {
         let query_response = client
             .query(near_jsonrpc_primitives::types::query::RpcQueryRequest {
                 block_reference: BlockReference::latest(),
-                request: QueryRequest::ViewAccessKeyList { account_id: "test".parse().unwrap() },
+                request: QueryRequest::ViewAccessKeyList {
+                    account_id: "test".parse().unwrap(),
+                    permission: None,
+                    after_public_key: None,
+                    limit: None,
+                },
             })
             .await
             .unwrap();
@@ -508,6 +514,19 @@ fn test_gas_price() {
     });
 }
 
+/// Retrieve gas price estimate
+#[test]
+fn test_gas_price_estimate() {
+    test_with_client!(test_utils::NodeType::NonValidator, client, async move {
+        let request = RpcGasPriceEstimateRequest { history: 10, target_blocks: 3 };
+        let estimate = client.EXPERIMENTAL_gas_price_estimate(request).await.unwrap().estimate;
+        assert!(estimate.gas_price > 0);
+        assert!(estimate.suggested_gas_price >= estimate.gas_price);
+        assert_eq!(estimate.target_blocks, 3);
+        assert!(estimate.recent_gas_prices.len() as u64 <= estimate.block_height);
+    });
+}
+
 #[test]
 fn test_invalid_methods() {
     test_with_client!(test_utils::NodeType::NonValidator, client, async move {
@@ -7,11 +7,12 @@ use futures::{future, FutureExt, TryFutureExt};
 use near_actix_test_utils::run_actix;
 use near_crypto::{InMemorySigner, KeyType};
 use near_jsonrpc::client::new_client;
+use near_jsonrpc_primitives::errors::RpcErrorKind;
 use near_logger_utils::{init_integration_logger, init_test_logger};
 use near_network::test_utils::WaitOrTimeoutActor;
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::serialize::{to_base, to_base64};
-use near_primitives::transaction::SignedTransaction;
+use near_primitives::transaction::{Action, SignedTransaction, TransferAction};
 use near_primitives::types::BlockReference;
 use near_primitives::views::FinalExecutionStatus;
 
@@ -224,3 +225,34 @@ fn test_check_invalid_tx() {
         }
     });
 }
+
+#[test]
+fn test_check_tx_too_many_actions() {
+    test_with_client!(test_utils::NodeType::Validator, client, async move {
+        let block_hash = client.block(BlockReference::latest()).await.unwrap().header.hash;
+        let signer = InMemorySigner::from_seed("test1".parse().unwrap(), KeyType::ED25519, "test1");
+        let tx = SignedTransaction::from_actions(
+            1,
+            "test1".parse().unwrap(),
+            "test2".parse().unwrap(),
+            &signer,
+            vec![Action::Transfer(TransferAction { deposit: 1 }); 101],
+            block_hash,
+        );
+        let bytes = tx.try_to_vec().unwrap();
+        match client.EXPERIMENTAL_check_tx(to_base64(&bytes)).await {
+            Err(e) => {
+                let cause = match e.error_struct {
+                    Some(RpcErrorKind::HandlerError(cause)) => cause,
+                    other => panic!("unexpected error: {:?}", other),
+                };
+                assert_eq!(cause["name"], "INVALID_TRANSACTION");
+                let limit_exceeded = &cause["info"]["limit_exceeded"];
+                assert_eq!(limit_exceeded["name"], "TOTAL_NUMBER_OF_ACTIONS_EXCEEDED");
+                assert_eq!(limit_exceeded["info"]["total_number_of_actions"], 101);
+                assert_eq!(limit_exceeded["info"]["limit"], 100);
+            }
+            Ok(_) => panic!("transaction should not succeed"),
+        }
+    });
+}
@@ -2,7 +2,9 @@ use serde_json::Value;
 
 use near_client_primitives::types::GetGasPriceError;
 use near_jsonrpc_primitives::errors::RpcParseError;
-use near_jsonrpc_primitives::types::gas_price::{RpcGasPriceError, RpcGasPriceRequest};
+use near_jsonrpc_primitives::types::gas_price::{
+    RpcGasPriceError, RpcGasPriceEstimateRequest, RpcGasPriceRequest,
+};
 use near_primitives::types::MaybeBlockId;
 
 use super::{parse_params, RpcFrom, RpcRequest};
@@ -13,6 +15,12 @@ impl RpcRequest for RpcGasPriceRequest {
     }
 }
 
+impl RpcRequest for RpcGasPriceEstimateRequest {
+    fn parse(value: Option<Value>) -> Result<Self, RpcParseError> {
+        parse_params::<Self>(value)
+    }
+}
+
 impl RpcFrom<actix::MailboxError> for RpcGasPriceError {
     fn rpc_from(error: actix::MailboxError) -> Self {
         Self::InternalError { error_message: error.to_string() }
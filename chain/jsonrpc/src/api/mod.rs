@@ -11,7 +11,10 @@ mod chunks;
 mod config;
 mod gas_price;
 mod light_client;
+mod network_advisory;
 mod network_info;
+mod peer_access;
+mod pinned_blocks;
 mod query;
 mod receipts;
 mod sandbox;
@@ -0,0 +1,46 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::network_advisory::{
+    RpcBroadcastNetworkAdvisoryError, RpcBroadcastNetworkAdvisoryRequest,
+};
+use near_network::types::{NetworkAdvisory, NetworkAdvisoryRejection};
+
+use super::{parse_params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcBroadcastNetworkAdvisoryRequest {
+    fn parse(value: Option<Value>) -> Result<Self, RpcParseError> {
+        parse_params::<Self>(value)
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcBroadcastNetworkAdvisoryError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<String> for RpcBroadcastNetworkAdvisoryError {
+    fn rpc_from(error_message: String) -> Self {
+        Self::InternalError { error_message }
+    }
+}
+
+impl RpcFrom<NetworkAdvisoryRejection> for RpcBroadcastNetworkAdvisoryError {
+    fn rpc_from(rejection: NetworkAdvisoryRejection) -> Self {
+        Self::Rejected { reason: rejection.as_ref().to_string() }
+    }
+}
+
+impl RpcFrom<RpcBroadcastNetworkAdvisoryRequest> for NetworkAdvisory {
+    fn rpc_from(request: RpcBroadcastNetworkAdvisoryRequest) -> Self {
+        Self {
+            signer: request.signer,
+            chain_id: request.chain_id,
+            nonce: request.nonce,
+            timestamp: request.timestamp,
+            message: request.message,
+            signature: request.signature,
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use near_client_primitives::types::NetworkInfoResponse;
 use near_jsonrpc_primitives::types::network_info::{
-    RpcKnownProducer, RpcNetworkInfoError, RpcNetworkInfoResponse, RpcPeerInfo,
+    RpcKnownProducer, RpcNetworkInfoError, RpcNetworkInfoResponse, RpcNetworkPeerInfoResponse,
+    RpcPeerConnectionInfo, RpcPeerInfo,
 };
+use near_network::types::ConnectedPeerInfo;
 use near_network_primitives::types::{KnownProducer, PeerInfo};
 
 use super::{RpcFrom, RpcInto};
@@ -49,6 +51,36 @@ impl RpcFrom<NetworkInfoResponse> for RpcNetworkInfoResponse {
     }
 }
 
+impl RpcFrom<ConnectedPeerInfo> for RpcPeerConnectionInfo {
+    fn rpc_from(peer: ConnectedPeerInfo) -> Self {
+        let full_peer_info = &peer.full_peer_info;
+        Self {
+            id: full_peer_info.peer_info.id.clone(),
+            addr: full_peer_info.peer_info.addr,
+            account_id: full_peer_info.peer_info.account_id.clone(),
+            peer_type: <&str>::from(peer.peer_type).to_string(),
+            encoding: peer.encoding.map(|encoding| <&str>::from(encoding).to_string()),
+            protocol_version: peer.protocol_version,
+            height: full_peer_info.chain_info.height,
+            tracked_shards: full_peer_info.chain_info.tracked_shards.clone(),
+            archival: full_peer_info.chain_info.archival,
+            received_bytes_per_sec: peer.received_bytes_per_sec,
+            sent_bytes_per_sec: peer.sent_bytes_per_sec,
+            received_messages_per_sec: peer.received_messages_per_min as f64 / 60.,
+            sent_messages_per_sec: peer.sent_messages_per_min as f64 / 60.,
+            connection_age_secs: peer.connection_age.whole_seconds().max(0) as u64,
+            last_time_received_message: peer.last_time_received_message.unix_timestamp_nanos()
+                as u64,
+        }
+    }
+}
+
+impl RpcFrom<Vec<ConnectedPeerInfo>> for RpcNetworkPeerInfoResponse {
+    fn rpc_from(peers: Vec<ConnectedPeerInfo>) -> Self {
+        Self { peers: peers.into_iter().map(RpcInto::rpc_into).collect() }
+    }
+}
+
 impl RpcFrom<String> for RpcNetworkInfoError {
     fn rpc_from(error_message: String) -> Self {
         Self::InternalError { error_message }
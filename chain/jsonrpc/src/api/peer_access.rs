@@ -0,0 +1,77 @@
+use serde_json::Value;
+
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::peer_access::{
+    RpcBannedPeer, RpcBannedPeersResponse, RpcPeerAccessListError, RpcPeerAccessListResponse,
+    RpcPeerAccessRule, RpcSetPeerAccessRuleRequest, RpcUnbanPeerRequest,
+};
+use near_network::types::BannedPeerInfo;
+use near_network_primitives::types::{AccessList, AccessRule};
+
+use super::{parse_params, RpcFrom, RpcInto, RpcRequest};
+
+impl RpcRequest for RpcSetPeerAccessRuleRequest {
+    fn parse(value: Option<Value>) -> Result<Self, RpcParseError> {
+        parse_params::<Self>(value)
+    }
+}
+
+impl RpcRequest for RpcUnbanPeerRequest {
+    fn parse(value: Option<Value>) -> Result<Self, RpcParseError> {
+        parse_params::<Self>(value)
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcPeerAccessListError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<String> for RpcPeerAccessListError {
+    fn rpc_from(error_message: String) -> Self {
+        Self::InternalError { error_message }
+    }
+}
+
+impl RpcFrom<RpcPeerAccessRule> for AccessRule {
+    fn rpc_from(rule: RpcPeerAccessRule) -> Self {
+        match rule {
+            RpcPeerAccessRule::Block => AccessRule::Block,
+            RpcPeerAccessRule::Allow => AccessRule::Allow,
+            RpcPeerAccessRule::Unlisted => AccessRule::Unlisted,
+        }
+    }
+}
+
+impl RpcFrom<AccessList> for RpcPeerAccessListResponse {
+    fn rpc_from(access_list: AccessList) -> Self {
+        let to_sorted = |list: &near_network_primitives::types::Blacklist| {
+            let mut entries: Vec<String> = list.iter().map(ToString::to_string).collect();
+            entries.sort();
+            entries
+        };
+        Self {
+            blacklist: to_sorted(&access_list.blacklist),
+            allowlist: to_sorted(&access_list.allowlist),
+        }
+    }
+}
+
+impl RpcFrom<BannedPeerInfo> for RpcBannedPeer {
+    fn rpc_from(peer: BannedPeerInfo) -> Self {
+        Self {
+            id: peer.peer_info.id,
+            addr: peer.peer_info.addr,
+            reason: format!("{:?}", peer.reason),
+            banned_at: peer.banned_at.unix_timestamp_nanos() as u64,
+            expires_at: peer.expires_at.unix_timestamp_nanos() as u64,
+        }
+    }
+}
+
+impl RpcFrom<Vec<BannedPeerInfo>> for RpcBannedPeersResponse {
+    fn rpc_from(peers: Vec<BannedPeerInfo>) -> Self {
+        Self { peers: peers.into_iter().map(RpcInto::rpc_into).collect() }
+    }
+}
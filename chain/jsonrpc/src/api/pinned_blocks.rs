@@ -0,0 +1,65 @@
+use serde_json::Value;
+
+use near_client_primitives::types::{BlockSelection, PinBlocksError, PinnedBlocksInfo};
+use near_jsonrpc_primitives::errors::RpcParseError;
+use near_jsonrpc_primitives::types::pinned_blocks::{
+    RpcPinBlocksError, RpcPinBlocksRequest, RpcPinnedBlock, RpcPinnedBlocksResponse,
+};
+
+use super::{parse_params, RpcFrom, RpcRequest};
+
+impl RpcRequest for RpcPinBlocksRequest {
+    fn parse(value: Option<Value>) -> Result<Self, RpcParseError> {
+        parse_params::<Self>(value)
+    }
+}
+
+impl RpcFrom<actix::MailboxError> for RpcPinBlocksError {
+    fn rpc_from(error: actix::MailboxError) -> Self {
+        Self::InternalError { error_message: error.to_string() }
+    }
+}
+
+impl RpcFrom<PinBlocksError> for RpcPinBlocksError {
+    fn rpc_from(error: PinBlocksError) -> Self {
+        match error {
+            PinBlocksError::UnknownBlock { error_message } => Self::UnknownBlock { error_message },
+            PinBlocksError::SizeLimitExceeded { size, size_limit } => {
+                Self::SizeLimitExceeded { size, size_limit }
+            }
+            PinBlocksError::InvalidHeightRange { start_height, end_height, max_length } => {
+                Self::InvalidHeightRange { start_height, end_height, max_length }
+            }
+            PinBlocksError::InternalError { error_message } => {
+                Self::InternalError { error_message }
+            }
+        }
+    }
+}
+
+impl RpcFrom<RpcPinBlocksRequest> for BlockSelection {
+    fn rpc_from(request: RpcPinBlocksRequest) -> Self {
+        Self {
+            block_hashes: request.block_hashes,
+            heights: request.heights.map(|range| range.start_height..=range.end_height),
+        }
+    }
+}
+
+impl RpcFrom<PinnedBlocksInfo> for RpcPinnedBlocksResponse {
+    fn rpc_from(info: PinnedBlocksInfo) -> Self {
+        Self {
+            blocks: info
+                .blocks
+                .into_iter()
+                .map(|block| RpcPinnedBlock {
+                    block_hash: block.block_hash,
+                    height: block.height,
+                    size: block.size,
+                })
+                .collect(),
+            total_size: info.total_size,
+            size_limit: info.size_limit,
+        }
+    }
+}
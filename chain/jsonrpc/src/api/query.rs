@@ -43,7 +43,12 @@ impl RpcRequest for RpcQueryRequest {
             let request = match query_command {
                 "account" => QueryRequest::ViewAccount { account_id },
                 "access_key" => match maybe_extra_arg {
-                    None => QueryRequest::ViewAccessKeyList { account_id },
+                    None => QueryRequest::ViewAccessKeyList {
+                        account_id,
+                        permission: None,
+                        after_public_key: None,
+                        limit: None,
+                    },
                     Some(pk) => QueryRequest::ViewAccessKey {
                         account_id,
                         public_key: pk
@@ -105,6 +110,12 @@ impl RpcFrom<QueryError> for RpcQueryError {
             QueryError::ContractExecutionError { vm_error, block_height, block_hash } => {
                 Self::ContractExecutionError { vm_error, block_height, block_hash }
             }
+            QueryError::ContractExecutionTimeout { block_height, block_hash } => {
+                Self::ContractExecutionTimeout { block_height, block_hash }
+            }
+            QueryError::ContractBlacklisted { contract_account_id, block_height, block_hash } => {
+                Self::ContractBlacklisted { contract_account_id, block_height, block_hash }
+            }
             QueryError::Unreachable { ref error_message } => {
                 tracing::warn!(target: "jsonrpc", "Unreachable error occurred: {}", error_message);
                 crate::metrics::RPC_UNREACHABLE_ERROR_COUNT
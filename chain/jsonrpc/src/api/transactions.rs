@@ -47,7 +47,7 @@ impl RpcFrom<TxStatusError> for RpcTransactionError {
             TxStatusError::MissingTransaction(requested_transaction_hash) => {
                 Self::UnknownTransaction { requested_transaction_hash }
             }
-            TxStatusError::InvalidTx(context) => Self::InvalidTransaction { context },
+            TxStatusError::InvalidTx(context) => Self::from_invalid_tx(context),
             TxStatusError::InternalError(debug_info) => Self::InternalError { debug_info },
             TxStatusError::TimeoutError => Self::TimeoutError,
         }
@@ -17,16 +17,21 @@ use tracing::info;
 use near_chain_configs::GenesisConfig;
 use near_client::{
     ClientActor, DebugStatus, GetBlock, GetBlockProof, GetChunk, GetExecutionOutcome, GetGasPrice,
-    GetNetworkInfo, GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetStateChanges,
-    GetStateChangesInBlock, GetValidatorInfo, GetValidatorOrdered, Query, Status, TxStatus,
-    ViewClientActor,
+    GetGasPriceEstimate, GetNetworkInfo, GetNextLightClientBlock, GetPinnedBlocks,
+    GetProtocolConfig, GetReceipt, GetStateChanges, GetStateChangesInBlock, GetValidatorInfo,
+    GetValidatorOrdered, PinBlocks, Query, Status, TxStatus, UnpinBlocks, ViewClientActor,
 };
 pub use near_jsonrpc_client as client;
 use near_jsonrpc_primitives::errors::RpcError;
 use near_jsonrpc_primitives::message::{Message, Request};
 use near_jsonrpc_primitives::types::config::RpcProtocolConfigResponse;
 use near_metrics::{prometheus, Encoder, TextEncoder};
-use near_network::types::{NetworkClientMessages, NetworkClientResponses};
+use near_network::types::{
+    NetworkClientMessages, NetworkClientResponses, PeerManagerMessageRequest,
+    PeerManagerMessageResponse,
+};
+use near_network::PeerManagerActor;
+use near_network_primitives::types::BlacklistEntry;
 use near_primitives::hash::CryptoHash;
 use near_primitives::serialize::BaseEncode;
 use near_primitives::transaction::SignedTransaction;
@@ -83,6 +88,10 @@ pub struct RpcConfig {
     // We disable it by default, as some of those endpoints might be quite CPU heavy.
     #[serde(default = "default_enable_debug_rpc")]
     pub enable_debug_rpc: bool,
+    // If true, enable the admin RPC methods (like ones to manage the peer blacklist).
+    // We disable it by default, as they change the behavior of the node.
+    #[serde(default)]
+    pub enable_admin_rpc: bool,
 }
 
 impl Default for RpcConfig {
@@ -94,6 +103,7 @@ impl Default for RpcConfig {
             polling_config: Default::default(),
             limits_config: Default::default(),
             enable_debug_rpc: false,
+            enable_admin_rpc: false,
         }
     }
 }
@@ -134,7 +144,7 @@ where
 impl near_jsonrpc_primitives::types::transactions::RpcTransactionError {
     pub fn from_network_client_responses(responses: NetworkClientResponses) -> Self {
         match responses {
-            NetworkClientResponses::InvalidTx(context) => Self::InvalidTransaction { context },
+            NetworkClientResponses::InvalidTx(context) => Self::from_invalid_tx(context),
             NetworkClientResponses::NoResponse => Self::TimeoutError,
             NetworkClientResponses::DoesNotTrackShard | NetworkClientResponses::RequestRouted => {
                 Self::DoesNotTrackShard
@@ -217,6 +227,8 @@ struct JsonRpcHandler {
     polling_config: RpcPollingConfig,
     genesis_config: GenesisConfig,
     enable_debug_rpc: bool,
+    enable_admin_rpc: bool,
+    peer_manager_addr: Option<Addr<PeerManagerActor>>,
 }
 
 impl JsonRpcHandler {
@@ -292,6 +304,33 @@ impl JsonRpcHandler {
                 process_method_call(request, |params| self.next_light_client_block(params)).await
             }
             "network_info" => process_method_call(request, |_params: ()| self.network_info()).await,
+            "network_peer_info" => {
+                process_method_call(request, |_params: ()| self.network_peer_info()).await
+            }
+            "admin_peer_access_list" if self.enable_admin_rpc => {
+                process_method_call(request, |_params: ()| self.peer_access_list()).await
+            }
+            "admin_set_peer_access_rule" if self.enable_admin_rpc => {
+                process_method_call(request, |params| self.set_peer_access_rule(params)).await
+            }
+            "admin_banned_peers" if self.enable_admin_rpc => {
+                process_method_call(request, |_params: ()| self.banned_peers()).await
+            }
+            "admin_unban_peer" if self.enable_admin_rpc => {
+                process_method_call(request, |params| self.unban_peer(params)).await
+            }
+            "admin_broadcast_network_advisory" if self.enable_admin_rpc => {
+                process_method_call(request, |params| self.broadcast_network_advisory(params)).await
+            }
+            "admin_pin_blocks" if self.enable_admin_rpc => {
+                process_method_call(request, |params| self.pin_blocks(params)).await
+            }
+            "admin_pinned_blocks" if self.enable_admin_rpc => {
+                process_method_call(request, |_params: ()| self.pinned_blocks()).await
+            }
+            "admin_unpin_blocks" if self.enable_admin_rpc => {
+                process_method_call(request, |params| self.unpin_blocks(params)).await
+            }
             "query" => {
                 let params = RpcRequest::parse(request.params)?;
                 let query_response = self.query(params).await;
@@ -314,6 +353,9 @@ impl JsonRpcHandler {
             "EXPERIMENTAL_check_tx" => {
                 process_method_call(request, |params| self.check_tx(params)).await
             }
+            "EXPERIMENTAL_gas_price_estimate" => {
+                process_method_call(request, |params| self.gas_price_estimate(params)).await
+            }
             "EXPERIMENTAL_genesis_config" => {
                 process_method_call(request, |_params: ()| async {
                     Result::<_, std::convert::Infallible>::Ok(&self.genesis_config)
@@ -496,9 +538,7 @@ impl JsonRpcHandler {
                                 self.send_tx(tx.clone(), true).await
                             {
                                 break Err(
-                                    near_jsonrpc_primitives::types::transactions::RpcTransactionError::InvalidTransaction {
-                                        context
-                                    }
+                                    near_jsonrpc_primitives::types::transactions::RpcTransactionError::from_invalid_tx(context)
                                 );
                             }
                         }
@@ -745,6 +785,10 @@ impl JsonRpcHandler {
                 "/debug/api/validator_status" => {
                     self.client_send(DebugStatus::ValidatorStatus).await?
                 }
+                "/debug/api/protocol_upgrade" => {
+                    self.client_send(DebugStatus::ProtocolUpgrade).await?
+                }
+                "/debug/api/orphans" => self.client_send(DebugStatus::Orphans).await?,
                 _ => return Ok(None),
             };
             return Ok(Some(debug_status.rpc_into()));
@@ -931,6 +975,142 @@ impl JsonRpcHandler {
         Ok(network_info.rpc_into())
     }
 
+    async fn network_peer_info(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::network_info::RpcNetworkPeerInfoResponse,
+        near_jsonrpc_primitives::types::network_info::RpcNetworkInfoError,
+    > {
+        match self.peer_manager_send(PeerManagerMessageRequest::FetchConnectedPeers).await? {
+            PeerManagerMessageResponse::FetchConnectedPeers(peers) => Ok(peers.rpc_into()),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn peer_manager_send<E>(
+        &self,
+        msg: PeerManagerMessageRequest,
+    ) -> Result<PeerManagerMessageResponse, E>
+    where
+        E: RpcFrom<actix::MailboxError> + RpcFrom<String>,
+    {
+        match &self.peer_manager_addr {
+            Some(addr) => addr.send(msg).await.map_err(RpcFrom::rpc_from),
+            None => Err(E::rpc_from("peer manager is not available".to_string())),
+        }
+    }
+
+    async fn peer_access_list(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListResponse,
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError,
+    > {
+        match self.peer_manager_send(PeerManagerMessageRequest::GetAccessList).await? {
+            PeerManagerMessageResponse::AccessList(access_list) => Ok(access_list.rpc_into()),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn set_peer_access_rule(
+        &self,
+        request_data: near_jsonrpc_primitives::types::peer_access::RpcSetPeerAccessRuleRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListResponse,
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError,
+    > {
+        let entry = request_data.entry.parse::<BlacklistEntry>().map_err(|err| {
+            near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError::InvalidEntry {
+                error_message: err.to_string(),
+            }
+        })?;
+        let msg = PeerManagerMessageRequest::SetAccessRule(entry, request_data.rule.rpc_into());
+        match self.peer_manager_send(msg).await? {
+            PeerManagerMessageResponse::AccessList(access_list) => Ok(access_list.rpc_into()),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn banned_peers(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::peer_access::RpcBannedPeersResponse,
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError,
+    > {
+        match self.peer_manager_send(PeerManagerMessageRequest::FetchBannedPeers).await? {
+            PeerManagerMessageResponse::FetchBannedPeers(peers) => Ok(peers.rpc_into()),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn unban_peer(
+        &self,
+        request_data: near_jsonrpc_primitives::types::peer_access::RpcUnbanPeerRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::peer_access::RpcBannedPeersResponse,
+        near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError,
+    > {
+        let msg = PeerManagerMessageRequest::UnbanPeer(request_data.peer_id.clone());
+        match self.peer_manager_send(msg).await? {
+            PeerManagerMessageResponse::UnbanPeer(true) => self.banned_peers().await,
+            PeerManagerMessageResponse::UnbanPeer(false) => Err(
+                near_jsonrpc_primitives::types::peer_access::RpcPeerAccessListError::PeerNotBanned {
+                    peer_id: request_data.peer_id,
+                },
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    async fn broadcast_network_advisory(
+        &self,
+        request_data: near_jsonrpc_primitives::types::network_advisory::RpcBroadcastNetworkAdvisoryRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::network_advisory::RpcBroadcastNetworkAdvisoryResponse,
+        near_jsonrpc_primitives::types::network_advisory::RpcBroadcastNetworkAdvisoryError,
+    > {
+        let msg = PeerManagerMessageRequest::BroadcastNetworkAdvisory(request_data.rpc_into());
+        match self.peer_manager_send(msg).await? {
+            PeerManagerMessageResponse::BroadcastNetworkAdvisory(result) => {
+                result.map_err(RpcFrom::rpc_from)?;
+                Ok(near_jsonrpc_primitives::types::network_advisory::RpcBroadcastNetworkAdvisoryResponse {})
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    async fn pin_blocks(
+        &self,
+        request_data: near_jsonrpc_primitives::types::pinned_blocks::RpcPinBlocksRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinnedBlocksResponse,
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinBlocksError,
+    > {
+        let pinned_blocks = self.view_client_send(PinBlocks(request_data.rpc_into())).await?;
+        Ok(pinned_blocks.rpc_into())
+    }
+
+    async fn unpin_blocks(
+        &self,
+        request_data: near_jsonrpc_primitives::types::pinned_blocks::RpcPinBlocksRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinnedBlocksResponse,
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinBlocksError,
+    > {
+        let pinned_blocks = self.view_client_send(UnpinBlocks(request_data.rpc_into())).await?;
+        Ok(pinned_blocks.rpc_into())
+    }
+
+    async fn pinned_blocks(
+        &self,
+    ) -> Result<
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinnedBlocksResponse,
+        near_jsonrpc_primitives::types::pinned_blocks::RpcPinBlocksError,
+    > {
+        let pinned_blocks = self.view_client_send(GetPinnedBlocks).await?;
+        Ok(pinned_blocks.rpc_into())
+    }
+
     async fn gas_price(
         &self,
         request_data: near_jsonrpc_primitives::types::gas_price::RpcGasPriceRequest,
@@ -943,6 +1123,22 @@ impl JsonRpcHandler {
         Ok(near_jsonrpc_primitives::types::gas_price::RpcGasPriceResponse { gas_price_view })
     }
 
+    async fn gas_price_estimate(
+        &self,
+        request_data: near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateRequest,
+    ) -> Result<
+        near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateResponse,
+        near_jsonrpc_primitives::types::gas_price::RpcGasPriceError,
+    > {
+        let estimate = self
+            .view_client_send(GetGasPriceEstimate {
+                history: request_data.history,
+                target_blocks: request_data.target_blocks,
+            })
+            .await?;
+        Ok(near_jsonrpc_primitives::types::gas_price::RpcGasPriceEstimateResponse { estimate })
+    }
+
     async fn validators(
         &self,
         request_data: near_jsonrpc_primitives::types::validator::RpcValidatorRequest,
@@ -1309,6 +1505,89 @@ async fn display_debug_html(
     }
 }
 
+/// Status served in the safe mode, see [`start_safe_mode_http`].
+struct SafeModeStatus(Value);
+
+async fn safe_mode_rpc_handler(
+    message: web::Json<Message>,
+    status: web::Data<SafeModeStatus>,
+) -> HttpResponse {
+    let id = message.id();
+    let response = match message.0 {
+        Message::Request(request) if request.method == "status" => {
+            Message::response(id, Ok(status.0.clone()))
+        }
+        Message::Request(request) => Message::response(
+            id,
+            Err(RpcError::new_internal_error(
+                None,
+                format!(
+                    "{} is not available: the database is broken and the node runs in the \
+                     safe mode, see the db_recovery field of the status",
+                    request.method
+                ),
+            )),
+        ),
+        _ => {
+            Message::error(RpcError::parse_error("JSON RPC Request format was expected".to_owned()))
+        }
+    };
+    HttpResponse::Ok().json(&response)
+}
+
+async fn safe_mode_status_handler(status: web::Data<SafeModeStatus>) -> HttpResponse {
+    metrics::HTTP_STATUS_REQUEST_COUNT.inc();
+    HttpResponse::Ok().json(&status.0)
+}
+
+async fn safe_mode_health_handler() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().finish()
+}
+
+/// Starts an HTTP server for the safe mode, in which the node runs when its database
+/// couldn't be repaired after an unclean shutdown.
+///
+/// Only the `status` JSON RPC method and the `/status` endpoint answer, with the given
+/// status describing what is broken; `/health` reports the node as unavailable and the
+/// other methods return an error.  `/metrics` is served as usual.
+pub fn start_safe_mode_http(
+    config: RpcConfig,
+    status: near_primitives::views::StatusResponse,
+) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
+    let RpcConfig { addr, cors_allowed_origins, limits_config, .. } = config;
+    let status = serde_json::to_value(&status).expect("Failed to serialize the status");
+    info!(target:"network", "Starting safe mode http server at {}", addr);
+    let server = HttpServer::new(move || {
+        App::new()
+            .wrap(get_cors(&cors_allowed_origins))
+            .app_data(web::Data::new(SafeModeStatus(status.clone())))
+            .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
+            .wrap(middleware::Logger::default())
+            .service(web::resource("/").route(web::post().to(safe_mode_rpc_handler)))
+            .service(
+                web::resource("/status")
+                    .route(web::get().to(safe_mode_status_handler))
+                    .route(web::head().to(safe_mode_status_handler)),
+            )
+            .service(
+                web::resource("/health")
+                    .route(web::get().to(safe_mode_health_handler))
+                    .route(web::head().to(safe_mode_health_handler)),
+            )
+            .service(web::resource("/metrics").route(web::get().to(prometheus_handler)))
+    })
+    .bind(addr)
+    .unwrap()
+    .workers(1)
+    .shutdown_timeout(5)
+    .disable_signals()
+    .run();
+
+    let servers = vec![("Safe mode JSON RPC", server.handle())];
+    tokio::spawn(server);
+    servers
+}
+
 /// Starts HTTP server(s) listening for RPC requests.
 ///
 /// Starts an HTTP server which handles JSON RPC calls as well as states
@@ -1325,6 +1604,7 @@ pub fn start_http(
     genesis_config: GenesisConfig,
     client_addr: Addr<ClientActor>,
     view_client_addr: Addr<ViewClientActor>,
+    peer_manager_addr: Option<Addr<PeerManagerActor>>,
 ) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
     let RpcConfig {
         addr,
@@ -1333,6 +1613,7 @@ pub fn start_http(
         polling_config,
         limits_config,
         enable_debug_rpc,
+        enable_admin_rpc,
     } = config;
     let prometheus_addr = prometheus_addr.filter(|it| it != &addr);
     let cors_allowed_origins_clone = cors_allowed_origins.clone();
@@ -1347,6 +1628,8 @@ pub fn start_http(
                 polling_config,
                 genesis_config: genesis_config.clone(),
                 enable_debug_rpc,
+                enable_admin_rpc,
+                peer_manager_addr: peer_manager_addr.clone(),
             }))
             .app_data(web::JsonConfig::default().limit(limits_config.json_payload_max_size))
             .wrap(middleware::Logger::default())
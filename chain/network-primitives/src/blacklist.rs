@@ -1,4 +1,7 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_primitives::network::PeerId;
 use std::collections::HashSet;
+use std::fmt;
 use std::net;
 
 /// Only IPv6 addresses are stored.  IPv4 addresses are mapped to IPv6 before being added.
@@ -7,44 +10,115 @@ use std::net;
 /// it is presented as IPv6.
 /// TODO: alternatively we could use IpAddr::to_canonical(), but then the variants of
 /// the Entry enum would have to be private.
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum Entry {
     Ip(net::Ipv6Addr),
     IpPort(net::Ipv6Addr, u16),
+    /// All addresses sharing the first `prefix_len` bits with the given one.
+    /// Host bits of the address are always zero, so that equal networks compare equal.
+    Net(net::Ipv6Addr, u8),
+    /// The peer, regardless of the address it connects from.
+    Peer(PeerId),
+}
+
+fn to_ipv6(ip: net::IpAddr) -> net::Ipv6Addr {
+    match ip {
+        net::IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        net::IpAddr::V6(ip) => ip,
+    }
+}
+
+/// Inverse of `to_ipv6`.
+fn to_canonical(ip: net::Ipv6Addr) -> net::IpAddr {
+    match ip.octets() {
+        [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+            net::Ipv4Addr::new(a, b, c, d).into()
+        }
+        _ => ip.into(),
+    }
+}
+
+fn net_mask(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shl(128 - u32::from(prefix_len)).unwrap_or(0)
 }
 
 impl Entry {
     pub fn from_ip(ip: net::IpAddr) -> Entry {
-        Entry::Ip(match ip {
-            net::IpAddr::V4(ip) => ip.to_ipv6_mapped(),
-            net::IpAddr::V6(ip) => ip,
-        })
+        Entry::Ip(to_ipv6(ip))
     }
 
     pub fn from_addr(addr: net::SocketAddr) -> Entry {
-        Entry::IpPort(
-            match addr.ip() {
-                net::IpAddr::V4(ip) => ip.to_ipv6_mapped(),
-                net::IpAddr::V6(ip) => ip,
-            },
-            addr.port(),
-        )
+        Entry::IpPort(to_ipv6(addr.ip()), addr.port())
+    }
+
+    /// Network of the addresses sharing the first `prefix_len` bits with `ip`.
+    /// For IPv4 the prefix length is that of the IPv4 address, i.e. at most 32.
+    /// Returns None if the prefix length is too large.
+    pub fn from_net(ip: net::IpAddr, prefix_len: u8) -> Option<Entry> {
+        let prefix_len = match ip {
+            net::IpAddr::V4(_) if prefix_len <= 32 => prefix_len + 96,
+            net::IpAddr::V6(_) if prefix_len <= 128 => prefix_len,
+            _ => return None,
+        };
+        let ip = u128::from(to_ipv6(ip)) & net_mask(prefix_len);
+        Some(Entry::Net(ip.into(), prefix_len))
+    }
+
+    pub fn from_peer_id(peer_id: PeerId) -> Entry {
+        Entry::Peer(peer_id)
+    }
+
+    /// Whether the network entry contains the given IP.
+    fn net_contains(ip: &net::Ipv6Addr, prefix_len: u8, other: &net::Ipv6Addr) -> bool {
+        let mask = net_mask(prefix_len);
+        u128::from(*ip) & mask == u128::from(*other) & mask
     }
 }
 
 impl std::str::FromStr for Entry {
-    type Err = std::net::AddrParseError;
+    type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.parse::<std::net::IpAddr>() {
-            Ok(ip) => Ok(Entry::from_ip(ip)),
-            Err(_) => Ok(Entry::from_addr(s.parse::<net::SocketAddr>()?)),
+        if let Some((ip, prefix_len)) = s.split_once('/') {
+            let ip = ip.parse::<net::IpAddr>()?;
+            let prefix_len = prefix_len.parse::<u8>()?;
+            return Entry::from_net(ip, prefix_len)
+                .ok_or_else(|| anyhow::anyhow!("prefix length too large in {s:?}"));
+        }
+        if let Ok(ip) = s.parse::<net::IpAddr>() {
+            return Ok(Entry::from_ip(ip));
+        }
+        if let Ok(addr) = s.parse::<net::SocketAddr>() {
+            return Ok(Entry::from_addr(addr));
+        }
+        match s.parse::<near_crypto::PublicKey>() {
+            Ok(key) => Ok(Entry::Peer(PeerId::new(key))),
+            Err(_) => anyhow::bail!("{s:?} is neither IP, IP:port, CIDR nor peer id"),
         }
     }
 }
 
-/// A blacklist for socket addresses.  Supports adding individual IP:port tuples
-/// to the blacklist or entire IPs.
+/// Inverse of `FromStr`, IPv4-mapped addresses are displayed as IPv4.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Ip(ip) => write!(f, "{}", to_canonical(*ip)),
+            Entry::IpPort(ip, port) => {
+                write!(f, "{}", net::SocketAddr::new(to_canonical(*ip), *port))
+            }
+            Entry::Net(ip, prefix_len) => match to_canonical(*ip) {
+                ip @ net::IpAddr::V4(_) if *prefix_len >= 96 => {
+                    write!(f, "{}/{}", ip, prefix_len - 96)
+                }
+                _ => write!(f, "{}/{}", ip, prefix_len),
+            },
+            Entry::Peer(peer_id) => write!(f, "{}", peer_id),
+        }
+    }
+}
+
+/// A blacklist for peers.  Supports adding individual IP:port tuples
+/// to the blacklist, entire IPs, networks (CIDR) and peer ids.
 #[derive(Debug, Default, Clone)]
 pub struct Blacklist(HashSet<Entry>);
 
@@ -59,7 +133,102 @@ impl FromIterator<Entry> for Blacklist {
 impl Blacklist {
     /// Returns whether given address is on the blacklist.
     pub fn contains(&self, addr: net::SocketAddr) -> bool {
-        self.0.contains(&Entry::from_ip(addr.ip())) || self.0.contains(&Entry::from_addr(addr))
+        let ip = to_ipv6(addr.ip());
+        self.0.contains(&Entry::Ip(ip))
+            || self.0.contains(&Entry::IpPort(ip, addr.port()))
+            || self.0.iter().any(|e| match e {
+                Entry::Net(net, prefix_len) => Entry::net_contains(net, *prefix_len, &ip),
+                _ => false,
+            })
+    }
+
+    /// Returns whether given peer id is on the blacklist.
+    pub fn contains_peer(&self, peer_id: &PeerId) -> bool {
+        self.0.contains(&Entry::Peer(peer_id.clone()))
+    }
+
+    /// Adds the entry, returns false if it was already present.
+    pub fn insert(&mut self, entry: Entry) -> bool {
+        self.0.insert(entry)
+    }
+
+    /// Removes the entry, returns false if it wasn't present.
+    pub fn remove(&mut self, entry: &Entry) -> bool {
+        self.0.remove(entry)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.0.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Rule applied to an entry by the node operator at runtime.
+/// Rules are persisted and take precedence over the config.
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessRule {
+    /// The entry is added to the blacklist.
+    Block,
+    /// The entry is added to the allowlist.
+    Allow,
+    /// The entry is removed from both lists.
+    Unlisted,
+}
+
+/// Blacklist together with the allowlist of exceptions from it.
+///
+/// Address entries and peer id entries are matched independently: an allowed address
+/// exempts it from the blocked networks, an allowed peer id exempts it from the blocked
+/// peer ids. This way an inbound connection can be rejected by address as soon as
+/// it is accepted, and by peer id once the handshake tells us who the peer is.
+#[derive(Debug, Default, Clone)]
+pub struct AccessList {
+    pub blacklist: Blacklist,
+    pub allowlist: Blacklist,
+}
+
+impl AccessList {
+    pub fn new(blacklist: Blacklist, allowlist: Blacklist) -> Self {
+        Self { blacklist, allowlist }
+    }
+
+    /// Whether connections from/to the address are rejected.
+    pub fn is_addr_blocked(&self, addr: net::SocketAddr) -> bool {
+        self.blacklist.contains(addr) && !self.allowlist.contains(addr)
+    }
+
+    /// Whether connections from/to the peer are rejected.
+    pub fn is_peer_blocked(&self, peer_id: &PeerId) -> bool {
+        self.blacklist.contains_peer(peer_id) && !self.allowlist.contains_peer(peer_id)
+    }
+
+    /// Whether connections with the peer at the given address are rejected.
+    pub fn is_blocked(&self, peer_id: &PeerId, addr: Option<net::SocketAddr>) -> bool {
+        self.is_peer_blocked(peer_id) || addr.map_or(false, |addr| self.is_addr_blocked(addr))
+    }
+
+    pub fn set_rule(&mut self, entry: Entry, rule: AccessRule) {
+        self.blacklist.remove(&entry);
+        self.allowlist.remove(&entry);
+        match rule {
+            AccessRule::Block => self.blacklist.insert(entry),
+            AccessRule::Allow => self.allowlist.insert(entry),
+            AccessRule::Unlisted => false,
+        };
     }
 }
 
@@ -78,7 +247,9 @@ mod test {
 
         assert_eq!(None, parse("foo"));
         assert_eq!(None, parse("192.0.2.*"));
-        assert_eq!(None, parse("192.0.2.0/24"));
+        assert_eq!(None, parse("192.0.2.0/33"));
+        assert_eq!(None, parse("::/129"));
+        assert_eq!(None, parse("ed25519:foo"));
         assert_eq!(None, parse("192.0.2.4.5"));
         assert_eq!(None, parse("192.0.2.4:424242"));
 
@@ -94,6 +265,24 @@ mod test {
             Entry::from_addr(net::SocketAddr::new(LO4, 42)),
             parse("[::ffff:127.0.0.1]:42").unwrap()
         );
+
+        // Host bits are ignored.
+        assert_eq!(parse("192.0.2.0/24").unwrap(), parse("192.0.2.4/24").unwrap());
+        assert_eq!(parse("192.0.2.0/24").unwrap(), parse("::ffff:192.0.2.0/120").unwrap());
+        assert_eq!(Entry::from_net(LO6, 64).unwrap(), parse("::/64").unwrap());
+
+        let peer_id = PeerId::new(
+            near_crypto::SecretKey::from_seed(near_crypto::KeyType::ED25519, "test").public_key(),
+        );
+        assert_eq!(Entry::from_peer_id(peer_id.clone()), parse(&peer_id.to_string()).unwrap());
+    }
+
+    #[test]
+    fn test_display_entry() {
+        for s in ["192.0.2.4", "192.0.2.4:42", "[::1]:42", "::1", "192.0.2.0/24", "2001:db8::/32"] {
+            assert_eq!(s, s.parse::<Entry>().unwrap().to_string());
+        }
+        assert_eq!("192.0.2.0/24", "192.0.2.4/24".parse::<Entry>().unwrap().to_string());
     }
 
     #[test]
@@ -124,4 +313,57 @@ mod test {
         assert!(blacklist.contains(SocketAddr::new(mapped_ip, 42)));
         assert!(!blacklist.contains(SocketAddr::new(mapped_ip, 8080)));
     }
+
+    #[test]
+    fn test_blacklist_net() {
+        use std::net::*;
+
+        let blacklist: Blacklist = ["192.0.2.0/24", "2001:db8::/32", "198.51.100.7/32"]
+            .into_iter()
+            .map(|e| e.parse().unwrap())
+            .collect();
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+
+        assert!(blacklist.contains(addr("192.0.2.4:42")));
+        assert!(blacklist.contains(addr("192.0.2.255:1")));
+        assert!(blacklist.contains(addr("[::ffff:192.0.2.4]:42")));
+        assert!(!blacklist.contains(addr("192.0.3.4:42")));
+        assert!(blacklist.contains(addr("[2001:db8::1]:42")));
+        assert!(!blacklist.contains(addr("[2001:db9::1]:42")));
+        assert!(blacklist.contains(addr("198.51.100.7:42")));
+        assert!(!blacklist.contains(addr("198.51.100.8:42")));
+    }
+
+    #[test]
+    fn test_access_list() {
+        use std::net::*;
+
+        let peer_id = |seed| {
+            PeerId::new(
+                near_crypto::SecretKey::from_seed(near_crypto::KeyType::ED25519, seed).public_key(),
+            )
+        };
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+        let mut list = AccessList::new(
+            ["192.0.2.0/24".parse().unwrap(), Entry::from_peer_id(peer_id("a"))]
+                .into_iter()
+                .collect(),
+            ["192.0.2.4".parse().unwrap()].into_iter().collect(),
+        );
+
+        assert!(list.is_addr_blocked(addr("192.0.2.5:42")));
+        assert!(!list.is_addr_blocked(addr("192.0.2.4:42")));
+        assert!(list.is_peer_blocked(&peer_id("a")));
+        assert!(!list.is_peer_blocked(&peer_id("b")));
+        assert!(list.is_blocked(&peer_id("a"), Some(addr("192.0.2.4:42"))));
+        assert!(list.is_blocked(&peer_id("b"), Some(addr("192.0.2.5:42"))));
+        assert!(!list.is_blocked(&peer_id("b"), None));
+
+        list.set_rule(Entry::from_peer_id(peer_id("a")), AccessRule::Allow);
+        assert!(!list.is_peer_blocked(&peer_id("a")));
+        list.set_rule("192.0.2.4".parse().unwrap(), AccessRule::Unlisted);
+        assert!(list.is_addr_blocked(addr("192.0.2.4:42")));
+        list.set_rule("192.0.2.0/24".parse().unwrap(), AccessRule::Unlisted);
+        assert!(!list.is_addr_blocked(addr("192.0.2.4:42")));
+    }
 }
@@ -0,0 +1,115 @@
+//! Fault injection for testing how nodes cope with an unreliable network, without tc/netem.
+//!
+//! A `ChaosConfig` is a schedule of rules, each of which drops, delays, reorders or
+//! duplicates the messages of a single type received from peers while it is in effect.
+//! The schedule is relative to the start of the node. It can only be set programmatically,
+//! in tests and in the mock node, and never from config.json.
+use crate::time;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChaosAction {
+    Drop,
+    /// Delays the message. The messages after it are not delayed.
+    Delay(Duration),
+    /// Holds the message back until the next message is processed.
+    Reorder,
+    /// Processes the message twice.
+    Duplicate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosRule {
+    /// Type of the affected messages, e.g. "Block", or for routed messages the type of the
+    /// body, e.g. "PartialEncodedChunkRequest".
+    pub msg_type: String,
+    pub action: ChaosAction,
+    /// The rule applies to every `every`-th message of the type, starting from the first one.
+    #[serde(default = "default_every")]
+    pub every: u64,
+    /// Time since the start of the node from which the rule is in effect.
+    #[serde(default)]
+    pub start: Duration,
+    /// Time since the start of the node from which the rule is no longer in effect.
+    /// None if the rule stays in effect.
+    #[serde(default)]
+    pub end: Option<Duration>,
+}
+
+fn default_every() -> u64 {
+    1
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChaosConfig {
+    pub rules: Vec<ChaosRule>,
+}
+
+impl ChaosConfig {
+    pub fn on(mut self, msg_type: &str, action: ChaosAction) -> Self {
+        self.rules.push(ChaosRule {
+            msg_type: msg_type.to_string(),
+            action,
+            every: 1,
+            start: Duration::ZERO,
+            end: None,
+        });
+        self
+    }
+}
+
+/// Schedule in effect, shared by all the connections of a node.
+pub struct Chaos {
+    rules: Vec<ChaosRule>,
+    /// Number of the messages which matched each rule so far.
+    matched: Vec<AtomicU64>,
+    start: time::Instant,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig, start: time::Instant) -> Self {
+        let matched = config.rules.iter().map(|_| AtomicU64::new(0)).collect();
+        Self { rules: config.rules, matched, start }
+    }
+
+    /// Action to apply to a message of type `msg_type` received at `now`, if any. The first
+    /// rule in effect for the type applies.
+    pub fn action(&self, msg_type: &str, now: time::Instant) -> Option<ChaosAction> {
+        let elapsed: Duration = (now - self.start).try_into().unwrap_or_default();
+        let (i, rule) = self.rules.iter().enumerate().find(|(_, rule)| {
+            rule.msg_type == msg_type
+                && rule.start <= elapsed
+                && rule.end.map_or(true, |end| elapsed < end)
+        })?;
+        let matched = self.matched[i].fetch_add(1, Ordering::Relaxed);
+        (matched % rule.every.max(1) == 0).then(|| rule.action.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_action() {
+        let clock = time::FakeClock::default();
+        let mut config = ChaosConfig::default()
+            .on("Block", ChaosAction::Drop)
+            .on("Block", ChaosAction::Duplicate)
+            .on("BlockHeaders", ChaosAction::Reorder);
+        config.rules[0].end = Some(Duration::from_secs(10));
+        config.rules[2].every = 2;
+        let chaos = Chaos::new(config, clock.now());
+
+        assert_eq!(Some(ChaosAction::Drop), chaos.action("Block", clock.now()));
+        assert_eq!(None, chaos.action("Transaction", clock.now()));
+        assert_eq!(Some(ChaosAction::Reorder), chaos.action("BlockHeaders", clock.now()));
+        assert_eq!(None, chaos.action("BlockHeaders", clock.now()));
+        assert_eq!(Some(ChaosAction::Reorder), chaos.action("BlockHeaders", clock.now()));
+        clock.advance(time::Duration::seconds(10));
+        assert_eq!(Some(ChaosAction::Duplicate), chaos.action("Block", clock.now()));
+    }
+}
@@ -1,9 +1,14 @@
+use crate::chaos::ChaosConfig;
 use crate::network_protocol::PeerInfo;
-use crate::types::{Blacklist, ROUTED_MESSAGE_TTL};
-use near_crypto::{KeyType, SecretKey};
+use crate::types::{
+    BanRule, BanSeverity, Blacklist, BlacklistEntry, ReasonForBan, SocketOptions, Transport,
+    ROUTED_MESSAGE_TTL,
+};
+use near_crypto::{KeyType, PublicKey, SecretKey};
 use near_primitives::network::PeerId;
 use near_primitives::types::AccountId;
 use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
+use std::collections::HashMap;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,6 +19,12 @@ pub const HIGHEST_PEER_HORIZON: u64 = 5;
 /// Maximum amount of routes to store for each account id.
 pub const MAX_ROUTES_TO_STORE: usize = 5;
 
+/// Prefix of the `boot_nodes` entries which name a domain listing the boot nodes.
+const DNS_BOOT_NODE_PREFIX: &str = "dns:";
+
+/// Default duration of the ban for minor offenses, once they add up.
+const MINOR_BAN_WINDOW: Duration = Duration::from_secs(15 * 60);
+
 /// ValidatorEndpoints are the endpoints that peers should connect to, to send messages to this
 /// validator. Validator will sign the endpoints and broadcast them to the network.
 /// For a static setup (a static IP, or a list of relay nodes with static IPs) use PublicAddrs.
@@ -42,6 +53,30 @@ impl ValidatorConfig {
     }
 }
 
+/// Listening socket with its own policy for the inbound connections.
+#[derive(Clone, Debug)]
+pub struct ListenerConfig {
+    pub addr: SocketAddr,
+    /// Accept only the IPs of `NetworkConfig::whitelist_nodes`, and of `allowlist`.
+    pub whitelist_only: bool,
+    /// Addresses (IPs, IP:port pairs or networks) accepted by this listener. If not empty,
+    /// the listener accepts only these, and the whitelisted nodes if `whitelist_only`.
+    pub allowlist: Blacklist,
+    pub max_inbound_connections_per_ip_per_minute: u32,
+    pub max_pending_handshakes_per_ip: u32,
+}
+
+impl ListenerConfig {
+    /// Whether the listener accepts a connection from `addr`, given whether it is the address
+    /// of a node from `NetworkConfig::whitelist_nodes`.
+    pub fn accepts(&self, addr: SocketAddr, is_whitelisted: bool) -> bool {
+        if !self.whitelist_only && self.allowlist.is_empty() {
+            return true;
+        }
+        (self.whitelist_only && is_whitelisted) || self.allowlist.contains(addr)
+    }
+}
+
 /// Configuration for the peer-to-peer manager.
 #[derive(Clone)]
 pub struct NetworkConfig {
@@ -50,8 +85,14 @@ pub struct NetworkConfig {
     pub validator: Option<ValidatorConfig>,
 
     pub boot_nodes: Vec<PeerInfo>,
+    /// Domains whose TXT records list boot nodes, resolved periodically.
+    pub boot_node_domains: Vec<String>,
     pub whitelist_nodes: Vec<PeerInfo>,
+    /// Maximum time for a connection to complete the handshake, see
+    /// `Config::handshake_timeout_base`.
     pub handshake_timeout: Duration,
+    pub handshake_timeout_base: Duration,
+    pub handshake_timeout_rtt_multiplier: u32,
     pub reconnect_delay: Duration,
     pub bootstrap_peers_period: Duration,
     /// Maximum number of active peers. Hard limit.
@@ -72,6 +113,8 @@ pub struct NetworkConfig {
     pub archival_peer_connections_lower_bound: u32,
     /// Duration of the ban for misbehaving peers.
     pub ban_window: Duration,
+    /// Overrides of the default rules for the reasons for ban, see `ban_rule`.
+    pub ban_rules: HashMap<ReasonForBan, BanRule>,
     /// Remove expired peers.
     pub peer_expiration_duration: Duration,
     /// Maximum number of peer addresses we should ever send on PeersRequest.
@@ -94,11 +137,77 @@ pub struct NetworkConfig {
     pub push_info_period: Duration,
     /// Nodes will not accept or try to establish connection to such peers.
     pub blacklist: Blacklist,
+    /// Exceptions from the blacklist.
+    pub allowlist: Blacklist,
     /// Flag to disable outbound connections. When this flag is active, nodes will not try to
     /// establish connection with other nodes, but will accept incoming connection if other requirements
     /// are satisfied.
     /// This flag should be ALWAYS FALSE. Only set to true for testing purposes.
     pub outbound_disabled: bool,
+    /// Encrypt connections with peers which support encryption.
+    pub encrypt_connections: bool,
+    /// Transport used for the connections with peers.
+    pub transport: Transport,
+    /// Outbound bandwidth limit of sync traffic per connection, in bytes per second.
+    pub peer_send_limit_bytes_per_sec: Option<u64>,
+    /// Outbound bandwidth limit of sync traffic of all the connections, in bytes per second.
+    pub total_send_limit_bytes_per_sec: Option<u64>,
+    /// Maximum number of inbound connections accepted from a single IP per minute.
+    pub max_inbound_connections_per_ip_per_minute: u32,
+    /// Maximum number of inbound connections from a single IP in the middle of the handshake.
+    pub max_pending_handshakes_per_ip: u32,
+    /// Maximum number of inbound connections from all the IPs in the middle of the handshake.
+    pub max_pending_handshakes: u32,
+    /// Inbound connections on which no complete frame has been received within this time
+    /// are closed before a `PeerActor` is spawned for them.
+    pub first_frame_timeout: Duration,
+    /// Proof of work (in bits) required in the handshake from IPs over the connection rate
+    /// limit. 0 means such IPs are rejected.
+    pub handshake_pow_difficulty: u8,
+    /// Forward the listening port on the local router with UPnP or NAT-PMP.
+    pub port_mapping: bool,
+    /// File to record the frames received from peers to.
+    pub record_frames: Option<std::path::PathBuf>,
+    /// Listening sockets besides `node_addr`, which follows the top-level policy.
+    pub listeners: Vec<ListenerConfig>,
+    /// Maximum number of edges kept in the routing table.
+    pub max_routing_table_edges: usize,
+    /// Maintain direct connections with the other block and chunk producers, if this node
+    /// is a validator.
+    pub tier1_connections: bool,
+    /// Maximum number of connected peers in a single IPv4 /24 or IPv6 /48 subnet.
+    pub max_peers_per_subnet: Option<u32>,
+    /// Maximum number of connected peers in a single autonomous system.
+    pub max_peers_per_asn: Option<u32>,
+    /// Database of the IP ranges of autonomous systems, see `Config::asn_database`.
+    pub asn_database: Option<std::path::PathBuf>,
+    /// How often to send a keep-alive message to each peer.
+    pub keep_alive_interval: Duration,
+    /// Connections on which nothing has been received for this long are closed.
+    pub keep_alive_timeout: Duration,
+    /// Talk to peers only in the protobuf encoding, see `Config::protobuf_only`.
+    pub protobuf_only: bool,
+    /// Keys allowed to sign advisories, see `Config::advisory_keys`.
+    pub advisory_keys: Vec<PublicKey>,
+    /// Drop the advisories, see `Config::ignore_advisories`.
+    pub ignore_advisories: bool,
+    /// Number of the recently received routed messages remembered to drop their duplicates.
+    pub routed_message_dedup_capacity: usize,
+    /// Duplicates of a routed message received within this time after it are dropped.
+    pub routed_message_dedup_window: Duration,
+    /// Save the recently received routed messages on shutdown and load them on startup.
+    pub persist_routed_message_dedup: bool,
+    /// Options of the TCP sockets of the connections with peers.
+    pub socket_options: SocketOptions,
+    /// Fraction of `max_num_peers` reserved for the inbound peers tracking some of our
+    /// shards, see `Config::inbound_tracked_shards_ratio`.
+    pub inbound_tracked_shards_ratio: f64,
+    /// Time for which the consensus messages to unreachable validators are kept for delivery,
+    /// see `Config::delayed_delivery_ttl`. None disables the queue.
+    pub delayed_delivery_ttl: Option<Duration>,
+    /// Faults to inject into the messages received from peers.
+    /// Only set for testing purposes, it can't be set in config.json.
+    pub chaos: Option<ChaosConfig>,
     /// Not clear old data, set `true` for archive nodes.
     pub archive: bool,
 }
@@ -134,9 +243,16 @@ impl NetworkConfig {
             } else {
                 cfg.boot_nodes
                     .split(',')
+                    .filter(|chunk| !chunk.starts_with(DNS_BOOT_NODE_PREFIX))
                     .map(|chunk| chunk.try_into().expect("Failed to parse PeerInfo"))
                     .collect()
             },
+            boot_node_domains: cfg
+                .boot_nodes
+                .split(',')
+                .filter_map(|chunk| chunk.strip_prefix(DNS_BOOT_NODE_PREFIX))
+                .map(|domain| domain.to_string())
+                .collect(),
             whitelist_nodes: (|| -> Vec<_> {
                 let w = &cfg.whitelist_nodes;
                 if w.is_empty() {
@@ -153,6 +269,8 @@ impl NetworkConfig {
                 peers
             }()),
             handshake_timeout: cfg.handshake_timeout,
+            handshake_timeout_base: cfg.handshake_timeout_base,
+            handshake_timeout_rtt_multiplier: cfg.handshake_timeout_rtt_multiplier,
             reconnect_delay: cfg.reconnect_delay,
             bootstrap_peers_period: Duration::from_secs(60),
             max_num_peers: cfg.max_num_peers,
@@ -163,6 +281,7 @@ impl NetworkConfig {
             safe_set_size: cfg.safe_set_size,
             archival_peer_connections_lower_bound: cfg.archival_peer_connections_lower_bound,
             ban_window: cfg.ban_window,
+            ban_rules: cfg.ban_rules,
             max_send_peers: 512,
             peer_expiration_duration: Duration::from_secs(7 * 24 * 60 * 60),
             peer_stats_period: Duration::from_secs(5),
@@ -176,7 +295,60 @@ impl NetworkConfig {
                 .iter()
                 .map(|e| e.parse().expect("failed to parse blacklist"))
                 .collect(),
+            allowlist: cfg
+                .allowlist
+                .iter()
+                .map(|e| e.parse().expect("failed to parse allowlist"))
+                .collect(),
             outbound_disabled: false,
+            encrypt_connections: cfg.encrypt_connections,
+            transport: cfg.transport,
+            peer_send_limit_bytes_per_sec: cfg.peer_send_limit_bytes_per_sec,
+            total_send_limit_bytes_per_sec: cfg.total_send_limit_bytes_per_sec,
+            max_inbound_connections_per_ip_per_minute: cfg
+                .max_inbound_connections_per_ip_per_minute,
+            max_pending_handshakes_per_ip: cfg.max_pending_handshakes_per_ip,
+            max_pending_handshakes: cfg.max_pending_handshakes,
+            first_frame_timeout: cfg.first_frame_timeout,
+            handshake_pow_difficulty: cfg.handshake_pow_difficulty,
+            port_mapping: cfg.port_mapping,
+            record_frames: cfg.record_frames,
+            listeners: cfg
+                .listeners
+                .iter()
+                .map(|l| ListenerConfig {
+                    addr: l.addr.parse().expect("Failed to parse SocketAddr"),
+                    whitelist_only: l.whitelist_only,
+                    allowlist: l
+                        .allowlist
+                        .iter()
+                        .map(|e| e.parse().expect("failed to parse listener allowlist"))
+                        .collect(),
+                    max_inbound_connections_per_ip_per_minute: l
+                        .max_inbound_connections_per_ip_per_minute
+                        .unwrap_or(cfg.max_inbound_connections_per_ip_per_minute),
+                    max_pending_handshakes_per_ip: l
+                        .max_pending_handshakes_per_ip
+                        .unwrap_or(cfg.max_pending_handshakes_per_ip),
+                })
+                .collect(),
+            max_routing_table_edges: cfg.max_routing_table_edges,
+            tier1_connections: cfg.tier1_connections,
+            max_peers_per_subnet: cfg.max_peers_per_subnet,
+            max_peers_per_asn: cfg.max_peers_per_asn,
+            asn_database: cfg.asn_database,
+            keep_alive_interval: cfg.keep_alive_interval,
+            keep_alive_timeout: cfg.keep_alive_timeout,
+            protobuf_only: cfg.protobuf_only,
+            advisory_keys: cfg.advisory_keys,
+            ignore_advisories: cfg.ignore_advisories,
+            routed_message_dedup_capacity: cfg.routed_message_dedup_capacity,
+            routed_message_dedup_window: cfg.routed_message_dedup_window,
+            persist_routed_message_dedup: cfg.persist_routed_message_dedup,
+            socket_options: cfg.socket_options,
+            inbound_tracked_shards_ratio: cfg.inbound_tracked_shards_ratio,
+            delayed_delivery_ttl: cfg.delayed_delivery_ttl,
+            chaos: None,
             archive,
         }
     }
@@ -185,6 +357,32 @@ impl NetworkConfig {
         PeerId::new(self.node_key.public_key())
     }
 
+    /// Reaction to a peer misbehaving for the given reason.
+    /// Offenses which honest peers might commit because of a bug or a transient condition
+    /// are minor by default, the rest are major and banned for `ban_window`.
+    pub fn ban_rule(&self, reason: ReasonForBan) -> BanRule {
+        if let Some(rule) = self.ban_rules.get(&reason) {
+            return *rule;
+        }
+        match reason {
+            ReasonForBan::Abusive
+            | ReasonForBan::BadBlockApproval
+            | ReasonForBan::EpochSyncNoResponse
+            | ReasonForBan::InvalidRoutedResponse => BanRule {
+                severity: BanSeverity::Minor,
+                duration: self.ban_window.min(MINOR_BAN_WINDOW),
+            },
+            _ => BanRule { severity: BanSeverity::Major, duration: self.ban_window },
+        }
+    }
+
+    /// Time for a connection with the given round-trip time to complete the handshake.
+    pub fn handshake_timeout_for_rtt(&self, rtt: Duration) -> Duration {
+        rtt.checked_mul(self.handshake_timeout_rtt_multiplier)
+            .and_then(|rtts| self.handshake_timeout_base.checked_add(rtts))
+            .map_or(self.handshake_timeout, |timeout| timeout.min(self.handshake_timeout))
+    }
+
     /// Returns network config with given seed used for peer id.
     pub fn from_seed(seed: &str, port: u16) -> Self {
         let node_key = SecretKey::from_seed(KeyType::ED25519, seed);
@@ -203,8 +401,11 @@ impl NetworkConfig {
             node_key,
             validator: Some(validator),
             boot_nodes: vec![],
+            boot_node_domains: vec![],
             whitelist_nodes: vec![],
             handshake_timeout: Duration::from_secs(60),
+            handshake_timeout_base: Duration::from_secs(60),
+            handshake_timeout_rtt_multiplier: 20,
             reconnect_delay: Duration::from_secs(60),
             bootstrap_peers_period: Duration::from_millis(100),
             max_num_peers: 40,
@@ -215,6 +416,7 @@ impl NetworkConfig {
             safe_set_size: 20,
             archival_peer_connections_lower_bound: 10,
             ban_window: Duration::from_secs(1),
+            ban_rules: HashMap::new(),
             peer_expiration_duration: Duration::from_secs(60 * 60),
             max_send_peers: 512,
             peer_stats_period: Duration::from_secs(5),
@@ -224,7 +426,37 @@ impl NetworkConfig {
             highest_peer_horizon: 5,
             push_info_period: Duration::from_millis(100),
             blacklist: Blacklist::default(),
+            allowlist: Blacklist::default(),
             outbound_disabled: false,
+            encrypt_connections: false,
+            transport: Transport::Tcp,
+            peer_send_limit_bytes_per_sec: None,
+            total_send_limit_bytes_per_sec: None,
+            max_inbound_connections_per_ip_per_minute: 600,
+            max_pending_handshakes_per_ip: 100,
+            max_pending_handshakes: 1000,
+            first_frame_timeout: Duration::from_secs(5),
+            handshake_pow_difficulty: 0,
+            port_mapping: false,
+            record_frames: None,
+            listeners: vec![],
+            max_routing_table_edges: 200_000,
+            tier1_connections: false,
+            max_peers_per_subnet: None,
+            max_peers_per_asn: None,
+            asn_database: None,
+            keep_alive_interval: Duration::from_secs(5),
+            keep_alive_timeout: Duration::from_secs(20),
+            protobuf_only: false,
+            advisory_keys: vec![],
+            ignore_advisories: false,
+            routed_message_dedup_capacity: 100_000,
+            routed_message_dedup_window: Duration::from_millis(50),
+            persist_routed_message_dedup: false,
+            socket_options: SocketOptions::default(),
+            inbound_tracked_shards_ratio: 0.,
+            delayed_delivery_ttl: None,
+            chaos: None,
             archive: false,
         }
     }
@@ -263,6 +495,88 @@ impl NetworkConfig {
                 self.peer_recent_time_window.as_secs(), UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE.as_secs()
             );
         }
+
+        for (i, listener) in self.listeners.iter().enumerate() {
+            if Some(listener.addr) == self.node_addr
+                || self.listeners[..i].iter().any(|l| l.addr == listener.addr)
+            {
+                anyhow::bail!("Listener address {} is used more than once.", listener.addr);
+            }
+            if listener.allowlist.iter().any(|e| matches!(e, BlacklistEntry::Peer(_))) {
+                anyhow::bail!(
+                    "Allowlist of the listener at {} contains peer ids, only addresses are supported.",
+                    listener.addr
+                );
+            }
+            if listener.whitelist_only
+                && self.whitelist_nodes.is_empty()
+                && listener.allowlist.is_empty()
+            {
+                anyhow::bail!(
+                    "Listener at {} accepts only whitelisted nodes, but whitelist_nodes is empty.",
+                    listener.addr
+                );
+            }
+        }
+
+        if self.max_peers_per_asn.is_some() && self.asn_database.is_none() {
+            anyhow::bail!("max_peers_per_asn is set, but asn_database is not.");
+        }
+
+        if self.keep_alive_timeout <= self.keep_alive_interval {
+            anyhow::bail!(
+                "keep_alive_timeout({:?}) has to be longer than keep_alive_interval({:?}).",
+                self.keep_alive_timeout,
+                self.keep_alive_interval
+            );
+        }
+
+        if self.max_pending_handshakes == 0 {
+            anyhow::bail!("max_pending_handshakes has to be positive.");
+        }
+
+        if self.handshake_timeout_base > self.handshake_timeout {
+            anyhow::bail!(
+                "handshake_timeout_base({:?}) can't be longer than handshake_timeout({:?}).",
+                self.handshake_timeout_base,
+                self.handshake_timeout
+            );
+        }
+
+        if self.first_frame_timeout > self.handshake_timeout {
+            anyhow::bail!(
+                "first_frame_timeout({:?}) can't be longer than handshake_timeout({:?}).",
+                self.first_frame_timeout,
+                self.handshake_timeout
+            );
+        }
+
+        if self.routed_message_dedup_capacity == 0 {
+            anyhow::bail!("routed_message_dedup_capacity has to be positive.");
+        }
+
+        if self.socket_options.send_buffer_size == Some(0)
+            || self.socket_options.recv_buffer_size == Some(0)
+        {
+            anyhow::bail!("socket_options buffer sizes have to be positive.");
+        }
+
+        if let Some((reason, _)) =
+            self.ban_rules.iter().find(|(_, rule)| rule.duration == Duration::ZERO)
+        {
+            anyhow::bail!("ban_rules: duration of the ban for {:?} has to be positive.", reason);
+        }
+
+        if !(0. ..=1.).contains(&self.inbound_tracked_shards_ratio) {
+            anyhow::bail!(
+                "inbound_tracked_shards_ratio({}) has to be between 0 and 1.",
+                self.inbound_tracked_shards_ratio
+            );
+        }
+
+        if self.delayed_delivery_ttl == Some(Duration::ZERO) {
+            anyhow::bail!("delayed_delivery_ttl has to be positive.");
+        }
         Ok(())
     }
 }
@@ -301,4 +615,85 @@ mod test {
         let res = nc.verify();
         assert!(res.is_err(), "{:?}", res);
     }
+
+    #[test]
+    fn test_ban_rule() {
+        use crate::types::{BanRule, BanSeverity, ReasonForBan};
+        use std::time::Duration;
+
+        let mut nc = NetworkConfig::from_seed("123", 213);
+        nc.ban_window = Duration::from_secs(3 * 60 * 60);
+        assert_eq!(
+            BanRule { severity: BanSeverity::Major, duration: nc.ban_window },
+            nc.ban_rule(ReasonForBan::InvalidSignature)
+        );
+        let minor = nc.ban_rule(ReasonForBan::InvalidRoutedResponse);
+        assert_eq!(BanSeverity::Minor, minor.severity);
+        assert!(minor.duration < nc.ban_window);
+
+        let rule = BanRule { severity: BanSeverity::Major, duration: Duration::from_secs(60) };
+        nc.ban_rules.insert(ReasonForBan::InvalidRoutedResponse, rule);
+        assert_eq!(rule, nc.ban_rule(ReasonForBan::InvalidRoutedResponse));
+        assert!(nc.verify().is_ok());
+
+        let rule = BanRule { severity: BanSeverity::Major, duration: Duration::ZERO };
+        nc.ban_rules.insert(ReasonForBan::InvalidSignature, rule);
+        assert!(nc.verify().is_err());
+    }
+
+    #[test]
+    fn test_handshake_timeout_for_rtt() {
+        use std::time::Duration;
+
+        let mut nc = NetworkConfig::from_seed("123", 213);
+        nc.handshake_timeout = Duration::from_secs(20);
+        nc.handshake_timeout_base = Duration::from_secs(5);
+        nc.handshake_timeout_rtt_multiplier = 20;
+        assert_eq!(Duration::from_secs(5), nc.handshake_timeout_for_rtt(Duration::ZERO));
+        assert_eq!(
+            Duration::from_secs(9),
+            nc.handshake_timeout_for_rtt(Duration::from_millis(200))
+        );
+        assert_eq!(Duration::from_secs(20), nc.handshake_timeout_for_rtt(Duration::from_secs(1)));
+        assert_eq!(Duration::from_secs(20), nc.handshake_timeout_for_rtt(Duration::MAX));
+
+        nc.handshake_timeout_base = Duration::from_secs(30);
+        assert!(nc.verify().is_err());
+    }
+
+    #[test]
+    fn test_boot_node_domains() {
+        let mut cfg = crate::config_json::Config::default();
+        cfg.boot_nodes = "ed25519:86EtEy7epneKyrcJwSWP7zsisTkfDRH5CFVszt4qiQYw@127.0.0.1:24567,\
+                          dns:boot.example.org"
+            .to_string();
+        let node_key = near_crypto::SecretKey::from_random(near_crypto::KeyType::ED25519);
+        let nc = NetworkConfig::new(cfg, node_key, None, false);
+        assert_eq!(1, nc.boot_nodes.len());
+        assert_eq!(vec!["boot.example.org".to_string()], nc.boot_node_domains);
+    }
+
+    #[test]
+    fn test_listener_accepts() {
+        let mut listener = crate::types::ListenerConfig {
+            addr: "127.0.0.1:24568".parse().unwrap(),
+            whitelist_only: false,
+            allowlist: Default::default(),
+            max_inbound_connections_per_ip_per_minute: 600,
+            max_pending_handshakes_per_ip: 100,
+        };
+        let private = "10.1.2.3:1234".parse().unwrap();
+        let public = "8.8.8.8:1234".parse().unwrap();
+        assert!(listener.accepts(public, false));
+
+        listener.allowlist = ["10.0.0.0/8".parse().unwrap()].into_iter().collect();
+        assert!(listener.accepts(private, false));
+        assert!(!listener.accepts(public, false));
+        assert!(!listener.accepts(public, true));
+
+        listener.whitelist_only = true;
+        assert!(listener.accepts(private, false));
+        assert!(listener.accepts(public, true));
+        assert!(!listener.accepts(public, false));
+    }
 }
@@ -1,4 +1,6 @@
+use crate::types::ReasonForBan;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 
 /// Time to persist Accounts Id in the router without removing them in seconds.
@@ -42,6 +44,106 @@ fn default_ttl_account_id_router() -> Duration {
 fn default_peer_stats_period() -> Duration {
     Duration::from_secs(5)
 }
+/// Maximum number of inbound connections accepted from a single IP per minute.
+fn default_max_inbound_connections_per_ip_per_minute() -> u32 {
+    60
+}
+/// Maximum number of inbound connections from a single IP in the middle of the handshake.
+fn default_max_pending_handshakes_per_ip() -> u32 {
+    8
+}
+/// Maximum number of inbound connections from all the IPs in the middle of the handshake.
+fn default_max_pending_handshakes() -> u32 {
+    128
+}
+/// Time to receive the first frame on an inbound connection.
+fn default_first_frame_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+/// Time to complete the handshake on top of the round trips it takes.
+fn default_handshake_timeout_base() -> Duration {
+    Duration::from_secs(5)
+}
+/// Number of round trips allowed for the handshake.
+fn default_handshake_timeout_rtt_multiplier() -> u32 {
+    20
+}
+/// Maximum number of edges kept in the routing table.
+fn default_max_routing_table_edges() -> usize {
+    200_000
+}
+fn default_tier1_connections() -> bool {
+    true
+}
+/// How often to send `KeepAlive` to a peer.
+fn default_keep_alive_interval() -> Duration {
+    Duration::from_secs(5)
+}
+/// Time without any message from a peer after which the connection is considered dead.
+fn default_keep_alive_timeout() -> Duration {
+    Duration::from_secs(20)
+}
+/// Number of the recently received routed messages remembered to drop their duplicates.
+fn default_routed_message_dedup_capacity() -> usize {
+    100_000
+}
+/// Duplicates of a routed message received within this time after it are dropped.
+fn default_routed_message_dedup_window() -> Duration {
+    Duration::from_millis(50)
+}
+
+/// Transport over which the connections with peers are established.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Tcp,
+    Quic,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Tcp
+    }
+}
+
+/// Options of the TCP sockets of the connections with peers. Options which aren't set keep
+/// the defaults of the OS.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Disable Nagle's algorithm (TCP_NODELAY), so that small messages like approvals are
+    /// sent right away instead of being coalesced with the following ones.
+    #[serde(default)]
+    pub nodelay: bool,
+    /// Size of the kernel send buffer (SO_SNDBUF), in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub send_buffer_size: Option<usize>,
+    /// Size of the kernel receive buffer (SO_RCVBUF), in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recv_buffer_size: Option<usize>,
+    /// Type of service (IP_TOS) of the outgoing packets, e.g. 184 for DSCP EF (46 << 2).
+    /// Applies only to IPv4 connections.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tos: Option<u8>,
+}
+
+/// How severely a misbehaving peer is punished.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BanSeverity {
+    /// The offense lowers the score of the peer, which recovers over time.
+    /// The peer is banned only once its score drops too low after repeated offenses.
+    Minor,
+    /// The peer is banned right away.
+    Major,
+}
+
+/// Reaction of the node to a peer misbehaving for a given `ReasonForBan`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BanRule {
+    pub severity: BanSeverity,
+    /// Duration of the ban.
+    pub duration: Duration,
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Config {
@@ -51,9 +153,12 @@ pub struct Config {
     /// If empty, will use the same port as the addr, and will introspect on the listener.
     pub external_address: String,
     /// Comma separated list of nodes to connect to.
+    /// An entry of the form dns:<domain> refers to the nodes listed in the TXT records
+    /// of the domain, in the same format, which are resolved periodically.
     /// Examples:
     ///   ed25519:86EtEy7epneKyrcJwSWP7zsisTkfDRH5CFVszt4qiQYw@31.192.22.209:24567
     ///   ed25519:86EtEy7epneKyrcJwSWP7zsisTkfDRH5CFVszt4qiQYw@nearnode.com:24567
+    ///   dns:boot.nearnode.com
     pub boot_nodes: String,
     /// Comma separated list of whitelisted nodes. Inbound connections from the nodes on
     /// the whitelist are accepted even if the limit of the inbound connection has been reached.
@@ -86,24 +191,179 @@ pub struct Config {
     /// if we are an archival node.
     #[serde(default = "default_archival_peer_connections_lower_bound")]
     pub archival_peer_connections_lower_bound: u32,
-    /// Handshake timeout.
+    /// Maximum time for a connection to complete the handshake, and the time given to the
+    /// connections whose round-trip time isn't known.
     pub handshake_timeout: Duration,
+    /// A connection has `handshake_timeout_base` plus `handshake_timeout_rtt_multiplier` times
+    /// its round-trip time, measured when it is established, to complete the handshake, but no
+    /// more than `handshake_timeout`. So peers far away aren't dropped in the middle of a
+    /// healthy handshake, while nearby hosts stalling the handshake are cut off sooner.
+    #[serde(default = "default_handshake_timeout_base")]
+    pub handshake_timeout_base: Duration,
+    /// See `handshake_timeout_base`.
+    #[serde(default = "default_handshake_timeout_rtt_multiplier")]
+    pub handshake_timeout_rtt_multiplier: u32,
     /// Duration before trying to reconnect to a peer.
     pub reconnect_delay: Duration,
     /// Skip waiting for peers before starting node.
     pub skip_sync_wait: bool,
     /// Ban window for peers who misbehave.
     pub ban_window: Duration,
+    /// Overrides of the severity and the ban duration of the reasons for ban, e.g.
+    /// `{"InvalidRoutedResponse": {"severity": "major", "duration": {"secs": 600, "nanos": 0}}}`.
+    /// By default the offenses which honest peers might commit because of a bug or a transient
+    /// condition are minor, and the rest are major and banned for `ban_window`.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub ban_rules: HashMap<ReasonForBan, BanRule>,
     /// List of addresses that will not be accepted as valid neighbors.
-    /// It can be IP:Port or IP (to blacklist all connections coming from this address).
+    /// It can be IP:Port or IP (to blacklist all connections coming from this address),
+    /// a network in CIDR notation (e.g. 192.0.2.0/24) or a peer id.
+    /// Can be updated at runtime via the admin RPC, the updates take precedence over the config.
     #[serde(default)]
     pub blacklist: Vec<String>,
+    /// Exceptions from the blacklist, in the same format.
+    /// An allowed address is exempt from the blacklisted networks,
+    /// an allowed peer id is exempt from the blacklisted peer ids.
+    #[serde(default)]
+    pub allowlist: Vec<String>,
     /// Time to persist Accounts Id in the router without removing them in seconds.
     #[serde(default = "default_ttl_account_id_router")]
     pub ttl_account_id_router: Duration,
     /// Period to check on peer status
     #[serde(default = "default_peer_stats_period")]
     pub peer_stats_period: Duration,
+    /// Encrypt connections with peers which support encryption.
+    /// Connections with peers which don't support it remain unencrypted.
+    #[serde(default)]
+    pub encrypt_connections: bool,
+    /// Transport used for the connections with peers: "tcp" or "quic".
+    /// With "quic" the node additionally accepts QUIC connections on the UDP port of `addr`
+    /// and dials peers over QUIC, falling back to TCP if that fails.
+    #[serde(default)]
+    pub transport: Transport,
+    /// Limit of the outbound bandwidth of a single connection, in bytes per second.
+    /// Applies only to sync traffic (blocks, headers, state parts), so that a syncing peer
+    /// can't starve consensus messages. Unlimited if not set.
+    #[serde(default)]
+    pub peer_send_limit_bytes_per_sec: Option<u64>,
+    /// Limit of the outbound bandwidth of all the connections together, in bytes per second.
+    /// Applies to the same traffic as `peer_send_limit_bytes_per_sec`. Unlimited if not set.
+    #[serde(default)]
+    pub total_send_limit_bytes_per_sec: Option<u64>,
+    /// Maximum number of inbound connections accepted from a single IP per minute.
+    /// Connections from whitelisted nodes are not limited.
+    #[serde(default = "default_max_inbound_connections_per_ip_per_minute")]
+    pub max_inbound_connections_per_ip_per_minute: u32,
+    /// Maximum number of inbound connections from a single IP which haven't completed
+    /// the handshake yet. Connections from whitelisted nodes are not limited.
+    #[serde(default = "default_max_pending_handshakes_per_ip")]
+    pub max_pending_handshakes_per_ip: u32,
+    /// Maximum number of inbound connections from all the IPs together which haven't
+    /// completed the handshake yet, so that many hosts can't exhaust the resources of the
+    /// node by opening connections and never completing the handshake. Connections from
+    /// whitelisted nodes are not limited.
+    #[serde(default = "default_max_pending_handshakes")]
+    pub max_pending_handshakes: u32,
+    /// Inbound connections on which no complete frame has been received within this time
+    /// are closed. No thread is spawned for a connection before its first frame arrives.
+    #[serde(default = "default_first_frame_timeout")]
+    pub first_frame_timeout: Duration,
+    /// If non-zero, an IP exceeding `max_inbound_connections_per_ip_per_minute` is not
+    /// rejected outright, but has to attach a proof of work of this many bits to the
+    /// handshake. Peers using borsh encoding can't provide it and are rejected.
+    #[serde(default)]
+    pub handshake_pow_difficulty: u8,
+    /// Forward the port of `addr` on the local router with UPnP or NAT-PMP at startup,
+    /// and advertise the external port to peers. Requires neard built with the
+    /// `port_mapping` feature; ignored with a warning otherwise.
+    #[serde(default)]
+    pub port_mapping: bool,
+    /// Record all the frames received from peers to this file, relative to the home
+    /// directory, for debugging. The recording grows quickly, so it should only be enabled
+    /// while reproducing an issue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record_frames: Option<std::path::PathBuf>,
+    /// Additional listening sockets besides `addr`, each with its own policy for the inbound
+    /// connections, e.g. a private interface shared with other validators and a public one
+    /// with stricter rate limits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<Listener>,
+    /// Maximum number of edges kept in the routing table. Above it, the least recently
+    /// updated edges are pruned, except for the edges of this node.
+    #[serde(default = "default_max_routing_table_edges")]
+    pub max_routing_table_edges: usize,
+    /// If this node is a validator, maintain direct connections with all the block and
+    /// chunk producers of the current and the next epoch, so that approvals and chunk parts
+    /// don't have to be routed through other peers.
+    #[serde(default = "default_tier1_connections")]
+    pub tier1_connections: bool,
+    /// Maximum number of connected peers in a single IPv4 /24 or IPv6 /48 subnet, inbound
+    /// and outbound together, so that an outage of one hosting provider can't partition the
+    /// node. Whitelisted nodes and block and chunk producers are not limited. Unlimited if
+    /// not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_peers_per_subnet: Option<u32>,
+    /// Maximum number of connected peers in a single autonomous system, like
+    /// `max_peers_per_subnet`. Requires `asn_database`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_peers_per_asn: Option<u32>,
+    /// Database of the IP ranges of autonomous systems, relative to the home directory, in
+    /// the TSV format of https://iptoasn.com, e.g. `ip2asn-combined.tsv`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn_database: Option<std::path::PathBuf>,
+    /// How often to send a keep-alive message to each peer.
+    #[serde(default = "default_keep_alive_interval")]
+    pub keep_alive_interval: Duration,
+    /// Connections on which nothing has been received for this long are closed, so that a
+    /// peer which stopped responding, e.g. because its VM was suspended, frees its slot
+    /// without waiting for the TCP timeouts. Applies only to peers which send keep-alive
+    /// messages themselves.
+    #[serde(default = "default_keep_alive_timeout")]
+    pub keep_alive_timeout: Duration,
+    /// Talk to peers only in the protobuf encoding: refuse Borsh-encoded handshakes and
+    /// don't send every message in both encodings until the encoding of a new peer is
+    /// known. Peers which support only Borsh can't connect to this node, and this node
+    /// can't connect to them.
+    #[serde(default)]
+    pub protobuf_only: bool,
+    /// Keys allowed to sign the advisories for node operators broadcast in the network of
+    /// this chain, e.g. "security release available, upgrade before epoch X". Advisories
+    /// signed by other keys are dropped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub advisory_keys: Vec<near_crypto::PublicKey>,
+    /// Drop the advisories instead of logging them, showing them in `/status` and relaying
+    /// them to peers.
+    #[serde(default)]
+    pub ignore_advisories: bool,
+    /// Number of the recently received routed messages, e.g. forwarded transactions and
+    /// chunk parts, remembered by the node so that their duplicates received from any peer
+    /// are dropped before being deserialized. The least recently received are forgotten.
+    #[serde(default = "default_routed_message_dedup_capacity")]
+    pub routed_message_dedup_capacity: usize,
+    /// Duplicates of a routed message received within this time after it are dropped.
+    /// Duplicates received later are processed, as the author may resend a message.
+    #[serde(default = "default_routed_message_dedup_window")]
+    pub routed_message_dedup_window: Duration,
+    /// Save the recently received routed messages in the database on shutdown and load
+    /// them on startup, so that their duplicates are dropped after a restart too. Useful
+    /// only with a `routed_message_dedup_window` longer than a restart.
+    #[serde(default)]
+    pub persist_routed_message_dedup: bool,
+    /// Options of the TCP sockets of the connections with peers, for tuning the latency of
+    /// the node, e.g. `{"nodelay": true, "tos": 184}`. Don't apply to QUIC connections.
+    #[serde(default)]
+    pub socket_options: SocketOptions,
+    /// Fraction of `max_num_peers` reserved for the inbound peers which track some of the
+    /// shards this node tracks. Other inbound peers are rejected once only the reserved
+    /// slots are left, and when all the slots are taken an inbound peer tracking none of our
+    /// shards is disconnected to make room for one which does. 0 disables the reservation.
+    #[serde(default)]
+    pub inbound_tracked_shards_ratio: f64,
+    /// Approvals and chunk parts addressed to a validator which the node has no route to are
+    /// kept for this long and sent once the routing table changes and a route appears,
+    /// instead of being dropped right away. Not set by default, which disables the queue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delayed_delivery_ttl: Option<Duration>,
 
     /// List of the public addresses (IP:port) of this node. Useful only if this node is a validator.
     /// This list will be signed and broadcasted to the whole network, so that everyone
@@ -151,12 +411,45 @@ impl Default for Config {
             safe_set_size: default_safe_set_size(),
             archival_peer_connections_lower_bound: default_archival_peer_connections_lower_bound(),
             handshake_timeout: Duration::from_secs(20),
+            handshake_timeout_base: default_handshake_timeout_base(),
+            handshake_timeout_rtt_multiplier: default_handshake_timeout_rtt_multiplier(),
             reconnect_delay: Duration::from_secs(60),
             skip_sync_wait: false,
             ban_window: Duration::from_secs(3 * 60 * 60),
+            ban_rules: HashMap::new(),
             blacklist: vec![],
+            allowlist: vec![],
             ttl_account_id_router: default_ttl_account_id_router(),
             peer_stats_period: default_peer_stats_period(),
+            encrypt_connections: false,
+            transport: Transport::Tcp,
+            peer_send_limit_bytes_per_sec: None,
+            total_send_limit_bytes_per_sec: None,
+            max_inbound_connections_per_ip_per_minute:
+                default_max_inbound_connections_per_ip_per_minute(),
+            max_pending_handshakes_per_ip: default_max_pending_handshakes_per_ip(),
+            max_pending_handshakes: default_max_pending_handshakes(),
+            first_frame_timeout: default_first_frame_timeout(),
+            handshake_pow_difficulty: 0,
+            port_mapping: false,
+            record_frames: None,
+            listeners: vec![],
+            max_routing_table_edges: default_max_routing_table_edges(),
+            tier1_connections: default_tier1_connections(),
+            max_peers_per_subnet: None,
+            max_peers_per_asn: None,
+            asn_database: None,
+            keep_alive_interval: default_keep_alive_interval(),
+            keep_alive_timeout: default_keep_alive_timeout(),
+            protobuf_only: false,
+            advisory_keys: vec![],
+            ignore_advisories: false,
+            routed_message_dedup_capacity: default_routed_message_dedup_capacity(),
+            routed_message_dedup_window: default_routed_message_dedup_window(),
+            persist_routed_message_dedup: false,
+            socket_options: SocketOptions::default(),
+            inbound_tracked_shards_ratio: 0.,
+            delayed_delivery_ttl: None,
             public_addrs: vec![],
             trusted_stun_servers: vec![],
         }
@@ -164,3 +457,22 @@ impl Default for Config {
 }
 
 impl Config {}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Listener {
+    /// Address to listen on (IP:port).
+    pub addr: String,
+    /// Accept only the IPs of `whitelist_nodes`, and of `allowlist`.
+    #[serde(default)]
+    pub whitelist_only: bool,
+    /// Addresses accepted by this listener: IP, IP:port or CIDR. If not empty, the listener
+    /// accepts only these, and the whitelisted nodes with `whitelist_only`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowlist: Vec<String>,
+    /// Overrides `max_inbound_connections_per_ip_per_minute` for this listener.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_inbound_connections_per_ip_per_minute: Option<u32>,
+    /// Overrides `max_pending_handshakes_per_ip` for this listener.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pending_handshakes_per_ip: Option<u32>,
+}
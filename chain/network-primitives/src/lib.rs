@@ -1,4 +1,5 @@
 mod blacklist;
+pub mod chaos;
 pub(crate) mod config;
 pub(crate) mod config_json;
 mod network_protocol;
@@ -16,7 +16,9 @@ use near_primitives::sharding::{
 };
 use near_primitives::syncing::{ShardStateSyncResponse, ShardStateSyncResponseV1};
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{AccountId, BlockHeight, BlockReference, ShardId};
+use near_primitives::types::{
+    AccountId, BlockHeight, BlockReference, EpochHeight, ProtocolVersion, ShardId,
+};
 use near_primitives::views::{FinalExecutionOutcomeView, QueryRequest, QueryResponse};
 use std::collections::HashSet;
 use std::fmt;
@@ -169,6 +171,16 @@ impl From<PeerChainInfo> for PeerChainInfoV2 {
     }
 }
 
+/// Notice that the network switches to `protocol_version` at the start of the epoch with
+/// height `epoch_height`, after which nodes running older protocol versions can neither
+/// follow the chain nor connect to upgraded peers.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProtocolDeprecation {
+    pub protocol_version: ProtocolVersion,
+    pub epoch_height: EpochHeight,
+}
+
 /// Test code that someone become part of our protocol?
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(BorshSerialize, BorshDeserialize, PartialEq, Eq, Clone, Debug, Hash)]
@@ -498,6 +510,13 @@ impl StateResponseInfo {
         }
     }
 
+    pub fn part_id(&self) -> Option<u64> {
+        match self {
+            Self::V1(info) => info.state_response.part_id(),
+            Self::V2(info) => info.state_response.part_id(),
+        }
+    }
+
     pub fn take_state_response(self) -> ShardStateSyncResponse {
         match self {
             Self::V1(info) => ShardStateSyncResponse::V1(info.state_response),
@@ -17,7 +17,7 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::syncing::{EpochSyncFinalizationResponse, EpochSyncResponse};
 use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
-use near_primitives::types::{AccountId, BlockHeight, EpochId, ShardId};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, NumShards, ShardId};
 use near_primitives::views::{FinalExecutionOutcomeView, QueryResponse};
 use serde::Serialize;
 use std::fmt::Debug;
@@ -28,14 +28,15 @@ use tokio::net::TcpStream;
 /// Exported types, which are part of network protocol.
 pub use crate::network_protocol::{
     PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
-    PeerChainInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, Ping, Pong, RoutedMessage,
-    RoutedMessageBody, RoutedMessageV2, StateResponseInfo, StateResponseInfoV1,
+    PeerChainInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, Ping, Pong, ProtocolDeprecation,
+    RoutedMessage, RoutedMessageBody, RoutedMessageV2, StateResponseInfo, StateResponseInfoV1,
     StateResponseInfoV2,
 };
 
-pub use crate::blacklist::{Blacklist, Entry as BlacklistEntry};
-pub use crate::config::{NetworkConfig, ValidatorConfig, ValidatorEndpoints};
+pub use crate::blacklist::{AccessList, AccessRule, Blacklist, Entry as BlacklistEntry};
+pub use crate::config::{ListenerConfig, NetworkConfig, ValidatorConfig, ValidatorEndpoints};
 pub use crate::config_json::Config as ConfigJSON;
+pub use crate::config_json::{BanRule, BanSeverity, SocketOptions, Transport};
 pub use crate::network_protocol::edge::{Edge, EdgeState, PartialEdgeInfo};
 
 /// Number of hops a message is allowed to travel before being dropped.
@@ -186,6 +187,9 @@ impl KnownPeerState {
 pub struct InboundTcpConnect {
     /// Tcp stream of the inbound connections
     pub stream: TcpStream,
+    /// Index in `NetworkConfig::listeners` of the listener which accepted the connection,
+    /// None for `NetworkConfig::node_addr`.
+    pub listener: Option<usize>,
 }
 
 #[cfg(feature = "deepsize_feature")]
@@ -197,8 +201,8 @@ impl deepsize::DeepSizeOf for InboundTcpConnect {
 
 impl InboundTcpConnect {
     /// Method to create a new InboundTcpConnect message from a TCP stream
-    pub fn new(stream: TcpStream) -> InboundTcpConnect {
-        InboundTcpConnect { stream }
+    pub fn new(stream: TcpStream, listener: Option<usize>) -> InboundTcpConnect {
+        InboundTcpConnect { stream, listener }
     }
 }
 
@@ -212,8 +216,20 @@ pub struct OutboundTcpConnect {
 }
 
 /// Ban reason.
+/// How the node reacts to each reason is configured by `NetworkConfig::ban_rule`.
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
-#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq, Copy)]
+#[derive(
+    BorshSerialize,
+    BorshDeserialize,
+    Serialize,
+    serde::Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    Copy,
+)]
 pub enum ReasonForBan {
     None = 0,
     BadBlock = 1,
@@ -230,6 +246,8 @@ pub enum ReasonForBan {
     EpochSyncInvalidResponse = 12,
     EpochSyncInvalidFinalizationResponse = 13,
     Blacklisted = 14,
+    InvalidRoutedResponse = 15,
+    InvalidChainInfo = 16,
 }
 
 /// Banning signal sent from Peer instance to PeerManager
@@ -313,6 +331,8 @@ pub enum NetworkViewClientMessages {
     EpochSyncFinalizationRequest { epoch_id: EpochId },
     /// Get Chain information from Client.
     GetChainInfo,
+    /// Get the block and chunk producers of the current and the next epoch.
+    GetTier1Accounts,
     /// Account announcements that needs to be validated before being processed.
     /// They are paired with last epoch id known to this announcement, in order to accept only
     /// newer announcements.
@@ -335,13 +355,23 @@ pub enum NetworkViewClientResponses {
     ChainInfo {
         genesis_id: GenesisId,
         height: BlockHeight,
+        /// Height of the header head, the highest header this node verified.
+        header_height: BlockHeight,
         tracked_shards: Vec<ShardId>,
         archival: bool,
+        /// Number of shards in the epoch of `height`, used to validate the shards tracked by
+        /// the peers. None if unknown.
+        num_shards: Option<NumShards>,
+        /// Switch to a newer protocol version at the start of the next epoch, if the
+        /// validators voted for one.
+        protocol_upgrade: Option<ProtocolDeprecation>,
     },
     /// Response to state request.
     StateResponse(Box<StateResponseInfo>),
     /// Valid announce accounts.
     AnnounceAccount(Vec<AnnounceAccount>),
+    /// Block and chunk producers of the current and the next epoch.
+    Tier1Accounts(Vec<AccountId>),
     /// A response to a request for a light client block during Epoch Sync
     EpochSyncResponse(Box<EpochSyncResponse>),
     /// A response to a request for headers and proofs during Epoch Sync
@@ -0,0 +1,93 @@
+//! Benchmarks of the receive path of large messages: decoding a frame from the read buffer
+//! and parsing the message in it, with the frame passed as a slice of the read buffer versus
+//! copied to a `Vec` first.  Besides the time, the bytes allocated per message are printed.
+#[macro_use]
+extern crate criterion;
+
+use bytesize::MIB;
+use criterion::{black_box, BatchSize, Criterion};
+use near_crypto::{KeyType, SecretKey};
+use near_network::test_utils::{decode_frame, encode_frame};
+use near_network::types::{Encoding, PeerMessage};
+use near_network_primitives::types::{
+    AccountOrPeerIdOrHash, RawRoutedMessage, RoutedMessageBody, StateResponseInfo,
+    StateResponseInfoV2,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::syncing::{ShardStateSyncResponse, ShardStateSyncResponseV2};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Allocator counting the allocated bytes.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Routed state response carrying a state part of `part_size` bytes.
+fn make_state_part_message(part_size: usize) -> PeerMessage {
+    let secret_key = SecretKey::from_seed(KeyType::ED25519, "author");
+    let author = PeerId::new(secret_key.public_key());
+    let body =
+        RoutedMessageBody::VersionedStateResponse(StateResponseInfo::V2(StateResponseInfoV2 {
+            shard_id: 0,
+            sync_hash: CryptoHash::default(),
+            state_response: ShardStateSyncResponse::V2(ShardStateSyncResponseV2 {
+                header: None,
+                part: Some((0, vec![7; part_size])),
+            }),
+        }));
+    let target = AccountOrPeerIdOrHash::PeerId(author.clone());
+    PeerMessage::Routed(RawRoutedMessage { target, body }.sign(author, &secret_key, 1, None))
+}
+
+fn receive_state_part(c: &mut Criterion) {
+    for part_size in [MIB as usize, 16 * MIB as usize] {
+        let msg = make_state_part_message(part_size);
+        for enc in [Encoding::Proto, Encoding::Borsh] {
+            let frame = encode_frame(&msg, enc);
+            for (name, copy) in [("copy", true), ("slice", false)] {
+                let name = format!("receive_state_part_{}MiB_{:?}_{}", part_size >> 20, enc, name);
+                let mut buf = frame.clone();
+                let before = ALLOCATED.load(Ordering::Relaxed);
+                let decoded = decode_frame(&mut buf, enc, copy);
+                let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+                assert_eq!(decoded, msg);
+                println!(
+                    "{}: {:.2} bytes allocated per byte of the part",
+                    name,
+                    allocated as f64 / part_size as f64
+                );
+                c.bench_function(&name, |bench| {
+                    bench.iter_batched(
+                        || frame.clone(),
+                        |mut buf| black_box(decode_frame(&mut buf, enc, copy)),
+                        BatchSize::LargeInput,
+                    )
+                });
+            }
+        }
+    }
+}
+
+criterion_group!(benches, receive_state_part);
+criterion_main!(benches);
@@ -2,6 +2,8 @@ fn main() -> anyhow::Result<()> {
     println!("cargo:rerun-if-changed=src/network_protocol/network.proto");
     protobuf_codegen::Codegen::new()
         .pure()
+        // Decode the `bytes` fields as slices of the received frame rather than copying them.
+        .customize(protobuf_codegen::Customize::default().tokio_bytes(true))
         .includes(&["src/"])
         .input("src/network_protocol/network.proto")
         .cargo_out_dir("proto")
@@ -1,3 +1,4 @@
+pub use crate::peer::recorder::{read_frames, RecordedFrame};
 pub use crate::peer_manager::peer_manager_actor::{Event, PeerManagerActor};
 pub use crate::peer_manager::peer_store::iter_peers_from_store;
 #[cfg(feature = "test_features")]
@@ -10,6 +11,7 @@ pub(crate) mod private_actix;
 pub mod routing;
 pub(crate) mod stats;
 pub(crate) mod store;
+pub(crate) mod transport;
 pub mod types;
 
 pub mod test_utils;
@@ -5,7 +5,7 @@
 /// We need to maintain backwards compatibility, all changes to this file needs to be reviews.
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_network_primitives::types::{
-    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerInfo, RoutedMessage,
+    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerInfo, ProtocolDeprecation, RoutedMessage,
 };
 use near_primitives::block::{Block, BlockHeader, GenesisId};
 use near_primitives::challenge::Challenge;
@@ -93,6 +93,7 @@ pub enum HandshakeFailureReason {
     ProtocolVersionMismatch { version: u32, oldest_supported_version: u32 },
     GenesisMismatch(GenesisId),
     InvalidTarget,
+    ProofOfWorkRequired { difficulty: u8 },
 }
 const _: () = assert!(
     std::mem::size_of::<HandshakeFailureReason>() <= 64,
@@ -107,6 +108,9 @@ impl fmt::Display for HandshakeFailureReason {
 
 impl std::error::Error for HandshakeFailureReason {}
 
+/// Index of `PeerMessage::Routed`, i.e. the first byte of its encoding.
+pub(super) const ROUTED_MESSAGE_TAG: u8 = 13;
+
 /// Warning, position of each message type in this enum defines the protocol due to serialization.
 /// DO NOT MOVE, REORDER, DELETE items from the list. Only add new items to the end.
 /// If need to remove old items - replace with `None`.
@@ -146,6 +150,27 @@ pub(super) enum PeerMessage {
     EpochSyncFinalizationResponse(Box<EpochSyncFinalizationResponse>),
 
     _RoutingTableSyncV2,
+
+    EncryptionHandshake(Vec<u8>),
+    Encrypted(Vec<u8>),
+
+    AddressChallenge {
+        verifier: PeerId,
+        target: PeerId,
+        nonce: u64,
+    },
+    AddressProof(u64),
+    AddressChallengeRequest,
+    ProtocolDeprecation(ProtocolDeprecation),
+    KeepAlive,
+    NetworkAdvisory {
+        signer: near_crypto::PublicKey,
+        chain_id: String,
+        nonce: u64,
+        timestamp: u64,
+        message: String,
+        signature: near_crypto::Signature,
+    },
 }
 #[cfg(target_arch = "x86_64")] // Non-x86_64 doesn't match this requirement yet but it's not bad as it's not production-ready
 const _: () = assert!(std::mem::size_of::<PeerMessage>() <= 1144, "PeerMessage > 1144 bytes");
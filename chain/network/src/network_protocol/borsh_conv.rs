@@ -14,6 +14,9 @@ impl From<&net::Handshake> for mem::Handshake {
             sender_listen_port: x.sender_listen_port,
             sender_chain_info: x.sender_chain_info.clone(),
             partial_edge_info: x.partial_edge_info.clone(),
+            sender_encryption_signature: None,
+            proof_of_work: 0,
+            sender_time: None,
         }
     }
 }
@@ -50,6 +53,9 @@ impl From<&net::HandshakeFailureReason> for mem::HandshakeFailureReason {
             net::HandshakeFailureReason::InvalidTarget => {
                 mem::HandshakeFailureReason::InvalidTarget
             }
+            net::HandshakeFailureReason::ProofOfWorkRequired { difficulty } => {
+                mem::HandshakeFailureReason::ProofOfWorkRequired { difficulty: *difficulty }
+            }
         }
     }
 }
@@ -70,6 +76,9 @@ impl From<&mem::HandshakeFailureReason> for net::HandshakeFailureReason {
             mem::HandshakeFailureReason::InvalidTarget => {
                 net::HandshakeFailureReason::InvalidTarget
             }
+            mem::HandshakeFailureReason::ProofOfWorkRequired { difficulty } => {
+                net::HandshakeFailureReason::ProofOfWorkRequired { difficulty: *difficulty }
+            }
         }
     }
 }
@@ -98,10 +107,10 @@ pub enum ParsePeerMessageError {
     DeprecatedRoutingTableSyncV2,
 }
 
-impl TryFrom<&net::PeerMessage> for mem::PeerMessage {
+impl TryFrom<net::PeerMessage> for mem::PeerMessage {
     type Error = ParsePeerMessageError;
-    fn try_from(x: &net::PeerMessage) -> Result<Self, Self::Error> {
-        Ok(match x.clone() {
+    fn try_from(x: net::PeerMessage) -> Result<Self, Self::Error> {
+        Ok(match x {
             net::PeerMessage::Handshake(h) => mem::PeerMessage::Handshake((&h).into()),
             net::PeerMessage::HandshakeFailure(pi, hfr) => {
                 mem::PeerMessage::HandshakeFailure(pi, (&hfr).into())
@@ -113,7 +122,7 @@ impl TryFrom<&net::PeerMessage> for mem::PeerMessage {
             net::PeerMessage::RequestUpdateNonce(e) => mem::PeerMessage::RequestUpdateNonce(e),
             net::PeerMessage::ResponseUpdateNonce(e) => mem::PeerMessage::ResponseUpdateNonce(e),
             net::PeerMessage::PeersRequest => mem::PeerMessage::PeersRequest,
-            net::PeerMessage::PeersResponse(pis) => mem::PeerMessage::PeersResponse(pis),
+            net::PeerMessage::PeersResponse(pis) => mem::PeerMessage::PeersResponse(pis, vec![]),
             net::PeerMessage::BlockHeadersRequest(bhs) => {
                 mem::PeerMessage::BlockHeadersRequest(bhs)
             }
@@ -140,6 +149,34 @@ impl TryFrom<&net::PeerMessage> for mem::PeerMessage {
             net::PeerMessage::_RoutingTableSyncV2 => {
                 return Err(Self::Error::DeprecatedRoutingTableSyncV2)
             }
+            net::PeerMessage::EncryptionHandshake(p) => mem::PeerMessage::EncryptionHandshake(p),
+            net::PeerMessage::Encrypted(p) => mem::PeerMessage::Encrypted(p.into()),
+            net::PeerMessage::AddressChallenge { verifier, target, nonce } => {
+                mem::PeerMessage::AddressChallenge(mem::AddressChallenge {
+                    verifier,
+                    target,
+                    nonce,
+                })
+            }
+            net::PeerMessage::AddressProof(nonce) => mem::PeerMessage::AddressProof(nonce),
+            net::PeerMessage::AddressChallengeRequest => mem::PeerMessage::AddressChallengeRequest,
+            net::PeerMessage::ProtocolDeprecation(d) => mem::PeerMessage::ProtocolDeprecation(d),
+            net::PeerMessage::KeepAlive => mem::PeerMessage::KeepAlive,
+            net::PeerMessage::NetworkAdvisory {
+                signer,
+                chain_id,
+                nonce,
+                timestamp,
+                message,
+                signature,
+            } => mem::PeerMessage::NetworkAdvisory(mem::NetworkAdvisory {
+                signer,
+                chain_id,
+                nonce,
+                timestamp,
+                message,
+                signature,
+            }),
         })
     }
 }
@@ -158,7 +195,7 @@ impl From<&mem::PeerMessage> for net::PeerMessage {
             mem::PeerMessage::RequestUpdateNonce(e) => net::PeerMessage::RequestUpdateNonce(e),
             mem::PeerMessage::ResponseUpdateNonce(e) => net::PeerMessage::ResponseUpdateNonce(e),
             mem::PeerMessage::PeersRequest => net::PeerMessage::PeersRequest,
-            mem::PeerMessage::PeersResponse(pis) => net::PeerMessage::PeersResponse(pis),
+            mem::PeerMessage::PeersResponse(pis, _) => net::PeerMessage::PeersResponse(pis),
             mem::PeerMessage::BlockHeadersRequest(bhs) => {
                 net::PeerMessage::BlockHeadersRequest(bhs)
             }
@@ -179,6 +216,25 @@ impl From<&mem::PeerMessage> for net::PeerMessage {
             mem::PeerMessage::EpochSyncFinalizationResponse(esfr) => {
                 net::PeerMessage::EpochSyncFinalizationResponse(esfr)
             }
+            mem::PeerMessage::EncryptionHandshake(p) => net::PeerMessage::EncryptionHandshake(p),
+            mem::PeerMessage::Encrypted(p) => net::PeerMessage::Encrypted(p.to_vec()),
+            mem::PeerMessage::AddressChallenge(c) => net::PeerMessage::AddressChallenge {
+                verifier: c.verifier,
+                target: c.target,
+                nonce: c.nonce,
+            },
+            mem::PeerMessage::AddressProof(nonce) => net::PeerMessage::AddressProof(nonce),
+            mem::PeerMessage::AddressChallengeRequest => net::PeerMessage::AddressChallengeRequest,
+            mem::PeerMessage::ProtocolDeprecation(d) => net::PeerMessage::ProtocolDeprecation(*d),
+            mem::PeerMessage::KeepAlive => net::PeerMessage::KeepAlive,
+            mem::PeerMessage::NetworkAdvisory(a) => net::PeerMessage::NetworkAdvisory {
+                signer: a.signer,
+                chain_id: a.chain_id,
+                nonce: a.nonce,
+                timestamp: a.timestamp,
+                message: a.message,
+                signature: a.signature,
+            },
         }
     }
 }
@@ -15,9 +15,11 @@ mod _proto {
 pub use _proto::network as proto;
 
 use ::borsh::{BorshDeserialize as _, BorshSerialize as _};
+use bytes::Bytes;
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerInfo, RoutedMessageBody, RoutedMessageV2,
+    Edge, PartialEdgeInfo, PeerChainInfoV2, PeerIdOrHash, PeerInfo, ProtocolDeprecation,
+    RoutedMessage, RoutedMessageBody, RoutedMessageV2,
 };
 use near_primitives::block::{Block, BlockHeader, GenesisId};
 use near_primitives::challenge::Challenge;
@@ -103,6 +105,17 @@ pub struct Handshake {
     pub(crate) sender_chain_info: PeerChainInfoV2,
     /// Represents new `edge`. Contains only `none` and `Signature` from the sender.
     pub(crate) partial_edge_info: PartialEdgeInfo,
+    /// Signature of `encryption_hash` by the sender, present iff the sender is willing to
+    /// encrypt the connection. It binds the willingness to the connection, so that it can't
+    /// be forged by a man in the middle.
+    /// Can be advertised only in proto encoding, so it is always None in a borsh Handshake.
+    pub(crate) sender_encryption_signature: Option<near_crypto::Signature>,
+    /// Nonce solving the proof of work required by the receiver, 0 if none was required.
+    /// Can be sent only in proto encoding, so it is always 0 in a borsh Handshake.
+    pub(crate) proof_of_work: u64,
+    /// Time of sending the handshake, according to the clock of the sender.
+    /// Can be sent only in proto encoding, so it is always None in a borsh Handshake.
+    pub(crate) sender_time: Option<time::Utc>,
 }
 
 impl Handshake {
@@ -113,8 +126,9 @@ impl Handshake {
         listen_port: Option<u16>,
         chain_info: PeerChainInfoV2,
         partial_edge_info: PartialEdgeInfo,
+        encryption_key: Option<&near_crypto::SecretKey>,
     ) -> Self {
-        Handshake {
+        let mut handshake = Handshake {
             protocol_version: version,
             oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
             sender_peer_id: peer_id,
@@ -122,8 +136,139 @@ impl Handshake {
             sender_listen_port: listen_port,
             sender_chain_info: chain_info,
             partial_edge_info,
+            sender_encryption_signature: None,
+            proof_of_work: 0,
+            sender_time: None,
+        };
+        handshake.sender_encryption_signature =
+            encryption_key.map(|key| key.sign(handshake.encryption_hash().as_ref()));
+        handshake
+    }
+
+    /// Hash signed by the sender to advertise that it is willing to encrypt the connection.
+    /// It commits to both peer ids and the edge nonce, so that a signature can't be reused
+    /// for another connection.
+    fn encryption_hash(&self) -> CryptoHash {
+        CryptoHash::hash_borsh(&(
+            "encryption",
+            &self.sender_peer_id,
+            &self.target_peer_id,
+            self.partial_edge_info.nonce,
+        ))
+    }
+
+    /// Whether the sender is willing to encrypt the connection.
+    pub(crate) fn sender_supports_encryption(&self) -> bool {
+        self.sender_encryption_signature.is_some()
+    }
+
+    /// Whether `sender_encryption_signature`, if present, is a valid signature of the sender.
+    pub(crate) fn verify_encryption_signature(&self) -> bool {
+        self.sender_encryption_signature.as_ref().map_or(true, |signature| {
+            signature.verify(self.encryption_hash().as_ref(), self.sender_peer_id.public_key())
+        })
+    }
+
+    /// Hash which has to start with `difficulty` zero bits for `nonce` to be a valid
+    /// proof of work. It commits to both peer ids and the edge nonce, so that a proof
+    /// can't be reused for another connection.
+    fn proof_of_work_hash(&self, nonce: u64) -> CryptoHash {
+        CryptoHash::hash_borsh(&(
+            &self.sender_peer_id,
+            &self.target_peer_id,
+            self.partial_edge_info.nonce,
+            nonce,
+        ))
+    }
+
+    /// Finds a proof of work of the given difficulty (in bits) and sets `proof_of_work`.
+    /// Takes `2^difficulty` hashes on average.
+    pub(crate) fn solve_proof_of_work(&mut self, difficulty: u8) {
+        self.proof_of_work = (1..)
+            .find(|nonce| leading_zero_bits(&self.proof_of_work_hash(*nonce)) >= difficulty as u32)
+            .unwrap();
+    }
+
+    pub(crate) fn verify_proof_of_work(&self, difficulty: u8) -> bool {
+        difficulty == 0
+            || (self.proof_of_work != 0
+                && leading_zero_bits(&self.proof_of_work_hash(self.proof_of_work))
+                    >= difficulty as u32)
+    }
+}
+
+fn leading_zero_bits(hash: &CryptoHash) -> u32 {
+    let mut bits = 0;
+    for byte in hash.as_ref() {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
         }
     }
+    bits
+}
+
+/// Observations of the sender of `PeersResponse` about a peer it is connected to.
+/// Can be sent only in proto encoding, so they are always empty in a borsh `PeersResponse`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct PeerHint {
+    pub peer_id: PeerId,
+    /// Average latency of the responses of the peer, if the sender has measured it.
+    pub latency: Option<time::Duration>,
+    /// Whether the peer keeps the full history of the chain.
+    pub archival: bool,
+}
+
+/// Sent by `verifier` as the only message of a new connection to the address which `target`
+/// advertised in its handshake. If `target` is listening at that address, it proves it by
+/// sending `AddressProof(nonce)` back to `verifier` over their existing connection.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AddressChallenge {
+    pub verifier: PeerId,
+    pub target: PeerId,
+    pub nonce: u64,
+}
+
+/// Notice for node operators, e.g. "security release available, upgrade before epoch X",
+/// signed by one of the advisory keys of the chain (`NetworkConfig::advisory_keys`) and
+/// flooded to all the nodes, which show it in their logs and `/status`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct NetworkAdvisory {
+    pub signer: near_crypto::PublicKey,
+    /// Id of the chain the advisory is meant for.
+    pub chain_id: String,
+    /// Increases with every advisory of the signer. Advisories with a nonce not above the
+    /// last accepted one are dropped, so that they can't be replayed.
+    pub nonce: u64,
+    /// Time of signing, as a unix timestamp in seconds.
+    pub timestamp: u64,
+    pub message: String,
+    /// Signature of `NetworkAdvisory::payload_hash`.
+    pub signature: near_crypto::Signature,
+}
+
+impl NetworkAdvisory {
+    pub fn new(
+        signer: &dyn near_crypto::Signer,
+        chain_id: String,
+        nonce: u64,
+        timestamp: u64,
+        message: String,
+    ) -> Self {
+        let hash = Self::payload_hash(&chain_id, nonce, timestamp, &message);
+        let signature = signer.sign(hash.as_ref());
+        Self { signer: signer.public_key(), chain_id, nonce, timestamp, message, signature }
+    }
+
+    /// Hash of the signed fields, independent of the encoding of the message.
+    fn payload_hash(chain_id: &str, nonce: u64, timestamp: u64, message: &str) -> CryptoHash {
+        CryptoHash::hash_borsh(&(chain_id, nonce, timestamp, message))
+    }
+
+    pub fn verify(&self) -> bool {
+        let hash = Self::payload_hash(&self.chain_id, self.nonce, self.timestamp, &self.message);
+        self.signature.verify(hash.as_ref(), &self.signer)
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, strum::IntoStaticStr)]
@@ -131,6 +276,7 @@ pub enum HandshakeFailureReason {
     ProtocolVersionMismatch { version: u32, oldest_supported_version: u32 },
     GenesisMismatch(GenesisId),
     InvalidTarget,
+    ProofOfWorkRequired { difficulty: u8 },
 }
 
 #[derive(PartialEq, Eq, Clone, Debug, strum::IntoStaticStr, strum::EnumVariantNames)]
@@ -146,7 +292,8 @@ pub enum PeerMessage {
     ResponseUpdateNonce(Edge),
 
     PeersRequest,
-    PeersResponse(Vec<PeerInfo>),
+    /// Peers known to the sender, with the sender's hints about some of them.
+    PeersResponse(Vec<PeerInfo>, Vec<PeerHint>),
 
     BlockHeadersRequest(Vec<CryptoHash>),
     BlockHeaders(Vec<BlockHeader>),
@@ -164,6 +311,25 @@ pub enum PeerMessage {
     EpochSyncResponse(Box<EpochSyncResponse>),
     EpochSyncFinalizationRequest(EpochId),
     EpochSyncFinalizationResponse(Box<EpochSyncFinalizationResponse>),
+
+    /// Message of the Noise handshake, see `peer::encryption`.
+    EncryptionHandshake(Vec<u8>),
+    /// Serialized `PeerMessage` encrypted with the keys established by the Noise handshake.
+    Encrypted(Bytes),
+
+    AddressChallenge(AddressChallenge),
+    /// Nonce of the `AddressChallenge` received at the sender's listening address.
+    AddressProof(u64),
+    /// Asks the receiver to send an `AddressChallenge` to the sender's listening address,
+    /// so that the sender learns whether it is reachable from the outside.
+    AddressChallengeRequest,
+    /// Warns the receiver that its protocol version stops being supported by the network
+    /// at the given epoch, so that its operator upgrades the node in time.
+    ProtocolDeprecation(ProtocolDeprecation),
+    /// Sent periodically to show that the connection is alive, see
+    /// `NetworkConfig::keep_alive_timeout`.
+    KeepAlive,
+    NetworkAdvisory(NetworkAdvisory),
 }
 
 impl fmt::Display for PeerMessage {
@@ -172,6 +338,40 @@ impl fmt::Display for PeerMessage {
     }
 }
 
+/// Identity of a routed message, which doesn't depend on the path the message took: its
+/// author, target, signature and the hash of its body. The key is recorded before the
+/// signature is verified, so it covers the body: a copy with a forged body doesn't make
+/// the genuine message a duplicate.
+pub(crate) type RoutedMessageKey = (PeerId, PeerIdOrHash, near_crypto::Signature, CryptoHash);
+
+pub(crate) fn routed_message_key(msg: &RoutedMessage) -> RoutedMessageKey {
+    (
+        msg.author.clone(),
+        msg.target.clone(),
+        msg.signature.clone(),
+        CryptoHash::hash_borsh(&msg.body),
+    )
+}
+
+/// Borsh encoding of the `RoutedMessage` in the protobuf `PeerMessage` `data`, if it is one.
+fn proto_routed_message_borsh(data: &[u8]) -> Option<&[u8]> {
+    // Tags of the length-delimited fields `PeerMessage.routed` and `RoutedMessage.borsh`,
+    // see network.proto.
+    const ROUTED_TAG: u32 = 17 << 3 | 2;
+    const BORSH_TAG: u32 = 1 << 3 | 2;
+    let mut input = protobuf::CodedInputStream::from_bytes(data);
+    if input.read_raw_varint32().ok()? != ROUTED_TAG {
+        return None;
+    }
+    input.read_raw_varint32().ok()?;
+    if input.read_raw_varint32().ok()? != BORSH_TAG {
+        return None;
+    }
+    let len = input.read_raw_varint32().ok()? as usize;
+    let start = input.pos() as usize;
+    data.get(start..start.checked_add(len)?)
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, strum::IntoStaticStr)]
 pub enum Encoding {
     Borsh,
@@ -202,16 +402,58 @@ impl PeerMessage {
         enc: Encoding,
         data: &[u8],
     ) -> Result<PeerMessage, ParsePeerMessageError> {
-        Ok(match enc {
-            Encoding::Borsh => (&borsh::PeerMessage::try_from_slice(data)
-                .map_err(ParsePeerMessageError::BorshDecode)?)
-                .try_into()
-                .map_err(ParsePeerMessageError::BorshConv)?,
-            Encoding::Proto => (&proto::PeerMessage::parse_from_bytes(data)
-                .map_err(ParsePeerMessageError::ProtoDecode)?)
-                .try_into()
-                .map_err(ParsePeerMessageError::ProtoConv)?,
-        })
+        match enc {
+            Encoding::Borsh => Self::deserialize_borsh(data),
+            Encoding::Proto => Self::from_proto(
+                &proto::PeerMessage::parse_from_bytes(data)
+                    .map_err(ParsePeerMessageError::ProtoDecode)?,
+            ),
+        }
+    }
+
+    /// Like `deserialize`, but the `bytes` fields of the protobuf encoding, e.g. the borsh
+    /// encoded blocks and the encrypted messages, are slices of `data` rather than copies.
+    pub(crate) fn deserialize_bytes(
+        enc: Encoding,
+        data: &Bytes,
+    ) -> Result<PeerMessage, ParsePeerMessageError> {
+        match enc {
+            Encoding::Borsh => Self::deserialize_borsh(data),
+            Encoding::Proto => Self::from_proto(
+                &proto::PeerMessage::parse_from_tokio_bytes(data)
+                    .map_err(ParsePeerMessageError::ProtoDecode)?,
+            ),
+        }
+    }
+
+    fn deserialize_borsh(data: &[u8]) -> Result<PeerMessage, ParsePeerMessageError> {
+        borsh::PeerMessage::try_from_slice(data)
+            .map_err(ParsePeerMessageError::BorshDecode)?
+            .try_into()
+            .map_err(ParsePeerMessageError::BorshConv)
+    }
+
+    fn from_proto(msg: &proto::PeerMessage) -> Result<PeerMessage, ParsePeerMessageError> {
+        msg.try_into().map_err(ParsePeerMessageError::ProtoConv)
+    }
+
+    /// Key of the routed message encoded in `data`, read without deserializing the body of
+    /// the message, which is only hashed. None if `data` is not a routed message, or if its fields are not laid
+    /// out in the order in which this node encodes them.
+    pub(crate) fn routed_message_key(enc: Encoding, data: &[u8]) -> Option<RoutedMessageKey> {
+        let mut header = match enc {
+            Encoding::Borsh => match data.split_first()? {
+                (&borsh::ROUTED_MESSAGE_TAG, rest) => rest,
+                _ => return None,
+            },
+            Encoding::Proto => proto_routed_message_borsh(data)?,
+        };
+        let target = PeerIdOrHash::deserialize(&mut header).ok()?;
+        let author = PeerId::deserialize(&mut header).ok()?;
+        let signature = near_crypto::Signature::deserialize(&mut header).ok()?;
+        let _ttl = u8::deserialize(&mut header).ok()?;
+        // The rest is the body.
+        Some((author, target, signature, CryptoHash::hash_bytes(header)))
     }
 
     pub(crate) fn msg_variant(&self) -> &'static str {
@@ -82,7 +82,7 @@ pub enum ParseSignedValidatorError {
 impl From<&SignedValidator> for proto::AccountKeySignedPayload {
     fn from(x: &SignedValidator) -> Self {
         Self {
-            payload: (&x.payload.payload).clone(),
+            payload: x.payload.payload.clone().into(),
             signature: MF::some((&x.payload.signature).into()),
             ..Self::default()
         }
@@ -107,7 +107,7 @@ impl TryFrom<&proto::AccountKeySignedPayload> for SignedValidator {
         Ok(Self {
             validator: (&validator).try_into().map_err(Self::Error::Validator)?,
             payload: AccountKeySignedPayload {
-                payload: x.payload.clone(),
+                payload: x.payload.to_vec(),
                 signature: try_from_required(&x.signature).map_err(Self::Error::Signature)?,
             },
         })
@@ -11,7 +11,7 @@ pub type ParseCryptoHashError = Box<dyn std::error::Error + Send + Sync>;
 impl From<&CryptoHash> for proto::CryptoHash {
     fn from(x: &CryptoHash) -> Self {
         let mut y = Self::new();
-        y.hash = x.0.into();
+        y.hash = x.0.to_vec().into();
         y
     }
 }
@@ -29,7 +29,7 @@ pub type ParsePeerIdError = borsh::maybestd::io::Error;
 
 impl From<&PeerId> for proto::PublicKey {
     fn from(x: &PeerId) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
@@ -42,11 +42,28 @@ impl TryFrom<&proto::PublicKey> for PeerId {
 
 //////////////////////////////////////////
 
+pub type ParsePublicKeyError = borsh::maybestd::io::Error;
+
+impl From<&near_crypto::PublicKey> for proto::PublicKey {
+    fn from(x: &near_crypto::PublicKey) -> Self {
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
+    }
+}
+
+impl TryFrom<&proto::PublicKey> for near_crypto::PublicKey {
+    type Error = ParsePublicKeyError;
+    fn try_from(p: &proto::PublicKey) -> Result<Self, Self::Error> {
+        Self::try_from_slice(&p.borsh)
+    }
+}
+
+//////////////////////////////////////////
+
 pub type ParseSignatureError = borsh::maybestd::io::Error;
 
 impl From<&near_crypto::Signature> for proto::Signature {
     fn from(x: &near_crypto::Signature) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
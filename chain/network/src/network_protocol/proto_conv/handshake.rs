@@ -75,6 +75,10 @@ pub enum ParseHandshakeError {
     SenderChainInfo(ParseRequiredError<ParsePeerChainInfoV2Error>),
     #[error("partial_edge_info {0}")]
     PartialEdgeInfo(ParseRequiredError<ParsePartialEdgeInfoError>),
+    #[error("sender_time {0}")]
+    SenderTime(ParseTimestampError),
+    #[error("sender_encryption_signature {0}")]
+    SenderEncryptionSignature(ParseSignatureError),
 }
 
 impl From<&Handshake> for proto::Handshake {
@@ -87,6 +91,11 @@ impl From<&Handshake> for proto::Handshake {
             sender_listen_port: x.sender_listen_port.unwrap_or(0).into(),
             sender_chain_info: MF::some((&x.sender_chain_info).into()),
             partial_edge_info: MF::some((&x.partial_edge_info).into()),
+            sender_encryption_signature: MF::from_option(
+                x.sender_encryption_signature.as_ref().map(Into::into),
+            ),
+            proof_of_work: x.proof_of_work,
+            sender_time: MF::from_option(x.sender_time.as_ref().map(utc_to_proto)),
             ..Self::default()
         }
     }
@@ -115,6 +124,19 @@ impl TryFrom<&proto::Handshake> for Handshake {
                 .map_err(Self::Error::SenderChainInfo)?,
             partial_edge_info: try_from_required(&p.partial_edge_info)
                 .map_err(Self::Error::PartialEdgeInfo)?,
+            sender_encryption_signature: p
+                .sender_encryption_signature
+                .as_ref()
+                .map(|s| s.try_into())
+                .transpose()
+                .map_err(Self::Error::SenderEncryptionSignature)?,
+            proof_of_work: p.proof_of_work,
+            sender_time: p
+                .sender_time
+                .as_ref()
+                .map(utc_from_proto)
+                .transpose()
+                .map_err(Self::Error::SenderTime)?,
         })
     }
 }
@@ -145,6 +167,12 @@ impl From<(&PeerInfo, &HandshakeFailureReason)> for proto::HandshakeFailure {
                 reason: proto::handshake_failure::Reason::InvalidTarget.into(),
                 ..Self::default()
             },
+            HandshakeFailureReason::ProofOfWorkRequired { difficulty } => Self {
+                peer_info: MF::some(pi.into()),
+                reason: proto::handshake_failure::Reason::ProofOfWorkRequired.into(),
+                pow_difficulty: (*difficulty).into(),
+                ..Self::default()
+            },
         }
     }
 }
@@ -157,6 +185,8 @@ pub enum ParseHandshakeFailureError {
     GenesisId(ParseRequiredError<ParseGenesisIdError>),
     #[error("reason: unknown")]
     UnknownReason,
+    #[error("pow_difficulty: {0}")]
+    PowDifficulty(std::num::TryFromIntError),
 }
 
 impl TryFrom<&proto::HandshakeFailure> for (PeerInfo, HandshakeFailureReason) {
@@ -178,6 +208,12 @@ impl TryFrom<&proto::HandshakeFailure> for (PeerInfo, HandshakeFailureReason) {
             proto::handshake_failure::Reason::InvalidTarget => {
                 HandshakeFailureReason::InvalidTarget
             }
+            proto::handshake_failure::Reason::ProofOfWorkRequired => {
+                HandshakeFailureReason::ProofOfWorkRequired {
+                    difficulty: u8::try_from(x.pow_difficulty)
+                        .map_err(Self::Error::PowDifficulty)?,
+                }
+            }
             proto::handshake_failure::Reason::UNKNOWN => return Err(Self::Error::UnknownReason),
         };
         Ok((pi, hfr))
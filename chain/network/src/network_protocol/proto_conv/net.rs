@@ -21,8 +21,8 @@ impl From<&SocketAddr> for proto::SocketAddr {
     fn from(x: &SocketAddr) -> Self {
         Self {
             ip: match x.ip() {
-                IpAddr::V4(ip) => ip.octets().to_vec(),
-                IpAddr::V6(ip) => ip.octets().to_vec(),
+                IpAddr::V4(ip) => ip.octets().to_vec().into(),
+                IpAddr::V6(ip) => ip.octets().to_vec().into(),
             },
             port: x.port() as u32,
             ..Self::default()
@@ -82,7 +82,7 @@ impl TryFrom<&proto::PeerAddr> for PeerAddr {
 
 impl From<&PeerInfo> for proto::PeerInfo {
     fn from(x: &PeerInfo) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
@@ -101,7 +101,7 @@ pub type ParsePartialEdgeInfoError = borsh::maybestd::io::Error;
 
 impl From<&PartialEdgeInfo> for proto::PartialEdgeInfo {
     fn from(x: &PartialEdgeInfo) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
@@ -118,7 +118,7 @@ pub type ParseEdgeError = borsh::maybestd::io::Error;
 
 impl From<&Edge> for proto::Edge {
     fn from(x: &Edge) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
@@ -135,7 +135,7 @@ pub type ParseAnnounceAccountError = borsh::maybestd::io::Error;
 
 impl From<&AnnounceAccount> for proto::AnnounceAccount {
     fn from(x: &AnnounceAccount) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Self::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Self::default() }
     }
 }
 
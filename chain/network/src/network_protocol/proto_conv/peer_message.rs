@@ -3,10 +3,12 @@ use super::*;
 
 use crate::network_protocol::proto;
 use crate::network_protocol::proto::peer_message::Message_type as ProtoMT;
-use crate::network_protocol::{PeerMessage, RoutingTableUpdate};
+use crate::network_protocol::{
+    AddressChallenge, NetworkAdvisory, PeerHint, PeerMessage, RoutingTableUpdate,
+};
 use borsh::{BorshDeserialize as _, BorshSerialize as _};
 use near_network_primitives::time::error::ComponentRange;
-use near_network_primitives::types::{RoutedMessage, RoutedMessageV2};
+use near_network_primitives::types::{ProtocolDeprecation, RoutedMessage, RoutedMessageV2};
 use near_primitives::block::{Block, BlockHeader};
 use near_primitives::challenge::Challenge;
 use near_primitives::syncing::{EpochSyncFinalizationResponse, EpochSyncResponse};
@@ -50,7 +52,7 @@ impl TryFrom<&proto::RoutingTableUpdate> for RoutingTableUpdate {
 
 impl From<&BlockHeader> for proto::BlockHeader {
     fn from(x: &BlockHeader) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Default::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Default::default() }
     }
 }
 
@@ -67,7 +69,7 @@ impl TryFrom<&proto::BlockHeader> for BlockHeader {
 
 impl From<&Block> for proto::Block {
     fn from(x: &Block) -> Self {
-        Self { borsh: x.try_to_vec().unwrap(), ..Default::default() }
+        Self { borsh: x.try_to_vec().unwrap().into(), ..Default::default() }
     }
 }
 
@@ -82,6 +84,43 @@ impl TryFrom<&proto::Block> for Block {
 
 //////////////////////////////////////////
 
+#[derive(thiserror::Error, Debug)]
+pub enum ParsePeerHintError {
+    #[error("peer_id {0}")]
+    PeerId(ParseRequiredError<ParsePeerIdError>),
+    #[error("latency {0}")]
+    Latency(ParseDurationError),
+}
+
+impl From<&PeerHint> for proto::PeerHint {
+    fn from(x: &PeerHint) -> Self {
+        Self {
+            peer_id: MF::some((&x.peer_id).into()),
+            latency: MF::from_option(x.latency.as_ref().map(duration_to_proto)),
+            archival: x.archival,
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&proto::PeerHint> for PeerHint {
+    type Error = ParsePeerHintError;
+    fn try_from(x: &proto::PeerHint) -> Result<Self, Self::Error> {
+        Ok(Self {
+            peer_id: try_from_required(&x.peer_id).map_err(Self::Error::PeerId)?,
+            latency: x
+                .latency
+                .as_ref()
+                .map(duration_from_proto)
+                .transpose()
+                .map_err(Self::Error::Latency)?,
+            archival: x.archival,
+        })
+    }
+}
+
+//////////////////////////////////////////
+
 impl From<&PeerMessage> for proto::PeerMessage {
     fn from(x: &PeerMessage) -> Self {
         Self {
@@ -108,10 +147,13 @@ impl From<&PeerMessage> for proto::PeerMessage {
                     })
                 }
                 PeerMessage::PeersRequest => ProtoMT::PeersRequest(proto::PeersRequest::new()),
-                PeerMessage::PeersResponse(pis) => ProtoMT::PeersResponse(proto::PeersResponse {
-                    peers: pis.iter().map(Into::into).collect(),
-                    ..Default::default()
-                }),
+                PeerMessage::PeersResponse(pis, hints) => {
+                    ProtoMT::PeersResponse(proto::PeersResponse {
+                        peers: pis.iter().map(Into::into).collect(),
+                        hints: hints.iter().map(Into::into).collect(),
+                        ..Default::default()
+                    })
+                }
                 PeerMessage::BlockHeadersRequest(bhs) => {
                     ProtoMT::BlockHeadersRequest(proto::BlockHeadersRequest {
                         block_hashes: bhs.iter().map(Into::into).collect(),
@@ -133,17 +175,17 @@ impl From<&PeerMessage> for proto::PeerMessage {
                     ..Default::default()
                 }),
                 PeerMessage::Transaction(t) => ProtoMT::Transaction(proto::SignedTransaction {
-                    borsh: t.try_to_vec().unwrap(),
+                    borsh: t.try_to_vec().unwrap().into(),
                     ..Default::default()
                 }),
                 PeerMessage::Routed(r) => ProtoMT::Routed(proto::RoutedMessage {
-                    borsh: r.msg.try_to_vec().unwrap(),
+                    borsh: r.msg.try_to_vec().unwrap().into(),
                     created_at: MF::from_option(r.created_at.as_ref().map(utc_to_proto)),
                     ..Default::default()
                 }),
                 PeerMessage::Disconnect => ProtoMT::Disconnect(proto::Disconnect::new()),
                 PeerMessage::Challenge(r) => ProtoMT::Challenge(proto::Challenge {
-                    borsh: r.try_to_vec().unwrap(),
+                    borsh: r.try_to_vec().unwrap().into(),
                     ..Default::default()
                 }),
                 PeerMessage::EpochSyncRequest(epoch_id) => {
@@ -154,7 +196,7 @@ impl From<&PeerMessage> for proto::PeerMessage {
                 }
                 PeerMessage::EpochSyncResponse(esr) => {
                     ProtoMT::EpochSyncResponse(proto::EpochSyncResponse {
-                        borsh: esr.try_to_vec().unwrap(),
+                        borsh: esr.try_to_vec().unwrap().into(),
                         ..Default::default()
                     })
                 }
@@ -166,7 +208,51 @@ impl From<&PeerMessage> for proto::PeerMessage {
                 }
                 PeerMessage::EpochSyncFinalizationResponse(esfr) => {
                     ProtoMT::EpochSyncFinalizationResponse(proto::EpochSyncFinalizationResponse {
-                        borsh: esfr.try_to_vec().unwrap(),
+                        borsh: esfr.try_to_vec().unwrap().into(),
+                        ..Default::default()
+                    })
+                }
+                PeerMessage::EncryptionHandshake(p) => {
+                    ProtoMT::EncryptionHandshake(proto::EncryptionHandshake {
+                        payload: p.clone().into(),
+                        ..Default::default()
+                    })
+                }
+                PeerMessage::Encrypted(p) => ProtoMT::Encrypted(proto::Encrypted {
+                    payload: p.clone(),
+                    ..Default::default()
+                }),
+                PeerMessage::AddressChallenge(c) => {
+                    ProtoMT::AddressChallenge(proto::AddressChallenge {
+                        verifier: MF::some((&c.verifier).into()),
+                        target: MF::some((&c.target).into()),
+                        nonce: c.nonce,
+                        ..Default::default()
+                    })
+                }
+                PeerMessage::AddressProof(nonce) => ProtoMT::AddressProof(proto::AddressProof {
+                    nonce: *nonce,
+                    ..Default::default()
+                }),
+                PeerMessage::AddressChallengeRequest => {
+                    ProtoMT::AddressChallengeRequest(proto::AddressChallengeRequest::new())
+                }
+                PeerMessage::ProtocolDeprecation(d) => {
+                    ProtoMT::ProtocolDeprecation(proto::ProtocolDeprecation {
+                        protocol_version: d.protocol_version,
+                        epoch_height: d.epoch_height,
+                        ..Default::default()
+                    })
+                }
+                PeerMessage::KeepAlive => ProtoMT::KeepAlive(proto::KeepAlive::new()),
+                PeerMessage::NetworkAdvisory(a) => {
+                    ProtoMT::NetworkAdvisory(proto::NetworkAdvisory {
+                        signer: MF::some((&a.signer).into()),
+                        chain_id: a.chain_id.clone(),
+                        nonce: a.nonce,
+                        timestamp: a.timestamp,
+                        message: a.message.clone(),
+                        signature: MF::some((&a.signature).into()),
                         ..Default::default()
                     })
                 }
@@ -200,6 +286,8 @@ pub enum ParsePeerMessageError {
     UpdateNonceResponse(ParseRequiredError<ParseEdgeError>),
     #[error("peers_response: {0}")]
     PeersResponse(ParseVecError<ParsePeerInfoError>),
+    #[error("peers_response_hints: {0}")]
+    PeersResponseHints(ParseVecError<ParsePeerHintError>),
     #[error("block_headers_request: {0}")]
     BlockHeadersRequest(ParseVecError<ParseCryptoHashError>),
     #[error("block_headers_response: {0}")]
@@ -224,6 +312,14 @@ pub enum ParsePeerMessageError {
     EpochSyncFinalizationResponse(ParseEpochSyncFinalizationResponseError),
     #[error("routed_created_at: {0}")]
     RoutedCreatedAtTimestamp(ComponentRange),
+    #[error("address_challenge_verifier: {0}")]
+    AddressChallengeVerifier(ParseRequiredError<ParsePeerIdError>),
+    #[error("address_challenge_target: {0}")]
+    AddressChallengeTarget(ParseRequiredError<ParsePeerIdError>),
+    #[error("network_advisory_signer: {0}")]
+    NetworkAdvisorySigner(ParseRequiredError<ParsePublicKeyError>),
+    #[error("network_advisory_signature: {0}")]
+    NetworkAdvisorySignature(ParseRequiredError<ParseSignatureError>),
 }
 
 impl TryFrom<&proto::PeerMessage> for PeerMessage {
@@ -253,6 +349,7 @@ impl TryFrom<&proto::PeerMessage> for PeerMessage {
             ProtoMT::PeersRequest(_) => PeerMessage::PeersRequest,
             ProtoMT::PeersResponse(pr) => PeerMessage::PeersResponse(
                 try_from_slice(&pr.peers).map_err(Self::Error::PeersResponse)?,
+                try_from_slice(&pr.hints).map_err(Self::Error::PeersResponseHints)?,
             ),
             ProtoMT::BlockHeadersRequest(bhr) => PeerMessage::BlockHeadersRequest(
                 try_from_slice(&bhr.block_hashes).map_err(Self::Error::BlockHeadersRequest)?,
@@ -301,6 +398,35 @@ impl TryFrom<&proto::PeerMessage> for PeerMessage {
                         .map_err(Self::Error::EpochSyncFinalizationResponse)?,
                 ))
             }
+            ProtoMT::EncryptionHandshake(eh) => {
+                PeerMessage::EncryptionHandshake(eh.payload.to_vec())
+            }
+            ProtoMT::Encrypted(e) => PeerMessage::Encrypted(e.payload.clone()),
+            ProtoMT::AddressChallenge(c) => PeerMessage::AddressChallenge(AddressChallenge {
+                verifier: try_from_required(&c.verifier)
+                    .map_err(Self::Error::AddressChallengeVerifier)?,
+                target: try_from_required(&c.target)
+                    .map_err(Self::Error::AddressChallengeTarget)?,
+                nonce: c.nonce,
+            }),
+            ProtoMT::AddressProof(p) => PeerMessage::AddressProof(p.nonce),
+            ProtoMT::AddressChallengeRequest(_) => PeerMessage::AddressChallengeRequest,
+            ProtoMT::ProtocolDeprecation(d) => {
+                PeerMessage::ProtocolDeprecation(ProtocolDeprecation {
+                    protocol_version: d.protocol_version,
+                    epoch_height: d.epoch_height,
+                })
+            }
+            ProtoMT::KeepAlive(_) => PeerMessage::KeepAlive,
+            ProtoMT::NetworkAdvisory(a) => PeerMessage::NetworkAdvisory(NetworkAdvisory {
+                signer: try_from_required(&a.signer).map_err(Self::Error::NetworkAdvisorySigner)?,
+                chain_id: a.chain_id.clone(),
+                nonce: a.nonce,
+                timestamp: a.timestamp,
+                message: a.message.clone(),
+                signature: try_from_required(&a.signature)
+                    .map_err(Self::Error::NetworkAdvisorySignature)?,
+            }),
         })
     }
 }
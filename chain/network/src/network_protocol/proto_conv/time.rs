@@ -1,4 +1,4 @@
-/// Conversion functions for the proto timestamp messages.
+/// Conversion functions for the proto timestamp and duration messages.
 use near_network_primitives::time;
 pub use protobuf::well_known_types::timestamp::Timestamp as ProtoTimestamp;
 
@@ -16,3 +16,32 @@ pub fn utc_to_proto(x: &time::Utc) -> ProtoTimestamp {
 pub fn utc_from_proto(x: &ProtoTimestamp) -> Result<time::Utc, ParseTimestampError> {
     time::Utc::from_unix_timestamp_nanos((x.seconds as i128 * 1_000_000_000) + (x.nanos as i128))
 }
+
+pub use protobuf::well_known_types::duration::Duration as ProtoDuration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseDurationError {
+    #[error("negative duration")]
+    Negative,
+    #[error("nanos out of range")]
+    NanosOutOfRange,
+}
+
+pub fn duration_to_proto(x: &time::Duration) -> ProtoDuration {
+    ProtoDuration {
+        seconds: x.whole_seconds(),
+        // x.subsec_nanoseconds() is in range (-10^9,10^9) and has the same sign as seconds.
+        nanos: x.subsec_nanoseconds(),
+        ..Default::default()
+    }
+}
+
+pub fn duration_from_proto(x: &ProtoDuration) -> Result<time::Duration, ParseDurationError> {
+    if !(0..1_000_000_000).contains(&x.nanos) {
+        return Err(ParseDurationError::NanosOutOfRange);
+    }
+    if x.seconds < 0 {
+        return Err(ParseDurationError::Negative);
+    }
+    Ok(time::Duration::new(x.seconds, x.nanos))
+}
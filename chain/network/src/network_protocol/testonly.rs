@@ -48,6 +48,7 @@ pub fn make_block(
     Block::produce(
         PROTOCOL_VERSION,                                      // this_epoch_protocol_version
         PROTOCOL_VERSION,                                      // next_epoch_protocol_version
+        PROTOCOL_VERSION,                                      // latest_protocol_version
         prev.header(),                                         // prev
         prev.header().height() + 5,                            // height
         prev.header().block_ordinal() + 1,                     // block_ordinal
@@ -100,6 +101,14 @@ pub fn make_peer_info<R: Rng>(rng: &mut R) -> PeerInfo {
     }
 }
 
+pub fn make_peer_hint<R: Rng>(rng: &mut R, peer_id: PeerId) -> PeerHint {
+    PeerHint {
+        peer_id,
+        latency: Some(time::Duration::milliseconds(rng.gen_range(0..1000))),
+        archival: rng.gen(),
+    }
+}
+
 pub fn make_announce_account<R: Rng>(rng: &mut R) -> AnnounceAccount {
     let peer_id = make_peer_id(rng);
     let validator_signer = make_validator_signer(rng);
@@ -281,6 +290,7 @@ pub fn make_handshake<R: Rng>(rng: &mut R, chain: &Chain) -> Handshake {
         Some(rng.gen()),
         chain.get_info(),
         make_partial_edge(rng),
+        None,
     )
 }
 
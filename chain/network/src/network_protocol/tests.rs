@@ -1,14 +1,19 @@
+use crate::network_protocol;
 use crate::network_protocol::testonly as data;
-use crate::network_protocol::Encoding;
+use crate::network_protocol::{AddressChallenge, Encoding, NetworkAdvisory};
 use crate::testonly::make_rng;
 use crate::types::{HandshakeFailureReason, PeerMessage};
 use anyhow::{bail, Context as _};
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, RoutedMessageBody,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, ProtocolDeprecation,
+    RoutedMessageBody,
 };
+use near_primitives::network::PeerId;
 use near_primitives::syncing::EpochSyncResponse;
 use near_primitives::types::EpochId;
+use near_primitives::version::PROTOCOL_VERSION;
+use rand::Rng as _;
 
 // TODO: RoutingTableUpdate.validators field is supported only in proto encoding.
 // Remove this test once borsh support is removed.
@@ -22,6 +27,118 @@ fn serialize_deserialize_protobuf_only() {
     assert_eq!(m, m2);
 }
 
+// Peer hints are supported only in proto encoding.
+#[test]
+fn serialize_deserialize_peer_hints() {
+    let mut rng = make_rng(39521947542);
+    let peers: Vec<_> = (0..5).map(|_| data::make_peer_info(&mut rng)).collect();
+    let mut hints: Vec<_> =
+        peers.iter().map(|p| data::make_peer_hint(&mut rng, p.id.clone())).collect();
+    hints[0].latency = None;
+    let m = PeerMessage::PeersResponse(peers, hints);
+    let m2 = PeerMessage::deserialize(Encoding::Proto, &m.serialize(Encoding::Proto)).unwrap();
+    assert_eq!(m, m2);
+}
+
+// Encryption can be negotiated only in proto encoding.
+#[test]
+fn serialize_deserialize_encryption() {
+    let mut rng = make_rng(39521947542);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let signer = data::make_signer(&mut rng);
+    let handshake = network_protocol::Handshake::new(
+        PROTOCOL_VERSION,
+        PeerId::new(signer.public_key.clone()),
+        data::make_peer_id(&mut rng),
+        None,
+        chain.get_info(),
+        data::make_partial_edge(&mut rng),
+        Some(&signer.secret_key),
+    );
+    assert!(handshake.sender_supports_encryption());
+    assert!(handshake.verify_encryption_signature());
+    // The signature can't be reused for another connection.
+    let mut other = handshake.clone();
+    other.partial_edge_info.nonce += 2;
+    assert!(!other.verify_encryption_signature());
+    other.sender_encryption_signature = None;
+    assert!(!other.sender_supports_encryption());
+    assert!(other.verify_encryption_signature());
+
+    let msgs = [
+        PeerMessage::Handshake(handshake),
+        PeerMessage::EncryptionHandshake(rng.gen::<[u8; 32]>().to_vec()),
+        PeerMessage::Encrypted(rng.gen::<[u8; 32]>().to_vec().into()),
+    ];
+    for m in msgs {
+        let m2 = PeerMessage::deserialize(Encoding::Proto, &m.serialize(Encoding::Proto)).unwrap();
+        assert_eq!(m, m2);
+    }
+}
+
+// Proof of work can be attached only in proto encoding.
+#[test]
+fn proof_of_work() {
+    let mut rng = make_rng(63082117364);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let mut handshake = data::make_handshake(&mut rng, &chain);
+    assert!(handshake.verify_proof_of_work(0));
+    assert!(!handshake.verify_proof_of_work(8));
+    handshake.solve_proof_of_work(8);
+    assert!(handshake.verify_proof_of_work(8));
+
+    let msgs = [
+        PeerMessage::Handshake(handshake),
+        PeerMessage::HandshakeFailure(
+            data::make_peer_info(&mut rng),
+            HandshakeFailureReason::ProofOfWorkRequired { difficulty: 8 },
+        ),
+    ];
+    for m in msgs {
+        let m2 = PeerMessage::deserialize(Encoding::Proto, &m.serialize(Encoding::Proto)).unwrap();
+        assert_eq!(m, m2);
+    }
+}
+
+#[test]
+fn routed_message_key() {
+    let mut rng = make_rng(39521947542);
+    let mut clock = time::FakeClock::default();
+    let chain = data::Chain::make(&mut clock, &mut rng, 12);
+    let tx = data::make_signed_transaction(&mut rng);
+    let routed = data::make_routed_message(&mut rng, RoutedMessageBody::ForwardTx(tx));
+    let key = Some(network_protocol::routed_message_key(&routed.msg));
+    let routed = PeerMessage::Routed(routed);
+    let block = PeerMessage::Block(chain.blocks[5].clone());
+    for enc in [Encoding::Borsh, Encoding::Proto] {
+        assert_eq!(key, PeerMessage::routed_message_key(enc, &routed.serialize(enc)));
+        assert_eq!(None, PeerMessage::routed_message_key(enc, &block.serialize(enc)));
+        assert_eq!(None, PeerMessage::routed_message_key(enc, &routed.serialize(enc)[..8]));
+    }
+}
+
+#[test]
+fn routed_message_key_covers_body() {
+    let mut rng = make_rng(39521947542);
+    let tx = data::make_signed_transaction(&mut rng);
+    let routed = data::make_routed_message(&mut rng, RoutedMessageBody::ForwardTx(tx));
+    let key = network_protocol::routed_message_key(&routed.msg);
+    // Same author, target and signature, but a forged body.
+    let mut forged = routed.clone();
+    forged.msg.body = RoutedMessageBody::ForwardTx(data::make_signed_transaction(&mut rng));
+    assert_ne!(key, network_protocol::routed_message_key(&forged.msg));
+    let forged = PeerMessage::Routed(forged);
+    for enc in [Encoding::Borsh, Encoding::Proto] {
+        assert_ne!(Some(key.clone()), PeerMessage::routed_message_key(enc, &forged.serialize(enc)));
+    }
+    // The ttl changes on the way, but the key doesn't.
+    let mut forwarded = routed.clone();
+    forwarded.msg.ttl -= 1;
+    assert_eq!(key, network_protocol::routed_message_key(&forwarded.msg));
+}
+
 #[test]
 fn serialize_deserialize() -> anyhow::Result<()> {
     let mut rng = make_rng(89028037453);
@@ -66,7 +183,10 @@ fn serialize_deserialize() -> anyhow::Result<()> {
         PeerMessage::RequestUpdateNonce(data::make_partial_edge(&mut rng)),
         PeerMessage::ResponseUpdateNonce(edge),
         PeerMessage::PeersRequest,
-        PeerMessage::PeersResponse((0..5).map(|_| data::make_peer_info(&mut rng)).collect()),
+        PeerMessage::PeersResponse(
+            (0..5).map(|_| data::make_peer_info(&mut rng)).collect(),
+            vec![],
+        ),
         PeerMessage::BlockHeadersRequest(chain.blocks.iter().map(|b| b.hash().clone()).collect()),
         PeerMessage::BlockHeaders(chain.get_block_headers()),
         PeerMessage::BlockRequest(chain.blocks[5].hash().clone()),
@@ -80,6 +200,25 @@ fn serialize_deserialize() -> anyhow::Result<()> {
         PeerMessage::EpochSyncResponse(Box::new(EpochSyncResponse::UpToDate)),
         PeerMessage::EpochSyncFinalizationRequest(epoch_id),
         // TODO: EpochSyncFinalizationResponse
+        PeerMessage::AddressChallenge(AddressChallenge {
+            verifier: data::make_peer_id(&mut rng),
+            target: data::make_peer_id(&mut rng),
+            nonce: rng.gen(),
+        }),
+        PeerMessage::AddressProof(rng.gen()),
+        PeerMessage::AddressChallengeRequest,
+        PeerMessage::ProtocolDeprecation(ProtocolDeprecation {
+            protocol_version: rng.gen(),
+            epoch_height: rng.gen(),
+        }),
+        PeerMessage::KeepAlive,
+        PeerMessage::NetworkAdvisory(NetworkAdvisory::new(
+            &data::make_signer(&mut rng),
+            "localnet".to_string(),
+            rng.gen(),
+            rng.gen(),
+            "Security release available, upgrade before epoch 1000".to_string(),
+        )),
     ];
 
     // Check that serialize;deserialize = 1
@@ -0,0 +1,125 @@
+use near_network_primitives::time;
+use std::sync::{Arc, Mutex};
+
+/// Token bucket limiting the number of bytes sent per second.
+///
+/// The bucket holds at most 1s worth of tokens. Sending takes tokens for the whole message
+/// as long as the bucket is not empty, so a message larger than the bucket is still sent,
+/// at the cost of a debt which has to be paid off before the next one.
+pub(crate) struct TokenBucket {
+    bytes_per_sec: u64,
+    tokens: i64,
+    last_refill: time::Instant,
+}
+
+impl TokenBucket {
+    pub fn new(bytes_per_sec: u64, now: time::Instant) -> Self {
+        Self { bytes_per_sec, tokens: Self::capacity(bytes_per_sec), last_refill: now }
+    }
+
+    fn capacity(bytes_per_sec: u64) -> i64 {
+        i64::try_from(bytes_per_sec).unwrap_or(i64::MAX)
+    }
+
+    fn refill(&mut self, now: time::Instant) {
+        let elapsed = now - self.last_refill;
+        if elapsed <= time::Duration::ZERO {
+            return;
+        }
+        let new_tokens = (elapsed.as_seconds_f64() * self.bytes_per_sec as f64) as i64;
+        self.tokens =
+            self.tokens.saturating_add(new_tokens).min(Self::capacity(self.bytes_per_sec));
+        self.last_refill = now;
+    }
+
+    /// Whether there are tokens left to send a message.
+    pub fn has_tokens(&mut self, now: time::Instant) -> bool {
+        self.refill(now);
+        self.tokens > 0
+    }
+
+    /// Takes tokens for `bytes` sent, possibly going into debt.
+    pub fn take(&mut self, now: time::Instant, bytes: usize) {
+        self.refill(now);
+        self.tokens = self.tokens.saturating_sub(i64::try_from(bytes).unwrap_or(i64::MAX));
+    }
+}
+
+/// Bucket shared by all the connections, limiting the total upload bandwidth.
+pub(crate) type SharedTokenBucket = Arc<Mutex<TokenBucket>>;
+
+/// Outbound bandwidth limits of a single connection: its own and the global one.
+/// Only bulk messages are subject to the limits, but all sent bytes count towards them.
+#[derive(Default)]
+pub(crate) struct BandwidthLimiter {
+    peer: Option<TokenBucket>,
+    total: Option<SharedTokenBucket>,
+}
+
+impl BandwidthLimiter {
+    pub fn new(
+        peer_bytes_per_sec: Option<u64>,
+        total: Option<SharedTokenBucket>,
+        now: time::Instant,
+    ) -> Self {
+        Self { peer: peer_bytes_per_sec.map(|rate| TokenBucket::new(rate, now)), total }
+    }
+
+    /// Whether a bulk message can be sent now.
+    pub fn can_send(&mut self, now: time::Instant) -> bool {
+        self.peer.as_mut().map_or(true, |b| b.has_tokens(now))
+            && self.total.as_ref().map_or(true, |b| b.lock().unwrap().has_tokens(now))
+    }
+
+    /// Records `bytes` sent to the peer.
+    pub fn record_sent(&mut self, now: time::Instant, bytes: usize) {
+        if let Some(b) = self.peer.as_mut() {
+            b.take(now, bytes);
+        }
+        if let Some(b) = self.total.as_ref() {
+            b.lock().unwrap().take(now, bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let clock = time::FakeClock::default();
+        let mut bucket = TokenBucket::new(1000, clock.now());
+        assert!(bucket.has_tokens(clock.now()));
+        bucket.take(clock.now(), 600);
+        assert!(bucket.has_tokens(clock.now()));
+        // A message larger than the remaining tokens is allowed, but leaves a debt.
+        bucket.take(clock.now(), 1400);
+        assert!(!bucket.has_tokens(clock.now()));
+        clock.advance(time::Duration::milliseconds(500));
+        assert!(!bucket.has_tokens(clock.now()));
+        clock.advance(time::Duration::milliseconds(600));
+        assert!(bucket.has_tokens(clock.now()));
+        // Tokens don't accumulate beyond 1s worth of traffic.
+        clock.advance(time::Duration::seconds(100));
+        bucket.take(clock.now(), 1000);
+        assert!(!bucket.has_tokens(clock.now()));
+    }
+
+    #[test]
+    fn test_limiter_shares_total_bucket() {
+        let clock = time::FakeClock::default();
+        let total = Arc::new(Mutex::new(TokenBucket::new(1000, clock.now())));
+        let mut a = BandwidthLimiter::new(None, Some(total.clone()), clock.now());
+        let mut b = BandwidthLimiter::new(Some(10_000), Some(total), clock.now());
+        a.record_sent(clock.now(), 1000);
+        assert!(!a.can_send(clock.now()));
+        assert!(!b.can_send(clock.now()));
+        clock.advance(time::Duration::seconds(1));
+        assert!(b.can_send(clock.now()));
+
+        let mut unlimited = BandwidthLimiter::default();
+        unlimited.record_sent(clock.now(), usize::MAX);
+        assert!(unlimited.can_send(clock.now()));
+    }
+}
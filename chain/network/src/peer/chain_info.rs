@@ -0,0 +1,42 @@
+use near_network_primitives::types::PeerChainInfoV2;
+use near_primitives::types::{BlockHeight, NumShards};
+
+/// How far above the header head of this node a peer may claim to be, for its height to
+/// be plausible. It is a generous bound: header sync keeps the header head close to the
+/// head of the network.
+pub(crate) const MAX_HEIGHT_AHEAD: BlockHeight = 10_000;
+
+/// Reasons for rejecting the chain info advertised by a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+pub(crate) enum InvalidChainInfo {
+    /// Peer is on a different chain.
+    GenesisMismatch,
+    /// Peer claims to track a shard which doesn't exist in the shard layout.
+    UnknownShard,
+}
+
+/// Checks the shards tracked by a peer against the shard layout at our head.
+/// The number of shards never decreases, so the check is sound only for peers which are
+/// not ahead of us: a peer ahead of us may already be in an epoch with more shards.
+pub(crate) fn check_tracked_shards(
+    info: &PeerChainInfoV2,
+    my_height: BlockHeight,
+    num_shards: Option<NumShards>,
+) -> Result<(), InvalidChainInfo> {
+    let num_shards = match num_shards {
+        Some(num_shards) if info.height <= my_height => num_shards,
+        _ => return Ok(()),
+    };
+    if info.tracked_shards.iter().any(|shard_id| *shard_id >= num_shards) {
+        return Err(InvalidChainInfo::UnknownShard);
+    }
+    Ok(())
+}
+
+/// Checks the height advertised by a peer against the header head of this node, i.e. the
+/// headers we verified ourselves. An implausible height is not proof of fraud, since we may
+/// be far behind, so such peers are only deprioritized. Header sync bans the peers which
+/// fail to deliver the headers up to the height they claim.
+pub(crate) fn is_height_plausible(height: BlockHeight, my_header_height: BlockHeight) -> bool {
+    height <= my_header_height.saturating_add(MAX_HEIGHT_AHEAD)
+}
@@ -5,8 +5,10 @@
 ///
 /// NOTES:
 ///     - Code has an extra logic to ban peers if they sent messages that are too large.
+///     - Decoded messages are slices of the read buffer, so that large messages (blocks, state
+///       parts) are not copied on their way to the `PeerActor`.
 use crate::stats::metrics;
-use bytes::{Buf, BufMut, BytesMut};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use bytesize::{GIB, MIB};
 use near_network_primitives::types::ReasonForBan;
 use near_performance_metrics::framed_write::EncoderCallBack;
@@ -75,7 +77,7 @@ impl Encoder<Vec<u8>> for Codec {
 }
 
 impl Decoder for Codec {
-    type Item = Result<Vec<u8>, ReasonForBan>;
+    type Item = Result<Bytes, ReasonForBan>;
     type Error = Error;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
@@ -91,17 +93,16 @@ impl Decoder for Codec {
             return Ok(Some(Err(ReasonForBan::Abusive)));
         }
 
-        if let Some(data_buf) = buf.get(4..4 + len) {
-            let res = Some(Ok(data_buf.to_vec()));
-            buf.advance(4 + len);
-            if buf.is_empty() && buf.capacity() > 0 {
-                *buf = BytesMut::new();
-            }
-            Ok(res)
-        } else {
+        if buf.len() < 4 + len {
             // not enough bytes, keep waiting
-            Ok(None)
+            return Ok(None);
+        }
+        buf.advance(4);
+        let res = Some(Ok(buf.split_to(len).freeze()));
+        if buf.is_empty() && buf.capacity() > 0 {
+            *buf = BytesMut::new();
         }
+        Ok(res)
     }
 }
 
@@ -130,7 +131,7 @@ mod test {
             let mut buffer = BytesMut::new();
             codec.encode(msg.serialize(enc), &mut buffer).unwrap();
             let decoded = codec.decode(&mut buffer).unwrap().unwrap().unwrap();
-            assert_eq!(PeerMessage::deserialize(enc, &decoded).unwrap(), msg);
+            assert_eq!(PeerMessage::deserialize_bytes(enc, &decoded).unwrap(), msg);
         }
     }
 
@@ -178,6 +179,9 @@ mod test {
                 archival: false,
             },
             partial_edge_info: PartialEdgeInfo::default(),
+            sender_encryption_signature: None,
+            proof_of_work: 0,
+            sender_time: None,
         };
         let msg = PeerMessage::Handshake(fake_handshake);
         test_codec(msg);
@@ -187,7 +191,7 @@ mod test {
     fn test_peer_message_info_gossip() {
         let peer_info1 = PeerInfo::random();
         let peer_info2 = PeerInfo::random();
-        let msg = PeerMessage::PeersResponse(vec![peer_info1, peer_info2]);
+        let msg = PeerMessage::PeersResponse(vec![peer_info1, peer_info2], vec![]);
         test_codec(msg);
     }
 
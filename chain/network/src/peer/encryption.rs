@@ -0,0 +1,234 @@
+/// Encryption of the connections between peers.
+///
+/// Peers advertise encryption support in the `Handshake`. If both of them support it, then once
+/// the connection is established the outbound peer initiates a Noise XX handshake, carried in
+/// `PeerMessage::EncryptionHandshake`. Noise static keys are X25519 keys derived from the ED25519
+/// node keys, so at the end of the handshake each peer verifies that the static key of the other
+/// side belongs to the `PeerId` it is connected to. Afterwards every `PeerMessage` is sent
+/// wrapped in `PeerMessage::Encrypted`.
+///
+/// Noise limits a single message to 64KiB, so larger `PeerMessage`s are encrypted in chunks,
+/// which are concatenated into a single `PeerMessage::Encrypted`.
+use curve25519_dalek::edwards::CompressedEdwardsY;
+use near_crypto::{PublicKey, SecretKey};
+use near_primitives::network::PeerId;
+use sha2::Digest as _;
+
+const NOISE_PARAMS: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+/// Maximal size of a single Noise message.
+const MAX_NOISE_MESSAGE_LEN: usize = 65535;
+/// Size of the authentication tag appended to every Noise transport message.
+const TAG_LEN: usize = 16;
+/// Maximal size of the plaintext which fits into a single Noise transport message.
+const MAX_CHUNK_LEN: usize = MAX_NOISE_MESSAGE_LEN - TAG_LEN;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptionError {
+    #[error("noise: {0}")]
+    Noise(#[from] snow::Error),
+    #[error("only ED25519 keys are supported")]
+    UnsupportedKeyType,
+    #[error("peer id is not a valid ED25519 public key")]
+    InvalidPeerId,
+    #[error("static key of the peer doesn't match its peer id")]
+    StaticKeyMismatch,
+    #[error("encryption was not negotiated")]
+    NotNegotiated,
+    #[error("encryption handshake has already been completed")]
+    HandshakeCompleted,
+    #[error("encryption handshake has not been completed yet")]
+    HandshakeNotCompleted,
+}
+
+enum State {
+    Handshake(Box<snow::HandshakeState>),
+    Transport(Box<snow::TransportState>),
+    /// Handshake has failed, the session is unusable.
+    Failed,
+}
+
+/// Noise session with a single peer.
+pub(crate) struct Session {
+    /// Id of the peer on the other side. Its static key is expected to match it.
+    peer_id: PeerId,
+    state: State,
+}
+
+impl Session {
+    /// Starts the handshake as the initiator.
+    /// Returns the session and the first handshake message to send to the peer.
+    pub fn initiate(key: &SecretKey, peer_id: PeerId) -> Result<(Self, Vec<u8>), EncryptionError> {
+        let key = x25519_secret_key(key)?;
+        let mut state = builder().local_private_key(&key).build_initiator()?;
+        let mut msg = vec![0; MAX_NOISE_MESSAGE_LEN];
+        let n = state.write_message(&[], &mut msg)?;
+        msg.truncate(n);
+        Ok((Self { peer_id, state: State::Handshake(Box::new(state)) }, msg))
+    }
+
+    /// Starts the handshake as the responder. The initiator speaks first.
+    pub fn respond(key: &SecretKey, peer_id: PeerId) -> Result<Self, EncryptionError> {
+        let key = x25519_secret_key(key)?;
+        let state = builder().local_private_key(&key).build_responder()?;
+        Ok(Self { peer_id, state: State::Handshake(Box::new(state)) })
+    }
+
+    pub fn is_established(&self) -> bool {
+        matches!(self.state, State::Transport(_))
+    }
+
+    /// Processes a handshake message received from the peer.
+    /// Returns the handshake message to send back, if any.
+    pub fn handshake(&mut self, msg: &[u8]) -> Result<Option<Vec<u8>>, EncryptionError> {
+        let mut state = match std::mem::replace(&mut self.state, State::Failed) {
+            State::Handshake(state) => state,
+            state => {
+                self.state = state;
+                return Err(EncryptionError::HandshakeCompleted);
+            }
+        };
+        state.read_message(msg, &mut vec![0; MAX_NOISE_MESSAGE_LEN])?;
+        let mut resp = None;
+        if state.is_my_turn() && !state.is_handshake_finished() {
+            let mut msg = vec![0; MAX_NOISE_MESSAGE_LEN];
+            let n = state.write_message(&[], &mut msg)?;
+            msg.truncate(n);
+            resp = Some(msg);
+        }
+        self.state = if state.is_handshake_finished() {
+            if state.get_remote_static() != Some(&x25519_public_key(&self.peer_id)?[..]) {
+                return Err(EncryptionError::StaticKeyMismatch);
+            }
+            State::Transport(Box::new(state.into_transport_mode()?))
+        } else {
+            State::Handshake(state)
+        };
+        Ok(resp)
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let state = self.transport()?;
+        let chunks = std::cmp::max(1, (plaintext.len() + MAX_CHUNK_LEN - 1) / MAX_CHUNK_LEN);
+        let mut ciphertext = vec![0; plaintext.len() + chunks * TAG_LEN];
+        let mut n = 0;
+        if plaintext.is_empty() {
+            n += state.write_message(&[], &mut ciphertext)?;
+        }
+        for chunk in plaintext.chunks(MAX_CHUNK_LEN) {
+            n += state.write_message(chunk, &mut ciphertext[n..])?;
+        }
+        ciphertext.truncate(n);
+        Ok(ciphertext)
+    }
+
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let state = self.transport()?;
+        let mut plaintext = vec![0; ciphertext.len()];
+        let mut n = 0;
+        for chunk in ciphertext.chunks(MAX_NOISE_MESSAGE_LEN) {
+            n += state.read_message(chunk, &mut plaintext[n..])?;
+        }
+        plaintext.truncate(n);
+        Ok(plaintext)
+    }
+
+    fn transport(&mut self) -> Result<&mut snow::TransportState, EncryptionError> {
+        match &mut self.state {
+            State::Transport(state) => Ok(state),
+            _ => Err(EncryptionError::HandshakeNotCompleted),
+        }
+    }
+}
+
+fn builder() -> snow::Builder<'static> {
+    // NOISE_PARAMS is a valid constant, parsing it cannot fail.
+    snow::Builder::new(NOISE_PARAMS.parse().unwrap())
+}
+
+/// Converts the ED25519 secret key to the X25519 secret key, in the same way as ED25519
+/// derives its signing scalar from the seed. Clamping is done by the Noise implementation.
+fn x25519_secret_key(key: &SecretKey) -> Result<[u8; 32], EncryptionError> {
+    match key {
+        SecretKey::ED25519(key) => {
+            let hash = sha2::Sha512::digest(&key.0[..32]);
+            let mut x25519 = [0; 32];
+            x25519.copy_from_slice(&hash[..32]);
+            Ok(x25519)
+        }
+        _ => Err(EncryptionError::UnsupportedKeyType),
+    }
+}
+
+/// Converts the ED25519 public key of the peer to the X25519 public key (i.e. maps the
+/// Edwards point to the birationally equivalent Montgomery point).
+fn x25519_public_key(peer_id: &PeerId) -> Result<[u8; 32], EncryptionError> {
+    match peer_id.public_key() {
+        PublicKey::ED25519(key) => CompressedEdwardsY(key.0)
+            .decompress()
+            .map(|p| p.to_montgomery().to_bytes())
+            .ok_or(EncryptionError::InvalidPeerId),
+        _ => Err(EncryptionError::UnsupportedKeyType),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_crypto::KeyType;
+
+    fn make_key(seed: &str) -> SecretKey {
+        SecretKey::from_seed(KeyType::ED25519, seed)
+    }
+
+    fn handshake(a: &SecretKey, b: &SecretKey) -> Result<(Session, Session), EncryptionError> {
+        let (mut initiator, msg) = Session::initiate(a, PeerId::new(b.public_key()))?;
+        let mut responder = Session::respond(b, PeerId::new(a.public_key()))?;
+        let msg = responder.handshake(&msg)?.unwrap();
+        let msg = initiator.handshake(&msg)?.unwrap();
+        assert!(initiator.is_established());
+        assert_eq!(None, responder.handshake(&msg)?);
+        assert!(responder.is_established());
+        Ok((initiator, responder))
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let (mut a, mut b) = handshake(&make_key("a"), &make_key("b")).unwrap();
+        for len in [0, 1, 1000, MAX_CHUNK_LEN, MAX_CHUNK_LEN + 1, 3 * MAX_NOISE_MESSAGE_LEN] {
+            let msg: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let ciphertext = a.encrypt(&msg).unwrap();
+            assert!(ciphertext.len() > msg.len());
+            assert_eq!(msg, b.decrypt(&ciphertext).unwrap());
+            let ciphertext = b.encrypt(&msg).unwrap();
+            assert_eq!(msg, a.decrypt(&ciphertext).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_tampered_message() {
+        let (mut a, mut b) = handshake(&make_key("a"), &make_key("b")).unwrap();
+        let mut ciphertext = a.encrypt(b"hello").unwrap();
+        ciphertext[0] ^= 1;
+        assert!(b.decrypt(&ciphertext).is_err());
+    }
+
+    #[test]
+    fn test_static_key_mismatch() {
+        let a = make_key("a");
+        let b = make_key("b");
+        // Initiator expects to talk to `c`, but `b` responds.
+        let (mut initiator, msg) =
+            Session::initiate(&a, PeerId::new(make_key("c").public_key())).unwrap();
+        let mut responder = Session::respond(&b, PeerId::new(a.public_key())).unwrap();
+        let msg = responder.handshake(&msg).unwrap().unwrap();
+        assert!(matches!(initiator.handshake(&msg), Err(EncryptionError::StaticKeyMismatch)));
+        assert!(!initiator.is_established());
+    }
+
+    #[test]
+    fn test_not_established() {
+        let a = make_key("a");
+        let mut responder = Session::respond(&a, PeerId::new(make_key("b").public_key())).unwrap();
+        assert!(matches!(responder.encrypt(b"hello"), Err(EncryptionError::HandshakeNotCompleted)));
+    }
+}
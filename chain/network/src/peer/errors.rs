@@ -0,0 +1,134 @@
+//! Failures to handle the messages received from a peer.
+//!
+//! `PeerActor` doesn't just log them: every failure is a `PeerError` whose kind determines
+//! the consequence. Ban-worthy errors ban the peer right away, fatal protocol violations
+//! close the connection, and the other errors drop the message. The errors are counted and
+//! reported to the `PeerManagerActor` with the rest of `PeerBehavior`, which bans the peers
+//! misbehaving too often, see `MAX_MISBEHAVIOR_PER_PERIOD`.
+use crate::network_protocol::ParsePeerMessageError;
+use crate::peer::encryption::EncryptionError;
+use near_network_primitives::types::ReasonForBan;
+
+/// Errors caused by the peer, i.e. all but the transient ones, tolerated between two
+/// consecutive `QueryPeerStats`. The peer is banned once it exceeds the limit.
+pub(crate) const MAX_MISBEHAVIOR_PER_PERIOD: u64 = 100;
+
+#[derive(thiserror::Error, Debug, strum::IntoStaticStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum PeerError {
+    /// The message couldn't be decoded.
+    #[error("invalid message: {0}")]
+    Parse(#[from] ParsePeerMessageError),
+    #[error("protocol violation: {0}")]
+    ProtocolViolation(#[from] ProtocolViolation),
+    /// The peer has proven malicious, e.g. sent a message with an invalid signature.
+    #[error("ban: {0:?}")]
+    Ban(ReasonForBan),
+    /// The message couldn't be handled for a reason which is not the peer's fault, e.g. the
+    /// client is shutting down.
+    #[error("transient: {0}")]
+    Transient(#[from] actix::MailboxError),
+}
+
+impl PeerError {
+    /// Label of the kind of the error in the metrics.
+    pub fn kind(&self) -> &'static str {
+        self.into()
+    }
+}
+
+/// Message which the peer mustn't send in the current state of the connection.
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum ProtocolViolation {
+    #[error("received {msg_type} while {status}")]
+    UnexpectedMessage { msg_type: &'static str, status: String },
+    #[error("received unencrypted {0} over an encrypted connection")]
+    Unencrypted(&'static str),
+    #[error("received an encryption message inside an encrypted one")]
+    NestedEncryption,
+    #[error("encryption: {0}")]
+    Encryption(#[from] EncryptionError),
+    #[error("nonce in the handshake doesn't match the proposed one")]
+    HandshakeNonceMismatch,
+    #[error("received LastEdge which is unexpected or invalid")]
+    InvalidLastEdge,
+}
+
+impl ProtocolViolation {
+    /// Whether the connection can't go on after the violation. Otherwise only the message is
+    /// dropped.
+    pub fn is_fatal(&self) -> bool {
+        !matches!(self, Self::UnexpectedMessage { .. })
+    }
+}
+
+/// Number of the errors of each kind, except `PeerError::Ban`, after which the connection
+/// is closed immediately.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PeerErrorCounts {
+    pub parse: u64,
+    pub protocol_violation: u64,
+    pub transient: u64,
+}
+
+impl PeerErrorCounts {
+    pub(crate) fn add(&mut self, err: &PeerError) {
+        match err {
+            PeerError::Parse(_) => self.parse += 1,
+            PeerError::ProtocolViolation(_) => self.protocol_violation += 1,
+            PeerError::Ban(_) => {}
+            PeerError::Transient(_) => self.transient += 1,
+        }
+    }
+
+    pub(crate) fn add_all(&mut self, other: &PeerErrorCounts) {
+        self.parse += other.parse;
+        self.protocol_violation += other.protocol_violation;
+        self.transient += other.transient;
+    }
+
+    /// Errors caused by the peer.
+    pub fn misbehavior(&self) -> u64 {
+        self.parse.saturating_add(self.protocol_violation)
+    }
+
+    /// Counts by the kind label of `PeerError::kind`.
+    pub(crate) fn by_kind(&self) -> [(&'static str, u64); 3] {
+        [
+            ("parse", self.parse),
+            ("protocol_violation", self.protocol_violation),
+            ("transient", self.transient),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let mut counts = PeerErrorCounts::default();
+        counts.add(&ProtocolViolation::NestedEncryption.into());
+        counts.add(&PeerError::Ban(ReasonForBan::InvalidSignature));
+        counts.add(&actix::MailboxError::Closed.into());
+        let mut total = counts.clone();
+        total.add_all(&counts);
+        assert_eq!(PeerErrorCounts { parse: 0, protocol_violation: 2, transient: 2 }, total);
+        assert_eq!(2, total.misbehavior());
+        assert!(ProtocolViolation::NestedEncryption.is_fatal());
+        let unexpected = ProtocolViolation::UnexpectedMessage {
+            msg_type: "Handshake",
+            status: "Ready on Inbound connection".to_string(),
+        };
+        assert!(!unexpected.is_fatal());
+        for err in [
+            PeerError::from(ProtocolViolation::NestedEncryption),
+            PeerError::Ban(ReasonForBan::None),
+            actix::MailboxError::Timeout.into(),
+        ] {
+            let kind = err.kind();
+            assert!(total.by_kind().iter().any(|(k, _)| *k == kind) || kind == "ban");
+        }
+    }
+}
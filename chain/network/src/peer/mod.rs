@@ -1,5 +1,11 @@
+pub(crate) mod bandwidth;
+pub(crate) mod chain_info;
 pub(crate) mod codec;
+mod encryption;
+pub(crate) mod errors;
 pub(crate) mod peer_actor;
+pub(crate) mod recorder;
+mod send_queue;
 mod tracker;
 mod transfer_stats;
 
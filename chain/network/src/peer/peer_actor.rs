@@ -1,12 +1,22 @@
-use crate::network_protocol::{Encoding, ParsePeerMessageError};
+use crate::network_protocol::{self, Encoding, ParsePeerMessageError, RoutedMessageKey};
+use crate::peer::bandwidth::BandwidthLimiter;
+use crate::peer::chain_info::{self, InvalidChainInfo};
 use crate::peer::codec::Codec;
+use crate::peer::encryption::{EncryptionError, Session};
+use crate::peer::errors::{PeerError, ProtocolViolation};
+use crate::peer::recorder::FrameRecorder;
+use crate::peer::send_queue::{Priority, QueuedMessage, SendQueue};
 use crate::peer::tracker::Tracker;
+use crate::peer_manager::dedup::RoutedMessageDedup;
+use crate::peer_manager::handshake_limiter::PendingHandshake;
+use crate::peer_manager::scoring::PeerBehavior;
 use crate::private_actix::PeersResponse;
 use crate::private_actix::{PeerToManagerMsg, PeerToManagerMsgResp};
 use crate::private_actix::{
     PeersRequest, RegisterPeer, RegisterPeerResponse, SendMessage, Unregister,
 };
 use crate::stats::metrics;
+use crate::transport::WriteHalf;
 use crate::types::{
     Handshake, HandshakeFailureReason, NetworkClientMessages, NetworkClientResponses, PeerMessage,
     PeerStatsResult, QueryPeerStats,
@@ -15,13 +25,14 @@ use actix::{
     Actor, ActorContext, ActorFutureExt, Arbiter, AsyncContext, Context, ContextFutureSpawner,
     Handler, Recipient, Running, StreamHandler, WrapFuture,
 };
-use lru::LruCache;
-use near_crypto::Signature;
+use bytes::Bytes;
+use near_crypto::SecretKey;
+use near_network_primitives::chaos::{Chaos, ChaosAction};
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    Ban, NetworkViewClientMessages, NetworkViewClientResponses, PeerChainInfoV2, PeerIdOrHash,
-    PeerInfo, PeerManagerRequest, PeerManagerRequestWithContext, PeerType, ReasonForBan,
-    RoutedMessage, RoutedMessageBody, RoutedMessageFrom, StateResponseInfo,
+    Ban, NetworkViewClientMessages, NetworkViewClientResponses, PeerChainInfoV2, PeerInfo,
+    PeerManagerRequest, PeerManagerRequestWithContext, PeerType, ReasonForBan, RoutedMessage,
+    RoutedMessageBody, RoutedMessageFrom, StateResponseInfo,
     UPDATE_INTERVAL_LAST_TIME_RECEIVED_MESSAGE,
 };
 use near_network_primitives::types::{Edge, PartialEdgeInfo};
@@ -31,11 +42,14 @@ use near_primitives::block::GenesisId;
 use near_primitives::logging;
 use near_primitives::network::PeerId;
 use near_primitives::sharding::PartialEncodedChunk;
+use near_primitives::types::{BlockHeight, NumShards};
 use near_primitives::utils::DisplayOption;
 use near_primitives::version::{
-    ProtocolVersion, PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION,
+    ProtocolVersion, KEEP_ALIVE_PROTOCOL_VERSION, PEER_HINTS_PROTOCOL_VERSION,
+    PEER_MIN_ALLOWED_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
 use near_rate_limiter::{ActixMessageWrapper, ThrottleController};
+use parking_lot::Mutex;
 use std::cmp::max;
 use std::fmt::Debug;
 use std::io;
@@ -46,8 +60,6 @@ use thiserror::Error;
 use tracing::{debug, error, info, trace, warn};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-type WriteHalf = tokio::io::WriteHalf<tokio::net::TcpStream>;
-
 /// Maximum number of messages per minute from single peer.
 // TODO(#5453): current limit is way to high due to us sending lots of messages during sync.
 const MAX_PEER_MSG_PER_MIN: usize = usize::MAX;
@@ -56,10 +68,24 @@ const MAX_PEER_MSG_PER_MIN: usize = usize::MAX;
 /// The purpose of this constant is to ensure we do not spend too much time deserializing and
 /// dispatching transactions when we should be focusing on consensus-related messages.
 const MAX_TRANSACTIONS_PER_BLOCK_MESSAGE: usize = 1000;
-/// Limit cache size of 1000 messages
-const ROUTED_MESSAGE_CACHE_SIZE: usize = 1000;
-/// Duplicated messages will be dropped if routed through the same peer multiple times.
-const DROP_DUPLICATED_MESSAGES_PERIOD: time::Duration = time::Duration::milliseconds(50);
+/// How long a peer can stay with an unknown encoding, during which every message is sent in
+/// both encodings. Afterwards we assume the peer supports only Borsh, until it sends us a proto
+/// message.
+const MAX_UNKNOWN_ENCODING_DURATION: time::Duration = time::Duration::seconds(10);
+/// How often the messages waiting in the send queue are retried.
+const SEND_QUEUE_FLUSH_INTERVAL: time::Duration = time::Duration::milliseconds(10);
+/// Messages wait in the send queue, where they can be reordered by priority, while the
+/// write buffer of the connection holds more than this.
+const MAX_WRITE_BUFFER_BYTES: usize = 256 * bytesize::KIB as usize;
+/// Bulk messages are dropped instead of queued once the queued ones exceed this size.
+const MAX_QUEUED_BULK_BYTES: usize = 64 * bytesize::MIB as usize;
+/// Messages waiting for the encryption handshake are dropped once the waiting ones exceed
+/// this size, so that a peer which never completes the handshake can't make us buffer
+/// traffic until the handshake timeout.
+const MAX_PENDING_ENCRYPTION_BYTES: usize = 8 * bytesize::MIB as usize;
+/// Maximum difficulty (in bits) of the proof of work we are willing to solve to connect to
+/// a peer. Solving it takes about a second.
+const MAX_PROOF_OF_WORK_DIFFICULTY: u8 = 20;
 
 pub(crate) struct PeerActor {
     clock: time::Clock,
@@ -77,8 +103,17 @@ pub(crate) struct PeerActor {
     protocol_version: ProtocolVersion,
     /// Framed wrapper to send messages through the TCP connection.
     framed: FramedWrite<Vec<u8>, WriteHalf, Codec, Codec>,
-    /// Handshake timeout.
+    /// Time given to the peer to complete the handshake, derived from the round-trip time
+    /// of the connection.
     handshake_timeout: time::Duration,
+    /// Time the actor was created at, to measure the duration of the handshake.
+    created_at: time::Instant,
+    /// How often to send `KeepAlive` once the connection is established.
+    keep_alive_interval: time::Duration,
+    /// Time without any message from the peer after which the connection is closed.
+    keep_alive_timeout: time::Duration,
+    /// Last time anything was received from the peer.
+    last_time_received: time::Instant,
     /// Peer manager recipient to break the dependency loop.
     /// PeerManager is a recipient of 2 types of messages, therefore
     /// to inject a fake PeerManager in tests, we need a separate
@@ -91,8 +126,14 @@ pub(crate) struct PeerActor {
     view_client_addr: Recipient<NetworkViewClientMessages>,
     /// Tracker for requests and responses.
     tracker: Tracker,
+    /// Behavior of the peer since the last `QueryPeerStats`, used to score the peer.
+    behavior: PeerBehavior,
     /// This node genesis id.
     genesis_id: GenesisId,
+    /// Height of this node's head, as of the start of the actor.
+    my_height: BlockHeight,
+    /// Number of shards at this node's head. None if unknown.
+    num_shards: Option<NumShards>,
     /// Latest chain info from the peer.
     chain_info: PeerChainInfoV2,
     /// Edge information needed to build the real edge. This is relevant for handshake.
@@ -104,8 +145,9 @@ pub(crate) struct PeerActor {
     txns_since_last_block: Arc<AtomicUsize>,
     /// How many peer actors are created
     peer_counter: Arc<AtomicUsize>,
-    /// Cache of recently routed messages, this allows us to drop duplicates
-    routed_message_cache: LruCache<(PeerId, PeerIdOrHash, Signature), time::Instant>,
+    /// Recently received routed messages, shared by all the connections, which allows us to
+    /// drop duplicates.
+    routed_message_dedup: Arc<Mutex<RoutedMessageDedup>>,
     /// A helper data structure for limiting reading
     throttle_controller: ThrottleController,
     /// Whether we detected support for protocol buffers during handshake.
@@ -113,6 +155,37 @@ pub(crate) struct PeerActor {
     /// Whether the PeerActor should skip protobuf support detection and use
     /// a given encoding right away.
     force_encoding: Option<Encoding>,
+    /// Time the actor was created at, while the encoding of the peer is unknown.
+    unknown_encoding_since: Option<time::Instant>,
+    /// Whether the encoding stayed unknown for longer than `MAX_UNKNOWN_ENCODING_DURATION`,
+    /// in which case Borsh is assumed.
+    borsh_assumed: bool,
+    /// Node key used for encryption of the connection. None if encryption is disabled.
+    encryption_key: Option<SecretKey>,
+    /// Whether the peer advertised encryption support in its handshake.
+    peer_supports_encryption: bool,
+    /// Encryption session with the peer. Present once the encryption handshake has started.
+    encryption: Option<Session>,
+    /// Messages waiting for the encryption handshake to complete, with their total size.
+    pending_messages: Vec<PeerMessage>,
+    pending_messages_bytes: usize,
+    /// Outbound bandwidth limits applied to bulk messages.
+    bandwidth_limiter: BandwidthLimiter,
+    /// Messages waiting for the connection to accept them.
+    send_queue: SendQueue,
+    /// Whether a retry of sending the queued messages is scheduled.
+    flush_scheduled: bool,
+    /// Slot of an inbound connection in the `HandshakeLimiter`, released once the handshake
+    /// completes. None for outbound connections.
+    pending_handshake: Option<PendingHandshake>,
+    /// Proof of work (in bits) the peer requested from us in the handshake.
+    pow_difficulty: u8,
+    /// Recording of the received frames, if enabled.
+    recorder: Option<Arc<FrameRecorder>>,
+    /// Faults to inject into the received messages, if enabled.
+    chaos: Option<Arc<Chaos>>,
+    /// Message held back by `ChaosAction::Reorder`, with the size of its frame.
+    chaos_held: Option<(PeerMessage, usize)>,
 }
 
 impl Debug for PeerActor {
@@ -128,6 +201,8 @@ impl Debug for PeerActor {
 pub enum IOError {
     #[error("{tid} Failed to send message {message_type} of size {size}")]
     Send { tid: usize, message_type: String, size: usize },
+    #[error("Failed to encrypt message {message_type}: {err}")]
+    Encrypt { message_type: String, err: EncryptionError },
 }
 
 impl PeerActor {
@@ -140,6 +215,8 @@ impl PeerActor {
         peer_type: PeerType,
         framed: FramedWrite<Vec<u8>, WriteHalf, Codec, Codec>,
         handshake_timeout: time::Duration,
+        keep_alive_interval: time::Duration,
+        keep_alive_timeout: time::Duration,
         peer_manager_addr: Recipient<PeerToManagerMsg>,
         peer_manager_wrapper_addr: Recipient<ActixMessageWrapper<PeerToManagerMsg>>,
         client_addr: Recipient<NetworkClientMessages>,
@@ -147,8 +224,14 @@ impl PeerActor {
         partial_edge_info: Option<PartialEdgeInfo>,
         txns_since_last_block: Arc<AtomicUsize>,
         peer_counter: Arc<AtomicUsize>,
+        routed_message_dedup: Arc<Mutex<RoutedMessageDedup>>,
         throttle_controller: ThrottleController,
+        encryption_key: Option<SecretKey>,
         force_encoding: Option<Encoding>,
+        bandwidth_limiter: BandwidthLimiter,
+        pending_handshake: Option<PendingHandshake>,
+        recorder: Option<Arc<FrameRecorder>>,
+        chaos: Option<Arc<Chaos>>,
     ) -> Self {
         let now = clock.now();
         PeerActor {
@@ -161,21 +244,43 @@ impl PeerActor {
             protocol_version: PROTOCOL_VERSION,
             framed,
             handshake_timeout,
+            created_at: now,
+            keep_alive_interval,
+            keep_alive_timeout,
+            last_time_received: now,
             peer_manager_addr,
             peer_manager_wrapper_addr,
             client_addr,
             view_client_addr,
             tracker: Default::default(),
+            behavior: Default::default(),
             genesis_id: Default::default(),
+            my_height: 0,
+            num_shards: None,
             chain_info: Default::default(),
             partial_edge_info,
             last_time_received_message_update: now,
             txns_since_last_block,
             peer_counter,
-            routed_message_cache: LruCache::new(ROUTED_MESSAGE_CACHE_SIZE),
+            routed_message_dedup,
             throttle_controller,
             protocol_buffers_supported: false,
             force_encoding,
+            unknown_encoding_since: Some(now),
+            borsh_assumed: false,
+            encryption_key,
+            peer_supports_encryption: false,
+            encryption: None,
+            pending_messages: vec![],
+            pending_messages_bytes: 0,
+            bandwidth_limiter,
+            send_queue: SendQueue::default(),
+            flush_scheduled: false,
+            pending_handshake,
+            pow_difficulty: 0,
+            recorder,
+            chaos,
+            chaos_held: None,
         }
     }
 
@@ -190,53 +295,191 @@ impl PeerActor {
         if self.protocol_buffers_supported {
             return Some(Encoding::Proto);
         }
-        if self.peer_status == PeerStatus::Connecting {
+        if self.peer_status == PeerStatus::Connecting && !self.borsh_assumed {
             return None;
         }
         return Some(Encoding::Borsh);
     }
 
-    fn parse_message(&mut self, msg: &[u8]) -> Result<PeerMessage, ParsePeerMessageError> {
+    /// Records how long the encoding of the peer stayed unknown once it is determined,
+    /// and assumes Borsh if it stays unknown for too long.
+    fn update_encoding_state(&mut self) {
+        let since = match self.unknown_encoding_since {
+            Some(since) => since,
+            None => return,
+        };
+        let elapsed = self.clock.now() - since;
+        let label = match self.encoding() {
+            Some(enc) => {
+                debug!(target: "network", peer_addr = %self.peer_addr, ?enc, ?elapsed, "Encoding of the peer determined");
+                enc.into()
+            }
+            None if elapsed >= MAX_UNKNOWN_ENCODING_DURATION => {
+                info!(target: "network", peer_addr = %self.peer_addr, ?elapsed, "Encoding of the peer is still unknown, assuming Borsh");
+                self.borsh_assumed = true;
+                "BorshAssumed"
+            }
+            None => return,
+        };
+        metrics::PEER_UNKNOWN_ENCODING_DURATION
+            .with_label_values(&[label])
+            .observe(elapsed.as_seconds_f64());
+        self.unknown_encoding_since = None;
+    }
+
+    fn parse_message(&mut self, msg: &Bytes) -> Result<PeerMessage, ParsePeerMessageError> {
         let _span = tracing::trace_span!(target: "network", "parse_message").entered();
         if let Some(e) = self.encoding() {
-            return PeerMessage::deserialize(e, msg);
+            return PeerMessage::deserialize_bytes(e, msg);
         }
-        if let Ok(msg) = PeerMessage::deserialize(Encoding::Proto, msg) {
+        if let Ok(msg) = PeerMessage::deserialize_bytes(Encoding::Proto, msg) {
             self.protocol_buffers_supported = true;
+            self.update_encoding_state();
             return Ok(msg);
         }
         return PeerMessage::deserialize(Encoding::Borsh, msg);
     }
 
-    fn send_message_or_log(&mut self, msg: &PeerMessage) {
+    /// Whether `msg`, which failed to parse in the forced protobuf encoding, is a Borsh
+    /// handshake, which is refused with `NetworkConfig::protobuf_only`.
+    fn is_refused_borsh_handshake(&self, msg: &[u8]) -> bool {
+        self.force_encoding == Some(Encoding::Proto)
+            && self.peer_status == PeerStatus::Connecting
+            && matches!(
+                PeerMessage::deserialize(Encoding::Borsh, msg),
+                Ok(PeerMessage::Handshake(_))
+            )
+    }
+
+    fn send_message_or_log(&mut self, ctx: &mut Context<PeerActor>, msg: &PeerMessage) {
         if let Err(err) = self.send_message(msg) {
             warn!(target: "network", "send_message(): {}", err);
         }
+        self.schedule_flush_send_queue(ctx);
     }
 
     fn send_message(&mut self, msg: &PeerMessage) -> Result<(), IOError> {
+        if self.is_encryption_pending() && !is_plaintext_message(msg) {
+            // Messages will be sent once the encryption handshake is completed.
+            let bytes = msg.serialize(Encoding::Proto).len();
+            if self.pending_messages_bytes + bytes > MAX_PENDING_ENCRYPTION_BYTES {
+                let msg_type: &'static str = msg.into();
+                debug!(target: "network", "Dropping {} to {}: too much data waiting for encryption", msg_type, self.peer_info);
+                metrics::PEER_ENCRYPTION_PENDING_MESSAGES_DROPPED
+                    .with_label_values(&[msg_type])
+                    .inc();
+                return Ok(());
+            }
+            self.pending_messages_bytes += bytes;
+            self.pending_messages.push(msg.clone());
+            return Ok(());
+        }
+        self.update_encoding_state();
         if let Some(enc) = self.encoding() {
-            return self.send_message_with_encoding(msg, enc);
+            return self.send_message_with_encoding(msg, enc).map(drop);
         }
         self.send_message_with_encoding(msg, Encoding::Proto)?;
-        self.send_message_with_encoding(msg, Encoding::Borsh)?;
+        let duplicate_bytes = self.send_message_with_encoding(msg, Encoding::Borsh)?;
+        if duplicate_bytes > 0 {
+            let msg_type: &'static str = msg.into();
+            metrics::PEER_DOUBLE_ENCODED_MESSAGES.with_label_values(&[msg_type]).inc();
+            metrics::PEER_DOUBLE_ENCODED_BYTES.inc_by(duplicate_bytes as u64);
+        }
         Ok(())
     }
 
+    /// Returns the number of bytes queued, 0 if the message was skipped or dropped.
     fn send_message_with_encoding(
         &mut self,
         msg: &PeerMessage,
         enc: Encoding,
-    ) -> Result<(), IOError> {
+    ) -> Result<usize, IOError> {
         // Skip sending block and headers if we received it or header from this peer.
         // Record block requests in tracker.
         match msg {
-            PeerMessage::Block(b) if self.tracker.has_received(b.hash()) => return Ok(()),
-            PeerMessage::BlockRequest(h) => self.tracker.push_request(*h),
+            PeerMessage::Block(b) if self.tracker.has_received(b.hash()) => return Ok(0),
+            PeerMessage::BlockRequest(h) => self.tracker.push_request(*h, self.clock.now()),
             _ => (),
         };
 
-        let bytes = msg.serialize(enc);
+        let msg = QueuedMessage {
+            msg_type: msg.into(),
+            bytes: msg.serialize(enc),
+            enc,
+            plaintext: is_plaintext_message(msg),
+            priority: message_priority(msg),
+        };
+        if msg.priority == Priority::Bulk {
+            if self.send_queue.bytes(Priority::Bulk) + msg.bytes.len() > MAX_QUEUED_BULK_BYTES {
+                debug!(target: "network", "Dropping {} to {}: too much bulk data queued", msg.msg_type, self.peer_info);
+                metrics::PEER_MESSAGES_THROTTLED
+                    .with_label_values(&[msg.msg_type, "dropped"])
+                    .inc();
+                return Ok(0);
+            }
+            if !self.send_queue.is_empty(Priority::Bulk)
+                || !self.bandwidth_limiter.can_send(self.clock.now())
+            {
+                metrics::PEER_MESSAGES_THROTTLED
+                    .with_label_values(&[msg.msg_type, "delayed"])
+                    .inc();
+            }
+        }
+        let bytes = msg.bytes.len();
+        self.send_queue.push(msg);
+        self.flush_send_queue()?;
+        Ok(bytes)
+    }
+
+    /// Writes the queued messages to the connection, highest priority first, until its
+    /// write buffer fills up. Bulk messages are written only as far as the bandwidth limits allow.
+    fn flush_send_queue(&mut self) -> Result<(), IOError> {
+        while self.framed.buffer_len() < MAX_WRITE_BUFFER_BYTES {
+            let allow_bulk = self.bandwidth_limiter.can_send(self.clock.now());
+            match self.send_queue.pop(allow_bulk) {
+                Some(msg) => self.write_message(msg.msg_type, msg.bytes, msg.enc, msg.plaintext)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Schedules a retry of sending the queued messages, as long as there are any left which
+    /// the write buffer or the bandwidth limits didn't let through.
+    fn schedule_flush_send_queue(&mut self, ctx: &mut Context<PeerActor>) {
+        if self.flush_scheduled || self.send_queue.is_all_empty() {
+            return;
+        }
+        self.flush_scheduled = true;
+        near_performance_metrics::actix::run_later(
+            ctx,
+            SEND_QUEUE_FLUSH_INTERVAL.try_into().unwrap(),
+            |act, ctx| {
+                act.flush_scheduled = false;
+                if let Err(err) = act.flush_send_queue() {
+                    warn!(target: "network", "send_message(): {}", err);
+                }
+                act.schedule_flush_send_queue(ctx);
+            },
+        );
+    }
+
+    /// Writes a serialized message to the connection, encrypting it if needed.
+    fn write_message(
+        &mut self,
+        msg_type: &'static str,
+        mut bytes: Vec<u8>,
+        enc: Encoding,
+        plaintext: bool,
+    ) -> Result<(), IOError> {
+        if let Some(session) = self.encryption.as_mut() {
+            if session.is_established() && !plaintext {
+                let ciphertext = session
+                    .encrypt(&bytes)
+                    .map_err(|err| IOError::Encrypt { message_type: msg_type.to_string(), err })?;
+                bytes = PeerMessage::Encrypted(ciphertext.into()).serialize(enc);
+            }
+        }
         self.tracker.increment_sent(bytes.len() as u64);
         let bytes_len = bytes.len();
         if !self.framed.write(bytes) {
@@ -244,9 +487,9 @@ impl PeerActor {
             let tid = near_rust_allocator_proxy::get_tid();
             #[cfg(not(feature = "performance_stats"))]
             let tid = 0;
-            let msg_type: &str = msg.into();
             return Err(IOError::Send { tid, message_type: msg_type.to_string(), size: bytes_len });
         }
+        self.bandwidth_limiter.record_sent(self.clock.now(), bytes_len);
         Ok(())
     }
 
@@ -256,8 +499,15 @@ impl PeerActor {
                 .send(NetworkViewClientMessages::GetChainInfo)
                 .into_actor(self)
                 .then(move |res, act, _ctx| match res {
-                    Ok(NetworkViewClientResponses::ChainInfo { genesis_id, .. }) => {
+                    Ok(NetworkViewClientResponses::ChainInfo {
+                        genesis_id,
+                        height,
+                        num_shards,
+                        ..
+                    }) => {
                         act.genesis_id = genesis_id;
+                        act.my_height = height;
+                        act.num_shards = num_shards;
                         actix::fut::ready(())
                     }
                     Err(err) => {
@@ -278,29 +528,35 @@ impl PeerActor {
         self.view_client_addr
             .send(NetworkViewClientMessages::GetChainInfo)
             .into_actor(self)
-            .then(move |res, act, _ctx| match res {
+            .then(move |res, act, ctx| match res {
                 Ok(NetworkViewClientResponses::ChainInfo {
                     genesis_id,
                     height,
                     tracked_shards,
                     archival,
+                    ..
                 }) => {
-                    let handshake = match act.protocol_version {
-                        39..=PROTOCOL_VERSION => PeerMessage::Handshake(Handshake::new(
+                    let mut handshake = match act.protocol_version {
+                        39..=PROTOCOL_VERSION => Handshake::new(
                             act.protocol_version,
                             act.my_node_id().clone(),
                             act.other_peer_id().unwrap().clone(),
                             act.my_node_info.addr_port(),
                             PeerChainInfoV2 { genesis_id, height, tracked_shards, archival },
                             act.partial_edge_info.as_ref().unwrap().clone(),
-                        )),
+                            act.encryption_key.as_ref(),
+                        ),
                         _ => {
                             error!(target: "network", "Trying to talk with peer with no supported version: {}", act.protocol_version);
                             return actix::fut::ready(());
                         }
                     };
+                    if act.pow_difficulty > 0 {
+                        handshake.solve_proof_of_work(act.pow_difficulty);
+                    }
+                    handshake.sender_time = Some(act.clock.now_utc());
 
-                    act.send_message_or_log(&handshake);
+                    act.send_message_or_log(ctx, &PeerMessage::Handshake(handshake));
                     actix::fut::ready(())
                 }
                 Err(err) => {
@@ -319,6 +575,24 @@ impl PeerActor {
         ctx.stop();
     }
 
+    /// Applies the consequence of a failure to handle a message received from the peer and
+    /// counts it in `behavior`, see `peer::errors`.
+    fn on_peer_error(&mut self, ctx: &mut Context<PeerActor>, err: PeerError) {
+        metrics::PEER_ERRORS.with_label_values(&[err.kind()]).inc();
+        self.behavior.errors.add(&err);
+        match err {
+            PeerError::Ban(ban_reason) => self.ban_peer(ctx, ban_reason),
+            PeerError::ProtocolViolation(violation) if violation.is_fatal() => {
+                warn!(target: "network", %violation, "Protocol violation by {}. Disconnecting.", self.peer_info);
+                ctx.stop();
+            }
+            PeerError::Transient(err) => {
+                error!(target: "network", %err, "Failed to handle message from {}", self.peer_info);
+            }
+            err => debug!(target: "network", %err, "Dropping message from {}", self.peer_info),
+        }
+    }
+
     /// `PeerId` of the current node.
     fn my_node_id(&self) -> &PeerId {
         &self.my_node_info.id
@@ -329,6 +603,106 @@ impl PeerActor {
         self.peer_info.as_ref().as_ref().map(|peer_info| &peer_info.id)
     }
 
+    /// Whether both peers support encryption, i.e. the connection has to be encrypted.
+    /// Encryption support can be advertised only in proto encoding, so encrypted messages
+    /// are always proto encoded.
+    fn is_encryption_negotiated(&self) -> bool {
+        self.encryption_key.is_some()
+            && self.peer_supports_encryption
+            && self.encoding() == Some(Encoding::Proto)
+    }
+
+    /// Whether the connection has to be encrypted, but the encryption handshake
+    /// has not been completed yet.
+    fn is_encryption_pending(&self) -> bool {
+        self.is_encryption_negotiated()
+            && !self.encryption.as_ref().map_or(false, Session::is_established)
+    }
+
+    /// Starts the encryption handshake, if both peers support encryption.
+    /// It is initiated by the outbound peer once the connection is established.
+    fn start_encryption(&mut self, ctx: &mut Context<PeerActor>) {
+        if !self.is_encryption_negotiated() {
+            return;
+        }
+        let peer_id = self.other_peer_id().unwrap().clone();
+        match Session::initiate(self.encryption_key.as_ref().unwrap(), peer_id) {
+            Ok((session, msg)) => {
+                self.encryption = Some(session);
+                self.send_message_or_log(ctx, &PeerMessage::EncryptionHandshake(msg));
+            }
+            Err(err) => self.stop_on_encryption_error(ctx, err),
+        }
+    }
+
+    fn receive_encryption_handshake(
+        &mut self,
+        ctx: &mut Context<PeerActor>,
+        msg: &[u8],
+    ) -> Result<(), EncryptionError> {
+        let key = match &self.encryption_key {
+            Some(key) if self.peer_supports_encryption => key,
+            _ => return Err(EncryptionError::NotNegotiated),
+        };
+        if self.encryption.is_none() {
+            // Inbound peer responds to the handshake initiated by the outbound peer.
+            if self.peer_type != PeerType::Inbound {
+                return Err(EncryptionError::NotNegotiated);
+            }
+            let peer_id = self.peer_info.as_ref().as_ref().unwrap().id.clone();
+            self.encryption = Some(Session::respond(key, peer_id)?);
+        }
+        let resp = self.encryption.as_mut().unwrap().handshake(msg)?;
+        if let Some(resp) = resp {
+            self.send_message_or_log(ctx, &PeerMessage::EncryptionHandshake(resp));
+        }
+        if !self.is_encryption_pending() {
+            metrics::PEER_ENCRYPTION_HANDSHAKES_TOTAL.with_label_values(&["ok"]).inc();
+            debug!(target: "network", "Established encrypted connection with {}", self.peer_info);
+            self.pending_messages_bytes = 0;
+            for msg in std::mem::take(&mut self.pending_messages) {
+                self.send_message_or_log(ctx, &msg);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop_on_encryption_error(&mut self, ctx: &mut Context<PeerActor>, err: EncryptionError) {
+        metrics::PEER_ENCRYPTION_HANDSHAKES_TOTAL.with_label_values(&["error"]).inc();
+        warn!(target: "network", "Encryption handshake with {} failed: {}", self.peer_info, err);
+        ctx.stop();
+    }
+
+    /// Unwraps the message received from the peer, if it is encrypted.
+    fn decrypt_message(&mut self, msg: PeerMessage) -> Result<PeerMessage, PeerError> {
+        let ciphertext = match msg {
+            PeerMessage::Encrypted(ciphertext) => ciphertext,
+            msg => {
+                let encrypted = self.encryption.as_ref().map_or(false, Session::is_established);
+                if encrypted || (self.is_encryption_negotiated() && !is_plaintext_message(&msg)) {
+                    return Err(ProtocolViolation::Unencrypted(msg.msg_variant()).into());
+                }
+                return Ok(msg);
+            }
+        };
+        let encoding = self.encoding();
+        let plaintext = match (self.encryption.as_mut(), encoding) {
+            (Some(session), Some(_)) => session.decrypt(&ciphertext),
+            _ => Err(EncryptionError::NotNegotiated),
+        };
+        let plaintext = Bytes::from(plaintext.map_err(ProtocolViolation::Encryption)?);
+        match PeerMessage::deserialize_bytes(encoding.unwrap(), &plaintext) {
+            Ok(PeerMessage::EncryptionHandshake(_) | PeerMessage::Encrypted(_)) => {
+                Err(ProtocolViolation::NestedEncryption.into())
+            }
+            Ok(msg) => Ok(msg),
+            Err(err) => {
+                trace!(target: "network", "Received invalid data {:?} from {}", logging::pretty_vec(&plaintext), self.peer_info);
+                Err(err.into())
+            }
+        }
+    }
+
     fn receive_message(&mut self, ctx: &mut Context<PeerActor>, msg: PeerMessage) {
         if msg.is_view_client_message() {
             self.receive_view_client_message(ctx, msg);
@@ -388,7 +762,7 @@ impl PeerActor {
         self.view_client_addr
             .send(view_client_message)
             .into_actor(self)
-            .then(move |res, act, _ctx| {
+            .then(move |res, act, ctx| {
                 // Ban peer if client thinks received data is bad.
                 match res {
                     Ok(NetworkViewClientResponses::TxStatus(tx_result)) => {
@@ -420,27 +794,21 @@ impl PeerActor {
                     }
                     Ok(NetworkViewClientResponses::Block(block)) => {
                         // MOO need protocol version
-                        act.send_message_or_log(&PeerMessage::Block(*block));
+                        act.send_message_or_log(ctx, &PeerMessage::Block(*block));
                     }
                     Ok(NetworkViewClientResponses::BlockHeaders(headers)) => {
-                        act.send_message_or_log(&PeerMessage::BlockHeaders(headers));
+                        act.send_message_or_log(ctx, &PeerMessage::BlockHeaders(headers));
                     }
                     Ok(NetworkViewClientResponses::EpochSyncResponse(response)) => {
-                        act.send_message_or_log(&PeerMessage::EpochSyncResponse(response));
+                        act.send_message_or_log(ctx, &PeerMessage::EpochSyncResponse(response));
                     }
                     Ok(NetworkViewClientResponses::EpochSyncFinalizationResponse(response)) => {
-                        act.send_message_or_log(&PeerMessage::EpochSyncFinalizationResponse(
-                            response,
-                        ));
-                    }
-                    Err(err) => {
-                        error!(
-                            target: "network",
-                            "Received error sending message to view client: {} for {}",
-                            err, act.peer_info
+                        act.send_message_or_log(
+                            ctx,
+                            &PeerMessage::EpochSyncFinalizationResponse(response),
                         );
-                        return actix::fut::ready(());
                     }
+                    Err(err) => act.on_peer_error(ctx, err.into()),
                     _ => {}
                 };
                 actix::fut::ready(())
@@ -463,6 +831,11 @@ impl PeerActor {
             PeerMessage::Block(block) => {
                 let block_hash = *block.hash();
                 self.tracker.push_received(block_hash);
+                let now = self.clock.now();
+                if let Some(latency) = self.tracker.response_latency(&block_hash, now) {
+                    self.behavior.useful_responses += 1;
+                    self.behavior.response_latencies.push(latency);
+                }
                 self.chain_info.height = max(self.chain_info.height, block.header().height());
                 NetworkClientMessages::Block(block, peer_id, self.tracker.has_request(&block_hash))
             }
@@ -500,6 +873,7 @@ impl PeerActor {
                         NetworkClientMessages::PartialEncodedChunkRequest(request, msg_hash)
                     }
                     RoutedMessageBody::PartialEncodedChunkResponse(response) => {
+                        self.behavior.useful_responses += 1;
                         NetworkClientMessages::PartialEncodedChunkResponse(
                             response,
                             self.clock.now().into(),
@@ -541,7 +915,7 @@ impl PeerActor {
             PeerMessage::Handshake(_)
             | PeerMessage::HandshakeFailure(_, _)
             | PeerMessage::PeersRequest
-            | PeerMessage::PeersResponse(_)
+            | PeerMessage::PeersResponse(..)
             | PeerMessage::SyncRoutingTable(_)
             | PeerMessage::LastEdge(_)
             | PeerMessage::Disconnect
@@ -550,7 +924,15 @@ impl PeerActor {
             | PeerMessage::BlockRequest(_)
             | PeerMessage::BlockHeadersRequest(_)
             | PeerMessage::EpochSyncRequest(_)
-            | PeerMessage::EpochSyncFinalizationRequest(_) => {
+            | PeerMessage::EpochSyncFinalizationRequest(_)
+            | PeerMessage::EncryptionHandshake(_)
+            | PeerMessage::Encrypted(_)
+            | PeerMessage::AddressChallenge(_)
+            | PeerMessage::AddressProof(_)
+            | PeerMessage::AddressChallengeRequest
+            | PeerMessage::ProtocolDeprecation(_)
+            | PeerMessage::KeepAlive
+            | PeerMessage::NetworkAdvisory(_) => {
                 error!(target: "network", "Peer receive_client_message received unexpected type: {:?}", msg);
                 return;
             }
@@ -567,16 +949,9 @@ impl PeerActor {
                         // TODO: count as malicious behavior?
                     }
                     Ok(NetworkClientResponses::Ban { ban_reason }) => {
-                        act.ban_peer(ctx, ban_reason);
-                    }
-                    Err(err) => {
-                        error!(
-                            target: "network",
-                            "Received error sending message to client: {} for {}",
-                            err, act.peer_info
-                        );
-                        return actix::fut::ready(());
+                        act.on_peer_error(ctx, PeerError::Ban(ban_reason));
                     }
+                    Err(err) => act.on_peer_error(ctx, err.into()),
                     _ => {}
                 };
                 actix::fut::ready(())
@@ -584,6 +959,29 @@ impl PeerActor {
             .spawn(ctx);
     }
 
+    /// Periodically sends `KeepAlive` to the peer and closes the connection if nothing has
+    /// been received from the peer for `keep_alive_timeout`, e.g. because the connection is
+    /// half-open after the peer's host was suspended. Peers which don't send `KeepAlive`
+    /// themselves are not checked, since idle connections with them are silent.
+    fn keep_alive_trigger(&mut self, ctx: &mut Context<PeerActor>) {
+        if self.peer_status == PeerStatus::Ready
+            && self.protocol_version >= KEEP_ALIVE_PROTOCOL_VERSION
+        {
+            if self.clock.now() - self.last_time_received > self.keep_alive_timeout {
+                info!(target: "network", peer_info = %self.peer_info, "Nothing received from the peer for too long, closing the connection");
+                metrics::PEER_KEEP_ALIVE_TIMEOUTS.inc();
+                ctx.stop();
+                return;
+            }
+            self.send_message_or_log(ctx, &PeerMessage::KeepAlive);
+        }
+        near_performance_metrics::actix::run_later(
+            ctx,
+            self.keep_alive_interval.try_into().unwrap(),
+            |act, ctx| act.keep_alive_trigger(ctx),
+        );
+    }
+
     /// Hook called on every valid message received from this peer from the network.
     fn on_receive_message(&mut self) {
         if let Some(peer_id) = self.other_peer_id().cloned() {
@@ -640,13 +1038,23 @@ impl Actor for PeerActor {
             ctx,
             self.handshake_timeout.try_into().unwrap(),
             move |act, ctx| {
-                if act.peer_status != PeerStatus::Ready {
-                    info!(target: "network", "Handshake timeout expired for {}", act.peer_info);
-                    ctx.stop();
-                }
+                let cause = if act.peer_status != PeerStatus::Ready {
+                    "handshake"
+                } else if act.is_encryption_pending() {
+                    "encryption"
+                } else {
+                    return;
+                };
+                info!(target: "network", timeout = ?act.handshake_timeout, cause, "Handshake timeout expired for {}", act.peer_info);
+                metrics::PEER_HANDSHAKE_TIMEOUTS
+                    .with_label_values(&[act.peer_type.into(), cause])
+                    .inc();
+                ctx.stop();
             },
         );
 
+        self.keep_alive_trigger(ctx);
+
         // If outbound peer, initiate handshake.
         if self.peer_type == PeerType::Outbound {
             self.send_handshake(ctx);
@@ -687,46 +1095,146 @@ impl Actor for PeerActor {
 
 impl WriteHandler<io::Error> for PeerActor {}
 
-impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
+impl StreamHandler<Result<Bytes, ReasonForBan>> for PeerActor {
     #[perf]
-    fn handle(&mut self, msg: Result<Vec<u8>, ReasonForBan>, ctx: &mut Self::Context) {
+    fn handle(&mut self, msg: Result<Bytes, ReasonForBan>, ctx: &mut Self::Context) {
         let _span = tracing::trace_span!(target: "network", "handle").entered();
         let msg = match msg {
             Ok(msg) => msg,
             Err(ban_reason) => {
-                self.ban_peer(ctx, ban_reason);
+                self.on_peer_error(ctx, PeerError::Ban(ban_reason));
                 return;
             }
         };
         // TODO(#5155) We should change our code to track size of messages received from Peer
         // as long as it travels to PeerManager, etc.
 
+        self.last_time_received = self.clock.now();
+
         self.update_stats_on_receiving_message(msg.len());
+        // Routed messages are deduplicated before deserialization where possible, i.e. unless
+        // the connection is encrypted.
+        let mut deduplicated = false;
+        if let Some(key) =
+            self.encoding().and_then(|enc| PeerMessage::routed_message_key(enc, &msg))
+        {
+            if self.is_duplicate_routed_message(&key) {
+                // The body is not deserialized yet.
+                metrics::MessageDropped::DuplicateRouted.inc_msg_type("Routed");
+                return;
+            }
+            deduplicated = true;
+        }
         let peer_msg = match self.parse_message(&msg) {
             Ok(msg) => msg,
+            Err(_) if self.is_refused_borsh_handshake(&msg) => {
+                debug!(target: "network", peer_addr = %self.peer_addr, "Refusing Borsh handshake, only protobuf is supported");
+                metrics::PEER_BORSH_HANDSHAKES_REFUSED.inc();
+                ctx.stop();
+                return;
+            }
             Err(err) => {
-                debug!(target: "network", "Received invalid data {:?} from {}: {}", logging::pretty_vec(&msg), self.peer_info, err);
+                trace!(target: "network", "Received invalid data {:?} from {}", logging::pretty_vec(&msg), self.peer_info);
+                metrics::MessageDropped::ParseError.inc_unknown_msg();
+                self.on_peer_error(ctx, err.into());
                 return;
             }
         };
-
-        if self.should_we_drop_msg(&peer_msg) {
-            return;
-        }
-
-        // Drop duplicated messages routed within DROP_DUPLICATED_MESSAGES_PERIOD ms
-        if let PeerMessage::Routed(msg) = &peer_msg {
-            let msg = &msg.msg;
-            let key = (msg.author.clone(), msg.target.clone(), msg.signature.clone());
-            let now = self.clock.now();
-            if let Some(&t) = self.routed_message_cache.get(&key) {
-                if now <= t + DROP_DUPLICATED_MESSAGES_PERIOD {
-                    debug!(target: "network", "Dropping duplicated message from {} to {:?}", msg.author, msg.target);
+        let encrypted = matches!(peer_msg, PeerMessage::Encrypted(_));
+        let peer_msg = match self.decrypt_message(peer_msg) {
+            Ok(msg) => msg,
+            Err(err) => {
+                self.on_peer_error(ctx, err);
+                return;
+            }
+        };
+        if !deduplicated {
+            if let PeerMessage::Routed(routed) = &peer_msg {
+                let key = network_protocol::routed_message_key(&routed.msg);
+                if self.is_duplicate_routed_message(&key) {
+                    metrics::MessageDropped::DuplicateRouted.inc_peer_msg(&peer_msg);
                     return;
                 }
             }
-            self.routed_message_cache.put(key, now);
         }
+        if let Some(recorder) = &self.recorder {
+            if encrypted {
+                let data = peer_msg.serialize(Encoding::Proto);
+                recorder.record(&self.clock, self.other_peer_id(), &data);
+            } else {
+                recorder.record(&self.clock, self.other_peer_id(), &msg);
+            }
+        }
+
+        match self
+            .chaos
+            .as_ref()
+            .and_then(|chaos| chaos.action(peer_msg.msg_variant(), self.clock.now()))
+        {
+            None => {}
+            Some(ChaosAction::Drop) => return,
+            Some(ChaosAction::Delay(delay)) => {
+                let msg_len = msg.len();
+                near_performance_metrics::actix::run_later(ctx, delay, move |act, ctx| {
+                    act.process_message(ctx, peer_msg, msg_len)
+                });
+                return;
+            }
+            Some(ChaosAction::Reorder) if self.chaos_held.is_none() => {
+                self.chaos_held = Some((peer_msg, msg.len()));
+                return;
+            }
+            Some(ChaosAction::Reorder) => {}
+            Some(ChaosAction::Duplicate) => self.process_message(ctx, peer_msg.clone(), msg.len()),
+        }
+        self.process_message(ctx, peer_msg, msg.len());
+        if let Some((held, msg_len)) = self.chaos_held.take() {
+            self.process_message(ctx, held, msg_len);
+        }
+    }
+}
+
+impl PeerActor {
+    /// Whether the routed message is a duplicate of one received recently from any peer.
+    fn is_duplicate_routed_message(&mut self, key: &RoutedMessageKey) -> bool {
+        let duplicate = self.routed_message_dedup.lock().is_duplicate(key, self.clock.now_utc());
+        metrics::ROUTED_MESSAGE_DEDUP
+            .with_label_values(&[if duplicate { "hit" } else { "miss" }])
+            .inc();
+        if duplicate {
+            debug!(target: "network", author = %key.0, target = ?key.1, "Dropping duplicated routed message");
+            self.behavior.duplicate_routed_messages += 1;
+        }
+        duplicate
+    }
+
+    /// Handles a message received from the peer. `msg_len` is the size of its frame.
+    fn process_message(
+        &mut self,
+        ctx: &mut Context<PeerActor>,
+        peer_msg: PeerMessage,
+        msg_len: usize,
+    ) {
+        if let Err(err) = self.try_process_message(ctx, peer_msg, msg_len) {
+            self.on_peer_error(ctx, err);
+        }
+    }
+
+    fn try_process_message(
+        &mut self,
+        ctx: &mut Context<PeerActor>,
+        peer_msg: PeerMessage,
+        msg_len: usize,
+    ) -> Result<(), PeerError> {
+        if let PeerStatus::Banned(_) = self.peer_status {
+            metrics::MessageDropped::PeerBanned.inc_peer_msg(&peer_msg);
+            return Ok(());
+        }
+        if self.should_we_drop_msg(&peer_msg) {
+            metrics::MessageDropped::TooManyTransactions.inc_peer_msg(&peer_msg);
+            return Ok(());
+        }
+
         if let PeerMessage::Routed(routed) = &peer_msg {
             if let RoutedMessage { body: RoutedMessageBody::ForwardTx(_), .. } = routed.as_ref().msg
             {
@@ -745,7 +1253,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_TOTAL.with_label_values(&labels).inc();
             metrics::PEER_MESSAGE_RECEIVED_BY_TYPE_BYTES
                 .with_label_values(&labels)
-                .inc_by(msg.len() as u64);
+                .inc_by(msg_len as u64);
         }
 
         match (self.peer_status, peer_msg) {
@@ -769,7 +1277,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                             // Use target_version as protocol_version to talk with this peer
                             self.protocol_version = target_version;
                             self.send_handshake(ctx);
-                            return;
+                            return Ok(());
                         } else {
                             warn!(target: "network", "Unable to connect to a node ({}) due to a network protocol version mismatch. Our version: {:?}, their: {:?}", peer_info, (PROTOCOL_VERSION, PEER_MIN_ALLOWED_PROTOCOL_VERSION), (version, oldest_supported_version));
                         }
@@ -783,6 +1291,32 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                             ),
                         );
                     }
+                    HandshakeFailureReason::ProofOfWorkRequired { difficulty } => {
+                        // Retry only with a higher difficulty, so that a peer rejecting
+                        // our proof doesn't keep us busy.
+                        if self.peer_type == PeerType::Outbound
+                            && self.pow_difficulty < difficulty
+                            && difficulty <= MAX_PROOF_OF_WORK_DIFFICULTY
+                        {
+                            self.pow_difficulty = difficulty;
+                            self.send_handshake(ctx);
+                            return Ok(());
+                        }
+                        warn!(target: "network", "Unable to connect to a node ({}), which requires a proof of work of {} bits.", peer_info, difficulty);
+                    }
+                }
+                ctx.stop();
+            }
+            (PeerStatus::Connecting, PeerMessage::AddressChallenge(challenge))
+                if self.peer_type == PeerType::Inbound =>
+            {
+                // The challenge is the only message sent over the connection.
+                if &challenge.target == self.my_node_id() {
+                    let _ = self
+                        .peer_manager_addr
+                        .do_send(PeerToManagerMsg::AddressChallenge(challenge));
+                } else {
+                    debug!(target: "network", peer_addr = ?self.peer_addr, target = ?challenge.target, "Received address challenge for another peer.");
                 }
                 ctx.stop();
             }
@@ -796,14 +1330,17 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                         target: "network",
                         version = handshake.protocol_version,
                         "Received connection from node with unsupported PROTOCOL_VERSION.");
-                    self.send_message_or_log(&PeerMessage::HandshakeFailure(
-                        self.my_node_info.clone(),
-                        HandshakeFailureReason::ProtocolVersionMismatch {
-                            version: PROTOCOL_VERSION,
-                            oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
-                        },
-                    ));
-                    return;
+                    self.send_message_or_log(
+                        ctx,
+                        &PeerMessage::HandshakeFailure(
+                            self.my_node_info.clone(),
+                            HandshakeFailureReason::ProtocolVersionMismatch {
+                                version: PROTOCOL_VERSION,
+                                oldest_supported_version: PEER_MIN_ALLOWED_PROTOCOL_VERSION,
+                            },
+                        ),
+                    );
+                    return Ok(());
                     // Connection will be closed by a handshake timeout
                 }
                 let target_version = std::cmp::min(handshake.protocol_version, PROTOCOL_VERSION);
@@ -811,28 +1348,69 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
 
                 if handshake.sender_chain_info.genesis_id != self.genesis_id {
                     debug!(target: "network", "Received connection from node with different genesis.");
-                    self.send_message_or_log(&PeerMessage::HandshakeFailure(
-                        self.my_node_info.clone(),
-                        HandshakeFailureReason::GenesisMismatch(self.genesis_id.clone()),
-                    ));
-                    return;
+                    metrics::PEER_CHAIN_INFO_REJECTED
+                        .with_label_values(&[InvalidChainInfo::GenesisMismatch.as_ref()])
+                        .inc();
+                    self.send_message_or_log(
+                        ctx,
+                        &PeerMessage::HandshakeFailure(
+                            self.my_node_info.clone(),
+                            HandshakeFailureReason::GenesisMismatch(self.genesis_id.clone()),
+                        ),
+                    );
+                    return Ok(());
                     // Connection will be closed by a handshake timeout
                 }
 
+                if let Err(err) = chain_info::check_tracked_shards(
+                    &handshake.sender_chain_info,
+                    self.my_height,
+                    self.num_shards,
+                ) {
+                    metrics::PEER_CHAIN_INFO_REJECTED.with_label_values(&[err.as_ref()]).inc();
+                    debug!(target: "network", ?err, tracked_shards = ?handshake.sender_chain_info.tracked_shards, "Received invalid chain info on handshake from {}", handshake.sender_peer_id);
+                    return Err(PeerError::Ban(ReasonForBan::InvalidChainInfo));
+                }
+
                 if handshake.sender_peer_id == self.my_node_info.id {
                     metrics::RECEIVED_INFO_ABOUT_ITSELF.inc();
                     debug!(target: "network", "Received info about itself. Disconnecting this peer.");
                     ctx.stop();
-                    return;
+                    return Ok(());
                 }
 
                 if handshake.target_peer_id != self.my_node_info.id {
                     debug!(target: "network", "Received handshake from {:?} to {:?} but I am {:?}", handshake.sender_peer_id, handshake.target_peer_id, self.my_node_info.id);
-                    self.send_message_or_log(&PeerMessage::HandshakeFailure(
-                        self.my_node_info.clone(),
-                        HandshakeFailureReason::InvalidTarget,
-                    ));
-                    return;
+                    self.send_message_or_log(
+                        ctx,
+                        &PeerMessage::HandshakeFailure(
+                            self.my_node_info.clone(),
+                            HandshakeFailureReason::InvalidTarget,
+                        ),
+                    );
+                    return Ok(());
+                    // Connection will be closed by a handshake timeout
+                }
+
+                let pow_difficulty =
+                    self.pending_handshake.as_ref().map_or(0, |h| h.pow_difficulty);
+                if !handshake.verify_proof_of_work(pow_difficulty) {
+                    debug!(target: "network", peer_addr = ?self.peer_addr, pow_difficulty, "Received handshake without the required proof of work.");
+                    // The proof of work can be sent only in proto encoding.
+                    if self.encoding() != Some(Encoding::Proto) {
+                        ctx.stop();
+                        return Ok(());
+                    }
+                    self.send_message_or_log(
+                        ctx,
+                        &PeerMessage::HandshakeFailure(
+                            self.my_node_info.clone(),
+                            HandshakeFailureReason::ProofOfWorkRequired {
+                                difficulty: pow_difficulty,
+                            },
+                        ),
+                    );
+                    return Ok(());
                     // Connection will be closed by a handshake timeout
                 }
 
@@ -842,9 +1420,10 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                     &handshake.sender_peer_id,
                     &handshake.partial_edge_info,
                 ) {
-                    warn!(target: "network", "Received invalid signature on handshake. Disconnecting peer {}", handshake.sender_peer_id);
-                    self.ban_peer(ctx, ReasonForBan::InvalidSignature);
-                    return;
+                    return Err(PeerError::Ban(ReasonForBan::InvalidSignature));
+                }
+                if !handshake.verify_encryption_signature() {
+                    return Err(PeerError::Ban(ReasonForBan::InvalidSignature));
                 }
 
                 // Check that received nonce on handshake match our proposed nonce.
@@ -852,9 +1431,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                     && handshake.partial_edge_info.nonce
                         != self.partial_edge_info.as_ref().map(|edge_info| edge_info.nonce).unwrap()
                 {
-                    warn!(target: "network", "Received invalid nonce on handshake. Disconnecting peer {}", handshake.sender_peer_id);
-                    ctx.stop();
-                    return;
+                    return Err(ProtocolViolation::HandshakeNonceMismatch.into());
                 }
 
                 let peer_info = PeerInfo {
@@ -865,6 +1442,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                     account_id: None,
                 };
                 self.chain_info = handshake.sender_chain_info.clone();
+                self.peer_supports_encryption = handshake.sender_supports_encryption();
                 self.peer_manager_wrapper_addr
                     .send(ActixMessageWrapper::new_without_size(PeerToManagerMsg::RegisterPeer(RegisterPeer {
                         actor: ctx.address(),
@@ -875,6 +1453,8 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                         other_edge_info: handshake.partial_edge_info.clone(),
                         peer_protocol_version: self.protocol_version,
                         throttle_controller: self.throttle_controller.clone(),
+                        clock_offset: handshake.sender_time.map(|sent| sent - self.clock.now_utc()),
+                        peer_supports_encryption: self.peer_supports_encryption,
                     }), Some(self.throttle_controller.clone())))
                     .into_actor(self)
                     .then(move |res, act, ctx| {
@@ -882,16 +1462,23 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                             Ok(RegisterPeerResponse::Accept(edge_info)) => {
                                 act.peer_info = Some(peer_info).into();
                                 act.peer_status = PeerStatus::Ready;
+                                act.pending_handshake = None;
+                                metrics::PEER_HANDSHAKE_DURATION
+                                    .with_label_values(&[act.peer_type.into()])
+                                    .observe((act.clock.now() - act.created_at).as_seconds_f64());
+                                act.update_encoding_state();
                                 // Respond to handshake if it's inbound and connection was consolidated.
                                 if act.peer_type == PeerType::Inbound {
                                     act.partial_edge_info = edge_info;
                                     act.send_handshake(ctx);
+                                } else {
+                                    act.start_encryption(ctx);
                                 }
                                 actix::fut::ready(())
                             },
                             Ok(RegisterPeerResponse::InvalidNonce(edge)) => {
                                 debug!(target: "network", "{:?}: Received invalid nonce from peer {:?} sending evidence.", act.my_node_id(), act.peer_addr);
-                                act.send_message_or_log(&PeerMessage::LastEdge(*edge));
+                                act.send_message_or_log(ctx, &PeerMessage::LastEdge(*edge));
                                 actix::fut::ready(())
                             }
                             _ => {
@@ -905,17 +1492,9 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
             }
             (PeerStatus::Connecting, PeerMessage::LastEdge(edge)) => {
                 // This message will be received only if we started the connection.
-                if self.peer_type == PeerType::Inbound {
-                    info!(target: "network", "{:?}: Inbound peer {:?} sent invalid message. Disconnect.", self.my_node_id(), self.peer_addr);
-                    ctx.stop();
-                    return;
-                }
-
                 // Disconnect if neighbor propose invalid edge.
-                if !edge.verify() {
-                    info!(target: "network", "{:?}: Peer {:?} sent invalid edge. Disconnect.", self.my_node_id(), self.peer_addr);
-                    ctx.stop();
-                    return;
+                if self.peer_type == PeerType::Inbound || !edge.verify() {
+                    return Err(ProtocolViolation::InvalidLastEdge.into());
                 }
 
                 self.peer_manager_wrapper_addr
@@ -942,32 +1521,72 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                 debug!(target: "network", "Disconnect signal. Me: {:?} Peer: {:?}", self.my_node_info.id, self.other_peer_id());
                 ctx.stop();
             }
-            (PeerStatus::Ready, PeerMessage::Handshake(_)) => {
-                // Received handshake after already have seen handshake from this peer.
-                debug!(target: "network", "Duplicate handshake from {}", self.peer_info);
+            // Received handshake after already have seen handshake from this peer.
+            (PeerStatus::Ready, msg @ PeerMessage::Handshake(_)) => {
+                return Err(self.unexpected_message(&msg).into());
+            }
+            (PeerStatus::Ready, PeerMessage::EncryptionHandshake(msg)) => {
+                if let Err(err) = self.receive_encryption_handshake(ctx, &msg) {
+                    metrics::PEER_ENCRYPTION_HANDSHAKES_TOTAL.with_label_values(&["error"]).inc();
+                    return Err(ProtocolViolation::Encryption(err).into());
+                }
             }
             (PeerStatus::Ready, PeerMessage::PeersRequest) => {
                 self.peer_manager_wrapper_addr.send(ActixMessageWrapper::new_without_size(PeerToManagerMsg::PeersRequest(PeersRequest {}),
                                                                      Some(self.throttle_controller.clone()),
 
-                )).into_actor(self).then(|res, act, _ctx| {
+                )).into_actor(self).then(|res, act, ctx| {
                     if let Ok(peers) = res.map(|f|f.into_inner().unwrap_peers_request_result()) {
                         if !peers.peers.is_empty() {
                             debug!(target: "network", "Peers request from {}: sending {} peers.", act.peer_info, peers.peers.len());
-                            act.send_message_or_log(&PeerMessage::PeersResponse(peers.peers));
+                            let hints = if act.protocol_version >= PEER_HINTS_PROTOCOL_VERSION {
+                                peers.hints
+                            } else {
+                                vec![]
+                            };
+                            act.send_message_or_log(ctx, &PeerMessage::PeersResponse(peers.peers, hints));
                         }
                     }
                     actix::fut::ready(())
                 }).spawn(ctx);
             }
-            (PeerStatus::Ready, PeerMessage::PeersResponse(peers)) => {
-                debug!(target: "network", "Received peers from {}: {} peers.", self.peer_info, peers.len());
+            (PeerStatus::Ready, PeerMessage::PeersResponse(peers, hints)) => {
+                debug!(target: "network", "Received peers from {}: {} peers, {} hints.", self.peer_info, peers.len(), hints.len());
                 let _ =
                     self.peer_manager_wrapper_addr.do_send(ActixMessageWrapper::new_without_size(
-                        PeerToManagerMsg::PeersResponse(PeersResponse { peers }),
+                        PeerToManagerMsg::PeersResponse(PeersResponse { peers, hints }),
                         Some(self.throttle_controller.clone()),
                     ));
             }
+            (PeerStatus::Ready, PeerMessage::AddressProof(nonce)) => {
+                let _ = self.peer_manager_addr.do_send(PeerToManagerMsg::AddressProof(
+                    self.other_peer_id().unwrap().clone(),
+                    nonce,
+                ));
+            }
+            (PeerStatus::Ready, PeerMessage::AddressChallengeRequest) => {
+                let _ = self.peer_manager_addr.do_send(PeerToManagerMsg::AddressChallengeRequest(
+                    self.other_peer_id().unwrap().clone(),
+                ));
+            }
+            // Only refreshes `last_time_received`.
+            (PeerStatus::Ready, PeerMessage::KeepAlive) => {}
+            (PeerStatus::Ready, PeerMessage::ProtocolDeprecation(deprecation)) => {
+                let _ = self.peer_manager_addr.do_send(PeerToManagerMsg::ProtocolDeprecation(
+                    self.other_peer_id().unwrap().clone(),
+                    deprecation,
+                ));
+            }
+            (PeerStatus::Ready, PeerMessage::NetworkAdvisory(advisory)) => {
+                if advisory.chain_id != self.genesis_id.chain_id {
+                    debug!(target: "network", chain_id = advisory.chain_id, "Dropping network advisory for another chain");
+                    return Ok(());
+                }
+                let _ = self.peer_manager_addr.do_send(PeerToManagerMsg::NetworkAdvisory(
+                    self.other_peer_id().unwrap().clone(),
+                    advisory,
+                ));
+            }
             (PeerStatus::Ready, PeerMessage::RequestUpdateNonce(edge_info)) => self
                 .peer_manager_addr
                 .send(PeerToManagerMsg::RequestUpdateNonce(
@@ -978,10 +1597,10 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                 .then(|res, act, ctx| {
                     match res.map(|f| f) {
                         Ok(PeerToManagerMsgResp::EdgeUpdate(edge)) => {
-                            act.send_message_or_log(&PeerMessage::ResponseUpdateNonce(*edge));
+                            act.send_message_or_log(ctx, &PeerMessage::ResponseUpdateNonce(*edge));
                         }
                         Ok(PeerToManagerMsgResp::BanPeer(reason_for_ban)) => {
-                            act.ban_peer(ctx, reason_for_ban);
+                            act.on_peer_error(ctx, PeerError::Ban(reason_for_ban));
                         }
                         _ => {}
                     }
@@ -995,7 +1614,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                 .then(|res, act, ctx| {
                     match res {
                         Ok(PeerToManagerMsgResp::BanPeer(reason_for_ban)) => {
-                            act.ban_peer(ctx, reason_for_ban)
+                            act.on_peer_error(ctx, PeerError::Ban(reason_for_ban))
                         }
                         _ => {}
                     }
@@ -1017,7 +1636,7 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
 
                 // Receive invalid routed message from peer.
                 if !routed_message.verify() {
-                    self.ban_peer(ctx, ReasonForBan::InvalidSignature);
+                    return Err(PeerError::Ban(ReasonForBan::InvalidSignature));
                 } else {
                     self.peer_manager_wrapper_addr
                         .send(ActixMessageWrapper::new_without_size(
@@ -1044,9 +1663,19 @@ impl StreamHandler<Result<Vec<u8>, ReasonForBan>> for PeerActor {
                 self.receive_message(ctx, msg);
             }
             (_, msg) => {
-                warn!(target: "network", "Received {} while {:?} from {:?} connection.", msg, self.peer_status, self.peer_type);
+                return Err(self.unexpected_message(&msg).into());
             }
         }
+        Ok(())
+    }
+
+    /// Counts the message as dropped and returns the violation to report.
+    fn unexpected_message(&self, msg: &PeerMessage) -> ProtocolViolation {
+        metrics::MessageDropped::UnexpectedMessage.inc_peer_msg(msg);
+        ProtocolViolation::UnexpectedMessage {
+            msg_type: msg.msg_variant(),
+            status: format!("{:?} on {:?} connection", self.peer_status, self.peer_type),
+        }
     }
 }
 
@@ -1054,12 +1683,12 @@ impl Handler<SendMessage> for PeerActor {
     type Result = ();
 
     #[perf]
-    fn handle(&mut self, msg: SendMessage, _: &mut Self::Context) {
+    fn handle(&mut self, msg: SendMessage, ctx: &mut Self::Context) {
         let span =
             tracing::trace_span!(target: "network", "handle", handler="SendMessage").entered();
         span.set_parent(msg.context);
         let _d = delay_detector::DelayDetector::new(|| "send message".into());
-        self.send_message_or_log(&msg.message);
+        self.send_message_or_log(ctx, &msg.message);
     }
 }
 
@@ -1067,12 +1696,12 @@ impl Handler<Arc<SendMessage>> for PeerActor {
     type Result = ();
 
     #[perf]
-    fn handle(&mut self, msg: Arc<SendMessage>, _: &mut Self::Context) {
+    fn handle(&mut self, msg: Arc<SendMessage>, ctx: &mut Self::Context) {
         let span =
             tracing::trace_span!(target: "network", "handle", handler="SendMessage").entered();
         span.set_parent(msg.context.clone());
         let _d = delay_detector::DelayDetector::new(|| "send message".into());
-        self.send_message_or_log(&msg.as_ref().message);
+        self.send_message_or_log(ctx, &msg.as_ref().message);
     }
 }
 
@@ -1104,6 +1733,7 @@ impl Handler<QueryPeerStats> for PeerActor {
             is_abusive,
             message_counts: (sent.count_per_min, received.count_per_min),
             encoding: self.encoding(),
+            behavior: std::mem::take(&mut self.behavior),
         }
     }
 }
@@ -1134,6 +1764,48 @@ impl Handler<PeerManagerRequestWithContext> for PeerActor {
     }
 }
 
+/// Messages exchanged while establishing the connection, which are never encrypted.
+fn is_plaintext_message(msg: &PeerMessage) -> bool {
+    matches!(
+        msg,
+        PeerMessage::Handshake(_)
+            | PeerMessage::HandshakeFailure(..)
+            | PeerMessage::LastEdge(_)
+            | PeerMessage::EncryptionHandshake(_)
+            | PeerMessage::AddressChallenge(_)
+    )
+}
+
+/// Priority of sending the message when the connection is backed up.
+/// Consensus messages (approvals, chunks) and connection setup go first. Sync traffic goes
+/// last and is subject to the outbound bandwidth limits.
+fn message_priority(msg: &PeerMessage) -> Priority {
+    if is_plaintext_message(msg) {
+        return Priority::High;
+    }
+    match msg {
+        PeerMessage::Block(_)
+        | PeerMessage::BlockHeaders(_)
+        | PeerMessage::EpochSyncResponse(_)
+        | PeerMessage::EpochSyncFinalizationResponse(_) => Priority::Bulk,
+        // Mustn't wait behind bulk data, or a busy connection could be considered dead.
+        PeerMessage::KeepAlive => Priority::High,
+        PeerMessage::Routed(msg) => match msg.msg.body {
+            RoutedMessageBody::BlockApproval(_)
+            | RoutedMessageBody::PartialEncodedChunkRequest(_)
+            | RoutedMessageBody::PartialEncodedChunkResponse(_)
+            | RoutedMessageBody::PartialEncodedChunk(_)
+            | RoutedMessageBody::VersionedPartialEncodedChunk(_)
+            | RoutedMessageBody::PartialEncodedChunkForward(_) => Priority::High,
+            RoutedMessageBody::StateResponse(_) | RoutedMessageBody::VersionedStateResponse(_) => {
+                Priority::Bulk
+            }
+            _ => Priority::Normal,
+        },
+        _ => Priority::Normal,
+    }
+}
+
 /// Peer status.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum PeerStatus {
@@ -0,0 +1,80 @@
+//! Recording of the frames received from peers, for reproducing issues like consensus
+//! stalls offline.
+//!
+//! Every frame received by a `PeerActor` is appended to a single file shared by all the
+//! connections, as a `RecordedFrame` preceded by the length of its borsh serialization as
+//! u32 little endian. Encrypted frames are recorded decrypted. The recording can be fed
+//! back into a `PeerManagerActor` with the replay harness in `testonly::replay`.
+use crate::network_protocol::{Encoding, PeerMessage};
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_network_primitives::time;
+use near_primitives::network::PeerId;
+use parking_lot::Mutex;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use tracing::warn;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedFrame {
+    /// Time the frame was received at, in nanoseconds since the UNIX epoch.
+    pub timestamp_nanos: u64,
+    /// Peer the frame was received from. None if received before the handshake.
+    pub peer_id: Option<PeerId>,
+    /// Frame in the encoding of the connection.
+    pub data: Vec<u8>,
+}
+
+impl RecordedFrame {
+    /// Decodes the frame, trying both encodings.
+    pub fn message(&self) -> Option<PeerMessage> {
+        PeerMessage::deserialize(Encoding::Proto, &self.data)
+            .or_else(|_| PeerMessage::deserialize(Encoding::Borsh, &self.data))
+            .ok()
+    }
+}
+
+pub struct FrameRecorder {
+    file: Mutex<BufWriter<File>>,
+}
+
+impl FrameRecorder {
+    /// Opens the recording, appending to it if it exists.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(BufWriter::new(file)) })
+    }
+
+    pub(crate) fn record(&self, clock: &time::Clock, peer_id: Option<&PeerId>, data: &[u8]) {
+        let frame = RecordedFrame {
+            timestamp_nanos: clock.now_utc().unix_timestamp_nanos() as u64,
+            peer_id: peer_id.cloned(),
+            data: data.to_vec(),
+        };
+        let frame = frame.try_to_vec().unwrap();
+        let mut file = self.file.lock();
+        let result = file
+            .write_all(&(frame.len() as u32).to_le_bytes())
+            .and_then(|()| file.write_all(&frame))
+            .and_then(|()| file.flush());
+        if let Err(err) = result {
+            warn!(target: "network", %err, "Failed to record a frame");
+        }
+    }
+}
+
+/// Reads all the frames of a recording.
+pub fn read_frames(path: &Path) -> io::Result<Vec<RecordedFrame>> {
+    let data = std::fs::read(path)?;
+    let mut frames = vec![];
+    let mut rest = data.as_slice();
+    while !rest.is_empty() {
+        let len = u32::deserialize(&mut rest)? as usize;
+        if rest.len() < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated frame"));
+        }
+        frames.push(RecordedFrame::try_from_slice(&rest[..len])?);
+        rest = &rest[len..];
+    }
+    Ok(frames)
+}
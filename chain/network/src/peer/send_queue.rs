@@ -0,0 +1,139 @@
+use crate::network_protocol::Encoding;
+use crate::stats::metrics;
+use std::collections::VecDeque;
+
+/// Priority class of an outgoing message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Priority {
+    /// Consensus and connection setup messages: approvals, chunks, handshakes.
+    High,
+    Normal,
+    /// Sync traffic: blocks, headers, state parts. Subject to the bandwidth limits.
+    Bulk,
+}
+
+const PRIORITIES: [Priority; 3] = [Priority::High, Priority::Normal, Priority::Bulk];
+
+/// Serialized message waiting to be written to the connection.
+pub(crate) struct QueuedMessage {
+    pub msg_type: &'static str,
+    pub bytes: Vec<u8>,
+    pub enc: Encoding,
+    /// Whether the message is sent unencrypted even over an encrypted connection.
+    pub plaintext: bool,
+    pub priority: Priority,
+}
+
+/// Outgoing messages of a single connection, waiting for the connection to accept them.
+/// Messages of a higher priority go first, messages of the same priority keep their order.
+#[derive(Default)]
+pub(crate) struct SendQueue {
+    queues: [VecDeque<QueuedMessage>; 3],
+    bytes: [usize; 3],
+}
+
+impl SendQueue {
+    pub fn push(&mut self, msg: QueuedMessage) {
+        let i = msg.priority as usize;
+        metrics::PEER_SEND_QUEUE_MESSAGES.with_label_values(&[msg.priority.as_ref()]).inc();
+        metrics::PEER_SEND_QUEUE_BYTES
+            .with_label_values(&[msg.priority.as_ref()])
+            .add(msg.bytes.len() as i64);
+        self.bytes[i] += msg.bytes.len();
+        self.queues[i].push_back(msg);
+    }
+
+    /// Pops the first message of the highest priority.
+    /// Bulk messages are popped only if `allow_bulk` is set.
+    pub fn pop(&mut self, allow_bulk: bool) -> Option<QueuedMessage> {
+        for priority in PRIORITIES {
+            if priority == Priority::Bulk && !allow_bulk {
+                break;
+            }
+            let i = priority as usize;
+            if let Some(msg) = self.queues[i].pop_front() {
+                metrics::PEER_SEND_QUEUE_MESSAGES.with_label_values(&[priority.as_ref()]).dec();
+                metrics::PEER_SEND_QUEUE_BYTES
+                    .with_label_values(&[priority.as_ref()])
+                    .sub(msg.bytes.len() as i64);
+                self.bytes[i] -= msg.bytes.len();
+                return Some(msg);
+            }
+        }
+        None
+    }
+
+    /// Total size of the queued messages of the given priority.
+    pub fn bytes(&self, priority: Priority) -> usize {
+        self.bytes[priority as usize]
+    }
+
+    pub fn is_empty(&self, priority: Priority) -> bool {
+        self.queues[priority as usize].is_empty()
+    }
+
+    /// Whether no messages of any priority are queued.
+    pub fn is_all_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    /// Drops all the queued messages.
+    pub fn clear(&mut self) {
+        while self.pop(true).is_some() {}
+    }
+}
+
+impl Drop for SendQueue {
+    fn drop(&mut self) {
+        // Keep the queue depth metrics accurate.
+        self.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn msg(msg_type: &'static str, size: usize, priority: Priority) -> QueuedMessage {
+        QueuedMessage {
+            msg_type,
+            bytes: vec![0; size],
+            enc: Encoding::Proto,
+            plaintext: false,
+            priority,
+        }
+    }
+
+    fn pop_type(queue: &mut SendQueue, allow_bulk: bool) -> Option<&'static str> {
+        queue.pop(allow_bulk).map(|msg| msg.msg_type)
+    }
+
+    #[test]
+    fn test_priority_order() {
+        let mut queue = SendQueue::default();
+        queue.push(msg("block", 100, Priority::Bulk));
+        queue.push(msg("sync_routing_table", 10, Priority::Normal));
+        queue.push(msg("approval1", 1, Priority::High));
+        queue.push(msg("approval2", 1, Priority::High));
+        assert_eq!(100, queue.bytes(Priority::Bulk));
+        assert_eq!(2, queue.bytes(Priority::High));
+        assert_eq!(Some("approval1"), pop_type(&mut queue, true));
+        assert_eq!(Some("approval2"), pop_type(&mut queue, true));
+        assert_eq!(Some("sync_routing_table"), pop_type(&mut queue, true));
+        assert!(!queue.is_empty(Priority::Bulk));
+        assert_eq!(Some("block"), pop_type(&mut queue, true));
+        assert_eq!(None, pop_type(&mut queue, true));
+        assert_eq!(0, queue.bytes(Priority::Bulk));
+    }
+
+    #[test]
+    fn test_bulk_held_back() {
+        let mut queue = SendQueue::default();
+        queue.push(msg("block", 100, Priority::Bulk));
+        assert_eq!(None, pop_type(&mut queue, false));
+        queue.push(msg("approval", 1, Priority::High));
+        assert_eq!(Some("approval"), pop_type(&mut queue, false));
+        assert_eq!(Some("block"), pop_type(&mut queue, true));
+    }
+}
@@ -1,11 +1,16 @@
 use crate::broadcast;
 use crate::network_protocol::testonly as data;
+use crate::network_protocol::NetworkAdvisory;
+use crate::peer::bandwidth::BandwidthLimiter;
 use crate::peer::codec::Codec;
 use crate::peer::peer_actor::PeerActor;
+use crate::peer_manager::dedup::RoutedMessageDedup;
 use crate::private_actix::{PeerRequestResult, RegisterPeerResponse, SendMessage};
 use crate::private_actix::{PeerToManagerMsg, PeerToManagerMsgResp};
 use crate::testonly::actix::ActixSystem;
 use crate::testonly::fake_client;
+use crate::transport::quic::{self, QuicStream};
+use crate::transport::{Stream, Transport};
 use crate::types::{PeerMessage, RoutingTableUpdate};
 use actix::{Actor, Context, Handler, StreamHandler as _};
 use near_crypto::InMemorySigner;
@@ -22,6 +27,8 @@ use near_rate_limiter::{
 };
 
 use near_network_primitives::time::Utc;
+use parking_lot::Mutex;
+use std::net::Ipv4Addr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::Arc;
 use tokio::net::{TcpListener, TcpStream};
@@ -35,6 +42,7 @@ pub struct PeerConfig {
     pub peers: Vec<PeerInfo>,
     pub start_handshake_with: Option<PeerId>,
     pub force_encoding: Option<crate::network_protocol::Encoding>,
+    pub encrypt_connection: bool,
 }
 
 impl PeerConfig {
@@ -62,6 +70,7 @@ pub enum Event {
     RequestUpdateNonce(PartialEdgeInfo),
     ResponseUpdateNonce(Edge),
     PeersResponse(Vec<PeerInfo>),
+    NetworkAdvisory(NetworkAdvisory),
     Client(fake_client::Event),
 }
 
@@ -140,12 +149,17 @@ impl Handler<PeerToManagerMsg> for FakePeerManagerActor {
                 // This also triggers sending a message to the peer.
                 PeerToManagerMsgResp::PeersRequest(PeerRequestResult {
                     peers: self.cfg.peers.clone(),
+                    hints: vec![],
                 })
             }
             PeerToManagerMsg::PeersResponse(resp) => {
                 self.event_sink.push(Event::PeersResponse(resp.peers));
                 PeerToManagerMsgResp::Empty
             }
+            PeerToManagerMsg::NetworkAdvisory(_, advisory) => {
+                self.event_sink.push(Event::NetworkAdvisory(advisory));
+                PeerToManagerMsgResp::Empty
+            }
             _ => panic!("unsupported message"),
         }
     }
@@ -188,10 +202,10 @@ impl PeerHandle {
         )
     }
 
-    pub async fn start_endpoint(
+    pub(crate) async fn start_endpoint(
         clock: time::Clock,
         cfg: PeerConfig,
-        stream: TcpStream,
+        stream: impl Transport,
     ) -> PeerHandle {
         let cfg = Arc::new(cfg);
         let cfg_ = cfg.clone();
@@ -199,8 +213,10 @@ impl PeerHandle {
         let actix = ActixSystem::spawn(move || {
             let my_addr = stream.local_addr().unwrap();
             let peer_addr = stream.peer_addr().unwrap();
-            let (read, write) = tokio::io::split(stream);
+            let (read, write) = tokio::io::split(Box::new(stream) as Stream);
             let handshake_timeout = time::Duration::seconds(5);
+            let keep_alive_interval = time::Duration::seconds(5);
+            let keep_alive_timeout = time::Duration::seconds(20);
             let fpm = FakePeerManagerActor { cfg: cfg.clone(), event_sink: send.sink() }.start();
             let fc = fake_client::start(cfg.chain.clone(), send.sink().compose(Event::Client));
             let rate_limiter = ThrottleController::new(usize::MAX, usize::MAX);
@@ -224,6 +240,8 @@ impl PeerHandle {
                     cfg.peer_type(),
                     FramedWrite::new(write, Codec::default(), Codec::default(), ctx),
                     handshake_timeout,
+                    keep_alive_interval,
+                    keep_alive_timeout,
                     fpm.clone().recipient(),
                     fpm.clone().recipient(),
                     fc.clone().recipient(),
@@ -231,8 +249,17 @@ impl PeerHandle {
                     cfg.start_handshake_with.as_ref().map(|id| cfg.partial_edge_info(id, 1)),
                     Arc::new(AtomicUsize::new(0)),
                     Arc::new(AtomicUsize::new(0)),
+                    Arc::new(Mutex::new(RoutedMessageDedup::new(
+                        1000,
+                        time::Duration::milliseconds(50),
+                    ))),
                     rate_limiter,
+                    cfg.encrypt_connection.then(|| cfg.signer.secret_key.clone()),
                     cfg.force_encoding,
+                    BandwidthLimiter::default(),
+                    None,
+                    None,
+                    None,
                 )
             })
         })
@@ -249,4 +276,14 @@ impl PeerHandle {
         let (inbound_stream, _) = accept_result.unwrap();
         (outbound_stream, inbound_stream)
     }
+
+    pub(crate) async fn start_quic_connection() -> (QuicStream, QuicStream) {
+        let localhost = (Ipv4Addr::LOCALHOST, 0).into();
+        let (inbound_endpoint, mut incoming) = quic::Endpoint::bind(localhost).unwrap();
+        let (outbound_endpoint, _) = quic::Endpoint::bind(localhost).unwrap();
+        let connect_future = outbound_endpoint.connect(inbound_endpoint.local_addr().unwrap());
+        let accept_future = async { inbound_endpoint.accept(incoming.next().await.unwrap()).await };
+        let (connect_result, accept_result) = tokio::join!(connect_future, accept_future);
+        (connect_result.unwrap(), accept_result.unwrap())
+    }
 }
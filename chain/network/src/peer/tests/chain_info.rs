@@ -0,0 +1,50 @@
+use crate::peer::chain_info::{
+    check_tracked_shards, is_height_plausible, InvalidChainInfo, MAX_HEIGHT_AHEAD,
+};
+use near_network_primitives::types::PeerChainInfoV2;
+use near_primitives::types::{BlockHeight, ShardId};
+
+fn chain_info(height: BlockHeight, tracked_shards: Vec<ShardId>) -> PeerChainInfoV2 {
+    PeerChainInfoV2 { height, tracked_shards, ..Default::default() }
+}
+
+#[test]
+fn tracked_shards_within_layout() {
+    assert_eq!(Ok(()), check_tracked_shards(&chain_info(10, vec![0, 1, 2, 3]), 10, Some(4)));
+    assert_eq!(Ok(()), check_tracked_shards(&chain_info(10, vec![]), 10, Some(4)));
+}
+
+#[test]
+fn tracked_shards_outside_layout() {
+    assert_eq!(
+        Err(InvalidChainInfo::UnknownShard),
+        check_tracked_shards(&chain_info(10, vec![0, 4]), 10, Some(4))
+    );
+    assert_eq!(
+        Err(InvalidChainInfo::UnknownShard),
+        check_tracked_shards(&chain_info(5, vec![u64::MAX]), 10, Some(1))
+    );
+}
+
+#[test]
+fn tracked_shards_not_checked_when_unknown() {
+    // The peer may be already in an epoch with more shards.
+    assert_eq!(Ok(()), check_tracked_shards(&chain_info(11, vec![0, 4]), 10, Some(4)));
+    // We don't know the shard layout.
+    assert_eq!(Ok(()), check_tracked_shards(&chain_info(10, vec![0, 4]), 10, None));
+}
+
+#[test]
+fn height_close_to_header_head() {
+    assert!(is_height_plausible(110, 100));
+    assert!(is_height_plausible(100 + MAX_HEIGHT_AHEAD, 100));
+    // Peers behind us are fine, they might be syncing.
+    assert!(is_height_plausible(0, 100));
+}
+
+#[test]
+fn height_far_above_header_head() {
+    assert!(!is_height_plausible(101 + MAX_HEIGHT_AHEAD, 100));
+    assert!(!is_height_plausible(BlockHeight::MAX, 100));
+    assert!(is_height_plausible(BlockHeight::MAX, BlockHeight::MAX));
+}
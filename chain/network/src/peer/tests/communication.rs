@@ -1,6 +1,7 @@
 use crate::network_protocol::testonly as data;
 use crate::network_protocol::Encoding;
 use crate::peer::testonly::{Event, PeerConfig, PeerHandle};
+use crate::stats::metrics;
 use crate::testonly::fake_client::Event as CE;
 use crate::testonly::make_rng;
 use crate::testonly::stream::Stream;
@@ -10,7 +11,7 @@ use assert_matches::assert_matches;
 use near_logger_utils::init_test_logger;
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, RoutedMessageBody,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, RoutedMessageBody, Transport,
 };
 use near_primitives::syncing::EpochSyncResponse;
 use near_primitives::types::EpochId;
@@ -20,6 +21,9 @@ use std::sync::Arc;
 async fn test_peer_communication(
     outbound_encoding: Option<Encoding>,
     inbound_encoding: Option<Encoding>,
+    outbound_encryption: bool,
+    inbound_encryption: bool,
+    transport: Transport,
 ) -> anyhow::Result<()> {
     let mut rng = make_rng(89028037453);
     let mut clock = time::FakeClock::default();
@@ -31,6 +35,7 @@ async fn test_peer_communication(
         peers: (0..5).map(|_| data::make_peer_info(&mut rng)).collect(),
         force_encoding: inbound_encoding,
         start_handshake_with: None,
+        encrypt_connection: inbound_encryption,
     };
     let outbound_cfg = PeerConfig {
         signer: data::make_signer(&mut rng),
@@ -38,12 +43,25 @@ async fn test_peer_communication(
         peers: (0..5).map(|_| data::make_peer_info(&mut rng)).collect(),
         force_encoding: outbound_encoding,
         start_handshake_with: Some(inbound_cfg.id()),
+        encrypt_connection: outbound_encryption,
     };
 
-    let (outbound_stream, inbound_stream) = PeerHandle::start_connection().await;
-    let mut inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
-    let mut outbound =
-        PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream).await;
+    let (mut inbound, mut outbound) = match transport {
+        Transport::Tcp => {
+            let (outbound_stream, inbound_stream) = PeerHandle::start_connection().await;
+            (
+                PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await,
+                PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream).await,
+            )
+        }
+        Transport::Quic => {
+            let (outbound_stream, inbound_stream) = PeerHandle::start_quic_connection().await;
+            (
+                PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await,
+                PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream).await,
+            )
+        }
+    };
 
     outbound.complete_handshake().await;
     inbound.complete_handshake().await;
@@ -169,14 +187,66 @@ async fn peer_communication() -> anyhow::Result<()> {
                     continue;
                 }
             }
-            test_peer_communication(outbound.clone(), inbound.clone())
-                .await
-                .with_context(|| format!("(outbound={outbound:?},inbound={inbound:?})"))?;
+            test_peer_communication(
+                outbound.clone(),
+                inbound.clone(),
+                false,
+                false,
+                Transport::Tcp,
+            )
+            .await
+            .with_context(|| format!("(outbound={outbound:?},inbound={inbound:?})"))?;
+        }
+    }
+    Ok(())
+}
+
+#[tokio::test]
+// Verifies that peers encrypt the connection if both of them support it,
+// and fall back to plaintext otherwise.
+async fn peer_communication_encrypted() -> anyhow::Result<()> {
+    let encodings = [None, Some(Encoding::Proto), Some(Encoding::Borsh)];
+    let encryption = [(true, true), (true, false), (false, true)];
+    let handshakes = metrics::PEER_ENCRYPTION_HANDSHAKES_TOTAL.with_label_values(&["ok"]);
+    for encoding in &encodings {
+        for (outbound, inbound) in encryption {
+            let before = handshakes.get();
+            test_peer_communication(
+                encoding.clone(),
+                encoding.clone(),
+                outbound,
+                inbound,
+                Transport::Tcp,
+            )
+            .await
+            .with_context(|| {
+                format!("(encoding={encoding:?},outbound={outbound},inbound={inbound})")
+            })?;
+            // Encryption can be negotiated only in proto encoding.
+            let encrypted = outbound && inbound && encoding != &Some(Encoding::Borsh);
+            assert_eq!(if encrypted { 2 } else { 0 }, handshakes.get() - before);
         }
     }
     Ok(())
 }
 
+#[tokio::test]
+// Verifies that peers are able to communicate over QUIC, with and without encryption.
+async fn peer_communication_quic() -> anyhow::Result<()> {
+    for encryption in [false, true] {
+        test_peer_communication(
+            Some(Encoding::Proto),
+            Some(Encoding::Proto),
+            encryption,
+            encryption,
+            Transport::Quic,
+        )
+        .await
+        .with_context(|| format!("(encryption={encryption})"))?;
+    }
+    Ok(())
+}
+
 async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: Option<Encoding>) {
     let mut rng = make_rng(89028037453);
     let mut clock = time::FakeClock::default();
@@ -188,6 +258,7 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         peers: (0..5).map(|_| data::make_peer_info(&mut rng)).collect(),
         force_encoding: inbound_encoding,
         start_handshake_with: None,
+        encrypt_connection: false,
     };
     let outbound_cfg = PeerConfig {
         signer: data::make_signer(&mut rng),
@@ -195,6 +266,7 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         peers: (0..5).map(|_| data::make_peer_info(&mut rng)).collect(),
         force_encoding: outbound_encoding,
         start_handshake_with: None,
+        encrypt_connection: false,
     };
     let (outbound_stream, inbound_stream) = PeerHandle::start_connection().await;
     let inbound = PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream).await;
@@ -209,6 +281,9 @@ async fn test_handshake(outbound_encoding: Option<Encoding>, inbound_encoding: O
         sender_listen_port: Some(outbound.local_addr.port()),
         sender_chain_info: outbound_cfg.chain.get_info(),
         partial_edge_info: outbound_cfg.partial_edge_info(&inbound.cfg.id(), 1),
+        sender_encryption_signature: None,
+        proof_of_work: 0,
+        sender_time: None,
     };
     // We will also introduce chain_id mismatch, but ProtocolVersionMismatch is expected to take priority.
     handshake.sender_chain_info.genesis_id.chain_id = "unknown_chain".to_string();
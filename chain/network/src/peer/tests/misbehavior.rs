@@ -0,0 +1,64 @@
+use crate::network_protocol::testonly as data;
+use crate::peer::testonly::{Event, PeerConfig, PeerHandle};
+use crate::test_utils::misbehavior::{MisbehavingPeer, Misbehavior, Script};
+use crate::testonly::fake_client::Event as CE;
+use crate::testonly::make_rng;
+use crate::types::PeerMessage;
+use near_logger_utils::init_test_logger;
+use near_network_primitives::time;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn misbehaving_peer() {
+    init_test_logger();
+    let mut rng = make_rng(89028037453);
+    let mut clock = time::FakeClock::default();
+
+    let chain = Arc::new(data::Chain::make(&mut clock, &mut rng, 12));
+    let inbound_cfg = PeerConfig {
+        signer: data::make_signer(&mut rng),
+        chain: chain.clone(),
+        peers: vec![],
+        force_encoding: None,
+        start_handshake_with: None,
+        encrypt_connection: false,
+    };
+    let outbound_cfg = PeerConfig {
+        signer: data::make_signer(&mut rng),
+        chain: chain.clone(),
+        peers: vec![],
+        force_encoding: None,
+        start_handshake_with: Some(inbound_cfg.id()),
+        encrypt_connection: false,
+    };
+
+    // Messages sent by the inbound peer reach the outbound one through the misbehaving peer.
+    let script = Script::default()
+        .on("Challenge", Misbehavior::Drop)
+        .on("Block", Misbehavior::Reorder)
+        .on("BlockHeaders", Misbehavior::Corrupt);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let misbehaving = MisbehavingPeer::start(listener.local_addr().unwrap(), script).await.unwrap();
+    let (outbound_stream, inbound_stream) =
+        tokio::join!(TcpStream::connect(misbehaving.addr()), listener.accept());
+    let mut inbound =
+        PeerHandle::start_endpoint(clock.clock(), inbound_cfg, inbound_stream.unwrap().0).await;
+    let mut outbound =
+        PeerHandle::start_endpoint(clock.clock(), outbound_cfg, outbound_stream.unwrap()).await;
+    outbound.complete_handshake().await;
+    inbound.complete_handshake().await;
+
+    // Dropped.
+    inbound.send(PeerMessage::Challenge(data::make_challenge(&mut rng))).await;
+    // Corrupted, so it fails to parse.
+    inbound.send(PeerMessage::BlockHeaders(chain.get_block_headers())).await;
+    // Delivered after the transaction.
+    let block = chain.blocks[5].clone();
+    inbound.send(PeerMessage::Block(block.clone())).await;
+    let tx = data::make_signed_transaction(&mut rng);
+    inbound.send(PeerMessage::Transaction(tx.clone())).await;
+
+    assert_eq!(Event::Client(CE::Transaction(tx)), outbound.events.recv().await);
+    assert_eq!(Event::Client(CE::Block(block)), outbound.events.recv().await);
+}
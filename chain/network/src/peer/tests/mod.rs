@@ -1 +1,3 @@
+mod chain_info;
 mod communication;
+mod misbehavior;
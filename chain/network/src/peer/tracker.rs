@@ -1,6 +1,7 @@
 use crate::peer::transfer_stats::TransferStats;
 use near_network_primitives::time;
 use near_primitives::hash::CryptoHash;
+use std::collections::HashMap;
 
 /// Maximum number of requests and responses to track.
 const MAX_TRACK_SIZE: usize = 30;
@@ -22,19 +23,22 @@ impl CircularUniqueQueue {
         self.v.contains(hash)
     }
 
-    /// Pushes an element if it's not in the queue already. The queue will pop the oldest element.
-    fn push(&mut self, hash: CryptoHash) {
-        if !self.contains(&hash) {
-            if self.v.len() < self.limit {
-                self.v.push(hash);
-            } else {
-                self.v[self.index] = hash;
-                self.index += 1;
-                if self.index == self.limit {
-                    self.index = 0;
-                }
-            }
+    /// Pushes an element if it's not in the queue already. The queue will pop the oldest element,
+    /// which is returned.
+    fn push(&mut self, hash: CryptoHash) -> Option<CryptoHash> {
+        if self.contains(&hash) {
+            return None;
+        }
+        if self.v.len() < self.limit {
+            self.v.push(hash);
+            return None;
+        }
+        let popped = std::mem::replace(&mut self.v[self.index], hash);
+        self.index += 1;
+        if self.index == self.limit {
+            self.index = 0;
         }
+        Some(popped)
     }
 }
 
@@ -47,6 +51,9 @@ pub(crate) struct Tracker {
     pub(crate) received_bytes: TransferStats,
     /// Sent requests.
     requested: CircularUniqueQueue,
+    /// When the requests in `requested` have been sent. Entries are removed once the
+    /// response arrives.
+    request_times: HashMap<CryptoHash, time::Instant>,
     /// Received elements.
     received: CircularUniqueQueue,
 }
@@ -57,6 +64,7 @@ impl Default for Tracker {
             sent_bytes: TransferStats::default(),
             received_bytes: TransferStats::default(),
             requested: CircularUniqueQueue::new(MAX_TRACK_SIZE),
+            request_times: HashMap::new(),
             received: CircularUniqueQueue::new(MAX_TRACK_SIZE),
         }
     }
@@ -85,8 +93,20 @@ impl Tracker {
         self.requested.contains(hash)
     }
 
-    pub(crate) fn push_request(&mut self, hash: CryptoHash) {
-        self.requested.push(hash);
+    pub(crate) fn push_request(&mut self, hash: CryptoHash, now: time::Instant) {
+        if let Some(popped) = self.requested.push(hash) {
+            self.request_times.remove(&popped);
+        }
+        self.request_times.entry(hash).or_insert(now);
+    }
+
+    /// Time since the request for `hash` has been sent, if it is the first response to it.
+    pub(crate) fn response_latency(
+        &mut self,
+        hash: &CryptoHash,
+        now: time::Instant,
+    ) -> Option<time::Duration> {
+        self.request_times.remove(hash).map(|sent| now - sent)
     }
 }
 
@@ -164,4 +184,33 @@ mod tests {
         }
         assert!(q.contains(&hash(&[5])));
     }
+
+    #[test]
+    fn test_circular_queue_returns_popped() {
+        let mut q = CircularUniqueQueue::new(2);
+        assert_eq!(None, q.push(hash(&[1])));
+        assert_eq!(None, q.push(hash(&[2])));
+        assert_eq!(None, q.push(hash(&[2])));
+        assert_eq!(Some(hash(&[1])), q.push(hash(&[3])));
+    }
+
+    #[test]
+    fn test_tracker_response_latency() {
+        let clock = time::FakeClock::default();
+        let mut tracker = Tracker::default();
+        tracker.push_request(hash(&[1]), clock.now());
+        clock.advance(time::Duration::milliseconds(300));
+        // Repeated request doesn't reset the time.
+        tracker.push_request(hash(&[1]), clock.now());
+        clock.advance(time::Duration::milliseconds(200));
+        assert_eq!(
+            Some(time::Duration::milliseconds(500)),
+            tracker.response_latency(&hash(&[1]), clock.now())
+        );
+        // Only the first response is measured.
+        assert_eq!(None, tracker.response_latency(&hash(&[1]), clock.now()));
+        assert!(tracker.has_request(&hash(&[1])));
+        // Unknown request.
+        assert_eq!(None, tracker.response_latency(&hash(&[2]), clock.now()));
+    }
 }
@@ -0,0 +1,40 @@
+//! Verification that an inbound peer listens at the address it advertises in the handshake.
+//!
+//! The IP of an inbound connection is known, but the listening port is just a claim of the
+//! peer, so a peer could advertise the address of another node and make the network store
+//! that address under its own peer id. Before storing the address, the node dials it and
+//! sends an `AddressChallenge` with a random nonce. Only the node listening at the address
+//! learns the nonce; the peer proves to be that node by sending the nonce back over the
+//! existing connection in `AddressProof`.
+use crate::network_protocol::{AddressChallenge, Encoding, PeerMessage};
+use crate::peer::codec::Codec;
+use crate::transport::{self, quic};
+use futures::SinkExt as _;
+use near_network_primitives::types::SocketOptions;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio_util::codec::FramedWrite;
+
+/// Same as the timeout of the outbound connections.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Timeout for sending the challenge once connected.
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Dials `addr` and sends `challenge` as the only message of the connection. The connection
+/// is short-lived, so it is made with the default socket options.
+pub(crate) async fn send_challenge(
+    quic: Option<quic::Endpoint>,
+    addr: SocketAddr,
+    challenge: AddressChallenge,
+) -> anyhow::Result<()> {
+    let (stream, _rtt) =
+        transport::connect(quic, addr, CONNECT_TIMEOUT, SocketOptions::default()).await?;
+    let mut framed = FramedWrite::new(stream, Codec::default());
+    let msg = PeerMessage::AddressChallenge(challenge).serialize(Encoding::Proto);
+    tokio::time::timeout(SEND_TIMEOUT, async {
+        framed.send(msg).await?;
+        framed.close().await
+    })
+    .await??;
+    Ok(())
+}
@@ -0,0 +1,170 @@
+//! Network advisories: notices for node operators, e.g. "security release available, upgrade
+//! before epoch X", signed by one of the advisory keys of the chain.
+//!
+//! An advisory is accepted only once: it has to be signed by a configured key, be recent and
+//! carry a nonce above the last accepted one of its signer. Each signer can get at most one
+//! advisory accepted per `MIN_INTERVAL`, so that a leaked key can't be used to flood the
+//! network. Accepted advisories are relayed to the connected peers.
+use crate::network_protocol::NetworkAdvisory;
+use near_crypto::PublicKey;
+use near_network_primitives::time;
+use std::collections::{HashMap, VecDeque};
+
+/// Maximal length of the message of an advisory, in bytes.
+pub(crate) const MAX_MESSAGE_LEN: usize = 1000;
+/// Advisories signed longer ago than this are dropped.
+const MAX_AGE: time::Duration = time::Duration::days(1);
+/// Advisories signed further in the future than this are dropped.
+const MAX_CLOCK_SKEW: time::Duration = time::Duration::minutes(10);
+/// Minimal interval between two accepted advisories of the same signer.
+pub(crate) const MIN_INTERVAL: time::Duration = time::Duration::minutes(10);
+/// Number of the latest accepted advisories reported in `/status`.
+const MAX_LATEST: usize = 10;
+
+/// Reason for dropping an advisory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub enum Rejection {
+    /// The node opted out of advisories, see `NetworkConfig::ignore_advisories`.
+    Ignored,
+    UnknownSigner,
+    /// The message is empty, too long or contains control characters.
+    InvalidMessage,
+    Expired,
+    /// The nonce is not above the last accepted one of the signer, e.g. the advisory has
+    /// already been accepted.
+    Replayed,
+    RateLimited,
+    InvalidSignature,
+}
+
+struct SignerState {
+    last_nonce: u64,
+    last_accepted: time::Instant,
+}
+
+pub(crate) struct Advisories {
+    keys: Vec<PublicKey>,
+    ignore: bool,
+    signers: HashMap<PublicKey, SignerState>,
+    /// Latest accepted advisories, oldest first.
+    latest: VecDeque<NetworkAdvisory>,
+}
+
+impl Advisories {
+    pub fn new(keys: Vec<PublicKey>, ignore: bool) -> Self {
+        Self { keys, ignore, signers: HashMap::new(), latest: VecDeque::new() }
+    }
+
+    /// Checks the advisory and records it if it is accepted. The signature is checked last,
+    /// so that duplicates received from many peers cost no signature verification.
+    pub fn accept(
+        &mut self,
+        clock: &time::Clock,
+        advisory: &NetworkAdvisory,
+    ) -> Result<(), Rejection> {
+        if self.ignore {
+            return Err(Rejection::Ignored);
+        }
+        if !self.keys.contains(&advisory.signer) {
+            return Err(Rejection::UnknownSigner);
+        }
+        if advisory.message.is_empty()
+            || advisory.message.len() > MAX_MESSAGE_LEN
+            || advisory.message.chars().any(char::is_control)
+        {
+            return Err(Rejection::InvalidMessage);
+        }
+        let now_utc = clock.now_utc().unix_timestamp();
+        let timestamp = i64::try_from(advisory.timestamp).unwrap_or(i64::MAX);
+        if timestamp < now_utc - MAX_AGE.whole_seconds()
+            || timestamp > now_utc + MAX_CLOCK_SKEW.whole_seconds()
+        {
+            return Err(Rejection::Expired);
+        }
+        let now = clock.now();
+        if let Some(state) = self.signers.get(&advisory.signer) {
+            if advisory.nonce <= state.last_nonce {
+                return Err(Rejection::Replayed);
+            }
+            if now < state.last_accepted + MIN_INTERVAL {
+                return Err(Rejection::RateLimited);
+            }
+        }
+        if !advisory.verify() {
+            return Err(Rejection::InvalidSignature);
+        }
+        self.signers.insert(
+            advisory.signer.clone(),
+            SignerState { last_nonce: advisory.nonce, last_accepted: now },
+        );
+        if self.latest.len() == MAX_LATEST {
+            self.latest.pop_front();
+        }
+        self.latest.push_back(advisory.clone());
+        Ok(())
+    }
+
+    /// Latest accepted advisories, oldest first.
+    pub fn latest(&self) -> Vec<NetworkAdvisory> {
+        self.latest.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network_protocol::testonly as data;
+    use crate::testonly::make_rng;
+
+    #[test]
+    fn test_accept() {
+        let mut rng = make_rng(89028037453);
+        let clock = time::FakeClock::default();
+        let signer = data::make_signer(&mut rng);
+        let other = data::make_signer(&mut rng);
+        let mut advisories = Advisories::new(vec![signer.public_key.clone()], false);
+        let now = clock.now_utc().unix_timestamp() as u64;
+        let make = |signer: &dyn near_crypto::Signer, nonce, timestamp, message: &str| {
+            NetworkAdvisory::new(signer, "test".to_string(), nonce, timestamp, message.to_string())
+        };
+
+        let advisory = make(&signer, 1, now, "Upgrade before epoch 100");
+        assert_eq!(Ok(()), advisories.accept(&clock.clock(), &advisory));
+        assert_eq!(Err(Rejection::Replayed), advisories.accept(&clock.clock(), &advisory));
+        assert_eq!(
+            Err(Rejection::RateLimited),
+            advisories.accept(&clock.clock(), &make(&signer, 2, now, "Again"))
+        );
+        assert_eq!(
+            Err(Rejection::UnknownSigner),
+            advisories.accept(&clock.clock(), &make(&other, 2, now, "Spam"))
+        );
+
+        clock.advance(MIN_INTERVAL);
+        let now = clock.now_utc().unix_timestamp() as u64;
+        for message in ["", "line\nbreak", &"x".repeat(MAX_MESSAGE_LEN + 1)] {
+            assert_eq!(
+                Err(Rejection::InvalidMessage),
+                advisories.accept(&clock.clock(), &make(&signer, 2, now, message))
+            );
+        }
+        let old = now - MAX_AGE.whole_seconds() as u64 - 1;
+        assert_eq!(
+            Err(Rejection::Expired),
+            advisories.accept(&clock.clock(), &make(&signer, 2, old, "Old"))
+        );
+        let mut forged = make(&signer, 2, now, "Genuine");
+        forged.message = "Forged".to_string();
+        assert_eq!(Err(Rejection::InvalidSignature), advisories.accept(&clock.clock(), &forged));
+        let advisory2 = make(&signer, 2, now, "Genuine");
+        assert_eq!(Ok(()), advisories.accept(&clock.clock(), &advisory2));
+        assert_eq!(vec![advisory, advisory2], advisories.latest());
+
+        let mut ignoring = Advisories::new(vec![signer.public_key.clone()], true);
+        assert_eq!(
+            Err(Rejection::Ignored),
+            ignoring.accept(&clock.clock(), &make(&signer, 3, now, "Ignored"))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Estimation of the skew of the local clock relative to the clocks of the peers.
+//!
+//! The clock of a peer is compared with ours when it connects, using the time it puts in its
+//! handshake, and whenever it answers our Ping, using the creation time of its Pong and the
+//! round trip of the Ping. A skewed local clock is a common cause of rejected edge nonces and
+//! block timestamps, so a warning is logged when the median offset of the clocks of the
+//! connected peers is too large.
+use crate::stats::metrics;
+use near_network_primitives::time;
+use tracing::{info, warn};
+
+/// Minimal number of peers with a known clock offset to estimate the skew.
+const MIN_PEERS: usize = 3;
+/// Skew of the local clock above which a warning is logged.
+const MAX_SKEW: time::Duration = time::Duration::seconds(10);
+
+/// Offset of the clock of the peer which has created the Pong at `created_at`, relative to
+/// ours, assuming that the Ping and the Pong took the same time to arrive.
+pub(crate) fn pong_offset(
+    created_at: time::Utc,
+    now: time::Utc,
+    round_trip: time::Duration,
+) -> time::Duration {
+    created_at - (now - round_trip / 2)
+}
+
+#[derive(Default)]
+pub(crate) struct ClockSkew {
+    skewed: bool,
+}
+
+impl ClockSkew {
+    /// Estimates the skew of the local clock from the offsets of the clocks of the connected
+    /// peers, as the opposite of their median, so that it is positive if the local clock is
+    /// ahead. Returns None if too few offsets are known.
+    pub fn update(&mut self, mut offsets: Vec<time::Duration>) -> Option<time::Duration> {
+        if offsets.len() < MIN_PEERS {
+            return None;
+        }
+        offsets.sort();
+        let skew = -offsets[offsets.len() / 2];
+        metrics::CLOCK_SKEW.set(skew.as_seconds_f64());
+        let skewed = skew.abs() > MAX_SKEW;
+        if skewed && !self.skewed {
+            warn!(target: "network", skew_seconds = skew.as_seconds_f64(), peers = offsets.len(), "The local clock appears to be skewed relative to the clocks of the peers, which makes them reject our edges and blocks. Check that the clock is synchronized, e.g. with NTP.");
+        } else if !skewed && self.skewed {
+            info!(target: "network", skew_seconds = skew.as_seconds_f64(), "The local clock is in sync with the clocks of the peers again");
+        }
+        self.skewed = skewed;
+        Some(skew)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pong_offset() {
+        let clock = time::FakeClock::default();
+        let now = clock.now_utc();
+        let round_trip = time::Duration::milliseconds(200);
+        // The peer created the Pong half a round trip ago according to our clock.
+        assert_eq!(time::Duration::ZERO, pong_offset(now - round_trip / 2, now, round_trip));
+        assert_eq!(
+            time::Duration::seconds(5),
+            pong_offset(now - round_trip / 2 + time::Duration::seconds(5), now, round_trip)
+        );
+    }
+
+    #[test]
+    fn test_clock_skew() {
+        let s = time::Duration::seconds;
+        let mut clock_skew = ClockSkew::default();
+        assert_eq!(None, clock_skew.update(vec![s(1), s(2)]));
+        // A single peer with a skewed clock doesn't skew the median.
+        assert_eq!(Some(-s(1)), clock_skew.update(vec![s(1), s(-2), s(3600)]));
+        assert!(!clock_skew.skewed);
+        assert_eq!(Some(s(30)), clock_skew.update(vec![s(-30), s(-31), s(-30), s(0)]));
+        assert!(clock_skew.skewed);
+        assert_eq!(Some(s(0)), clock_skew.update(vec![s(0), s(1), s(-1)]));
+        assert!(!clock_skew.skewed);
+    }
+}
@@ -0,0 +1,113 @@
+//! Deduplication of the routed messages received from all the peers.
+//!
+//! A routed message may reach this node more than once, e.g. when a peer retries it or when
+//! it travels along several routes. The duplicates are recognized by the author, target,
+//! signature and body hash of the message, which `PeerActor` reads from the frame before
+//! deserializing the body, so that duplicated transactions and chunk parts cost no
+//! deserialization. The signature is verified only later, so the key has to cover the body:
+//! otherwise a copy with a forged body would make the genuine message a duplicate. The cache
+//! is shared by all the connections. With `NetworkConfig::persist_routed_message_dedup` it
+//! is saved in the store when the `PeerManagerActor` stops and loaded when it starts.
+use crate::network_protocol::RoutedMessageKey;
+use lru::LruCache;
+use near_network_primitives::time;
+
+pub(crate) struct RoutedMessageDedup {
+    window: time::Duration,
+    /// Time each message was first received at.
+    received: LruCache<RoutedMessageKey, time::Utc>,
+}
+
+impl RoutedMessageDedup {
+    pub fn new(capacity: usize, window: time::Duration) -> Self {
+        Self { window, received: LruCache::new(capacity) }
+    }
+
+    /// Records the message received at `now`. Returns whether it is a duplicate of a message
+    /// received within the window, in which case the time of the first copy is kept.
+    pub fn is_duplicate(&mut self, key: &RoutedMessageKey, now: time::Utc) -> bool {
+        if let Some(&t) = self.received.get(key) {
+            if now <= t + self.window {
+                return true;
+            }
+        }
+        self.received.put(key.clone(), now);
+        false
+    }
+
+    /// Messages received within the window before `now`, least recently received first.
+    pub fn recent(&self, now: time::Utc) -> Vec<(RoutedMessageKey, time::Utc)> {
+        let mut recent: Vec<_> = self
+            .received
+            .iter()
+            .filter(|(_, t)| now <= **t + self.window)
+            .map(|(k, t)| (k.clone(), *t))
+            .collect();
+        recent.reverse();
+        recent
+    }
+
+    /// Loads the messages received before a restart. The ones outside the window are dropped.
+    pub fn load(&mut self, messages: Vec<(RoutedMessageKey, time::Utc)>, now: time::Utc) {
+        let mut messages: Vec<_> =
+            messages.into_iter().filter(|(_, t)| now <= *t + self.window).collect();
+        messages.sort_by_key(|(_, t)| *t);
+        for (key, t) in messages {
+            self.received.put(key, t);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.received.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::network_protocol;
+    use crate::network_protocol::testonly as data;
+    use crate::testonly::make_rng;
+    use near_network_primitives::types::RoutedMessageBody;
+
+    #[test]
+    fn test_is_duplicate() {
+        let mut rng = make_rng(89028037453);
+        let clock = time::FakeClock::default();
+        let window = time::Duration::seconds(1);
+        let keys: Vec<_> = (0..3)
+            .map(|_| {
+                let tx = data::make_signed_transaction(&mut rng);
+                let msg = data::make_routed_message(&mut rng, RoutedMessageBody::ForwardTx(tx));
+                network_protocol::routed_message_key(&msg.msg)
+            })
+            .collect();
+        let mut dedup = RoutedMessageDedup::new(2, window);
+
+        assert!(!dedup.is_duplicate(&keys[0], clock.now_utc()));
+        assert!(dedup.is_duplicate(&keys[0], clock.now_utc()));
+        clock.advance(window);
+        assert!(dedup.is_duplicate(&keys[0], clock.now_utc()));
+        assert!(!dedup.is_duplicate(&keys[1], clock.now_utc()));
+        clock.advance(time::Duration::milliseconds(1));
+        // Out of the window of the first copy.
+        assert!(!dedup.is_duplicate(&keys[0], clock.now_utc()));
+        // Evicts the least recently received message, keys[1].
+        assert!(!dedup.is_duplicate(&keys[2], clock.now_utc()));
+        assert!(!dedup.is_duplicate(&keys[1], clock.now_utc()));
+        assert_eq!(2, dedup.len());
+
+        let recent = dedup.recent(clock.now_utc());
+        assert_eq!(
+            vec![keys[2].clone(), keys[1].clone()],
+            recent.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>()
+        );
+        let mut restarted = RoutedMessageDedup::new(2, window);
+        restarted.load(recent.clone(), clock.now_utc());
+        assert_eq!(recent, restarted.recent(clock.now_utc()));
+        assert!(restarted.is_duplicate(&keys[1], clock.now_utc()));
+        let mut restarted_late = RoutedMessageDedup::new(2, window);
+        restarted_late.load(recent, clock.now_utc() + window + time::Duration::milliseconds(1));
+        assert_eq!(0, restarted_late.len());
+    }
+}
@@ -0,0 +1,76 @@
+use near_async::Ctx;
+use near_network_primitives::types::PeerInfo;
+use std::time::Duration;
+use tracing::warn;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Resolutions taking longer than that are abandoned until the next attempt.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Number of the domains resolved concurrently.
+const MAX_CONCURRENT_RESOLVES: usize = 4;
+
+/// Resolves the boot nodes published in each of the `domains`, see `resolve`.
+///
+/// The failures to resolve a domain are returned next to it, the error is only returned
+/// if the resolution as a whole couldn't complete.
+pub(crate) async fn resolve_all(
+    domains: Vec<String>,
+) -> anyhow::Result<Vec<(String, anyhow::Result<Vec<PeerInfo>>)>> {
+    let ctx = Ctx::background();
+    near_async::parallel_map(&ctx, MAX_CONCURRENT_RESOLVES, domains, |ctx, domain| async move {
+        let res = near_async::timeout(&ctx, RESOLVE_TIMEOUT, |_ctx| resolve(domain.clone())).await;
+        anyhow::Ok((domain, res.map_err(anyhow::Error::from).and_then(|res| res)))
+    })
+    .await
+}
+
+/// Resolves the boot nodes published in the TXT records of `domain`.
+///
+/// Each record holds a comma separated list of peers, in the same format as the
+/// `boot_nodes` config option. TXT records are used rather than SRV records, since the
+/// latter can't carry the `PeerId` of a node. Invalid entries are skipped.
+pub(crate) async fn resolve(domain: String) -> anyhow::Result<Vec<PeerInfo>> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+    let lookup = resolver.txt_lookup(domain.as_str()).await?;
+    let records: Vec<String> = lookup
+        .iter()
+        .map(|txt| txt.txt_data().iter().map(|data| String::from_utf8_lossy(data)).collect())
+        .collect();
+    // Entries can contain host names, which are resolved in a blocking way.
+    Ok(tokio::task::spawn_blocking(move || parse_records(&domain, &records)).await?)
+}
+
+fn parse_records(domain: &str, records: &[String]) -> Vec<PeerInfo> {
+    let mut peers = vec![];
+    for entry in records.iter().flat_map(|record| record.split(',')) {
+        let entry = entry.trim();
+        if entry.is_empty() {
+            continue;
+        }
+        match PeerInfo::try_from(entry) {
+            Ok(peer_info) if peer_info.addr.is_some() => peers.push(peer_info),
+            Ok(_) => warn!(target: "network", domain, entry, "Boot node has no address"),
+            Err(err) => warn!(target: "network", domain, entry, ?err, "Invalid boot node"),
+        }
+    }
+    peers
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_records() {
+        let id1 = "ed25519:86EtEy7epneKyrcJwSWP7zsisTkfDRH5CFVszt4qiQYw";
+        let id2 = "ed25519:7PGseFbWxvYVgZ89K1uTJKYoKetWs7BJtbyXDzfbAcqX";
+        let records = vec![
+            format!("{id1}@127.0.0.1:24567, {id2}@127.0.0.2:24567"),
+            format!("{id1},invalid"),
+            "".to_string(),
+        ];
+        let peers = parse_records("boot.example.org", &records);
+        let addrs: Vec<_> = peers.iter().map(|p| p.addr.unwrap().to_string()).collect();
+        assert_eq!(vec!["127.0.0.1:24567", "127.0.0.2:24567"], addrs);
+    }
+}
@@ -0,0 +1,174 @@
+use lru::LruCache;
+use near_network_primitives::time;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+/// Number of IPs whose connection rate is tracked. The least recently seen are forgotten.
+const RATE_CAPACITY: usize = 10_000;
+/// Window over which the connections from a single IP are counted.
+const RATE_WINDOW: time::Duration = time::Duration::minutes(1);
+
+/// Reason for rejecting an inbound connection before the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Rejection {
+    TooManyPendingHandshakes,
+    /// Too many connections from all the IPs together haven't completed the handshake, see
+    /// `NetworkConfig::max_pending_handshakes`.
+    TooManyPendingHandshakesTotal,
+    TooManyConnections,
+    /// No complete frame received within `NetworkConfig::first_frame_timeout`.
+    FirstFrameTimeout,
+    /// The first frame is too large or the connection has been closed before it.
+    InvalidFirstFrame,
+    /// Connected to a listener which accepts only whitelisted nodes.
+    NotWhitelisted,
+    /// Connected to a listener whose allowlist doesn't contain the address.
+    NotAllowed,
+}
+
+/// Slot of an inbound connection from `ip` which hasn't completed the handshake yet.
+/// The slot is released when dropped.
+pub(crate) struct PendingHandshake {
+    ip: IpAddr,
+    pending: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    /// Proof of work (in bits) required in the handshake, 0 if none.
+    pub pow_difficulty: u8,
+}
+
+impl Drop for PendingHandshake {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap();
+        if let Some(n) = pending.get_mut(&self.ip) {
+            *n -= 1;
+            if *n == 0 {
+                pending.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// Limits the inbound connections per IP before a `PeerActor` is spawned for them,
+/// so that a single host can't exhaust the connection slots by opening sockets
+/// and never completing the handshake.
+///
+/// An IP is limited in the number of connections per minute and in the number of
+/// connections which haven't completed the handshake. Above the rate limit the
+/// connections are rejected, or, if `pow_difficulty` is set, accepted on the condition
+/// that the handshake carries a proof of work.
+pub(crate) struct HandshakeLimiter {
+    max_connections_per_window: u32,
+    max_pending_handshakes: usize,
+    pow_difficulty: u8,
+    /// Start of the current window and number of connections in it, per IP.
+    connections: LruCache<IpAddr, (time::Instant, u32)>,
+    pending: Arc<Mutex<HashMap<IpAddr, usize>>>,
+}
+
+impl HandshakeLimiter {
+    pub fn new(
+        max_connections_per_minute: u32,
+        max_pending_handshakes: u32,
+        pow_difficulty: u8,
+    ) -> Self {
+        Self {
+            max_connections_per_window: max_connections_per_minute,
+            max_pending_handshakes: max_pending_handshakes as usize,
+            pow_difficulty,
+            connections: LruCache::new(RATE_CAPACITY),
+            pending: Default::default(),
+        }
+    }
+
+    /// Decides whether to accept a new inbound connection from `ip`.
+    /// Rejected connections count towards the rate limit too.
+    pub fn admit(&mut self, ip: IpAddr, now: time::Instant) -> Result<PendingHandshake, Rejection> {
+        let count = match self.connections.get_mut(&ip) {
+            Some((start, count)) if now - *start < RATE_WINDOW => {
+                *count = count.saturating_add(1);
+                *count
+            }
+            _ => {
+                self.connections.put(ip, (now, 1));
+                1
+            }
+        };
+        let mut pending = self.pending.lock().unwrap();
+        if pending.get(&ip).map_or(false, |n| *n >= self.max_pending_handshakes) {
+            return Err(Rejection::TooManyPendingHandshakes);
+        }
+        let pow_difficulty = if count <= self.max_connections_per_window {
+            0
+        } else if self.pow_difficulty > 0 {
+            self.pow_difficulty
+        } else {
+            return Err(Rejection::TooManyConnections);
+        };
+        *pending.entry(ip).or_default() += 1;
+        Ok(PendingHandshake { ip, pending: self.pending.clone(), pow_difficulty })
+    }
+
+    /// Number of inbound connections from all the IPs which haven't completed the handshake.
+    pub fn num_pending(&self) -> usize {
+        self.pending.lock().unwrap().values().sum()
+    }
+
+    /// Number of inbound connections from `ip` which haven't completed the handshake.
+    #[cfg(test)]
+    fn pending(&self, ip: IpAddr) -> usize {
+        self.pending.lock().unwrap().get(&ip).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const IP1: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    const IP2: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+    #[test]
+    fn test_pending_handshakes() {
+        let clock = time::FakeClock::default();
+        let mut limiter = HandshakeLimiter::new(100, 2, 0);
+        let a = limiter.admit(IP1, clock.now()).unwrap();
+        let b = limiter.admit(IP1, clock.now()).unwrap();
+        assert_eq!(
+            Rejection::TooManyPendingHandshakes,
+            limiter.admit(IP1, clock.now()).err().unwrap()
+        );
+        // Other IPs are not affected.
+        let _c = limiter.admit(IP2, clock.now()).unwrap();
+        drop(a);
+        assert_eq!(1, limiter.pending(IP1));
+        let _d = limiter.admit(IP1, clock.now()).unwrap();
+        drop(b);
+        assert_eq!(1, limiter.pending(IP1));
+        assert_eq!(2, limiter.num_pending());
+    }
+
+    #[test]
+    fn test_connection_rate() {
+        let clock = time::FakeClock::default();
+        let mut limiter = HandshakeLimiter::new(2, 100, 0);
+        for _ in 0..2 {
+            assert_eq!(0, limiter.admit(IP1, clock.now()).unwrap().pow_difficulty);
+        }
+        assert_eq!(Rejection::TooManyConnections, limiter.admit(IP1, clock.now()).err().unwrap());
+        assert!(limiter.admit(IP2, clock.now()).is_ok());
+        clock.advance(RATE_WINDOW);
+        assert!(limiter.admit(IP1, clock.now()).is_ok());
+        assert_eq!(0, limiter.pending(IP1));
+    }
+
+    #[test]
+    fn test_proof_of_work_above_rate() {
+        let clock = time::FakeClock::default();
+        let mut limiter = HandshakeLimiter::new(1, 100, 12);
+        assert_eq!(0, limiter.admit(IP1, clock.now()).unwrap().pow_difficulty);
+        assert_eq!(12, limiter.admit(IP1, clock.now()).unwrap().pow_difficulty);
+        assert_eq!(0, limiter.admit(IP2, clock.now()).unwrap().pow_difficulty);
+    }
+}
@@ -1,5 +1,16 @@
+pub(crate) mod address_proof;
+pub(crate) mod advisories;
+pub(crate) mod clock_skew;
+pub(crate) mod dedup;
+pub(crate) mod dns_boot_nodes;
+pub(crate) mod handshake_limiter;
+pub(crate) mod peer_diversity;
 pub(crate) mod peer_manager_actor;
 pub(crate) mod peer_store;
+#[cfg(feature = "port_mapping")]
+pub(crate) mod port_mapping;
+pub(crate) mod scoring;
+pub(crate) mod shard_admission;
 
 #[cfg(test)]
 pub(crate) mod testonly;
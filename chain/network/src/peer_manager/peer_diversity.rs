@@ -0,0 +1,183 @@
+//! Limits on the number of connected peers in a single subnet and in a single autonomous
+//! system, so that an outage of one hosting provider can't partition the node.
+//!
+//! Subnets are IPv4 /24 and IPv6 /48. Autonomous systems are looked up in an offline database
+//! in the TSV format of <https://iptoasn.com>, one range per line:
+//! `range_start range_end AS_number country_code AS_description`.
+use anyhow::{bail, Context as _};
+use near_network_primitives::types::NetworkConfig;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// Reason for rejecting a connection which would make the peers less diverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Rejection {
+    TooManyPeersInSubnet,
+    TooManyPeersInAsn,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Subnet {
+    V4([u8; 3]),
+    V6([u16; 3]),
+}
+
+fn subnet(ip: IpAddr) -> Subnet {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            Subnet::V4([a, b, c])
+        }
+        IpAddr::V6(ip) => {
+            let [a, b, c, ..] = ip.segments();
+            Subnet::V6([a, b, c])
+        }
+    }
+}
+
+/// Ranges of IPs announced by each autonomous system.
+pub(crate) struct AsnDatabase {
+    /// Disjoint ranges `(start, end, asn)`, sorted by `start`.
+    ranges: Vec<(IpAddr, IpAddr, u32)>,
+}
+
+impl AsnDatabase {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)?;
+        Self::parse(&data)
+    }
+
+    fn parse(data: &str) -> anyhow::Result<Self> {
+        let mut ranges = vec![];
+        for (i, line) in data.lines().enumerate() {
+            let fields: Vec<_> = line.split('\t').collect();
+            if fields.len() < 3 {
+                bail!("line {}: expected at least 3 fields", i + 1);
+            }
+            let start: IpAddr = fields[0].parse().with_context(|| format!("line {}", i + 1))?;
+            let end: IpAddr = fields[1].parse().with_context(|| format!("line {}", i + 1))?;
+            let asn: u32 = fields[2].parse().with_context(|| format!("line {}", i + 1))?;
+            // AS 0 marks the ranges which are not routed.
+            if asn != 0 {
+                ranges.push((start, end, asn));
+            }
+        }
+        ranges.sort();
+        Ok(Self { ranges })
+    }
+
+    /// Autonomous system announcing `ip`, if any.
+    pub fn lookup(&self, ip: IpAddr) -> Option<u32> {
+        let i = self.ranges.partition_point(|(start, _, _)| *start <= ip);
+        let (_, end, asn) = self.ranges.get(i.checked_sub(1)?)?;
+        (ip <= *end).then(|| *asn)
+    }
+}
+
+pub(crate) struct PeerDiversity {
+    max_peers_per_subnet: Option<u32>,
+    max_peers_per_asn: Option<u32>,
+    asn_database: Option<AsnDatabase>,
+}
+
+impl PeerDiversity {
+    pub fn new(config: &NetworkConfig) -> anyhow::Result<Self> {
+        let asn_database = match &config.asn_database {
+            Some(path) => Some(
+                AsnDatabase::load(path)
+                    .with_context(|| format!("failed to load the ASN database {path:?}"))?,
+            ),
+            None => None,
+        };
+        Ok(Self {
+            max_peers_per_subnet: config.max_peers_per_subnet,
+            max_peers_per_asn: config.max_peers_per_asn,
+            asn_database,
+        })
+    }
+
+    /// Decides whether a peer at `ip` can be connected in addition to the peers at
+    /// `connected`. IPs which are not in the ASN database are limited by the subnet only.
+    pub fn check(
+        &self,
+        ip: IpAddr,
+        connected: impl Iterator<Item = IpAddr>,
+    ) -> Result<(), Rejection> {
+        let ip_subnet = subnet(ip);
+        let ip_asn = match (&self.asn_database, self.max_peers_per_asn) {
+            (Some(db), Some(_)) => db.lookup(ip),
+            _ => None,
+        };
+        let mut in_subnet = 0;
+        let mut in_asn = 0;
+        for other in connected {
+            if subnet(other) == ip_subnet {
+                in_subnet += 1;
+            }
+            if ip_asn.is_some() && ip_asn == self.asn_database.as_ref().unwrap().lookup(other) {
+                in_asn += 1;
+            }
+        }
+        if self.max_peers_per_subnet.map_or(false, |max| in_subnet >= max) {
+            return Err(Rejection::TooManyPeersInSubnet);
+        }
+        if ip_asn.is_some() && self.max_peers_per_asn.map_or(false, |max| in_asn >= max) {
+            return Err(Rejection::TooManyPeersInAsn);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATABASE: &str = "\
+1.0.0.0\t1.0.0.255\t13335\tUS\tCLOUDFLARENET
+1.0.1.0\t1.0.3.255\t0\tNone\tNot routed
+1.0.4.0\t1.0.7.255\t38803\tAU\tWPL-AS-AP
+2001:200::\t2001:200:ffff:ffff:ffff:ffff:ffff:ffff\t2500\tJP\tWIDE-BB
+";
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_lookup() {
+        let db = AsnDatabase::parse(DATABASE).unwrap();
+        assert_eq!(Some(13335), db.lookup(ip("1.0.0.0")));
+        assert_eq!(Some(13335), db.lookup(ip("1.0.0.255")));
+        assert_eq!(None, db.lookup(ip("1.0.2.1")));
+        assert_eq!(Some(38803), db.lookup(ip("1.0.5.1")));
+        assert_eq!(None, db.lookup(ip("1.0.8.1")));
+        assert_eq!(None, db.lookup(ip("0.0.0.1")));
+        assert_eq!(Some(2500), db.lookup(ip("2001:200::1")));
+        assert_eq!(None, db.lookup(ip("2001:201::1")));
+        assert!(AsnDatabase::parse("1.0.0.0\t1.0.0.255\n").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let diversity = PeerDiversity {
+            max_peers_per_subnet: Some(2),
+            max_peers_per_asn: Some(3),
+            asn_database: Some(AsnDatabase::parse(DATABASE).unwrap()),
+        };
+        let connected = [ip("1.0.0.1"), ip("1.0.0.2"), ip("1.0.4.1")];
+        assert_eq!(
+            Err(Rejection::TooManyPeersInSubnet),
+            diversity.check(ip("1.0.0.3"), connected.into_iter())
+        );
+        assert_eq!(Ok(()), diversity.check(ip("1.0.5.1"), connected.into_iter()));
+        let connected = [ip("1.0.4.1"), ip("1.0.5.1"), ip("1.0.6.1")];
+        assert_eq!(
+            Err(Rejection::TooManyPeersInAsn),
+            diversity.check(ip("1.0.7.1"), connected.into_iter())
+        );
+        // IPs outside of the database are limited by the subnet only.
+        let connected = [ip("10.0.0.1"), ip("10.0.1.1"), ip("10.0.2.1")];
+        assert_eq!(Ok(()), diversity.check(ip("10.0.3.1"), connected.into_iter()));
+    }
+}
@@ -1,56 +1,81 @@
-use crate::network_protocol::Encoding;
+use crate::network_protocol::{AddressChallenge, Encoding, NetworkAdvisory, PeerHint};
+use crate::peer::bandwidth::{BandwidthLimiter, SharedTokenBucket, TokenBucket};
+use crate::peer::chain_info;
 use crate::peer::codec::Codec;
+use crate::peer::errors::{self, PeerErrorCounts};
 use crate::peer::peer_actor::PeerActor;
+use crate::peer::recorder::FrameRecorder;
+use crate::peer_manager::address_proof;
+use crate::peer_manager::advisories::{self, Advisories};
+use crate::peer_manager::clock_skew::{self, ClockSkew};
+use crate::peer_manager::dedup::RoutedMessageDedup;
+use crate::peer_manager::dns_boot_nodes;
+use crate::peer_manager::handshake_limiter::{HandshakeLimiter, PendingHandshake, Rejection};
+use crate::peer_manager::peer_diversity::{self, PeerDiversity};
 use crate::peer_manager::peer_store::PeerStore;
+#[cfg(feature = "port_mapping")]
+use crate::peer_manager::port_mapping;
+use crate::peer_manager::scoring::PeerScores;
+use crate::peer_manager::shard_admission::{Admission, ShardAdmission};
 use crate::private_actix::{
     PeerRequestResult, PeersRequest, RegisterPeer, RegisterPeerResponse, SendMessage, StopMsg,
     Unregister, ValidateEdgeList,
 };
 use crate::private_actix::{PeerToManagerMsg, PeerToManagerMsgResp, PeersResponse};
 use crate::routing;
+use crate::routing::delayed_delivery::DelayedDelivery;
 use crate::routing::edge_validator_actor::EdgeValidatorHelper;
+use crate::routing::pending_requests::PendingRequests;
 use crate::routing::routing_table_view::RoutingTableView;
 use crate::sink::Sink;
 use crate::stats::metrics;
 use crate::store;
+use crate::transport;
+use crate::transport::{quic, Stream, Transport as _};
 use crate::types::{
-    FullPeerInfo, NetworkClientMessages, NetworkInfo, NetworkRequests, NetworkResponses,
-    PeerManagerMessageRequest, PeerManagerMessageResponse, PeerMessage, QueryPeerStats,
-    RoutingTableUpdate,
+    BannedPeerInfo, ConnectedPeerInfo, FullPeerInfo, NetworkClientMessages, NetworkInfo,
+    NetworkRequests, NetworkResponses, PeerManagerMessageRequest, PeerManagerMessageResponse,
+    PeerMessage, QueryPeerStats, RoutingTableUpdate,
 };
 use actix::{
     Actor, ActorFutureExt, Addr, Arbiter, AsyncContext, Context, ContextFutureSpawner, Handler,
     Recipient, Running, StreamHandler, WrapFuture,
 };
-use anyhow::bail;
+use anyhow::{bail, Context as _};
+use near_network_primitives::chaos::Chaos;
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    AccountOrPeerIdOrHash, Ban, Edge, InboundTcpConnect, KnownPeerStatus, KnownProducer,
-    NetworkConfig, NetworkViewClientMessages, NetworkViewClientResponses, OutboundTcpConnect,
-    PeerIdOrHash, PeerInfo, PeerManagerRequest, PeerManagerRequestWithContext, PeerType, Ping,
-    Pong, RawRoutedMessage, ReasonForBan, RoutedMessageBody, RoutedMessageFrom, RoutedMessageV2,
-    StateResponseInfo,
+    AccessList, AccessRule, AccountOrPeerIdOrHash, Ban, BanSeverity, BlacklistEntry, Edge,
+    InboundTcpConnect, KnownPeerStatus, KnownProducer, NetworkConfig, NetworkViewClientMessages,
+    NetworkViewClientResponses, OutboundTcpConnect, PeerIdOrHash, PeerInfo, PeerManagerRequest,
+    PeerManagerRequestWithContext, PeerType, Ping, Pong, ProtocolDeprecation, RawRoutedMessage,
+    ReasonForBan, RoutedMessageBody, RoutedMessageFrom, RoutedMessageV2, StateResponseInfo,
+    Transport,
 };
 use near_network_primitives::types::{EdgeState, PartialEdgeInfo};
 use near_performance_metrics::framed_write::FramedWrite;
 use near_performance_metrics_macros::perf;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::types::{AccountId, EpochId};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, ShardId};
+use near_primitives::version::{
+    ProtocolVersion, NETWORK_ADVISORY_PROTOCOL_VERSION, PEER_ADDRESS_PROOF_PROTOCOL_VERSION,
+    PROTOCOL_DEPRECATION_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
 use near_rate_limiter::{
     ActixMessageResponse, ActixMessageWrapper, ThrottleController, ThrottleFramedRead,
     ThrottleToken,
 };
 use parking_lot::RwLock;
-use rand::seq::IteratorRandom;
-use rand::thread_rng;
+use rand::seq::SliceRandom;
+use rand::{Rng as _, SeedableRng as _};
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::ops::Sub;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
 use tokio_stream::StreamExt;
 use tracing::{debug, error, info, trace, warn, Instrument, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -74,6 +99,9 @@ const MONITOR_PEERS_MAX_DURATION: time::Duration = time::Duration::milliseconds(
 const MONITOR_PEERS_INITIAL_DURATION: time::Duration = time::Duration::milliseconds(10);
 /// Limit number of pending Peer actors to avoid OOM.
 const LIMIT_PENDING_PEERS: usize = 60;
+/// Number of known peers sampled when choosing an outbound peer. The one with the highest
+/// score is chosen, so that peers with a good history are preferred, but not exclusively.
+const OUTBOUND_CANDIDATES: usize = 5;
 /// How ofter should we broadcast edges.
 const BROADCAST_VALIDATED_EDGES_INTERVAL: time::Duration = time::Duration::milliseconds(50);
 /// Maximum amount of time spend processing edges.
@@ -82,6 +110,23 @@ const BROAD_CAST_EDGES_MAX_WORK_ALLOWED: time::Duration = time::Duration::millis
 const WAIT_FOR_SYNC_DELAY: time::Duration = time::Duration::milliseconds(1_000);
 /// How often should we update the routing table
 const UPDATE_ROUTING_TABLE_INTERVAL: time::Duration = time::Duration::milliseconds(1_000);
+/// How often to check for routed requests which haven't been answered in time.
+const EXPIRE_ROUTED_REQUESTS_INTERVAL: time::Duration = time::Duration::milliseconds(1_000);
+/// How often to resolve the boot nodes published in DNS.
+const RESOLVE_DNS_BOOT_NODES_INTERVAL: time::Duration = time::Duration::minutes(30);
+/// How often to renew the mapping of the listening port on the router, half of its lease.
+#[cfg(feature = "port_mapping")]
+const MAP_PORT_INTERVAL: time::Duration = time::Duration::minutes(30);
+/// How often to check whether a reachability check is due or has timed out.
+const CHECK_REACHABILITY_TRIGGER_INTERVAL: time::Duration = time::Duration::seconds(30);
+/// How often to ask a peer to dial our listening address back.
+const REACHABILITY_CHECK_INTERVAL: time::Duration = time::Duration::minutes(10);
+/// How long to wait for the dial-back before considering the listening address unreachable.
+const REACHABILITY_CHECK_TIMEOUT: time::Duration = time::Duration::seconds(30);
+/// How often to ask the view client whether the network switches to a newer protocol version.
+const CHECK_PROTOCOL_UPGRADE_INTERVAL: time::Duration = time::Duration::minutes(10);
+/// How often to refresh the block and chunk producers and connect to the missing ones.
+const TIER1_CONNECT_INTERVAL: time::Duration = time::Duration::seconds(10);
 /// How often to report bandwidth stats.
 const REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL: time::Duration =
     time::Duration::milliseconds(60_000);
@@ -98,6 +143,8 @@ const REPORT_BANDWIDTH_THRESHOLD_BYTES: usize = 10_000_000;
 const REPORT_BANDWIDTH_THRESHOLD_COUNT: usize = 10_000;
 /// How long a peer has to be unreachable, until we prune it from the in-memory graph.
 const PRUNE_UNREACHABLE_PEERS_AFTER: time::Duration = time::Duration::hours(1);
+/// Max number of peers remembered to support encryption, see `encrypting_peers`.
+const ENCRYPTING_PEERS_CAPACITY: usize = 10_000;
 
 /// Contains information relevant to a connected peer.
 struct ConnectedPeer {
@@ -119,6 +166,26 @@ struct ConnectedPeer {
     throttle_controller: ThrottleController,
     /// Encoding used for communication.
     encoding: Option<Encoding>,
+    /// Protocol version negotiated in the handshake.
+    protocol_version: ProtocolVersion,
+    /// Number of messages we've received from the peer during the last minute.
+    received_messages_per_min: usize,
+    /// Number of messages we've sent to the peer during the last minute.
+    sent_messages_per_min: usize,
+    /// Nonce of the challenge sent to the listening address advertised by the peer, until
+    /// the peer proves that it has received it. The address is not stored in the peer store
+    /// until then.
+    address_challenge: Option<u64>,
+    /// Whether the peer has asked us to challenge its listening address. Answered only once
+    /// per connection.
+    address_challenge_requested: bool,
+    /// Protocol version of the last upgrade the peer has been warned about with
+    /// `ProtocolDeprecation`.
+    protocol_deprecation_sent: Option<ProtocolVersion>,
+    /// Failures to handle the messages received from the peer since it connected.
+    errors: PeerErrorCounts,
+    /// Latest estimate of the offset of the clock of the peer relative to ours.
+    clock_offset: Option<time::Duration>,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -160,12 +227,23 @@ pub struct PeerManagerActor {
     outgoing_peers: HashSet<PeerId>,
     /// Connected peers (inbound and outbound) with their full peer information.
     connected_peers: HashMap<PeerId, ConnectedPeer>,
+    /// Peers which have advertised encryption support in their handshakes. Their later
+    /// handshakes without it are rejected, so that a man in the middle can't downgrade
+    /// the connections to plaintext.
+    encrypting_peers: lru::LruCache<PeerId, ()>,
     /// View of the Routing table. It keeps:
     /// - routing information - how to route messages
     /// - edges adjacent to my_peer_id
     /// - account id
     /// Full routing table (that currently includes information about all edges in the graph) is now inside Routing Table.
     routing_table_view: RoutingTableView,
+    /// Routed requests sent by this node, used to validate the responses routed back to us.
+    pending_requests: PendingRequests,
+    /// Approvals and chunk parts to validators we had no route to, retried on the routing
+    /// table updates. None unless `NetworkConfig::delayed_delivery_ttl` is set.
+    delayed_delivery: Option<DelayedDelivery>,
+    /// Scores of the peers, used to choose which peers to connect to and disconnect from.
+    peer_scores: PeerScores,
     /// A graph of the whole NEAR network, shared between routing::Actor
     /// and PeerManagerActor. PeerManagerActor should have read-only access to the graph.
     /// TODO: this is an intermediate step towards replacing actix runtime with a
@@ -188,10 +266,77 @@ pub struct PeerManagerActor {
     /// Whitelisted nodes, which are allowed to connect even if the connection limit has been
     /// reached.
     whitelist_nodes: Vec<WhitelistNode>,
+    /// QUIC endpoint, used to accept and dial connections if `config.transport` is QUIC.
+    /// Bound in `started`, since binding requires a running tokio runtime.
+    quic_endpoint: Option<quic::Endpoint>,
+    /// Limit of the total outbound bandwidth, shared by all the PeerActors.
+    total_send_limit: Option<SharedTokenBucket>,
+    /// Per-IP limits of the inbound connections, applied before spawning a PeerActor.
+    handshake_limiter: HandshakeLimiter,
+    /// Limits of the connections accepted by `config.listeners`, by listener.
+    listener_limiters: Vec<HandshakeLimiter>,
+    /// External port mapped to our listening port on the router and the time it was mapped.
+    /// Advertised in the handshake instead of the port of `config.node_addr`.
+    mapped_port: Option<(u16, time::Instant)>,
+    /// Peer asked to dial our listening address back and the time it was asked.
+    reachability_check: Option<(PeerId, time::Instant)>,
+    /// Time the last reachability check started.
+    last_reachability_check: Option<time::Instant>,
+    /// Switch of the network to a newer protocol version in the next epoch, as reported by
+    /// the view client. Peers running older versions are warned about it.
+    protocol_upgrade: Option<ProtocolDeprecation>,
+    /// Height of the header head of this node, as reported by the view client. None until
+    /// the first report.
+    my_header_height: Option<BlockHeight>,
+    /// Peers of the other block and chunk producers of the current and next epoch, as
+    /// announced in `AnnounceAccount`. The node keeps direct connections to them, see
+    /// `NetworkConfig::tier1_connections`.
+    tier1_peers: HashSet<PeerId>,
+    /// Limits on the connected peers per subnet and autonomous system.
+    peer_diversity: PeerDiversity,
+    /// Slots reserved for the inbound peers tracking some of our shards, see
+    /// `NetworkConfig::inbound_tracked_shards_ratio`.
+    shard_admission: ShardAdmission,
+    /// Deprecation of our protocol version announced by a peer, reported to the client.
+    protocol_deprecation: Option<ProtocolDeprecation>,
+    /// Network advisories accepted so far, see `NetworkConfig::advisory_keys`.
+    advisories: Advisories,
+    /// Skew of the local clock relative to the clocks of the connected peers.
+    clock_skew: ClockSkew,
+    /// Recording of the frames received from all the peers, see `NetworkConfig::record_frames`.
+    recorder: Option<Arc<FrameRecorder>>,
+    /// Faults injected into the messages received from all the peers, see
+    /// `NetworkConfig::chaos`.
+    chaos: Option<Arc<Chaos>>,
+    /// Routed messages recently received from all the peers, to drop their duplicates.
+    routed_message_dedup: Arc<parking_lot::Mutex<RoutedMessageDedup>>,
+    /// Store in which `routed_message_dedup` is saved, see
+    /// `NetworkConfig::persist_routed_message_dedup`.
+    store: store::Store,
+    /// Source of randomness of all the decisions: which peers to dial, disconnect or ask for
+    /// chunks. Together with `clock` it makes the behavior reproducible, see `with_rng_seed`.
+    rng: Rng,
     /// test-only.
     event_sink: Sink<Event>,
 }
 
+type Rng = rand_pcg::Pcg32;
+
+/// Round-trip time of a new connection: the estimate of the transport if it keeps one,
+/// `measured` otherwise, which `source` tells how was measured.
+fn connection_rtt(
+    stream: &Stream,
+    measured: std::time::Duration,
+    source: &'static str,
+) -> std::time::Duration {
+    let (rtt, source) = match stream.rtt() {
+        Some(rtt) => (rtt, "quic"),
+        None => (measured, source),
+    };
+    metrics::PEER_CONNECTION_RTT.with_label_values(&[source]).observe(rtt.as_secs_f64());
+    rtt
+}
+
 // test-only
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Event {
@@ -206,34 +351,71 @@ impl Actor for PeerManagerActor {
     type Context = Context<Self>;
 
     fn started(&mut self, ctx: &mut Self::Context) {
-        // Start server if address provided.
-        if let Some(server_addr) = self.config.node_addr {
-            debug!(target: "network", at = ?server_addr, "starting public server");
+        // Start server if address provided, along with the additional listeners.
+        let server_addrs: Vec<_> = self
+            .config
+            .node_addr
+            .map(|addr| (None, addr))
+            .into_iter()
+            .chain(self.config.listeners.iter().enumerate().map(|(i, l)| (Some(i), l.addr)))
+            .collect();
+        if !server_addrs.is_empty() {
+            debug!(target: "network", at = ?server_addrs, "starting public server");
             let peer_manager_addr = ctx.address();
             let event_sink = self.event_sink.clone();
+            let socket_options = self.config.socket_options;
 
             actix::spawn(async move {
-                let listener = match TcpListener::bind(server_addr).await {
-                    Ok(it) => it,
-                    Err(e) => {
-                        panic!(
-                            "failed to start listening on server_addr={:?} e={:?}",
-                            server_addr, e
-                        );
+                let mut listeners = vec![];
+                for (index, server_addr) in server_addrs {
+                    match TcpListener::bind(server_addr).await {
+                        Ok(it) => {
+                            if let Err(err) = transport::set_socket_options(
+                                (&it).into(),
+                                &server_addr,
+                                &socket_options,
+                            ) {
+                                warn!(target: "network", ?server_addr, ?err, "Failed to set socket options");
+                            }
+                            listeners.push((index, it))
+                        }
+                        Err(e) => {
+                            panic!(
+                                "failed to start listening on server_addr={:?} e={:?}",
+                                server_addr, e
+                            );
+                        }
                     }
-                };
+                }
                 event_sink.push(Event::ServerStarted);
-                loop {
-                    if let Ok((conn, client_addr)) = listener.accept().await {
-                        peer_manager_addr.do_send(PeerToManagerMsg::InboundTcpConnect(
-                            InboundTcpConnect::new(conn),
-                        ));
-                        debug!(target: "network", from = ?client_addr, "got new connection");
+                futures::future::join_all(listeners.into_iter().map(|(index, listener)| {
+                    let peer_manager_addr = peer_manager_addr.clone();
+                    async move {
+                        loop {
+                            if let Ok((conn, client_addr)) = listener.accept().await {
+                                if let Err(err) = transport::set_socket_options(
+                                    (&conn).into(),
+                                    &client_addr,
+                                    &socket_options,
+                                ) {
+                                    warn!(target: "network", ?client_addr, ?err, "Failed to set socket options");
+                                }
+                                peer_manager_addr.do_send(PeerToManagerMsg::InboundTcpConnect(
+                                    InboundTcpConnect::new(conn, index),
+                                ));
+                                debug!(target: "network", from = ?client_addr, listener = ?index, "got new connection");
+                            }
+                        }
                     }
-                }
+                }))
+                .await;
             });
         }
 
+        if self.config.transport == Transport::Quic {
+            self.start_quic_endpoint(ctx);
+        }
+
         // Periodically push network information to client.
         self.push_network_info_trigger(ctx, self.config.push_info_period.try_into().unwrap());
 
@@ -258,6 +440,35 @@ impl Actor for PeerManagerActor {
 
         // Periodically prints bandwidth stats for each peer.
         self.report_bandwidth_stats_trigger(ctx, REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL);
+
+        // Periodically reports the routes of unanswered routed requests to the routing table.
+        self.expire_routed_requests_trigger(ctx, EXPIRE_ROUTED_REQUESTS_INTERVAL);
+
+        // Periodically resolves the boot nodes published in DNS.
+        if !self.config.boot_node_domains.is_empty() {
+            self.resolve_dns_boot_nodes_trigger(ctx, RESOLVE_DNS_BOOT_NODES_INTERVAL);
+        }
+
+        if self.config.node_addr.is_some() {
+            // Periodically maps the listening port on the router.
+            if self.config.port_mapping {
+                #[cfg(feature = "port_mapping")]
+                self.map_port_trigger(ctx, MAP_PORT_INTERVAL);
+                #[cfg(not(feature = "port_mapping"))]
+                warn!(target: "network", "network.port_mapping is set, but neard was built without the port_mapping feature");
+            }
+
+            // Periodically asks a peer to dial the listening address back.
+            self.check_reachability_trigger(ctx, CHECK_REACHABILITY_TRIGGER_INTERVAL);
+        }
+
+        // Periodically warns peers running protocol versions which the next epoch drops.
+        self.check_protocol_upgrade_trigger(ctx, CHECK_PROTOCOL_UPGRADE_INTERVAL);
+
+        // Periodically connects to the other block and chunk producers directly.
+        if self.config.tier1_connections && self.config.validator.is_some() {
+            self.tier1_connect_trigger(ctx, TIER1_CONNECT_INTERVAL);
+        }
     }
 
     /// Try to gracefully disconnect from connected peers.
@@ -271,36 +482,95 @@ impl Actor for PeerManagerActor {
 
         self.routing_table_addr.do_send(StopMsg {});
 
+        if self.config.persist_routed_message_dedup {
+            let messages = self.routed_message_dedup.lock().recent(self.clock.now_utc());
+            if let Err(err) = self.store.set_routed_messages(&messages) {
+                error!(target: "network", ?err, "Failed to save the recently received routed messages");
+            }
+        }
+
         Running::Stop
     }
 }
 
 impl PeerManagerActor {
     pub fn new(
+        clock: time::Clock,
         store: near_store::Store,
         config: NetworkConfig,
         client_addr: Recipient<NetworkClientMessages>,
         view_client_addr: Recipient<NetworkViewClientMessages>,
     ) -> anyhow::Result<Self> {
-        let clock = time::Clock::real();
         let store = store::Store::from(store);
-        let peer_store =
-            PeerStore::new(&clock, store.clone(), &config.boot_nodes, config.blacklist.clone())
-                .map_err(|e| anyhow::Error::msg(e.to_string()))?;
+        let peer_store = PeerStore::new(
+            &clock,
+            store.clone(),
+            &config.boot_nodes,
+            AccessList::new(config.blacklist.clone(), config.allowlist.clone()),
+        )
+        .map_err(|e| anyhow::Error::msg(e.to_string()))?;
         debug!(target: "network",
                len = peer_store.len(),
                boot_nodes = config.boot_nodes.len(),
                banned = peer_store.count_banned(),
                "Found known peers");
-        debug!(target: "network", blacklist = ?config.blacklist, "Blacklist");
+        debug!(target: "network", access_list = ?peer_store.access_list(), "Blacklist");
 
         let my_peer_id = config.node_id();
         let network_graph = Arc::new(RwLock::new(routing::GraphWithCache::new(my_peer_id.clone())));
-        let routing_table_addr =
-            routing::Actor::new(clock.clone(), store.clone(), network_graph.clone()).start();
-        let routing_table_view = RoutingTableView::new(store, my_peer_id.clone());
+        let routing_table_addr = routing::Actor::new(
+            clock.clone(),
+            store.clone(),
+            network_graph.clone(),
+            config.max_routing_table_edges,
+        )
+        .start();
+        let routing_table_view = RoutingTableView::new(store.clone(), my_peer_id.clone());
+
+        let mut routed_message_dedup = RoutedMessageDedup::new(
+            config.routed_message_dedup_capacity,
+            config.routed_message_dedup_window.try_into()?,
+        );
+        if config.persist_routed_message_dedup {
+            routed_message_dedup.load(store.list_routed_messages()?, clock.now_utc());
+            debug!(target: "network", len = routed_message_dedup.len(), "Loaded recently received routed messages");
+        }
 
         let txns_since_last_block = Arc::new(AtomicUsize::new(0));
+        let total_send_limit = config
+            .total_send_limit_bytes_per_sec
+            .map(|rate| Arc::new(Mutex::new(TokenBucket::new(rate, clock.now()))));
+
+        let handshake_limiter = HandshakeLimiter::new(
+            config.max_inbound_connections_per_ip_per_minute,
+            config.max_pending_handshakes_per_ip,
+            config.handshake_pow_difficulty,
+        );
+        let listener_limiters = config
+            .listeners
+            .iter()
+            .map(|listener| {
+                HandshakeLimiter::new(
+                    listener.max_inbound_connections_per_ip_per_minute,
+                    listener.max_pending_handshakes_per_ip,
+                    config.handshake_pow_difficulty,
+                )
+            })
+            .collect();
+
+        let recorder = match &config.record_frames {
+            Some(path) => {
+                info!(target: "network", ?path, "Recording the frames received from peers");
+                Some(Arc::new(FrameRecorder::open(path).context("failed to open the recording")?))
+            }
+            None => None,
+        };
+
+        let chaos = config.chaos.clone().map(|chaos| Arc::new(Chaos::new(chaos, clock.now())));
+
+        let peer_diversity = PeerDiversity::new(&config)?;
+        let shard_admission = ShardAdmission::new(config.inbound_tracked_shards_ratio);
+        let advisories = Advisories::new(config.advisory_keys.clone(), config.ignore_advisories);
 
         let whitelist_nodes = {
             let mut v = vec![];
@@ -309,6 +579,10 @@ impl PeerManagerActor {
             }
             v
         };
+        let delayed_delivery = match config.delayed_delivery_ttl {
+            Some(ttl) => Some(DelayedDelivery::new(ttl.try_into()?)),
+            None => None,
+        };
 
         Ok(Self {
             clock,
@@ -318,8 +592,12 @@ impl PeerManagerActor {
             view_client_addr,
             peer_store,
             connected_peers: HashMap::default(),
+            encrypting_peers: lru::LruCache::new(ENCRYPTING_PEERS_CAPACITY),
             outgoing_peers: HashSet::default(),
             routing_table_view,
+            pending_requests: PendingRequests::default(),
+            delayed_delivery,
+            peer_scores: PeerScores::default(),
             network_graph,
             routing_table_exchange_helper: Default::default(),
             started_connect_attempts: false,
@@ -328,16 +606,66 @@ impl PeerManagerActor {
             txns_since_last_block,
             peer_counter: Arc::new(AtomicUsize::new(0)),
             whitelist_nodes,
+            quic_endpoint: None,
+            total_send_limit,
+            handshake_limiter,
+            listener_limiters,
+            mapped_port: None,
+            reachability_check: None,
+            last_reachability_check: None,
+            protocol_upgrade: None,
+            my_header_height: None,
+            tier1_peers: HashSet::default(),
+            peer_diversity,
+            shard_admission,
+            protocol_deprecation: None,
+            advisories,
+            clock_skew: ClockSkew::default(),
+            recorder,
+            chaos,
+            routed_message_dedup: Arc::new(parking_lot::Mutex::new(routed_message_dedup)),
+            store,
+            rng: Rng::seed_from_u64(rand::thread_rng().gen()),
             event_sink: Sink::void(),
         })
     }
 
+    /// Binds the QUIC endpoint to the UDP port of `node_addr` and starts accepting connections.
+    /// Without `node_addr` the endpoint is bound to an ephemeral port and only used to dial.
+    fn start_quic_endpoint(&mut self, ctx: &mut Context<Self>) {
+        let addr = self.config.node_addr.unwrap_or_else(|| (Ipv4Addr::UNSPECIFIED, 0).into());
+        let (endpoint, mut incoming) = match quic::Endpoint::bind(addr) {
+            Ok(it) => it,
+            Err(e) => panic!("failed to start QUIC endpoint on addr={:?} e={:?}", addr, e),
+        };
+        self.quic_endpoint = Some(endpoint.clone());
+        if self.config.node_addr.is_none() {
+            return;
+        }
+        debug!(target: "network", at = ?addr, "starting QUIC server");
+        let peer_manager_addr = ctx.address();
+        actix::spawn(async move {
+            // The connections are completed by the PeerManager, once they pass the same limits
+            // as the TCP ones, see `handle_msg_inbound_quic_connect`.
+            while let Some(connecting) = incoming.next().await {
+                peer_manager_addr.do_send(PeerToManagerMsg::InboundQuicConnect(connecting));
+            }
+        });
+    }
+
     /// test-only, sets the event handler.
     pub fn with_event_sink(mut self, event_sink: Sink<Event>) -> Self {
         self.event_sink = event_sink;
         self
     }
 
+    /// test-only, seeds the random decisions, so that given the same seed, clock and inputs
+    /// the actor behaves the same way.
+    pub fn with_rng_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::seed_from_u64(seed);
+        self
+    }
+
     fn update_routing_table(
         &self,
         ctx: &mut Context<Self>,
@@ -356,6 +684,7 @@ impl PeerManagerActor {
                         act.routing_table_view.remove_local_edge(peer_id);
                     }
                     act.routing_table_view.set_next_hops(next_hops.clone());
+                    act.retry_delayed_messages();
                     for peer in peers_to_ban {
                         act.ban_peer(&peer, ReasonForBan::InvalidEdge);
                     }
@@ -387,6 +716,7 @@ impl PeerManagerActor {
         for account in &accounts {
             self.routing_table_view.add_account(account.clone());
         }
+        self.retry_delayed_messages();
 
         Self::broadcast_message(
             &self.connected_peers,
@@ -414,6 +744,302 @@ impl PeerManagerActor {
         );
     }
 
+    /// Periodically reports the first hops of the routed requests which haven't been answered
+    /// in time to the routing table, so that it tries alternative routes.
+    fn expire_routed_requests_trigger(
+        &mut self,
+        ctx: &mut Context<Self>,
+        interval: time::Duration,
+    ) {
+        for first_hop in self.pending_requests.expire(self.clock.now()) {
+            metrics::ROUTED_REQUEST_TIMEOUTS.inc();
+            debug!(target: "network", ?first_hop, "Routed request timed out");
+            self.routing_table_view.record_route_failure(&self.clock, &first_hop);
+        }
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.expire_routed_requests_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Periodically resolves the boot nodes published in DNS and adds them to the peer store,
+    /// so that the boot nodes can be changed without updating the config of every node.
+    fn resolve_dns_boot_nodes_trigger(&self, ctx: &mut Context<Self>, interval: time::Duration) {
+        dns_boot_nodes::resolve_all(self.config.boot_node_domains.clone())
+            .into_actor(self)
+            .map(|results, act, _ctx| {
+                let results = match results {
+                    Ok(results) => results,
+                    Err(err) => {
+                        warn!(target: "network", ?err, "Failed to resolve boot nodes");
+                        return;
+                    }
+                };
+                for (domain, result) in results {
+                    match result {
+                        Ok(peers) => {
+                            debug!(target: "network", %domain, ?peers, "Resolved boot nodes");
+                            if let Err(err) = act.peer_store.add_indirect_peers(
+                                &act.clock,
+                                peers
+                                    .into_iter()
+                                    .filter(|peer_info| peer_info.id != act.my_peer_id),
+                            ) {
+                                error!(target: "network", ?err, "Fail to update peer store");
+                            }
+                        }
+                        Err(err) => {
+                            warn!(target: "network", %domain, ?err, "Failed to resolve boot nodes")
+                        }
+                    }
+                }
+            })
+            .spawn(ctx);
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.resolve_dns_boot_nodes_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Periodically maps the listening port on the router, renewing the lease of the mapping.
+    /// A change of the external port applies to the connections established afterwards.
+    #[cfg(feature = "port_mapping")]
+    fn map_port_trigger(&self, ctx: &mut Context<Self>, interval: time::Duration) {
+        let server_addr = match self.config.node_addr {
+            Some(server_addr) => server_addr,
+            None => return,
+        };
+        let udp = self.config.transport == Transport::Quic;
+        port_mapping::map_port(server_addr, udp)
+            .into_actor(self)
+            .map(|result, act, _ctx| match result {
+                Ok(port) => {
+                    if act.mapped_port.map(|(mapped_port, _)| mapped_port) != Some(port) {
+                        info!(target: "network", port, "Mapped the listening port on the router");
+                        act.mapped_port = Some((port, act.clock.now()));
+                        // Check the new port right away.
+                        act.last_reachability_check = None;
+                    }
+                }
+                Err(err) => {
+                    warn!(target: "network", ?err, "Failed to map the listening port on the router")
+                }
+            })
+            .spawn(ctx);
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.map_port_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Periodically asks a random peer to dial our listening address back, and warns if it
+    /// doesn't, since then no peer can connect to us. Only peers we have dialed are asked:
+    /// the others may not know our listening address.
+    fn check_reachability_trigger(&mut self, ctx: &mut Context<Self>, interval: time::Duration) {
+        let now = self.clock.now();
+        if let Some((peer_id, started)) = &self.reachability_check {
+            if now - *started >= REACHABILITY_CHECK_TIMEOUT {
+                let port = match self.mapped_port {
+                    Some((port, _)) => port,
+                    None => self.config.node_addr.map_or(0, |addr| addr.port()),
+                };
+                warn!(target: "network", ?peer_id, port,
+                      "Peer failed to connect to our listening address, other nodes may be unable to connect to us. \
+                       Forward the port on the router or firewall{}",
+                      if self.config.port_mapping { "" } else { ", or set network.port_mapping" });
+                metrics::LISTEN_ADDR_REACHABLE.set(0);
+                self.reachability_check = None;
+            }
+        }
+        let check_due = self
+            .last_reachability_check
+            .map_or(true, |last| now - last >= REACHABILITY_CHECK_INTERVAL);
+        if self.reachability_check.is_none() && check_due {
+            let mapped_at = self.mapped_port.map(|(_, mapped_at)| mapped_at);
+            let candidates: Vec<_> = self
+                .connected_peers
+                .iter()
+                .filter(|(_, p)| {
+                    p.peer_type == PeerType::Outbound
+                        && p.protocol_version >= PEER_ADDRESS_PROOF_PROTOCOL_VERSION
+                        // Connections established earlier advertise the old port.
+                        && mapped_at.map_or(true, |t| p.connection_established_time > t)
+                })
+                .map(|(peer_id, _)| peer_id.clone())
+                .collect();
+            if let Some(peer_id) = candidates.choose(&mut self.rng) {
+                debug!(target: "network", ?peer_id, "Asking peer to dial our listening address");
+                Self::send_message(
+                    &self.connected_peers,
+                    peer_id.clone(),
+                    PeerMessage::AddressChallengeRequest,
+                );
+                self.reachability_check = Some((peer_id.clone(), now));
+                self.last_reachability_check = Some(now);
+            }
+        }
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.check_reachability_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Periodically asks the view client whether the network switches to a newer protocol
+    /// version in the next epoch, and warns the connected peers which don't support it.
+    /// Also refreshes the shards tracked by the node, for `shard_admission`, and the header
+    /// head, for `highest_height_peers`.
+    fn check_protocol_upgrade_trigger(&self, ctx: &mut Context<Self>, interval: time::Duration) {
+        self.view_client_addr
+            .send(NetworkViewClientMessages::GetChainInfo)
+            .into_actor(self)
+            .map(|response, act, _ctx| match response {
+                Ok(NetworkViewClientResponses::ChainInfo {
+                    header_height,
+                    protocol_upgrade,
+                    tracked_shards,
+                    ..
+                }) => {
+                    act.my_header_height = Some(header_height);
+                    act.protocol_upgrade = protocol_upgrade;
+                    act.shard_admission.set_tracked_shards(tracked_shards);
+                    act.send_protocol_deprecations();
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    error!(target: "network", ?err, "Failed sending GetChainInfo to view client")
+                }
+            })
+            .spawn(ctx);
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.check_protocol_upgrade_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Periodically asks the view client for the block and chunk producers of the current and
+    /// next epoch, and connects to the ones the node isn't connected to yet.
+    fn tier1_connect_trigger(&self, ctx: &mut Context<Self>, interval: time::Duration) {
+        self.view_client_addr
+            .send(NetworkViewClientMessages::GetTier1Accounts)
+            .into_actor(self)
+            .map(|response, act, ctx| match response {
+                Ok(NetworkViewClientResponses::Tier1Accounts(accounts)) => {
+                    let my_account_id = act.config.validator.as_ref().map(|v| v.account_id());
+                    let mut tier1_peers = HashSet::new();
+                    for account_id in &accounts {
+                        if Some(account_id) == my_account_id.as_ref() {
+                            continue;
+                        }
+                        // Accounts which haven't been announced yet are retried next time.
+                        if let Ok(peer_id) = act.routing_table_view.account_owner(account_id) {
+                            if peer_id != act.my_peer_id {
+                                tier1_peers.insert(peer_id);
+                            }
+                        }
+                    }
+                    act.tier1_peers = tier1_peers;
+                    act.connect_tier1_peers(ctx);
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    error!(target: "network", ?err, "Failed sending GetTier1Accounts to view client")
+                }
+            })
+            .spawn(ctx);
+
+        near_performance_metrics::actix::run_later(
+            ctx,
+            interval.try_into().unwrap(),
+            move |act, ctx| {
+                act.tier1_connect_trigger(ctx, interval);
+            },
+        );
+    }
+
+    /// Dials the TIER1 peers the node isn't connected to, at the addresses from the peer
+    /// store. Peers whose address is unknown are reached over multiple hops until they dial us.
+    fn connect_tier1_peers(&mut self, ctx: &mut Context<Self>) {
+        let mut to_dial = vec![];
+        for peer_id in &self.tier1_peers {
+            if self.connected_peers.contains_key(peer_id)
+                || self.outgoing_peers.contains(peer_id)
+                || self.peer_store.is_banned(peer_id)
+            {
+                continue;
+            }
+            match self.peer_store.get(peer_id) {
+                Some(state) if state.peer_info.addr.is_some() => {
+                    to_dial.push(state.peer_info.clone())
+                }
+                _ => {}
+            }
+        }
+        for peer_info in to_dial {
+            debug!(target: "network", peer_id = ?peer_info.id, "Connecting to TIER1 peer");
+            self.outgoing_peers.insert(peer_info.id.clone());
+            ctx.notify(PeerManagerMessageRequest::OutboundTcpConnect(OutboundTcpConnect {
+                peer_info,
+            }));
+        }
+        metrics::TIER1_PEERS.set(self.tier1_peers.len() as i64);
+        metrics::TIER1_PEERS_CONNECTED.set(
+            self.tier1_peers.iter().filter(|id| self.connected_peers.contains_key(id)).count()
+                as i64,
+        );
+    }
+
+    /// Sends `ProtocolDeprecation` to the connected peers running a protocol version older
+    /// than the upcoming upgrade, once per connection and upgrade.
+    fn send_protocol_deprecations(&mut self) {
+        let upgrade = match self.protocol_upgrade {
+            Some(upgrade) => upgrade,
+            None => return,
+        };
+        // The version negotiated with a peer is capped at ours, so it tells whether the peer
+        // supports the upgrade only if we do.
+        if PROTOCOL_VERSION < upgrade.protocol_version {
+            return;
+        }
+        let mut peer_ids = vec![];
+        for (peer_id, connected_peer) in self.connected_peers.iter_mut() {
+            if connected_peer.protocol_version >= PROTOCOL_DEPRECATION_PROTOCOL_VERSION
+                && connected_peer.protocol_version < upgrade.protocol_version
+                && connected_peer.protocol_deprecation_sent != Some(upgrade.protocol_version)
+            {
+                connected_peer.protocol_deprecation_sent = Some(upgrade.protocol_version);
+                peer_ids.push(peer_id.clone());
+            }
+        }
+        for peer_id in peer_ids {
+            debug!(target: "network", ?peer_id, ?upgrade, "Warning peer about deprecated protocol version");
+            Self::send_message(
+                &self.connected_peers,
+                peer_id,
+                PeerMessage::ProtocolDeprecation(upgrade),
+            );
+        }
+    }
+
     /// Periodically prints bandwidth stats for each peer.
     fn report_bandwidth_stats_trigger(&mut self, ctx: &mut Context<Self>, every: time::Duration) {
         let mut total_bandwidth_used_by_all_peers: usize = 0;
@@ -547,8 +1173,10 @@ impl PeerManagerActor {
         full_peer_info: FullPeerInfo,
         partial_edge_info: PartialEdgeInfo,
         peer_type: PeerType,
+        protocol_version: ProtocolVersion,
         addr: Addr<PeerActor>,
         throttle_controller: ThrottleController,
+        clock_offset: Option<time::Duration>,
         ctx: &mut Context<Self>,
     ) {
         let _span = tracing::trace_span!(target: "network", "register_peer").entered();
@@ -557,7 +1185,21 @@ impl PeerManagerActor {
         if self.outgoing_peers.contains(&full_peer_info.peer_info.id) {
             self.outgoing_peers.remove(&full_peer_info.peer_info.id);
         }
-        if let Err(err) = self.peer_store.peer_connected(&self.clock, &full_peer_info.peer_info) {
+        // The listening address advertised by an inbound peer is stored only once the peer
+        // proves that it is listening there, see `address_proof`.
+        let address_challenge = if peer_type == PeerType::Inbound
+            && protocol_version >= PEER_ADDRESS_PROOF_PROTOCOL_VERSION
+            && !self.peer_store.has_signed_addr(&full_peer_info.peer_info)
+        {
+            full_peer_info.peer_info.addr.map(|addr| (addr, self.rng.gen::<u64>()))
+        } else {
+            None
+        };
+        let mut peer_info = full_peer_info.peer_info.clone();
+        if address_challenge.is_some() {
+            peer_info.addr = None;
+        }
+        if let Err(err) = self.peer_store.peer_connected(&self.clock, &peer_info) {
             error!(target: "network", ?err, "Failed to save peer data");
             return;
         };
@@ -585,8 +1227,23 @@ impl PeerManagerActor {
                 peer_type,
                 throttle_controller: throttle_controller.clone(),
                 encoding: None,
+                protocol_version,
+                received_messages_per_min: 0,
+                sent_messages_per_min: 0,
+                address_challenge: address_challenge.map(|(_, nonce)| nonce),
+                address_challenge_requested: false,
+                protocol_deprecation_sent: None,
+                errors: PeerErrorCounts::default(),
+                clock_offset: None,
             },
         );
+        if let Some(offset) = clock_offset {
+            self.record_clock_offset(&target_peer_id, "handshake", offset);
+        }
+        if let Some((listen_addr, nonce)) = address_challenge {
+            self.send_address_challenge(ctx, target_peer_id.clone(), listen_addr, nonce);
+        }
+        self.send_protocol_deprecations();
 
         self.add_verified_edges_to_routing_table(vec![new_edge.clone()]);
 
@@ -602,6 +1259,147 @@ impl PeerManagerActor {
         );
     }
 
+    /// Dials the listening address advertised by an inbound peer and sends a challenge there,
+    /// which the peer is expected to answer with `AddressProof`.
+    fn send_address_challenge(
+        &self,
+        ctx: &mut Context<Self>,
+        peer_id: PeerId,
+        addr: SocketAddr,
+        nonce: u64,
+    ) {
+        metrics::PEER_ADDRESS_CHALLENGES.with_label_values(&["sent"]).inc();
+        let challenge =
+            AddressChallenge { verifier: self.my_peer_id.clone(), target: peer_id, nonce };
+        address_proof::send_challenge(self.quic_endpoint.clone(), addr, challenge)
+            .into_actor(self)
+            .map(move |result, _act, _ctx| {
+                if let Err(err) = result {
+                    metrics::PEER_ADDRESS_CHALLENGES.with_label_values(&["unreachable"]).inc();
+                    debug!(target: "network", ?addr, ?err, "Failed to send address challenge");
+                }
+            })
+            .spawn(ctx);
+    }
+
+    /// Answers a challenge received at our listening address over our connection with
+    /// the verifier. Receiving it also proves that our listening address is reachable.
+    fn handle_msg_address_challenge(&mut self, challenge: AddressChallenge) {
+        if self.reachability_check.take().is_some() {
+            debug!(target: "network", verifier = ?challenge.verifier, "Listening address is reachable");
+        }
+        metrics::LISTEN_ADDR_REACHABLE.set(1);
+        Self::send_message(
+            &self.connected_peers,
+            challenge.verifier,
+            PeerMessage::AddressProof(challenge.nonce),
+        );
+    }
+
+    /// Challenges the listening address of the peer on its request, unless a challenge is
+    /// already on its way.
+    fn handle_msg_address_challenge_request(&mut self, ctx: &mut Context<Self>, peer_id: PeerId) {
+        let connected_peer = match self.connected_peers.get_mut(&peer_id) {
+            Some(connected_peer) => connected_peer,
+            None => return,
+        };
+        if connected_peer.address_challenge_requested {
+            debug!(target: "network", ?peer_id, "Received repeated address challenge request");
+            return;
+        }
+        connected_peer.address_challenge_requested = true;
+        if connected_peer.address_challenge.is_some() {
+            return;
+        }
+        let addr = match connected_peer.full_peer_info.peer_info.addr {
+            Some(addr) => addr,
+            None => return,
+        };
+        let nonce = self.rng.gen::<u64>();
+        connected_peer.address_challenge = Some(nonce);
+        self.send_address_challenge(ctx, peer_id, addr, nonce);
+    }
+
+    /// Warns the operator that the network is about to drop our protocol version. Any peer
+    /// can send the notice, so it is only logged and reported in `/status`, not acted upon.
+    fn handle_msg_protocol_deprecation(
+        &mut self,
+        peer_id: PeerId,
+        deprecation: ProtocolDeprecation,
+    ) {
+        if deprecation.protocol_version <= PROTOCOL_VERSION {
+            debug!(target: "network", ?peer_id, ?deprecation, "Received deprecation of an older protocol version");
+            return;
+        }
+        if self.protocol_deprecation == Some(deprecation) {
+            return;
+        }
+        warn!(target: "network", ?peer_id,
+              "Network switches to protocol version {} at epoch {}, which this node doesn't support (its protocol version is {}). \
+               Upgrade neard before then, otherwise the node will stop following the chain.",
+              deprecation.protocol_version, deprecation.epoch_height, PROTOCOL_VERSION);
+        self.protocol_deprecation = Some(deprecation);
+    }
+
+    /// Logs the advisory, reports it in `/status` and relays it to the connected peers other
+    /// than `from`, if it is accepted. Peers sending advisories with an invalid signature of
+    /// one of the advisory keys are banned.
+    fn handle_msg_network_advisory(
+        &mut self,
+        from: Option<PeerId>,
+        advisory: NetworkAdvisory,
+    ) -> Result<(), advisories::Rejection> {
+        let result = self.advisories.accept(&self.clock, &advisory);
+        let label = match &result {
+            Ok(()) => "accepted",
+            Err(rejection) => rejection.as_ref(),
+        };
+        metrics::NETWORK_ADVISORIES.with_label_values(&[label]).inc();
+        if let Err(rejection) = result {
+            debug!(target: "network", peer_id = ?from, ?rejection, ?advisory, "Dropping network advisory");
+            if let (Some(peer_id), advisories::Rejection::InvalidSignature) = (&from, rejection) {
+                self.try_ban_peer(peer_id, ReasonForBan::InvalidSignature);
+            }
+            return result;
+        }
+        warn!(target: "network", signer = %advisory.signer, nonce = advisory.nonce, timestamp = advisory.timestamp,
+              "Network advisory: {}", advisory.message);
+        let peer_ids: Vec<_> = (self.connected_peers.iter())
+            .filter(|(peer_id, connected_peer)| {
+                Some(*peer_id) != from.as_ref()
+                    && connected_peer.protocol_version >= NETWORK_ADVISORY_PROTOCOL_VERSION
+            })
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect();
+        for peer_id in peer_ids {
+            Self::send_message(
+                &self.connected_peers,
+                peer_id,
+                PeerMessage::NetworkAdvisory(advisory.clone()),
+            );
+        }
+        Ok(())
+    }
+
+    /// Stores the listening address of the peer, if it has proven to receive our challenge there.
+    fn handle_msg_address_proof(&mut self, peer_id: PeerId, nonce: u64) {
+        let connected_peer = match self.connected_peers.get_mut(&peer_id) {
+            Some(connected_peer) => connected_peer,
+            None => return,
+        };
+        if connected_peer.address_challenge != Some(nonce) {
+            debug!(target: "network", ?peer_id, "Received unexpected address proof");
+            return;
+        }
+        connected_peer.address_challenge = None;
+        let peer_info = connected_peer.full_peer_info.peer_info.clone();
+        metrics::PEER_ADDRESS_CHALLENGES.with_label_values(&["verified"]).inc();
+        debug!(target: "network", ?peer_info, "Verified listening address of the peer");
+        if let Err(err) = self.peer_store.add_signed_peer(&self.clock, peer_info) {
+            error!(target: "network", ?err, "Failed to save peer data");
+        }
+    }
+
     fn send_sync(
         &self,
         peer_type: PeerType,
@@ -669,7 +1467,15 @@ impl PeerManagerActor {
 
         // If the last edge we have with this peer represent a connection addition, create the edge
         // update that represents the connection removal.
-        self.connected_peers.remove(peer_id);
+        if let Some(peer) = self.connected_peers.remove(peer_id) {
+            for (kind, _) in peer.errors.by_kind() {
+                let _ =
+                    metrics::PEER_ERRORS_BY_PEER.remove_label_values(&[&peer_id.to_string(), kind]);
+            }
+            if peer.clock_offset.is_some() {
+                self.update_clock_skew();
+            }
+        }
 
         if let Some(edge) = self.routing_table_view.get_local_edge(peer_id) {
             if edge.edge_type() == EdgeState::Active {
@@ -715,6 +1521,14 @@ impl PeerManagerActor {
     /// This function should only be called after Peer instance is stopped.
     /// Note: Use `try_ban_peer` if there might be a Peer instance still connected.
     fn ban_peer(&mut self, peer_id: &PeerId, ban_reason: ReasonForBan) {
+        if self.penalize_minor_offense(peer_id, ban_reason) {
+            // The Peer instance is stopped already, so the peer is just disconnected.
+            self.remove_connected_peer(peer_id, None);
+            if let Err(err) = self.peer_store.peer_disconnected(&self.clock, peer_id) {
+                error!(target: "network", ?err, "Failed to save peer data");
+            };
+            return;
+        }
         warn!(target: "network", ?peer_id, ?ban_reason, "Banning peer");
         self.remove_connected_peer(peer_id, None);
         if let Err(err) = self.peer_store.peer_ban(&self.clock, peer_id, ban_reason) {
@@ -725,6 +1539,9 @@ impl PeerManagerActor {
     /// Ban peer. Stop peer instance if it is still connected,
     /// and then mark peer as banned in the peer store.
     pub(crate) fn try_ban_peer(&mut self, peer_id: &PeerId, ban_reason: ReasonForBan) {
+        if self.penalize_minor_offense(peer_id, ban_reason) {
+            return;
+        }
         if let Some(peer) = self.connected_peers.get(peer_id) {
             peer.addr.do_send(PeerManagerRequestWithContext {
                 msg: PeerManagerRequest::BanPeer(ban_reason),
@@ -738,25 +1555,90 @@ impl PeerManagerActor {
         }
     }
 
-    /// Connects peer with given TcpStream and optional information if it's outbound.
+    /// Penalizes the offense in the score of the peer instead of banning it, if the offense
+    /// is minor. Returns false if the peer has to be banned, either because the offense is
+    /// major or because its minor offenses add up.
+    fn penalize_minor_offense(&mut self, peer_id: &PeerId, ban_reason: ReasonForBan) -> bool {
+        if self.config.ban_rule(ban_reason).severity != BanSeverity::Minor {
+            return false;
+        }
+        if self.peer_scores.add_minor_offense(peer_id) {
+            warn!(target: "network", ?peer_id, ?ban_reason, "Minor offenses of the peer add up to a ban");
+            return false;
+        }
+        debug!(target: "network", ?peer_id, ?ban_reason, score = self.peer_scores.get(peer_id), "Penalizing peer for a minor offense");
+        true
+    }
+
+    /// Lifts the ban of the peer, on the operator's request.
+    /// Returns whether the peer was banned.
+    fn unban_peer(&mut self, peer_id: &PeerId) -> bool {
+        if !self.peer_store.is_banned(peer_id) {
+            return false;
+        }
+        info!(target: "network", ?peer_id, "Unbanning peer");
+        self.peer_scores.pardon(peer_id);
+        if let Err(err) = self.peer_store.peer_unban(peer_id) {
+            error!(target: "network", ?err, "Failed to unban a peer");
+        }
+        true
+    }
+
+    fn get_banned_peers_info(&self) -> Vec<BannedPeerInfo> {
+        let mut peers: Vec<_> = (self.peer_store.iter())
+            .filter_map(|(_, peer_state)| match peer_state.status {
+                KnownPeerStatus::Banned(reason, banned_at) => Some(BannedPeerInfo {
+                    peer_info: peer_state.peer_info.clone(),
+                    reason,
+                    banned_at,
+                    expires_at: banned_at + self.config.ban_rule(reason).duration,
+                }),
+                _ => None,
+            })
+            .collect();
+        peers.sort_by(|a, b| a.peer_info.id.cmp(&b.peer_info.id));
+        peers
+    }
+
+    /// Connects peer with given stream and optional information if it's outbound.
     /// This might fail if the other peers drop listener at its endpoint while establishing connection.
+    /// The time given to the peer to complete the handshake depends on the round-trip time
+    /// `rtt` of the connection.
     fn try_connect_peer(
         &self,
         recipient: Addr<Self>,
-        stream: TcpStream,
+        stream: Stream,
+        rtt: std::time::Duration,
         peer_type: PeerType,
         peer_info: Option<PeerInfo>,
         partial_edge_info: Option<PartialEdgeInfo>,
+        pending_handshake: Option<PendingHandshake>,
     ) {
         let my_peer_id = self.my_peer_id.clone();
         let account_id = self.config.validator.as_ref().map(|v| v.account_id());
         let server_addr = self.config.node_addr;
-        let handshake_timeout = self.config.handshake_timeout.try_into().unwrap();
+        let handshake_timeout = self.config.handshake_timeout_for_rtt(rtt);
+        metrics::PEER_HANDSHAKE_TIMEOUT
+            .with_label_values(&[peer_type.into()])
+            .observe(handshake_timeout.as_secs_f64());
+        let handshake_timeout = handshake_timeout.try_into().unwrap();
+        let keep_alive_interval = self.config.keep_alive_interval.try_into().unwrap();
+        let keep_alive_timeout = self.config.keep_alive_timeout.try_into().unwrap();
         let client_addr = self.client_addr.clone();
         let view_client_addr = self.view_client_addr.clone();
+        let encryption_key = self.config.encrypt_connections.then(|| self.config.node_key.clone());
+        let force_encoding = self.config.protobuf_only.then_some(Encoding::Proto);
+        let bandwidth_limiter = BandwidthLimiter::new(
+            self.config.peer_send_limit_bytes_per_sec,
+            self.total_send_limit.clone(),
+            self.clock.now(),
+        );
 
         let server_addr = match server_addr {
-            Some(server_addr) => server_addr,
+            Some(server_addr) => match self.mapped_port {
+                Some((port, _)) => SocketAddr::new(server_addr.ip(), port),
+                None => server_addr,
+            },
             None => match stream.local_addr() {
                 Ok(server_addr) => server_addr,
                 _ => {
@@ -781,6 +1663,9 @@ impl PeerManagerActor {
         let peer_counter = self.peer_counter.clone();
         peer_counter.fetch_add(1, Ordering::SeqCst);
         let clock = self.clock.clone();
+        let recorder = self.recorder.clone();
+        let chaos = self.chaos.clone();
+        let routed_message_dedup = self.routed_message_dedup.clone();
         PeerActor::start_in_arbiter(&arbiter.handle(), move |ctx| {
             let (read, write) = tokio::io::split(stream);
 
@@ -807,6 +1692,8 @@ impl PeerManagerActor {
                 peer_type,
                 FramedWrite::new(write, Codec::default(), Codec::default(), ctx),
                 handshake_timeout,
+                keep_alive_interval,
+                keep_alive_timeout,
                 recipient.clone().recipient(),
                 recipient.clone().recipient(),
                 client_addr,
@@ -814,8 +1701,14 @@ impl PeerManagerActor {
                 partial_edge_info,
                 txns_since_last_block,
                 peer_counter,
+                routed_message_dedup,
                 rate_limiter,
-                None,
+                encryption_key,
+                force_encoding,
+                bandwidth_limiter,
+                pending_handshake,
+                recorder,
+                chaos,
             )
         });
     }
@@ -837,10 +1730,75 @@ impl PeerManagerActor {
             && !self.config.outbound_disabled
     }
 
+    /// Decides whether a peer at `ip` keeps the connected peers diverse enough, see
+    /// `NetworkConfig::max_peers_per_subnet`.
+    fn check_peer_diversity(&self, ip: IpAddr) -> Result<(), peer_diversity::Rejection> {
+        let connected = self
+            .connected_peers
+            .values()
+            .filter_map(|p| p.full_peer_info.peer_info.addr.map(|addr| addr.ip()));
+        self.peer_diversity.check(ip, connected)
+    }
+
     fn is_inbound_allowed(&self) -> bool {
         self.connected_peers.len() + self.outgoing_peers.len() < self.config.max_num_peers as usize
     }
 
+    /// Number of inbound connections which haven't completed the handshake, over all the
+    /// listeners.
+    fn num_pending_handshakes(&self) -> usize {
+        self.handshake_limiter.num_pending()
+            + self.listener_limiters.iter().map(HandshakeLimiter::num_pending).sum::<usize>()
+    }
+
+    /// Inbound peers which can be disconnected to make room for an inbound peer tracking some
+    /// of our shards: the ones tracking none of our shards, except whitelisted and TIER1 peers.
+    fn evictable_inbound_peers(&self) -> Vec<PeerId> {
+        let mut peers: Vec<_> = self
+            .connected_peers
+            .iter()
+            .filter(|(id, p)| {
+                p.peer_type == PeerType::Inbound
+                    && !self.shard_admission.is_needed(&p.full_peer_info.chain_info.tracked_shards)
+                    && !self.is_peer_whitelisted(&p.full_peer_info.peer_info)
+                    && !self.tier1_peers.contains(id)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        peers.sort();
+        peers
+    }
+
+    /// Decides whether to register an inbound peer tracking `tracked_shards`, see
+    /// `NetworkConfig::inbound_tracked_shards_ratio`.
+    fn admit_inbound_peer(&self, tracked_shards: &[ShardId]) -> Admission {
+        let num_needed = (self.connected_peers.values())
+            .filter(|p| self.shard_admission.is_needed(&p.full_peer_info.chain_info.tracked_shards))
+            .count();
+        self.shard_admission.admit(
+            tracked_shards,
+            self.connected_peers.len() + self.outgoing_peers.len(),
+            num_needed,
+            self.config.max_num_peers as usize,
+            !self.evictable_inbound_peers().is_empty(),
+        )
+    }
+
+    /// Disconnects a random inbound peer tracking none of our shards, to make room for one
+    /// which tracks some of them.
+    fn evict_inbound_peer(&mut self) {
+        let evictable = self.evictable_inbound_peers();
+        if let Some(peer_id) = evictable.choose(&mut self.rng) {
+            debug!(target: "network", ?peer_id, "Disconnecting inbound peer tracking none of our shards");
+            if let Some(peer) = self.connected_peers.get(peer_id) {
+                peer.addr.do_send(PeerManagerRequestWithContext {
+                    msg: PeerManagerRequest::UnregisterPeer,
+                    context: Span::current().context(),
+                });
+            }
+        }
+    }
+
     /// is_peer_whitelisted checks whether a peer is a whitelisted node.
     /// whitelisted nodes are allowed to connect, even if the inbound connections limit has
     /// been reached. This predicate should be evaluated AFTER the Handshake.
@@ -863,8 +1821,22 @@ impl PeerManagerActor {
 
     /// Returns single random peer with close to the highest height
     fn highest_height_peers(&self) -> Vec<FullPeerInfo> {
+        // Peers claiming heights far above our header head, so that a peer can't make itself
+        // the highest height peer with a bogus height, are used only if there are no others.
+        let mut candidates: Vec<_> = (self.connected_peers.values())
+            .filter(|cp| match self.my_header_height {
+                Some(my_header_height) => chain_info::is_height_plausible(
+                    cp.full_peer_info.chain_info.height,
+                    my_header_height,
+                ),
+                None => true,
+            })
+            .collect();
+        if candidates.is_empty() {
+            candidates = self.connected_peers.values().collect();
+        }
         // This finds max height among peers, and returns one peer close to such height.
-        let max_height = match (self.connected_peers.values())
+        let max_height = match (candidates.iter())
             .map(|connected_peer| connected_peer.full_peer_info.chain_info.height)
             .max()
         {
@@ -872,8 +1844,8 @@ impl PeerManagerActor {
             None => return vec![],
         };
         // Find all peers whose height is within `highest_peer_horizon` from max height peer(s).
-        self.connected_peers
-            .values()
+        candidates
+            .into_iter()
             .filter(|cp| {
                 cp.full_peer_info.chain_info.height.saturating_add(self.config.highest_peer_horizon)
                     >= max_height
@@ -1008,11 +1980,23 @@ impl PeerManagerActor {
                                 // if let Some(connected_peer) = act.connected_peers.get(&peer_id1) {
                                 //     connected_peer.addr.do_send(PeerManagerRequest::BanPeer(ReasonForBan::Abusive));
                                 // }
+                            } else if res.behavior.errors.misbehavior() > errors::MAX_MISBEHAVIOR_PER_PERIOD {
+                                warn!(target: "network", ?peer_id1, errors = ?res.behavior.errors, "Banning peer for sending too many invalid messages");
+                                act.try_ban_peer(&peer_id1, ReasonForBan::Abusive);
                             } else if let Some(connected_peer) = act.connected_peers.get_mut(&peer_id1) {
+                                act.peer_scores.update(&peer_id1, &res.behavior);
+                                connected_peer.errors.add_all(&res.behavior.errors);
+                                for (kind, count) in connected_peer.errors.by_kind() {
+                                    metrics::PEER_ERRORS_BY_PEER
+                                        .with_label_values(&[&peer_id1.to_string(), kind])
+                                        .set(count as i64);
+                                }
                                 connected_peer.full_peer_info.chain_info = res.chain_info;
                                 connected_peer.sent_bytes_per_sec = res.sent_bytes_per_sec;
                                 connected_peer.received_bytes_per_sec = res.received_bytes_per_sec;
                                 connected_peer.encoding = res.encoding;
+                                connected_peer.sent_messages_per_min = res.message_counts.0;
+                                connected_peer.received_messages_per_min = res.message_counts.1;
                             }
                         }
                         Err(err) => {
@@ -1033,17 +2017,17 @@ impl PeerManagerActor {
     }
 
     /// Check if the number of connections (excluding whitelisted ones) exceeds ideal_connections_hi.
-    /// If so, constructs a safe set of peers and selects one random peer outside of that set
-    /// and sends signal to stop connection to it gracefully.
+    /// If so, constructs a safe set of peers and selects the peer with the lowest score outside
+    /// of that set and sends signal to stop connection to it gracefully.
     ///
     /// Safe set contruction process:
-    /// 1. Add all whitelisted peers to the safe set.
+    /// 1. Add all whitelisted and TIER1 peers to the safe set.
     /// 2. If the number of outbound connections is less or equal than minimum_outbound_connections,
     ///    add all outbound connections to the safe set.
     /// 3. Find all peers who sent us a message within the last peer_recent_time_window,
     ///    and add them one by one to the safe_set (starting from earliest connection time)
     ///    until safe set has safe_set_size elements.
-    fn maybe_stop_active_connection(&self) {
+    fn maybe_stop_active_connection(&mut self) {
         let filter_peers = |predicate: &dyn Fn(&ConnectedPeer) -> bool| {
             self.connected_peers
                 .iter()
@@ -1055,8 +2039,11 @@ impl PeerManagerActor {
         let mut safe_set = HashSet::new();
 
         // If there is not enough non-whitelisted peers, return without disconnecting anyone.
-        let whitelisted_peers =
-            filter_peers(&|p| self.is_peer_whitelisted(&p.full_peer_info.peer_info));
+        // TIER1 peers are kept like the whitelisted ones.
+        let whitelisted_peers = filter_peers(&|p| {
+            self.is_peer_whitelisted(&p.full_peer_info.peer_info)
+                || self.tier1_peers.contains(&p.full_peer_info.peer_info.id)
+        });
         if self.connected_peers.len() - whitelisted_peers.len()
             <= self.config.ideal_connections_hi as usize
         {
@@ -1102,9 +2089,15 @@ impl PeerManagerActor {
         }
 
         // Build valid candidate list to choose the peer to be removed. All peers outside the safe set.
-        let candidates = self.connected_peers.iter().filter(|(id, _)| !safe_set.contains(id));
-        if let Some((id, p)) = candidates.choose(&mut rand::thread_rng()) {
+        // Remove the one with the lowest score, breaking ties at random.
+        let mut candidates: Vec<_> =
+            self.connected_peers.iter().filter(|(id, _)| !safe_set.contains(id)).collect();
+        candidates.sort_by_key(|(id, _)| *id);
+        candidates.shuffle(&mut self.rng);
+        let to_remove = candidates.into_iter().min_by_key(|(id, _)| self.peer_scores.get(id));
+        if let Some((id, p)) = to_remove {
             debug!(target: "network", ?id,
+                score = self.peer_scores.get(id),
                 connected_peers_len = self.connected_peers.len(),
                 ideal_connections_hi = self.config.ideal_connections_hi,
                 "Stop active connection"
@@ -1137,9 +2130,9 @@ impl PeerManagerActor {
         let _span = tracing::trace_span!(target: "network", "monitor_peers_trigger").entered();
         let mut to_unban = vec![];
         for (peer_id, peer_state) in self.peer_store.iter() {
-            if let KnownPeerStatus::Banned(_, last_banned) = peer_state.status {
+            if let KnownPeerStatus::Banned(ban_reason, last_banned) = peer_state.status {
                 let interval = self.clock.now_utc() - last_banned;
-                if interval > self.config.ban_window {
+                if interval > self.config.ban_rule(ban_reason).duration {
                     info!(target: "network", unbanned = ?peer_id, after = ?interval, "Monitor peers:");
                     to_unban.push(peer_id.clone());
                 }
@@ -1153,12 +2146,25 @@ impl PeerManagerActor {
         }
 
         if self.is_outbound_bootstrap_needed() {
-            if let Some(peer_info) = self.peer_store.unconnected_peer(|peer_state| {
-                // Ignore connecting to ourself
-                self.my_peer_id == peer_state.peer_info.id
-                    || self.config.node_addr == peer_state.peer_info.addr
-                    // Or to peers we are currently trying to connect to
-                    || self.outgoing_peers.contains(&peer_state.peer_info.id)
+            let candidates = self.peer_store.unconnected_peers(
+                &mut self.rng,
+                |peer_state| {
+                    // Ignore connecting to ourself
+                    self.my_peer_id == peer_state.peer_info.id
+                        || self.config.node_addr == peer_state.peer_info.addr
+                        // Or to peers we are currently trying to connect to
+                        || self.outgoing_peers.contains(&peer_state.peer_info.id)
+                        // Or to peers in a subnet or an AS we have enough peers in
+                        || peer_state.peer_info.addr.map_or(false, |addr| {
+                            self.check_peer_diversity(addr.ip()).is_err()
+                        })
+                },
+                OUTBOUND_CANDIDATES,
+            );
+            // Candidates are sampled at random, out of them pick the one with the highest
+            // score, using the hints received from other peers to break ties.
+            if let Some(peer_info) = candidates.into_iter().max_by_key(|peer_info| {
+                self.peer_scores.dial_priority(&peer_info.id, self.config.archive)
             }) {
                 // Start monitor_peers_attempts from start after we discover the first healthy peer
                 if !self.started_connect_attempts {
@@ -1289,7 +2295,15 @@ impl PeerManagerActor {
             }
         }
 
-        match self.routing_table_view.find_route(&self.clock, &msg.msg.target) {
+        // Messages to a directly connected peer, e.g. approvals and chunk parts sent to TIER1
+        // peers, skip the routing table: the shortest route may go through other peers.
+        let route = match &msg.msg.target {
+            PeerIdOrHash::PeerId(target) if self.connected_peers.contains_key(target) => {
+                Ok(target.clone())
+            }
+            target => self.routing_table_view.find_route(&self.clock, target),
+        };
+        match route {
             Ok(peer_id) => {
                 // Remember if we expect a response for this message.
                 if msg.msg.author == self.my_peer_id && msg.expect_response() {
@@ -1299,6 +2313,7 @@ impl PeerManagerActor {
                         msg.hash(),
                         self.my_peer_id.clone(),
                     );
+                    self.pending_requests.insert(&msg.msg, peer_id.clone(), self.clock.now());
                 }
 
                 Self::send_message(&self.connected_peers, peer_id, PeerMessage::Routed(msg))
@@ -1333,6 +2348,10 @@ impl PeerManagerActor {
         let target = match self.routing_table_view.account_owner(account_id) {
             Ok(peer_id) => peer_id,
             Err(find_route_error) => {
+                let msg = match self.delay_message(account_id, msg) {
+                    Some(msg) => msg,
+                    None => return false,
+                };
                 // TODO(MarX, #1369): Message is dropped here. Define policy for this case.
                 metrics::MessageDropped::UnknownAccount.inc(&msg);
                 debug!(target: "network",
@@ -1346,10 +2365,73 @@ impl PeerManagerActor {
             }
         };
 
+        // The owner of the account may be known, but unreachable at the moment.
+        let msg = if self.is_reachable(&target) {
+            msg
+        } else {
+            match self.delay_message(account_id, msg) {
+                Some(msg) => msg,
+                None => return false,
+            }
+        };
+
         let msg = RawRoutedMessage { target: AccountOrPeerIdOrHash::PeerId(target), body: msg };
         self.send_message_to_peer(msg)
     }
 
+    /// Whether the peer is this node, is connected or there is a route to it.
+    fn is_reachable(&self, peer_id: &PeerId) -> bool {
+        peer_id == &self.my_peer_id
+            || self.connected_peers.contains_key(peer_id)
+            || self.routing_table_view.view_route(peer_id).map_or(false, |hops| !hops.is_empty())
+    }
+
+    /// Queues an approval or a chunk part to an account we have no route to, to deliver it
+    /// once a route appears, see `DelayedDelivery`. Returns the message back if it isn't queued.
+    fn delay_message(
+        &mut self,
+        account_id: &AccountId,
+        msg: RoutedMessageBody,
+    ) -> Option<RoutedMessageBody> {
+        let delayed_delivery = match &mut self.delayed_delivery {
+            Some(delayed_delivery) if DelayedDelivery::is_delayable(&msg) => delayed_delivery,
+            _ => return Some(msg),
+        };
+        debug!(target: "network", to = ?account_id, ?msg, "Delay message to unreachable account");
+        metrics::DELAYED_DELIVERY.with_label_values(&["queued"]).inc();
+        delayed_delivery.push(account_id.clone(), msg, self.clock.now());
+        None
+    }
+
+    /// Delivers the queued messages to the accounts which have become reachable, and drops
+    /// the messages queued for longer than `NetworkConfig::delayed_delivery_ttl`.
+    fn retry_delayed_messages(&mut self) {
+        let (messages, dropped) = match &mut self.delayed_delivery {
+            Some(delayed_delivery) if !delayed_delivery.is_empty() => {
+                delayed_delivery.take(self.clock.now())
+            }
+            _ => return,
+        };
+        metrics::DELAYED_DELIVERY.with_label_values(&["expired"]).inc_by(dropped.expired as u64);
+        metrics::DELAYED_DELIVERY.with_label_values(&["evicted"]).inc_by(dropped.evicted as u64);
+        for msg in messages {
+            let reachable = match self.routing_table_view.account_owner(&msg.account_id) {
+                Ok(peer_id) => self.is_reachable(&peer_id),
+                Err(_) => false,
+            };
+            if !reachable {
+                if let Some(delayed_delivery) = &mut self.delayed_delivery {
+                    delayed_delivery.requeue(msg);
+                }
+                continue;
+            }
+            debug!(target: "network", to = ?msg.account_id, "Deliver delayed message");
+            if self.send_message_to_account(&msg.account_id, msg.body) {
+                metrics::DELAYED_DELIVERY.with_label_values(&["delivered"]).inc();
+            }
+        }
+    }
+
     fn sign_routed_message(
         &self,
         msg: RawRoutedMessage,
@@ -1425,11 +2507,43 @@ impl PeerManagerActor {
                 })
                 .collect(),
             peer_counter: self.peer_counter.load(Ordering::SeqCst),
+            peer_scores: (self.connected_peers.keys())
+                .map(|peer_id| (peer_id.clone(), self.peer_scores.get(peer_id)))
+                .collect(),
+            protocol_deprecation: self.protocol_deprecation,
+            advisories: self.advisories.latest(),
         }
     }
 
+    /// Details of the connections with the connected peers, sorted by peer id.
+    fn get_connected_peers_info(&self) -> Vec<ConnectedPeerInfo> {
+        let now = self.clock.now();
+        let now_utc = self.clock.now_utc();
+        let mut peers: Vec<_> = (self.connected_peers.values())
+            .map(|cp| ConnectedPeerInfo {
+                full_peer_info: cp.full_peer_info.clone(),
+                peer_type: cp.peer_type,
+                encoding: cp.encoding,
+                protocol_version: cp.protocol_version,
+                received_bytes_per_sec: cp.received_bytes_per_sec,
+                sent_bytes_per_sec: cp.sent_bytes_per_sec,
+                received_messages_per_min: cp.received_messages_per_min,
+                sent_messages_per_min: cp.sent_messages_per_min,
+                connection_age: now - cp.connection_established_time,
+                last_time_received_message: now_utc - (now - cp.last_time_received_message),
+            })
+            .collect();
+        peers.sort_by(|a, b| a.full_peer_info.peer_info.id.cmp(&b.full_peer_info.peer_info.id));
+        peers
+    }
+
     fn push_network_info_trigger(&self, ctx: &mut Context<Self>, interval: time::Duration) {
         let network_info = self.get_network_info();
+        for (account_id, latency) in self.routing_table_view.account_route_latencies() {
+            metrics::ACCOUNT_ROUTE_LATENCY
+                .with_label_values(&[account_id.as_ref()])
+                .set(latency.as_seconds_f64());
+        }
 
         let _ = self.client_addr.do_send(NetworkClientMessages::NetworkInfo(network_info));
 
@@ -1596,8 +2710,8 @@ impl PeerManagerActor {
                             }
                         }
 
-                        if let Some(matching_peer) = matching_peers.iter().choose(&mut thread_rng())
-                        {
+                        matching_peers.sort();
+                        if let Some(matching_peer) = matching_peers.choose(&mut self.rng) {
                             if self.send_message_to_peer(RawRoutedMessage {
                                 target: AccountOrPeerIdOrHash::PeerId(matching_peer.clone()),
                                 body: RoutedMessageBody::PartialEncodedChunkRequest(
@@ -1705,23 +2819,199 @@ impl PeerManagerActor {
         }
     }
 
-    #[perf]
-    fn handle_msg_inbound_tcp_connect(&self, msg: InboundTcpConnect, ctx: &mut Context<Self>) {
-        let _d = delay_detector::DelayDetector::new(|| "inbound tcp connect".into());
-        if self.is_inbound_allowed()
-            || msg
-                .stream
-                .peer_addr()
-                .map(|addr| self.is_ip_whitelisted(&addr.ip()))
-                .unwrap_or(false)
+    /// Checks whether an inbound connection from `addr`, accepted by `listener`, may proceed to
+    /// the handshake, before anything is read from it. Returns `Err` if the connection is to
+    /// be dropped, and otherwise the slot it takes among the pending handshakes, unless the
+    /// peer is whitelisted.
+    fn admit_inbound_connection(
+        &mut self,
+        addr: Option<SocketAddr>,
+        listener: Option<usize>,
+    ) -> Result<Option<PendingHandshake>, ()> {
+        if self.peer_counter.load(Ordering::SeqCst)
+            >= self.config.max_num_peers as usize + LIMIT_PENDING_PEERS
         {
-            self.try_connect_peer(ctx.address(), msg.stream, PeerType::Inbound, None, None);
+            return Err(());
+        }
+        // Drop blacklisted addresses right away. Peer ids and IP:port entries
+        // (the port being the listening one) are checked in the handshake.
+        if let Some(addr) = addr {
+            if self.peer_store.is_blacklisted(&addr) {
+                debug!(target: "network", ?addr, "Inbound connection dropped (blacklisted address).");
+                return Err(());
+            }
+        }
+        let is_whitelisted = addr.map_or(false, |addr| self.is_ip_whitelisted(&addr.ip()));
+        if let Some(index) = listener {
+            let listener = &self.config.listeners[index];
+            let accepted = match addr {
+                Some(addr) => listener.accepts(addr, is_whitelisted),
+                None => !listener.whitelist_only && listener.allowlist.is_empty(),
+            };
+            if !accepted {
+                let rejection = if listener.allowlist.is_empty() {
+                    Rejection::NotWhitelisted
+                } else {
+                    Rejection::NotAllowed
+                };
+                metrics::PEER_INBOUND_CONNECTIONS_REJECTED
+                    .with_label_values(&[rejection.as_ref()])
+                    .inc();
+                debug!(target: "network", ?addr, ?rejection, "Inbound connection dropped (not allowed by the listener).");
+                return Err(());
+            }
+        }
+        if let Some(addr) = addr {
+            if !is_whitelisted {
+                if let Err(rejection) = self.check_peer_diversity(addr.ip()) {
+                    metrics::PEER_DIVERSITY_REJECTED
+                        .with_label_values(&["inbound", rejection.as_ref()])
+                        .inc();
+                    debug!(target: "network", ?addr, ?rejection, "Inbound connection dropped (peer diversity).");
+                    return Err(());
+                }
+            }
+        }
+        // Limit the connections per IP and in total before spawning a PeerActor, so that hosts
+        // can't hold all the slots in the middle of the handshake. Whitelisted nodes are exempt.
+        let pending_handshake = match addr {
+            Some(addr) if !is_whitelisted => {
+                let admitted = if self.num_pending_handshakes()
+                    >= self.config.max_pending_handshakes as usize
+                {
+                    Err(Rejection::TooManyPendingHandshakesTotal)
+                } else {
+                    let limiter = match listener {
+                        Some(index) => &mut self.listener_limiters[index],
+                        None => &mut self.handshake_limiter,
+                    };
+                    limiter.admit(addr.ip(), self.clock.now())
+                };
+                match admitted {
+                    Ok(pending_handshake) => Some(pending_handshake),
+                    Err(rejection) => {
+                        metrics::PEER_INBOUND_CONNECTIONS_REJECTED
+                            .with_label_values(&[rejection.as_ref()])
+                            .inc();
+                        debug!(target: "network", ?addr, ?rejection, "Inbound connection dropped (handshake limit).");
+                        return Err(());
+                    }
+                }
+            }
+            _ => None,
+        };
+        // The peer may replace an inbound peer tracking none of our shards, which is only known
+        // after the handshake.
+        let may_evict =
+            self.shard_admission.is_enabled() && !self.evictable_inbound_peers().is_empty();
+        if self.is_inbound_allowed() || is_whitelisted || may_evict {
+            Ok(pending_handshake)
         } else {
             // TODO(1896): Gracefully drop inbound connection for other peer.
             debug!(target: "network", "Inbound connection dropped (network at max capacity).");
+            Err(())
         }
     }
 
+    #[perf]
+    fn handle_msg_inbound_connect(
+        &mut self,
+        stream: Stream,
+        listener: Option<usize>,
+        ctx: &mut Context<Self>,
+    ) {
+        let _d = delay_detector::DelayDetector::new(|| "inbound connect".into());
+        if let Ok(pending_handshake) =
+            self.admit_inbound_connection(stream.peer_addr().ok(), listener)
+        {
+            self.wait_for_first_frame(stream, pending_handshake, ctx);
+        }
+    }
+
+    /// Completes an inbound QUIC connection once it passes the limits of the TCP ones, so that
+    /// the QUIC handshake, TLS included, is done only for the admitted connections, and takes
+    /// their pending handshake slot.
+    #[perf]
+    fn handle_msg_inbound_quic_connect(
+        &mut self,
+        connecting: quinn::Connecting,
+        ctx: &mut Context<Self>,
+    ) {
+        let _d = delay_detector::DelayDetector::new(|| "inbound quic connect".into());
+        let endpoint = match &self.quic_endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => return,
+        };
+        let addr = connecting.remote_address();
+        let pending_handshake = match self.admit_inbound_connection(Some(addr), None) {
+            Ok(pending_handshake) => pending_handshake,
+            Err(()) => return,
+        };
+        let handshake_timeout: std::time::Duration =
+            self.config.handshake_timeout.try_into().unwrap();
+        tokio::time::timeout(handshake_timeout, async move { endpoint.accept(connecting).await })
+            .into_actor(self)
+            .map(move |res, act, ctx| match res {
+                Ok(Ok(stream)) => {
+                    debug!(target: "network", from = ?addr, "got new QUIC connection");
+                    act.wait_for_first_frame(Box::new(stream), pending_handshake, ctx);
+                }
+                Ok(Err(err)) => {
+                    debug!(target: "network", ?addr, ?err, "failed to accept QUIC connection")
+                }
+                Err(_) => debug!(target: "network", ?addr, "accepting QUIC connection timed out"),
+            })
+            .spawn(ctx);
+    }
+
+    /// Spawns a PeerActor for an admitted inbound connection, once its first frame arrives.
+    fn wait_for_first_frame(
+        &self,
+        stream: Stream,
+        pending_handshake: Option<PendingHandshake>,
+        ctx: &mut Context<Self>,
+    ) {
+        // Spawn the PeerActor only once the first frame arrives, so that connections which
+        // send nothing hold just their pending handshake slot until the timeout.
+        let addr = stream.peer_addr().ok();
+        let accepted_at = self.clock.now();
+        let first_frame = transport::read_first_frame(stream);
+        tokio::time::timeout(self.config.first_frame_timeout, first_frame)
+            .into_actor(self)
+            .map(move |res, act, ctx| {
+                let rejection = match res {
+                    Ok(Ok(stream)) => {
+                        // The round-trip time of TCP connections isn't known on the
+                        // accepting side, but the peer sends its handshake right after
+                        // connecting, so the time it took to arrive stands in for it.
+                        let first_frame_delay =
+                            (act.clock.now() - accepted_at).try_into().unwrap_or_default();
+                        let rtt = connection_rtt(&stream, first_frame_delay, "first_frame");
+                        act.try_connect_peer(
+                            ctx.address(),
+                            stream,
+                            rtt,
+                            PeerType::Inbound,
+                            None,
+                            None,
+                            pending_handshake,
+                        );
+                        return;
+                    }
+                    Ok(Err(err)) => {
+                        debug!(target: "network", ?addr, ?err, "Failed to read the first frame");
+                        Rejection::InvalidFirstFrame
+                    }
+                    Err(_) => Rejection::FirstFrameTimeout,
+                };
+                metrics::PEER_INBOUND_CONNECTIONS_REJECTED
+                    .with_label_values(&[rejection.as_ref()])
+                    .inc();
+                debug!(target: "network", ?addr, ?rejection, "Inbound connection dropped (first frame).");
+            })
+            .spawn(ctx);
+    }
+
     #[perf]
     fn handle_msg_outbound_tcp_connect(&self, msg: OutboundTcpConnect, ctx: &mut Context<Self>) {
         let _d = delay_detector::DelayDetector::new(|| "outbound tcp connect".into());
@@ -1735,29 +3025,29 @@ impl PeerManagerActor {
             // Why exactly a second? It was hard-coded in a library we used
             // before, so we keep it to preserve behavior. Removing the timeout
             // completely was observed to break stuff for real on the testnet.
-            tokio::time::timeout(std::time::Duration::from_secs(1), TcpStream::connect(addr))
+            //
+            // The same timeout applies to a QUIC connection attempt, if enabled.
+            let quic = self.quic_endpoint.clone();
+            let socket_options = self.config.socket_options;
+            transport::connect(quic, addr, std::time::Duration::from_secs(1), socket_options)
                 .into_actor(self)
                 .then(move |res, act, ctx| match res {
-                    Ok(res) => match res {
-                        Ok(stream) => {
-                            debug!(target: "network", peer_info = ?msg.peer_info, "Connecting");
-                            let edge_info = act.propose_edge(&msg.peer_info.id, None);
-
-                            act.try_connect_peer(
-                                ctx.address(),
-                                stream,
-                                PeerType::Outbound,
-                                Some(msg.peer_info),
-                                Some(edge_info),
-                            );
-                            actix::fut::ready(())
-                        }
-                        Err(err) => {
-                            info!(target: "network", ?addr, ?err, "Error connecting to");
-                            act.outgoing_peers.remove(&msg.peer_info.id);
-                            actix::fut::ready(())
-                        }
-                    },
+                    Ok((stream, connect_rtt)) => {
+                        debug!(target: "network", peer_info = ?msg.peer_info, "Connecting");
+                        let edge_info = act.propose_edge(&msg.peer_info.id, None);
+
+                        let rtt = connection_rtt(&stream, connect_rtt, "tcp_connect");
+                        act.try_connect_peer(
+                            ctx.address(),
+                            stream,
+                            rtt,
+                            PeerType::Outbound,
+                            Some(msg.peer_info),
+                            Some(edge_info),
+                            None,
+                        );
+                        actix::fut::ready(())
+                    }
                     Err(err) => {
                         info!(target: "network", ?addr, ?err, "Error connecting to");
                         act.outgoing_peers.remove(&msg.peer_info.id);
@@ -1779,7 +3069,7 @@ impl PeerManagerActor {
         let _d = delay_detector::DelayDetector::new(|| "consolidate".into());
 
         // Check if this is a blacklisted peer.
-        if (msg.peer_info.addr.as_ref()).map_or(true, |addr| self.peer_store.is_blacklisted(addr)) {
+        if msg.peer_info.addr.is_none() || self.peer_store.is_peer_blacklisted(&msg.peer_info) {
             debug!(target: "network", peer_info = ?msg.peer_info, "Dropping connection from blacklisted peer or unknown address");
             return RegisterPeerResponse::Reject;
         }
@@ -1795,6 +3085,16 @@ impl PeerManagerActor {
             return RegisterPeerResponse::Reject;
         }
 
+        if self.config.encrypt_connections {
+            if msg.peer_supports_encryption {
+                self.encrypting_peers.put(msg.peer_info.id.clone(), ());
+            } else if self.encrypting_peers.contains(&msg.peer_info.id) {
+                metrics::PEER_ENCRYPTION_HANDSHAKES_TOTAL.with_label_values(&["downgrade"]).inc();
+                warn!(target: "network", id = ?msg.peer_info.id, "Dropping handshake (Encryption downgrade).");
+                return RegisterPeerResponse::Reject;
+            }
+        }
+
         // This is incoming connection but we have this peer already in outgoing.
         // This only happens when both of us connect at the same time, break tie using higher peer id.
         if msg.peer_type == PeerType::Inbound && self.outgoing_peers.contains(&msg.peer_info.id) {
@@ -1805,17 +3105,45 @@ impl PeerManagerActor {
             }
         }
 
+        // Whether to disconnect an inbound peer tracking none of our shards to make room for
+        // this one, once it is accepted.
+        let mut evict = false;
         if msg.peer_type == PeerType::Inbound
-            && !self.is_inbound_allowed()
             && !self.is_peer_whitelisted(&msg.peer_info)
+            && !self.tier1_peers.contains(&msg.peer_info.id)
         {
-            // TODO(1896): Gracefully drop inbound connection for other peer.
-            debug!(target: "network",
-                connected_peers = self.connected_peers.len(), outgoing_peers = self.outgoing_peers.len(),
-                max_num_peers = self.config.max_num_peers,
-                "Inbound connection dropped (network at max capacity)."
-            );
-            return RegisterPeerResponse::Reject;
+            let admission = self.admit_inbound_peer(&msg.chain_info.tracked_shards);
+            evict = admission == Admission::Evict;
+            if admission == Admission::Reject {
+                metrics::PEER_SHARD_ADMISSION.with_label_values(&[admission.as_ref()]).inc();
+                // TODO(1896): Gracefully drop inbound connection for other peer.
+                debug!(target: "network",
+                    connected_peers = self.connected_peers.len(), outgoing_peers = self.outgoing_peers.len(),
+                    max_num_peers = self.config.max_num_peers,
+                    tracked_shards = ?msg.chain_info.tracked_shards,
+                    "Inbound connection dropped (network at max capacity)."
+                );
+                return RegisterPeerResponse::Reject;
+            }
+        }
+
+        // Checked again after the handshake, since other peers could have connected in the
+        // meantime and outbound peers are only checked when picked.
+        if !self.is_peer_whitelisted(&msg.peer_info)
+            && !self.tier1_peers.contains(&msg.peer_info.id)
+        {
+            let ip = msg.peer_info.addr.unwrap().ip();
+            if let Err(rejection) = self.check_peer_diversity(ip) {
+                let direction = match msg.peer_type {
+                    PeerType::Inbound => "inbound",
+                    PeerType::Outbound => "outbound",
+                };
+                metrics::PEER_DIVERSITY_REJECTED
+                    .with_label_values(&[direction, rejection.as_ref()])
+                    .inc();
+                debug!(target: "network", peer_info = ?msg.peer_info, ?rejection, "Dropping handshake (peer diversity).");
+                return RegisterPeerResponse::Reject;
+            }
         }
 
         if msg.other_edge_info.nonce == 0 {
@@ -1846,6 +3174,11 @@ impl PeerManagerActor {
 
         let edge_info_response = if require_response { Some(edge_info.clone()) } else { None };
 
+        if evict {
+            metrics::PEER_SHARD_ADMISSION.with_label_values(&[Admission::Evict.as_ref()]).inc();
+            self.evict_inbound_peer();
+        }
+
         // TODO: double check that address is connectable and add account id.
         self.register_peer(
             FullPeerInfo {
@@ -1855,8 +3188,10 @@ impl PeerManagerActor {
             },
             edge_info,
             msg.peer_type,
+            msg.peer_protocol_version,
             msg.actor,
             msg.throttle_controller,
+            msg.clock_offset,
             ctx,
         );
 
@@ -1875,16 +3210,51 @@ impl PeerManagerActor {
         self.ban_peer(&msg.peer_id, msg.ban_reason);
     }
 
+    fn handle_msg_set_access_rule(&mut self, entry: BlacklistEntry, rule: AccessRule) {
+        info!(target: "network", %entry, ?rule, "Setting access rule");
+        if let Err(err) = self.peer_store.set_access_rule(entry, rule) {
+            error!(target: "network", ?err, "Failed to save access rule");
+            return;
+        }
+        for (id, p) in &self.connected_peers {
+            if self.peer_store.is_peer_blacklisted(&p.full_peer_info.peer_info) {
+                debug!(target: "network", ?id, "Stop active connection (blacklisted)");
+                p.addr.do_send(PeerManagerRequestWithContext {
+                    msg: PeerManagerRequest::UnregisterPeer,
+                    context: Span::current().context(),
+                });
+            }
+        }
+    }
+
     #[perf]
-    fn handle_msg_peers_request(&self, _msg: PeersRequest) -> PeerRequestResult {
+    fn handle_msg_peers_request(&mut self, _msg: PeersRequest) -> PeerRequestResult {
         let _d = delay_detector::DelayDetector::new(|| "peers request".into());
-        PeerRequestResult {
-            peers: self.peer_store.healthy_peers(self.config.max_send_peers as usize),
-        }
+        let peers =
+            self.peer_store.healthy_peers(&mut self.rng, self.config.max_send_peers as usize);
+        // Share what we know about the peers we are connected to.
+        let hints = (peers.iter())
+            .filter_map(|peer_info| {
+                let connected_peer = self.connected_peers.get(&peer_info.id)?;
+                Some(PeerHint {
+                    peer_id: peer_info.id.clone(),
+                    latency: self.peer_scores.latency(&peer_info.id),
+                    archival: connected_peer.full_peer_info.chain_info.archival,
+                })
+            })
+            .collect();
+        PeerRequestResult { peers, hints }
     }
 
     fn handle_msg_peers_response(&mut self, msg: PeersResponse) {
         let _d = delay_detector::DelayDetector::new(|| "peers response".into());
+        // Ignore the hints about peers which are not in the response.
+        let peer_ids: HashSet<&PeerId> = msg.peers.iter().map(|peer_info| &peer_info.id).collect();
+        for hint in msg.hints {
+            if hint.peer_id != self.my_peer_id && peer_ids.contains(&hint.peer_id) {
+                self.peer_scores.add_hint(hint);
+            }
+        }
         if let Err(err) = self.peer_store.add_indirect_peers(
             &self.clock,
             msg.peers.into_iter().filter(|peer_info| peer_info.id != self.my_peer_id),
@@ -1927,6 +3297,27 @@ impl PeerManagerActor {
                 self.send_ping(nonce, target);
                 PeerManagerMessageResponse::PingTo
             }
+            PeerManagerMessageRequest::SetAccessRule(entry, rule) => {
+                self.handle_msg_set_access_rule(entry, rule);
+                PeerManagerMessageResponse::AccessList(self.peer_store.access_list().clone())
+            }
+            PeerManagerMessageRequest::GetAccessList => {
+                PeerManagerMessageResponse::AccessList(self.peer_store.access_list().clone())
+            }
+            PeerManagerMessageRequest::FetchConnectedPeers => {
+                PeerManagerMessageResponse::FetchConnectedPeers(self.get_connected_peers_info())
+            }
+            PeerManagerMessageRequest::FetchBannedPeers => {
+                PeerManagerMessageResponse::FetchBannedPeers(self.get_banned_peers_info())
+            }
+            PeerManagerMessageRequest::UnbanPeer(peer_id) => {
+                PeerManagerMessageResponse::UnbanPeer(self.unban_peer(&peer_id))
+            }
+            PeerManagerMessageRequest::BroadcastNetworkAdvisory(advisory) => {
+                PeerManagerMessageResponse::BroadcastNetworkAdvisory(
+                    self.handle_msg_network_advisory(None, advisory),
+                )
+            }
         }
     }
 
@@ -1973,12 +3364,32 @@ impl PeerManagerActor {
                 }
                 PeerToManagerMsgResp::Empty
             }
+            PeerToManagerMsg::AddressChallenge(challenge) => {
+                self.handle_msg_address_challenge(challenge);
+                PeerToManagerMsgResp::Empty
+            }
+            PeerToManagerMsg::AddressProof(peer_id, nonce) => {
+                self.handle_msg_address_proof(peer_id, nonce);
+                PeerToManagerMsgResp::Empty
+            }
+            PeerToManagerMsg::AddressChallengeRequest(peer_id) => {
+                self.handle_msg_address_challenge_request(ctx, peer_id);
+                PeerToManagerMsgResp::Empty
+            }
+            PeerToManagerMsg::ProtocolDeprecation(peer_id, deprecation) => {
+                self.handle_msg_protocol_deprecation(peer_id, deprecation);
+                PeerToManagerMsgResp::Empty
+            }
+            PeerToManagerMsg::NetworkAdvisory(peer_id, advisory) => {
+                let _ = self.handle_msg_network_advisory(Some(peer_id), advisory);
+                PeerToManagerMsgResp::Empty
+            }
             PeerToManagerMsg::InboundTcpConnect(msg) => {
-                if self.peer_counter.load(Ordering::SeqCst)
-                    < self.config.max_num_peers as usize + LIMIT_PENDING_PEERS
-                {
-                    self.handle_msg_inbound_tcp_connect(msg, ctx);
-                }
+                self.handle_msg_inbound_connect(Box::new(msg.stream), msg.listener, ctx);
+                PeerToManagerMsgResp::Empty
+            }
+            PeerToManagerMsg::InboundQuicConnect(connecting) => {
+                self.handle_msg_inbound_quic_connect(connecting, ctx);
                 PeerToManagerMsgResp::Empty
             }
             PeerToManagerMsg::Unregister(msg) => {
@@ -2103,7 +3514,22 @@ impl PeerManagerActor {
         }
 
         if Self::message_for_me(&mut self.routing_table_view, &self.my_peer_id, &msg.msg.target) {
+            // Signature of the author has been already verified by PeerActor, check that the
+            // author is the peer we have sent the request to.
+            if let Err(err) = self.pending_requests.check_response(&msg.msg) {
+                metrics::ROUTED_RESPONSE_REJECTED
+                    .with_label_values(&[msg.body_variant(), err.as_ref()])
+                    .inc();
+                warn!(target: "network", author = ?msg.author, ?from, ?err, body = ?msg.body, "Dropping invalid routed response");
+                // We can only penalize the author if it is our direct peer. Otherwise we just
+                // drop the message, since the author is not connected to us.
+                if self.connected_peers.contains_key(&msg.author) {
+                    self.try_ban_peer(&msg.author, ReasonForBan::InvalidRoutedResponse);
+                }
+                return false;
+            }
             self.record_routed_msg_latency(&msg);
+            self.record_routed_response(&msg);
             // Handle Ping and Pong message if they are for us without sending to client.
             // i.e. Return false in case of Ping and Pong
             match &msg.msg.body {
@@ -2140,6 +3566,43 @@ impl PeerManagerActor {
                 .observe(duration.as_seconds_f64());
         }
     }
+
+    // The response to a routed request sent by us has arrived. Count it as a success of the
+    // route it was sent through, and if it is a response to a Ping or a chunk request, update
+    // the round trip latency to the peer which has sent it.
+    fn record_routed_response(&mut self, msg: &RoutedMessageV2) {
+        if let Some(answered) = self.pending_requests.answer(&msg.msg, self.clock.now()) {
+            self.routing_table_view.record_route_success(&answered.first_hop);
+            if let Some(latency) = answered.latency {
+                metrics::ROUTED_REQUEST_ROUND_TRIP_LATENCY
+                    .with_label_values(&[msg.body_variant()])
+                    .observe(latency.as_seconds_f64());
+                self.routing_table_view.record_route_latency(&answered.target, latency);
+                if let (RoutedMessageBody::Pong(_), Some(created_at)) = (&msg.body, msg.created_at)
+                {
+                    let offset = clock_skew::pong_offset(created_at, self.clock.now_utc(), latency);
+                    self.record_clock_offset(&answered.target, "pong", offset);
+                }
+            }
+        }
+    }
+
+    /// Records the offset of the clock of a connected peer relative to ours, estimated from
+    /// the given source, and updates the skew of the local clock.
+    fn record_clock_offset(&mut self, peer_id: &PeerId, source: &str, offset: time::Duration) {
+        let peer = match self.connected_peers.get_mut(peer_id) {
+            Some(peer) => peer,
+            None => return,
+        };
+        peer.clock_offset = Some(offset);
+        metrics::PEER_CLOCK_OFFSET.with_label_values(&[source]).observe(offset.as_seconds_f64());
+        self.update_clock_skew();
+    }
+
+    fn update_clock_skew(&mut self) {
+        let offsets = self.connected_peers.values().filter_map(|peer| peer.clock_offset).collect();
+        self.clock_skew.update(offsets);
+    }
 }
 
 impl Handler<ActixMessageWrapper<PeerToManagerMsg>> for PeerManagerActor {
@@ -2,11 +2,12 @@ use crate::store;
 use anyhow::bail;
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    Blacklist, KnownPeerState, KnownPeerStatus, NetworkConfig, PeerInfo, ReasonForBan,
+    AccessList, AccessRule, BlacklistEntry, KnownPeerState, KnownPeerStatus, NetworkConfig,
+    PeerInfo, ReasonForBan,
 };
 use near_primitives::network::PeerId;
-use rand::seq::IteratorRandom;
-use rand::thread_rng;
+use rand::seq::SliceRandom;
+use rand::Rng;
 use std::collections::hash_map::{Entry, Iter};
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -51,7 +52,8 @@ pub struct PeerStore {
     // It can happens that some peers don't have known address, so
     // they will not be present in this list, otherwise they will be present.
     addr_peers: HashMap<SocketAddr, VerifiedPeer>,
-    blacklist: Blacklist,
+    /// Blacklist from the config, with the operator's runtime updates applied.
+    access_list: AccessList,
 }
 
 impl PeerStore {
@@ -59,8 +61,12 @@ impl PeerStore {
         clock: &time::Clock,
         store: store::Store,
         boot_nodes: &[PeerInfo],
-        blacklist: Blacklist,
+        mut access_list: AccessList,
     ) -> anyhow::Result<Self> {
+        for (entry, rule) in store.list_access_rules()? {
+            access_list.set_rule(entry, rule);
+        }
+
         // A mapping from `PeerId` to `KnownPeerState`.
         let mut peerid_2_state = HashMap::default();
         // Stores mapping from `SocketAddr` to `VerifiedPeer`, which contains `PeerId`.
@@ -107,10 +113,8 @@ impl PeerStore {
                 status,
             };
 
-            let is_blacklisted =
-                peer_state.peer_info.addr.map_or(false, |addr| blacklist.contains(addr));
-            if is_blacklisted {
-                info!(target: "network", "Removing {:?} because address is blacklisted", peer_state.peer_info);
+            if access_list.is_blocked(&peer_id, peer_state.peer_info.addr) {
+                info!(target: "network", "Removing {:?} because it is blacklisted", peer_state.peer_info);
                 peers_to_delete.push(peer_id);
             } else {
                 peers_to_keep.push((peer_id, peer_state));
@@ -142,13 +146,34 @@ impl PeerStore {
         }
 
         let mut peer_store =
-            PeerStore { store, peer_states: peerid_2_state, addr_peers: addr_2_peer, blacklist };
+            PeerStore { store, peer_states: peerid_2_state, addr_peers: addr_2_peer, access_list };
         peer_store.delete_peers(&peers_to_delete)?;
         Ok(peer_store)
     }
 
     pub fn is_blacklisted(&self, addr: &SocketAddr) -> bool {
-        self.blacklist.contains(*addr)
+        self.access_list.is_addr_blocked(*addr)
+    }
+
+    /// Whether the peer is blacklisted, either by its id or by its address.
+    pub(crate) fn is_peer_blacklisted(&self, peer_info: &PeerInfo) -> bool {
+        self.access_list.is_blocked(&peer_info.id, peer_info.addr)
+    }
+
+    pub(crate) fn access_list(&self) -> &AccessList {
+        &self.access_list
+    }
+
+    /// Applies the operator's rule to the entry and persists it,
+    /// so that it takes precedence over the config after restart.
+    pub(crate) fn set_access_rule(
+        &mut self,
+        entry: BlacklistEntry,
+        rule: AccessRule,
+    ) -> anyhow::Result<()> {
+        self.store.set_access_rule(&entry, &rule)?;
+        self.access_list.set_rule(entry, rule);
+        Ok(())
     }
 
     pub(crate) fn len(&self) -> usize {
@@ -165,6 +190,17 @@ impl PeerStore {
         self.peer_states.values().filter(|st| st.status.is_banned()).count()
     }
 
+    /// Whether the address of `peer_info` is known to belong to the peer, because the peer
+    /// proved that it receives connections at it.
+    pub(crate) fn has_signed_addr(&self, peer_info: &PeerInfo) -> bool {
+        peer_info.addr.map_or(false, |addr| {
+            self.addr_peers.get(&addr).map_or(false, |verified_peer| {
+                verified_peer.peer_id == peer_info.id
+                    && verified_peer.trust_level == TrustLevel::Signed
+            })
+        })
+    }
+
     pub(crate) fn peer_connected(
         &mut self,
         clock: &time::Clock,
@@ -232,39 +268,44 @@ impl PeerStore {
     }
 
     /// Find a random subset of peers based on filter.
-    fn find_peers<F>(&self, filter: F, count: usize) -> Vec<PeerInfo>
+    /// The subset depends only on the state of the store and `rng`, not on the order of iteration.
+    fn find_peers<F>(&self, rng: &mut impl Rng, filter: F, count: usize) -> Vec<PeerInfo>
     where
         F: FnMut(&&KnownPeerState) -> bool,
     {
-        (self.peer_states.values())
-            .filter(filter)
-            .choose_multiple(&mut thread_rng(), count)
-            .into_iter()
-            .map(|kps| kps.peer_info.clone())
-            .collect()
+        let mut peers: Vec<_> = self.peer_states.values().filter(filter).collect();
+        peers.sort_by(|a, b| a.peer_info.id.cmp(&b.peer_info.id));
+        peers.choose_multiple(rng, count).map(|kps| kps.peer_info.clone()).collect()
     }
 
-    /// Return unconnected or peers with unknown status that we can try to connect to.
-    /// Peers with unknown addresses are filtered out.
-    pub(crate) fn unconnected_peer(
+    /// Return up to `max_count` random unconnected or peers with unknown status that we can try
+    /// to connect to. Peers with unknown addresses are filtered out.
+    pub(crate) fn unconnected_peers(
         &self,
+        rng: &mut impl Rng,
         ignore_fn: impl Fn(&KnownPeerState) -> bool,
-    ) -> Option<PeerInfo> {
+        max_count: usize,
+    ) -> Vec<PeerInfo> {
         self.find_peers(
+            rng,
             |p| {
                 (p.status == KnownPeerStatus::NotConnected || p.status == KnownPeerStatus::Unknown)
                     && !ignore_fn(p)
                     && p.peer_info.addr.is_some()
+                    // Peers blacklisted at runtime are kept in the store.
+                    && !self.is_peer_blacklisted(&p.peer_info)
             },
-            1,
+            max_count,
         )
-        .get(0)
-        .cloned()
     }
 
     /// Return healthy known peers up to given amount.
-    pub(crate) fn healthy_peers(&self, max_count: usize) -> Vec<PeerInfo> {
-        self.find_peers(|p| matches!(p.status, KnownPeerStatus::Banned(_, _)).not(), max_count)
+    pub(crate) fn healthy_peers(&self, rng: &mut impl Rng, max_count: usize) -> Vec<PeerInfo> {
+        self.find_peers(rng, |p| matches!(p.status, KnownPeerStatus::Banned(_, _)).not(), max_count)
+    }
+
+    pub(crate) fn get(&self, peer_id: &PeerId) -> Option<&KnownPeerState> {
+        self.peer_states.get(peer_id)
     }
 
     /// Return iterator over all known peers.
@@ -413,8 +454,7 @@ impl PeerStore {
         let mut blacklisted: usize = 0;
         for peer_info in peers {
             total += 1;
-            let is_blacklisted = peer_info.addr.map_or(false, |addr| self.blacklist.contains(addr));
-            if is_blacklisted {
+            if self.is_peer_blacklisted(&peer_info) {
                 blacklisted += 1;
             } else {
                 self.add_peer(clock, peer_info, TrustLevel::Indirect)?;
@@ -1,11 +1,12 @@
 use near_crypto::{KeyType, SecretKey};
-use near_network_primitives::types::{Blacklist, BlacklistEntry};
+use near_network_primitives::types::{AccessList, AccessRule, Blacklist, BlacklistEntry};
 use near_store::test_utils::create_test_store;
 use near_store::{Store, StoreOpener};
 use std::collections::HashSet;
 use std::net::{Ipv4Addr, SocketAddrV4};
 
 use super::*;
+use crate::testonly::make_rng;
 
 fn get_peer_id(seed: String) -> PeerId {
     PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed.as_str()).public_key())
@@ -29,6 +30,7 @@ fn gen_peer_info(port: u16) -> PeerInfo {
 
 #[test]
 fn ban_store() {
+    let mut rng = make_rng(921853233);
     let clock = time::FakeClock::default();
     let (_tmp_dir, opener) = Store::test_opener();
     let peer_info_a = gen_peer_info(0);
@@ -38,20 +40,21 @@ fn ban_store() {
         let store = store::Store::from(opener.open());
         let mut peer_store =
             PeerStore::new(&clock.clock(), store, &boot_nodes, Default::default()).unwrap();
-        assert_eq!(peer_store.healthy_peers(3).len(), 2);
+        assert_eq!(peer_store.healthy_peers(&mut rng, 3).len(), 2);
         peer_store.peer_ban(&clock.clock(), &peer_info_to_ban.id, ReasonForBan::Abusive).unwrap();
-        assert_eq!(peer_store.healthy_peers(3).len(), 1);
+        assert_eq!(peer_store.healthy_peers(&mut rng, 3).len(), 1);
     }
     {
         let store_new = store::Store::from(opener.open());
         let peer_store_new =
             PeerStore::new(&clock.clock(), store_new, &boot_nodes, Default::default()).unwrap();
-        assert_eq!(peer_store_new.healthy_peers(3).len(), 1);
+        assert_eq!(peer_store_new.healthy_peers(&mut rng, 3).len(), 1);
     }
 }
 
 #[test]
 fn test_unconnected_peer() {
+    let mut rng = make_rng(921853233);
     let clock = time::FakeClock::default();
     let (_tmp_dir, opener) = Store::test_opener();
     let peer_info_a = gen_peer_info(0);
@@ -61,11 +64,29 @@ fn test_unconnected_peer() {
         let store = store::Store::from(opener.open());
         let peer_store =
             PeerStore::new(&clock.clock(), store, &boot_nodes, Default::default()).unwrap();
-        assert!(peer_store.unconnected_peer(|_| false).is_some());
-        assert!(peer_store.unconnected_peer(|_| true).is_none());
+        assert_eq!(peer_store.unconnected_peers(&mut rng, |_| false, 1).len(), 1);
+        assert!(peer_store.unconnected_peers(&mut rng, |_| true, 1).is_empty());
     }
 }
 
+#[test]
+fn test_unconnected_peers_deterministic() {
+    let clock = time::FakeClock::default();
+    let boot_nodes: Vec<_> = (0..20).map(gen_peer_info).collect();
+    let sample = |seed| {
+        let peer_store = PeerStore::new(
+            &clock.clock(),
+            store::Store::from(create_test_store()),
+            &boot_nodes,
+            Default::default(),
+        )
+        .unwrap();
+        peer_store.unconnected_peers(&mut make_rng(seed), |_| false, 5)
+    };
+    // The sample depends only on the seed, not on the iteration order of the store.
+    assert_eq!(sample(89028037453), sample(89028037453));
+}
+
 fn check_exist(
     peer_store: &PeerStore,
     peer_id: &PeerId,
@@ -148,6 +169,31 @@ fn dont_handle_address_change() {
     assert!(check_integrity(&peer_store));
 }
 
+/// An inbound peer is connected without its address until it proves to listen at it.
+#[test]
+fn signed_addr() {
+    let clock = time::FakeClock::default();
+    let store = store::Store::from(create_test_store());
+    let mut peer_store = PeerStore::new(&clock.clock(), store, &[], Default::default()).unwrap();
+
+    let peers_id = (0..2).map(|ix| get_peer_id(format!("node{}", ix))).collect::<Vec<_>>();
+    let addr = get_addr(0);
+    let peer_aa = get_peer_info(peers_id[0].clone(), Some(addr));
+    let peer_ba = get_peer_info(peers_id[1].clone(), Some(addr));
+
+    peer_store.add_peer(&clock.clock(), peer_aa.clone(), TrustLevel::Direct).unwrap();
+    assert!(!peer_store.has_signed_addr(&peer_aa));
+
+    peer_store.peer_connected(&clock.clock(), &get_peer_info(peers_id[0].clone(), None)).unwrap();
+    assert!(check_exist(&peer_store, &peers_id[0], Some((addr, TrustLevel::Direct))));
+    assert!(!peer_store.has_signed_addr(&peer_aa));
+
+    peer_store.add_signed_peer(&clock.clock(), peer_aa.clone()).unwrap();
+    assert!(peer_store.has_signed_addr(&peer_aa));
+    assert!(!peer_store.has_signed_addr(&peer_ba));
+    assert!(check_integrity(&peer_store));
+}
+
 #[test]
 fn check_add_peers_overriding() {
     let clock = time::FakeClock::default();
@@ -277,7 +323,13 @@ fn check_ignore_blacklisted_peers() {
     {
         let blacklist: Blacklist =
             ["127.0.0.1:2", "127.0.0.1:5"].iter().map(|e| e.parse().unwrap()).collect();
-        let mut peer_store = PeerStore::new(&clock.clock(), store, &[], blacklist).unwrap();
+        let mut peer_store = PeerStore::new(
+            &clock.clock(),
+            store,
+            &[],
+            AccessList::new(blacklist, Blacklist::default()),
+        )
+        .unwrap();
         // Peer 127.0.0.1:2 is removed since it's blacklisted.
         assert_peers(&peer_store, &[&ids[1]]);
 
@@ -323,11 +375,70 @@ fn remove_blacklisted_peers_from_store() {
         let store = store::Store::from(opener.open());
         let blacklist: Blacklist =
             [BlacklistEntry::from_addr(peer_infos[2].addr.unwrap())].into_iter().collect();
-        let _peer_store = PeerStore::new(&clock.clock(), store, &[], blacklist).unwrap();
+        let _peer_store = PeerStore::new(
+            &clock.clock(),
+            store,
+            &[],
+            AccessList::new(blacklist, Blacklist::default()),
+        )
+        .unwrap();
     }
     assert_peers_in_store(&opener, &peer_ids[0..2]);
 }
 
+#[test]
+fn access_rules_are_persisted() {
+    let clock = time::FakeClock::default();
+    let (_tmp_dir, opener) = Store::test_opener();
+    let (ids, infos): (Vec<_>, Vec<_>) = (0..3)
+        .map(|i| {
+            let id = get_peer_id(format!("node{}", i));
+            let info = get_peer_info(id.clone(), Some(get_addr(i)));
+            (id, info)
+        })
+        .unzip();
+    let config_blacklist: Blacklist = ["127.0.0.0/24".parse().unwrap()].into_iter().collect();
+
+    {
+        let store = store::Store::from(opener.open());
+        let mut peer_store = PeerStore::new(
+            &clock.clock(),
+            store,
+            &[],
+            AccessList::new(config_blacklist.clone(), Blacklist::default()),
+        )
+        .unwrap();
+        assert!(peer_store.is_peer_blacklisted(&infos[0]));
+        peer_store
+            .set_access_rule(BlacklistEntry::from_addr(infos[1].addr.unwrap()), AccessRule::Allow)
+            .unwrap();
+        peer_store
+            .set_access_rule(BlacklistEntry::from_peer_id(ids[2].clone()), AccessRule::Block)
+            .unwrap();
+        assert!(peer_store.is_peer_blacklisted(&infos[0]));
+        assert!(!peer_store.is_peer_blacklisted(&infos[1]));
+        assert!(peer_store.is_peer_blacklisted(&get_peer_info(ids[2].clone(), None)));
+    }
+
+    // Rules set at runtime survive the restart and take precedence over the config.
+    {
+        let store = store::Store::from(opener.open());
+        let mut peer_store = PeerStore::new(
+            &clock.clock(),
+            store,
+            &[],
+            AccessList::new(config_blacklist, Blacklist::default()),
+        )
+        .unwrap();
+        assert!(peer_store.is_peer_blacklisted(&infos[0]));
+        assert!(!peer_store.is_peer_blacklisted(&infos[1]));
+        assert!(peer_store.is_peer_blacklisted(&get_peer_info(ids[2].clone(), None)));
+
+        peer_store.set_access_rule("127.0.0.0/24".parse().unwrap(), AccessRule::Unlisted).unwrap();
+        assert!(!peer_store.is_peer_blacklisted(&infos[0]));
+    }
+}
+
 #[track_caller]
 fn assert_peers_in_store(opener: &StoreOpener, want: &[PeerId]) {
     let store = store::Store::from(opener.open());
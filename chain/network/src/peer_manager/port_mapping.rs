@@ -0,0 +1,144 @@
+//! Forwarding of the listening port on the local router, for nodes behind a NAT, e.g.
+//! validators run at home.
+//!
+//! UPnP IGD is tried first and NAT-PMP second. The mapping is leased for `LEASE`, so it
+//! disappears when the node stops, and has to be renewed periodically while it runs.
+//! Whether the node is actually reachable at the mapped port is checked separately, by
+//! asking a peer to dial it back, see `PeerMessage::AddressChallengeRequest`.
+use anyhow::{anyhow, bail};
+use igd::aio::{search_gateway, Gateway};
+use igd::{AddPortError, PortMappingProtocol, SearchOptions};
+use natpmp::{Natpmp, Protocol, Response};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// Duration of the mapping. The peer manager renews it twice as often.
+const LEASE: Duration = Duration::from_secs(60 * 60);
+/// Timeout for discovering a UPnP gateway in the local network.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(3);
+/// Timeout for a NAT-PMP response, including the retransmissions.
+const NATPMP_TIMEOUT: Duration = Duration::from_secs(3);
+/// Description of the mapping shown by the router.
+const DESCRIPTION: &str = "nearcore";
+
+/// Maps a port of the router to `local_addr`, for TCP and, if `udp` is set, for UDP at the
+/// same port. Returns the external port, which is the port of `local_addr` unless another
+/// mapping already uses it.
+pub(crate) async fn map_port(local_addr: SocketAddr, udp: bool) -> anyhow::Result<u16> {
+    let local_addr = match local_addr {
+        SocketAddr::V4(addr) => addr,
+        SocketAddr::V6(_) => bail!("port mapping is not supported for IPv6 addresses"),
+    };
+    let upnp_err = match map_upnp(local_addr, udp).await {
+        Ok(port) => return Ok(port),
+        Err(err) => err,
+    };
+    debug!(target: "network", err = %upnp_err, "UPnP port mapping failed, trying NAT-PMP");
+    let port = local_addr.port();
+    tokio::task::spawn_blocking(move || map_natpmp(port, udp))
+        .await?
+        .map_err(|err| anyhow!("UPnP: {upnp_err:#}; NAT-PMP: {err:#}"))
+}
+
+async fn map_upnp(local_addr: SocketAddrV4, udp: bool) -> anyhow::Result<u16> {
+    let gateway =
+        search_gateway(SearchOptions { timeout: Some(SEARCH_TIMEOUT), ..Default::default() })
+            .await?;
+    let local_addr = if local_addr.ip().is_unspecified() {
+        SocketAddrV4::new(local_ip(*gateway.addr.ip())?, local_addr.port())
+    } else {
+        local_addr
+    };
+    let tcp = add_upnp_port(&gateway, PortMappingProtocol::TCP, local_addr.port(), local_addr);
+    let port = match tcp.await {
+        Ok(()) => local_addr.port(),
+        Err(AddPortError::PortInUse) => {
+            gateway
+                .add_any_port(
+                    PortMappingProtocol::TCP,
+                    local_addr,
+                    LEASE.as_secs() as u32,
+                    DESCRIPTION,
+                )
+                .await?
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if udp {
+        if let Err(err) = add_upnp_port(&gateway, PortMappingProtocol::UDP, port, local_addr).await
+        {
+            warn!(target: "network", %err, port, "Failed to map the UDP port, peers will connect over TCP");
+        }
+    }
+    Ok(port)
+}
+
+/// Adds a UPnP mapping, falling back to a permanent one if the router doesn't support leases.
+async fn add_upnp_port(
+    gateway: &Gateway,
+    protocol: PortMappingProtocol,
+    external_port: u16,
+    local_addr: SocketAddrV4,
+) -> Result<(), AddPortError> {
+    let lease = LEASE.as_secs() as u32;
+    match gateway.add_port(protocol, external_port, local_addr, lease, DESCRIPTION).await {
+        Err(AddPortError::OnlyPermanentLeasesSupported) => {
+            gateway.add_port(protocol, external_port, local_addr, 0, DESCRIPTION).await
+        }
+        result => result,
+    }
+}
+
+/// IP of the local interface on the route to `gateway`. Connecting a UDP socket doesn't
+/// send anything, it just picks the interface.
+fn local_ip(gateway: Ipv4Addr) -> anyhow::Result<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.connect((gateway, 1))?;
+    match socket.local_addr()?.ip() {
+        IpAddr::V4(ip) => Ok(ip),
+        IpAddr::V6(ip) => bail!("unexpected local address {ip}"),
+    }
+}
+
+/// The NAT-PMP client is blocking, so this has to run on a blocking thread.
+fn map_natpmp(port: u16, udp: bool) -> anyhow::Result<u16> {
+    let mut natpmp = Natpmp::new().map_err(|err| anyhow!("{err:?}"))?;
+    let external_port = request_natpmp(&mut natpmp, Protocol::TCP, port, port)?;
+    if udp {
+        match request_natpmp(&mut natpmp, Protocol::UDP, port, external_port) {
+            Ok(udp_port) if udp_port == external_port => {}
+            Ok(udp_port) => {
+                warn!(target: "network", external_port, udp_port, "UDP port mapped to a different port than TCP, peers will connect over TCP")
+            }
+            Err(err) => {
+                warn!(target: "network", %err, port, "Failed to map the UDP port, peers will connect over TCP")
+            }
+        }
+    }
+    Ok(external_port)
+}
+
+fn request_natpmp(
+    natpmp: &mut Natpmp,
+    protocol: Protocol,
+    private_port: u16,
+    public_port: u16,
+) -> anyhow::Result<u16> {
+    natpmp
+        .send_port_mapping_request(protocol, private_port, public_port, LEASE.as_secs() as u32)
+        .map_err(|err| anyhow!("{err:?}"))?;
+    let deadline = Instant::now() + NATPMP_TIMEOUT;
+    loop {
+        match natpmp.read_response_or_retry() {
+            Ok(Response::TCP(mapping)) | Ok(Response::UDP(mapping)) => {
+                return Ok(mapping.public_port())
+            }
+            Ok(_) => bail!("unexpected NAT-PMP response"),
+            Err(natpmp::Error::NATPMP_TRYAGAIN) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(50))
+            }
+            Err(err) => bail!("{err:?}"),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::network_protocol::PeerHint;
+use crate::peer::errors::PeerErrorCounts;
+use lru::LruCache;
+use near_network_primitives::time;
+use near_primitives::network::PeerId;
+use std::cmp::Reverse;
+
+#[cfg(test)]
+#[path = "scoring_test.rs"]
+mod test;
+
+/// default value for `capacity`
+const DEFAULT_CAPACITY: usize = 10_000;
+/// Penalty for a message which failed to parse or violated the protocol.
+const INVALID_MESSAGE_PENALTY: i64 = 10;
+/// Penalty for a routed message which the peer has already sent us recently.
+const DUPLICATE_ROUTED_MESSAGE_PENALTY: i64 = 1;
+/// Reward for a block or chunk response to our request.
+const USEFUL_RESPONSE_REWARD: i64 = 1;
+/// Penalty for every `LATENCY_PENALTY_UNIT` of the average response latency.
+const LATENCY_PENALTY_UNIT: time::Duration = time::Duration::milliseconds(100);
+/// Bound on the accumulated points, so that a peer can't build up an unlimited credit,
+/// nor stay penalized forever.
+const MAX_POINTS: i64 = 1000;
+/// Every update the accumulated points lose `1/POINTS_DECAY` of their value,
+/// so that recent behavior matters more than the old one.
+const POINTS_DECAY: i64 = 100;
+/// Weight `1/LATENCY_EMA_WEIGHT` of a new latency sample in the moving average.
+const LATENCY_EMA_WEIGHT: i32 = 4;
+/// Penalty for a minor offense, i.e. a reason for ban with `BanSeverity::Minor`.
+const MINOR_OFFENSE_PENALTY: i64 = 100;
+/// A peer whose points drop below this value because of minor offenses gets banned.
+const MIN_POINTS_BEFORE_BAN: i64 = -MAX_POINTS / 2;
+
+/// Behavior of a peer observed by its `PeerActor` since the last `QueryPeerStats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PeerBehavior {
+    /// Messages which couldn't be handled, by the kind of the failure.
+    pub errors: PeerErrorCounts,
+    /// Routed messages which the node has already received recently, from this or another peer.
+    pub duplicate_routed_messages: u64,
+    /// Requested blocks and chunks delivered by the peer.
+    pub useful_responses: u64,
+    /// Latencies of the responses to the blocks requested from the peer.
+    pub response_latencies: Vec<time::Duration>,
+}
+
+/// Score of a single peer. The higher the better.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PeerScore {
+    /// Rewards minus penalties, decaying over time.
+    points: i64,
+    /// Moving average of the response latency. None until the first response.
+    latency: Option<time::Duration>,
+}
+
+impl PeerScore {
+    pub fn update(&mut self, behavior: &PeerBehavior) {
+        let count =
+            |n: u64, weight: i64| i64::try_from(n).unwrap_or(i64::MAX).saturating_mul(weight);
+        let points = (self.points * (POINTS_DECAY - 1) / POINTS_DECAY)
+            .saturating_add(count(behavior.useful_responses, USEFUL_RESPONSE_REWARD))
+            .saturating_sub(count(behavior.errors.misbehavior(), INVALID_MESSAGE_PENALTY))
+            .saturating_sub(count(
+                behavior.duplicate_routed_messages,
+                DUPLICATE_ROUTED_MESSAGE_PENALTY,
+            ));
+        self.points = points.clamp(-MAX_POINTS, MAX_POINTS);
+        for latency in &behavior.response_latencies {
+            self.latency = Some(match self.latency {
+                Some(avg) => avg + (*latency - avg) / LATENCY_EMA_WEIGHT,
+                None => *latency,
+            });
+        }
+    }
+
+    /// Penalizes a minor offense. Returns whether the offenses of the peer add up to a ban.
+    pub fn add_minor_offense(&mut self) -> bool {
+        self.points = self.points.saturating_sub(MINOR_OFFENSE_PENALTY).max(-MAX_POINTS);
+        self.points < MIN_POINTS_BEFORE_BAN
+    }
+
+    /// Moving average of the response latency, if any response has been observed.
+    pub fn latency(&self) -> Option<time::Duration> {
+        self.latency
+    }
+
+    pub fn value(&self) -> i64 {
+        let latency_penalty = self.latency.map_or(0, |latency| {
+            (latency.whole_milliseconds() / LATENCY_PENALTY_UNIT.whole_milliseconds()) as i64
+        });
+        self.points - latency_penalty
+    }
+}
+
+/// Scores of the peers, used to pick the peers to connect to and to disconnect from.
+///
+/// Scores are kept after the peer disconnects, so that we can prefer the peers which
+/// behaved well when choosing whom to connect to.
+pub(crate) struct PeerScores {
+    scores: LruCache<PeerId, PeerScore>,
+    /// Hints about the peers received from other peers in `PeersResponse`.
+    /// Only break ties between peers with equal own scores, since they can't be verified.
+    hints: LruCache<PeerId, PeerHint>,
+}
+
+impl Default for PeerScores {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl PeerScores {
+    pub fn new(capacity: usize) -> Self {
+        Self { scores: LruCache::new(capacity), hints: LruCache::new(capacity) }
+    }
+
+    /// Updates the score of the peer with its recent behavior.
+    pub fn update(&mut self, peer_id: &PeerId, behavior: &PeerBehavior) {
+        if let Some(score) = self.scores.get_mut(peer_id) {
+            score.update(behavior);
+        } else {
+            let mut score = PeerScore::default();
+            score.update(behavior);
+            self.scores.put(peer_id.clone(), score);
+        }
+    }
+
+    /// Penalizes a minor offense of the peer, see `PeerScore::add_minor_offense`.
+    pub fn add_minor_offense(&mut self, peer_id: &PeerId) -> bool {
+        if let Some(score) = self.scores.get_mut(peer_id) {
+            score.add_minor_offense()
+        } else {
+            let mut score = PeerScore::default();
+            let ban = score.add_minor_offense();
+            self.scores.put(peer_id.clone(), score);
+            ban
+        }
+    }
+
+    /// Drops the penalties of the peer, e.g. when the operator lifts its ban.
+    pub fn pardon(&mut self, peer_id: &PeerId) {
+        if let Some(score) = self.scores.get_mut(peer_id) {
+            score.points = score.points.max(0);
+        }
+    }
+
+    /// Score of the peer. Peers we know nothing about have a neutral score of 0.
+    pub fn get(&self, peer_id: &PeerId) -> i64 {
+        self.scores.peek(peer_id).map_or(0, PeerScore::value)
+    }
+
+    /// Average response latency of the peer, as observed by us.
+    pub fn latency(&self, peer_id: &PeerId) -> Option<time::Duration> {
+        self.scores.peek(peer_id).and_then(PeerScore::latency)
+    }
+
+    /// Stores a hint about a peer received from another peer. Newer hints replace older ones.
+    pub fn add_hint(&mut self, hint: PeerHint) {
+        self.hints.put(hint.peer_id.clone(), hint);
+    }
+
+    /// Priority of dialing the peer, the higher the better.
+    /// Our own score of the peer comes first, the hints only break ties: archival peers
+    /// are preferred if `prefer_archival` is set, then the peers with lower hinted latency.
+    pub fn dial_priority(
+        &self,
+        peer_id: &PeerId,
+        prefer_archival: bool,
+    ) -> (i64, bool, Reverse<time::Duration>) {
+        let hint = self.hints.peek(peer_id);
+        (
+            self.get(peer_id),
+            prefer_archival && hint.map_or(false, |hint| hint.archival),
+            Reverse(hint.and_then(|hint| hint.latency).unwrap_or(time::Duration::MAX)),
+        )
+    }
+}
@@ -0,0 +1,148 @@
+use near_crypto::{KeyType, SecretKey};
+
+use super::*;
+
+fn get_peer_id(seed: &str) -> PeerId {
+    PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed).public_key())
+}
+
+fn useful(n: u64) -> PeerBehavior {
+    PeerBehavior { useful_responses: n, ..Default::default() }
+}
+
+fn errors(errors: PeerErrorCounts) -> PeerBehavior {
+    PeerBehavior { errors, ..Default::default() }
+}
+
+#[test]
+fn test_score_rewards_and_penalties() {
+    let mut score = PeerScore::default();
+    assert_eq!(0, score.value());
+    score.update(&useful(5));
+    assert_eq!(5, score.value());
+    score.update(&errors(PeerErrorCounts { parse: 1, ..Default::default() }));
+    assert!(score.value() < 0);
+
+    // Transient errors are not the peer's fault.
+    let mut score = PeerScore::default();
+    score.update(&errors(PeerErrorCounts { transient: 3, ..Default::default() }));
+    assert_eq!(0, score.value());
+    score.update(&errors(PeerErrorCounts { protocol_violation: 1, ..Default::default() }));
+    assert!(score.value() < 0);
+
+    let mut score = PeerScore::default();
+    score.update(&PeerBehavior { duplicate_routed_messages: 3, ..Default::default() });
+    assert_eq!(-3, score.value());
+}
+
+#[test]
+fn test_score_is_bounded() {
+    let mut score = PeerScore::default();
+    score.update(&useful(u64::MAX));
+    assert_eq!(MAX_POINTS, score.value());
+    score.update(&errors(PeerErrorCounts { parse: u64::MAX, ..Default::default() }));
+    assert_eq!(-MAX_POINTS, score.value());
+}
+
+#[test]
+fn test_score_decays() {
+    let mut score = PeerScore::default();
+    score.update(&useful(500));
+    let mut prev = score.value();
+    for _ in 0..10 {
+        score.update(&PeerBehavior::default());
+        assert!(score.value() < prev);
+        prev = score.value();
+    }
+}
+
+#[test]
+fn test_score_latency_penalty() {
+    let mut fast = PeerScore::default();
+    fast.update(&PeerBehavior {
+        useful_responses: 1,
+        response_latencies: vec![time::Duration::milliseconds(50)],
+        ..Default::default()
+    });
+    let mut slow = PeerScore::default();
+    slow.update(&PeerBehavior {
+        useful_responses: 1,
+        response_latencies: vec![time::Duration::seconds(2)],
+        ..Default::default()
+    });
+    assert_eq!(1, fast.value());
+    assert_eq!(1 - 20, slow.value());
+    // A single fast response doesn't wipe out the slow history.
+    slow.update(&PeerBehavior {
+        response_latencies: vec![time::Duration::milliseconds(50)],
+        ..Default::default()
+    });
+    assert!(slow.value() < fast.value());
+}
+
+#[test]
+fn test_scores() {
+    let mut scores = PeerScores::new(2);
+    let (a, b, c) = (get_peer_id("a"), get_peer_id("b"), get_peer_id("c"));
+    assert_eq!(0, scores.get(&a));
+    scores.update(&a, &useful(3));
+    scores.update(&a, &useful(3));
+    scores.update(&b, &useful(1));
+    assert!(scores.get(&a) > scores.get(&b));
+    // The least recently updated peer is forgotten.
+    scores.update(&c, &useful(1));
+    assert_eq!(0, scores.get(&a));
+    assert_eq!(1, scores.get(&b));
+}
+
+#[test]
+fn test_dial_priority() {
+    let mut scores = PeerScores::new(10);
+    let (a, b, c, d) = (get_peer_id("a"), get_peer_id("b"), get_peer_id("c"), get_peer_id("d"));
+    let hint = |peer_id: &PeerId, latency_ms: Option<i64>, archival: bool| PeerHint {
+        peer_id: peer_id.clone(),
+        latency: latency_ms.map(time::Duration::milliseconds),
+        archival,
+    };
+    scores.add_hint(hint(&b, Some(500), false));
+    scores.add_hint(hint(&c, Some(50), false));
+    scores.add_hint(hint(&d, None, true));
+    // Peers with lower hinted latency go first, unknown latency goes last.
+    assert!(scores.dial_priority(&c, false) > scores.dial_priority(&b, false));
+    assert!(scores.dial_priority(&b, false) > scores.dial_priority(&d, false));
+    assert!(scores.dial_priority(&b, false) > scores.dial_priority(&a, false));
+    // Archival peers go first if we prefer them.
+    assert!(scores.dial_priority(&d, true) > scores.dial_priority(&c, true));
+    // Hints don't override our own observations.
+    scores.update(&a, &useful(1));
+    assert!(scores.dial_priority(&a, true) > scores.dial_priority(&c, true));
+    assert!(scores.dial_priority(&a, true) > scores.dial_priority(&d, true));
+    // Newer hints replace older ones.
+    scores.add_hint(hint(&b, Some(10), false));
+    assert!(scores.dial_priority(&b, false) > scores.dial_priority(&c, false));
+}
+
+#[test]
+fn test_minor_offenses() {
+    let mut scores = PeerScores::new(10);
+    let a = get_peer_id("a");
+    // Minor offenses add up to a ban only if they are frequent.
+    for _ in 0..-MIN_POINTS_BEFORE_BAN / MINOR_OFFENSE_PENALTY {
+        assert!(!scores.add_minor_offense(&a));
+    }
+    assert!(scores.add_minor_offense(&a));
+
+    // The penalties decay.
+    let b = get_peer_id("b");
+    for _ in 0..-MIN_POINTS_BEFORE_BAN / MINOR_OFFENSE_PENALTY {
+        assert!(!scores.add_minor_offense(&b));
+    }
+    for _ in 0..30 {
+        scores.update(&b, &PeerBehavior::default());
+    }
+    assert!(!scores.add_minor_offense(&b));
+
+    scores.pardon(&a);
+    assert_eq!(0, scores.get(&a));
+    assert!(!scores.add_minor_offense(&a));
+}
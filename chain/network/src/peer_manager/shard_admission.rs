@@ -0,0 +1,118 @@
+//! Admission of the inbound peers by the shards they track, see
+//! `NetworkConfig::inbound_tracked_shards_ratio`.
+//!
+//! A fraction of the peer slots is reserved for the peers tracking some of the shards this
+//! node tracks, e.g. the chunk producers of our shards, so that they aren't crowded out by
+//! peers which are of no use for our shards. When all the slots are taken, an inbound peer
+//! tracking some of our shards replaces an inbound peer tracking none of them.
+use near_primitives::types::ShardId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Admission {
+    Accept,
+    /// Accept after disconnecting an inbound peer tracking none of our shards.
+    Evict,
+    Reject,
+}
+
+pub(crate) struct ShardAdmission {
+    ratio: f64,
+    /// Shards tracked by this node, as reported by the view client.
+    tracked_shards: Vec<ShardId>,
+}
+
+impl ShardAdmission {
+    pub fn new(ratio: f64) -> Self {
+        Self { ratio, tracked_shards: vec![] }
+    }
+
+    pub fn set_tracked_shards(&mut self, tracked_shards: Vec<ShardId>) {
+        self.tracked_shards = tracked_shards;
+    }
+
+    /// Whether any slots are reserved. Nothing is reserved until the node tracks a shard.
+    pub fn is_enabled(&self) -> bool {
+        self.ratio > 0. && !self.tracked_shards.is_empty()
+    }
+
+    /// Whether a peer tracking `shards` tracks some of our shards.
+    pub fn is_needed(&self, shards: &[ShardId]) -> bool {
+        shards.iter().any(|shard_id| self.tracked_shards.contains(shard_id))
+    }
+
+    /// Decides whether to accept an inbound peer tracking `shards`, given the number of the
+    /// connections (including the outbound ones being established), the number of the
+    /// connected peers tracking some of our shards, and whether an inbound peer tracking none
+    /// of our shards can be disconnected.
+    pub fn admit(
+        &self,
+        shards: &[ShardId],
+        num_connections: usize,
+        num_needed: usize,
+        max_num_peers: usize,
+        can_evict: bool,
+    ) -> Admission {
+        if !self.is_enabled() {
+            return if num_connections < max_num_peers {
+                Admission::Accept
+            } else {
+                Admission::Reject
+            };
+        }
+        if self.is_needed(shards) {
+            if num_connections < max_num_peers {
+                Admission::Accept
+            } else if can_evict {
+                Admission::Evict
+            } else {
+                Admission::Reject
+            }
+        } else {
+            let reserved = (max_num_peers as f64 * self.ratio).ceil() as usize;
+            let free_reserved = reserved.saturating_sub(num_needed);
+            if num_connections + free_reserved < max_num_peers {
+                Admission::Accept
+            } else {
+                Admission::Reject
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_admit() {
+        let mut admission = ShardAdmission::new(0.25);
+        // Nothing is reserved until the node tracks a shard.
+        assert!(!admission.is_enabled());
+        assert_eq!(Admission::Accept, admission.admit(&[], 7, 0, 8, true));
+        assert_eq!(Admission::Reject, admission.admit(&[1], 8, 0, 8, true));
+
+        admission.set_tracked_shards(vec![1, 2]);
+        assert!(admission.is_enabled());
+        assert!(admission.is_needed(&[0, 2]));
+        assert!(!admission.is_needed(&[0, 3]));
+        assert!(!admission.is_needed(&[]));
+
+        // 2 of 8 slots are reserved.
+        assert_eq!(Admission::Accept, admission.admit(&[0], 5, 0, 8, true));
+        assert_eq!(Admission::Reject, admission.admit(&[0], 6, 0, 8, true));
+        assert_eq!(Admission::Reject, admission.admit(&[0], 6, 1, 8, true));
+        // The reserved slots are taken by peers tracking our shards already.
+        assert_eq!(Admission::Accept, admission.admit(&[0], 6, 2, 8, true));
+        assert_eq!(Admission::Reject, admission.admit(&[0], 8, 2, 8, true));
+
+        assert_eq!(Admission::Accept, admission.admit(&[1], 7, 0, 8, false));
+        assert_eq!(Admission::Evict, admission.admit(&[1], 8, 0, 8, true));
+        assert_eq!(Admission::Reject, admission.admit(&[1], 8, 0, 8, false));
+
+        let mut disabled = ShardAdmission::new(0.);
+        disabled.set_tracked_shards(vec![1]);
+        assert!(!disabled.is_enabled());
+        assert_eq!(Admission::Reject, disabled.admit(&[1], 8, 0, 8, true));
+    }
+}
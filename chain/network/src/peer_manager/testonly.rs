@@ -2,11 +2,13 @@ use crate::broadcast;
 use crate::network_protocol::testonly as data;
 use crate::peer_manager::peer_manager_actor::Event as PME;
 use crate::testonly::actix::ActixSystem;
-use crate::testonly::fake_client;
+use crate::testonly::{fake_client, Rng};
 use crate::PeerManagerActor;
 use actix::Actor;
+use near_network_primitives::time;
 use near_network_primitives::types::NetworkConfig;
 use near_store::test_utils::create_test_store;
+use rand::Rng as _;
 use std::sync::Arc;
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -21,15 +23,22 @@ pub struct ActorHandler {
     _actix: ActixSystem<PeerManagerActor>,
 }
 
-pub async fn start(chain: Arc<data::Chain>, cfg: NetworkConfig) -> ActorHandler {
+pub async fn start(
+    clock: time::Clock,
+    rng: &mut Rng,
+    chain: Arc<data::Chain>,
+    cfg: NetworkConfig,
+) -> ActorHandler {
     let (send, recv) = broadcast::unbounded_channel();
+    let rng_seed = rng.gen();
     let actix = ActixSystem::spawn({
         let cfg = cfg.clone();
         move || {
             let store = create_test_store();
             let fc = fake_client::start(chain, send.sink().compose(Event::Client));
-            PeerManagerActor::new(store, cfg, fc.clone().recipient(), fc.clone().recipient())
+            PeerManagerActor::new(clock, store, cfg, fc.clone().recipient(), fc.clone().recipient())
                 .unwrap()
+                .with_rng_seed(rng_seed)
                 .with_event_sink(send.sink().compose(Event::PeerManager))
                 .start()
         }
@@ -1,19 +1,25 @@
 use crate::network_protocol::testonly as data;
-use crate::network_protocol::Encoding;
+use crate::network_protocol::{Encoding, NetworkAdvisory};
 use crate::peer;
 use crate::peer_manager;
 use crate::peer_manager::peer_manager_actor::Event as PME;
 use crate::peer_manager::testonly::Event;
-use crate::testonly::make_rng;
+use crate::testonly::fake_client::Event as CE;
+use crate::testonly::{make_rng, replay};
+use crate::transport::quic;
 use crate::types::{PeerMessage, RoutingTableUpdate};
 use near_logger_utils::init_test_logger;
+use near_network_primitives::chaos::{ChaosAction, ChaosConfig};
 use near_network_primitives::time;
-use near_network_primitives::types::NetworkConfig;
+use near_network_primitives::types::{ListenerConfig, NetworkConfig, PeerInfo, Transport};
 use near_network_primitives::types::{Ping, RoutedMessageBody};
 use near_primitives::network::PeerId;
+use near_primitives::version::{NETWORK_ADVISORY_PROTOCOL_VERSION, PROTOCOL_VERSION};
 use rand::Rng as _;
 use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 
 // After the initial exchange, all subsequent SyncRoutingTable messages are
@@ -26,14 +32,20 @@ async fn repeated_data_in_sync_routing_table() {
     let mut clock = time::FakeClock::default();
     let port = crate::test_utils::open_port();
     let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
-    let pm =
-        peer_manager::testonly::start(chain.clone(), NetworkConfig::from_seed("test1", port)).await;
+    let pm = peer_manager::testonly::start(
+        clock.clock(),
+        rng,
+        chain.clone(),
+        NetworkConfig::from_seed("test1", port),
+    )
+    .await;
     let cfg = peer::testonly::PeerConfig {
         signer: data::make_signer(rng),
         chain,
         peers: vec![],
         start_handshake_with: Some(PeerId::new(pm.cfg.node_key.public_key())),
         force_encoding: Some(Encoding::Proto),
+        encrypt_connection: false,
     };
     let stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
     let mut peer = peer::testonly::PeerHandle::start_endpoint(clock.clock(), cfg, stream).await;
@@ -96,14 +108,20 @@ async fn ttl() {
     let mut clock = time::FakeClock::default();
     let port = crate::test_utils::open_port();
     let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
-    let mut pm =
-        peer_manager::testonly::start(chain.clone(), NetworkConfig::from_seed("test1", port)).await;
+    let mut pm = peer_manager::testonly::start(
+        clock.clock(),
+        rng,
+        chain.clone(),
+        NetworkConfig::from_seed("test1", port),
+    )
+    .await;
     let cfg = peer::testonly::PeerConfig {
         signer: data::make_signer(rng),
         chain,
         peers: vec![],
         start_handshake_with: Some(PeerId::new(pm.cfg.node_key.public_key())),
         force_encoding: Some(Encoding::Proto),
+        encrypt_connection: false,
     };
     let stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
     let mut peer = peer::testonly::PeerHandle::start_endpoint(clock.clock(), cfg, stream).await;
@@ -149,3 +167,308 @@ async fn ttl() {
         }
     }
 }
+
+// Frames recorded by one node, when replayed into another, reach its client the same way.
+#[tokio::test]
+async fn record_and_replay() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let tmp_dir = tempfile::Builder::new().prefix("record_frames").tempdir().unwrap();
+    let path = tmp_dir.path().join("frames");
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.record_frames = Some(path.clone());
+    let mut pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+    let block = chain.blocks[5].clone();
+    let tx = data::make_signed_transaction(rng);
+    peer.send(PeerMessage::Block(block.clone())).await;
+    peer.send(PeerMessage::Transaction(tx.clone())).await;
+    pm.events
+        .recv_until(|ev| (ev == Event::Client(CE::Transaction(tx.clone()))).then_some(()))
+        .await;
+    drop(peer);
+    drop(pm);
+
+    let frames = crate::read_frames(&path).unwrap();
+    assert!(frames.iter().any(|f| f.message() == Some(PeerMessage::Block(block.clone()))));
+    let cfg = NetworkConfig::from_seed("test2", crate::test_utils::open_port());
+    let mut replay =
+        replay::replay(clock.clock(), rng, chain.clone(), cfg, &frames, f64::INFINITY).await;
+    assert_eq!(replay.peers.len(), 1);
+    replay
+        .pm
+        .events
+        .recv_until(|ev| (ev == Event::Client(CE::Block(block.clone()))).then_some(()))
+        .await;
+    replay
+        .pm
+        .events
+        .recv_until(|ev| (ev == Event::Client(CE::Transaction(tx.clone()))).then_some(()))
+        .await;
+}
+
+// Connections to a whitelist-only listener are dropped unless they come from a whitelisted
+// IP, connections to a listener with an allowlist unless they come from an allowed IP, while
+// the other listeners accept them as usual.
+#[tokio::test]
+async fn listeners() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    let private_addr = SocketAddr::from(([127, 0, 0, 1], crate::test_utils::open_port()));
+    let public_addr = SocketAddr::from(([127, 0, 0, 1], crate::test_utils::open_port()));
+    let infra_addr = SocketAddr::from(([127, 0, 0, 1], crate::test_utils::open_port()));
+    cfg.whitelist_nodes =
+        vec![PeerInfo::new(data::make_peer_id(rng), SocketAddr::from(([10, 0, 0, 1], 24567)))];
+    cfg.listeners = vec![
+        ListenerConfig {
+            addr: private_addr,
+            whitelist_only: true,
+            allowlist: Default::default(),
+            max_inbound_connections_per_ip_per_minute: 100,
+            max_pending_handshakes_per_ip: 10,
+        },
+        ListenerConfig {
+            addr: public_addr,
+            whitelist_only: false,
+            allowlist: Default::default(),
+            max_inbound_connections_per_ip_per_minute: 1,
+            max_pending_handshakes_per_ip: 1,
+        },
+        ListenerConfig {
+            addr: infra_addr,
+            whitelist_only: false,
+            allowlist: ["10.0.0.0/8".parse().unwrap()].into_iter().collect(),
+            max_inbound_connections_per_ip_per_minute: 100,
+            max_pending_handshakes_per_ip: 10,
+        },
+    ];
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+
+    // Not whitelisted, so dropped before the handshake.
+    let mut stream = TcpStream::connect(private_addr).await.unwrap();
+    let mut buf = [0; 1];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+    // Not in the allowlist of the listener.
+    let mut stream = TcpStream::connect(infra_addr).await.unwrap();
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+
+    // The public listener accepts anyone.
+    let peer_cfg = peer::testonly::PeerConfig {
+        signer: data::make_signer(rng),
+        chain: chain.clone(),
+        peers: vec![],
+        start_handshake_with: Some(PeerId::new(pm.cfg.node_key.public_key())),
+        force_encoding: Some(Encoding::Proto),
+        encrypt_connection: false,
+    };
+    let stream = TcpStream::connect(public_addr).await.unwrap();
+    let mut peer =
+        peer::testonly::PeerHandle::start_endpoint(clock.clock(), peer_cfg, stream).await;
+    peer.complete_handshake().await;
+
+    // Second connection within a minute exceeds the limit of the public listener.
+    let mut stream = TcpStream::connect(public_addr).await.unwrap();
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+}
+
+// Connections from a subnet which already has `max_peers_per_subnet` connected peers are
+// dropped.
+#[tokio::test]
+async fn max_peers_per_subnet() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.max_peers_per_subnet = Some(1);
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let _peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+
+    // Second peer from 127.0.0.0/24.
+    let mut stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
+    let mut buf = [0; 1];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+}
+
+// Connections which send nothing hold their pending handshake slot until the first frame
+// timeout, and connections over `max_pending_handshakes` are dropped right away.
+#[tokio::test]
+async fn first_frame_timeout() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.max_pending_handshakes = 1;
+    cfg.first_frame_timeout = std::time::Duration::from_millis(500);
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+
+    let mut idle = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
+    let mut buf = [0; 1];
+    let mut stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+    // Closed once the timeout expires, which releases the slot.
+    assert_eq!(0, idle.read(&mut buf).await.unwrap());
+    let _peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+}
+
+// Inbound QUIC connections take a pending handshake slot before the QUIC handshake, so the
+// connections over `max_pending_handshakes` are dropped without being completed.
+#[tokio::test]
+async fn quic_pending_handshakes() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.transport = Transport::Quic;
+    cfg.max_pending_handshakes = 1;
+    cfg.first_frame_timeout = std::time::Duration::from_millis(500);
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let addr = pm.cfg.node_addr.unwrap();
+
+    let (client, _) = quic::Endpoint::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+    let mut idle = client.connect(addr).await.unwrap();
+    let second =
+        tokio::time::timeout(std::time::Duration::from_millis(500), client.connect(addr)).await;
+    assert!(second.map_or(true, |res| res.is_err()));
+    // Closed once the timeout expires, which releases the slot.
+    let mut buf = [0; 1];
+    assert!(matches!(idle.read(&mut buf).await, Ok(0) | Err(_)));
+    let _peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+}
+
+// With protobuf_only, Borsh handshakes are refused, while protobuf peers connect as usual.
+#[tokio::test]
+async fn protobuf_only() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.protobuf_only = true;
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let _peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+
+    let mut stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
+    let handshake = PeerMessage::Handshake(data::make_handshake(rng, &chain));
+    let frame = handshake.serialize(Encoding::Borsh);
+    stream.write_u32_le(frame.len() as u32).await.unwrap();
+    stream.write_all(&frame).await.unwrap();
+    let mut buf = [0; 1];
+    assert_eq!(0, stream.read(&mut buf).await.unwrap());
+}
+
+// Messages received from peers are dropped and reordered according to the chaos schedule.
+#[tokio::test]
+async fn chaos() {
+    init_test_logger();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.chaos = Some(
+        ChaosConfig::default()
+            .on("Block", ChaosAction::Drop)
+            .on("Transaction", ChaosAction::Reorder),
+    );
+    let mut pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let peer = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+    let tx1 = data::make_signed_transaction(rng);
+    let tx2 = data::make_signed_transaction(rng);
+    peer.send(PeerMessage::Transaction(tx1.clone())).await;
+    peer.send(PeerMessage::Block(chain.blocks[5].clone())).await;
+    peer.send(PeerMessage::Transaction(tx2.clone())).await;
+    let mut received = vec![];
+    while received.len() < 2 {
+        if let Event::Client(ev @ (CE::Block(_) | CE::Transaction(_))) = pm.events.recv().await {
+            received.push(ev);
+        }
+    }
+    assert_eq!(vec![CE::Transaction(tx2), CE::Transaction(tx1)], received);
+}
+
+async fn recv_advisory(peer: &mut peer::testonly::PeerHandle) -> NetworkAdvisory {
+    peer.events
+        .recv_until(|ev| match ev {
+            peer::testonly::Event::NetworkAdvisory(advisory) => Some(advisory),
+            _ => None,
+        })
+        .await
+}
+
+// Accepted advisories are relayed to the other peers, replayed ones are dropped.
+#[tokio::test]
+async fn network_advisory() {
+    init_test_logger();
+    if PROTOCOL_VERSION < NETWORK_ADVISORY_PROTOCOL_VERSION {
+        // Advisories are relayed only to the peers which support them.
+        return;
+    }
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let signer = data::make_signer(rng);
+
+    let mut cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    cfg.advisory_keys = vec![signer.public_key.clone()];
+    let pm = peer_manager::testonly::start(clock.clock(), rng, chain.clone(), cfg).await;
+    let peer1 = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+    let mut peer2 = replay::connect(clock.clock(), rng, chain.clone(), &pm).await;
+    let make = |nonce, clock: &time::FakeClock| {
+        let timestamp = clock.now_utc().unix_timestamp() as u64;
+        let message = format!("Upgrade before epoch {}", nonce);
+        NetworkAdvisory::new(&signer, chain.genesis_id.chain_id.clone(), nonce, timestamp, message)
+    };
+    let advisory1 = make(1, &clock);
+    peer1.send(PeerMessage::NetworkAdvisory(advisory1.clone())).await;
+    assert_eq!(advisory1, recv_advisory(&mut peer2).await);
+    peer1.send(PeerMessage::NetworkAdvisory(advisory1)).await;
+    clock.advance(peer_manager::advisories::MIN_INTERVAL);
+    let advisory2 = make(2, &clock);
+    peer1.send(PeerMessage::NetworkAdvisory(advisory2.clone())).await;
+    assert_eq!(advisory2, recv_advisory(&mut peer2).await);
+}
+
+// Replays a recording made by a node operator, set in NEAR_REPLAY_FRAMES, and prints the
+// messages which reach the client.
+#[tokio::test]
+#[ignore]
+async fn replay_recording() {
+    init_test_logger();
+    let path = std::env::var("NEAR_REPLAY_FRAMES").expect("NEAR_REPLAY_FRAMES not set");
+    let frames = crate::read_frames(path.as_ref()).unwrap();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let cfg = NetworkConfig::from_seed("test1", crate::test_utils::open_port());
+    let mut replay = replay::replay(clock.clock(), rng, chain, cfg, &frames, 1.).await;
+    while let Ok(ev) =
+        tokio::time::timeout(std::time::Duration::from_secs(10), replay.pm.events.recv()).await
+    {
+        if let Event::Client(ev) = ev {
+            tracing::info!(target: "test", ?ev, "Replayed client event");
+        }
+    }
+}
@@ -1,12 +1,14 @@
 /// This file is contains all types used for communication between `Actors` within this crate.
 /// They are not meant to be used outside.
-use crate::network_protocol::{PeerMessage, RoutingTableUpdate};
+use crate::network_protocol::{
+    AddressChallenge, NetworkAdvisory, PeerHint, PeerMessage, RoutingTableUpdate,
+};
 use crate::peer::peer_actor::PeerActor;
 use conqueue::QueueSender;
 use near_network_primitives::time;
 use near_network_primitives::types::{
     Ban, Edge, InboundTcpConnect, PartialEdgeInfo, PeerChainInfoV2, PeerInfo, PeerType,
-    ReasonForBan, RoutedMessageBody, RoutedMessageFrom,
+    ProtocolDeprecation, ReasonForBan, RoutedMessageBody, RoutedMessageFrom,
 };
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
@@ -21,6 +23,8 @@ use std::sync::{Arc, Mutex};
 #[derive(Debug, Clone)]
 pub struct PeersResponse {
     pub(crate) peers: Vec<PeerInfo>,
+    /// Hints of the sender about some of the `peers`.
+    pub(crate) hints: Vec<PeerHint>,
 }
 
 #[derive(actix::Message, Debug, strum::IntoStaticStr)]
@@ -31,6 +35,7 @@ pub(crate) enum PeerToManagerMsg {
     PeersRequest(PeersRequest),
     PeersResponse(PeersResponse),
     InboundTcpConnect(InboundTcpConnect),
+    InboundQuicConnect(quinn::Connecting),
     Unregister(Unregister),
     Ban(Ban),
     RequestUpdateNonce(PeerId, PartialEdgeInfo),
@@ -46,6 +51,16 @@ pub(crate) enum PeerToManagerMsg {
     RouteBack(Box<RoutedMessageBody>, CryptoHash),
     UpdatePeerInfo(PeerInfo),
     ReceivedMessage(PeerId, time::Instant),
+    /// `AddressChallenge` received at our listening address.
+    AddressChallenge(AddressChallenge),
+    /// `AddressProof` received from a connected peer.
+    AddressProof(PeerId, u64),
+    /// `AddressChallengeRequest` received from a connected peer.
+    AddressChallengeRequest(PeerId),
+    /// `ProtocolDeprecation` received from a connected peer.
+    ProtocolDeprecation(PeerId, ProtocolDeprecation),
+    /// `NetworkAdvisory` for our chain received from a connected peer.
+    NetworkAdvisory(PeerId, NetworkAdvisory),
 }
 
 /// List of all replies to messages to `PeerManager`. See `PeerManagerMessageRequest` for more details.
@@ -80,11 +95,16 @@ pub(crate) struct RegisterPeer {
     pub this_edge_info: Option<PartialEdgeInfo>,
     /// Edge information from other node.
     pub other_edge_info: PartialEdgeInfo,
-    /// Protocol version of new peer. May be higher than ours.
-    #[allow(dead_code)]
+    /// Protocol version negotiated with the new peer.
     pub peer_protocol_version: ProtocolVersion,
     /// A helper data structure for limiting reading, reporting bandwidth stats.
     pub throttle_controller: ThrottleController,
+    /// Offset of the clock of the peer relative to ours, estimated from the time in its
+    /// handshake. None if the peer didn't send it.
+    pub clock_offset: Option<time::Duration>,
+    /// Whether the peer has advertised in its handshake that it is willing to encrypt the
+    /// connection.
+    pub peer_supports_encryption: bool,
 }
 
 #[derive(actix::MessageResponse, Debug)]
@@ -111,6 +131,8 @@ pub struct PeersRequest {}
 #[derive(Debug, actix::MessageResponse)]
 pub struct PeerRequestResult {
     pub peers: Vec<PeerInfo>,
+    /// Our hints about some of the `peers`. Dropped if the requester doesn't support them.
+    pub hints: Vec<PeerHint>,
 }
 
 #[derive(actix::Message)]
@@ -15,7 +15,7 @@ use near_rate_limiter::{ActixMessageResponse, ActixMessageWrapper, ThrottleToken
 use parking_lot::RwLock;
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::warn;
+use tracing::{debug, warn};
 
 /// Actor that maintains routing table information.
 ///
@@ -45,6 +45,9 @@ pub(crate) struct Actor {
     /// Number of edge validations in progress; We will not update routing table as long as
     /// this number is non zero.
     edge_validator_requests_in_progress: u64,
+    /// Maximal number of edges kept in the graph. Above it, the least recently updated
+    /// edges are pruned.
+    max_edges: usize,
 }
 
 impl Actor {
@@ -52,8 +55,13 @@ impl Actor {
         clock: time::Clock,
         store: store::Store,
         graph: Arc<RwLock<routing::GraphWithCache>>,
+        max_edges: usize,
     ) -> Self {
         let my_peer_id = graph.read().my_peer_id();
+        match store.count_components() {
+            Ok(components) => metrics::ROUTING_TABLE_COMPONENTS.set(components as i64),
+            Err(e) => warn!("store.count_components(): {}", e),
+        }
         Self {
             clock,
             my_peer_id,
@@ -63,6 +71,7 @@ impl Actor {
             peers_to_ban: Default::default(),
             edge_validator_requests_in_progress: 0,
             edge_validator_pool: actix::SyncArbiter::start(4, || EdgeValidatorActor {}),
+            max_edges,
         }
     }
 
@@ -84,10 +93,16 @@ impl Actor {
         }
         // Update metrics after edge update
         metrics::EDGE_UPDATES.inc_by(total as u64);
-        metrics::EDGE_ACTIVE.set(self.graph.read().total_active_edges() as i64);
+        self.update_edge_metrics();
         edges
     }
 
+    fn update_edge_metrics(&self) {
+        let graph = self.graph.read();
+        metrics::EDGE_ACTIVE.set(graph.total_active_edges() as i64);
+        metrics::EDGE_TOTAL.set(graph.edges().len() as i64);
+    }
+
     /// If peer_id is not in memory check if it is on disk in bring it back on memory.
     ///
     /// Note: here an advanced example, which shows what's happening.
@@ -123,7 +138,11 @@ impl Actor {
             return;
         }
         let edges = match self.store.pop_component(peer_id) {
-            Ok(edges) => edges,
+            Ok(edges) if edges.is_empty() => return,
+            Ok(edges) => {
+                metrics::ROUTING_TABLE_COMPONENTS.dec();
+                edges
+            }
             Err(e) => {
                 warn!("self.store.pop_component({}): {}", peer_id, e);
                 return;
@@ -169,8 +188,9 @@ impl Actor {
         let edges = self.graph.write().remove_adjacent_edges(&peers);
 
         // Store the pruned data in DB.
-        if let Err(e) = self.store.push_component(&peers, &edges) {
-            warn!("self.store.push_component(): {}", e);
+        match self.store.push_component(&peers, &edges) {
+            Ok(()) => metrics::ROUTING_TABLE_COMPONENTS.inc(),
+            Err(e) => warn!("self.store.push_component(): {}", e),
         }
         edges
     }
@@ -179,6 +199,7 @@ impl Actor {
     /// 1. recomputes the routing table (if needed)
     /// 2. bumps peer_reachable_at to now() for peers which are still reachable.
     /// 3. prunes peers which are unreachable `prune_unreachable_since`.
+    /// 4. prunes the least recently updated edges above `max_edges`.
     /// Returns the new routing table and the pruned edges - adjacent to the pruned peers.
     /// Should be called periodically.
     pub fn update_routing_table(
@@ -200,6 +221,14 @@ impl Actor {
             None => vec![],
             Some(t) => self.prune_unreachable_peers(t),
         };
+        // Edges adjacent to this node are never pruned, so this is not reported in
+        // `pruned_edges`. The routing table is recomputed on the next update.
+        let pruned = self.graph.write().prune_edges(self.max_edges);
+        if !pruned.is_empty() {
+            debug!(target: "network", pruned = pruned.len(), "Pruned edges above the limit");
+            metrics::EDGE_PRUNED.inc_by(pruned.len() as u64);
+        }
+        self.update_edge_metrics();
         (next_hops, pruned_edges)
     }
 }
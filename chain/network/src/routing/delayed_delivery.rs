@@ -0,0 +1,90 @@
+use near_network_primitives::time;
+use near_network_primitives::types::RoutedMessageBody;
+use near_primitives::types::AccountId;
+use std::collections::VecDeque;
+
+/// default value for `capacity`
+const DEFAULT_CAPACITY: usize = 1_000;
+
+/// Message to a validator account which couldn't be routed when it was sent.
+pub(crate) struct DelayedMessage {
+    pub account_id: AccountId,
+    pub body: RoutedMessageBody,
+    queued_at: time::Instant,
+}
+
+/// Number of the messages which were dropped by `DelayedDelivery`, see `DelayedDelivery::take`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Dropped {
+    /// Queued for longer than the TTL.
+    pub expired: usize,
+    /// Evicted to make room for newer messages.
+    pub evicted: usize,
+}
+
+/// Consensus-critical messages to validator accounts, which couldn't be delivered because
+/// there was no route to the account.
+///
+/// A validator is often unreachable only for a moment, e.g. while it reconnects or before
+/// its `AnnounceAccount` reaches us at the start of an epoch. Instead of dropping its
+/// approvals and chunk parts right away, they are kept for `ttl` and retried whenever the
+/// routing table changes. Only the `capacity` most recent messages are kept.
+pub(crate) struct DelayedDelivery {
+    messages: VecDeque<DelayedMessage>,
+    capacity: usize,
+    ttl: time::Duration,
+    /// Messages evicted since the last `take`.
+    evicted: usize,
+}
+
+impl DelayedDelivery {
+    pub fn new(ttl: time::Duration) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, ttl)
+    }
+
+    pub fn with_capacity(capacity: usize, ttl: time::Duration) -> Self {
+        Self { messages: VecDeque::new(), capacity, ttl, evicted: 0 }
+    }
+
+    /// Whether the message is worth delivering late: approvals and chunk parts, without
+    /// which the validator may miss its block or chunk.
+    pub fn is_delayable(body: &RoutedMessageBody) -> bool {
+        matches!(
+            body,
+            RoutedMessageBody::BlockApproval(_)
+                | RoutedMessageBody::PartialEncodedChunk(_)
+                | RoutedMessageBody::VersionedPartialEncodedChunk(_)
+                | RoutedMessageBody::PartialEncodedChunkForward(_)
+        )
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    /// Queues the message, evicting the oldest message if the queue is full.
+    pub fn push(&mut self, account_id: AccountId, body: RoutedMessageBody, now: time::Instant) {
+        if self.messages.len() >= self.capacity {
+            self.messages.pop_front();
+            self.evicted += 1;
+        }
+        self.messages.push_back(DelayedMessage { account_id, body, queued_at: now });
+    }
+
+    /// Takes all the messages queued within the TTL out of the queue, oldest first, and
+    /// drops the others. The messages which still can't be delivered should be put back
+    /// with `requeue`, so that they keep their original deadline.
+    pub fn take(&mut self, now: time::Instant) -> (Vec<DelayedMessage>, Dropped) {
+        let ttl = self.ttl;
+        let (messages, expired): (Vec<_>, Vec<_>) =
+            self.messages.drain(..).partition(|msg| now - msg.queued_at < ttl);
+        let dropped =
+            Dropped { expired: expired.len(), evicted: std::mem::take(&mut self.evicted) };
+        (messages, dropped)
+    }
+
+    /// Puts back a message returned by `take`.
+    pub fn requeue(&mut self, msg: DelayedMessage) {
+        self.messages.push_back(msg);
+    }
+}
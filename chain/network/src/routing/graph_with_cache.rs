@@ -1,5 +1,6 @@
 use crate::routing;
 use crate::stats::metrics;
+use lru::LruCache;
 use near_network_primitives::types::{Edge, EdgeState};
 use near_primitives::network::PeerId;
 use parking_lot::Mutex;
@@ -19,6 +20,9 @@ pub struct GraphWithCache {
     /// Edges of the raw_graph, indexed by Edge::key().
     /// Contains also the edge tombstones.
     edges: HashMap<EdgeKey, Edge>,
+    /// Keys of `edges`, in the order of the last update, for pruning the least recently
+    /// updated ones.
+    edges_lru: LruCache<EdgeKey, ()>,
     /// Peers of this node, which are on any shortest path to the given node.
     /// Derived from graph.
     cached_next_hops: Mutex<Option<Arc<NextHopTable>>>,
@@ -29,6 +33,7 @@ impl GraphWithCache {
         Self {
             graph: routing::Graph::new(my_peer_id),
             edges: Default::default(),
+            edges_lru: LruCache::unbounded(),
             cached_next_hops: Default::default(),
         }
     }
@@ -61,6 +66,7 @@ impl GraphWithCache {
             EdgeState::Removed => self.graph.remove_edge(&key.0, &key.1),
         }
         self.edges.insert(key.clone(), edge);
+        self.edges_lru.put(key.clone(), ());
         // Invalidate cache.
         *self.cached_next_hops.lock() = None;
         true
@@ -74,6 +80,7 @@ impl GraphWithCache {
     /// Removes an edge by key. O(1).
     pub fn remove_edge(&mut self, key: &EdgeKey) {
         if self.edges.remove(key).is_some() {
+            self.edges_lru.pop(key);
             self.graph.remove_edge(&key.0, &key.1);
             *self.cached_next_hops.lock() = None;
         }
@@ -109,4 +116,38 @@ impl GraphWithCache {
         }
         edges
     }
+
+    /// Removes the least recently updated edges until at most `max_edges` are left.
+    /// Edges adjacent to this node are never removed, since they describe our own
+    /// connections. Returns the removed edges.
+    ///
+    /// Unlike the edges of the unreachable peers, the removed edges are not stored in DB:
+    /// they are typically tombstones and edges between peers which have long been gone,
+    /// and if not, peers will send them again.
+    pub fn prune_edges(&mut self, max_edges: usize) -> Vec<Edge> {
+        let my_peer_id = self.my_peer_id();
+        let mut local = vec![];
+        let mut pruned = vec![];
+        while self.edges.len() > max_edges {
+            let key = match self.edges_lru.pop_lru() {
+                Some((key, ())) => key,
+                None => break,
+            };
+            if key.0 == my_peer_id || key.1 == my_peer_id {
+                local.push(key);
+                continue;
+            }
+            if let Some(edge) = self.edges.remove(&key) {
+                self.graph.remove_edge(&key.0, &key.1);
+                pruned.push(edge);
+            }
+        }
+        for key in local {
+            self.edges_lru.put(key, ());
+        }
+        if !pruned.is_empty() {
+            *self.cached_next_hops.lock() = None;
+        }
+        pruned
+    }
 }
@@ -1,4 +1,6 @@
+pub(crate) mod delayed_delivery;
 pub(crate) mod edge_validator_actor;
+pub(crate) mod pending_requests;
 mod route_back_cache;
 pub mod routing_table_view;
 
@@ -0,0 +1,239 @@
+use lru::LruCache;
+use near_network_primitives::time;
+use near_network_primitives::types::{PeerIdOrHash, RoutedMessage, RoutedMessageBody};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::types::ShardId;
+use std::collections::VecDeque;
+
+/// default value for `capacity`
+const DEFAULT_CAPACITY: usize = 10_000;
+/// default value for `timeout`
+const DEFAULT_TIMEOUT: time::Duration = time::Duration::seconds(5);
+
+/// What we expect to find in the body of the response to a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ExpectedBody {
+    TxStatus {
+        tx_hash: CryptoHash,
+    },
+    StateHeader {
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+    },
+    StatePart {
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        part_id: u64,
+    },
+    /// Responses which we don't validate beyond their author.
+    Any,
+}
+
+impl ExpectedBody {
+    fn from_request(body: &RoutedMessageBody) -> Self {
+        match body {
+            RoutedMessageBody::TxStatusRequest(_, tx_hash) => Self::TxStatus { tx_hash: *tx_hash },
+            RoutedMessageBody::StateRequestHeader(shard_id, sync_hash) => {
+                Self::StateHeader { shard_id: *shard_id, sync_hash: *sync_hash }
+            }
+            RoutedMessageBody::StateRequestPart(shard_id, sync_hash, part_id) => {
+                Self::StatePart { shard_id: *shard_id, sync_hash: *sync_hash, part_id: *part_id }
+            }
+            _ => Self::Any,
+        }
+    }
+
+    fn matches(&self, body: &RoutedMessageBody) -> bool {
+        let (shard_id, sync_hash, part) = match body {
+            RoutedMessageBody::TxStatusResponse(outcome) => {
+                return match self {
+                    Self::TxStatus { tx_hash } => &outcome.transaction.hash == tx_hash,
+                    Self::Any => true,
+                    _ => false,
+                };
+            }
+            RoutedMessageBody::StateResponse(info) => {
+                (info.shard_id, info.sync_hash, info.state_response.part_id())
+            }
+            RoutedMessageBody::VersionedStateResponse(info) => {
+                (info.shard_id(), info.sync_hash(), info.part_id())
+            }
+            _ => return true,
+        };
+        match self {
+            Self::StateHeader { shard_id: s, sync_hash: h } => (*s, *h) == (shard_id, sync_hash),
+            // A node which doesn't have the part responds with an empty one.
+            Self::StatePart { shard_id: s, sync_hash: h, part_id } => {
+                (*s, *h) == (shard_id, sync_hash) && part.map_or(true, |p| p == *part_id)
+            }
+            Self::Any => true,
+            Self::TxStatus { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PendingRequest {
+    /// Peer the request was addressed to. Only this peer is allowed to author the response.
+    target: PeerId,
+    expected: ExpectedBody,
+    /// Connected peer the request was sent through.
+    first_hop: PeerId,
+    sent_at: time::Instant,
+    /// Whether we measure the round trip latency of the response.
+    measure_latency: bool,
+    /// Set once the request is answered or times out.
+    answered: bool,
+}
+
+/// Request answered for the first time, see `PendingRequests::answer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct AnsweredRequest {
+    pub target: PeerId,
+    pub first_hop: PeerId,
+    /// Round trip latency, only measured for Pings and chunk requests.
+    pub latency: Option<time::Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr)]
+pub(crate) enum InvalidResponse {
+    /// Response is signed by a peer different than the one the request was sent to.
+    UnexpectedAuthor,
+    /// Response is signed by the right peer, but doesn't answer the request we sent.
+    MismatchedBody,
+}
+
+/// Routed requests sent by this node, which are still waiting for a response.
+///
+/// Responses are routed back to us along the reverse path of the request, so any peer on
+/// that path could try to answer the request on behalf of the target. Every routed message
+/// carries the signature of its author, which is verified by `PeerActor`, so by remembering
+/// which peer we addressed each request to, we can check that the response was produced by
+/// that peer, and that it answers the request we actually sent.
+///
+/// Requests which are expected to be answered (chunk requests and tx status requests) and
+/// which don't get a response within `timeout` are reported by `expire`, so that the routing
+/// table can avoid the route they were sent through.
+pub(crate) struct PendingRequests {
+    requests: LruCache<CryptoHash, PendingRequest>,
+    timeout: time::Duration,
+    /// Requests to check for timeout, in the order they were sent.
+    deadlines: VecDeque<(time::Instant, CryptoHash)>,
+}
+
+impl Default for PendingRequests {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY, DEFAULT_TIMEOUT)
+    }
+}
+
+impl PendingRequests {
+    pub fn new(capacity: usize, timeout: time::Duration) -> Self {
+        Self { requests: LruCache::new(capacity), timeout, deadlines: VecDeque::new() }
+    }
+
+    /// Remember a request authored by this node, sent through the connected peer `first_hop`.
+    /// Requests not addressed to a specific peer are ignored.
+    pub fn insert(&mut self, msg: &RoutedMessage, first_hop: PeerId, now: time::Instant) {
+        if let PeerIdOrHash::PeerId(target) = &msg.target {
+            let measure_latency = matches!(
+                msg.body,
+                RoutedMessageBody::Ping(_) | RoutedMessageBody::PartialEncodedChunkRequest(_)
+            );
+            let expect_answer = matches!(
+                msg.body,
+                RoutedMessageBody::PartialEncodedChunkRequest(_)
+                    | RoutedMessageBody::TxStatusRequest(_, _)
+            );
+            let hash = msg.hash();
+            self.requests.put(
+                hash,
+                PendingRequest {
+                    target: target.clone(),
+                    expected: ExpectedBody::from_request(&msg.body),
+                    first_hop,
+                    sent_at: now,
+                    measure_latency,
+                    answered: false,
+                },
+            );
+            if expect_answer {
+                // Requests evicted from `requests` are skipped by `expire`, so there is no
+                // point in keeping more deadlines than that.
+                if self.deadlines.len() >= self.requests.cap() {
+                    self.deadlines.pop_front();
+                }
+                self.deadlines.push_back((now + self.timeout, hash));
+            }
+        }
+    }
+
+    /// Checks the response routed back to us against the request it responds to.
+    /// Responses to unknown requests (for example already evicted from the cache) are accepted,
+    /// since there is nothing to check them against.
+    ///
+    /// The validation is split between the actors on purpose: `PeerActor` verifies the
+    /// signature of the author in its receive path, before forwarding the message, while
+    /// this check runs in `PeerManagerActor`, which owns the requests. Moving it into
+    /// `PeerActor` would require sharing the requests of all the connections with every
+    /// connection, and a response may arrive over a different connection than the request
+    /// left through.
+    pub fn check_response(&mut self, msg: &RoutedMessage) -> Result<(), InvalidResponse> {
+        let request_hash = match &msg.target {
+            PeerIdOrHash::Hash(hash) => hash,
+            PeerIdOrHash::PeerId(_) => return Ok(()),
+        };
+        let request = match self.requests.get(request_hash) {
+            Some(request) => request,
+            None => return Ok(()),
+        };
+        if request.target != msg.author {
+            return Err(InvalidResponse::UnexpectedAuthor);
+        }
+        if !request.expected.matches(&msg.body) {
+            return Err(InvalidResponse::MismatchedBody);
+        }
+        Ok(())
+    }
+
+    /// Marks the request answered by `msg`. Returns None if the request is unknown, has
+    /// already been answered or has timed out. Should be called after `check_response` accepted the response.
+    pub fn answer(&mut self, msg: &RoutedMessage, now: time::Instant) -> Option<AnsweredRequest> {
+        let request_hash = match &msg.target {
+            PeerIdOrHash::Hash(hash) => hash,
+            PeerIdOrHash::PeerId(_) => return None,
+        };
+        let request = self.requests.get_mut(request_hash)?;
+        if request.answered {
+            return None;
+        }
+        request.answered = true;
+        Some(AnsweredRequest {
+            target: request.target.clone(),
+            first_hop: request.first_hop.clone(),
+            latency: if request.measure_latency { Some(now - request.sent_at) } else { None },
+        })
+    }
+
+    /// Returns the connected peers through which the requests which haven't been answered
+    /// within the timeout were sent. Each request is reported once, and a late response to
+    /// it is not reported by `answer`.
+    pub fn expire(&mut self, now: time::Instant) -> Vec<PeerId> {
+        let mut first_hops = vec![];
+        while let Some((deadline, hash)) = self.deadlines.front() {
+            if *deadline > now {
+                break;
+            }
+            // The request is kept, so that a late response can still be validated.
+            if let Some(request) = self.requests.peek_mut(hash) {
+                if !request.answered {
+                    request.answered = true;
+                    first_hops.push(request.first_hop.clone());
+                }
+            }
+            self.deadlines.pop_front();
+        }
+        first_hops
+    }
+}
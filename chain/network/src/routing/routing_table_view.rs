@@ -13,6 +13,12 @@ use tracing::warn;
 
 const ANNOUNCE_ACCOUNT_CACHE_SIZE: usize = 10_000;
 const LAST_ROUTED_CACHE_SIZE: usize = 10_000;
+const ROUTE_LATENCY_CACHE_SIZE: usize = 10_000;
+const ROUTE_FAILURES_CACHE_SIZE: usize = 1_000;
+/// Route failures older than this are forgotten.
+const ROUTE_FAILURE_TTL: time::Duration = time::Duration::minutes(1);
+/// Weight `1/ROUTE_LATENCY_EMA_WEIGHT` of a new latency sample in the moving average.
+const ROUTE_LATENCY_EMA_WEIGHT: i32 = 4;
 
 pub(crate) struct RoutingTableView {
     my_peer_id: PeerId,
@@ -34,6 +40,12 @@ pub(crate) struct RoutingTableView {
     find_route_calls: u64,
     /// Last time the given peer was selected by find_route_by_peer_id.
     last_routed: LruCache<PeerId, u64>,
+    /// Moving average of the round trip latency of the routed requests (Pings and chunk
+    /// requests) to the given peer.
+    route_latency: LruCache<PeerId, time::Duration>,
+    /// For each connected peer, the number of routed requests sent through it which timed out,
+    /// minus the ones which were answered, and the time of the last timeout.
+    route_failures: LruCache<PeerId, (u32, time::Instant)>,
 }
 
 #[derive(Debug)]
@@ -54,6 +66,8 @@ impl RoutingTableView {
             store,
             find_route_calls: 0,
             last_routed: LruCache::new(LAST_ROUTED_CACHE_SIZE),
+            route_latency: LruCache::new(ROUTE_LATENCY_CACHE_SIZE),
+            route_failures: LruCache::new(ROUTE_FAILURES_CACHE_SIZE),
         }
     }
 
@@ -64,18 +78,55 @@ impl RoutingTableView {
     }
 
     /// Select a connected peer on some shortest path to `peer_id`.
-    /// If there are several such peers, pick the least recently used one.
-    fn find_route_from_peer_id(&mut self, peer_id: &PeerId) -> Result<PeerId, FindRouteError> {
+    /// If there are several such peers, pick the one with the fewest recent route failures,
+    /// and among those the least recently used one.
+    fn find_route_from_peer_id(
+        &mut self,
+        clock: &time::Clock,
+        peer_id: &PeerId,
+    ) -> Result<PeerId, FindRouteError> {
         let peers = self.next_hops.get(peer_id).ok_or(FindRouteError::PeerUnreachable)?;
+        let now = clock.now();
         let next_hop = peers
             .iter()
-            .min_by_key(|p| self.last_routed.get(*p).copied().unwrap_or(0))
+            .min_by_key(|p| {
+                (
+                    Self::route_failures(&self.route_failures, p, now),
+                    self.last_routed.get(*p).copied().unwrap_or(0),
+                )
+            })
             .ok_or(FindRouteError::PeerUnreachable)?;
         self.last_routed.put(next_hop.clone(), self.find_route_calls);
         self.find_route_calls += 1;
         Ok(next_hop.clone())
     }
 
+    fn route_failures(
+        route_failures: &LruCache<PeerId, (u32, time::Instant)>,
+        peer_id: &PeerId,
+        now: time::Instant,
+    ) -> u32 {
+        match route_failures.peek(peer_id) {
+            Some((failures, last_failure)) if now - *last_failure < ROUTE_FAILURE_TTL => *failures,
+            _ => 0,
+        }
+    }
+
+    /// Records that a routed request sent through the connected peer `first_hop` timed out,
+    /// so that alternative routes are preferred for a while.
+    pub(crate) fn record_route_failure(&mut self, clock: &time::Clock, first_hop: &PeerId) {
+        let now = clock.now();
+        let failures = Self::route_failures(&self.route_failures, first_hop, now);
+        self.route_failures.put(first_hop.clone(), (failures.saturating_add(1), now));
+    }
+
+    /// Records that a routed request sent through the connected peer `first_hop` was answered.
+    pub(crate) fn record_route_success(&mut self, first_hop: &PeerId) {
+        if let Some((failures, _)) = self.route_failures.peek_mut(first_hop) {
+            *failures = failures.saturating_sub(1);
+        }
+    }
+
     pub(crate) fn set_next_hops(&mut self, routing_table: Arc<routing::NextHopTable>) {
         self.next_hops = routing_table;
     }
@@ -93,13 +144,36 @@ impl RoutingTableView {
         target: &PeerIdOrHash,
     ) -> Result<PeerId, FindRouteError> {
         match target {
-            PeerIdOrHash::PeerId(peer_id) => self.find_route_from_peer_id(peer_id),
+            PeerIdOrHash::PeerId(peer_id) => self.find_route_from_peer_id(clock, peer_id),
             PeerIdOrHash::Hash(hash) => {
                 self.fetch_route_back(clock, *hash).ok_or(FindRouteError::RouteBackNotFound)
             }
         }
     }
 
+    /// Updates the moving average of the round trip latency to `peer_id` with a new sample.
+    pub(crate) fn record_route_latency(&mut self, peer_id: &PeerId, latency: time::Duration) {
+        let avg = match self.route_latency.get(peer_id) {
+            Some(avg) => *avg + (latency - *avg) / ROUTE_LATENCY_EMA_WEIGHT,
+            None => latency,
+        };
+        self.route_latency.put(peer_id.clone(), avg);
+    }
+
+    /// Moving average of the round trip latency to `peer_id`, if any request has been answered.
+    pub(crate) fn route_latency(&self, peer_id: &PeerId) -> Option<time::Duration> {
+        self.route_latency.peek(peer_id).copied()
+    }
+
+    /// Moving average of the round trip latency to the peers owning the known accounts.
+    pub(crate) fn account_route_latencies(
+        &self,
+    ) -> impl Iterator<Item = (&AccountId, time::Duration)> + '_ {
+        self.get_announce_accounts().filter_map(|announce_account| {
+            Some((&announce_account.account_id, self.route_latency(&announce_account.peer_id)?))
+        })
+    }
+
     pub(crate) fn view_route(&self, peer_id: &PeerId) -> Option<&Vec<PeerId>> {
         self.next_hops.get(peer_id)
     }
@@ -158,7 +232,12 @@ impl RoutingTableView {
                 (announce_account.account_id.clone(), announce_account.peer_id.clone())
             })
             .collect();
-        RoutingTableInfo { account_peers, next_hops: self.next_hops.clone() }
+        let route_latency = self
+            .route_latency
+            .iter()
+            .map(|(peer_id, latency)| (peer_id.clone(), *latency))
+            .collect();
+        RoutingTableInfo { account_peers, next_hops: self.next_hops.clone(), route_latency }
     }
 
     /// Public interface for `account_peers`.
@@ -214,4 +293,6 @@ impl RoutingTableView {
 pub struct RoutingTableInfo {
     pub account_peers: HashMap<AccountId, PeerId>,
     pub next_hops: Arc<routing::NextHopTable>,
+    /// Moving average of the round trip latency of the routed requests, per target peer.
+    pub route_latency: HashMap<PeerId, time::Duration>,
 }
@@ -53,10 +53,15 @@ impl RoutingTableTest {
     }
 
     fn new_actor(&self) -> routing::actor::Actor {
+        self.new_actor_with_max_edges(usize::MAX)
+    }
+
+    fn new_actor_with_max_edges(&self, max_edges: usize) -> routing::actor::Actor {
         routing::actor::Actor::new(
             self.clock.clock(),
             store::Store::from(&self.store),
             self.graph.clone(),
+            max_edges,
         )
     }
 
@@ -209,3 +214,33 @@ fn components_nonces_are_tracked_in_storage() {
         ],
     );
 }
+
+#[test]
+fn prune_edges_above_limit() {
+    let mut test = RoutingTableTest::new();
+    let mut actor = test.new_actor_with_max_edges(3);
+    let p1 = test.make_peer();
+    let p2 = test.make_peer();
+    let p3 = test.make_peer();
+    let e1 = edge(&test.me(), &p1, 1);
+    let e12 = edge(&p1, &p2, 1);
+    let e13 = edge(&p1, &p3, 1);
+    let e23 = edge(&p2, &p3, 1);
+    let e12v2 = edge(&p1, &p2, 2);
+    let e13v2 = edge(&p1, &p3, 2);
+
+    actor.add_verified_edges(vec![e1.clone(), e12.clone(), e13.clone()]);
+    actor.update_routing_table(None);
+    test.check(&[e1.clone(), e12.clone(), e13.clone()], &[]);
+
+    // The least recently updated edge is pruned, except for our own edge.
+    actor.add_verified_edges(vec![e23.clone()]);
+    actor.update_routing_table(None);
+    test.check(&[e1.clone(), e13.clone(), e23.clone()], &[]);
+
+    // Updating an edge makes it the most recently updated one.
+    actor.add_verified_edges(vec![e13v2.clone()]);
+    actor.add_verified_edges(vec![e12v2.clone()]);
+    actor.update_routing_table(None);
+    test.check(&[e1.clone(), e13v2.clone(), e12v2.clone()], &[]);
+}
@@ -0,0 +1,91 @@
+use crate::network_protocol::testonly as data;
+use crate::routing::delayed_delivery::{DelayedDelivery, DelayedMessage, Dropped};
+use crate::testonly::make_rng;
+use near_network_primitives::time;
+use near_network_primitives::types::{Ping, RoutedMessageBody};
+use near_primitives::network::PeerId;
+
+fn ping(nonce: u64) -> RoutedMessageBody {
+    let source = PeerId::new(data::make_signer(&mut make_rng(1)).public_key);
+    RoutedMessageBody::Ping(Ping { nonce, source })
+}
+
+fn nonces(messages: &[DelayedMessage]) -> Vec<u64> {
+    messages
+        .iter()
+        .map(|msg| match &msg.body {
+            RoutedMessageBody::Ping(ping) => ping.nonce,
+            _ => panic!("unexpected message"),
+        })
+        .collect()
+}
+
+#[test]
+fn expire_after_ttl() {
+    let mut rng = make_rng(89028037453);
+    let account_id = data::make_account_id(&mut rng);
+    let clock = time::FakeClock::default();
+    let mut delayed = DelayedDelivery::new(time::Duration::seconds(2));
+
+    delayed.push(account_id.clone(), ping(0), clock.now());
+    clock.advance(time::Duration::seconds(1));
+    delayed.push(account_id.clone(), ping(1), clock.now());
+    clock.advance(time::Duration::seconds(1));
+
+    let (messages, dropped) = delayed.take(clock.now());
+    assert_eq!(vec![1], nonces(&messages));
+    assert_eq!(Dropped { expired: 1, evicted: 0 }, dropped);
+    assert!(delayed.is_empty());
+
+    // A requeued message keeps its original deadline.
+    for msg in messages {
+        delayed.requeue(msg);
+    }
+    clock.advance(time::Duration::seconds(1));
+    let (messages, dropped) = delayed.take(clock.now());
+    assert!(messages.is_empty());
+    assert_eq!(Dropped { expired: 1, evicted: 0 }, dropped);
+}
+
+#[test]
+fn evict_oldest_when_full() {
+    let mut rng = make_rng(89028037453);
+    let account_id = data::make_account_id(&mut rng);
+    let clock = time::FakeClock::default();
+    let mut delayed = DelayedDelivery::with_capacity(2, time::Duration::seconds(2));
+
+    for nonce in 0..5 {
+        delayed.push(account_id.clone(), ping(nonce), clock.now());
+    }
+    let (messages, dropped) = delayed.take(clock.now());
+    assert_eq!(vec![3, 4], nonces(&messages));
+    assert_eq!(Dropped { expired: 0, evicted: 3 }, dropped);
+
+    // Evictions are reported only once.
+    let (_, dropped) = delayed.take(clock.now());
+    assert_eq!(Dropped::default(), dropped);
+}
+
+#[test]
+fn requeue_keeps_order() {
+    let mut rng = make_rng(89028037453);
+    let account_id = data::make_account_id(&mut rng);
+    let clock = time::FakeClock::default();
+    let mut delayed = DelayedDelivery::new(time::Duration::seconds(2));
+
+    for nonce in 0..3 {
+        delayed.push(account_id.clone(), ping(nonce), clock.now());
+    }
+    let (messages, _) = delayed.take(clock.now());
+    for msg in messages {
+        delayed.requeue(msg);
+    }
+    delayed.push(account_id, ping(3), clock.now());
+    let (messages, _) = delayed.take(clock.now());
+    assert_eq!(vec![0, 1, 2, 3], nonces(&messages));
+}
+
+#[test]
+fn delayable_messages() {
+    assert!(!DelayedDelivery::is_delayable(&ping(0)));
+}
@@ -1,3 +1,5 @@
 mod cache;
 mod cache_edges;
+mod delayed_delivery;
+mod pending_requests;
 mod routing_table_view;
@@ -0,0 +1,212 @@
+use crate::network_protocol::testonly as data;
+use crate::routing::pending_requests::{InvalidResponse, PendingRequests};
+use crate::testonly::make_rng;
+use near_crypto::InMemorySigner;
+use near_network_primitives::time;
+use near_network_primitives::types::{
+    AccountOrPeerIdOrHash, Ping, Pong, RawRoutedMessage, RoutedMessageBody, RoutedMessageV2,
+    StateResponseInfoV1,
+};
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use near_primitives::syncing::ShardStateSyncResponseV1;
+
+fn sign(
+    signer: &InMemorySigner,
+    target: AccountOrPeerIdOrHash,
+    body: RoutedMessageBody,
+) -> Box<RoutedMessageV2> {
+    RawRoutedMessage { target, body }.sign(
+        PeerId::new(signer.public_key.clone()),
+        &signer.secret_key,
+        /*ttl=*/ 1,
+        None,
+    )
+}
+
+fn hop() -> PeerId {
+    PeerId::new(data::make_signer(&mut make_rng(1)).public_key)
+}
+
+fn state_response(shard_id: u64, sync_hash: CryptoHash, part_id: Option<u64>) -> RoutedMessageBody {
+    RoutedMessageBody::StateResponse(StateResponseInfoV1 {
+        shard_id,
+        sync_hash,
+        state_response: ShardStateSyncResponseV1 {
+            header: None,
+            part: part_id.map(|id| (id, vec![])),
+        },
+    })
+}
+
+#[test]
+fn check_state_part_response() {
+    let mut rng = make_rng(53243);
+    let rng = &mut rng;
+    let me = data::make_signer(rng);
+    let target = data::make_signer(rng);
+    let other = data::make_signer(rng);
+    let sync_hash = CryptoHash([7; 32]);
+    let clock = time::FakeClock::default();
+
+    let mut pending = PendingRequests::default();
+    let request = sign(
+        &me,
+        AccountOrPeerIdOrHash::PeerId(PeerId::new(target.public_key.clone())),
+        RoutedMessageBody::StateRequestPart(3, sync_hash, 5),
+    );
+    pending.insert(&request.msg, hop(), clock.now());
+    let route_back = AccountOrPeerIdOrHash::Hash(request.hash());
+
+    let ok = sign(&target, route_back.clone(), state_response(3, sync_hash, Some(5)));
+    assert_eq!(Ok(()), pending.check_response(&ok.msg));
+    // Peer which doesn't have the part responds with an empty one.
+    let empty = sign(&target, route_back.clone(), state_response(3, sync_hash, None));
+    assert_eq!(Ok(()), pending.check_response(&empty.msg));
+
+    let forged = sign(&other, route_back.clone(), state_response(3, sync_hash, Some(5)));
+    assert_eq!(Err(InvalidResponse::UnexpectedAuthor), pending.check_response(&forged.msg));
+    let wrong_part = sign(&target, route_back.clone(), state_response(3, sync_hash, Some(6)));
+    assert_eq!(Err(InvalidResponse::MismatchedBody), pending.check_response(&wrong_part.msg));
+    let wrong_shard = sign(&target, route_back, state_response(4, sync_hash, Some(5)));
+    assert_eq!(Err(InvalidResponse::MismatchedBody), pending.check_response(&wrong_shard.msg));
+}
+
+#[test]
+fn unknown_request_is_accepted() {
+    let mut rng = make_rng(97234);
+    let rng = &mut rng;
+    let signer = data::make_signer(rng);
+    let mut pending = PendingRequests::default();
+    let response = sign(
+        &signer,
+        AccountOrPeerIdOrHash::Hash(CryptoHash([1; 32])),
+        state_response(0, CryptoHash::default(), None),
+    );
+    assert_eq!(Ok(()), pending.check_response(&response.msg));
+}
+
+#[test]
+fn evicted_request_is_accepted() {
+    let mut rng = make_rng(3254);
+    let rng = &mut rng;
+    let me = data::make_signer(rng);
+    let other = data::make_signer(rng);
+    let clock = time::FakeClock::default();
+    let mut pending = PendingRequests::new(1, time::Duration::seconds(5));
+    let make_request = |shard_id| {
+        sign(
+            &me,
+            AccountOrPeerIdOrHash::PeerId(PeerId::new(me.public_key.clone())),
+            RoutedMessageBody::StateRequestHeader(shard_id, CryptoHash::default()),
+        )
+    };
+    let first = make_request(0);
+    pending.insert(&first.msg, hop(), clock.now());
+    pending.insert(&make_request(1).msg, hop(), clock.now());
+    let response = sign(
+        &other,
+        AccountOrPeerIdOrHash::Hash(first.hash()),
+        state_response(0, CryptoHash::default(), None),
+    );
+    assert_eq!(Ok(()), pending.check_response(&response.msg));
+}
+
+#[test]
+fn ping_round_trip_latency() {
+    let mut rng = make_rng(8743);
+    let rng = &mut rng;
+    let me = data::make_signer(rng);
+    let target = data::make_signer(rng);
+    let target_id = PeerId::new(target.public_key.clone());
+    let clock = time::FakeClock::default();
+    let mut pending = PendingRequests::default();
+
+    let ping = sign(
+        &me,
+        AccountOrPeerIdOrHash::PeerId(target_id.clone()),
+        RoutedMessageBody::Ping(Ping { nonce: 1, source: PeerId::new(me.public_key.clone()) }),
+    );
+    pending.insert(&ping.msg, hop(), clock.now());
+    clock.advance(time::Duration::milliseconds(150));
+    let pong = sign(
+        &target,
+        AccountOrPeerIdOrHash::Hash(ping.hash()),
+        RoutedMessageBody::Pong(Pong { nonce: 1, source: target_id.clone() }),
+    );
+    assert_eq!(Ok(()), pending.check_response(&pong.msg));
+    let answered = pending.answer(&pong.msg, clock.now()).unwrap();
+    assert_eq!(target_id, answered.target);
+    assert_eq!(Some(time::Duration::milliseconds(150)), answered.latency);
+    // Only the first response is reported.
+    assert_eq!(None, pending.answer(&pong.msg, clock.now()));
+
+    // Latency of other requests is not measured.
+    let request = sign(
+        &me,
+        AccountOrPeerIdOrHash::PeerId(target_id),
+        RoutedMessageBody::StateRequestHeader(0, CryptoHash::default()),
+    );
+    pending.insert(&request.msg, hop(), clock.now());
+    let response = sign(
+        &target,
+        AccountOrPeerIdOrHash::Hash(request.hash()),
+        state_response(0, CryptoHash::default(), None),
+    );
+    assert_eq!(None, pending.answer(&response.msg, clock.now()).unwrap().latency);
+}
+
+#[test]
+fn unanswered_requests_expire() {
+    let mut rng = make_rng(4321);
+    let rng = &mut rng;
+    let me = data::make_signer(rng);
+    let target = data::make_signer(rng);
+    let target_id = PeerId::new(target.public_key.clone());
+    let hop1 = PeerId::new(data::make_signer(rng).public_key);
+    let hop2 = PeerId::new(data::make_signer(rng).public_key);
+    let clock = time::FakeClock::default();
+    let timeout = time::Duration::seconds(5);
+    let mut pending = PendingRequests::new(100, timeout);
+    let tx_status = |nonce| {
+        sign(
+            &me,
+            AccountOrPeerIdOrHash::PeerId(target_id.clone()),
+            RoutedMessageBody::TxStatusRequest("test".parse().unwrap(), CryptoHash([nonce; 32])),
+        )
+    };
+
+    let answered = tx_status(1);
+    pending.insert(&answered.msg, hop1.clone(), clock.now());
+    let unanswered = tx_status(2);
+    pending.insert(&unanswered.msg, hop2.clone(), clock.now());
+    // Requests which may legitimately stay unanswered are not tracked.
+    let state_request = sign(
+        &me,
+        AccountOrPeerIdOrHash::PeerId(target_id.clone()),
+        RoutedMessageBody::StateRequestHeader(0, CryptoHash::default()),
+    );
+    pending.insert(&state_request.msg, hop1.clone(), clock.now());
+
+    let response = sign(
+        &target,
+        AccountOrPeerIdOrHash::Hash(answered.hash()),
+        RoutedMessageBody::Pong(Pong { nonce: 1, source: target_id.clone() }),
+    );
+    assert_eq!(hop1, pending.answer(&response.msg, clock.now()).unwrap().first_hop);
+
+    clock.advance(timeout - time::Duration::milliseconds(1));
+    assert_eq!(Vec::<PeerId>::new(), pending.expire(clock.now()));
+    clock.advance(time::Duration::milliseconds(1));
+    assert_eq!(vec![hop2], pending.expire(clock.now()));
+    assert_eq!(Vec::<PeerId>::new(), pending.expire(clock.now()));
+
+    // A late response is still validated, but not reported as an answer.
+    let late = sign(
+        &target,
+        AccountOrPeerIdOrHash::Hash(unanswered.hash()),
+        RoutedMessageBody::Pong(Pong { nonce: 2, source: target_id }),
+    );
+    assert_eq!(Ok(()), pending.check_response(&late.msg));
+    assert_eq!(None, pending.answer(&late.msg, clock.now()));
+}
@@ -7,6 +7,7 @@ use near_network_primitives::time;
 use near_network_primitives::types::PeerIdOrHash;
 use near_store::test_utils::create_test_store;
 use rand::seq::SliceRandom;
+use std::collections::HashSet;
 use std::sync::Arc;
 
 #[test]
@@ -34,3 +35,40 @@ fn find_route() {
         assert!(next_hops.get(p).unwrap().contains(&got));
     }
 }
+
+#[test]
+fn avoid_failing_routes() {
+    let mut rng = make_rng(9472035);
+    let clock = time::FakeClock::default();
+    let rng = &mut rng;
+    let store = create_test_store();
+    let store = store::Store::from(&store);
+
+    let target = data::make_peer_id(rng);
+    let hop1 = data::make_peer_id(rng);
+    let hop2 = data::make_peer_id(rng);
+    let mut next_hops = routing::NextHopTable::new();
+    next_hops.insert(target.clone(), vec![hop1.clone(), hop2.clone()]);
+
+    let mut rtv = RoutingTableView::new(store, data::make_peer_id(rng));
+    rtv.set_next_hops(Arc::new(next_hops));
+    let find_route = |rtv: &mut RoutingTableView| {
+        rtv.find_route(&clock.clock(), &PeerIdOrHash::PeerId(target.clone())).unwrap()
+    };
+
+    rtv.record_route_failure(&clock.clock(), &hop1);
+    for _ in 0..3 {
+        assert_eq!(hop2, find_route(&mut rtv));
+    }
+    // Once the failure is balanced by a success, both hops are used again.
+    rtv.record_route_success(&hop1);
+    let hops: HashSet<_> = (0..2).map(|_| find_route(&mut rtv)).collect();
+    assert_eq!(2, hops.len());
+
+    // Failures are forgotten after a while.
+    rtv.record_route_failure(&clock.clock(), &hop2);
+    assert_eq!(hop1, find_route(&mut rtv));
+    clock.advance(time::Duration::minutes(1));
+    let hops: HashSet<_> = (0..2).map(|_| find_route(&mut rtv)).collect();
+    assert_eq!(2, hops.len());
+}
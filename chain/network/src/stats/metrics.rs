@@ -1,8 +1,9 @@
-use crate::network_protocol::Encoding;
+use crate::network_protocol::{Encoding, PeerMessage};
 use near_metrics::{
-    exponential_buckets, try_create_histogram, try_create_histogram_vec, try_create_int_counter,
-    try_create_int_counter_vec, try_create_int_gauge, Histogram, HistogramVec, IntCounter,
-    IntCounterVec, IntGauge, IntGaugeVec,
+    exponential_buckets, try_create_gauge, try_create_gauge_vec, try_create_histogram,
+    try_create_histogram_vec, try_create_int_counter, try_create_int_counter_vec,
+    try_create_int_gauge, Gauge, GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec,
 };
 use near_network_primitives::types::{PeerType, RoutedMessageBody};
 use once_cell::sync::Lazy;
@@ -28,6 +29,212 @@ pub(crate) fn set_peer_connections(values: HashMap<(PeerType, Option<Encoding>),
 pub(crate) static PEER_CONNECTIONS_TOTAL: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_peer_connections_total", "Number of connected peers").unwrap()
 });
+pub(crate) static PEER_KEEP_ALIVE_TIMEOUTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_peer_keep_alive_timeouts_total",
+        "Number of connections closed because nothing was received from the peer for too long",
+    )
+    .unwrap()
+});
+pub(crate) static PEER_ENCRYPTION_HANDSHAKES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_encryption_handshakes_total",
+        "Number of encryption handshakes with peers, by result: ok, error or downgrade",
+        &["result"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_ENCRYPTION_PENDING_MESSAGES_DROPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_encryption_pending_messages_dropped_total",
+        "Number of messages dropped while waiting for the encryption handshake, by message type",
+        &["type"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_CHAIN_INFO_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_chain_info_rejected_total",
+        "Number of peers rejected because of the chain info they advertised, by reason",
+        &["reason"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_INBOUND_CONNECTIONS_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_inbound_connections_rejected_total",
+        "Number of inbound connections rejected before the handshake, by reason",
+        &["reason"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_CONNECTION_RTT: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_connection_rtt_seconds",
+        "Round-trip time of the new connections with peers which the handshake timeout is derived from, by how it was measured",
+        &["source"],
+        Some(exponential_buckets(0.0005, 2.0, 14).unwrap()),
+    )
+    .unwrap()
+});
+pub(crate) static PEER_HANDSHAKE_TIMEOUT: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_handshake_timeout_seconds",
+        "Time given to the new connections with peers to complete the handshake, by peer type",
+        &["peer_type"],
+        Some(exponential_buckets(0.5, 1.5, 14).unwrap()),
+    )
+    .unwrap()
+});
+pub(crate) static PEER_HANDSHAKE_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_handshake_duration_seconds",
+        "Time from creating the connection until the peer is registered, by peer type",
+        &["peer_type"],
+        Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+pub(crate) static PEER_HANDSHAKE_TIMEOUTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_handshake_timeouts_total",
+        "Number of connections closed because the handshake timed out, by peer type and by what it was waiting for",
+        &["peer_type", "cause"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_SHARD_ADMISSION: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_shard_admission_total",
+        "Number of inbound peers rejected after the handshake for lack of slots or accepted in place of a peer tracking none of our shards, by outcome",
+        &["outcome"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_DIVERSITY_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_diversity_rejected_total",
+        "Number of connections rejected to keep the peers diverse, by direction and reason",
+        &["direction", "reason"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_ADDRESS_CHALLENGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_address_challenges_total",
+        "Number of challenges sent to the listening addresses advertised by inbound peers, by result",
+        &["result"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_errors_total",
+        "Number of failures to handle the messages received from peers, by kind",
+        &["kind"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_ERRORS_BY_PEER: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_peer_errors_by_peer",
+        "Number of failures to handle the messages received from each connected peer since it connected, by kind",
+        &["peer_id", "kind"],
+    )
+    .unwrap()
+});
+pub(crate) static NETWORK_ADVISORIES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_network_advisories_total",
+        "Number of network advisories received from peers or the operator, by result",
+        &["result"],
+    )
+    .unwrap()
+});
+pub(crate) static ROUTED_MESSAGE_DEDUP: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_routed_message_dedup_total",
+        "Number of routed messages looked up among the recently received ones, by result: hit for the duplicates, miss otherwise",
+        &["result"],
+    )
+    .unwrap()
+});
+pub(crate) static LISTEN_ADDR_REACHABLE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_listen_addr_reachable",
+        "Whether the listening address of the node was reachable by a peer in the last check",
+    )
+    .unwrap()
+});
+pub(crate) static TIER1_PEERS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_tier1_peers",
+        "Number of other block and chunk producers whose peer id is known",
+    )
+    .unwrap()
+});
+pub(crate) static TIER1_PEERS_CONNECTED: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_tier1_peers_connected",
+        "Number of other block and chunk producers the node is directly connected to",
+    )
+    .unwrap()
+});
+pub(crate) static PEER_MESSAGES_THROTTLED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_messages_throttled_total",
+        "Number of bulk messages delayed or dropped by the outbound bandwidth limits, by message type",
+        &["type", "result"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_DOUBLE_ENCODED_MESSAGES: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_peer_double_encoded_messages_total",
+        "Number of messages sent in both encodings, because the encoding of the peer was unknown",
+        &["type"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_DOUBLE_ENCODED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_peer_double_encoded_bytes_total",
+        "Bytes of the Borsh copies of the messages sent in both encodings",
+    )
+    .unwrap()
+});
+pub(crate) static PEER_BORSH_HANDSHAKES_REFUSED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_peer_borsh_handshakes_refused_total",
+        "Number of Borsh-encoded handshakes refused, because only protobuf is supported",
+    )
+    .unwrap()
+});
+pub(crate) static PEER_UNKNOWN_ENCODING_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_unknown_encoding_duration_seconds",
+        "Time from creating the connection until the encoding of the peer is known, by the encoding",
+        &["encoding"],
+        Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+    )
+    .unwrap()
+});
+pub(crate) static PEER_SEND_QUEUE_MESSAGES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_peer_send_queue_messages",
+        "Number of messages waiting to be sent to peers, by priority",
+        &["priority"],
+    )
+    .unwrap()
+});
+pub(crate) static PEER_SEND_QUEUE_BYTES: Lazy<IntGaugeVec> = Lazy::new(|| {
+    near_metrics::try_create_int_gauge_vec(
+        "near_peer_send_queue_bytes",
+        "Total size of messages waiting to be sent to peers, by priority",
+        &["priority"],
+    )
+    .unwrap()
+});
 pub(crate) static PEER_DATA_RECEIVED_BYTES: Lazy<IntCounter> = Lazy::new(|| {
     try_create_int_counter("near_peer_data_received_bytes", "Total data received from peers")
         .unwrap()
@@ -100,6 +307,27 @@ pub(crate) static EDGE_UPDATES: Lazy<IntCounter> =
 pub(crate) static EDGE_ACTIVE: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge("near_edge_active", "Total edges active between peers").unwrap()
 });
+pub(crate) static EDGE_TOTAL: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_edge_total",
+        "Total edges kept in memory by the routing table, including the removed ones",
+    )
+    .unwrap()
+});
+pub(crate) static EDGE_PRUNED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_edge_pruned_total",
+        "Number of edges pruned from the routing table to keep it within the size limit",
+    )
+    .unwrap()
+});
+pub(crate) static ROUTING_TABLE_COMPONENTS: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_routing_table_components",
+        "Number of components of unreachable peers stored in DB",
+    )
+    .unwrap()
+});
 pub(crate) static PEER_REACHABLE: Lazy<IntGauge> = Lazy::new(|| {
     try_create_int_gauge(
         "near_peer_reachable",
@@ -150,12 +378,87 @@ pub(crate) static NETWORK_ROUTED_MSG_LATENCY: Lazy<HistogramVec> = Lazy::new(||
     .unwrap()
 });
 
+pub(crate) static ROUTED_REQUEST_ROUND_TRIP_LATENCY: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_routed_request_round_trip_latency",
+        "Time between sending a routed request (Ping or chunk request) and receiving its response",
+        &["type"],
+        Some(exponential_buckets(0.001, 1.6, 20).unwrap()),
+    )
+    .unwrap()
+});
+
+pub(crate) static ROUTED_REQUEST_TIMEOUTS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_routed_request_timeouts_total",
+        "Number of routed requests (chunk and tx status requests) which weren't answered in time",
+    )
+    .unwrap()
+});
+
+pub(crate) static ACCOUNT_ROUTE_LATENCY: Lazy<GaugeVec> = Lazy::new(|| {
+    try_create_gauge_vec(
+        "near_account_route_latency",
+        "Moving average of the round trip latency of routed requests to the peer owning the account",
+        &["account_id"],
+    )
+    .unwrap()
+});
+
+pub(crate) static ROUTED_RESPONSE_REJECTED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_routed_response_rejected_total",
+        "Number of routed responses rejected because they don't match the request sent by this node",
+        &["type", "reason"],
+    )
+    .unwrap()
+});
+
+pub(crate) static DELAYED_DELIVERY: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_delayed_delivery_messages_total",
+        "Number of approvals and chunk parts to unreachable validators queued for a later delivery, by outcome: queued, delivered, expired or evicted",
+        &["outcome"],
+    )
+    .unwrap()
+});
+
+pub(crate) static PEER_CLOCK_OFFSET: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_peer_clock_offset_seconds",
+        "Offset of the clocks of the peers relative to the local clock, estimated from their handshake or from their answer to a Ping",
+        &["source"],
+        Some(vec![
+            -60., -10., -5., -2., -1., -0.5, -0.1, 0., 0.1, 0.5, 1., 2., 5., 10., 60.,
+        ]),
+    )
+    .unwrap()
+});
+
+pub(crate) static CLOCK_SKEW: Lazy<Gauge> = Lazy::new(|| {
+    try_create_gauge(
+        "near_clock_skew_seconds",
+        "Skew of the local clock relative to the median clock of the connected peers, positive if the local clock is ahead",
+    )
+    .unwrap()
+});
+
 #[derive(Clone, Copy, strum::AsRefStr)]
 pub(crate) enum MessageDropped {
     NoRouteFound,
     UnknownAccount,
     InputTooLong,
     MaxCapacityExceeded,
+    /// Transaction received after too many transactions since the last block.
+    TooManyTransactions,
+    /// Routed message received recently from some peer already.
+    DuplicateRouted,
+    /// Message which the peer mustn't send in the current state of the connection.
+    UnexpectedMessage,
+    /// Message which couldn't be decoded.
+    ParseError,
+    /// Message received after the peer has been banned.
+    PeerBanned,
 }
 
 impl MessageDropped {
@@ -163,11 +466,15 @@ impl MessageDropped {
         self.inc_msg_type(msg.into())
     }
 
+    pub fn inc_peer_msg(self, msg: &PeerMessage) {
+        self.inc_msg_type(msg.msg_variant())
+    }
+
     pub fn inc_unknown_msg(self) {
         self.inc_msg_type("unknown")
     }
 
-    fn inc_msg_type(self, msg_type: &str) {
+    pub fn inc_msg_type(self, msg_type: &str) {
         let reason = self.as_ref();
         DROPPED_MESSAGE_COUNT.with_label_values(&[msg_type, reason]).inc();
     }
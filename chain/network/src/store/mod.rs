@@ -1,7 +1,9 @@
 /// Store module defines atomic DB operations on top of schema module.
 /// All transactions should be implemented within this module,
 /// in particular schema::StoreUpdate is not exported.
-use near_network_primitives::types::{Edge, KnownPeerState};
+use crate::network_protocol::RoutedMessageKey;
+use near_network_primitives::time;
+use near_network_primitives::types::{AccessRule, BlacklistEntry, Edge, KnownPeerState};
 use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::types::AccountId;
 use std::collections::HashSet;
@@ -114,6 +116,11 @@ impl Store {
         self.0.commit(update).map_err(Error)?;
         Ok(edges)
     }
+
+    /// Number of components stored in DB.
+    pub fn count_components(&self) -> Result<usize, Error> {
+        self.0.count::<schema::ComponentEdges>().map_err(Error)
+    }
 }
 
 // PeerStore storage.
@@ -142,6 +149,44 @@ impl Store {
     }
 }
 
+// Operator-managed access rules storage.
+impl Store {
+    /// Inserts (entry,rule) to the PeerAccessRules column.
+    pub fn set_access_rule(
+        &mut self,
+        entry: &BlacklistEntry,
+        rule: &AccessRule,
+    ) -> Result<(), Error> {
+        let mut update = self.0.new_update();
+        update.set::<schema::PeerAccessRules>(entry, rule);
+        self.0.commit(update).map_err(Error)
+    }
+
+    /// Reads the whole PeerAccessRules column.
+    pub fn list_access_rules(&self) -> Result<Vec<(BlacklistEntry, AccessRule)>, Error> {
+        self.0.iter::<schema::PeerAccessRules>().collect::<Result<_, _>>().map_err(Error)
+    }
+}
+
+// Routed messages deduplication storage.
+impl Store {
+    /// Replaces the content of the RoutedMessages column with <messages>.
+    pub fn set_routed_messages(
+        &mut self,
+        messages: &[(RoutedMessageKey, time::Utc)],
+    ) -> Result<(), Error> {
+        let mut update = self.0.new_update();
+        update.delete_all::<schema::RoutedMessages>();
+        messages.iter().for_each(|(k, t)| update.set::<schema::RoutedMessages>(k, t));
+        self.0.commit(update).map_err(Error)
+    }
+
+    /// Reads the whole RoutedMessages column.
+    pub fn list_routed_messages(&self) -> Result<Vec<(RoutedMessageKey, time::Utc)>, Error> {
+        self.0.iter::<schema::RoutedMessages>().collect::<Result<_, _>>().map_err(Error)
+    }
+}
+
 impl From<near_store::Store> for Store {
     fn from(store: near_store::Store) -> Self {
         Self(schema::Store::new(store.into_inner()))
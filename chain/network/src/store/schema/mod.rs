@@ -1,6 +1,7 @@
 /// Schema module defines a type-safe access to the DB.
 /// It is a concise definition of key and value types
 /// of the DB columns. For high level access see store.rs.
+use crate::network_protocol::RoutedMessageKey;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_crypto::Signature;
 use near_network_primitives::time;
@@ -24,6 +25,21 @@ impl Format for AccountIdFormat {
     }
 }
 
+/// Blacklist entries are stored in their text form, as it appears in the config.
+pub struct BlacklistEntryFormat;
+impl Format for BlacklistEntryFormat {
+    type T = primitives::BlacklistEntry;
+    fn encode<W: io::Write>(a: &primitives::BlacklistEntry, w: &mut W) -> io::Result<()> {
+        w.write_all(a.to_string().as_bytes())
+    }
+    fn decode(a: &[u8]) -> Result<primitives::BlacklistEntry, Error> {
+        std::str::from_utf8(a)
+            .map_err(invalid_data)?
+            .parse()
+            .map_err(|err: anyhow::Error| Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
 #[derive(BorshSerialize, BorshDeserialize)]
 enum KnownPeerStatus {
     Unknown,
@@ -164,6 +180,20 @@ impl Column for LastComponentNonce {
     type Value = Borsh<u64>;
 }
 
+pub struct PeerAccessRules;
+impl Column for PeerAccessRules {
+    const COL: DBCol = DBCol::PeerAccessRules;
+    type Key = BlacklistEntryFormat;
+    type Value = Borsh<primitives::AccessRule>;
+}
+
+pub struct RoutedMessages;
+impl Column for RoutedMessages {
+    const COL: DBCol = DBCol::RoutedMessages;
+    type Key = Borsh<RoutedMessageKey>;
+    type Value = UtcNanos;
+}
+
 ////////////////////////////////////////////////////
 // Storage
 
@@ -248,6 +278,18 @@ impl Format for U64LE {
     }
 }
 
+/// UNIX timestamp in nanos, little endian.
+pub struct UtcNanos;
+impl Format for UtcNanos {
+    type T = time::Utc;
+    fn encode<W: io::Write>(a: &time::Utc, w: &mut W) -> io::Result<()> {
+        U64LE::encode(&(a.unix_timestamp_nanos() as u64), w)
+    }
+    fn decode(a: &[u8]) -> Result<time::Utc, Error> {
+        time::Utc::from_unix_timestamp_nanos(U64LE::decode(a)? as i128).map_err(invalid_data)
+    }
+}
+
 /// Column is a type-safe specification of the DB column.
 /// It defines how to encode/decode keys and values stored in the column.
 pub trait Column {
@@ -285,6 +327,15 @@ impl Store {
             .iter_raw_bytes(C::COL)
             .map(|item| item.and_then(|(k, v)| Ok((C::Key::decode(&k)?, C::Value::decode(&v)?))))
     }
+    /// Counts the rows of the column, without decoding them.
+    pub fn count<C: Column>(&self) -> Result<usize, Error> {
+        let mut count = 0;
+        for item in self.0.iter_raw_bytes(C::COL) {
+            item?;
+            count += 1;
+        }
+        Ok(count)
+    }
     pub fn get<C: Column>(
         &self,
         k: &<C::Key as Format>::T,
@@ -305,4 +356,7 @@ impl StoreUpdate {
     pub fn delete<C: Column>(&mut self, k: &<C::Key as Format>::T) {
         self.0.delete(C::COL, to_vec::<C::Key>(k))
     }
+    pub fn delete_all<C: Column>(&mut self) {
+        self.0.delete_all(C::COL)
+    }
 }
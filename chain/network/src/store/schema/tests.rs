@@ -11,3 +11,24 @@ fn borsh_wrapper_is_transparent() {
     let e = data::make_edge(&s1, &s2);
     assert_eq!(Borsh(e.clone()).try_to_vec().unwrap(), e.try_to_vec().unwrap());
 }
+
+#[test]
+fn blacklist_entry_format_roundtrip() {
+    let mut rng = make_rng(423423);
+    let peer_id = data::make_peer_id(&mut rng);
+    for entry in ["192.0.2.4", "192.0.2.4:42", "[::1]:42", "192.0.2.0/24", "2001:db8::/32"]
+        .into_iter()
+        .map(|e| e.parse().unwrap())
+        .chain([primitives::BlacklistEntry::from_peer_id(peer_id)])
+    {
+        let got = BlacklistEntryFormat::decode(&to_vec::<BlacklistEntryFormat>(&entry)).unwrap();
+        assert_eq!(entry, got);
+    }
+}
+
+#[test]
+fn utc_nanos_roundtrip() {
+    let clock = time::FakeClock::default();
+    let t = clock.now_utc();
+    assert_eq!(t, UtcNanos::decode(&to_vec::<UtcNanos>(&t)).unwrap());
+}
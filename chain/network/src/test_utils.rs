@@ -1,12 +1,15 @@
+use crate::peer::codec::Codec;
 use crate::types::{
-    NetworkInfo, NetworkResponses, PeerManagerAdapter, PeerManagerMessageRequest,
-    PeerManagerMessageResponse,
+    Encoding, NetworkInfo, NetworkResponses, PeerManagerAdapter, PeerManagerMessageRequest,
+    PeerManagerMessageResponse, PeerMessage,
 };
 use crate::PeerManagerActor;
 use actix::{Actor, ActorContext, Context, Handler, MailboxError, Message, Recipient};
+use bytes::BytesMut;
 use futures::future::BoxFuture;
 use futures::{future, Future, FutureExt};
 use near_crypto::{KeyType, SecretKey};
+use near_network_primitives::time;
 use near_network_primitives::types::{PeerInfo, ReasonForBan};
 use near_primitives::hash::hash;
 use near_primitives::network::PeerId;
@@ -19,8 +22,11 @@ use std::net::TcpListener;
 use std::ops::ControlFlow;
 use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
+use tokio_util::codec::{Decoder, Encoder};
 use tracing::debug;
 
+pub mod misbehavior;
+
 static OPENED_PORTS: Lazy<Mutex<HashSet<u16>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
 /// Returns available port.
@@ -347,16 +353,25 @@ pub mod test_features {
                     Box::new(Some(NetworkViewClientResponses::ChainInfo {
                         genesis_id: GenesisId::default(),
                         height: 1,
+                        header_height: 1,
                         tracked_shards: vec![],
                         archival: false,
+                        num_shards: None,
+                        protocol_upgrade: None,
                     }))
                 }
                 _ => Box::new(Some(NetworkViewClientResponses::NoResponse)),
             }
         }))
         .start();
-        PeerManagerActor::new(store, config, client_addr.recipient(), view_client_addr.recipient())
-            .unwrap()
+        PeerManagerActor::new(
+            time::Clock::real(),
+            store,
+            config,
+            client_addr.recipient(),
+            view_client_addr.recipient(),
+        )
+        .unwrap()
     }
 }
 
@@ -392,3 +407,22 @@ impl PeerManagerAdapter for NetworkRecipient {
 pub struct SetAdvOptions {
     pub set_max_peers: Option<u64>,
 }
+
+/// Serializes `msg` and frames it the way it is sent over the wire.
+pub fn encode_frame(msg: &PeerMessage, enc: Encoding) -> BytesMut {
+    let mut buf = BytesMut::new();
+    Codec::default().encode(msg.serialize(enc), &mut buf).unwrap();
+    buf
+}
+
+/// Decodes the first frame of `buf` and parses the message in it, the way `PeerActor` does.
+/// With `copy` set, the frame is copied to a `Vec` and parsed from there, like before the
+/// frames were passed as slices of the read buffer.  Used by the benchmarks of the receive path.
+pub fn decode_frame(buf: &mut BytesMut, enc: Encoding, copy: bool) -> PeerMessage {
+    let frame = Codec::default().decode(buf).unwrap().unwrap().unwrap();
+    if copy {
+        PeerMessage::deserialize(enc, &frame.to_vec()).unwrap()
+    } else {
+        PeerMessage::deserialize_bytes(enc, &frame).unwrap()
+    }
+}
@@ -0,0 +1,141 @@
+//! Peer which misbehaves in a scripted way, for testing how nodes cope with it: timeouts,
+//! retries and bans.
+//!
+//! `MisbehavingPeer` is a TCP proxy in front of a real node. The node under test connects
+//! to the proxy as if it was the real node, and the frames the real node sends back are
+//! delayed, reordered, corrupted or dropped according to a `Script`. Frames sent by the node
+//! under test are forwarded as they are. Messages are matched by their type, so the
+//! connection must not be encrypted.
+use crate::network_protocol::{Encoding, PeerMessage};
+use parking_lot::Mutex;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+#[derive(Clone, Debug)]
+pub enum Misbehavior {
+    Drop,
+    /// Delays the frame and, since the frames are delivered in order, all the frames after it.
+    Delay(Duration),
+    /// Holds the frame back until the next frame is delivered.
+    Reorder,
+    /// Replaces the frame with garbage of the same length, which fails to parse.
+    Corrupt,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Script {
+    rules: Vec<(&'static str, Misbehavior)>,
+}
+
+impl Script {
+    /// Misbehaves on the messages of type `msg_type`, e.g. "Block", or for routed messages
+    /// the type of the body, e.g. "PartialEncodedChunkRequest". The first matching rule
+    /// applies.
+    pub fn on(mut self, msg_type: &'static str, misbehavior: Misbehavior) -> Self {
+        self.rules.push((msg_type, misbehavior));
+        self
+    }
+
+    fn get(&self, frame: &[u8]) -> Option<&Misbehavior> {
+        let msg = PeerMessage::deserialize(Encoding::Proto, frame)
+            .or_else(|_| PeerMessage::deserialize(Encoding::Borsh, frame))
+            .ok()?;
+        let msg_type = msg.msg_variant();
+        self.rules.iter().find(|(t, _)| *t == msg_type).map(|(_, misbehavior)| misbehavior)
+    }
+}
+
+pub struct MisbehavingPeer {
+    addr: SocketAddr,
+    /// Accepting loop and the proxied connections, closed when the peer is dropped.
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl MisbehavingPeer {
+    /// Starts a proxy to the node at `peer_addr`, misbehaving according to `script`.
+    pub async fn start(peer_addr: SocketAddr, script: Script) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let script = Arc::new(script);
+        let tasks = Arc::new(Mutex::new(vec![]));
+        let accept = tokio::spawn({
+            let tasks = tasks.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let script = script.clone();
+                    tasks.lock().push(tokio::spawn(async move {
+                        if let Err(err) = proxy(stream, peer_addr, &script).await {
+                            debug!(target: "network", %err, "Misbehaving peer connection closed");
+                        }
+                    }));
+                }
+            }
+        });
+        tasks.lock().push(accept);
+        Ok(Self { addr, tasks })
+    }
+
+    /// Address to connect to instead of the real node.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for MisbehavingPeer {
+    fn drop(&mut self) {
+        for task in self.tasks.lock().drain(..) {
+            task.abort();
+        }
+    }
+}
+
+async fn proxy(stream: TcpStream, peer_addr: SocketAddr, script: &Script) -> io::Result<()> {
+    let peer = TcpStream::connect(peer_addr).await?;
+    let (mut read, mut write) = stream.into_split();
+    let (peer_read, mut peer_write) = peer.into_split();
+    let send = async {
+        io::copy(&mut read, &mut peer_write).await?;
+        peer_write.shutdown().await
+    };
+    futures::try_join!(send, forward(peer_read, &mut write, script))?;
+    Ok(())
+}
+
+/// Forwards the frames from the real node, applying the script.
+async fn forward(
+    mut read: OwnedReadHalf,
+    write: &mut OwnedWriteHalf,
+    script: &Script,
+) -> io::Result<()> {
+    let mut held = None;
+    loop {
+        let len = read.read_u32_le().await?;
+        let mut frame = vec![0; len as usize];
+        read.read_exact(&mut frame).await?;
+        match script.get(&frame) {
+            Some(Misbehavior::Drop) => continue,
+            Some(Misbehavior::Delay(delay)) => tokio::time::sleep(*delay).await,
+            Some(Misbehavior::Reorder) if held.is_none() => {
+                held = Some(frame);
+                continue;
+            }
+            Some(Misbehavior::Corrupt) => frame.fill(0xff),
+            _ => {}
+        }
+        write_frame(write, &frame).await?;
+        if let Some(held) = held.take() {
+            write_frame(write, &held).await?;
+        }
+    }
+}
+
+async fn write_frame(write: &mut OwnedWriteHalf, frame: &[u8]) -> io::Result<()> {
+    write.write_u32_le(frame.len() as u32).await?;
+    write.write_all(frame).await
+}
@@ -51,8 +51,11 @@ impl actix::Handler<NetworkViewClientMessages> for Actor {
                 NetworkViewClientResponses::ChainInfo {
                     genesis_id: ci.genesis_id,
                     height: ci.height,
+                    header_height: ci.height,
                     tracked_shards: ci.tracked_shards,
                     archival: ci.archival,
+                    num_shards: None,
+                    protocol_upgrade: None,
                 }
             }
             NetworkViewClientMessages::BlockRequest(block_hash) => {
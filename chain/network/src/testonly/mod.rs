@@ -1,5 +1,6 @@
 pub mod actix;
 pub mod fake_client;
+pub mod replay;
 pub mod stream;
 
 pub type Rng = rand_pcg::Pcg32;
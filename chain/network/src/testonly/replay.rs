@@ -0,0 +1,102 @@
+//! Replay of the frames recorded with `NetworkConfig::record_frames`, for reproducing issues
+//! reported by node operators, e.g. consensus stalls, without access to their network.
+use crate::network_protocol::testonly as data;
+use crate::network_protocol::Encoding;
+use crate::peer::recorder::RecordedFrame;
+use crate::peer::testonly::{PeerConfig, PeerHandle};
+use crate::peer_manager::testonly::ActorHandler;
+use crate::testonly::Rng;
+use crate::types::PeerMessage;
+use near_network_primitives::time;
+use near_network_primitives::types::NetworkConfig;
+use near_primitives::network::PeerId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+
+pub struct Replay {
+    pub pm: ActorHandler,
+    /// Test peers standing in for the recorded peers, by the id of the recorded peer.
+    pub peers: HashMap<PeerId, PeerHandle>,
+}
+
+/// Whether the message only makes sense on the connection it was received on, e.g. a part
+/// of the handshake. Such messages are not replayed.
+fn is_connection_message(msg: &PeerMessage) -> bool {
+    matches!(
+        msg,
+        PeerMessage::Handshake(_)
+            | PeerMessage::HandshakeFailure(..)
+            | PeerMessage::LastEdge(_)
+            | PeerMessage::RequestUpdateNonce(_)
+            | PeerMessage::ResponseUpdateNonce(_)
+            | PeerMessage::Disconnect
+            | PeerMessage::EncryptionHandshake(_)
+            | PeerMessage::Encrypted(_)
+            | PeerMessage::AddressChallenge(_)
+            | PeerMessage::AddressProof(_)
+            | PeerMessage::AddressChallengeRequest
+            | PeerMessage::KeepAlive
+    )
+}
+
+/// Connects a new test peer to `pm` and completes the handshake.
+pub async fn connect(
+    clock: time::Clock,
+    rng: &mut Rng,
+    chain: Arc<data::Chain>,
+    pm: &ActorHandler,
+) -> PeerHandle {
+    let cfg = PeerConfig {
+        signer: data::make_signer(rng),
+        chain,
+        peers: vec![],
+        start_handshake_with: Some(PeerId::new(pm.cfg.node_key.public_key())),
+        force_encoding: Some(Encoding::Proto),
+        encrypt_connection: false,
+    };
+    let stream = TcpStream::connect(pm.cfg.node_addr.unwrap()).await.unwrap();
+    let mut peer = PeerHandle::start_endpoint(clock, cfg, stream).await;
+    peer.complete_handshake().await;
+    peer
+}
+
+/// Starts a `PeerManagerActor` with a fake client and feeds it the recorded frames.
+/// The recording doesn't contain the keys of the recorded peers, so each of them is replaced
+/// with a test peer, which sends its frames. Frames are sent in the recorded order, with the
+/// recorded gaps between them divided by `speedup`.
+pub async fn replay(
+    clock: time::Clock,
+    rng: &mut Rng,
+    chain: Arc<data::Chain>,
+    cfg: NetworkConfig,
+    frames: &[RecordedFrame],
+    speedup: f64,
+) -> Replay {
+    let pm = crate::peer_manager::testonly::start(clock.clone(), rng, chain.clone(), cfg).await;
+    let mut peers = HashMap::new();
+    let mut last_timestamp = None;
+    for frame in frames {
+        let peer_id = match &frame.peer_id {
+            Some(peer_id) => peer_id,
+            None => continue,
+        };
+        let msg = match frame.message() {
+            Some(msg) if !is_connection_message(&msg) => msg,
+            _ => continue,
+        };
+        if let Some(last_timestamp) = last_timestamp {
+            let gap = std::time::Duration::from_nanos(
+                frame.timestamp_nanos.saturating_sub(last_timestamp),
+            );
+            tokio::time::sleep(gap.div_f64(speedup)).await;
+        }
+        last_timestamp = Some(frame.timestamp_nanos);
+        if !peers.contains_key(peer_id) {
+            let peer = connect(clock.clone(), rng, chain.clone(), &pm).await;
+            peers.insert(peer_id.clone(), peer);
+        }
+        peers[peer_id].send(msg).await;
+    }
+    Replay { pm, peers }
+}
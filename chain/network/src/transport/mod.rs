@@ -0,0 +1,196 @@
+/// Byte streams over which the connections with peers are carried.
+///
+/// `PeerActor` frames `PeerMessage`s on top of a `Transport` with `Codec`, so it doesn't care
+/// whether the connection has been established over TCP or QUIC.
+use near_network_primitives::types::SocketOptions;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, ReadBuf};
+use tokio::net::{TcpSocket, TcpStream};
+use tracing::{debug, warn};
+
+pub(crate) mod quic;
+
+#[cfg(test)]
+mod tests;
+
+pub(crate) trait Transport: AsyncRead + AsyncWrite + Send + Unpin + 'static {
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+    fn peer_addr(&self) -> io::Result<SocketAddr>;
+    /// Round-trip time of the connection, if the transport keeps track of it.
+    fn rtt(&self) -> Option<Duration>;
+}
+
+/// Connection with a peer, over any transport.
+pub(crate) type Stream = Box<dyn Transport>;
+pub(crate) type WriteHalf = tokio::io::WriteHalf<Stream>;
+
+impl Transport for TcpStream {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::local_addr(self)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        TcpStream::peer_addr(self)
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Maximal size of the first frame of an inbound connection, i.e. of the `Handshake`, which is
+/// way smaller.
+pub(crate) const MAX_FIRST_FRAME_LEN: usize = 64 * 1024;
+
+/// Stream which yields the already read `prefix` before the rest of `inner`.
+struct Prefixed {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: Stream,
+}
+
+impl AsyncRead for Prefixed {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.pos < this.prefix.len() {
+            let n = std::cmp::min(buf.remaining(), this.prefix.len() - this.pos);
+            buf.put_slice(&this.prefix[this.pos..this.pos + n]);
+            this.pos += n;
+            return Poll::Ready(Ok(()));
+        }
+        Pin::new(&mut this.inner).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Prefixed {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl Transport for Prefixed {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        self.inner.rtt()
+    }
+}
+
+/// Waits for the first frame of an inbound connection, so that no `PeerActor` (and no thread
+/// for it) is spawned for the connections which send nothing or garbage. Frames are prefixed
+/// with their length as a little-endian u32, see `Codec`. Returns the stream with the frame
+/// put back in front of it, for `PeerActor` to decode.
+pub(crate) async fn read_first_frame(mut stream: Stream) -> io::Result<Stream> {
+    let mut len_buf = [0; 4];
+    stream.read_exact(&mut len_buf).await?;
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len == 0 || len > MAX_FIRST_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("first frame of {} bytes", len),
+        ));
+    }
+    let mut frame = vec![0; 4 + len];
+    frame[..4].copy_from_slice(&len_buf);
+    stream.read_exact(&mut frame[4..]).await?;
+    Ok(Box::new(Prefixed { prefix: frame, pos: 0, inner: stream }))
+}
+
+/// Applies `opts` to a TCP socket connecting to or listening on `addr`. The buffer sizes
+/// determine the TCP window negotiated in the handshake, so the options are applied to the
+/// sockets before connecting and to the listeners, whose sockets pass them on to the accepted
+/// connections on most platforms, as well as to the accepted connections.
+pub(crate) fn set_socket_options(
+    socket: socket2::SockRef<'_>,
+    addr: &SocketAddr,
+    opts: &SocketOptions,
+) -> io::Result<()> {
+    if opts.nodelay {
+        socket.set_nodelay(true)?;
+    }
+    if let Some(size) = opts.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = opts.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    if let (Some(tos), SocketAddr::V4(_)) = (opts.tos, addr) {
+        socket.set_tos(tos.into())?;
+    }
+    Ok(())
+}
+
+/// Dials `addr` over TCP, with `opts` applied to the socket. Options which can't be applied
+/// are logged and skipped, so that a setting unsupported by the OS doesn't cut the node off.
+/// Returns the stream with the time the TCP handshake took, i.e. one round trip.
+async fn connect_tcp(addr: SocketAddr, opts: &SocketOptions) -> io::Result<(TcpStream, Duration)> {
+    let socket = match addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+    if let Err(err) = set_socket_options((&socket).into(), &addr, opts) {
+        warn!(target: "network", ?addr, ?err, "Failed to set socket options");
+    }
+    let started = Instant::now();
+    let stream = socket.connect(addr).await?;
+    Ok((stream, started.elapsed()))
+}
+
+/// Dials `addr`. If `quic` is set, tries QUIC first and falls back to TCP if that fails, so that
+/// peers which don't accept QUIC are still reachable. Addresses reached over TCP after QUIC
+/// failed are dialed over TCP right away for a while, see `quic::Endpoint::mark_unreachable`.
+/// `socket_options` apply only to TCP. Returns the stream with the round-trip time measured
+/// while connecting.
+pub(crate) async fn connect(
+    quic: Option<quic::Endpoint>,
+    addr: SocketAddr,
+    timeout: Duration,
+    socket_options: SocketOptions,
+) -> anyhow::Result<(Stream, Duration)> {
+    let quic = quic.filter(|endpoint| !endpoint.is_unreachable(&addr));
+    if let Some(endpoint) = &quic {
+        match tokio::time::timeout(timeout, endpoint.connect(addr)).await {
+            Ok(Ok(stream)) => {
+                let rtt = stream.rtt().unwrap_or_default();
+                return Ok((Box::new(stream), rtt));
+            }
+            Ok(Err(err)) => {
+                debug!(target: "network", ?addr, ?err, "QUIC connect failed, falling back to TCP")
+            }
+            Err(_) => {
+                debug!(target: "network", ?addr, "QUIC connect timed out, falling back to TCP")
+            }
+        }
+    }
+    let (stream, rtt) = tokio::time::timeout(timeout, connect_tcp(addr, &socket_options)).await??;
+    if let Some(endpoint) = quic {
+        endpoint.mark_unreachable(addr);
+    }
+    Ok((Box::new(stream), rtt))
+}
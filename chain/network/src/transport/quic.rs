@@ -0,0 +1,204 @@
+/// QUIC transport.
+///
+/// A connection with a peer is a single bidirectional stream, opened by the dialing side. QUIC
+/// streams become visible to the other side only once some data is sent over them, so the
+/// dialing side starts the stream with `STREAM_VERSION`.
+///
+/// TLS certificates are self-signed and not verified: peers authenticate each other with the
+/// `Handshake`, exactly as they do over TCP.
+use crate::transport::Transport;
+use anyhow::Context as _;
+use futures::StreamExt as _;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Server name presented in the TLS handshake. Certificates are not verified, so it's arbitrary.
+const SERVER_NAME: &str = "near";
+/// First byte sent over the stream by the dialing side.
+const STREAM_VERSION: u8 = 0;
+/// Idle QUIC connections are closed by the peers, unlike TCP ones. Keep-alives prevent that.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Maximal number of addresses remembered as unreachable over QUIC.
+const UNREACHABLE_CAPACITY: usize = 10_000;
+/// How long an address is dialed over TCP only, once it turned out to be unreachable over QUIC.
+const UNREACHABLE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// QUIC endpoint, bound to a single UDP socket. Used both to dial and accept connections.
+///
+/// Clones share the addresses which turned out to be unreachable over QUIC, see
+/// `mark_unreachable`.
+#[derive(Clone)]
+pub(crate) struct Endpoint {
+    inner: quinn::Endpoint,
+    unreachable: Arc<parking_lot::Mutex<lru::LruCache<SocketAddr, Instant>>>,
+}
+
+/// Stream of the incoming connections of an `Endpoint`.
+pub(crate) struct Incoming(quinn::Incoming);
+
+impl Endpoint {
+    /// Binds the endpoint to `addr`. Must be called within a tokio runtime.
+    pub fn bind(addr: SocketAddr) -> anyhow::Result<(Self, Incoming)> {
+        let (mut endpoint, incoming) =
+            quinn::Endpoint::server(server_config()?, addr).context("binding QUIC endpoint")?;
+        endpoint.set_default_client_config(client_config());
+        let unreachable =
+            Arc::new(parking_lot::Mutex::new(lru::LruCache::new(UNREACHABLE_CAPACITY)));
+        Ok((Self { inner: endpoint, unreachable }, Incoming(incoming)))
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Remembers that `addr` couldn't be dialed over QUIC, while it could be over TCP, e.g.
+    /// because the peer doesn't accept QUIC. Such addresses are dialed over TCP right away
+    /// for `UNREACHABLE_TTL`, instead of waiting for the QUIC attempt to fail first.
+    pub fn mark_unreachable(&self, addr: SocketAddr) {
+        self.unreachable.lock().put(addr, Instant::now());
+    }
+
+    pub fn is_unreachable(&self, addr: &SocketAddr) -> bool {
+        let mut unreachable = self.unreachable.lock();
+        match unreachable.get(addr).copied() {
+            Some(since) if since.elapsed() < UNREACHABLE_TTL => true,
+            Some(_) => {
+                unreachable.pop(addr);
+                false
+            }
+            None => false,
+        }
+    }
+
+    pub async fn connect(&self, addr: SocketAddr) -> anyhow::Result<QuicStream> {
+        let conn = self.inner.connect(addr, SERVER_NAME)?.await?;
+        let (mut send, recv) = conn.connection.open_bi().await?;
+        send.write_all(&[STREAM_VERSION]).await?;
+        Ok(QuicStream { connection: conn.connection, send, recv, local_addr: self.local_addr()? })
+    }
+
+    /// Completes an incoming connection, returning its stream once the peer opens it.
+    pub async fn accept(&self, connecting: quinn::Connecting) -> anyhow::Result<QuicStream> {
+        let mut conn = connecting.await?;
+        let (send, mut recv) =
+            conn.bi_streams.next().await.context("connection closed before opening a stream")??;
+        let mut version = [0];
+        recv.read_exact(&mut version).await?;
+        anyhow::ensure!(version[0] == STREAM_VERSION, "unsupported stream version {}", version[0]);
+        Ok(QuicStream { connection: conn.connection, send, recv, local_addr: self.local_addr()? })
+    }
+}
+
+impl Incoming {
+    /// Waits for the next connection attempt. Returns `None` once the endpoint is closed.
+    pub async fn next(&mut self) -> Option<quinn::Connecting> {
+        self.0.next().await
+    }
+}
+
+pub(crate) struct QuicStream {
+    connection: quinn::Connection,
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
+    local_addr: SocketAddr,
+}
+
+impl fmt::Debug for QuicStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QuicStream")
+            .field("local_addr", &self.local_addr)
+            .field("peer_addr", &self.connection.remote_address())
+            .finish()
+    }
+}
+
+impl Transport for QuicStream {
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.local_addr)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.connection.remote_address())
+    }
+
+    fn rtt(&self) -> Option<Duration> {
+        Some(self.connection.rtt())
+    }
+}
+
+impl AsyncRead for QuicStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.recv).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for QuicStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.send).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.send).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.send).poll_shutdown(cx)
+    }
+}
+
+fn transport_config() -> Arc<quinn::TransportConfig> {
+    let mut config = quinn::TransportConfig::default();
+    config.keep_alive_interval(Some(KEEP_ALIVE_INTERVAL));
+    Arc::new(config)
+}
+
+fn server_config() -> anyhow::Result<quinn::ServerConfig> {
+    let cert = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()])?;
+    let mut config = quinn::ServerConfig::with_single_cert(
+        vec![rustls::Certificate(cert.serialize_der()?)],
+        rustls::PrivateKey(cert.serialize_private_key_der()),
+    )?;
+    config.transport = transport_config();
+    Ok(config)
+}
+
+fn client_config() -> quinn::ClientConfig {
+    let crypto = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+        .with_no_client_auth();
+    let mut config = quinn::ClientConfig::new(Arc::new(crypto));
+    config.transport = transport_config();
+    config
+}
+
+/// Accepts any server certificate, see the module comment.
+struct SkipServerVerification;
+
+impl rustls::client::ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
@@ -0,0 +1,124 @@
+use crate::transport::{
+    connect, quic, read_first_frame, set_socket_options, Stream, Transport as _,
+    MAX_FIRST_FRAME_LEN,
+};
+use near_network_primitives::types::SocketOptions;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpListener;
+
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[tokio::test]
+async fn quic_roundtrip() {
+    let localhost = (Ipv4Addr::LOCALHOST, 0).into();
+    let (server, mut incoming) = quic::Endpoint::bind(localhost).unwrap();
+    let (client, _) = quic::Endpoint::bind(localhost).unwrap();
+    let server_addr = server.local_addr().unwrap();
+
+    let (mut outbound, _rtt) =
+        connect(Some(client.clone()), server_addr, TIMEOUT, SocketOptions::default())
+            .await
+            .unwrap();
+    let mut inbound = server.accept(incoming.next().await.unwrap()).await.unwrap();
+    assert_eq!(server_addr, outbound.peer_addr().unwrap());
+    assert_eq!(client.local_addr().unwrap(), inbound.peer_addr().unwrap());
+
+    outbound.write_all(b"ping").await.unwrap();
+    let mut buf = [0; 4];
+    inbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(b"ping", &buf);
+    inbound.write_all(b"pong").await.unwrap();
+    outbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(b"pong", &buf);
+}
+
+#[tokio::test]
+async fn quic_falls_back_to_tcp() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (client, _) = quic::Endpoint::bind((Ipv4Addr::LOCALHOST, 0).into()).unwrap();
+
+    let (outbound, accepted) = tokio::join!(
+        connect(Some(client.clone()), addr, TIMEOUT, SocketOptions::default()),
+        listener.accept()
+    );
+    let (inbound, _) = accepted.unwrap();
+    assert_eq!(outbound.unwrap().0.local_addr().unwrap(), inbound.peer_addr().unwrap());
+
+    // The address is dialed over TCP right away from now on.
+    assert!(client.is_unreachable(&addr));
+    let (outbound, accepted) = tokio::join!(
+        tokio::time::timeout(
+            TIMEOUT / 2,
+            connect(Some(client), addr, TIMEOUT, SocketOptions::default())
+        ),
+        listener.accept()
+    );
+    let (inbound, _) = accepted.unwrap();
+    assert_eq!(outbound.unwrap().unwrap().0.local_addr().unwrap(), inbound.peer_addr().unwrap());
+}
+
+#[tokio::test]
+async fn tcp_socket_options() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let opts = SocketOptions {
+        nodelay: true,
+        send_buffer_size: Some(64 * 1024),
+        recv_buffer_size: Some(64 * 1024),
+        tos: Some(184),
+    };
+
+    let (outbound, accepted) = tokio::join!(connect(None, addr, TIMEOUT, opts), listener.accept());
+    outbound.unwrap();
+    let (inbound, client_addr) = accepted.unwrap();
+    set_socket_options((&inbound).into(), &client_addr, &opts).unwrap();
+    let socket = socket2::SockRef::from(&inbound);
+    assert!(socket.nodelay().unwrap());
+    assert_eq!(184, socket.tos().unwrap());
+    // The kernel may round the buffer sizes up, e.g. Linux doubles them.
+    assert!(socket.send_buffer_size().unwrap() >= 64 * 1024);
+    assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+}
+
+async fn accept(listener: &TcpListener) -> Stream {
+    Box::new(listener.accept().await.unwrap().0)
+}
+
+#[tokio::test]
+async fn tcp_first_frame() {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let (outbound, inbound) =
+        tokio::join!(connect(None, addr, TIMEOUT, SocketOptions::default()), accept(&listener));
+    let (mut outbound, _rtt) = outbound.unwrap();
+    let frame = [&4u32.to_le_bytes()[..], b"ping", b"rest"].concat();
+    outbound.write_all(&frame).await.unwrap();
+    // The frame is put back in front of the stream.
+    let mut inbound = read_first_frame(inbound).await.unwrap();
+    assert_eq!(outbound.local_addr().unwrap(), inbound.peer_addr().unwrap());
+    let mut buf = vec![0; frame.len()];
+    inbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(frame, buf);
+    inbound.write_all(b"pong").await.unwrap();
+    let mut buf = [0; 4];
+    outbound.read_exact(&mut buf).await.unwrap();
+    assert_eq!(b"pong", &buf);
+
+    let (outbound, inbound) =
+        tokio::join!(connect(None, addr, TIMEOUT, SocketOptions::default()), accept(&listener));
+    let (mut outbound, _rtt) = outbound.unwrap();
+    outbound.write_all(&(MAX_FIRST_FRAME_LEN as u32 + 1).to_le_bytes()).await.unwrap();
+    assert!(read_first_frame(inbound).await.is_err());
+
+    let (outbound, inbound) =
+        tokio::join!(connect(None, addr, TIMEOUT, SocketOptions::default()), accept(&listener));
+    // Closed before a complete frame.
+    let (mut outbound, _rtt) = outbound.unwrap();
+    outbound.write_all(&4u32.to_le_bytes()).await.unwrap();
+    drop(outbound);
+    assert!(read_first_frame(inbound).await.is_err());
+}
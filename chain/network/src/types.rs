@@ -1,15 +1,17 @@
 /// Type that belong to the network protocol.
 pub use crate::network_protocol::{
-    Encoding, Handshake, HandshakeFailureReason, PeerMessage, RoutingTableUpdate,
+    Encoding, Handshake, HandshakeFailureReason, NetworkAdvisory, PeerMessage, RoutingTableUpdate,
 };
+pub use crate::peer_manager::advisories::Rejection as NetworkAdvisoryRejection;
+use crate::peer_manager::scoring::PeerBehavior;
 use crate::routing::routing_table_view::RoutingTableInfo;
 use futures::future::BoxFuture;
 use near_network_primitives::time;
 use near_network_primitives::types::{
-    AccountIdOrPeerTrackingShard, AccountOrPeerIdOrHash, KnownProducer, OutboundTcpConnect,
-    PartialEdgeInfo, PartialEncodedChunkForwardMsg, PartialEncodedChunkRequestMsg,
-    PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo, Ping, Pong, ReasonForBan,
-    StateResponseInfo,
+    AccessList, AccessRule, AccountIdOrPeerTrackingShard, AccountOrPeerIdOrHash, BlacklistEntry,
+    KnownProducer, OutboundTcpConnect, PartialEdgeInfo, PartialEncodedChunkForwardMsg,
+    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerChainInfoV2, PeerInfo,
+    PeerType, Ping, Pong, ProtocolDeprecation, ReasonForBan, StateResponseInfo,
 };
 use near_primitives::block::{Approval, ApprovalMessage, Block, BlockHeader};
 use near_primitives::challenge::Challenge;
@@ -20,7 +22,9 @@ use near_primitives::sharding::{PartialEncodedChunk, PartialEncodedChunkWithArcR
 use near_primitives::syncing::{EpochSyncFinalizationResponse, EpochSyncResponse};
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{AccountId, BlockReference, EpochId, ShardId};
+use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{KnownProducerView, NetworkInfoView, PeerInfoView, QueryRequest};
+use std::collections::HashMap;
 use std::fmt::Debug;
 
 /// Peer stats query.
@@ -45,6 +49,8 @@ pub struct PeerStatsResult {
     pub message_counts: (usize, usize),
     /// Encoding used for communication.
     pub encoding: Option<Encoding>,
+    /// Behavior of the peer since the previous query, used to score the peer.
+    pub(crate) behavior: PeerBehavior,
 }
 
 /// Public actix interface of `PeerManagerActor`.
@@ -66,6 +72,22 @@ pub enum PeerManagerMessageRequest {
         nonce: u64,
         target: PeerId,
     },
+    /// Applies the operator's rule to the blacklist entry. The rule is persisted
+    /// and peers which became blacklisted are disconnected.
+    /// Responds with the resulting access list.
+    SetAccessRule(BlacklistEntry, AccessRule),
+    /// Fetches the blacklist and the allowlist, with the operator's rules applied.
+    GetAccessList,
+    /// Fetches details of the connections with the connected peers.
+    FetchConnectedPeers,
+    /// Fetches the peers banned by this node.
+    FetchBannedPeers,
+    /// Lifts the ban of the peer and drops the penalties in its score.
+    /// Responds with whether the peer was banned.
+    UnbanPeer(PeerId),
+    /// Broadcasts an advisory signed by one of the advisory keys of the chain, e.g. offline.
+    /// It is validated the same way as the advisories received from peers.
+    BroadcastNetworkAdvisory(NetworkAdvisory),
 }
 
 impl PeerManagerMessageRequest {
@@ -95,6 +117,11 @@ pub enum PeerManagerMessageResponse {
     SetAdvOptions,
     FetchRoutingTable(RoutingTableInfo),
     PingTo,
+    AccessList(AccessList),
+    FetchConnectedPeers(Vec<ConnectedPeerInfo>),
+    FetchBannedPeers(Vec<BannedPeerInfo>),
+    UnbanPeer(bool),
+    BroadcastNetworkAdvisory(Result<(), NetworkAdvisoryRejection>),
 }
 
 impl PeerManagerMessageResponse {
@@ -228,10 +255,43 @@ impl From<&FullPeerInfo> for PeerInfoView {
             tracked_shards: full_peer_info.chain_info.tracked_shards.clone(),
             archival: full_peer_info.chain_info.archival,
             peer_id: full_peer_info.peer_info.id.public_key().clone(),
+            score: None,
         }
     }
 }
 
+/// Details of the connection with a connected peer.
+#[derive(Debug, Clone)]
+pub struct ConnectedPeerInfo {
+    pub full_peer_info: FullPeerInfo,
+    /// Who started the connection. Inbound (other) or Outbound (us).
+    pub peer_type: PeerType,
+    /// Encoding used for communication. None until the first message is received.
+    pub encoding: Option<Encoding>,
+    /// Protocol version negotiated in the handshake.
+    pub protocol_version: ProtocolVersion,
+    pub received_bytes_per_sec: u64,
+    pub sent_bytes_per_sec: u64,
+    /// Number of messages received from the peer during the last minute.
+    pub received_messages_per_min: usize,
+    /// Number of messages sent to the peer during the last minute.
+    pub sent_messages_per_min: usize,
+    /// For how long the connection has been established.
+    pub connection_age: time::Duration,
+    /// Last time we received a message from the peer.
+    pub last_time_received_message: time::Utc,
+}
+
+/// Details of the ban of a peer banned by this node.
+#[derive(Debug, Clone)]
+pub struct BannedPeerInfo {
+    pub peer_info: PeerInfo,
+    pub reason: ReasonForBan,
+    pub banned_at: time::Utc,
+    /// When the ban expires, according to the current `NetworkConfig::ban_rule`.
+    pub expires_at: time::Utc,
+}
+
 #[derive(Debug, Clone, actix::MessageResponse)]
 pub struct NetworkInfo {
     pub connected_peers: Vec<FullPeerInfo>,
@@ -243,6 +303,12 @@ pub struct NetworkInfo {
     /// Accounts of known block and chunk producers from routing table.
     pub known_producers: Vec<KnownProducer>,
     pub peer_counter: usize,
+    /// Scores of the connected peers. The higher the better.
+    pub peer_scores: HashMap<PeerId, i64>,
+    /// Deprecation of our protocol version announced by a peer, if any.
+    pub protocol_deprecation: Option<ProtocolDeprecation>,
+    /// Latest network advisories accepted by the node, oldest first.
+    pub advisories: Vec<NetworkAdvisory>,
 }
 
 impl From<NetworkInfo> for NetworkInfoView {
@@ -253,7 +319,10 @@ impl From<NetworkInfo> for NetworkInfoView {
             connected_peers: network_info
                 .connected_peers
                 .iter()
-                .map(|full_peer_info| full_peer_info.into())
+                .map(|full_peer_info| PeerInfoView {
+                    score: network_info.peer_scores.get(&full_peer_info.peer_info.id).copied(),
+                    ..full_peer_info.into()
+                })
                 .collect::<Vec<_>>(),
             known_producers: network_info
                 .known_producers
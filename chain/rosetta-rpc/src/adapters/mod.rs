@@ -7,6 +7,8 @@ use near_client::ViewClientActor;
 
 use validated_operations::ValidatedOperation;
 
+pub(crate) use transactions::ConversionDiagnostics;
+
 mod transactions;
 mod validated_operations;
 
@@ -116,6 +118,7 @@ async fn convert_genesis_records_to_transaction(
 pub(crate) async fn convert_block_to_transactions(
     view_client_addr: Addr<ViewClientActor>,
     block: &near_primitives::views::BlockView,
+    diagnostics: &mut transactions::ConversionDiagnostics,
 ) -> crate::errors::Result<Vec<crate::models::Transaction>> {
     let state_changes = view_client_addr
         .send(near_client::GetStateChangesInBlock { block_hash: block.header.hash })
@@ -170,6 +173,7 @@ pub(crate) async fn convert_block_to_transactions(
         accounts_changes,
         accounts_previous_state,
         exec_to_rx,
+        diagnostics,
     )
     .map(|dict| dict.into_values().collect())
 }
@@ -178,14 +182,78 @@ pub(crate) async fn collect_transactions(
     genesis: Arc<Genesis>,
     view_client_addr: Addr<ViewClientActor>,
     block: &near_primitives::views::BlockView,
+) -> crate::errors::Result<Vec<crate::models::Transaction>> {
+    let mut diagnostics = ConversionDiagnostics::new(block.header.hash, false);
+    collect_transactions_with_diagnostics(genesis, view_client_addr, block, &mut diagnostics).await
+}
+
+/// Same as `collect_transactions`, but also reports the events observed during
+/// the conversion to `diagnostics`.
+pub(crate) async fn collect_transactions_with_diagnostics(
+    genesis: Arc<Genesis>,
+    view_client_addr: Addr<ViewClientActor>,
+    block: &near_primitives::views::BlockView,
+    diagnostics: &mut ConversionDiagnostics,
 ) -> crate::errors::Result<Vec<crate::models::Transaction>> {
     if block.header.prev_hash == Default::default() {
         Ok(vec![convert_genesis_records_to_transaction(genesis, view_client_addr, block).await?])
     } else {
-        convert_block_to_transactions(view_client_addr, block).await
+        convert_block_to_transactions(view_client_addr, block, diagnostics).await
     }
 }
 
+/// Reduces the operations of the transactions of a block to the net balance
+/// change of every account, see `models::CondensedBlock`.  The operations which
+/// didn't succeed or carry no amount are ignored.
+pub(crate) fn condense_balance_changes(
+    transactions: &[crate::models::Transaction],
+) -> Vec<crate::models::BalanceChange> {
+    // The credited and debited amounts of the accounts, in the order of their
+    // first appearance.
+    let mut totals = Vec::new();
+    let mut indices = std::collections::HashMap::new();
+    for operation in transactions.iter().flat_map(|transaction| &transaction.operations) {
+        if !operation.status.map_or(true, |status| status.is_successful()) {
+            continue;
+        }
+        let amount = match &operation.amount {
+            Some(amount) => &amount.value,
+            None => continue,
+        };
+        let address = operation.account.address.clone();
+        let sub_account = operation.account.sub_account.clone();
+        let index = *indices
+            .entry((
+                address.clone(),
+                sub_account.as_ref().map(|sub_account| sub_account.address.clone()),
+            ))
+            .or_insert_with(|| {
+                totals.push((
+                    crate::models::AccountIdentifier { address, sub_account, metadata: None },
+                    0,
+                    0,
+                ));
+                totals.len() - 1
+            });
+        let (_, credited, debited) = &mut totals[index];
+        if amount.is_positive() {
+            *credited += amount.absolute_difference();
+        } else {
+            *debited += amount.absolute_difference();
+        }
+    }
+    totals
+        .into_iter()
+        .filter(|(_, credited, debited)| credited != debited)
+        .map(|(account, credited, debited)| crate::models::BalanceChange {
+            account,
+            amount: crate::models::Amount::from_yoctonear_diff(crate::utils::SignedDiff::cmp(
+                debited, credited,
+            )),
+        })
+        .collect()
+}
+
 /// This is used as a common denominator for matching Rosetta Operations to
 /// and from NEAR Actions (see From and TryFrom implementations).
 ///
@@ -770,12 +838,14 @@ mod tests {
                 storage_usage: 200000,
             },
         );
+        let mut diagnostics = ConversionDiagnostics::new(block_hash, false);
         let transactions = super::transactions::convert_block_changes_to_transactions(
             &runtime_config,
             &block_hash,
             accounts_changes,
             accounts_previous_state,
             super::transactions::ExecutionToReceipts::empty(),
+            &mut diagnostics,
         )
         .unwrap();
         assert_eq!(transactions.len(), 3);
@@ -803,6 +873,177 @@ mod tests {
             "nfvalidator2_action_receipt_gas_reward_transaction",
             nfvalidator2_action_receipt_gas_reward_transaction
         );
+        assert_eq!(diagnostics.events, vec![]);
+    }
+
+    #[test]
+    fn test_conversion_diagnostics() {
+        use crate::models::ConversionDiagnosticKind;
+
+        let runtime_config = near_primitives::runtime::config::RuntimeConfig::test();
+        let block_hash = near_primitives::hash::CryptoHash::default();
+        let account = near_primitives::views::AccountView {
+            amount: 5000000000000000000,
+            code_hash: near_primitives::hash::CryptoHash::default(),
+            locked: 0,
+            storage_paid_at: 0,
+            storage_usage: 200000,
+        };
+        let accounts_changes = || {
+            vec![
+                near_primitives::views::StateChangeWithCauseView {
+                    cause: near_primitives::views::StateChangeCauseView::InitialState,
+                    value: near_primitives::views::StateChangeValueView::AccountUpdate {
+                        account_id: "alice.near".parse().unwrap(),
+                        account: account.clone(),
+                    },
+                },
+                near_primitives::views::StateChangeWithCauseView {
+                    cause: near_primitives::views::StateChangeCauseView::InitialState,
+                    value: near_primitives::views::StateChangeValueView::AccountDeletion {
+                        account_id: "bob.near".parse().unwrap(),
+                    },
+                },
+            ]
+        };
+        let mut diagnostics = ConversionDiagnostics::new(block_hash, true);
+        super::transactions::convert_block_changes_to_transactions(
+            &runtime_config,
+            &block_hash,
+            accounts_changes(),
+            Default::default(),
+            super::transactions::ExecutionToReceipts::empty(),
+            &mut diagnostics,
+        )
+        .unwrap();
+        let kinds: Vec<_> = diagnostics.events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ConversionDiagnosticKind::AccountUpdate,
+                ConversionDiagnosticKind::MissingPreviousState
+            ]
+        );
+
+        // Violations are reported also in the non-verbose mode.
+        let mut diagnostics = ConversionDiagnostics::new(block_hash, false);
+        super::transactions::convert_block_changes_to_transactions(
+            &runtime_config,
+            &block_hash,
+            accounts_changes(),
+            Default::default(),
+            super::transactions::ExecutionToReceipts::empty(),
+            &mut diagnostics,
+        )
+        .unwrap();
+        let kinds: Vec<_> = diagnostics.events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![ConversionDiagnosticKind::MissingPreviousState]);
+
+        let mut diagnostics = ConversionDiagnostics::new(block_hash, false);
+        let result = super::transactions::convert_block_changes_to_transactions(
+            &runtime_config,
+            &block_hash,
+            vec![near_primitives::views::StateChangeWithCauseView {
+                cause: near_primitives::views::StateChangeCauseView::NotWritableToDisk,
+                value: near_primitives::views::StateChangeValueView::AccountDeletion {
+                    account_id: "bob.near".parse().unwrap(),
+                },
+            }],
+            Default::default(),
+            super::transactions::ExecutionToReceipts::empty(),
+            &mut diagnostics,
+        );
+        assert!(result.is_err());
+        let kinds: Vec<_> = diagnostics.events.iter().map(|event| event.kind).collect();
+        assert_eq!(kinds, vec![ConversionDiagnosticKind::UnexpectedCause]);
+    }
+
+    #[test]
+    fn test_condense_balance_changes() {
+        let operation = |account: &str,
+                         sub_account: Option<crate::models::SubAccount>,
+                         amount: Option<i128>,
+                         status: crate::models::OperationStatusKind| {
+            let mut account: crate::models::AccountIdentifier = account.parse().unwrap();
+            account.sub_account = sub_account.map(Into::into);
+            crate::models::Operation {
+                type_: crate::models::OperationType::Transfer,
+                account,
+                amount: amount.map(|amount| {
+                    let value = crate::models::Amount::from_yoctonear(amount.unsigned_abs());
+                    if amount < 0 {
+                        -value
+                    } else {
+                        value
+                    }
+                }),
+                operation_identifier: crate::models::OperationIdentifier::new(&[]),
+                related_operations: None,
+                status: Some(status),
+                metadata: None,
+            }
+        };
+        let transaction = |hash: u8, operations| crate::models::Transaction {
+            transaction_identifier: crate::models::TransactionIdentifier::transaction(
+                &near_primitives::hash::CryptoHash([hash; 32]),
+            ),
+            operations,
+            related_transactions: vec![],
+            metadata: crate::models::TransactionMetadata {
+                type_: crate::models::TransactionType::Transaction,
+            },
+        };
+        let success = crate::models::OperationStatusKind::Success;
+        let transactions = vec![
+            transaction(
+                1,
+                vec![
+                    operation("alice.near", None, Some(-100), success),
+                    operation("bob.near", None, Some(100), success),
+                    operation("alice.near", None, None, success),
+                ],
+            ),
+            transaction(
+                2,
+                vec![
+                    operation("bob.near", None, Some(-100), success),
+                    operation("carol.near", None, Some(70), success),
+                    operation(
+                        "carol.near",
+                        Some(crate::models::SubAccount::Locked),
+                        Some(30),
+                        success,
+                    ),
+                    operation(
+                        "dave.near",
+                        None,
+                        Some(5),
+                        crate::models::OperationStatusKind::Empty,
+                    ),
+                ],
+            ),
+        ];
+        let balance_changes = condense_balance_changes(&transactions);
+        // bob.near nets to zero and the operation on dave.near didn't succeed.
+        assert_eq!(
+            balance_changes
+                .iter()
+                .map(|change| (
+                    change.account.address.to_string(),
+                    change.account.sub_account.clone().map(|sub_account| sub_account.address),
+                    change.amount.value.to_string(),
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                ("alice.near".to_string(), None, "-100".to_string()),
+                ("carol.near".to_string(), None, "70".to_string()),
+                (
+                    "carol.near".to_string(),
+                    Some(crate::models::SubAccount::Locked),
+                    "30".to_string()
+                ),
+            ]
+        );
     }
 
     #[test]
@@ -124,6 +124,54 @@ fn convert_cause_to_transaction_id(
     }
 }
 
+/// Collects the events observed when converting a block to Rosetta transactions.
+///
+/// Violations of the conversion invariants are always counted, logged and
+/// collected.  In the verbose mode every account change is collected as well,
+/// so that the conversion of a block can be traced.
+pub(crate) struct ConversionDiagnostics {
+    block_hash: CryptoHash,
+    verbose: bool,
+    pub(crate) events: Vec<crate::models::ConversionDiagnostic>,
+}
+
+impl ConversionDiagnostics {
+    pub(crate) fn new(block_hash: CryptoHash, verbose: bool) -> Self {
+        Self { block_hash, verbose, events: Vec::new() }
+    }
+
+    fn record(
+        &mut self,
+        kind: crate::models::ConversionDiagnosticKind,
+        account_id: Option<&near_primitives::types::AccountId>,
+        message: impl FnOnce() -> String,
+    ) {
+        if kind.is_violation() {
+            crate::metrics::CONVERSION_INVARIANT_VIOLATIONS
+                .with_label_values(&[kind.as_ref()])
+                .inc();
+        } else if !self.verbose {
+            return;
+        }
+        let message = message();
+        if kind.is_violation() {
+            tracing::warn!(
+                target: "rosetta_rpc",
+                block_hash = %self.block_hash,
+                kind = kind.as_ref(),
+                ?account_id,
+                "{}",
+                message
+            );
+        }
+        self.events.push(crate::models::ConversionDiagnostic {
+            kind,
+            account_id: account_id.cloned().map(Into::into),
+            message,
+        });
+    }
+}
+
 type RosettaTransactionsMap = std::collections::HashMap<String, crate::models::Transaction>;
 
 pub(crate) struct RosettaTransactions<'a> {
@@ -174,10 +222,19 @@ pub(crate) fn convert_block_changes_to_transactions(
         near_primitives::views::AccountView,
     >,
     exec_to_rx: ExecutionToReceipts,
+    diagnostics: &mut ConversionDiagnostics,
 ) -> crate::errors::Result<RosettaTransactionsMap> {
+    use crate::models::ConversionDiagnosticKind;
+
     let mut transactions = RosettaTransactions::new(exec_to_rx, block_hash);
     for account_change in accounts_changes {
         let transactions_in_block = &transactions.exec_to_rx.transactions;
+        if let Err(err) = convert_cause_to_transaction_id(block_hash, &account_change.cause) {
+            diagnostics.record(ConversionDiagnosticKind::UnexpectedCause, None, || {
+                format!("{:?}: {:?}", account_change.cause, err)
+            });
+            return Err(err);
+        }
         match account_change.value {
             near_primitives::views::StateChangeValueView::AccountUpdate { account_id, account } => {
                 // Calculate the total amount of deposit from transfer actions.
@@ -213,6 +270,7 @@ pub(crate) fn convert_block_changes_to_transactions(
                     previous_account_state,
                     &account,
                     deposit,
+                    diagnostics,
                 );
                 accounts_previous_state.insert(account_id, account);
             }
@@ -223,13 +281,17 @@ pub(crate) fn convert_block_changes_to_transactions(
                     &mut transactions.get_for_cause(&account_change.cause)?.operations,
                     &account_id,
                     previous_account_state,
+                    diagnostics,
                 );
             }
             unexpected_value => {
+                diagnostics.record(ConversionDiagnosticKind::UnexpectedChangeValue, None, || {
+                    format!("{:?}: {:?}", account_change.cause, unexpected_value)
+                });
                 return Err(crate::errors::ErrorKind::InternalInvariantError(format!(
                     "queried AccountChanges, but received {:?}.",
                     unexpected_value
-                )))
+                )));
             }
         }
     }
@@ -244,7 +306,10 @@ fn convert_account_update_to_operations(
     previous_account_state: Option<&near_primitives::views::AccountView>,
     account: &near_primitives::views::AccountView,
     deposit: Option<near_primitives::types::Balance>,
+    diagnostics: &mut ConversionDiagnostics,
 ) {
+    use crate::models::ConversionDiagnosticKind;
+
     let previous_account_balances = previous_account_state
         .map(|account| crate::utils::RosettaAccountBalances::from_account(account, runtime_config))
         .unwrap_or_else(crate::utils::RosettaAccountBalances::zero);
@@ -252,6 +317,36 @@ fn convert_account_update_to_operations(
     let new_account_balances =
         crate::utils::RosettaAccountBalances::from_account(account, runtime_config);
 
+    diagnostics.record(ConversionDiagnosticKind::AccountUpdate, Some(account_id), || {
+        format!(
+            "{:?} -> {:?}, deposit: {:?}, previous state known: {}",
+            previous_account_balances,
+            new_account_balances,
+            deposit,
+            previous_account_state.is_some()
+        )
+    });
+
+    // The deposit can only be separated if it was taken from the liquid balance.
+    // Otherwise the transaction would be invalid, so report it and fall back to
+    // a single operation.
+    let deposit = deposit.filter(|&deposit| {
+        let underflow = previous_account_balances.liquid < deposit;
+        if underflow {
+            diagnostics.record(
+                ConversionDiagnosticKind::DepositUnderflow,
+                Some(account_id),
+                || {
+                    format!(
+                        "deposit {} exceeds the previous liquid balance {}",
+                        deposit, previous_account_balances.liquid
+                    )
+                },
+            );
+        }
+        !underflow
+    });
+
     if previous_account_balances.liquid != new_account_balances.liquid {
         // Transfers would only lead to change in liquid balance, so it is sufficient to
         // have the check here only. If deposit is not `None` then we separate it into its own
@@ -280,7 +375,7 @@ fn convert_account_update_to_operations(
                 },
                 amount: Some(crate::models::Amount::from_yoctonear_diff(
                     crate::utils::SignedDiff::cmp(
-                        // this operation is guaranteed to not underflow. Otherwise the transaction is invalid
+                        // this operation is guaranteed to not underflow, see the check above
                         previous_account_balances.liquid - deposit,
                         new_account_balances.liquid,
                     ),
@@ -359,14 +454,26 @@ fn convert_account_delete_to_operations(
     operations: &mut Vec<crate::models::Operation>,
     account_id: &near_primitives::types::AccountId,
     previous_account_state: Option<near_primitives::views::AccountView>,
+    diagnostics: &mut ConversionDiagnostics,
 ) {
+    use crate::models::ConversionDiagnosticKind;
+
     let previous_account_balances = if let Some(previous_account_state) = previous_account_state {
         crate::utils::RosettaAccountBalances::from_account(previous_account_state, runtime_config)
     } else {
+        diagnostics.record(
+            ConversionDiagnosticKind::MissingPreviousState,
+            Some(account_id),
+            || "the balance of the deleted account can't be accounted for".to_string(),
+        );
         return;
     };
     let new_account_balances = crate::utils::RosettaAccountBalances::zero();
 
+    diagnostics.record(ConversionDiagnosticKind::AccountDeletion, Some(account_id), || {
+        format!("{:?}", previous_account_balances)
+    });
+
     if previous_account_balances.liquid != new_account_balances.liquid {
         operations.push(crate::models::Operation {
             operation_identifier: crate::models::OperationIdentifier::new(operations),
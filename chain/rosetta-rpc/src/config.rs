@@ -25,10 +25,20 @@ impl RosettaRpcConfig {
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct RosettaRpcLimitsConfig {
     pub input_payload_max_size: usize,
+    /// Maximum number of heights a single /near/block/range call covers.
+    #[serde(default = "default_block_range_max_size")]
+    pub block_range_max_size: u64,
+}
+
+fn default_block_range_max_size() -> u64 {
+    100
 }
 
 impl Default for RosettaRpcLimitsConfig {
     fn default() -> Self {
-        Self { input_payload_max_size: 10 * 1024 * 1024 }
+        Self {
+            input_payload_max_size: 10 * 1024 * 1024,
+            block_range_max_size: default_block_range_max_size(),
+        }
     }
 }
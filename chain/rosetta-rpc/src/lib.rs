@@ -23,6 +23,7 @@ pub use config::RosettaRpcConfig;
 mod adapters;
 mod config;
 mod errors;
+mod metrics;
 mod models;
 mod types;
 mod utils;
@@ -64,6 +65,31 @@ async fn check_network_identifier(
     Ok(status)
 }
 
+/// Returns the identifier of the parent of the block.
+async fn get_parent_block_identifier(
+    block: &near_primitives::views::BlockView,
+    view_client_addr: &Addr<ViewClientActor>,
+) -> Result<models::BlockIdentifier, errors::ErrorKind> {
+    if block.header.prev_hash == Default::default() {
+        // According to Rosetta API genesis block should have the parent block
+        // identifier referencing itself:
+        return Ok((&block.header).into());
+    }
+    if let Some(prev_height) = block.header.prev_height {
+        return Ok(models::BlockIdentifier {
+            index: prev_height.try_into().unwrap(),
+            hash: block.header.prev_hash.to_base(),
+        });
+    }
+    let parent_block = view_client_addr
+        .send(near_client::GetBlock(
+            near_primitives::types::BlockId::Hash(block.header.prev_hash).into(),
+        ))
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    Ok((&parent_block.header).into())
+}
+
 /// Get List of Available Networks
 ///
 /// This endpoint returns a list of NetworkIdentifiers that the Rosetta server
@@ -220,24 +246,7 @@ async fn block_details(
         .ok_or_else(|| errors::ErrorKind::NotFound("Block not found".into()))?;
 
     let block_identifier: models::BlockIdentifier = (&block.header).into();
-
-    let parent_block_identifier = if block.header.prev_hash == Default::default() {
-        // According to Rosetta API genesis block should have the parent block
-        // identifier referencing itself:
-        block_identifier.clone()
-    } else {
-        let parent_block = view_client_addr
-            .send(near_client::GetBlock(
-                near_primitives::types::BlockId::Hash(block.header.prev_hash).into(),
-            ))
-            .await?
-            .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
-
-        models::BlockIdentifier {
-            index: parent_block.header.height.try_into().unwrap(),
-            hash: parent_block.header.hash.to_base(),
-        }
-    };
+    let parent_block_identifier = get_parent_block_identifier(&block, &view_client_addr).await?;
 
     let transactions = crate::adapters::collect_transactions(
         Arc::clone(&genesis),
@@ -311,6 +320,127 @@ async fn block_transaction_details(
     Ok(Json(models::BlockTransactionResponse { transaction }))
 }
 
+#[api_v2_operation]
+/// Diagnose a Block Conversion (not in the Rosetta spec)
+///
+/// Converts the block to Rosetta transactions, the same way as /block does,
+/// while tracing every account change.  The response lists the traced changes
+/// and the violations of the conversion invariants, together with either the
+/// converted transactions or the reason the conversion failed.
+async fn block_conversion_diagnostics(
+    genesis: web::Data<Genesis>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    view_client_addr: web::Data<Addr<ViewClientActor>>,
+    body: Json<models::BlockRequest>,
+) -> Result<Json<models::BlockConversionDiagnosticsResponse>, models::Error> {
+    let Json(models::BlockRequest { network_identifier, block_identifier }) = body;
+
+    check_network_identifier(&client_addr, network_identifier).await?;
+
+    let block_id: near_primitives::types::BlockReference = block_identifier.try_into()?;
+    let block = crate::utils::get_block_if_final(&block_id, view_client_addr.get_ref())
+        .await?
+        .ok_or_else(|| errors::ErrorKind::NotFound("Block not found".into()))?;
+
+    let mut diagnostics = crate::adapters::ConversionDiagnostics::new(block.header.hash, true);
+    let (transactions, error) = match crate::adapters::collect_transactions_with_diagnostics(
+        Arc::clone(&genesis),
+        Addr::clone(&view_client_addr),
+        &block,
+        &mut diagnostics,
+    )
+    .await
+    {
+        Ok(transactions) => (Some(transactions), None),
+        Err(err) => (None, Some(format!("{:?}", err))),
+    };
+
+    Ok(Json(models::BlockConversionDiagnosticsResponse {
+        block_identifier: (&block.header).into(),
+        transactions,
+        error,
+        diagnostics: diagnostics.events,
+    }))
+}
+
+#[api_v2_operation]
+/// Get the Balance Changes of a Range of Blocks (not in the Rosetta spec)
+///
+/// Returns the final blocks of a range of heights with their operations
+/// condensed into the net balance change of every account, so that the chain
+/// can be followed without a /block call per block.  The range is limited by
+/// `limits.block_range_max_size` of the server config and never goes past the
+/// last final block.  The next page starts at `next_index` of the response.
+async fn block_range(
+    genesis: web::Data<Genesis>,
+    client_addr: web::Data<Addr<ClientActor>>,
+    view_client_addr: web::Data<Addr<ViewClientActor>>,
+    limits: web::Data<config::RosettaRpcLimitsConfig>,
+    body: Json<models::BlockRangeRequest>,
+) -> Result<Json<models::BlockRangeResponse>, models::Error> {
+    let Json(models::BlockRangeRequest { network_identifier, start_index, limit }) = body;
+
+    check_network_identifier(&client_addr, network_identifier).await?;
+
+    let start_height: near_primitives::types::BlockHeight =
+        start_index.try_into().map_err(|_| {
+            errors::ErrorKind::InvalidInput("start_index must not be negative".to_string())
+        })?;
+    let limit = match limit {
+        Some(0) => {
+            return Err(errors::ErrorKind::InvalidInput("limit must be positive".to_string()).into())
+        }
+        Some(limit) => limit.min(limits.block_range_max_size),
+        None => limits.block_range_max_size,
+    };
+
+    let earliest_block = view_client_addr
+        .send(near_client::GetBlock(near_primitives::types::BlockReference::SyncCheckpoint(
+            near_primitives::types::SyncCheckpoint::EarliestAvailable,
+        )))
+        .await?
+        .map_err(|err| errors::ErrorKind::InternalError(err.to_string()))?;
+    if start_height < earliest_block.header.height {
+        return Err(errors::ErrorKind::NotFound(format!(
+            "Blocks below {} are not available",
+            earliest_block.header.height
+        ))
+        .into());
+    }
+
+    let final_block = crate::utils::get_final_block(&view_client_addr).await?;
+    let end_height = final_block.header.height.min(start_height.saturating_add(limit - 1));
+    let mut blocks = Vec::new();
+    for height in start_height..=end_height {
+        let block = match view_client_addr
+            .send(near_client::GetBlock(near_primitives::types::BlockId::Height(height).into()))
+            .await?
+        {
+            Ok(block) => block,
+            // No block has been produced at this height.
+            Err(near_client_primitives::types::GetBlockError::UnknownBlock { .. }) => continue,
+            Err(err) => return Err(errors::ErrorKind::InternalError(err.to_string()).into()),
+        };
+        let transactions = crate::adapters::collect_transactions(
+            Arc::clone(&genesis),
+            Addr::clone(&view_client_addr),
+            &block,
+        )
+        .await?;
+        blocks.push(models::CondensedBlock {
+            block_identifier: (&block.header).into(),
+            parent_block_identifier: get_parent_block_identifier(&block, &view_client_addr).await?,
+            timestamp: (block.header.timestamp / 1_000_000).try_into().unwrap(),
+            balance_changes: crate::adapters::condense_balance_changes(&transactions),
+        });
+    }
+
+    Ok(Json(models::BlockRangeResponse {
+        blocks,
+        next_index: end_height.saturating_add(1).max(start_height).try_into().unwrap(),
+    }))
+}
+
 #[api_v2_operation]
 /// Get an Account Balance
 ///
@@ -823,6 +953,7 @@ pub fn start_rosetta_rpc(
             .app_data(web::Data::from(genesis.clone()))
             .app_data(web::Data::new(client_addr.clone()))
             .app_data(web::Data::new(view_client_addr.clone()))
+            .app_data(web::Data::new(limits.clone()))
             .wrap(get_cors(&cors_allowed_origins))
             .wrap_api()
             .service(web::resource("/network/list").route(web::post().to(network_list)))
@@ -833,6 +964,11 @@ pub fn start_rosetta_rpc(
                 web::resource("/block/transaction")
                     .route(web::post().to(block_transaction_details)),
             )
+            .service(
+                web::resource("/debug/block/conversion")
+                    .route(web::post().to(block_conversion_diagnostics)),
+            )
+            .service(web::resource("/near/block/range").route(web::post().to(block_range)))
             .service(web::resource("/account/balance").route(web::post().to(account_balance)))
             .service(web::resource("/mempool").route(web::post().to(mempool)))
             .service(
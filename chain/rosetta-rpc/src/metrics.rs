@@ -0,0 +1,11 @@
+use near_metrics::IntCounterVec;
+use once_cell::sync::Lazy;
+
+pub(crate) static CONVERSION_INVARIANT_VIOLATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    near_metrics::try_create_int_counter_vec(
+        "near_rosetta_conversion_invariant_violations_total",
+        "Number of unexpected state changes encountered when converting blocks to Rosetta transactions, by kind",
+        &["kind"],
+    )
+    .unwrap()
+});
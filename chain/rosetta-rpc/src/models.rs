@@ -230,6 +230,133 @@ pub(crate) struct BlockTransactionResponse {
     pub transaction: Transaction,
 }
 
+/// A BlockRangeRequest is utilized to make a request on the /near/block/range
+/// endpoint. It is not a part of the Rosetta spec.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BlockRangeRequest {
+    pub network_identifier: NetworkIdentifier,
+
+    /// The height to start the range at.
+    pub start_index: i64,
+
+    /// The number of heights to cover, at most (and by default)
+    /// `limits.block_range_max_size` of the server config. Heights without a
+    /// block count towards the limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// A BlockRangeResponse is returned on the /near/block/range endpoint. It is
+/// not a part of the Rosetta spec: it condenses the operations of a range of
+/// final blocks into the net balance change of each account in each block, so
+/// that the chain can be followed without a /block call per block.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BlockRangeResponse {
+    /// The blocks of the range, ordered by height. The heights without a block
+    /// are skipped.
+    pub blocks: Vec<CondensedBlock>,
+
+    /// The start_index of the request for the next page: the index past the
+    /// range, or past the last final block if the range reaches it. It is the
+    /// start_index of this request if none of the range is final yet.
+    pub next_index: i64,
+}
+
+/// CondensedBlock is a block with its transactions reduced to the net balance
+/// changes of the accounts.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct CondensedBlock {
+    pub block_identifier: BlockIdentifier,
+
+    pub parent_block_identifier: BlockIdentifier,
+
+    /// The timestamp of the block in milliseconds since the Unix Epoch.
+    pub timestamp: i64,
+
+    /// The balance changes in the order the accounts first appear in the
+    /// operations of the block. The accounts whose balance didn't change in
+    /// total are omitted.
+    pub balance_changes: Vec<BalanceChange>,
+}
+
+/// BalanceChange is the sum of the amounts of all the successful operations on
+/// the account (or its sub-account) in a block.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BalanceChange {
+    pub account: AccountIdentifier,
+
+    pub amount: Amount,
+}
+
+/// A BlockConversionDiagnosticsResponse is returned on the
+/// /debug/block/conversion endpoint. It is not a part of the Rosetta spec: it
+/// exposes how the block was converted to Rosetta transactions, so that the
+/// accounting edge cases can be investigated.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct BlockConversionDiagnosticsResponse {
+    pub block_identifier: BlockIdentifier,
+
+    /// The transactions of the block, if the conversion succeeded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transactions: Option<Vec<Transaction>>,
+
+    /// The reason of the failure, if the conversion failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+
+    /// The events observed during the conversion, in order.
+    pub diagnostics: Vec<ConversionDiagnostic>,
+}
+
+/// ConversionDiagnosticKind is the kind of an event observed when converting
+/// a block to Rosetta transactions.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    Apiv2Schema,
+    strum::AsRefStr,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum ConversionDiagnosticKind {
+    /// A state change with a cause which should never be observed.
+    UnexpectedCause,
+    /// A state change which is neither an account update nor a deletion.
+    UnexpectedChangeValue,
+    /// The transferred deposit exceeds the previous liquid balance.
+    DepositUnderflow,
+    /// The account got deleted, but its previous state is unknown.
+    MissingPreviousState,
+    /// The account got updated. Reported only in the verbose mode.
+    AccountUpdate,
+    /// The account got deleted. Reported only in the verbose mode.
+    AccountDeletion,
+}
+
+impl ConversionDiagnosticKind {
+    /// Whether the event means that the conversion results may be wrong.
+    pub(crate) fn is_violation(&self) -> bool {
+        !matches!(self, Self::AccountUpdate | Self::AccountDeletion)
+    }
+}
+
+/// ConversionDiagnostic is an event observed when converting a block to
+/// Rosetta transactions.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+pub(crate) struct ConversionDiagnostic {
+    pub kind: ConversionDiagnosticKind,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_id: Option<super::types::AccountId>,
+
+    pub message: String,
+}
+
 /// ConstructionDeriveRequest is passed to the `/construction/derive`
 /// endpoint. Network is provided in the request because some blockchains
 /// have different address formats for different networks.
@@ -905,7 +1032,7 @@ pub(crate) struct Peer {
      * pub metadata: Option<serde_json::Value>, */
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, Apiv2Schema)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, Apiv2Schema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub(crate) enum SubAccount {
     LiquidBalanceForStorage,
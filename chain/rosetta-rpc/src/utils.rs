@@ -280,6 +280,7 @@ fn get_liquid_balance_for_storage(
         .unwrap_or(0)
 }
 
+#[derive(Debug)]
 pub(crate) struct RosettaAccountBalances {
     pub liquid: near_primitives::types::Balance,
     pub liquid_for_storage: near_primitives::types::Balance,
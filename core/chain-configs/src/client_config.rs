@@ -1,12 +1,15 @@
 //! Chain Client Configuration
 use std::cmp::max;
 use std::cmp::min;
+use std::path::PathBuf;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use near_primitives::types::{AccountId, BlockHeightDelta, Gas, NumBlocks, NumSeats, ShardId};
 use near_primitives::version::Version;
+use near_primitives::views::{DbRecoveryReport, PreflightReport};
 
 pub const TEST_STATE_SYNC_TIMEOUT: u64 = 5;
 
@@ -40,6 +43,17 @@ pub struct GCConfig {
     /// Number of epochs for which we keep store data.
     #[serde(default = "default_gc_num_epochs_to_keep")]
     pub gc_num_epochs_to_keep: u64,
+
+    /// Number of blocks for which the heights and shards of the executed transactions and
+    /// receipts are kept, for looking up their status.  If not set, they are kept as long
+    /// as the outcomes themselves, i.e. forever on archival nodes.
+    #[serde(default)]
+    pub outcome_locations_num_blocks_to_keep: Option<NumBlocks>,
+
+    /// Maximum total size in bytes of the copies of the blocks pinned by the node operator,
+    /// which are kept after the blocks are garbage collected.
+    #[serde(default = "default_pinned_blocks_size_limit")]
+    pub pinned_blocks_size_limit: u64,
 }
 
 impl Default for GCConfig {
@@ -48,6 +62,8 @@ impl Default for GCConfig {
             gc_blocks_limit: 2,
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
+            outcome_locations_num_blocks_to_keep: None,
+            pinned_blocks_size_limit: 1024 * 1024 * 1024,
         }
     }
 }
@@ -64,12 +80,31 @@ fn default_gc_num_epochs_to_keep() -> u64 {
     GCConfig::default().gc_num_epochs_to_keep()
 }
 
+fn default_pinned_blocks_size_limit() -> u64 {
+    GCConfig::default().pinned_blocks_size_limit
+}
+
 impl GCConfig {
     pub fn gc_num_epochs_to_keep(&self) -> u64 {
         max(MIN_GC_NUM_EPOCHS_TO_KEEP, self.gc_num_epochs_to_keep)
     }
 }
 
+/// Policy deciding which shards the client tracks, evaluated every epoch.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackedShardsPolicy {
+    /// Track the shards assigned to this validator in the current epoch, and state sync
+    /// the shards assigned to it in the next epoch as soon as the assignment is known.
+    /// Shards of the `tracked_accounts` are tracked as well.
+    AssignedPlusNext,
+    /// Track all shards.
+    All,
+    /// Track the given shards, in addition to the assigned ones. Shard ids are interpreted
+    /// in the shard layout of each epoch; the ones outside of the layout are ignored.
+    Shards(Vec<ShardId>),
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ClientConfig {
     /// Version of the binary.
@@ -130,6 +165,13 @@ pub struct ClientConfig {
     pub catchup_step_period: Duration,
     /// Time between checking to re-request chunks.
     pub chunk_request_retry_period: Duration,
+    /// Maximum number of nodes tracking the shard which the parts of a chunk are requested
+    /// from in parallel, instead of requesting all of them from a single node.
+    pub chunk_request_fan_out: usize,
+    /// Time after which the parts of a chunk which haven't arrived are also requested from
+    /// another node tracking the shard, without waiting for the requests to be retried.
+    /// None disables it.
+    pub chunk_request_hedge_delay: Option<Duration>,
     /// Time between running doomslug timer.
     pub doosmslug_step_period: Duration,
     /// Behind this horizon header fetch kicks in.
@@ -140,6 +182,9 @@ pub struct ClientConfig {
     pub tracked_accounts: Vec<AccountId>,
     /// Shards that this client tracks
     pub tracked_shards: Vec<ShardId>,
+    /// Policy deciding which shards this client tracks. If set, overrides
+    /// `tracked_shards`, which tracks all shards whenever it is non-empty.
+    pub tracked_shards_policy: Option<TrackedShardsPolicy>,
     /// Not clear old data, set `true` for archive nodes.
     pub archive: bool,
     /// Number of threads for ViewClientActor pool.
@@ -154,8 +199,37 @@ pub struct ClientConfig {
     /// genesis file.  The value only affects the RPCs without influencing the
     /// protocol thus changing it per-node doesn’t affect the blockchain.
     pub max_gas_burnt_view: Option<Gas>,
+    /// Max wall-clock time per view method.  Executions running longer are
+    /// aborted with a distinct error.  Like `max_gas_burnt_view`, it only
+    /// affects the RPCs.  None is no limit.
+    pub view_call_timeout: Option<Duration>,
+    /// Time budget of a single execution of a contract, either of a receipt or of a view
+    /// method.  Contracts exceeding it consistently are reported in the logs and metrics.
+    /// None disables the detection.
+    pub slow_contract_threshold: Option<Duration>,
+    /// Contracts whose view methods this node refuses to execute.  Like
+    /// `max_gas_burnt_view`, it only affects the RPCs.
+    pub view_call_blacklist: Vec<AccountId>,
     /// Re-export storage layer statistics as prometheus metrics.
     pub enable_statistics_export: bool,
+    /// Point in time after which the client starts voting for the latest protocol version it
+    /// supports.  `None` means that it votes for it immediately.
+    pub protocol_upgrade_voting_start: Option<DateTime<Utc>>,
+    /// Results of the startup checks of the environment, reported in the status.
+    /// None if the checks weren't run.
+    pub preflight_report: Option<PreflightReport>,
+    /// Results of the recovery of the database after an unclean shutdown, reported in the
+    /// status.  None if the node was shut down cleanly.
+    pub db_recovery_report: Option<DbRecoveryReport>,
+    /// Path of the database, used to check the free disk space before the epoch switches.
+    /// None disables the check.
+    pub store_path: Option<PathBuf>,
+    /// Number of blocks the head may get ahead of the final head before the finality stall
+    /// alarm is raised.  None disables the alarm.
+    pub finality_stall_threshold: Option<BlockHeightDelta>,
+    /// Directory the diagnostic bundles are written to, e.g. when finality stalls.
+    /// None disables writing them.
+    pub diagnostics_dir: Option<PathBuf>,
 }
 
 impl ClientConfig {
@@ -202,11 +276,14 @@ impl ClientConfig {
                 Duration::from_millis(100),
                 Duration::from_millis(min_block_prod_time / 5),
             ),
+            chunk_request_fan_out: 1,
+            chunk_request_hedge_delay: None,
             doosmslug_step_period: Duration::from_millis(100),
             block_header_fetch_horizon: 50,
             gc: GCConfig { gc_blocks_limit: 100, ..GCConfig::default() },
             tracked_accounts: vec![],
             tracked_shards: vec![],
+            tracked_shards_policy: None,
             archive,
             log_summary_style: LogSummaryStyle::Colored,
             view_client_threads: 1,
@@ -214,7 +291,16 @@ impl ClientConfig {
             view_client_throttle_period: Duration::from_secs(1),
             trie_viewer_state_size_limit: None,
             max_gas_burnt_view: None,
+            view_call_timeout: None,
+            slow_contract_threshold: None,
+            view_call_blacklist: vec![],
             enable_statistics_export: true,
+            protocol_upgrade_voting_start: None,
+            preflight_report: None,
+            db_recovery_report: None,
+            store_path: None,
+            finality_stall_threshold: Some(50),
+            diagnostics_dir: None,
         }
     }
 }
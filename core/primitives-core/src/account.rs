@@ -42,6 +42,9 @@ impl Account {
     /// Max number of bytes an account can have in its state (excluding contract code)
     /// before it is infeasible to delete.
     pub const MAX_ACCOUNT_DELETION_STORAGE_USAGE: u64 = 10_000;
+    /// Max number of contract data records an account can have before it is infeasible to
+    /// delete, as every record is removed from the trie one by one.
+    pub const MAX_ACCOUNT_DELETION_DATA_RECORDS: u64 = 100;
 
     pub fn new(
         amount: Balance,
@@ -247,6 +247,11 @@ impl VMLimitConfig {
 pub struct ViewConfig {
     /// If specified, defines max burnt gas per view method.
     pub max_gas_burnt: Gas,
+    /// If specified, defines max wall-clock time of a view method execution.
+    /// Unlike gas, it doesn't make execution deterministic, so it must never be
+    /// set for the executions that are part of the consensus.
+    #[serde(default)]
+    pub timeout: Option<std::time::Duration>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq)]
@@ -415,6 +420,14 @@ pub struct ExtCostsConfig {
     pub alt_bn128_pairing_check_base: Gas,
     /// Per element cost for pairing check
     pub alt_bn128_pairing_check_element: Gas,
+
+    // #############################
+    // # Receipt-level random seed #
+    // #############################
+    /// Cost of getting the random seed of the receipt
+    pub receipt_random_seed_base: Gas,
+    /// Cost for each byte of the salt of the random seed of the receipt
+    pub receipt_random_seed_byte: Gas,
 }
 
 fn default_read_cached_trie_node() -> Gas {
@@ -492,6 +505,11 @@ impl ExtCostsConfig {
             alt_bn128_pairing_check_element: 5_102_000_000_000,
             alt_bn128_g1_sum_base: 3_000_000_000,
             alt_bn128_g1_sum_element: 5_000_000_000,
+            // Placeholders, copied from `sha256_base` and `sha256_byte` which dominate the
+            // host function, until `ReceiptRandomSeedBase` and `ReceiptRandomSeedByte` are
+            // measured with the estimator.  The host function is nightly-only until then.
+            receipt_random_seed_base: SAFETY_MULTIPLIER * 1513656750,
+            receipt_random_seed_byte: SAFETY_MULTIPLIER * 8039117,
         }
     }
 
@@ -558,6 +576,8 @@ impl ExtCostsConfig {
             alt_bn128_pairing_check_element: 0,
             alt_bn128_g1_sum_base: 0,
             alt_bn128_g1_sum_element: 0,
+            receipt_random_seed_base: 0,
+            receipt_random_seed_byte: 0,
         }
     }
 }
@@ -625,6 +645,8 @@ pub enum ExtCosts {
     alt_bn128_pairing_check_element,
     alt_bn128_g1_sum_base,
     alt_bn128_g1_sum_element,
+    receipt_random_seed_base,
+    receipt_random_seed_byte,
 }
 
 // Type of an action, used in fees logic.
@@ -706,6 +728,8 @@ impl ExtCosts {
             alt_bn128_pairing_check_element => config.alt_bn128_pairing_check_element,
             alt_bn128_g1_sum_base => config.alt_bn128_g1_sum_base,
             alt_bn128_g1_sum_element => config.alt_bn128_g1_sum_element,
+            receipt_random_seed_base => config.receipt_random_seed_base,
+            receipt_random_seed_byte => config.receipt_random_seed_byte,
         }
     }
 }
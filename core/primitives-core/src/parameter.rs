@@ -143,6 +143,8 @@ pub enum Parameter {
     WasmAltBn128PairingCheckElement,
     WasmAltBn128G1SumBase,
     WasmAltBn128G1SumElement,
+    WasmReceiptRandomSeedBase,
+    WasmReceiptRandomSeedByte,
 
     // Smart contract limits
     MaxGasBurnt,
@@ -271,6 +273,8 @@ impl Parameter {
             Parameter::WasmAltBn128PairingCheckElement,
             Parameter::WasmAltBn128G1SumBase,
             Parameter::WasmAltBn128G1SumElement,
+            Parameter::WasmReceiptRandomSeedBase,
+            Parameter::WasmReceiptRandomSeedByte,
         ]
         .iter()
     }
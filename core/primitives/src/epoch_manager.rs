@@ -130,6 +130,31 @@ impl deepsize::DeepSizeOf for ValidatorSelectionConfig {
     }
 }
 
+/// Stake-weighted votes of the block producers of an epoch for the protocol version upgrade.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtocolUpgradeVotes {
+    /// Stake of the block producers voting for each protocol version.  The vote of a block
+    /// producer is the latest protocol version announced in the blocks it produced.
+    pub votes: HashMap<ProtocolVersion, Balance>,
+    /// Total stake of the block producers of the epoch.
+    pub total_block_producer_stake: Balance,
+    /// Protocol version gets upgraded if the stake voting for it exceeds the threshold.
+    pub upgrade_stake_threshold: Balance,
+}
+
+impl ProtocolUpgradeVotes {
+    /// Returns the protocol version whose votes exceed the threshold, if any.
+    pub fn winner(&self) -> Option<ProtocolVersion> {
+        // Note: non-deterministic iteration is fine here, there can be only one
+        // version with large enough stake.
+        self.votes
+            .iter()
+            .max_by_key(|&(_version, stake)| stake)
+            .filter(|&(_version, stake)| *stake > self.upgrade_stake_threshold)
+            .map(|(version, _stake)| *version)
+    }
+}
+
 pub mod block_info {
     use super::SlashState;
     use crate::challenge::SlashedValidator;
@@ -427,11 +452,12 @@ pub mod epoch_info {
     use smart_default::SmartDefault;
     use std::collections::{BTreeMap, HashMap};
 
-    use crate::{checked_feature, epoch_manager::RngSeed, rand::WeightedIndex};
-    use near_primitives_core::{
-        hash::hash,
-        types::{BlockHeight, ShardId},
+    use crate::{
+        checked_feature,
+        epoch_manager::{sampling, RngSeed},
+        rand::WeightedIndex,
     };
+    use near_primitives_core::types::{BlockHeight, ShardId};
 
     pub use super::EpochInfoV1;
 
@@ -792,6 +818,16 @@ pub mod epoch_info {
             }
         }
 
+        /// Seed of the sampling of the block and chunk producers of the epoch, see
+        /// [`sampling`].  None for the epochs before the stake-weighted sampling, in which the
+        /// producers are assigned round-robin over the settlements.
+        pub fn rng_seed(&self) -> Option<RngSeed> {
+            match self {
+                Self::V1(_) | Self::V2(_) => None,
+                Self::V3(v3) => Some(v3.rng_seed),
+            }
+        }
+
         pub fn sample_block_producer(&self, height: BlockHeight) -> ValidatorId {
             match &self {
                 Self::V1(v1) => {
@@ -803,7 +839,7 @@ pub mod epoch_info {
                     bp_settlement[(height % (bp_settlement.len() as u64)) as usize]
                 }
                 Self::V3(v3) => {
-                    let seed = Self::block_produce_seed(height, &v3.rng_seed);
+                    let seed = sampling::block_producer_seed(&v3.rng_seed, height);
                     v3.block_producers_settlement[v3.block_producers_sampler.sample(seed)]
                 }
             }
@@ -822,40 +858,18 @@ pub mod epoch_info {
                     shard_cps[(height as u64 % (shard_cps.len() as u64)) as usize]
                 }
                 Self::V3(v3) => {
-                    let protocol_version = self.protocol_version();
-                    let seed = if checked_feature!(
-                        "stable",
-                        SynchronizeBlockChunkProduction,
-                        protocol_version
-                    ) && !checked_feature!(
-                        "protocol_feature_chunk_only_producers",
-                        ChunkOnlyProducers,
-                        protocol_version
-                    ) {
-                        // This is same seed that used for determining block producer
-                        Self::block_produce_seed(height, &v3.rng_seed)
-                    } else {
-                        // 32 bytes from epoch_seed, 8 bytes from height, 8 bytes from shard_id
-                        let mut buffer = [0u8; 48];
-                        buffer[0..32].copy_from_slice(&v3.rng_seed);
-                        buffer[32..40].copy_from_slice(&height.to_le_bytes());
-                        buffer[40..48].copy_from_slice(&shard_id.to_le_bytes());
-                        hash(&buffer).0
-                    };
+                    let seed = sampling::chunk_producer_seed(
+                        &v3.rng_seed,
+                        height,
+                        shard_id,
+                        v3.protocol_version,
+                    );
                     let shard_id = shard_id as usize;
                     v3.chunk_producers_settlement[shard_id]
                         [v3.chunk_producers_sampler[shard_id].sample(seed)]
                 }
             }
         }
-
-        /// 32 bytes from epoch_seed, 8 bytes from height
-        fn block_produce_seed(height: BlockHeight, seed: &RngSeed) -> [u8; 32] {
-            let mut buffer = [0u8; 40];
-            buffer[0..32].copy_from_slice(seed);
-            buffer[32..40].copy_from_slice(&height.to_le_bytes());
-            hash(&buffer).0
-        }
     }
 
     #[derive(BorshSerialize, BorshDeserialize)]
@@ -872,6 +886,61 @@ pub mod epoch_info {
     }
 }
 
+/// Sampling of the block and chunk producers of the epochs with a random seed.
+///
+/// `EpochInfo::sample_block_producer` and `EpochInfo::sample_chunk_producer` are implemented
+/// with these functions, so that the assignment of the producers can be reproduced outside
+/// of the node from the seed and the settlements of the epoch alone.
+pub mod sampling {
+    use super::RngSeed;
+    use crate::checked_feature;
+    use crate::rand::WeightedIndex;
+    use near_primitives_core::hash::hash;
+    use near_primitives_core::types::{Balance, BlockHeight, ProtocolVersion, ShardId};
+
+    /// Seed of the sampling of the block producer at `height`: the hash of the 32 bytes of the
+    /// epoch seed and the 8 bytes of the height.
+    pub fn block_producer_seed(rng_seed: &RngSeed, height: BlockHeight) -> [u8; 32] {
+        let mut buffer = [0u8; 40];
+        buffer[0..32].copy_from_slice(rng_seed);
+        buffer[32..40].copy_from_slice(&height.to_le_bytes());
+        hash(&buffer).0
+    }
+
+    /// Seed of the sampling of the chunk producer of `shard_id` at `height` in an epoch of
+    /// `protocol_version`.
+    pub fn chunk_producer_seed(
+        rng_seed: &RngSeed,
+        height: BlockHeight,
+        shard_id: ShardId,
+        protocol_version: ProtocolVersion,
+    ) -> [u8; 32] {
+        if checked_feature!("stable", SynchronizeBlockChunkProduction, protocol_version)
+            && !checked_feature!(
+                "protocol_feature_chunk_only_producers",
+                ChunkOnlyProducers,
+                protocol_version
+            )
+        {
+            // This is same seed that used for determining block producer
+            block_producer_seed(rng_seed, height)
+        } else {
+            // 32 bytes from epoch_seed, 8 bytes from height, 8 bytes from shard_id
+            let mut buffer = [0u8; 48];
+            buffer[0..32].copy_from_slice(rng_seed);
+            buffer[32..40].copy_from_slice(&height.to_le_bytes());
+            buffer[40..48].copy_from_slice(&shard_id.to_le_bytes());
+            hash(&buffer).0
+        }
+    }
+
+    /// Samples an index into a settlement with the stakes `stakes` of its validators, i.e. the
+    /// position of the producer within the settlement.
+    pub fn sample_producer(stakes: &[Balance], seed: [u8; 32]) -> usize {
+        WeightedIndex::new(stakes.to_vec()).sample(seed)
+    }
+}
+
 /// Information per epoch.
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(SmartDefault, BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
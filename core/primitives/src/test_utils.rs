@@ -433,6 +433,7 @@ impl Block {
         block_merkle_root: CryptoHash,
     ) -> Self {
         Block::produce(
+            PROTOCOL_VERSION,
             PROTOCOL_VERSION,
             PROTOCOL_VERSION,
             prev.header(),
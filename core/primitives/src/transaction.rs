@@ -13,7 +13,7 @@ use crate::hash::{hash, CryptoHash};
 use crate::logging;
 use crate::merkle::MerklePath;
 use crate::serialize::{base64_format, u128_dec_format_compatible};
-use crate::types::{AccountId, Balance, Gas, Nonce};
+use crate::types::{AccountId, Balance, BlockHeight, Gas, Nonce, ShardId};
 use near_primitives_core::profile::ProfileData;
 
 pub type LogEntry = String;
@@ -449,6 +449,13 @@ impl ExecutionOutcomeWithIdAndProof {
     }
 }
 
+/// Height of the block and shard in which a transaction or receipt was executed.
+#[derive(PartialEq, Clone, Copy, Debug, BorshSerialize, BorshDeserialize, Eq)]
+pub struct OutcomeLocation {
+    pub height: BlockHeight,
+    pub shard_id: ShardId,
+}
+
 pub fn verify_transaction_signature(
     transaction: &SignedTransaction,
     public_keys: &[PublicKey],
@@ -22,6 +22,14 @@ impl FromStr for ProtocolUpgradeVotingSchedule {
 }
 
 impl ProtocolUpgradeVotingSchedule {
+    pub fn new(timestamp: DateTime<Utc>) -> Self {
+        Self { timestamp }
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
     pub fn is_in_future(&self) -> bool {
         chrono::Utc::now() < self.timestamp
     }
@@ -295,6 +295,15 @@ pub fn create_random_seed(
     res.as_ref().to_vec()
 }
 
+/// Creates the random seed shared by all the actions of a receipt from the `receipt_id` and
+/// the random value of the block, see `VMLogic::receipt_random_seed`.
+pub fn create_receipt_random_seed(receipt_id: &CryptoHash, random_seed: &CryptoHash) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(size_of::<CryptoHash>() + size_of::<CryptoHash>());
+    bytes.extend_from_slice(random_seed.as_ref());
+    bytes.extend_from_slice(receipt_id.as_ref());
+    hash(&bytes).as_ref().to_vec()
+}
+
 /// Creates a new CryptoHash ID based on the protocol version.
 /// Before `CREATE_HASH_PROTOCOL_VERSION` it uses `create_nonce_with_nonce` with
 /// just `base` and `salt`. But after `CREATE_HASH_PROTOCOL_VERSION` it uses
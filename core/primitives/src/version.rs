@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
@@ -20,7 +21,7 @@ pub struct Version {
 pub type DbVersion = u32;
 
 /// Current version of the database.
-pub const DB_VERSION: DbVersion = 31;
+pub const DB_VERSION: DbVersion = 36;
 
 use crate::upgrade_schedule::{get_protocol_version_internal, ProtocolUpgradeVotingSchedule};
 /// Protocol version type.
@@ -167,12 +168,41 @@ pub enum ProtocolFeature {
     /// Validate account id for function call access keys.
     #[cfg(feature = "protocol_feature_account_id_in_function_call_permission")]
     AccountIdInFunctionCallPermission,
+    /// Host function `receipt_random_seed` giving contracts a random seed derived from the
+    /// random value of the block and the receipt id.
+    #[cfg(feature = "protocol_feature_receipt_random_seed")]
+    ReceiptRandomSeed,
+    /// Refuse to delete accounts with more than `MAX_ACCOUNT_DELETION_DATA_RECORDS` contract
+    /// data records, so that the cost of removing them is bounded.
+    #[cfg(feature = "protocol_feature_delete_account_data_limit")]
+    DeleteAccountDataRecordLimit,
 }
 
 /// Both, outgoing and incoming tcp connections to peers, will be rejected if `peer's`
 /// protocol version is lower than this.
 pub const PEER_MIN_ALLOWED_PROTOCOL_VERSION: ProtocolVersion = STABLE_PROTOCOL_VERSION - 2;
 
+/// Peers send latency and capability hints in `PeersResponse` only if the protocol version
+/// negotiated for the connection is at least this.
+pub const PEER_HINTS_PROTOCOL_VERSION: ProtocolVersion = 57;
+
+/// Nodes verify the listening address advertised by an inbound peer with `AddressChallenge`
+/// only if the protocol version negotiated for the connection is at least this.
+pub const PEER_ADDRESS_PROOF_PROTOCOL_VERSION: ProtocolVersion = 57;
+
+/// Nodes warn peers which run a protocol version scheduled to be dropped with
+/// `ProtocolDeprecation` only if the protocol version negotiated for the connection is at
+/// least this.
+pub const PROTOCOL_DEPRECATION_PROTOCOL_VERSION: ProtocolVersion = 57;
+
+/// Peers send `KeepAlive` periodically, and close the connections which stay silent for too
+/// long, only if the protocol version negotiated for the connection is at least this.
+pub const KEEP_ALIVE_PROTOCOL_VERSION: ProtocolVersion = 57;
+
+/// Peers relay `NetworkAdvisory` only if the protocol version negotiated for the connection is
+/// at least this.
+pub const NETWORK_ADVISORY_PROTOCOL_VERSION: ProtocolVersion = 57;
+
 /// Current protocol version used on the mainnet.
 /// Some features (e. g. FixStorageUsage) require that there is at least one epoch with exactly
 /// the corresponding version
@@ -181,7 +211,7 @@ const STABLE_PROTOCOL_VERSION: ProtocolVersion = 56;
 /// Largest protocol version supported by the current binary.
 pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "nightly_protocol") {
     // On nightly, pick big enough version to support all features.
-    130
+    132
 } else if cfg!(feature = "shardnet") {
     // For shardnet, enable `ChunkOnlyProducers` but nothing else.
     100
@@ -191,7 +221,6 @@ pub const PROTOCOL_VERSION: ProtocolVersion = if cfg!(feature = "nightly_protoco
 };
 
 /// The points in time after which the voting for the protocol version should start.
-#[allow(dead_code)]
 const PROTOCOL_UPGRADE_SCHEDULE: Lazy<HashMap<ProtocolVersion, ProtocolUpgradeVotingSchedule>> =
     Lazy::new(|| {
         let mut schedule = HashMap::new();
@@ -201,14 +230,26 @@ const PROTOCOL_UPGRADE_SCHEDULE: Lazy<HashMap<ProtocolVersion, ProtocolUpgradeVo
         schedule
     });
 
+/// Returns the point in time after which the binary starts voting for `PROTOCOL_VERSION`, as
+/// defined by the release.  `None` means that it votes for it immediately.
+pub fn get_protocol_upgrade_voting_start() -> Option<DateTime<Utc>> {
+    PROTOCOL_UPGRADE_SCHEDULE.get(&PROTOCOL_VERSION).map(ProtocolUpgradeVotingSchedule::timestamp)
+}
+
 /// Gives new clients an option to upgrade without announcing that they support the new version.
 /// This gives non-validator nodes time to upgrade. See https://github.com/near/NEPs/issues/205
-pub fn get_protocol_version(next_epoch_protocol_version: ProtocolVersion) -> ProtocolVersion {
-    get_protocol_version_internal(
-        next_epoch_protocol_version,
-        PROTOCOL_VERSION,
-        &PROTOCOL_UPGRADE_SCHEDULE,
-    )
+///
+/// `voting_start` is the point in time after which the client votes for `PROTOCOL_VERSION`.
+/// Nodes use `get_protocol_upgrade_voting_start()` unless it is overridden in the config.
+pub fn get_protocol_version(
+    next_epoch_protocol_version: ProtocolVersion,
+    voting_start: Option<DateTime<Utc>>,
+) -> ProtocolVersion {
+    let schedule = voting_start
+        .map(|timestamp| (PROTOCOL_VERSION, ProtocolUpgradeVotingSchedule::new(timestamp)))
+        .into_iter()
+        .collect();
+    get_protocol_version_internal(next_epoch_protocol_version, PROTOCOL_VERSION, &schedule)
 }
 
 impl ProtocolFeature {
@@ -254,6 +295,10 @@ impl ProtocolFeature {
             ProtocolFeature::FixStakingThreshold => 126,
             #[cfg(feature = "protocol_feature_account_id_in_function_call_permission")]
             ProtocolFeature::AccountIdInFunctionCallPermission => 130,
+            #[cfg(feature = "protocol_feature_receipt_random_seed")]
+            ProtocolFeature::ReceiptRandomSeed => 131,
+            #[cfg(feature = "protocol_feature_delete_account_data_limit")]
+            ProtocolFeature::DeleteAccountDataRecordLimit => 132,
         }
     }
 }
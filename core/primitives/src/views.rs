@@ -242,11 +242,40 @@ pub struct AccessKeyInfoView {
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct AccessKeyList {
     pub keys: Vec<AccessKeyInfoView>,
+    /// Cursor to pass as `after_public_key` to get the next page of the keys,
+    /// None if there are no more keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_public_key: Option<PublicKey>,
 }
 
 impl FromIterator<AccessKeyInfoView> for AccessKeyList {
     fn from_iter<I: IntoIterator<Item = AccessKeyInfoView>>(iter: I) -> Self {
-        Self { keys: iter.into_iter().collect() }
+        Self { keys: iter.into_iter().collect(), next_public_key: None }
+    }
+}
+
+/// Permission of the access keys returned by the `view_access_key_list` query.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessKeyPermissionFilter {
+    FullAccess,
+    /// Function call keys, only the ones for the given receiver if it is set.
+    FunctionCall {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        receiver_id: Option<AccountId>,
+    },
+}
+
+impl AccessKeyPermissionFilter {
+    pub fn matches(&self, permission: &AccessKeyPermission) -> bool {
+        match (self, permission) {
+            (Self::FullAccess, AccessKeyPermission::FullAccess) => true,
+            (Self::FunctionCall { receiver_id }, AccessKeyPermission::FunctionCall(permission)) => {
+                receiver_id.as_ref().map_or(true, |id| id.as_ref() == permission.receiver_id)
+            }
+            _ => false,
+        }
     }
 }
 
@@ -282,6 +311,16 @@ pub enum QueryRequest {
     },
     ViewAccessKeyList {
         account_id: AccountId,
+        /// Only the keys with the given permission are returned.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        permission: Option<AccessKeyPermissionFilter>,
+        /// Only the keys after the given one are returned, i.e. the
+        /// `next_public_key` of the previous page.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        after_public_key: Option<PublicKey>,
+        /// Maximal number of the keys returned, all the keys if not set.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        limit: Option<u32>,
     },
     CallFunction {
         account_id: AccountId,
@@ -331,6 +370,8 @@ pub struct PeerInfoView {
     pub tracked_shards: Vec<ShardId>,
     pub archival: bool,
     pub peer_id: PublicKey,
+    /// Score of the peer, the higher the better. None if unknown.
+    pub score: Option<i64>,
 }
 
 /// Information about a Producer: its account name, peer_id and a list of connected peers that
@@ -402,6 +443,78 @@ pub struct DetailedDebugStatus {
     pub chunk_info: ChunkInfoView,
 }
 
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheckStatus {
+    Ok,
+    /// The node can run, but is likely to run into problems.
+    Warning,
+    /// The node is unlikely to work correctly until the problem is fixed.
+    Error,
+}
+
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PreflightCheckView {
+    /// Name of the check, e.g. "disk_space".
+    pub name: String,
+    pub status: PreflightCheckStatus,
+    /// What was found and, unless the check passed, how to fix it.
+    pub message: String,
+}
+
+/// Results of the checks of the environment the node runs in, done on startup.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheckView>,
+}
+
+/// Results of the checks and the repair of the database done on startup after an unclean
+/// shutdown of the node.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct DbRecoveryReport {
+    /// The database couldn't be repaired, so instead of the node only the status RPC runs,
+    /// until the database is restored, e.g. from a backup or a snapshot.
+    pub safe_mode: bool,
+    pub checks: Vec<PreflightCheckView>,
+}
+
+/// Readiness of the node for the upcoming epoch switch, evaluated during the last blocks
+/// of an epoch so that a validator can fix the problems before it starts missing chunks.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EpochReadinessView {
+    pub next_epoch_id: EpochId,
+    /// Expected height of the first block of the next epoch.
+    pub next_epoch_start_height: BlockHeight,
+    pub checks: Vec<PreflightCheckView>,
+}
+
+/// Switch of the network to a protocol version which this binary doesn't support, as
+/// announced by a peer.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolDeprecationView {
+    /// Protocol version the network switches to.
+    pub protocol_version: ProtocolVersion,
+    /// Height of the first epoch with the new protocol version.
+    pub epoch_height: EpochHeight,
+}
+
+/// Notice for node operators signed by one of the advisory keys of the chain.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NetworkAdvisoryView {
+    pub signer: PublicKey,
+    pub nonce: u64,
+    /// Time of signing, as a unix timestamp in seconds.
+    pub timestamp: u64,
+    pub message: String,
+}
+
 // TODO: add more information to status.
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
 #[derive(Serialize, Deserialize, Debug)]
@@ -426,6 +539,24 @@ pub struct StatusResponse {
     /// Information about last blocks, network, epoch and chain & chunk info.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub detailed_debug_status: Option<DetailedDebugStatus>,
+    /// Results of the checks of the environment done on startup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preflight_report: Option<PreflightReport>,
+    /// Results of the recovery of the database, present only if the node started after
+    /// an unclean shutdown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_recovery: Option<DbRecoveryReport>,
+    /// Upcoming switch of the network to a protocol version newer than
+    /// `latest_protocol_version`, after which the node can't follow the chain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_deprecation: Option<ProtocolDeprecationView>,
+    /// Latest network advisories accepted by the node, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network_advisories: Vec<NetworkAdvisoryView>,
+    /// Readiness checklist for the upcoming epoch switch, present only during the last
+    /// blocks of an epoch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epoch_readiness: Option<EpochReadinessView>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -1471,6 +1602,10 @@ pub struct CurrentEpochValidatorInfo {
     pub num_produced_chunks: NumBlocks,
     #[serde(default)]
     pub num_expected_chunks: NumBlocks,
+    /// Stake delegated to the validator, if it's a staking pool and the node is configured
+    /// with a source of the delegation metadata.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegation: Option<ValidatorDelegationView>,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -1481,6 +1616,26 @@ pub struct NextEpochValidatorInfo {
     #[serde(with = "u128_dec_format")]
     pub stake: Balance,
     pub shards: Vec<ShardId>,
+    /// Stake delegated to the validator, see `CurrentEpochValidatorInfo::delegation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delegation: Option<ValidatorDelegationView>,
+}
+
+/// Split of the stake of a staking pool between its owner and the delegators.  Reported for
+/// analytics only, the stake used by consensus is always the total one.
+#[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ValidatorDelegationView {
+    #[serde(with = "u128_dec_format")]
+    pub own_stake: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub delegated_stake: Balance,
+    pub num_delegators: u64,
+    /// Part of the reward for the previous epoch, minted at the start of this one, which is
+    /// attributed to the delegators in proportion to their stake, before the commission of
+    /// the pool.  Only known for the validators of the current epoch.
+    #[serde(default, with = "option_u128_dec_format", skip_serializing_if = "Option::is_none")]
+    pub delegators_reward: Option<Balance>,
 }
 
 #[cfg_attr(feature = "deepsize_feature", derive(deepsize::DeepSizeOf))]
@@ -1517,6 +1672,47 @@ pub struct GasPriceView {
     pub gas_price: Balance,
 }
 
+/// Gas price of a recent block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BlockGasPriceView {
+    pub block_height: BlockHeight,
+    #[serde(with = "u128_dec_format")]
+    pub gas_price: Balance,
+}
+
+/// Congestion of a shard as of the last block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShardCongestionView {
+    pub shard_id: ShardId,
+    /// Gas used by the last chunk of the shard and its limit. The gas price rises when the
+    /// chunks are more than half full.
+    pub gas_used: Gas,
+    pub gas_limit: Gas,
+    /// Number of receipts waiting in the delayed receipts queue of the shard, None if the
+    /// node doesn't track the shard. Transactions touching a congested shard take longer
+    /// to execute, whatever the attached gas price.
+    pub delayed_receipts: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GasPriceEstimateView {
+    pub block_height: BlockHeight,
+    pub block_hash: CryptoHash,
+    /// Gas price of the last block.
+    #[serde(with = "u128_dec_format")]
+    pub gas_price: Balance,
+    /// Gas prices of the blocks before the last one, most recent first.
+    pub recent_gas_prices: Vec<BlockGasPriceView>,
+    pub shards: Vec<ShardCongestionView>,
+    pub target_blocks: NumBlocks,
+    /// Highest gas price a transaction may be charged if it is included within
+    /// `target_blocks` blocks, that is, the price after as many blocks with full chunks.
+    /// Accounts holding enough balance for the gas at this price won't have their
+    /// transactions rejected for a price increase.
+    #[serde(with = "u128_dec_format")]
+    pub suggested_gas_price: Balance,
+}
+
 /// It is a [serializable view] of [`StateChangesRequest`].
 ///
 /// [serializable view]: ./index.html
@@ -15,6 +15,8 @@ use std::fmt;
     enum_map::Enum,
     strum::EnumIter,
     strum::IntoStaticStr,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 pub enum DBCol {
     /// Column to indicate which version of database this is.
@@ -233,6 +235,32 @@ pub enum DBCol {
     /// - *Rows*: BlockShardId (BlockHash || ShardId) - 40 bytes
     /// - *Column type*: StateChangesForSplitStates
     StateChangesForSplitStates = 49,
+    /// Peer blacklist and allowlist rules set by the node operator at runtime.
+    /// - *Rows*: blacklist entry (IP, IP:port, CIDR or peer id)
+    /// - *Column type*: AccessRule
+    PeerAccessRules = 50,
+    /// Height and shard in which a transaction or receipt was executed on the canonical
+    /// chain, so that its status can be looked up without guessing the shard. Outcomes of
+    /// blocks on forks are indexed when the blocks become canonical. The location may
+    /// still be stale after a reorg, so readers check that the block at that height has
+    /// an outcome for the id.
+    /// - *Rows*: outcome id (CryptoHash)
+    /// - *Column type*: OutcomeLocation
+    OutcomeLocations = 51,
+    /// Copies of the blocks pinned by the node operator, which are kept after the blocks
+    /// are garbage collected.
+    /// - *Rows*: block hash (CryptoHash)
+    /// - *Column type*: PinnedBlock
+    PinnedBlocks = 52,
+    /// Routed messages recently received by the network layer, saved on shutdown so that
+    /// their duplicates are dropped after a restart.
+    /// - *Rows*: author, target and signature of the message
+    /// - *Column type*: time the message was first received at
+    RoutedMessages = 53,
+    /// Index of the chunks included in the pinned blocks.
+    /// - *Rows*: chunk hash (ChunkHash)
+    /// - *Column type*: hash of the pinned block including the chunk (CryptoHash)
+    PinnedChunks = 54,
 }
 
 impl DBCol {
@@ -323,6 +351,10 @@ impl DBCol {
             | DBCol::PeerComponent  // Peer related info doesn't GC
             | DBCol::LastComponentNonce
             | DBCol::ComponentEdges
+            | DBCol::PeerAccessRules
+            | DBCol::PinnedBlocks  // unpinned by the node operator only
+            | DBCol::RoutedMessages
+            | DBCol::PinnedChunks  // unpinned by the node operator only
             | DBCol::BlockOrdinal
             | DBCol::EpochInfo           // https://github.com/nearprotocol/nearcore/pull/2952
             | DBCol::EpochValidatorInfo  // https://github.com/nearprotocol/nearcore/pull/2952
@@ -405,6 +437,11 @@ impl fmt::Display for DBCol {
             Self::EpochValidatorInfo => "epoch validator info",
             Self::HeaderHashesByHeight => "header hashes indexed by their height",
             Self::StateChangesForSplitStates => "state changes indexed by block hash and shard id",
+            Self::PeerAccessRules => "peer access rules",
+            Self::OutcomeLocations => "heights and shards of outcomes",
+            Self::PinnedBlocks => "copies of pinned blocks",
+            Self::RoutedMessages => "recently received routed messages",
+            Self::PinnedChunks => "pinned blocks by the chunks they include",
         };
         write!(f, "{}", desc)
     }
@@ -47,6 +47,47 @@ pub struct StoreConfig {
     /// We're still experimenting with this parameter and it seems decreasing its value can improve
     /// the performance of the storage
     pub trie_cache_capacities: Vec<(ShardUId, usize)>,
+
+    /// Limit on the rate at which flushes and compactions write to disk, in
+    /// bytes per second.
+    /// Default value: None, i.e. no limit.
+    /// Useful when the database shares the disk with other I/O-heavy processes
+    /// and background compactions starve foreground reads and writes.
+    pub rate_limiter_bytes_per_sec: Option<bytesize::ByteSize>,
+
+    /// Lower I/O priority of RocksDB background threads (flushes and
+    /// compactions).
+    /// Default value: false.
+    /// Only has effect on Linux.  Helps foreground reads which are on the
+    /// critical path of block processing at the cost of compactions possibly
+    /// falling behind.
+    pub lower_background_io_priority: bool,
+
+    /// Streaming of the committed transactions to an external sink, for replicas and
+    /// custom indexers following the database.
+    /// Default value: None, i.e. nothing is exported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub export: Option<StoreExportConfig>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StoreExportConfig {
+    pub sink: StoreExportSink,
+    /// Exported columns, e.g. `["Block", "Chunks"]`.  All if empty.
+    #[serde(default)]
+    pub columns: Vec<crate::DBCol>,
+}
+
+/// Where the committed transactions are exported, see [`crate::db::export`] for the format.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoreExportSink {
+    /// Appends the records to a file.  If relative, resolved relative to neard home
+    /// directory.
+    File(std::path::PathBuf),
+    /// Streams the records to a TCP address, e.g. "127.0.0.1:24568", reconnecting when the
+    /// connection breaks.
+    Tcp(String),
 }
 
 impl StoreConfig {
@@ -98,6 +139,11 @@ impl Default for StoreConfig {
             block_size: bytesize::ByteSize::kib(16),
 
             trie_cache_capacities: Default::default(),
+
+            rate_limiter_bytes_per_sec: None,
+            lower_background_io_priority: false,
+
+            export: None,
         }
     }
 }
@@ -118,6 +164,10 @@ pub struct StoreOpener<'a> {
     /// passed to [`Store::opener`].
     path: std::path::PathBuf,
 
+    /// Path to nearcore home directory, which relative paths of the export sink are resolved
+    /// from.
+    home_dir: std::path::PathBuf,
+
     /// Configuration as provided by the user.
     config: &'a StoreConfig,
 
@@ -130,7 +180,7 @@ impl<'a> StoreOpener<'a> {
     pub(crate) fn new(home_dir: &std::path::Path, config: &'a StoreConfig) -> Self {
         let path =
             home_dir.join(config.path.as_deref().unwrap_or(std::path::Path::new(STORE_PATH)));
-        Self { path, config, mode: Mode::ReadWrite }
+        Self { path, home_dir: home_dir.to_path_buf(), config, mode: Mode::ReadWrite }
     }
 
     /// Configure which mode the database should be opened in.
@@ -148,6 +198,18 @@ impl<'a> StoreOpener<'a> {
         self.path.join("CURRENT").is_file()
     }
 
+    /// Returns whether the database wasn't closed cleanly the last time it was opened for
+    /// writing, e.g. because the node crashed, was killed or the machine lost power.
+    pub fn shut_down_uncleanly(&self) -> bool {
+        self.path.join(crate::db::RUNNING_MARKER).is_file()
+    }
+
+    /// Tries to recover a database which can't be opened, see [`crate::RocksDB::repair`].
+    pub fn repair(&self) -> std::io::Result<()> {
+        tracing::warn!(target: "near", path=%self.path.display(), "Repairing RocksDB database");
+        crate::RocksDB::repair(&self.path, &self.config)
+    }
+
     /// Returns path to the underlying RocksDB database.
     ///
     /// Does not check whether the database actually exists.
@@ -166,19 +228,39 @@ impl<'a> StoreOpener<'a> {
 
     /// Opens the RocksDB database.
     ///
-    /// Panics on failure.
-    // TODO(mina86): Change it to return Result.
+    /// Panics on failure, see [`Self::try_open`].
     pub fn open(&self) -> crate::Store {
+        self.try_open().expect("Failed to open the database")
+    }
+
+    /// Opens the RocksDB database.
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` if the database files are corrupted.
+    pub fn try_open(&self) -> std::io::Result<crate::Store> {
         if self.check_if_exists() {
             tracing::info!(target: "near", path=%self.path.display(), "Opening RocksDB database");
         } else if matches!(self.mode, Mode::ReadOnly) {
             tracing::error!(target: "near", path=%self.path.display(), "Database does not exist");
-            panic!("Failed to open non-existent database for reading");
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Failed to open non-existent database for reading",
+            ));
         } else {
             tracing::info!(target: "near", path=%self.path.display(), "Creating new RocksDB database");
         }
-        let db = crate::RocksDB::open(&self.path, &self.config, self.mode)
-            .expect("Failed to open the database");
-        crate::Store::new(std::sync::Arc::new(db))
+        let db = crate::RocksDB::open(&self.path, &self.config, self.mode)?;
+        let store = match &self.config.export {
+            Some(export) if matches!(self.mode, Mode::ReadWrite) => {
+                let sink = match &export.sink {
+                    StoreExportSink::File(path) => StoreExportSink::File(self.home_dir.join(path)),
+                    sink => sink.clone(),
+                };
+                let db =
+                    crate::db::export::ExportDB::new(Box::new(db), sink, export.columns.clone())?;
+                crate::Store::new(std::sync::Arc::new(db))
+            }
+            _ => crate::Store::new(std::sync::Arc::new(db)),
+        };
+        Ok(store)
     }
 }
@@ -16,6 +16,7 @@ use std::sync::{Condvar, Mutex, RwLock};
 use strum::IntoEnumIterator;
 use tracing::{error, info, warn};
 
+pub mod export;
 pub mod refcount;
 
 pub const VERSION_KEY: &[u8; 7] = b"VERSION";
@@ -24,6 +25,7 @@ pub const HEAD_KEY: &[u8; 4] = b"HEAD";
 pub const TAIL_KEY: &[u8; 4] = b"TAIL";
 pub const CHUNK_TAIL_KEY: &[u8; 10] = b"CHUNK_TAIL";
 pub const FORK_TAIL_KEY: &[u8; 9] = b"FORK_TAIL";
+pub const OUTCOME_LOCATIONS_TAIL_KEY: &[u8; 22] = b"OUTCOME_LOCATIONS_TAIL";
 pub const HEADER_HEAD_KEY: &[u8; 11] = b"HEADER_HEAD";
 pub const FINAL_HEAD_KEY: &[u8; 10] = b"FINAL_HEAD";
 pub const LATEST_KNOWN_KEY: &[u8; 12] = b"LATEST_KNOWN";
@@ -34,6 +36,10 @@ pub const GENESIS_STATE_ROOTS_KEY: &[u8; 19] = b"GENESIS_STATE_ROOTS";
 /// archival node.  The default value (if missing) is false.
 pub const IS_ARCHIVE_KEY: &[u8; 10] = b"IS_ARCHIVE";
 
+/// File created in the database directory while the database is open for writing and
+/// removed when it's closed, so that an unclean shutdown can be detected on the next start.
+pub(crate) const RUNNING_MARKER: &str = "NEARD_RUNNING";
+
 #[derive(Default)]
 pub struct DBTransaction {
     pub(crate) ops: Vec<DBOp>,
@@ -99,6 +105,9 @@ pub struct RocksDB {
     check_free_space_interval: u16,
     free_space_threshold: bytesize::ByteSize,
 
+    /// Path of the [`RUNNING_MARKER`] file if the database is open for writing.
+    running_marker: Option<std::path::PathBuf>,
+
     // RAII-style of keeping track of the number of instances of RocksDB in a global variable.
     _instance_counter: InstanceCounter,
 }
@@ -116,6 +125,17 @@ fn into_other(error: rocksdb::Error) -> io::Error {
     io::Error::new(io::ErrorKind::Other, error.into_string())
 }
 
+/// Like [`into_other`], but reports corrupted database files as
+/// `io::ErrorKind::InvalidData`, since only such databases are worth repairing.
+fn into_open_error(error: rocksdb::Error) -> io::Error {
+    let kind = if error.as_ref().starts_with("Corruption:") {
+        io::ErrorKind::InvalidData
+    } else {
+        io::ErrorKind::Other
+    };
+    io::Error::new(kind, error.into_string())
+}
+
 fn col_name(col: DBCol) -> String {
     format!("col{}", col as usize)
 }
@@ -170,10 +190,20 @@ pub enum Mode {
 impl RocksDB {
     /// Opens the database either in read only or in read/write mode depending
     /// on the `mode` parameter specified in the store_config.
+    ///
+    /// Fails with `io::ErrorKind::InvalidData` if the database files are corrupted.
     pub fn open(path: &Path, store_config: &StoreConfig, mode: Mode) -> io::Result<RocksDB> {
         ensure_max_open_files_limit(store_config.max_open_files).map_err(other_error)?;
         let (db, db_opt) = Self::open_db(path, store_config, mode)?;
         let cf_handles = Self::get_cf_handles(&db);
+        let running_marker = match mode {
+            Mode::ReadOnly => None,
+            Mode::ReadWrite => {
+                let marker = path.join(RUNNING_MARKER);
+                std::fs::write(&marker, b"")?;
+                Some(marker)
+            }
+        };
 
         Ok(Self {
             db,
@@ -182,13 +212,21 @@ impl RocksDB {
             check_free_space_interval: 256,
             check_free_space_counter: std::sync::atomic::AtomicU16::new(0),
             free_space_threshold: bytesize::ByteSize::mb(16),
+            running_marker,
             _instance_counter: InstanceCounter::new(),
         })
     }
 
     /// Opens the database with all column families configured.
     fn open_db(path: &Path, store_config: &StoreConfig, mode: Mode) -> io::Result<(DB, Options)> {
-        let options = rocksdb_options(store_config, mode);
+        let mut options = rocksdb_options(store_config, mode);
+        if store_config.lower_background_io_priority && matches!(mode, Mode::ReadWrite) {
+            let mut env = Env::default().map_err(into_other)?;
+            // Low priority pool runs compactions, high priority one flushes.
+            env.lower_thread_pool_io_priority();
+            env.lower_high_priority_thread_pool_io_priority();
+            options.set_env(&env);
+        }
         let cf_descriptors = DBCol::iter()
             .map(|col| {
                 rocksdb::ColumnFamilyDescriptor::new(
@@ -203,7 +241,7 @@ impl RocksDB {
             }
             Mode::ReadWrite => DB::open_cf_descriptors(&options, path, cf_descriptors),
         }
-        .map_err(into_other)?;
+        .map_err(into_open_error)?;
         if cfg!(feature = "single_thread_rocksdb") {
             // These have to be set after open db
             let mut env = Env::default().unwrap();
@@ -216,6 +254,14 @@ impl RocksDB {
         Ok((db, options))
     }
 
+    /// Tries to recover a database which can't be opened, e.g. because some of its files
+    /// got corrupted in a crash, salvaging as much of the data as possible.  The data in
+    /// the corrupted files is lost.
+    pub fn repair(path: &Path, store_config: &StoreConfig) -> io::Result<()> {
+        let options = rocksdb_options(store_config, Mode::ReadWrite);
+        DB::repair(&options, path).map_err(into_other)
+    }
+
     /// Returns mapping from [`DBCol`] to cf handle used with RocksDB calls.
     fn get_cf_handles(db: &DB) -> enum_map::EnumMap<DBCol, std::ptr::NonNull<ColumnFamily>> {
         let mut cf_handles = enum_map::EnumMap::default();
@@ -431,17 +477,59 @@ impl Database for RocksDB {
     }
 
     fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        let mut result = StoreStatistics { data: vec![] };
         if let Some(stats_str) = self.db_opt.get_statistics() {
             match parse_statistics(&stats_str) {
-                Ok(parsed_statistics) => {
-                    return Some(parsed_statistics);
-                }
+                Ok(parsed_statistics) => result = parsed_statistics,
                 Err(err) => {
                     warn!(target: "store", "Failed to parse store statistics: {:?}", err);
                 }
             }
         }
-        None
+        // Properties are available even if statistics collection is disabled.
+        // They tell whether RocksDB is throttling writes which otherwise shows
+        // up only as unexplained block processing latency.
+        match self.get_write_stall_properties() {
+            Ok(properties) => result.data.extend(properties),
+            Err(err) => warn!(target: "store", "Failed to read store properties: {:?}", err),
+        }
+        if result.data.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+}
+
+impl RocksDB {
+    /// Returns RocksDB properties describing write stalls and compaction
+    /// backlog, formatted the same way as parsed statistics.
+    ///
+    /// Pending compaction bytes are summed over all column families.
+    fn get_write_stall_properties(&self) -> Result<Vec<(String, Vec<StatsValue>)>, rocksdb::Error> {
+        let mut result = vec![];
+        for name in [
+            rocksdb::properties::IS_WRITE_STOPPED,
+            rocksdb::properties::ACTUAL_DELAYED_WRITE_RATE,
+            rocksdb::properties::NUM_RUNNING_COMPACTIONS,
+            rocksdb::properties::NUM_RUNNING_FLUSHES,
+        ] {
+            if let Some(value) = self.db.property_int_value(name)? {
+                result.push((name.to_string(), vec![StatsValue::Count(value as i64)]));
+            }
+        }
+        for name in [
+            rocksdb::properties::ESTIMATE_PENDING_COMPACTION_BYTES,
+            rocksdb::properties::COMPACTION_PENDING,
+            rocksdb::properties::NUM_IMMUTABLE_MEM_TABLE,
+        ] {
+            let mut total = 0;
+            for col in DBCol::iter() {
+                total += self.db.property_int_value_cf(self.cf_handle(col), name)?.unwrap_or(0);
+            }
+            result.push((name.to_string(), vec![StatsValue::Count(total as i64)]));
+        }
+        Ok(result)
     }
 }
 
@@ -607,6 +695,11 @@ fn rocksdb_options(store_config: &StoreConfig, mode: Mode) -> Options {
         opts.set_max_total_wal_size(bytesize::GIB);
     }
 
+    if let Some(rate_limit) = store_config.rate_limiter_bytes_per_sec {
+        // Refill period and fairness are RocksDB’s defaults.
+        opts.set_ratelimiter(rate_limit.as_u64().try_into().unwrap_or(i64::MAX), 100_000, 10);
+    }
+
     if read_write && store_config.enable_statistics {
         // Rust API doesn't permit choosing stats level. The default stats level
         // is `kExceptDetailedTimers`, which is described as: "Collects all
@@ -771,6 +864,11 @@ impl Drop for RocksDB {
             env.set_background_threads(4);
         }
         self.db.cancel_all_background_work(true);
+        if let Some(marker) = &self.running_marker {
+            if let Err(err) = std::fs::remove_file(marker) {
+                warn!(target: "db", ?err, "Failed to remove {}", marker.display());
+            }
+        }
     }
 }
 
@@ -864,6 +962,49 @@ mod tests {
         store.pre_write_check().unwrap()
     }
 
+    #[test]
+    fn test_unclean_shutdown_marker() {
+        let (_tmp_dir, opener) = Store::test_opener();
+        assert!(!opener.shut_down_uncleanly());
+        let store = opener.open();
+        assert!(opener.shut_down_uncleanly());
+        drop(store);
+        assert!(!opener.shut_down_uncleanly());
+
+        // A crash leaves the marker behind.
+        std::fs::write(opener.get_path().join(super::RUNNING_MARKER), b"").unwrap();
+        assert!(opener.shut_down_uncleanly());
+        opener.repair().unwrap();
+        opener.open();
+    }
+
+    #[test]
+    fn test_open_corrupted() {
+        let (_tmp_dir, opener) = Store::test_opener();
+        drop(opener.open());
+        std::fs::write(opener.get_path().join("CURRENT"), b"garbage").unwrap();
+        let err = opener.try_open().err().expect("corrupted database should fail to open");
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    }
+
+    #[test]
+    fn test_write_stall_properties() {
+        let tmp_dir = tempfile::Builder::new().prefix("write_stall").tempdir().unwrap();
+        let config = StoreConfig {
+            rate_limiter_bytes_per_sec: Some(bytesize::ByteSize::mib(64)),
+            lower_background_io_priority: true,
+            ..StoreConfig::test_config()
+        };
+        let store = RocksDB::open(tmp_dir.path(), &config, Mode::ReadWrite).unwrap();
+        // Statistics are disabled but properties are still reported.
+        let stats = store.get_store_statistics().unwrap();
+        let get = |name: &str| {
+            stats.data.iter().find(|(stat, _)| stat == name).map(|(_, values)| values.clone())
+        };
+        assert_eq!(get("rocksdb.is-write-stopped"), Some(vec![Count(0)]));
+        assert_eq!(get("rocksdb.estimate-pending-compaction-bytes"), Some(vec![Count(0)]));
+    }
+
     #[test]
     fn rocksdb_merge_sanity() {
         let (_tmp_dir, opener) = Store::test_opener();
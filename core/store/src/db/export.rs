@@ -0,0 +1,286 @@
+//! Streaming of the committed transactions to an external sink, so that replicas and
+//! custom indexers can follow the database without reading RocksDB directly.
+//!
+//! Every transaction which touches an exported column becomes an [`ExportRecord`] with the
+//! next sequence number.  The sequence number is stored in the database in the same
+//! transaction, so the numbering continues after a restart.  Records are written to the
+//! sink in commit order by a background thread.  If the sink can't keep up or is
+//! unavailable for long, records are dropped rather than stalling the node; consumers
+//! detect that by a gap in the sequence numbers and have to resynchronise.
+use super::{DBIterator, DBOp, DBTransaction, Database, StoreStatistics};
+use crate::config::StoreExportSink;
+use crate::{metrics, DBCol};
+use borsh::{BorshDeserialize, BorshSerialize};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Key in [`DBCol::BlockMisc`] of the sequence number of the last exported record.
+pub const EXPORT_SEQUENCE_KEY: &[u8; 15] = b"EXPORT_SEQUENCE";
+
+/// Number of records buffered for the sink before new ones are dropped.
+const BUFFERED_RECORDS: usize = 10_000;
+/// Delay before opening the sink again after a failure.
+const REOPEN_DELAY: Duration = Duration::from_secs(1);
+
+/// Committed transaction, restricted to the exported columns.  On the wire, each record
+/// is preceded by the length of its borsh serialization as u32 little endian.
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
+pub struct ExportRecord {
+    /// Sequence number, starting from 1 and increasing by 1 with every record.
+    pub seq: u64,
+    pub ops: Vec<ExportOp>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, PartialEq, Eq)]
+pub enum ExportOp {
+    Set {
+        col: DBCol,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    /// Change of a reference-counted value, `value` ends with the refcount delta.  See
+    /// [`super::refcount`].
+    UpdateRefcount {
+        col: DBCol,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Delete {
+        col: DBCol,
+        key: Vec<u8>,
+    },
+    DeleteAll {
+        col: DBCol,
+    },
+}
+
+/// Database which exports every committed transaction, see the module documentation.
+pub(crate) struct ExportDB {
+    db: Box<dyn Database>,
+    /// Exported columns, all if empty.
+    columns: Vec<DBCol>,
+    /// Sequence number of the last record.  Locked while the transaction is written, so
+    /// that the records are numbered in commit order.
+    seq: Mutex<u64>,
+    sender: SyncSender<Vec<u8>>,
+}
+
+impl ExportDB {
+    pub(crate) fn new(
+        db: Box<dyn Database>,
+        sink: StoreExportSink,
+        columns: Vec<DBCol>,
+    ) -> io::Result<Self> {
+        let seq = match db.get_raw_bytes(DBCol::BlockMisc, EXPORT_SEQUENCE_KEY)? {
+            Some(bytes) => u64::try_from_slice(&bytes)?,
+            None => 0,
+        };
+        let (sender, receiver) = sync_channel(BUFFERED_RECORDS);
+        std::thread::Builder::new()
+            .name("store_export".to_string())
+            .spawn(move || write_records(sink, receiver))?;
+        tracing::info!(target: "store", seq, "Exporting committed transactions");
+        Ok(Self { db, columns, seq: Mutex::new(seq), sender })
+    }
+
+    fn is_exported(&self, col: DBCol) -> bool {
+        self.columns.is_empty() || self.columns.contains(&col)
+    }
+
+    fn export_ops(&self, transaction: &DBTransaction) -> Vec<ExportOp> {
+        let mut ops = vec![];
+        for op in &transaction.ops {
+            let col = match op {
+                DBOp::Set { col, .. }
+                | DBOp::Insert { col, .. }
+                | DBOp::UpdateRefcount { col, .. }
+                | DBOp::Delete { col, .. }
+                | DBOp::DeleteAll { col } => *col,
+            };
+            if !self.is_exported(col) {
+                continue;
+            }
+            ops.push(match op {
+                DBOp::Set { key, value, .. } | DBOp::Insert { key, value, .. } => {
+                    ExportOp::Set { col, key: key.clone(), value: value.clone() }
+                }
+                DBOp::UpdateRefcount { key, value, .. } => {
+                    ExportOp::UpdateRefcount { col, key: key.clone(), value: value.clone() }
+                }
+                DBOp::Delete { key, .. } => ExportOp::Delete { col, key: key.clone() },
+                DBOp::DeleteAll { .. } => ExportOp::DeleteAll { col },
+            });
+        }
+        ops
+    }
+}
+
+impl Database for ExportDB {
+    fn get_raw_bytes(&self, col: DBCol, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        self.db.get_raw_bytes(col, key)
+    }
+
+    fn iter<'a>(&'a self, column: DBCol) -> DBIterator<'a> {
+        self.db.iter(column)
+    }
+
+    fn iter_prefix<'a>(&'a self, col: DBCol, key_prefix: &'a [u8]) -> DBIterator<'a> {
+        self.db.iter_prefix(col, key_prefix)
+    }
+
+    fn iter_raw_bytes<'a>(&'a self, column: DBCol) -> DBIterator<'a> {
+        self.db.iter_raw_bytes(column)
+    }
+
+    fn write(&self, mut transaction: DBTransaction) -> io::Result<()> {
+        let ops = self.export_ops(&transaction);
+        if ops.is_empty() {
+            return self.db.write(transaction);
+        }
+        let mut last_seq = self.seq.lock().unwrap();
+        let seq = *last_seq + 1;
+        transaction.set(DBCol::BlockMisc, EXPORT_SEQUENCE_KEY.to_vec(), seq.try_to_vec()?);
+        self.db.write(transaction)?;
+        *last_seq = seq;
+
+        let record = ExportRecord { seq, ops }.try_to_vec()?;
+        let mut data = Vec::with_capacity(4 + record.len());
+        data.extend_from_slice(&(record.len() as u32).to_le_bytes());
+        data.extend_from_slice(&record);
+        match self.sender.try_send(data) {
+            Ok(()) => metrics::EXPORTED_RECORDS.with_label_values(&["buffered"]).inc(),
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                metrics::EXPORTED_RECORDS.with_label_values(&["dropped"]).inc();
+                tracing::warn!(target: "store", seq, "Export sink is behind, dropped record");
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.db.flush()
+    }
+
+    fn get_store_statistics(&self) -> Option<StoreStatistics> {
+        self.db.get_store_statistics()
+    }
+}
+
+/// Writes the records to the sink until the database is closed, reopening the sink after
+/// failures.  A record which fails to be written is retried with the reopened sink.
+fn write_records(sink: StoreExportSink, receiver: Receiver<Vec<u8>>) {
+    let mut writer = None;
+    for data in receiver {
+        loop {
+            match write_record(&sink, &mut writer, &data) {
+                Ok(()) => {
+                    metrics::EXPORTED_RECORDS.with_label_values(&["written"]).inc();
+                    break;
+                }
+                Err(err) => {
+                    tracing::warn!(target: "store", ?sink, %err, "Failed to export records");
+                    writer = None;
+                    std::thread::sleep(REOPEN_DELAY);
+                }
+            }
+        }
+    }
+}
+
+fn write_record(
+    sink: &StoreExportSink,
+    writer: &mut Option<Box<dyn Write>>,
+    data: &[u8],
+) -> io::Result<()> {
+    if writer.is_none() {
+        *writer = Some(open_sink(sink)?);
+    }
+    let writer = writer.as_mut().unwrap();
+    writer.write_all(data)?;
+    writer.flush()
+}
+
+fn open_sink(sink: &StoreExportSink) -> io::Result<Box<dyn Write>> {
+    Ok(match sink {
+        StoreExportSink::File(path) => {
+            Box::new(std::fs::OpenOptions::new().create(true).append(true).open(path)?)
+        }
+        StoreExportSink::Tcp(addr) => Box::new(TcpStream::connect(addr.as_str())?),
+    })
+}
+
+/// Reads the records written to a file sink.
+pub fn read_records(path: &Path) -> io::Result<Vec<ExportRecord>> {
+    let data = std::fs::read(path)?;
+    let mut records = vec![];
+    let mut rest = data.as_slice();
+    while !rest.is_empty() {
+        let len = u32::deserialize(&mut rest)? as usize;
+        if rest.len() < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated record"));
+        }
+        records.push(ExportRecord::try_from_slice(&rest[..len])?);
+        rest = &rest[len..];
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TestDB;
+
+    #[test]
+    fn test_export_records() {
+        let tmp_dir = tempfile::Builder::new().prefix("export").tempdir().unwrap();
+        let path = tmp_dir.path().join("export");
+        let db = ExportDB::new(
+            Box::new(TestDB::new()),
+            StoreExportSink::File(path.clone()),
+            vec![DBCol::Block],
+        )
+        .unwrap();
+
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::Block, b"a".to_vec(), b"1".to_vec());
+        transaction.set(DBCol::BlockHeader, b"b".to_vec(), b"2".to_vec());
+        db.write(transaction).unwrap();
+        // Not exported, so doesn't take a sequence number.
+        let mut transaction = DBTransaction::new();
+        transaction.set(DBCol::BlockHeader, b"c".to_vec(), b"3".to_vec());
+        db.write(transaction).unwrap();
+        let mut transaction = DBTransaction::new();
+        transaction.delete(DBCol::Block, b"a".to_vec());
+        db.write(transaction).unwrap();
+
+        let expected = vec![
+            ExportRecord {
+                seq: 1,
+                ops: vec![ExportOp::Set {
+                    col: DBCol::Block,
+                    key: b"a".to_vec(),
+                    value: b"1".to_vec(),
+                }],
+            },
+            ExportRecord {
+                seq: 2,
+                ops: vec![ExportOp::Delete { col: DBCol::Block, key: b"a".to_vec() }],
+            },
+        ];
+        for _ in 0..100 {
+            if read_records(&path).map_or(false, |records| records.len() == expected.len()) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(read_records(&path).unwrap(), expected);
+        assert_eq!(
+            db.get_raw_bytes(DBCol::BlockMisc, EXPORT_SEQUENCE_KEY).unwrap(),
+            Some(2u64.try_to_vec().unwrap())
+        );
+    }
+}
@@ -11,7 +11,7 @@ use once_cell::sync::Lazy;
 pub use columns::DBCol;
 pub use db::{
     CHUNK_TAIL_KEY, FINAL_HEAD_KEY, FORK_TAIL_KEY, HEADER_HEAD_KEY, HEAD_KEY,
-    LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY, TAIL_KEY,
+    LARGEST_TARGET_HEIGHT_KEY, LATEST_KNOWN_KEY, OUTCOME_LOCATIONS_TAIL_KEY, TAIL_KEY,
 };
 use near_crypto::PublicKey;
 use near_primitives::account::{AccessKey, Account};
@@ -31,8 +31,8 @@ use crate::db::{
 pub use crate::trie::iterator::TrieIterator;
 pub use crate::trie::update::{TrieUpdate, TrieUpdateIterator, TrieUpdateValuePtr};
 pub use crate::trie::{
-    estimator, split_state, ApplyStatePartResult, KeyForStateChanges, PartialStorage, ShardTries,
-    Trie, TrieCache, TrieCacheFactory, TrieCachingStorage, TrieChanges, TrieStorage,
+    estimator, split_state, ApplyStatePartResult, KeyForStateChanges, PartialStorage, ReadOnlyTrie,
+    ShardTries, Trie, TrieCache, TrieCacheFactory, TrieCachingStorage, TrieChanges, TrieStorage,
     WrappedTrieChanges,
 };
 
@@ -44,7 +44,7 @@ pub mod migrations;
 pub mod test_utils;
 mod trie;
 
-pub use crate::config::{StoreConfig, StoreOpener};
+pub use crate::config::{StoreConfig, StoreExportConfig, StoreExportSink, StoreOpener};
 
 #[derive(Clone)]
 pub struct Store {
@@ -1,4 +1,6 @@
-use near_metrics::{try_create_histogram_vec, HistogramVec};
+use near_metrics::{
+    try_create_histogram_vec, try_create_int_counter_vec, HistogramVec, IntCounterVec,
+};
 use once_cell::sync::Lazy;
 
 pub(crate) static DATABASE_OP_LATENCY_HIST: Lazy<HistogramVec> = Lazy::new(|| {
@@ -10,3 +12,13 @@ pub(crate) static DATABASE_OP_LATENCY_HIST: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static EXPORTED_RECORDS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_store_exported_records",
+        "Number of records of committed transactions exported to the sink, by status: \
+         buffered for the sink, dropped because the sink was behind, or written to the sink",
+        &["status"],
+    )
+    .unwrap()
+});
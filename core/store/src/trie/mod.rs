@@ -17,7 +17,7 @@ use crate::trie::insert_delete::NodesStorage;
 use crate::trie::iterator::TrieIterator;
 use crate::trie::nibble_slice::NibbleSlice;
 pub use crate::trie::shard_tries::{
-    KeyForStateChanges, ShardTries, TrieCacheFactory, WrappedTrieChanges,
+    KeyForStateChanges, ReadOnlyTrie, ShardTries, TrieCacheFactory, WrappedTrieChanges,
 };
 pub use crate::trie::trie_storage::{TrieCache, TrieCachingStorage, TrieStorage};
 use crate::trie::trie_storage::{TrieMemoryPartialStorage, TrieRecordingStorage};
@@ -489,6 +489,22 @@ impl Trie {
         StateRoot::default()
     }
 
+    /// Starts measuring the size of the storage proof of the nodes and values read from now
+    /// on, i.e. of the state witness, see `recorded_proof_size`. Unlike `recording_reads`, the
+    /// nodes are neither kept nor counted differently, so that it can be enabled when applying
+    /// chunks. Does nothing if the storage is not `TrieCachingStorage`.
+    pub fn record_proof_size(&self) {
+        if let Some(storage) = self.storage.as_caching_storage() {
+            storage.start_recording_proof_size();
+        }
+    }
+
+    /// Size of the distinct nodes and values read since `record_proof_size`, None if not
+    /// recording.
+    pub fn recorded_proof_size(&self) -> Option<u64> {
+        self.storage.as_caching_storage()?.recorded_proof_size()
+    }
+
     pub fn recorded_storage(&self) -> Option<PartialStorage> {
         let storage = self.storage.as_recording_storage()?;
         let mut nodes: Vec<_> =
@@ -1178,6 +1194,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_trie_recorded_proof_size() {
+        let store = create_test_store();
+        let tries = ShardTries::test(store, 1);
+        let empty_root = Trie::empty_root();
+        let changes = vec![
+            (b"doge".to_vec(), Some(b"coin".to_vec())),
+            (b"docu".to_vec(), Some(b"value".to_vec())),
+            (b"horse".to_vec(), Some(b"stallion".to_vec())),
+        ];
+        let root = test_populate_trie(&tries, &empty_root, ShardUId::single_shard(), changes);
+
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard());
+        assert_eq!(trie.recorded_proof_size(), None);
+        trie.record_proof_size();
+        assert_eq!(trie.recorded_proof_size(), Some(0));
+        trie.get(&root, b"doge").unwrap();
+        trie.get(&root, b"doge").unwrap();
+        trie.get(&root, b"horse").unwrap();
+
+        // Same size as the proof of the reads, with each node counted once.
+        let recording_trie = tries.get_trie_for_shard(ShardUId::single_shard()).recording_reads();
+        recording_trie.get(&root, b"doge").unwrap();
+        recording_trie.get(&root, b"horse").unwrap();
+        let proof = recording_trie.recorded_storage().unwrap();
+        let proof_size: usize = proof.nodes.0.iter().map(Vec::len).sum();
+        assert_eq!(trie.recorded_proof_size(), Some(proof_size as u64));
+    }
+
     #[test]
     fn test_dump_load_trie() {
         let store = create_test_store();
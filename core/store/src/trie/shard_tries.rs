@@ -4,6 +4,7 @@ use std::sync::{Arc, RwLock};
 
 use borsh::BorshSerialize;
 use near_primitives::borsh::maybestd::collections::HashMap;
+use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout;
 use near_primitives::shard_layout::{ShardUId, ShardVersion};
@@ -92,15 +93,46 @@ impl ShardTries {
         TrieUpdate::new(Rc::new(self.get_view_trie_for_shard(shard_uid)), state_root)
     }
 
-    fn get_trie_for_shard_internal(&self, shard_uid: ShardUId, is_view: bool) -> Trie {
+    /// Creates a read-only handle to the trie of the shard at `state_root`, which can be sent
+    /// to and shared between threads. Uses the cache of the client actor.
+    pub fn new_read_only_trie(&self, shard_uid: ShardUId, state_root: StateRoot) -> ReadOnlyTrie {
+        self.new_read_only_trie_internal(shard_uid, state_root, false)
+    }
+
+    /// Same as `new_read_only_trie`, but uses the cache of the readers.
+    pub fn new_read_only_trie_view(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+    ) -> ReadOnlyTrie {
+        self.new_read_only_trie_internal(shard_uid, state_root, true)
+    }
+
+    fn new_read_only_trie_internal(
+        &self,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        is_view: bool,
+    ) -> ReadOnlyTrie {
+        ReadOnlyTrie {
+            store: self.0.store.clone(),
+            cache: self.get_cache(shard_uid, is_view),
+            shard_uid,
+            state_root,
+        }
+    }
+
+    fn get_cache(&self, shard_uid: ShardUId, is_view: bool) -> TrieCache {
         let caches_to_use = if is_view { &self.0.view_caches } else { &self.0.caches };
-        let cache = {
-            let mut caches = caches_to_use.write().expect(POISONED_LOCK_ERR);
-            caches
-                .entry(shard_uid)
-                .or_insert_with(|| self.0.trie_cache_factory.create_cache(&shard_uid))
-                .clone()
-        };
+        let mut caches = caches_to_use.write().expect(POISONED_LOCK_ERR);
+        caches
+            .entry(shard_uid)
+            .or_insert_with(|| self.0.trie_cache_factory.create_cache(&shard_uid))
+            .clone()
+    }
+
+    fn get_trie_for_shard_internal(&self, shard_uid: ShardUId, is_view: bool) -> Trie {
+        let cache = self.get_cache(shard_uid, is_view);
         let store = Box::new(TrieCachingStorage::new(self.0.store.clone(), cache, shard_uid));
         Trie::new(store)
     }
@@ -240,6 +272,49 @@ impl ShardTries {
     }
 }
 
+/// Read-only handle to the trie of a shard at a fixed state root.
+///
+/// Unlike `Trie` and `TrieUpdate`, the handle is `Send + Sync` and doesn't borrow anything,
+/// so it can be cloned and moved into other threads, e.g. a rayon pool. Every thread creates
+/// its own `Trie` with `trie()`, all of them sharing the shard cache.
+#[derive(Clone)]
+pub struct ReadOnlyTrie {
+    store: Store,
+    cache: TrieCache,
+    shard_uid: ShardUId,
+    state_root: StateRoot,
+}
+
+impl ReadOnlyTrie {
+    pub fn shard_uid(&self) -> ShardUId {
+        self.shard_uid
+    }
+
+    pub fn state_root(&self) -> &StateRoot {
+        &self.state_root
+    }
+
+    /// Creates a `Trie` for the current thread, reading through the shared shard cache.
+    pub fn trie(&self) -> Trie {
+        let storage =
+            TrieCachingStorage::new(self.store.clone(), self.cache.clone(), self.shard_uid);
+        Trie::new(Box::new(storage))
+    }
+
+    /// Creates a `TrieUpdate` for the current thread, on top of the pinned state root.
+    pub fn trie_update(&self) -> TrieUpdate {
+        TrieUpdate::new(Rc::new(self.trie()), self.state_root)
+    }
+
+    pub fn get_ref(&self, key: &[u8]) -> Result<Option<(u32, CryptoHash)>, StorageError> {
+        self.trie().get_ref(&self.state_root, key)
+    }
+
+    pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        self.trie().get(&self.state_root, key)
+    }
+}
+
 pub struct WrappedTrieChanges {
     tries: ShardTries,
     shard_uid: ShardUId,
@@ -179,6 +179,12 @@ pub struct TrieCachingStorage {
     pub(crate) db_read_nodes: Cell<u64>,
     /// Counts trie nodes retrieved from the chunk cache.
     pub(crate) mem_read_nodes: Cell<u64>,
+
+    /// Hashes of the nodes and values read since `start_recording_proof_size`, None if not
+    /// recording. Only the hashes are kept, as only the size of the proof is measured.
+    pub(crate) proof_nodes: RefCell<Option<HashSet<CryptoHash>>>,
+    /// Total size of the nodes and values in `proof_nodes`.
+    pub(crate) proof_size: Cell<u64>,
 }
 
 impl TrieCachingStorage {
@@ -191,6 +197,8 @@ impl TrieCachingStorage {
             chunk_cache: RefCell::new(Default::default()),
             db_read_nodes: Cell::new(0),
             mem_read_nodes: Cell::new(0),
+            proof_nodes: RefCell::new(None),
+            proof_size: Cell::new(0),
         }
     }
 
@@ -227,6 +235,26 @@ impl TrieCachingStorage {
     pub fn set_mode(&self, state: TrieCacheMode) {
         self.cache_mode.set(state);
     }
+
+    /// Starts measuring the size of the storage proof of the reads, see `Trie::record_proof_size`.
+    pub fn start_recording_proof_size(&self) {
+        *self.proof_nodes.borrow_mut() = Some(HashSet::new());
+        self.proof_size.set(0);
+    }
+
+    /// Size of the distinct nodes and values read since `start_recording_proof_size`, None if not
+    /// recording.
+    pub fn recorded_proof_size(&self) -> Option<u64> {
+        self.proof_nodes.borrow().as_ref().map(|_| self.proof_size.get())
+    }
+
+    fn record_proof_node(&self, hash: &CryptoHash, val: &[u8]) {
+        if let Some(proof_nodes) = self.proof_nodes.borrow_mut().as_mut() {
+            if proof_nodes.insert(*hash) {
+                self.proof_size.set(self.proof_size.get() + val.len() as u64);
+            }
+        }
+    }
 }
 
 impl TrieStorage for TrieCachingStorage {
@@ -235,6 +263,7 @@ impl TrieStorage for TrieCachingStorage {
         // because we charge for reading nodes only when `CachingChunk` mode is enabled anyway.
         if let Some(val) = self.chunk_cache.borrow_mut().get(hash) {
             self.inc_mem_read_nodes();
+            self.record_proof_node(hash, val);
             return Ok(val.clone());
         }
 
@@ -286,6 +315,7 @@ impl TrieStorage for TrieCachingStorage {
         if let TrieCacheMode::CachingChunk = self.cache_mode.borrow().get() {
             self.chunk_cache.borrow_mut().insert(*hash, val.clone());
         };
+        self.record_proof_node(hash, &val);
 
         Ok(val)
     }
@@ -373,3 +373,47 @@ mod caching_storage_tests {
         assert_eq!(count_delta.mem_reads, 1);
     }
 }
+
+#[cfg(test)]
+mod read_only_trie_tests {
+    use super::*;
+    use crate::test_utils::create_tries;
+    use crate::ReadOnlyTrie;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_read_only_trie_concurrent_reads() {
+        assert_send_sync::<ReadOnlyTrie>();
+
+        let tries = create_tries();
+        let shard_uid = ShardUId::single_shard();
+        let changes: Vec<_> = (0..100u8).map(|i| (vec![i, i], Some(vec![i; 10]))).collect();
+        let state_root =
+            test_populate_trie(&tries, &Trie::empty_root(), shard_uid, changes.clone());
+
+        let handle = tries.new_read_only_trie_view(shard_uid, state_root);
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                let changes = changes.clone();
+                std::thread::spawn(move || {
+                    for (key, value) in changes {
+                        assert_eq!(handle.get(&key).unwrap(), value);
+                    }
+                    assert_eq!(handle.get(&[0, 1]).unwrap(), None);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // All the threads read through the shared view cache.
+        let trie = handle.trie();
+        let cache_len = trie.storage.as_caching_storage().unwrap().shard_cache.len();
+        assert!(cache_len > 0);
+        let trie_update = handle.trie_update();
+        assert_eq!(trie_update.get_root(), state_root);
+    }
+}
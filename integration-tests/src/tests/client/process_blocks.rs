@@ -357,6 +357,7 @@ fn receive_network_block() {
             );
             let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
             let block = Block::produce(
+                PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 &last_block.header.clone().into(),
@@ -435,6 +436,7 @@ fn produce_block_with_approvals() {
             );
             let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
             let block = Block::produce(
+                PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 &last_block.header.clone().into(),
@@ -626,6 +628,7 @@ fn invalid_blocks_common(is_requested: bool) {
             );
             let next_block_ordinal = last_block.header.block_ordinal.unwrap() + 1;
             let valid_block = Block::produce(
+                PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 PROTOCOL_VERSION,
                 &last_block.header.clone().into(),
@@ -975,6 +978,9 @@ fn client_sync_headers() {
             received_bytes_per_sec: 0,
             known_producers: vec![],
             peer_counter: 0,
+            peer_scores: Default::default(),
+            protocol_deprecation: None,
+            advisories: vec![],
         }));
         wait_or_panic(2000);
     });
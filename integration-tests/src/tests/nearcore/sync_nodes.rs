@@ -57,6 +57,7 @@ fn add_blocks(
         )])
         .unwrap();
         let block = Block::produce(
+            PROTOCOL_VERSION,
             PROTOCOL_VERSION,
             PROTOCOL_VERSION,
             prev.header(),
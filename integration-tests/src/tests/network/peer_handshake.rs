@@ -18,6 +18,7 @@ use near_network::test_utils::{
 };
 use near_network::types::NetworkClientResponses;
 use near_network::PeerManagerActor;
+use near_network_primitives::time;
 use near_network_primitives::types::{
     NetworkConfig, NetworkViewClientMessages, NetworkViewClientResponses,
 };
@@ -49,8 +50,11 @@ fn make_peer_manager(
                 Box::new(Some(NetworkViewClientResponses::ChainInfo {
                     genesis_id: Default::default(),
                     height: 1,
+                    header_height: 1,
                     tracked_shards: vec![],
                     archival: false,
+                    num_shards: None,
+                    protocol_upgrade: None,
                 }))
             }
             _ => Box::new(Some(NetworkViewClientResponses::NoResponse)),
@@ -58,8 +62,14 @@ fn make_peer_manager(
     }))
     .start();
 
-    PeerManagerActor::new(store, config, client_addr.recipient(), view_client_addr.recipient())
-        .unwrap()
+    PeerManagerActor::new(
+        time::Clock::real(),
+        store,
+        config,
+        client_addr.recipient(),
+        view_client_addr.recipient(),
+    )
+    .unwrap()
 }
 
 #[test]
@@ -13,6 +13,7 @@ use near_network::test_utils::{
 };
 use near_network::types::{PeerManagerMessageRequest, PeerManagerMessageResponse};
 use near_network::{Event, PeerManagerActor};
+use near_network_primitives::time;
 use near_network_primitives::types::{
     Blacklist, BlacklistEntry, NetworkConfig, OutboundTcpConnect, PeerInfo, Ping as NetPing,
     Pong as NetPong, ROUTED_MESSAGE_TTL,
@@ -88,6 +89,7 @@ fn setup_network_node(
         );
 
         PeerManagerActor::new(
+            time::Clock::real(),
             store.clone(),
             config,
             client_actor.recipient(),
@@ -16,6 +16,7 @@ use near_network::test_utils::{
 };
 use near_network::types::NetworkClientResponses;
 use near_network::PeerManagerActor;
+use near_network_primitives::time;
 use near_network_primitives::types::{
     NetworkConfig, NetworkViewClientMessages, NetworkViewClientResponses,
 };
@@ -39,16 +40,25 @@ fn make_peer_manager(seed: &str, port: u16, boot_nodes: Vec<(&str, u16)>) -> Pee
                 Box::new(Some(NetworkViewClientResponses::ChainInfo {
                     genesis_id: Default::default(),
                     height: 1,
+                    header_height: 1,
                     tracked_shards: vec![],
                     archival: false,
+                    num_shards: None,
+                    protocol_upgrade: None,
                 }))
             }
             _ => Box::new(Some(NetworkViewClientResponses::NoResponse)),
         }
     }))
     .start();
-    PeerManagerActor::new(store, config, client_addr.recipient(), view_client_addr.recipient())
-        .unwrap()
+    PeerManagerActor::new(
+        time::Clock::real(),
+        store,
+        config,
+        client_addr.recipient(),
+        view_client_addr.recipient(),
+    )
+    .unwrap()
 }
 
 /// This test spawns several (7) nodes but node 0 crash very frequently and restart.
@@ -1,9 +1,11 @@
 use crate::runtime_utils::{get_runtime_and_trie, get_test_trie_viewer, TEST_SHARD_UID};
+use near_crypto::{KeyType, PublicKey, SecretKey};
 use near_primitives::{
-    account::Account,
+    account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission},
     hash::hash as sha256,
     hash::CryptoHash,
-    views::{StateItem, ViewApplyState},
+    types::AccountId,
+    views::{AccessKeyPermissionFilter, StateItem, ViewApplyState},
 };
 use near_primitives::{
     test_utils::MockEpochInfoProvider,
@@ -11,9 +13,10 @@ use near_primitives::{
     types::{EpochId, StateChangeCause},
     version::PROTOCOL_VERSION,
 };
-use near_store::set_account;
+use near_store::{set_access_key, set_account};
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
+use std::time::Duration;
 use testlib::runtime_utils::{alice_account, encode_int};
 
 #[test]
@@ -103,6 +106,34 @@ fn test_view_call_with_args() {
     assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
 }
 
+#[test]
+fn test_view_call_timeout() {
+    let (_, root) = get_test_trie_viewer();
+    let viewer = TrieViewer::new(None, None, Some(Duration::ZERO));
+
+    let mut logs = vec![];
+    let view_state = ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+    };
+    let result = viewer.call_function(
+        root,
+        view_state,
+        &"test.contract".parse().unwrap(),
+        "run_test",
+        &[],
+        &mut logs,
+        &MockEpochInfoProvider::default(),
+    );
+    assert!(matches!(result, Err(errors::CallFunctionError::ExecutionTimeout { .. })));
+}
+
 #[test]
 fn test_view_state() {
     let (_, tries, root) = get_runtime_and_trie();
@@ -149,6 +180,74 @@ fn test_view_state() {
     );
 }
 
+#[test]
+fn test_view_access_keys() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let mut state_update = tries.new_trie_update(TEST_SHARD_UID, root);
+    let account_id: AccountId = "keys.near".parse().unwrap();
+    let function_call = |receiver_id: &str| AccessKey {
+        nonce: 0,
+        permission: AccessKeyPermission::FunctionCall(FunctionCallPermission {
+            allowance: None,
+            receiver_id: receiver_id.to_string(),
+            method_names: vec![],
+        }),
+    };
+    let access_keys = [
+        AccessKey::full_access(),
+        function_call("foo.near"),
+        AccessKey::full_access(),
+        function_call("bar.near"),
+        function_call("foo.near"),
+    ];
+    for (i, access_key) in access_keys.iter().enumerate() {
+        let public_key = SecretKey::from_seed(KeyType::ED25519, &i.to_string()).public_key();
+        set_access_key(&mut state_update, account_id.clone(), public_key, access_key);
+    }
+    let trie_viewer = TrieViewer::default();
+    let view = |permission: Option<&AccessKeyPermissionFilter>,
+                after_public_key: Option<&PublicKey>,
+                limit: Option<u32>| {
+        trie_viewer
+            .view_access_keys(&state_update, &account_id, permission, after_public_key, limit)
+            .unwrap()
+    };
+
+    let (all, next_public_key) = view(None, None, None);
+    assert_eq!(all.len(), 5);
+    assert_eq!(next_public_key, None);
+
+    // Paging through the keys returns all of them, in the same order.
+    let mut pages = vec![];
+    let mut after_public_key = None;
+    loop {
+        let (page, next_public_key) = view(None, after_public_key.as_ref(), Some(2));
+        assert!(page.len() <= 2);
+        pages.extend(page);
+        after_public_key = match next_public_key {
+            Some(next_public_key) => Some(next_public_key),
+            None => break,
+        };
+    }
+    assert_eq!(pages, all);
+
+    let (full_access, _) = view(Some(&AccessKeyPermissionFilter::FullAccess), None, None);
+    assert_eq!(full_access.len(), 2);
+    assert!(full_access.iter().all(|(_, key)| key.permission == AccessKeyPermission::FullAccess));
+    let function_call_filter =
+        |receiver_id: Option<&str>| AccessKeyPermissionFilter::FunctionCall {
+            receiver_id: receiver_id.map(|receiver_id| receiver_id.parse().unwrap()),
+        };
+    let (function_calls, _) = view(Some(&function_call_filter(None)), None, None);
+    assert_eq!(function_calls.len(), 3);
+    let (foo, next_public_key) = view(Some(&function_call_filter(Some("foo.near"))), None, Some(1));
+    assert_eq!(foo.len(), 1);
+    let (foo_rest, _) =
+        view(Some(&function_call_filter(Some("foo.near"))), next_public_key.as_ref(), None);
+    assert_eq!(foo_rest.len(), 1);
+    assert!([foo, foo_rest].concat().iter().all(|(_, key)| key == &function_call("foo.near")));
+}
+
 #[test]
 fn test_view_state_too_large() {
     let (_, tries, root) = get_runtime_and_trie();
@@ -158,7 +257,7 @@ fn test_view_state_too_large() {
         alice_account(),
         &Account::new(0, 0, CryptoHash::default(), 50_001),
     );
-    let trie_viewer = TrieViewer::new(Some(50_000), None);
+    let trie_viewer = TrieViewer::new(Some(50_000), None, None);
     let result = trie_viewer.view_state(&state_update, &alice_account(), b"");
     assert!(matches!(result, Err(errors::ViewStateError::AccountStateTooLarge { .. })));
 }
@@ -174,7 +273,7 @@ fn test_view_state_with_large_contract() {
         &Account::new(0, 0, sha256(&contract_code), 50_001),
     );
     state_update.set(TrieKey::ContractCode { account_id: alice_account() }, contract_code);
-    let trie_viewer = TrieViewer::new(Some(50_000), None);
+    let trie_viewer = TrieViewer::new(Some(50_000), None, None);
     let result = trie_viewer.view_state(&state_update, &alice_account(), b"");
     assert!(result.is_ok());
 }
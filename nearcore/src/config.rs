@@ -7,6 +7,7 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Utc};
 use near_primitives::time::Clock;
 use num_rational::Rational32;
 use serde::{Deserialize, Serialize};
@@ -16,7 +17,7 @@ use tracing::{info, warn};
 
 use near_chain_configs::{
     get_initial_supply, ClientConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode,
-    LogSummaryStyle,
+    LogSummaryStyle, TrackedShardsPolicy,
 };
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey, Signer};
 #[cfg(feature = "json_rpc")]
@@ -35,7 +36,7 @@ use near_primitives::types::{
 };
 use near_primitives::utils::{generate_random_string, get_num_seats_per_shard};
 use near_primitives::validator_signer::{InMemoryValidatorSigner, ValidatorSigner};
-use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::version::{get_protocol_upgrade_voting_start, PROTOCOL_VERSION};
 #[cfg(feature = "rosetta_rpc")]
 use near_rosetta_rpc::RosettaRpcConfig;
 use near_telemetry::TelemetryConfig;
@@ -183,6 +184,18 @@ fn default_sync_step_period() -> Duration {
     Duration::from_millis(10)
 }
 
+fn default_chunk_request_fan_out() -> usize {
+    2
+}
+
+fn default_chunk_request_hedge_delay() -> Option<Duration> {
+    Some(Duration::from_millis(100))
+}
+
+fn default_finality_stall_threshold() -> Option<BlockHeightDelta> {
+    Some(50)
+}
+
 fn default_view_client_threads() -> usize {
     4
 }
@@ -230,6 +243,14 @@ pub struct Consensus {
     pub catchup_step_period: Duration,
     /// Time between checking to re-request chunks.
     pub chunk_request_retry_period: Duration,
+    /// Maximum number of nodes tracking the shard which the parts of a chunk are requested
+    /// from in parallel.
+    #[serde(default = "default_chunk_request_fan_out")]
+    pub chunk_request_fan_out: usize,
+    /// Time after which the missing parts of a chunk are also requested from another node
+    /// tracking the shard. Null disables it.
+    #[serde(default = "default_chunk_request_hedge_delay")]
+    pub chunk_request_hedge_delay: Option<Duration>,
     /// How much time to wait after initial header sync
     #[serde(default = "default_header_sync_initial_timeout")]
     pub header_sync_initial_timeout: Duration,
@@ -254,6 +275,10 @@ pub struct Consensus {
     /// Time between running doomslug timer.
     #[serde(default = "default_doomslug_step_period")]
     pub doomslug_step_period: Duration,
+    /// Number of blocks the head may get ahead of the final head before the finality stall
+    /// alarm is raised. Null disables it.
+    #[serde(default = "default_finality_stall_threshold")]
+    pub finality_stall_threshold: Option<BlockHeightDelta>,
 }
 
 impl Default for Consensus {
@@ -271,6 +296,8 @@ impl Default for Consensus {
             block_header_fetch_horizon: BLOCK_HEADER_FETCH_HORIZON,
             catchup_step_period: Duration::from_millis(CATCHUP_STEP_PERIOD),
             chunk_request_retry_period: Duration::from_millis(CHUNK_REQUEST_RETRY_PERIOD),
+            chunk_request_fan_out: default_chunk_request_fan_out(),
+            chunk_request_hedge_delay: default_chunk_request_hedge_delay(),
             header_sync_initial_timeout: default_header_sync_initial_timeout(),
             header_sync_progress_timeout: default_header_sync_progress_timeout(),
             header_sync_stall_ban_timeout: default_header_sync_stall_ban_timeout(),
@@ -280,6 +307,7 @@ impl Default for Consensus {
             sync_check_period: default_sync_check_period(),
             sync_step_period: default_sync_step_period(),
             doomslug_step_period: default_doomslug_step_period(),
+            finality_stall_threshold: default_finality_stall_threshold(),
         }
     }
 }
@@ -302,6 +330,10 @@ pub struct Config {
     pub consensus: Consensus,
     pub tracked_accounts: Vec<AccountId>,
     pub tracked_shards: Vec<ShardId>,
+    /// Policy deciding which shards to track: "assigned_plus_next", "all" or
+    /// `{"shards": [...]}`. If set, overrides `tracked_shards`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tracked_shards_policy: Option<TrackedShardsPolicy>,
     pub archive: bool,
     pub log_summary_style: LogSummaryStyle,
     /// Garbage collection configuration.
@@ -317,6 +349,15 @@ pub struct Config {
     /// If set, overrides value in genesis configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_gas_burnt_view: Option<Gas>,
+    /// If set, view calls running longer than this are aborted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_call_timeout: Option<Duration>,
+    /// If set, contracts whose executions consistently take longer than this are reported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_contract_threshold: Option<Duration>,
+    /// View calls to these contracts are rejected.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub view_call_blacklist: Vec<AccountId>,
     /// Checkpoints let the user recover from interrupted DB migrations.
     #[serde(default = "default_use_checkpoints_for_db_migration")]
     pub use_db_migration_snapshot: bool,
@@ -328,6 +369,11 @@ pub struct Config {
     pub db_migration_snapshot_path: Option<PathBuf>,
     /// Different parameters to configure/optimize underlying storage.
     pub store: near_store::StoreConfig,
+    /// If set, overrides the point in time after which the node starts voting for
+    /// the latest protocol version it supports, e.g. "2022-09-01T15:00:00Z".
+    /// By default the schedule of the release is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub protocol_upgrade_voting_start: Option<DateTime<Utc>>,
 }
 
 impl Default for Config {
@@ -346,6 +392,7 @@ impl Default for Config {
             consensus: Consensus::default(),
             tracked_accounts: vec![],
             tracked_shards: vec![],
+            tracked_shards_policy: None,
             archive: false,
             log_summary_style: LogSummaryStyle::Colored,
             gc: GCConfig::default(),
@@ -354,9 +401,13 @@ impl Default for Config {
             view_client_throttle_period: default_view_client_throttle_period(),
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
             max_gas_burnt_view: None,
+            view_call_timeout: None,
+            slow_contract_threshold: None,
+            view_call_blacklist: vec![],
             db_migration_snapshot_path: None,
             use_db_migration_snapshot: true,
             store: near_store::StoreConfig::default(),
+            protocol_upgrade_voting_start: None,
         }
     }
 }
@@ -559,9 +610,13 @@ impl NearConfig {
                 block_header_fetch_horizon: config.consensus.block_header_fetch_horizon,
                 catchup_step_period: config.consensus.catchup_step_period,
                 chunk_request_retry_period: config.consensus.chunk_request_retry_period,
+                chunk_request_fan_out: config.consensus.chunk_request_fan_out,
+                chunk_request_hedge_delay: config.consensus.chunk_request_hedge_delay,
                 doosmslug_step_period: config.consensus.doomslug_step_period,
+                finality_stall_threshold: config.consensus.finality_stall_threshold,
                 tracked_accounts: config.tracked_accounts,
                 tracked_shards: config.tracked_shards,
+                tracked_shards_policy: config.tracked_shards_policy,
                 archive: config.archive,
                 log_summary_style: config.log_summary_style,
                 gc: config.gc,
@@ -570,7 +625,17 @@ impl NearConfig {
                 view_client_throttle_period: config.view_client_throttle_period,
                 trie_viewer_state_size_limit: config.trie_viewer_state_size_limit,
                 max_gas_burnt_view: config.max_gas_burnt_view,
+                view_call_timeout: config.view_call_timeout,
+                slow_contract_threshold: config.slow_contract_threshold,
+                view_call_blacklist: config.view_call_blacklist.clone(),
                 enable_statistics_export: config.store.enable_statistics_export,
+                protocol_upgrade_voting_start: config
+                    .protocol_upgrade_voting_start
+                    .or_else(get_protocol_upgrade_voting_start),
+                preflight_report: None,
+                db_recovery_report: None,
+                store_path: None,
+                diagnostics_dir: None,
             },
             network_config: NetworkConfig::new(
                 config.network,
@@ -1318,9 +1383,21 @@ fn test_config_from_file() {
         // values is probably not worth it but there may be some other defaults
         // we want to ensure that they happen.
         let want_gc = if has_gc {
-            GCConfig { gc_blocks_limit: 42, gc_fork_clean_step: 420, gc_num_epochs_to_keep: 24 }
+            GCConfig {
+                gc_blocks_limit: 42,
+                gc_fork_clean_step: 420,
+                gc_num_epochs_to_keep: 24,
+                outcome_locations_num_blocks_to_keep: None,
+                pinned_blocks_size_limit: 1024 * 1024 * 1024,
+            }
         } else {
-            GCConfig { gc_blocks_limit: 2, gc_fork_clean_step: 100, gc_num_epochs_to_keep: 5 }
+            GCConfig {
+                gc_blocks_limit: 2,
+                gc_fork_clean_step: 100,
+                gc_num_epochs_to_keep: 5,
+                outcome_locations_num_blocks_to_keep: None,
+                pinned_blocks_size_limit: 1024 * 1024 * 1024,
+            }
         };
         assert_eq!(want_gc, config.gc);
 
@@ -0,0 +1,274 @@
+//! Recovery of the database after an unclean shutdown of the node.
+//!
+//! A crash, a kill or a power loss may leave the database with corrupted files, which
+//! make the node fail right after it starts, over and over again when it runs under a
+//! supervisor.  So if the previous run didn't close the database cleanly, it is checked
+//! before the node starts, and repaired if it can't be opened because its files are
+//! corrupted.  Otherwise, or if that doesn't help, the node starts in the safe mode
+//! instead: only the status RPC runs, reporting what is broken, until the operator
+//! restores the database.
+use crate::config::NearConfig;
+use near_primitives::block::{Block, Tip};
+use near_primitives::block_header::BlockHeader;
+use near_primitives::types::BlockHeight;
+use near_primitives::version::PROTOCOL_VERSION;
+use near_primitives::views::{
+    DbRecoveryReport, PreflightCheckStatus, PreflightCheckView, StatusResponse, StatusSyncInfo,
+};
+use near_store::db::Mode;
+use near_store::{DBCol, Store, StoreOpener, HEAD_KEY, TAIL_KEY};
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// Number of the latest headers of the chain checked for being present and readable.
+const CHECKED_HEADERS: usize = 100;
+
+/// Checks the database, and repairs it if it's corrupted, if the node wasn't shut down
+/// cleanly.  Returns None if the previous shutdown was clean and nothing was checked.
+///
+/// The database is opened read-only, so that it stays marked as not shut down cleanly
+/// until the node opens it for writing.
+pub fn run(home_dir: &Path, config: &NearConfig) -> Option<DbRecoveryReport> {
+    let opener = Store::opener(home_dir, &config.config.store).mode(Mode::ReadOnly);
+    if !opener.check_if_exists() || !opener.shut_down_uncleanly() {
+        return None;
+    }
+    warn!(target: "db_recovery", path = %opener.get_path().display(), "The node wasn't shut down cleanly, checking the database");
+    let mut checks = Vec::new();
+    if let Some(store) = open_or_repair(&opener, &mut checks) {
+        checks.push(check_chain(&store));
+    }
+    for check in &checks {
+        match check.status {
+            PreflightCheckStatus::Ok => {
+                info!(target: "db_recovery", name = %check.name, "{}", check.message)
+            }
+            PreflightCheckStatus::Warning => {
+                warn!(target: "db_recovery", name = %check.name, "{}", check.message)
+            }
+            PreflightCheckStatus::Error => {
+                error!(target: "db_recovery", name = %check.name, "{}", check.message)
+            }
+        }
+    }
+    let safe_mode = checks.iter().any(|check| check.status == PreflightCheckStatus::Error);
+    Some(DbRecoveryReport { safe_mode, checks })
+}
+
+fn check(name: &str, status: PreflightCheckStatus, message: String) -> PreflightCheckView {
+    PreflightCheckView { name: name.to_string(), status, message }
+}
+
+/// Opens the database, repairing it once if it can't be opened because its files are
+/// corrupted.  The repair loses the data in the corrupted files, and can't fix any other
+/// failure, e.g. a lack of disk space or permissions, so other failures aren't repaired.
+fn open_or_repair(opener: &StoreOpener, checks: &mut Vec<PreflightCheckView>) -> Option<Store> {
+    const NAME: &str = "db_open";
+    let open_err = match opener.try_open() {
+        Ok(store) => {
+            checks.push(check(NAME, PreflightCheckStatus::Ok, "The database opens".to_string()));
+            return Some(store);
+        }
+        Err(err) => err,
+    };
+    if open_err.kind() != std::io::ErrorKind::InvalidData {
+        checks.push(check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "The database can't be opened: {open_err}.  Its files aren't corrupted, so it \
+                 isn't repaired; fix the cause of the failure and restart the node."
+            ),
+        ));
+        return None;
+    }
+    if let Err(err) = opener.repair() {
+        checks.push(check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "The database can't be opened ({open_err}) and repairing it failed: {err}.  \
+                 Restore the database from a backup or a snapshot."
+            ),
+        ));
+        return None;
+    }
+    match opener.try_open() {
+        Ok(store) => {
+            checks.push(check(
+                NAME,
+                PreflightCheckStatus::Warning,
+                format!(
+                    "The database couldn't be opened ({open_err}) and has been repaired.  \
+                     The data in its corrupted files is lost."
+                ),
+            ));
+            Some(store)
+        }
+        Err(err) => {
+            checks.push(check(
+                NAME,
+                PreflightCheckStatus::Error,
+                format!(
+                    "The database can't be opened ({open_err}), not even after repairing it: \
+                     {err}.  Restore the database from a backup or a snapshot."
+                ),
+            ));
+            None
+        }
+    }
+}
+
+/// Checks that the head block and the latest headers of the chain are present and
+/// readable, as the node can't start without them.
+fn check_chain(store: &Store) -> PreflightCheckView {
+    const NAME: &str = "db_chain";
+    match check_head(store) {
+        Ok(message) => check(NAME, PreflightCheckStatus::Ok, message),
+        Err(err) => check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!("{err:#}.  Restore the database from a backup or a snapshot."),
+        ),
+    }
+}
+
+fn check_head(store: &Store) -> anyhow::Result<String> {
+    use anyhow::Context;
+
+    let head = match store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY)? {
+        Some(head) => head,
+        None => return Ok("The database has no chain head yet".to_string()),
+    };
+    store
+        .get_ser::<Block>(DBCol::Block, head.last_block_hash.as_ref())
+        .context("Failed to read the head block")?
+        .with_context(|| format!("Head block {} is missing", head.last_block_hash))?;
+    // The blocks below the head may be missing after a state sync, but not the headers
+    // above the tail, which are garbage collected together with the blocks.
+    let tail = store.get_ser::<BlockHeight>(DBCol::BlockMisc, TAIL_KEY)?.unwrap_or_default();
+    let mut hash = head.last_block_hash;
+    for _ in 0..CHECKED_HEADERS {
+        let header = store
+            .get_ser::<BlockHeader>(DBCol::BlockHeader, hash.as_ref())
+            .with_context(|| format!("Failed to read the header of block {hash}"))?
+            .with_context(|| format!("Header of block {hash} is missing"))?;
+        if header.height() <= tail || header.prev_hash() == &Default::default() {
+            break;
+        }
+        hash = *header.prev_hash();
+    }
+    Ok(format!(
+        "Head block {} at height {} and the headers below it are readable",
+        head.last_block_hash, head.height
+    ))
+}
+
+/// Starts the safe mode, in which only the status RPC runs, reporting the failed
+/// recovery of the database.  The status is filled with what can be read from the
+/// database opened read-only.  Returns the started RPC servers.
+pub fn start_safe_mode(
+    home_dir: &Path,
+    config: NearConfig,
+    report: DbRecoveryReport,
+) -> Vec<(&'static str, actix_web::dev::ServerHandle)> {
+    error!(target: "db_recovery", "The database couldn't be repaired, starting in the safe mode.  Only the status RPC is served until the database is restored.");
+    let status = safe_mode_status(home_dir, &config, report);
+    #[cfg(feature = "json_rpc")]
+    if let Some(rpc_config) = config.rpc_config {
+        return near_jsonrpc::start_safe_mode_http(rpc_config, status);
+    }
+    error!(target: "db_recovery", ?status, "The RPC is disabled, so the status can't be served");
+    vec![]
+}
+
+fn safe_mode_status(
+    home_dir: &Path,
+    config: &NearConfig,
+    report: DbRecoveryReport,
+) -> StatusResponse {
+    let store = Store::opener(home_dir, &config.config.store).mode(Mode::ReadOnly).try_open().ok();
+    let head =
+        store.as_ref().and_then(|store| store.get_ser::<Tip>(DBCol::BlockMisc, HEAD_KEY).ok()?);
+    let head_header = store.as_ref().zip(head.as_ref()).and_then(|(store, head)| {
+        store.get_ser::<BlockHeader>(DBCol::BlockHeader, head.last_block_hash.as_ref()).ok()?
+    });
+    StatusResponse {
+        version: config.client_config.version.clone(),
+        chain_id: config.client_config.chain_id.clone(),
+        protocol_version: head_header
+            .as_ref()
+            .map_or(config.genesis.config.protocol_version, |header| {
+                header.latest_protocol_version()
+            }),
+        latest_protocol_version: PROTOCOL_VERSION,
+        rpc_addr: config.client_config.rpc_addr.clone(),
+        validators: vec![],
+        sync_info: StatusSyncInfo {
+            latest_block_hash: head.as_ref().map(|head| head.last_block_hash).unwrap_or_default(),
+            latest_block_height: head.as_ref().map_or(0, |head| head.height),
+            latest_state_root: head_header
+                .as_ref()
+                .map(|header| *header.prev_state_root())
+                .unwrap_or_default(),
+            latest_block_time: head_header
+                .as_ref()
+                .map_or(config.genesis.config.genesis_time, |header| header.timestamp()),
+            syncing: false,
+            earliest_block_hash: None,
+            earliest_block_height: None,
+            earliest_block_time: None,
+            epoch_id: head.as_ref().map(|head| head.epoch_id.clone()),
+            epoch_start_height: None,
+        },
+        validator_account_id: config
+            .validator_signer
+            .as_ref()
+            .map(|signer| signer.validator_id().clone()),
+        detailed_debug_status: None,
+        preflight_report: Some(crate::preflight::run(home_dir, config)),
+        db_recovery: Some(report),
+        protocol_deprecation: None,
+        network_advisories: vec![],
+        epoch_readiness: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::EpochId;
+
+    #[test]
+    fn test_open_without_repair() {
+        // The database doesn't exist, which the repair couldn't fix.
+        let (_tmp_dir, opener) = Store::test_opener();
+        let opener = opener.mode(Mode::ReadOnly);
+        let mut checks = Vec::new();
+        assert!(open_or_repair(&opener, &mut checks).is_none());
+        assert_eq!(PreflightCheckStatus::Error, checks[0].status);
+        assert!(checks[0].message.contains("isn't repaired"), "{}", checks[0].message);
+        assert!(!opener.check_if_exists());
+    }
+
+    #[test]
+    fn test_check_chain() {
+        let store = near_store::test_utils::create_test_store();
+        assert_eq!(PreflightCheckStatus::Ok, check_chain(&store).status);
+
+        let head = Tip {
+            height: 10,
+            last_block_hash: CryptoHash::hash_bytes(b"head"),
+            prev_block_hash: CryptoHash::hash_bytes(b"prev"),
+            epoch_id: EpochId::default(),
+            next_epoch_id: EpochId::default(),
+        };
+        let mut update = store.store_update();
+        update.set_ser(DBCol::BlockMisc, HEAD_KEY, &head).unwrap();
+        update.commit().unwrap();
+        let result = check_chain(&store);
+        assert_eq!(PreflightCheckStatus::Error, result.status);
+        assert!(result.message.contains("is missing"), "{}", result.message);
+    }
+}
@@ -1,5 +1,5 @@
 pub use crate::config::{init_configs, load_config, load_test_config, NearConfig, NEAR_BASE};
-use crate::migrations::migrate_30_to_31;
+use crate::migrations::{migrate_30_to_31, migrate_35_to_36};
 pub use crate::runtime::NightshadeRuntime;
 pub use crate::shard_tracker::TrackedConfig;
 use actix::{Actor, Addr, Arbiter};
@@ -26,9 +26,11 @@ use tracing::{error, info, trace};
 
 pub mod append_only_map;
 pub mod config;
+pub mod db_recovery;
 mod download_file;
 mod metrics;
 pub mod migrations;
+mod preflight;
 mod runtime;
 mod shard_tracker;
 
@@ -165,6 +167,46 @@ fn apply_store_migrations_if_exists(
         info!(target: "near", "Migrate DB from version 30 to 31");
         migrate_30_to_31(store_opener, &near_config);
     }
+    if db_version <= 31 {
+        // version 31 => 32: add DBCol::PeerAccessRules
+        // Does not need to do anything since open db with option
+        // `create_missing_column_families`.  Nevertheless need to bump db
+        // version, because db_version 31 binary can't open db_version 32 db.
+        info!(target: "near", "Migrate DB from version 31 to 32");
+        let store = store_opener.open();
+        set_store_version(&store, 32);
+    }
+    if db_version <= 32 {
+        // version 32 => 33: add DBCol::OutcomeLocations
+        // Does not need to do anything since open db with option
+        // `create_missing_column_families`.  The locations of the outcomes
+        // executed before the migration are not indexed, the lookups fall back
+        // to the shard of the signer for them.
+        info!(target: "near", "Migrate DB from version 32 to 33");
+        let store = store_opener.open();
+        set_store_version(&store, 33);
+    }
+    if db_version <= 33 {
+        // version 33 => 34: add DBCol::PinnedBlocks
+        // Does not need to do anything since open db with option
+        // `create_missing_column_families`.
+        info!(target: "near", "Migrate DB from version 33 to 34");
+        let store = store_opener.open();
+        set_store_version(&store, 34);
+    }
+    if db_version <= 34 {
+        // version 34 => 35: add DBCol::RoutedMessages
+        // Does not need to do anything since open db with option
+        // `create_missing_column_families`.
+        info!(target: "near", "Migrate DB from version 34 to 35");
+        let store = store_opener.open();
+        set_store_version(&store, 35);
+    }
+    if db_version <= 35 {
+        // version 35 => 36: add DBCol::PinnedChunks indexing the chunks of the pinned blocks
+        info!(target: "near", "Migrate DB from version 35 to 36");
+        migrate_35_to_36(store_opener, &near_config);
+    }
 
     if cfg!(feature = "nightly") || cfg!(feature = "nightly_protocol") {
         let store = store_opener.open();
@@ -240,11 +282,15 @@ pub fn start_with_config(home_dir: &Path, config: NearConfig) -> anyhow::Result<
 
 pub fn start_with_config_and_synchronization(
     home_dir: &Path,
-    config: NearConfig,
+    mut config: NearConfig,
     // 'shutdown_signal' will notify the corresponding `oneshot::Receiver` when an instance of
     // `ClientActor` gets dropped.
     shutdown_signal: Option<oneshot::Sender<()>>,
 ) -> anyhow::Result<NearNode> {
+    config.client_config.preflight_report = Some(preflight::run(home_dir, &config));
+    config.client_config.store_path =
+        Some(Store::opener(home_dir, &config.config.store).get_path().to_path_buf());
+    config.client_config.diagnostics_dir = Some(home_dir.join("diagnostics"));
     let store = init_and_migrate_store(home_dir, &config)?;
 
     let runtime = Arc::new(NightshadeRuntime::from_config(home_dir, store.clone(), &config));
@@ -280,11 +326,18 @@ pub fn start_with_config_and_synchronization(
     let mut rpc_servers = Vec::new();
     let arbiter = Arbiter::new();
     config.network_config.verify().context("start_with_config")?;
+    if let Some(path) = &mut config.network_config.record_frames {
+        *path = home_dir.join(&path);
+    }
+    if let Some(path) = &mut config.network_config.asn_database {
+        *path = home_dir.join(&path);
+    }
     let network_actor = PeerManagerActor::start_in_arbiter(&arbiter.handle(), {
         let client_actor = client_actor.clone();
         let view_client = view_client.clone();
         move |_ctx| {
             PeerManagerActor::new(
+                near_network_primitives::time::Clock::real(),
                 store,
                 config.network_config,
                 client_actor.recipient(),
@@ -302,6 +355,7 @@ pub fn start_with_config_and_synchronization(
             config.genesis.config.clone(),
             client_actor.clone(),
             view_client.clone(),
+            Some(network_actor.clone()),
         ));
     }
 
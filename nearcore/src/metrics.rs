@@ -1,4 +1,7 @@
-use near_metrics::{linear_buckets, try_create_histogram_vec, HistogramVec};
+use near_metrics::{
+    exponential_buckets, linear_buckets, try_create_histogram_vec, try_create_int_counter_vec,
+    try_create_int_gauge_vec, HistogramVec, IntCounterVec, IntGaugeVec,
+};
 use once_cell::sync::Lazy;
 
 pub static APPLY_CHUNK_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
@@ -24,3 +27,41 @@ pub static SECONDS_PER_PETAGAS: Lazy<HistogramVec> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub static STATE_WITNESS_SIZE: Lazy<HistogramVec> = Lazy::new(|| {
+    try_create_histogram_vec(
+        "near_state_witness_size_bytes",
+        "Size of the storage proof of the state read while applying a chunk, i.e. of the state witness needed to validate the chunk without the state",
+        &["shard_id"],
+        // From 10KiB to 10GiB.
+        Some(exponential_buckets(10_240.0, 2.0, 21).unwrap()),
+    )
+    .unwrap()
+});
+
+pub static STATE_WITNESS_TOP_CONTRACTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_state_witness_top_contracts_bytes",
+        "Average size per chunk of the part of the state witness read by the receipts to the contracts contributing the most to it over the last chunks of the shard",
+        &["shard_id", "account_id"],
+    )
+    .unwrap()
+});
+
+pub static SLOW_CONTRACT_EXECUTIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_slow_contract_executions_total",
+        "Number of the executions of contracts which took longer than slow_contract_threshold, by the kind of the execution: receipt or view_call",
+        &["kind"],
+    )
+    .unwrap()
+});
+
+pub static SLOW_CONTRACTS: Lazy<IntGaugeVec> = Lazy::new(|| {
+    try_create_int_gauge_vec(
+        "near_slow_contracts",
+        "Number of the consecutive executions over slow_contract_threshold of the contracts reported as slow",
+        &["kind", "account_id"],
+    )
+    .unwrap()
+});
@@ -37,6 +37,21 @@ pub fn migrate_30_to_31(store_opener: &near_store::StoreOpener, near_config: &cr
     set_store_version(&store, 31);
 }
 
+/// Index the chunks of the blocks pinned before `DBCol::PinnedChunks` was added.
+pub fn migrate_35_to_36(store_opener: &near_store::StoreOpener, near_config: &crate::NearConfig) {
+    let store = store_opener.open();
+    let chain_store =
+        ChainStore::new(store.clone(), near_config.genesis.config.genesis_height, false);
+    let pinned_blocks: Vec<_> = chain_store
+        .get_pinned_blocks()
+        .expect("Failed to read pinned blocks")
+        .into_iter()
+        .map(|(pinned, _)| pinned)
+        .collect();
+    chain_store.save_pinned_blocks(&pinned_blocks).expect("Failed to migrate");
+    set_store_version(&store, 36);
+}
+
 /// In test runs reads and writes here used 442 TGas, but in test on live net migration take
 /// between 4 and 4.5s. We do not want to process any receipts in this block
 const GAS_USED_FOR_STORAGE_USAGE_DELTA_MIGRATION: Gas = 1_000_000_000_000_000;
@@ -0,0 +1,267 @@
+//! Checks of the environment done on startup, before the node opens the database and
+//! starts listening.
+//!
+//! The checks don't stop the node from starting: failures are logged together with
+//! instructions how to fix them and reported in the `status` RPC, so that operators
+//! and monitoring can spot a misconfigured machine before it causes an outage.
+use crate::config::NearConfig;
+use bytesize::ByteSize;
+use chrono::{DateTime, TimeZone, Utc};
+use near_network_primitives::types::Transport;
+use near_primitives::views::{PreflightCheckStatus, PreflightCheckView, PreflightReport};
+use near_store::Store;
+use std::net::{SocketAddr, TcpListener, UdpSocket};
+use std::path::Path;
+use tracing::{error, info, warn};
+
+/// Rough estimate of the daily growth of the database of an archival node.
+const ARCHIVAL_DB_GROWTH_PER_DAY: ByteSize = ByteSize::gib(10);
+/// Rough estimate of the daily growth of the database of a non-archival node.
+/// Garbage collection keeps its size stable in the long run, but the space has to
+/// accommodate the epochs being kept and the compactions.
+const DB_GROWTH_PER_DAY: ByteSize = ByteSize::gib(2);
+/// Free disk space for fewer days of growth than this is reported as a warning.
+const DISK_SPACE_WARNING_DAYS: u64 = 7;
+/// File descriptors needed on top of the store's `max_open_files` and the peer
+/// connections: RPC connections, log files, etc.  Matches the margin reserved by the store.
+const EXTRA_FILE_DESCRIPTORS: u64 = 1000;
+
+/// Runs all the checks and logs the ones which didn't pass.
+pub fn run(home_dir: &Path, config: &NearConfig) -> PreflightReport {
+    let checks = vec![
+        check_disk_space(home_dir, config),
+        check_file_descriptors(config),
+        check_clock(config, Utc::now()),
+        check_ports(config),
+    ];
+    for check in &checks {
+        match check.status {
+            PreflightCheckStatus::Ok => {
+                info!(target: "preflight", name = %check.name, "{}", check.message)
+            }
+            PreflightCheckStatus::Warning => {
+                warn!(target: "preflight", name = %check.name, "{}", check.message)
+            }
+            PreflightCheckStatus::Error => {
+                error!(target: "preflight", name = %check.name, "{}", check.message)
+            }
+        }
+    }
+    PreflightReport { checks }
+}
+
+fn check(name: &str, status: PreflightCheckStatus, message: String) -> PreflightCheckView {
+    PreflightCheckView { name: name.to_string(), status, message }
+}
+
+/// Compares the free space on the disk holding the database with its projected growth.
+fn check_disk_space(home_dir: &Path, config: &NearConfig) -> PreflightCheckView {
+    const NAME: &str = "disk_space";
+    let opener = Store::opener(home_dir, &config.config.store);
+    let path = opener.get_path();
+    // The database may not exist yet, in which case check the disk it will be created on.
+    let existing = path.ancestors().find(|p| p.exists()).unwrap_or(home_dir);
+    let available = match fs2::available_space(existing) {
+        Ok(available) => ByteSize::b(available),
+        Err(err) => {
+            return check(
+                NAME,
+                PreflightCheckStatus::Warning,
+                format!("Unable to check free disk space at {}: {err}", existing.display()),
+            )
+        }
+    };
+    let growth_per_day =
+        if config.client_config.archive { ARCHIVAL_DB_GROWTH_PER_DAY } else { DB_GROWTH_PER_DAY };
+    let days = available.as_u64() / growth_per_day.as_u64();
+    let status = if days < 1 {
+        PreflightCheckStatus::Error
+    } else if days < DISK_SPACE_WARNING_DAYS {
+        PreflightCheckStatus::Warning
+    } else {
+        return check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("{available} free at {}, enough for about {days} days", path.display()),
+        );
+    };
+    check(
+        NAME,
+        status,
+        format!(
+            "Only {available} free at {}, enough for about {days} days of database growth \
+             (estimated at {growth_per_day} per day).  Free up space or move the database \
+             to a larger disk with the ‘store.path’ option.",
+            path.display()
+        ),
+    )
+}
+
+/// Checks that the limit of open files accommodates the database and the peer connections.
+fn check_file_descriptors(config: &NearConfig) -> PreflightCheckView {
+    const NAME: &str = "file_descriptors";
+    let max_open_files = config.config.store.max_open_files;
+    let max_num_peers = config.network_config.max_num_peers;
+    let required = max_open_files as u64 + max_num_peers as u64 + EXTRA_FILE_DESCRIPTORS;
+    let (soft, hard) = match rlimit::Resource::NOFILE.get() {
+        Ok(limits) => limits,
+        Err(err) => {
+            return check(
+                NAME,
+                PreflightCheckStatus::Warning,
+                format!("Unable to get the limit of open files (NOFILE): {err}"),
+            )
+        }
+    };
+    if hard < required {
+        check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "Hard limit of open files (NOFILE) is {hard}, but {required} are needed for \
+                 store.max_open_files={max_open_files} and network.max_num_peers={max_num_peers}.  \
+                 Raise it with ‘ulimit -Hn’ (or LimitNOFILE in the systemd unit) and restart \
+                 the node."
+            ),
+        )
+    } else if soft < required {
+        check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("Soft limit of open files (NOFILE) will be raised from {soft} to {required}"),
+        )
+    } else {
+        check(
+            NAME,
+            PreflightCheckStatus::Ok,
+            format!("Limit of open files (NOFILE) is {soft}, {required} are needed"),
+        )
+    }
+}
+
+/// Checks that the system clock is plausible: a node with a wrong clock rejects valid
+/// blocks and produces blocks which others reject.
+fn check_clock(config: &NearConfig, now: DateTime<Utc>) -> PreflightCheckView {
+    const NAME: &str = "clock";
+    // The binary wasn't built before this date, so a clock set earlier is surely wrong.
+    let min_plausible_time = Utc.ymd(2022, 8, 1).and_hms(0, 0, 0);
+    let genesis_time = config.genesis.config.genesis_time;
+    if now < min_plausible_time {
+        check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "System clock is set to {now}, which can't be right.  Synchronize it, \
+                 e.g. with NTP."
+            ),
+        )
+    } else if now < genesis_time {
+        check(
+            NAME,
+            PreflightCheckStatus::Warning,
+            format!(
+                "System clock ({now}) is before the genesis time ({genesis_time}), so no \
+                 blocks will be produced until then.  If the clock is wrong, synchronize \
+                 it, e.g. with NTP."
+            ),
+        )
+    } else {
+        check(NAME, PreflightCheckStatus::Ok, format!("System clock is set to {now}"))
+    }
+}
+
+/// Checks that the addresses the node listens on are not taken by another process.
+fn check_ports(config: &NearConfig) -> PreflightCheckView {
+    const NAME: &str = "ports";
+    let mut addrs: Vec<(&str, String)> = vec![];
+    if let Some(addr) = config.network_config.node_addr {
+        addrs.push(("network.addr", addr.to_string()));
+    }
+    #[cfg(feature = "json_rpc")]
+    if let Some(rpc_config) = &config.rpc_config {
+        addrs.push(("rpc.addr", rpc_config.addr.clone()));
+        if let Some(addr) = &rpc_config.prometheus_addr {
+            addrs.push(("rpc.prometheus_addr", addr.clone()));
+        }
+    }
+    #[cfg(feature = "rosetta_rpc")]
+    if let Some(rosetta_rpc_config) = &config.rosetta_rpc_config {
+        addrs.push(("rosetta_rpc.addr", rosetta_rpc_config.addr.clone()));
+    }
+
+    let mut errors = vec![];
+    for (option, addr) in &addrs {
+        let addr: SocketAddr = match addr.parse() {
+            Ok(addr) => addr,
+            Err(err) => {
+                errors.push(format!("‘{option}’ ({addr}) is not a valid address: {err}"));
+                continue;
+            }
+        };
+        if let Err(err) = TcpListener::bind(addr) {
+            errors.push(format!("‘{option}’ ({addr}) is not available: {err}"));
+        }
+        if *option == "network.addr" && config.network_config.transport == Transport::Quic {
+            if let Err(err) = UdpSocket::bind(addr) {
+                errors.push(format!("‘{option}’ ({addr}, UDP) is not available: {err}"));
+            }
+        }
+    }
+    if errors.is_empty() {
+        let addrs: Vec<_> = addrs.iter().map(|(_, addr)| addr.as_str()).collect();
+        check(NAME, PreflightCheckStatus::Ok, format!("Listening addresses are free: {addrs:?}"))
+    } else {
+        check(
+            NAME,
+            PreflightCheckStatus::Error,
+            format!(
+                "{}.  Stop the process using the address (is another node running?) or \
+                 change the option in config.json.",
+                errors.join("; ")
+            ),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{load_test_config, GenesisExt};
+    use near_chain_configs::Genesis;
+
+    fn test_config() -> NearConfig {
+        load_test_config("", 0, Genesis::test(vec!["test".parse().unwrap()], 1))
+    }
+
+    #[test]
+    fn test_check_clock() {
+        let mut config = test_config();
+        let genesis_time = Utc.ymd(2023, 1, 1).and_hms(0, 0, 0);
+        config.genesis.config.genesis_time = genesis_time;
+        let status = |now| check_clock(&config, now).status;
+        assert_eq!(PreflightCheckStatus::Error, status(Utc.ymd(1970, 1, 1).and_hms(0, 0, 0)));
+        assert_eq!(PreflightCheckStatus::Warning, status(Utc.ymd(2022, 12, 1).and_hms(0, 0, 0)));
+        assert_eq!(PreflightCheckStatus::Ok, status(genesis_time));
+    }
+
+    #[test]
+    fn test_check_ports() {
+        let mut config = test_config();
+        config.network_config.node_addr = Some("127.0.0.1:0".parse().unwrap());
+        #[cfg(feature = "json_rpc")]
+        {
+            config.rpc_config = None;
+        }
+        #[cfg(feature = "rosetta_rpc")]
+        {
+            config.rosetta_rpc_config = None;
+        }
+        assert_eq!(PreflightCheckStatus::Ok, check_ports(&config).status);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        config.network_config.node_addr = Some(listener.local_addr().unwrap());
+        let result = check_ports(&config);
+        assert_eq!(PreflightCheckStatus::Error, result.status);
+        assert!(result.message.contains("network.addr"), "{}", result.message);
+    }
+}
@@ -20,6 +20,9 @@ impl QueryError {
             node_runtime::state_viewer::errors::CallFunctionError::VMError { error_message } => {
                 Self::ContractExecutionError { error_message, block_height, block_hash }
             }
+            node_runtime::state_viewer::errors::CallFunctionError::ExecutionTimeout { .. } => {
+                Self::ContractExecutionTimeout { block_height, block_hash }
+            }
         }
     }
 
@@ -14,7 +14,7 @@ use near_chain_configs::{
     MIN_GC_NUM_EPOCHS_TO_KEEP,
 };
 use near_crypto::{PublicKey, Signature};
-use near_epoch_manager::EpochManager;
+use near_epoch_manager::{DelegationInfoProvider, EpochManager};
 use near_pool::types::PoolIterator;
 use near_primitives::account::{AccessKey, Account};
 use near_primitives::block::{Approval, ApprovalInner};
@@ -22,7 +22,7 @@ use near_primitives::challenge::ChallengesResult;
 use near_primitives::contract::ContractCode;
 use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
-use near_primitives::epoch_manager::{EpochConfig, ShardConfig};
+use near_primitives::epoch_manager::{EpochConfig, ProtocolUpgradeVotes, ShardConfig};
 use near_primitives::errors::{EpochError, InvalidTxError, RuntimeError};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::receipt::Receipt;
@@ -45,8 +45,8 @@ use near_primitives::types::{
 };
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views::{
-    AccessKeyInfoView, CallResult, EpochValidatorInfo, QueryRequest, QueryResponse,
-    QueryResponseKind, ViewApplyState, ViewStateResult,
+    AccessKeyInfoView, AccessKeyList, AccessKeyPermissionFilter, CallResult, EpochValidatorInfo,
+    QueryRequest, QueryResponse, QueryResponseKind, ViewApplyState, ViewStateResult,
 };
 use near_store::split_state::get_delayed_receipts;
 use near_store::{
@@ -63,15 +63,18 @@ use node_runtime::{
     validate_transaction, verify_and_charge_transaction, ApplyState, Runtime,
     ValidatorAccountsUpdate,
 };
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
-use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
-use std::time::Instant;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant};
 use tracing::{debug, error, info, warn};
 
 pub mod errors;
+mod slow_contracts;
+mod state_witness;
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 const STATE_DUMP_FILE: &str = "state_dump";
@@ -142,6 +145,10 @@ pub struct NightshadeRuntime {
     genesis_state_roots: Vec<StateRoot>,
     migration_data: Arc<MigrationData>,
     gc_num_epochs_to_keep: u64,
+    state_witness_stats: Mutex<state_witness::StateWitnessStats>,
+    slow_contracts: Mutex<slow_contracts::SlowContracts>,
+    /// Contracts whose view methods aren't executed, see `ClientConfig::view_call_blacklist`.
+    view_call_blacklist: HashSet<AccountId>,
 }
 
 impl NightshadeRuntime {
@@ -153,6 +160,9 @@ impl NightshadeRuntime {
             TrackedConfig::from_config(&config.client_config),
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
+            config.client_config.view_call_timeout,
+            config.client_config.slow_contract_threshold,
+            config.client_config.view_call_blacklist.clone(),
             None,
             config.config.gc.gc_num_epochs_to_keep(),
             config.config.store.trie_cache_capacities.clone(),
@@ -166,6 +176,9 @@ impl NightshadeRuntime {
         tracked_config: TrackedConfig,
         trie_viewer_state_size_limit: Option<u64>,
         max_gas_burnt_view: Option<Gas>,
+        view_call_timeout: Option<Duration>,
+        slow_contract_threshold: Option<Duration>,
+        view_call_blacklist: Vec<AccountId>,
         runtime_config_store: Option<RuntimeConfigStore>,
         gc_num_epochs_to_keep: u64,
         trie_cache_capacities: Vec<(ShardUId, usize)>,
@@ -176,7 +189,8 @@ impl NightshadeRuntime {
         };
 
         let runtime = Runtime::new();
-        let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
+        let trie_viewer =
+            TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view, view_call_timeout);
         let genesis_config = genesis.config.clone();
         assert_eq!(
             genesis_config.shard_layout.num_shards(),
@@ -210,6 +224,9 @@ impl NightshadeRuntime {
             genesis_state_roots: state_roots,
             migration_data: Arc::new(load_migration_data(&genesis.config.chain_id)),
             gc_num_epochs_to_keep: gc_num_epochs_to_keep.max(MIN_GC_NUM_EPOCHS_TO_KEEP),
+            state_witness_stats: Default::default(),
+            slow_contracts: Mutex::new(slow_contracts::SlowContracts::new(slow_contract_threshold)),
+            view_call_blacklist: view_call_blacklist.into_iter().collect(),
         }
     }
 
@@ -227,6 +244,9 @@ impl NightshadeRuntime {
             tracked_config,
             None,
             None,
+            None,
+            None,
+            vec![],
             Some(runtime_config_store),
             DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             Default::default(),
@@ -248,6 +268,11 @@ impl NightshadeRuntime {
         epoch_manager.get_epoch_id(hash).map_err(Error::from)
     }
 
+    /// Sets the source of the delegation metadata reported by the validators RPC.
+    pub fn set_delegation_info_provider(&self, provider: Arc<dyn DelegationInfoProvider>) {
+        self.epoch_manager.write().set_delegation_info_provider(provider);
+    }
+
     /// Create store of runtime configs for the given chain id.
     ///
     /// For mainnet and other chains except testnet we don't need to override runtime config for
@@ -598,6 +623,16 @@ impl NightshadeRuntime {
                 Error::Other("Integer overflow during burnt balance summation".to_string())
             })?;
 
+        if let Some(state_witness_size) = &apply_result.state_witness_size {
+            self.state_witness_stats.lock().unwrap().record(shard_id, state_witness_size);
+        }
+        {
+            let mut slow_contracts = self.slow_contracts.lock().unwrap();
+            for (account_id, elapsed) in &apply_result.receipt_execution_times {
+                slow_contracts.record(slow_contracts::ExecutionKind::Receipt, account_id, *elapsed);
+            }
+        }
+
         let shard_uid = self.get_shard_uid_from_prev_hash(shard_id, prev_block_hash)?;
 
         let result = ApplyTransactionResult {
@@ -703,6 +738,28 @@ pub fn state_record_to_shard_id(state_record: &StateRecord, shard_layout: &Shard
     account_id_to_shard_id(state_record_to_account_id(state_record), shard_layout)
 }
 
+/// `block_hash` is a block whose `prev_state_root` is `state_root`
+fn obtain_state_part_from_trie(
+    trie: &Trie,
+    block_hash: &CryptoHash,
+    state_root: &StateRoot,
+    part_id: PartId,
+) -> Result<Vec<u8>, Error> {
+    let result = match trie.get_trie_nodes_for_part(part_id, state_root) {
+        Ok(partial_state) => partial_state,
+        Err(e) => {
+            error!(target: "runtime",
+                   "Can't get_trie_nodes_for_part for block {:?} state root {:?}, part_id {:?}, num_parts {:?}, {:?}",
+                   block_hash, state_root, part_id.idx, part_id.total, e
+            );
+            return Err(e.into());
+        }
+    }
+    .try_to_vec()
+    .expect("serializer should not fail");
+    Ok(result)
+}
+
 impl RuntimeAdapter for NightshadeRuntime {
     fn genesis_state(&self) -> (Store, Vec<StateRoot>) {
         (self.store.clone(), self.genesis_state_roots.clone())
@@ -1340,6 +1397,14 @@ impl RuntimeAdapter for NightshadeRuntime {
         Ok(epoch_manager.get_epoch_info(epoch_id)?.protocol_version())
     }
 
+    fn get_protocol_upgrade_votes(
+        &self,
+        last_block_hash: &CryptoHash,
+    ) -> Result<ProtocolUpgradeVotes, Error> {
+        let epoch_manager = self.epoch_manager.read();
+        Ok(epoch_manager.get_protocol_upgrade_votes(last_block_hash)?)
+    }
+
     fn epoch_sync_init_epoch_manager(
         &self,
         prev_epoch_first_block_info: BlockInfo,
@@ -1429,6 +1494,10 @@ impl RuntimeAdapter for NightshadeRuntime {
             panic!("Storage proof generation is not enabled yet");
         }
         // let trie = if generate_storage_proof { trie.recording_reads() } else { trie };
+        // Measures the state witness of the new chunks, see `state_witness`.
+        if is_new_chunk {
+            trie.record_proof_size();
+        }
         match self.process_state_update(
             trie,
             *state_root,
@@ -1549,24 +1618,44 @@ impl RuntimeAdapter for NightshadeRuntime {
                     (epoch_info.epoch_height(), epoch_info.protocol_version())
                 };
 
-                let call_function_result = self
-                    .call_function(
-                        &shard_uid,
-                        *state_root,
+                if self.view_call_blacklist.contains(account_id) {
+                    return Err(
+                        near_chain::near_chain_primitives::error::QueryError::ContractBlacklisted {
+                            contract_account_id: account_id.clone(),
+                            block_height,
+                            block_hash: *block_hash,
+                        },
+                    );
+                }
+                let started = Instant::now();
+                let call_function_result = self.call_function(
+                    &shard_uid,
+                    *state_root,
+                    block_height,
+                    block_timestamp,
+                    prev_block_hash,
+                    block_hash,
+                    epoch_height,
+                    epoch_id,
+                    account_id,
+                    method_name,
+                    args.as_ref(),
+                    &mut logs,
+                    &self.epoch_manager,
+                    current_protocol_version,
+                );
+                self.slow_contracts.lock().unwrap().record(
+                    slow_contracts::ExecutionKind::ViewCall,
+                    account_id,
+                    started.elapsed(),
+                );
+                let call_function_result = call_function_result.map_err(|err| {
+                    near_chain::near_chain_primitives::error::QueryError::from_call_function_error(
+                        err,
                         block_height,
-                        block_timestamp,
-                        prev_block_hash,
-                        block_hash,
-                        epoch_height,
-                        epoch_id,
-                        account_id,
-                        method_name,
-                        args.as_ref(),
-                        &mut logs,
-                        &self.epoch_manager,
-                        current_protocol_version,
+                        *block_hash,
                     )
-                    .map_err(|err| near_chain::near_chain_primitives::error::QueryError::from_call_function_error(err, block_height, *block_hash))?;
+                })?;
                 Ok(QueryResponse {
                     kind: QueryResponseKind::CallResult(CallResult {
                         result: call_function_result,
@@ -1592,25 +1681,32 @@ impl RuntimeAdapter for NightshadeRuntime {
                     block_hash: *block_hash,
                 })
             }
-            QueryRequest::ViewAccessKeyList { account_id } => {
-                let access_key_list =
-                    self.view_access_keys(&shard_uid, *state_root, account_id).map_err(|err| {
+            QueryRequest::ViewAccessKeyList { account_id, permission, after_public_key, limit } => {
+                let (access_key_list, next_public_key) = self
+                    .view_access_keys(
+                        &shard_uid,
+                        *state_root,
+                        account_id,
+                        permission.as_ref(),
+                        after_public_key.as_ref(),
+                        *limit,
+                    )
+                    .map_err(|err| {
                         near_chain::near_chain_primitives::error::QueryError::from_view_access_key_error(
                             err,
                             block_height,
                             *block_hash,
                         )
                     })?;
+                let keys = access_key_list
+                    .into_iter()
+                    .map(|(public_key, access_key)| AccessKeyInfoView {
+                        public_key,
+                        access_key: access_key.into(),
+                    })
+                    .collect();
                 Ok(QueryResponse {
-                    kind: QueryResponseKind::AccessKeyList(
-                        access_key_list
-                            .into_iter()
-                            .map(|(public_key, access_key)| AccessKeyInfoView {
-                                public_key,
-                                access_key: access_key.into(),
-                            })
-                            .collect(),
-                    ),
+                    kind: QueryResponseKind::AccessKeyList(AccessKeyList { keys, next_public_key }),
                     block_height,
                     block_hash: *block_hash,
                 })
@@ -1655,19 +1751,29 @@ impl RuntimeAdapter for NightshadeRuntime {
         let epoch_id = self.get_epoch_id(block_hash)?;
         let shard_uid = self.get_shard_uid_from_epoch_id(shard_id, &epoch_id)?;
         let trie = self.tries.get_view_trie_for_shard(shard_uid);
-        let result = match trie.get_trie_nodes_for_part(part_id, state_root) {
-            Ok(partial_state) => partial_state,
-            Err(e) => {
-                error!(target: "runtime",
-                       "Can't get_trie_nodes_for_part for block {:?} state root {:?}, part_id {:?}, num_parts {:?}, {:?}",
-                       block_hash, state_root, part_id.idx, part_id.total, e
-                );
-                return Err(e.into());
-            }
-        }
-        .try_to_vec()
-        .expect("serializer should not fail");
-        Ok(result)
+        obtain_state_part_from_trie(&trie, block_hash, state_root, part_id)
+    }
+
+    /// Generates the parts in the rayon thread pool, every thread reading the state through its
+    /// own read-only handle of the trie. The number of the parts, and thus the memory taken by
+    /// them, is bounded by the caller.
+    fn obtain_state_parts(
+        &self,
+        shard_id: ShardId,
+        block_hash: &CryptoHash,
+        state_root: &StateRoot,
+        part_ids: &[PartId],
+    ) -> Result<Vec<Vec<u8>>, Error> {
+        let epoch_id = self.get_epoch_id(block_hash)?;
+        let shard_uid = self.get_shard_uid_from_epoch_id(shard_id, &epoch_id)?;
+        let read_only_trie = self.tries.new_read_only_trie_view(shard_uid, *state_root);
+        part_ids
+            .par_iter()
+            .map(|part_id| {
+                let trie = read_only_trie.trie();
+                obtain_state_part_from_trie(&trie, block_hash, state_root, *part_id)
+            })
+            .collect()
     }
 
     fn validate_state_part(&self, state_root: &StateRoot, part_id: PartId, data: &Vec<u8>) -> bool {
@@ -1985,10 +2091,21 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         shard_uid: &ShardUId,
         state_root: MerkleHash,
         account_id: &AccountId,
-    ) -> Result<Vec<(PublicKey, AccessKey)>, node_runtime::state_viewer::errors::ViewAccessKeyError>
-    {
+        permission: Option<&AccessKeyPermissionFilter>,
+        after_public_key: Option<&PublicKey>,
+        limit: Option<u32>,
+    ) -> Result<
+        (Vec<(PublicKey, AccessKey)>, Option<PublicKey>),
+        node_runtime::state_viewer::errors::ViewAccessKeyError,
+    > {
         let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
-        self.trie_viewer.view_access_keys(&state_update, account_id)
+        self.trie_viewer.view_access_keys(
+            &state_update,
+            account_id,
+            permission,
+            after_public_key,
+            limit,
+        )
     }
 
     fn view_state(
@@ -2179,6 +2296,9 @@ mod test {
                 tracked_config,
                 None,
                 None,
+                None,
+                None,
+                vec![],
                 Some(RuntimeConfigStore::free()),
                 DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
                 Default::default(),
@@ -2690,6 +2810,18 @@ mod test {
             .runtime
             .obtain_state_part(0, &block_hash, &env.state_roots[0], PartId::new(0, 1))
             .unwrap();
+        // Generating the parts concurrently gives the same parts as one by one.
+        let part_ids: Vec<_> = (0..4).map(|part_id| PartId::new(part_id, 4)).collect();
+        let state_parts =
+            env.runtime.obtain_state_parts(0, &block_hash, &env.state_roots[0], &part_ids).unwrap();
+        for (part_id, part) in part_ids.into_iter().zip(state_parts) {
+            assert_eq!(
+                part,
+                env.runtime
+                    .obtain_state_part(0, &block_hash, &env.state_roots[0], part_id)
+                    .unwrap()
+            );
+        }
         let root_node =
             env.runtime.get_state_root_node(0, &block_hash, &env.state_roots[0]).unwrap();
         let mut new_env = TestEnv::new(vec![validators], 2, false);
@@ -2819,6 +2951,7 @@ mod test {
                 num_expected_blocks: expected_blocks[0],
                 num_produced_chunks: expected_chunks[0],
                 num_expected_chunks: expected_chunks[0],
+                delegation: None,
             },
             CurrentEpochValidatorInfo {
                 account_id: "test2".parse().unwrap(),
@@ -2830,6 +2963,7 @@ mod test {
                 num_expected_blocks: expected_blocks[1],
                 num_produced_chunks: expected_chunks[1],
                 num_expected_chunks: expected_chunks[1],
+                delegation: None,
             },
         ];
         let next_epoch_validator_info = vec![
@@ -2838,12 +2972,14 @@ mod test {
                 public_key: block_producers[0].public_key(),
                 stake: TESTING_INIT_STAKE,
                 shards: vec![0],
+                delegation: None,
             },
             NextEpochValidatorInfo {
                 account_id: "test2".parse().unwrap(),
                 public_key: block_producers[1].public_key(),
                 stake: TESTING_INIT_STAKE,
                 shards: vec![0],
+                delegation: None,
             },
         ];
         let response = env
@@ -2893,6 +3029,7 @@ mod test {
                 public_key: block_producers[1].public_key(),
                 stake: TESTING_INIT_STAKE,
                 shards: vec![0],
+                delegation: None,
             }]
         );
         assert!(response.current_proposals.is_empty());
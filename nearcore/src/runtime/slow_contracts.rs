@@ -0,0 +1,116 @@
+//! Detection of the contracts whose executions consistently take longer than
+//! `ClientConfig::slow_contract_threshold`, either when executing receipts or view methods.
+//! Such contracts are reported in the logs and metrics, so that the operators of the RPC nodes
+//! can add them to `ClientConfig::view_call_blacklist`.
+use crate::metrics;
+use near_primitives::types::AccountId;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::warn;
+
+/// Number of the consecutive executions over the threshold after which a contract is reported.
+const SLOW_EXECUTIONS_TO_REPORT: u32 = 5;
+/// Number of the contracts with slow executions tracked at once, to bound the memory and the
+/// number of the exported metrics.
+const MAX_TRACKED_CONTRACTS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::AsRefStr)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum ExecutionKind {
+    Receipt,
+    ViewCall,
+}
+
+pub(crate) struct SlowContracts {
+    threshold: Option<Duration>,
+    /// Number of the consecutive slow executions of the tracked contracts.
+    streaks: HashMap<(ExecutionKind, AccountId), u32>,
+}
+
+impl SlowContracts {
+    pub fn new(threshold: Option<Duration>) -> Self {
+        Self { threshold, streaks: HashMap::new() }
+    }
+
+    /// Records an execution of the contract `account_id`. Returns whether the contract has
+    /// just been reported as slow.
+    pub fn record(
+        &mut self,
+        kind: ExecutionKind,
+        account_id: &AccountId,
+        elapsed: Duration,
+    ) -> bool {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return false,
+        };
+        let key = (kind, account_id.clone());
+        if elapsed <= threshold {
+            if let Some(streak) = self.streaks.remove(&key) {
+                if streak >= SLOW_EXECUTIONS_TO_REPORT {
+                    let _ = metrics::SLOW_CONTRACTS
+                        .remove_label_values(&[kind.as_ref(), account_id.as_ref()]);
+                }
+            }
+            return false;
+        }
+        metrics::SLOW_CONTRACT_EXECUTIONS.with_label_values(&[kind.as_ref()]).inc();
+        if !self.streaks.contains_key(&key) && self.streaks.len() >= MAX_TRACKED_CONTRACTS {
+            return false;
+        }
+        let streak = self.streaks.entry(key).or_default();
+        *streak += 1;
+        if *streak < SLOW_EXECUTIONS_TO_REPORT {
+            return false;
+        }
+        metrics::SLOW_CONTRACTS
+            .with_label_values(&[kind.as_ref(), account_id.as_ref()])
+            .set(*streak as i64);
+        if *streak > SLOW_EXECUTIONS_TO_REPORT {
+            return false;
+        }
+        warn!(
+            target: "runtime",
+            %account_id,
+            kind = kind.as_ref(),
+            ?elapsed,
+            "Contract exceeded the execution time budget {} times in a row",
+            SLOW_EXECUTIONS_TO_REPORT,
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let ms = Duration::from_millis;
+        let (a, b): (AccountId, AccountId) = ("a.near".parse().unwrap(), "b.near".parse().unwrap());
+        let mut disabled = SlowContracts::new(None);
+        assert!(!disabled.record(ExecutionKind::Receipt, &a, ms(1000)));
+        assert!(disabled.streaks.is_empty());
+
+        let mut slow = SlowContracts::new(Some(ms(100)));
+        for _ in 1..SLOW_EXECUTIONS_TO_REPORT {
+            assert!(!slow.record(ExecutionKind::ViewCall, &a, ms(150)));
+            assert!(!slow.record(ExecutionKind::ViewCall, &b, ms(150)));
+        }
+        // A fast execution breaks the streak.
+        assert!(!slow.record(ExecutionKind::ViewCall, &b, ms(50)));
+        // Executions of the receipts are tracked separately.
+        assert!(!slow.record(ExecutionKind::Receipt, &a, ms(150)));
+        assert!(slow.record(ExecutionKind::ViewCall, &a, ms(150)));
+        assert!(!slow.record(ExecutionKind::ViewCall, &b, ms(150)));
+        assert_eq!(
+            SLOW_EXECUTIONS_TO_REPORT as i64,
+            metrics::SLOW_CONTRACTS.with_label_values(&["view_call", "a.near"]).get()
+        );
+        // Reported only once per streak.
+        assert!(!slow.record(ExecutionKind::ViewCall, &a, ms(150)));
+        assert!(!slow.record(ExecutionKind::ViewCall, &a, ms(100)));
+        assert!(!slow.streaks.contains_key(&(ExecutionKind::ViewCall, a)));
+    }
+}
@@ -0,0 +1,112 @@
+//! Metrics of the size of the state witnesses of the applied chunks, i.e. of the storage
+//! proofs which validators would need to validate the chunks without having the state.
+//! Collected by all the nodes to inform the limits of the state witness.
+use crate::metrics;
+use near_primitives::types::{AccountId, ShardId};
+use node_runtime::StateWitnessSize;
+use std::collections::HashMap;
+
+/// Number of the chunks of a shard over which the contributions of the contracts are summed up.
+const TOP_CONTRACTS_WINDOW: u64 = 100;
+/// Number of the contracts contributing the most exported per shard.
+const NUM_TOP_CONTRACTS: usize = 10;
+
+#[derive(Default)]
+struct ShardStats {
+    num_chunks: u64,
+    by_receiver: HashMap<AccountId, u64>,
+    /// Contracts exported at the end of the previous window.
+    exported: Vec<AccountId>,
+}
+
+#[derive(Default)]
+pub(crate) struct StateWitnessStats {
+    shards: HashMap<ShardId, ShardStats>,
+}
+
+impl StateWitnessStats {
+    /// Records the size of the state witness of a chunk of `shard_id`. At the end of every
+    /// window, exports the contracts contributing the most instead of the previous ones, so
+    /// that the number of the exported contracts stays bounded.
+    pub fn record(&mut self, shard_id: ShardId, size: &StateWitnessSize) {
+        let shard_label = shard_id.to_string();
+        metrics::STATE_WITNESS_SIZE.with_label_values(&[&shard_label]).observe(size.total as f64);
+        let shard = self.shards.entry(shard_id).or_default();
+        for (account_id, bytes) in &size.by_receiver {
+            *shard.by_receiver.entry(account_id.clone()).or_default() += bytes;
+        }
+        shard.num_chunks += 1;
+        if shard.num_chunks < TOP_CONTRACTS_WINDOW {
+            return;
+        }
+        for account_id in shard.exported.drain(..) {
+            let _ = metrics::STATE_WITNESS_TOP_CONTRACTS
+                .remove_label_values(&[&shard_label, account_id.as_ref()]);
+        }
+        for (account_id, bytes) in top_contracts(std::mem::take(&mut shard.by_receiver)) {
+            metrics::STATE_WITNESS_TOP_CONTRACTS
+                .with_label_values(&[&shard_label, account_id.as_ref()])
+                .set((bytes / shard.num_chunks) as i64);
+            shard.exported.push(account_id);
+        }
+        shard.num_chunks = 0;
+    }
+}
+
+/// `NUM_TOP_CONTRACTS` contracts with the most bytes, the largest first.
+fn top_contracts(by_receiver: HashMap<AccountId, u64>) -> Vec<(AccountId, u64)> {
+    let mut contracts: Vec<_> = by_receiver.into_iter().collect();
+    contracts.sort_by(|(a, a_bytes), (b, b_bytes)| b_bytes.cmp(a_bytes).then_with(|| a.cmp(b)));
+    contracts.truncate(NUM_TOP_CONTRACTS);
+    contracts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_top_contracts() {
+        let by_receiver: HashMap<AccountId, u64> = (0..20u64)
+            .map(|i| (format!("contract{:02}.near", i).parse().unwrap(), i % 15))
+            .collect();
+        let top = top_contracts(by_receiver);
+        assert_eq!(NUM_TOP_CONTRACTS, top.len());
+        assert_eq!(("contract14.near".parse().unwrap(), 14), top[0]);
+        // Ties are broken by the account id.
+        assert_eq!(("contract13.near".parse().unwrap(), 13), top[1]);
+        assert_eq!(("contract12.near".parse().unwrap(), 12), top[2]);
+        assert_eq!(("contract05.near".parse().unwrap(), 5), top[9]);
+    }
+
+    #[test]
+    fn test_record() {
+        let mut stats = StateWitnessStats::default();
+        let size = StateWitnessSize {
+            total: 300,
+            by_receiver: [("a.near".parse().unwrap(), 200), ("b.near".parse().unwrap(), 50)]
+                .into_iter()
+                .collect(),
+        };
+        for _ in 0..TOP_CONTRACTS_WINDOW - 1 {
+            stats.record(7, &size);
+        }
+        assert!(stats.shards[&7].exported.is_empty());
+        assert_eq!(
+            Some(&(200 * (TOP_CONTRACTS_WINDOW - 1))),
+            stats.shards[&7].by_receiver.get(&"a.near".parse().unwrap())
+        );
+        stats.record(7, &size);
+        let shard = &stats.shards[&7];
+        assert_eq!(0, shard.num_chunks);
+        assert!(shard.by_receiver.is_empty());
+        assert_eq!(
+            vec!["a.near".parse::<AccountId>().unwrap(), "b.near".parse().unwrap()],
+            shard.exported
+        );
+        assert_eq!(
+            200,
+            metrics::STATE_WITNESS_TOP_CONTRACTS.with_label_values(&["7", "a.near"]).get()
+        );
+    }
+}
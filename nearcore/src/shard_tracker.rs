@@ -1,7 +1,7 @@
 use std::sync::{Arc, RwLock};
 
 use crate::append_only_map::AppendOnlyMap;
-use near_chain_configs::ClientConfig;
+use near_chain_configs::{ClientConfig, TrackedShardsPolicy};
 use near_epoch_manager::EpochManager;
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
@@ -12,6 +12,7 @@ const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
 pub enum TrackedConfig {
     Accounts(Vec<AccountId>),
+    Shards(Vec<ShardId>),
     AllShards,
 }
 
@@ -21,10 +22,17 @@ impl TrackedConfig {
     }
 
     pub fn from_config(config: &ClientConfig) -> Self {
-        if config.tracked_shards.is_empty() {
-            TrackedConfig::Accounts(config.tracked_accounts.clone())
-        } else {
-            TrackedConfig::AllShards
+        match &config.tracked_shards_policy {
+            Some(TrackedShardsPolicy::AssignedPlusNext) => {
+                TrackedConfig::Accounts(config.tracked_accounts.clone())
+            }
+            Some(TrackedShardsPolicy::All) => TrackedConfig::AllShards,
+            Some(TrackedShardsPolicy::Shards(shards)) => TrackedConfig::Shards(shards.clone()),
+            // Without a policy, any non-empty `tracked_shards` means all shards.
+            None if config.tracked_shards.is_empty() => {
+                TrackedConfig::Accounts(config.tracked_accounts.clone())
+            }
+            None => TrackedConfig::AllShards,
         }
     }
 }
@@ -32,12 +40,13 @@ impl TrackedConfig {
 // bit mask for which shard to track
 type BitMask = Vec<bool>;
 
-/// Tracker that tracks shard ids and accounts. Right now, it only supports two modes
+/// Tracker that tracks shard ids and accounts. It supports three modes
 /// TrackedConfig::Accounts(accounts): track the shards where `accounts` belong to
+/// TrackedConfig::Shards(shards): track the given shards of each epoch's shard layout
 /// TrackedConfig::AllShards: track all shards
 pub struct ShardTracker {
     tracked_config: TrackedConfig,
-    /// Stores shard tracking information by epoch, only useful if TrackedState == Accounts
+    /// Stores shard tracking information by epoch, only useful if TrackedState is Accounts or Shards
     tracking_shards: AppendOnlyMap<EpochId, BitMask>,
     /// Epoch manager that for given block hash computes the epoch id.
     epoch_manager: Arc<RwLock<EpochManager>>,
@@ -67,6 +76,20 @@ impl ShardTracker {
                 });
                 Ok(tracking_mask.get(shard_id as usize).copied().unwrap_or(false))
             }
+            TrackedConfig::Shards(tracked_shards) => {
+                let epoch_manager = self.epoch_manager.read().expect(POISONED_LOCK_ERR);
+                let shard_layout = epoch_manager.get_shard_layout(epoch_id)?;
+                let tracking_mask = self.tracking_shards.get_or_insert(epoch_id, || {
+                    let mut tracking_mask = vec![false; shard_layout.num_shards() as usize];
+                    for shard_id in tracked_shards {
+                        if let Some(tracked) = tracking_mask.get_mut(*shard_id as usize) {
+                            *tracked = true;
+                        }
+                    }
+                    tracking_mask
+                });
+                Ok(tracking_mask.get(shard_id as usize).copied().unwrap_or(false))
+            }
             TrackedConfig::AllShards => Ok(true),
         }
     }
@@ -152,6 +175,7 @@ mod tests {
 
     use crate::shard_tracker::TrackedConfig;
     use crate::shard_tracker::POISONED_LOCK_ERR;
+    use near_chain_configs::{ClientConfig, TrackedShardsPolicy};
     use near_epoch_manager::test_utils::hash_range;
     use near_primitives::utils::get_num_seats_per_shard;
     use near_primitives::version::ProtocolFeature::SimpleNightshade;
@@ -283,6 +307,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_track_shards() {
+        let num_shards = 4;
+        let epoch_manager = get_epoch_manager(PROTOCOL_VERSION, num_shards, None);
+        // Shards outside of the layout are ignored.
+        let tracker = ShardTracker::new(
+            TrackedConfig::Shards(vec![1, 3, 7]),
+            Arc::new(RwLock::new(epoch_manager)),
+        );
+        let total_tracked_shards: HashSet<_> = [1, 3].into_iter().collect();
+
+        assert_eq!(
+            get_all_shards_care_about(&tracker, num_shards, &CryptoHash::default()),
+            total_tracked_shards
+        );
+        assert_eq!(
+            get_all_shards_will_care_about(&tracker, num_shards, &CryptoHash::default()),
+            total_tracked_shards
+        );
+    }
+
+    #[test]
+    fn test_tracked_config_from_policy() {
+        let mut config = ClientConfig::test(false, 100, 200, 1, false, true);
+        config.tracked_accounts = vec!["test1".parse().unwrap()];
+        config.tracked_shards = vec![0];
+        assert!(matches!(TrackedConfig::from_config(&config), TrackedConfig::AllShards));
+        config.tracked_shards_policy = Some(TrackedShardsPolicy::Shards(vec![0]));
+        assert!(matches!(
+            TrackedConfig::from_config(&config),
+            TrackedConfig::Shards(shards) if shards == vec![0]
+        ));
+        config.tracked_shards_policy = Some(TrackedShardsPolicy::AssignedPlusNext);
+        assert!(matches!(
+            TrackedConfig::from_config(&config),
+            TrackedConfig::Accounts(accounts) if accounts == config.tracked_accounts
+        ));
+        config.tracked_shards = vec![];
+        config.tracked_shards_policy = Some(TrackedShardsPolicy::All);
+        assert!(matches!(TrackedConfig::from_config(&config), TrackedConfig::AllShards));
+    }
+
     #[test]
     fn test_track_all_shards() {
         let num_shards = 4;
@@ -412,9 +412,25 @@ impl RunCmd {
         let (tx, rx) = oneshot::channel::<()>();
         let sys = new_actix_system(runtime);
         sys.block_on(async move {
-            let nearcore::NearNode { rpc_servers, .. } =
-                nearcore::start_with_config_and_synchronization(home_dir, near_config, Some(tx))
-                    .expect("start_with_config");
+            // A node with a database which couldn't be repaired serves only the status
+            // instead of crash-looping; `tx` is kept alive as there is no client to die.
+            let (rpc_servers, _tx) = match nearcore::db_recovery::run(home_dir, &near_config) {
+                Some(report) if report.safe_mode => (
+                    nearcore::db_recovery::start_safe_mode(home_dir, near_config, report),
+                    Some(tx),
+                ),
+                report => {
+                    near_config.client_config.db_recovery_report = report;
+                    let nearcore::NearNode { rpc_servers, .. } =
+                        nearcore::start_with_config_and_synchronization(
+                            home_dir,
+                            near_config,
+                            Some(tx),
+                        )
+                        .expect("start_with_config");
+                    (rpc_servers, None)
+                }
+            };
 
             let sig = wait_for_interrupt_signal(home_dir, rx).await;
             warn!(target: "neard", "{}, stopping... this may take a few minutes.", sig);
@@ -41,6 +41,8 @@ extern "C" {
     fn alt_bn128_g1_sum(value_len: u64, value_ptr: u64, register_id: u64);
     fn alt_bn128_pairing_check(value_len: u64, value_ptr: u64) -> u64;
     fn random_seed(register_id: u64);
+    #[cfg(feature = "nightly")]
+    fn receipt_random_seed(salt_len: u64, salt_ptr: u64, register_id: u64);
     fn sha256(value_len: u64, value_ptr: u64, register_id: u64);
     fn keccak256(value_len: u64, value_ptr: u64, register_id: u64);
     fn keccak512(value_len: u64, value_ptr: u64, register_id: u64);
@@ -374,6 +376,29 @@ pub unsafe fn sha256_10kib_10k() {
     }
 }
 
+// Function to measure `receipt_random_seed_base` and `receipt_random_seed_byte`. Also measures
+// `base`, `write_register_base`, and `write_register_byte`, like `sha256_10b_10k`.
+// Compute receipt_random_seed with 10b salt 10k times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn receipt_random_seed_10b_10k() {
+    let buffer = [65u8; 10];
+    for _ in 0..10_000 {
+        receipt_random_seed(buffer.len() as u64, buffer.as_ptr() as *const u64 as u64, 0);
+    }
+}
+// Function to measure `receipt_random_seed_base` and `receipt_random_seed_byte`. Also measures
+// `base`, `write_register_base`, and `write_register_byte`, like `sha256_10kib_10k`.
+// Compute receipt_random_seed with 10kib salt 10k times.
+#[cfg(feature = "nightly")]
+#[no_mangle]
+pub unsafe fn receipt_random_seed_10kib_10k() {
+    let buffer = [65u8; 10240];
+    for _ in 0..10_000 {
+        receipt_random_seed(buffer.len() as u64, buffer.as_ptr() as *const u64 as u64, 0);
+    }
+}
+
 // Function to measure `keccak256_base` and `keccak256_byte`. Also measures `base`, `write_register_base`,
 // and `write_register_byte`. However `keccak256` computation is more expensive than register writing
 // so we are okay overcharging it.
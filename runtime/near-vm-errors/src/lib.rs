@@ -224,6 +224,8 @@ pub enum HostError {
     /// Invalid input to alt_bn128 familiy of functions (e.g., point which isn't
     /// on the curve).
     AltBn128InvalidInput { msg: String },
+    /// Exceeded the wall-clock time limit of a view method execution.
+    ExecutionTimeout,
 }
 
 #[derive(Debug, PartialEq)]
@@ -421,6 +423,7 @@ impl std::fmt::Display for HostError {
             Deprecated {method_name}=> write!(f, "Attempted to call deprecated host function {}", method_name),
             AltBn128InvalidInput { msg } => write!(f, "AltBn128 invalid input: {}", msg),
             ECRecoverError { msg } => write!(f, "ECDSA recover error: {}", msg),
+            ExecutionTimeout => write!(f, "Exceeded the time limit of the view method execution."),
         }
     }
 }
@@ -54,6 +54,10 @@ pub struct VMContext {
     #[serde(with = "crate::serde_with::bytes_as_base58")]
     /// Initial seed for randomness
     pub random_seed: Vec<u8>,
+    /// Seed for randomness shared by all the actions of the receipt, derived from the random
+    /// value of the block and the receipt id.
+    #[serde(with = "crate::serde_with::bytes_as_base58")]
+    pub receipt_random_seed: Vec<u8>,
     /// If Some, it means that execution is made in a view mode and defines its configuration.
     /// View mode means that only read-only operations are allowed.
     /// See <https://nomicon.io/Proposals/0018-view-change-method.html> for more details.
@@ -10,6 +10,8 @@ use near_primitives_core::{
 };
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
 
 #[inline]
 pub fn with_ext_cost_counter(f: impl FnOnce(&mut HashMap<ExtCosts, u64>)) {
@@ -29,7 +31,7 @@ type Result<T> = ::std::result::Result<T, VMLogicError>;
 
 /// Fast gas counter with very simple structure, could be exposed to compiled code in the VM.
 #[repr(C)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug)]
 pub struct FastGasCounter {
     /// The following three fields must be put next to another to make sure
     /// generated gas counting code can use and adjust them.
@@ -39,8 +41,10 @@ pub struct FastGasCounter {
 
     /// The amount of gas that was irreversibly used for contract execution.
     pub burnt_gas: u64,
-    /// Hard gas limit for execution
-    pub gas_limit: u64,
+    /// Hard gas limit for execution.  Atomic, as it is dropped to zero from another
+    /// thread to interrupt the compiled code at the deadline of the execution.
+    /// `AtomicU64` has the same in-memory representation as `u64`.
+    pub gas_limit: AtomicU64,
     /// Single WASM opcode cost
     pub opcode_cost: u64,
 }
@@ -57,6 +61,8 @@ pub struct GasCounter {
     prepaid_gas: Gas,
     /// If this is a view-only call.
     is_view: bool,
+    /// Wall-clock time after which the execution is aborted. Only set for view calls.
+    deadline: Option<Instant>,
     ext_costs_config: ExtCostsConfig,
     /// Where to store profile data, if needed.
     profile: ProfileData,
@@ -75,6 +81,7 @@ impl GasCounter {
         opcode_cost: u32,
         prepaid_gas: Gas,
         is_view: bool,
+        deadline: Option<Instant>,
     ) -> Self {
         use std::cmp::min;
         // Ignore prepaid gas limit when in view.
@@ -83,13 +90,14 @@ impl GasCounter {
             ext_costs_config,
             fast_counter: FastGasCounter {
                 burnt_gas: 0,
-                gas_limit: min(max_gas_burnt, prepaid_gas),
+                gas_limit: AtomicU64::new(min(max_gas_burnt, prepaid_gas)),
                 opcode_cost: Gas::from(opcode_cost),
             },
             max_gas_burnt: max_gas_burnt,
             promises_gas: 0,
             prepaid_gas,
             is_view,
+            deadline,
             profile: Default::default(),
         }
     }
@@ -99,6 +107,7 @@ impl GasCounter {
     /// being used, i.e. if `burn_gas > use_gas`.
     fn deduct_gas(&mut self, burn_gas: Gas, use_gas: Gas) -> Result<()> {
         assert!(burn_gas <= use_gas);
+        self.check_deadline()?;
         let promise_gas = use_gas - burn_gas;
         let new_promises_gas =
             self.promises_gas.checked_add(promise_gas).ok_or(HostError::IntegerOverflow)?;
@@ -109,8 +118,12 @@ impl GasCounter {
         if new_burnt_gas <= self.max_gas_burnt && new_used_gas <= self.prepaid_gas {
             use std::cmp::min;
             if promise_gas != 0 && !self.is_view {
-                self.fast_counter.gas_limit =
-                    min(self.max_gas_burnt, self.prepaid_gas - new_promises_gas);
+                // The limit only decreases here, and taking the minimum keeps
+                // it at zero if the execution was interrupted meanwhile.
+                self.fast_counter.gas_limit.fetch_min(
+                    min(self.max_gas_burnt, self.prepaid_gas - new_promises_gas),
+                    Ordering::Relaxed,
+                );
             }
             self.fast_counter.burnt_gas = new_burnt_gas;
             self.promises_gas = new_promises_gas;
@@ -122,9 +135,10 @@ impl GasCounter {
 
     // Optimized version of above function for cases where no promises involved.
     pub fn burn_gas(&mut self, value: Gas) -> Result<()> {
+        self.check_deadline()?;
         let new_burnt_gas =
             self.fast_counter.burnt_gas.checked_add(value).ok_or(HostError::IntegerOverflow)?;
-        if new_burnt_gas <= self.fast_counter.gas_limit {
+        if new_burnt_gas <= self.fast_counter.gas_limit.load(Ordering::Relaxed) {
             self.fast_counter.burnt_gas = new_burnt_gas;
             Ok(())
         } else {
@@ -132,6 +146,31 @@ impl GasCounter {
         }
     }
 
+    /// Wall-clock time after which the execution is aborted, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Reports `ExecutionTimeout` if the deadline of the execution has passed.
+    ///
+    /// Checked whenever gas is charged, i.e. on every host function call. The
+    /// compiled code of wasmer2 counts the gas of the plain Wasm instructions
+    /// itself, so its runner interrupts that code at the deadline by dropping
+    /// the gas limit to zero, see `process_gas_limit`.
+    #[inline]
+    fn check_deadline(&self) -> Result<()> {
+        if self.is_past_deadline() {
+            Err(HostError::ExecutionTimeout.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    fn is_past_deadline(&self) -> bool {
+        matches!(self.deadline, Some(deadline) if Instant::now() >= deadline)
+    }
+
     pub fn process_gas_limit(&mut self, new_burnt_gas: Gas, new_used_gas: Gas) -> HostError {
         use std::cmp::min;
         // Never burn more gas than what was paid for.
@@ -147,6 +186,12 @@ impl GasCounter {
         assert!(used_gas_limit >= self.fast_counter.burnt_gas);
         self.promises_gas = used_gas_limit - self.fast_counter.burnt_gas;
 
+        // The gas limit is also exceeded when the execution is interrupted at
+        // its deadline, in which case it is the timeout that is reported.
+        if self.is_past_deadline() {
+            return HostError::ExecutionTimeout;
+        }
+
         // If we crossed both limits prefer reporting GasLimitExceeded.
         // Alternative would be to prefer reporting limit that is lower (or
         // perhaps even some other heuristic) but old code preferred
@@ -304,9 +349,10 @@ impl GasCounter {
 mod tests {
     use crate::{ExtCostsConfig, HostError};
     use near_primitives_core::types::Gas;
+    use std::time::{Duration, Instant};
 
     fn make_test_counter(max_burnt: Gas, prepaid: Gas, is_view: bool) -> super::GasCounter {
-        super::GasCounter::new(ExtCostsConfig::test(), max_burnt, 1, prepaid, is_view)
+        super::GasCounter::new(ExtCostsConfig::test(), max_burnt, 1, prepaid, is_view, None)
     }
 
     #[test]
@@ -317,6 +363,23 @@ mod tests {
         assert_eq!(counter.used_gas(), 10);
     }
 
+    #[test]
+    fn test_deadline() {
+        let deadline = Instant::now() + Duration::from_secs(3600);
+        let mut counter =
+            super::GasCounter::new(ExtCostsConfig::test(), 10, 1, 10, true, Some(deadline));
+        counter.burn_gas(5).expect("burn_gas should work before the deadline");
+
+        let deadline = Instant::now();
+        let mut counter =
+            super::GasCounter::new(ExtCostsConfig::test(), 10, 1, 10, true, Some(deadline));
+        assert_eq!(counter.burn_gas(5), Err(HostError::ExecutionTimeout.into()));
+        assert_eq!(counter.deduct_gas(5, 5), Err(HostError::ExecutionTimeout.into()));
+        assert_eq!(counter.burnt_gas(), 0);
+        // The compiled code of wasmer2 exhausts its gas limit at the deadline.
+        assert_eq!(counter.process_gas_limit(11, 11), HostError::ExecutionTimeout);
+    }
+
     #[test]
     #[should_panic]
     fn test_burn_gas_must_be_lt_use_gas() {
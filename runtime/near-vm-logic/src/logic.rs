@@ -24,6 +24,7 @@ use near_vm_errors::{HostError, VMLogicError};
 use near_vm_errors::{InconsistentStateError, VMError};
 use std::collections::HashMap;
 use std::mem::size_of;
+use std::time::Instant;
 
 pub type Result<T> = ::std::result::Result<T, VMLogicError>;
 
@@ -116,9 +117,14 @@ impl<'a> VMLogic<'a> {
         let current_account_balance = context.account_balance + context.attached_deposit;
         let current_storage_usage = context.storage_usage;
         let max_gas_burnt = match context.view_config {
-            Some(ViewConfig { max_gas_burnt: max_gas_burnt_view }) => max_gas_burnt_view,
+            Some(ViewConfig { max_gas_burnt: max_gas_burnt_view, .. }) => max_gas_burnt_view,
             None => config.limit_config.max_gas_burnt,
         };
+        let deadline = context
+            .view_config
+            .as_ref()
+            .and_then(|view_config| view_config.timeout)
+            .map(|timeout| Instant::now() + timeout);
 
         let current_account_locked_balance = context.account_locked_balance;
         let gas_counter = GasCounter::new(
@@ -127,6 +133,7 @@ impl<'a> VMLogic<'a> {
             config.regular_op_cost,
             context.prepaid_gas,
             context.is_view(),
+            deadline,
         );
         Self {
             ext,
@@ -932,6 +939,56 @@ impl<'a> VMLogic<'a> {
         self.internal_write_register(register_id, self.context.random_seed.clone())
     }
 
+    /// Writes into the register a random seed shared by all the actions of the current receipt,
+    /// mixed with `salt`: `sha256(receipt_random_seed || salt)`, where `receipt_random_seed` is
+    /// derived from the random value of the block and the receipt id. Contracts get a distinct
+    /// value per receipt without hashing the block hash or the block timestamp themselves, and
+    /// can draw several independent values within a receipt by passing different salts.
+    ///
+    /// # Security
+    ///
+    /// The value is deterministic, so that all the validators agree on the result of the
+    /// execution, and hence it is not a secret:
+    ///
+    /// * The random value of the block is public as soon as the block is produced, so anyone
+    ///   can compute the seed of a receipt executed in it, e.g. in a view call, before the
+    ///   receipt is executed.
+    /// * The block producer knows the random value of its block before publishing it, and can
+    ///   bias the outcome by skipping its block, at the cost of the block reward.
+    /// * Without a salt, all the actions and all the cross-contract calls made within the
+    ///   receipt get the same value.
+    ///
+    /// Contracts at stake for more than a block reward should use a commit-reveal scheme
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// If `salt_len + salt_ptr` points outside the memory or the registers use more memory than
+    /// the limit with `MemoryAccessViolation`.
+    ///
+    /// # Cost
+    ///
+    /// `base + read_memory_base + read_memory_byte * salt_len + receipt_random_seed_base +
+    /// receipt_random_seed_byte * salt_len + write_register_base + write_register_byte * 32`
+    pub fn receipt_random_seed(
+        &mut self,
+        salt_len: u64,
+        salt_ptr: u64,
+        register_id: u64,
+    ) -> Result<()> {
+        self.gas_counter.pay_base(base)?;
+        self.gas_counter.pay_base(receipt_random_seed_base)?;
+        let salt = self.get_vec_from_memory_or_register(salt_ptr, salt_len)?;
+        self.gas_counter.pay_per(receipt_random_seed_byte, salt.len() as u64)?;
+
+        use sha2::Digest;
+
+        let mut hasher = sha2::Sha256::new();
+        hasher.update(&self.context.receipt_random_seed);
+        hasher.update(&salt);
+        self.internal_write_register(register_id, hasher.finalize().as_slice().to_vec())
+    }
+
     /// Hashes the given value using sha256 and returns it into `register_id`.
     ///
     /// # Errors
@@ -2724,6 +2781,11 @@ impl<'a> VMLogic<'a> {
         self.gas_counter.gas_counter_raw_ptr()
     }
 
+    /// Wall-clock time after which the execution is aborted, if any.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.gas_counter.deadline()
+    }
+
     /// Properly handles gas limit exceeded error.
     pub fn process_gas_limit(&mut self) -> HostError {
         let new_burn_gas = self.gas_counter.burnt_gas();
@@ -17,6 +17,7 @@ pub fn create_context() -> VMContext {
         attached_deposit: 2u128,
         prepaid_gas: 10_u64.pow(14),
         random_seed: vec![0, 1, 2],
+        receipt_random_seed: vec![3, 4, 5],
         view_config: None,
         output_data_receivers: vec![],
     }
@@ -462,6 +462,42 @@ fn test_sha256() {
     });
 }
 
+#[test]
+fn test_receipt_random_seed() {
+    let mut logic_builder = VMLogicBuilder::default();
+    let mut context = get_context(vec![], false);
+    context.receipt_random_seed = vec![7; 32];
+    let mut logic = logic_builder.build(context);
+    let salt = b"tesdsst";
+
+    logic.receipt_random_seed(salt.len() as _, salt.as_ptr() as _, 0).unwrap();
+    let res = &vec![0u8; 32];
+    logic.read_register(0, res.as_ptr() as _).expect("OK");
+    use sha2::Digest;
+    let expected = sha2::Sha256::digest(&[&[7; 32][..], &salt[..]].concat());
+    assert_eq!(res.as_slice(), expected.as_slice());
+    let len = salt.len() as u64;
+    assert_costs(map! {
+        ExtCosts::base: 1,
+        ExtCosts::read_memory_base: 1,
+        ExtCosts::read_memory_byte: len,
+        ExtCosts::write_memory_base: 1,
+        ExtCosts::write_memory_byte: 32,
+        ExtCosts::read_register_base: 1,
+        ExtCosts::read_register_byte: 32,
+        ExtCosts::write_register_base: 1,
+        ExtCosts::write_register_byte: 32,
+        ExtCosts::receipt_random_seed_base: 1,
+        ExtCosts::receipt_random_seed_byte: len,
+    });
+
+    // Other salts give independent values.
+    logic.receipt_random_seed(0, 0, 1).unwrap();
+    let unsalted = &vec![0u8; 32];
+    logic.read_register(1, unsalted.as_ptr() as _).expect("OK");
+    assert_ne!(res, unsalted);
+}
+
 #[test]
 fn test_keccak256() {
     let mut logic_builder = VMLogicBuilder::default();
@@ -228,6 +228,10 @@ imports! {
     #[AltBn128] alt_bn128_g1_multiexp<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[AltBn128] alt_bn128_g1_sum<[value_len: u64, value_ptr: u64, register_id: u64] -> []>,
     #[AltBn128] alt_bn128_pairing_check<[value_len: u64, value_ptr: u64] -> [u64]>,
+    // #############################
+    // # Receipt-level random seed #
+    // #############################
+    #["protocol_feature_receipt_random_seed", ReceiptRandomSeed] receipt_random_seed<[salt_len: u64, salt_ptr: u64, register_id: u64] -> []>,
     // #############
     // #  Sandbox  #
     // #############
@@ -2,6 +2,7 @@ use super::test_builder::test_builder;
 use expect_test::expect;
 use near_primitives::version::ProtocolFeature;
 use std::fmt::Write;
+use std::time::Duration;
 
 static INFINITE_INITIALIZER_CONTRACT: &str = r#"
 (module
@@ -21,6 +22,24 @@ fn test_infinite_initializer() {
         "#]]);
 }
 
+static INFINITE_LOOP_CONTRACT: &str = r#"
+(module
+  (func (export "main") (loop (br 0)))
+)"#;
+
+#[test]
+fn test_infinite_loop_view_timeout() {
+    // The loop calls no host functions, so only the deadline can stop it
+    // before the view gas limit is reached.
+    test_builder()
+        .wat(INFINITE_LOOP_CONTRACT)
+        .view_timeout(Duration::from_millis(100))
+        .opaque_outcome()
+        .expect(expect![[r#"
+            Err: Exceeded the time limit of the view method execution.
+        "#]]);
+}
+
 #[test]
 fn test_infinite_initializer_export_not_found() {
     test_builder()
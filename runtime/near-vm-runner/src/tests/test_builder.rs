@@ -1,5 +1,6 @@
 use crate::internal::VMKind;
 use near_primitives::{
+    config::ViewConfig,
     contract::ContractCode,
     runtime::{config_store::RuntimeConfigStore, fees::RuntimeFeesConfig},
     types::Gas,
@@ -8,6 +9,7 @@ use near_primitives::{
 use near_vm_logic::{mocks::mock_external::MockedExternal, ProtocolVersion, VMContext};
 use std::collections::HashSet;
 use std::fmt::Write;
+use std::time::Duration;
 
 pub(crate) fn test_builder() -> TestBuilder {
     let context = VMContext {
@@ -25,6 +27,7 @@ pub(crate) fn test_builder() -> TestBuilder {
         attached_deposit: 2u128,
         prepaid_gas: 10_u64.pow(14),
         random_seed: vec![0, 1, 2],
+        receipt_random_seed: vec![3, 4, 5],
         view_config: None,
         output_data_receivers: vec![],
     };
@@ -77,6 +80,13 @@ impl TestBuilder {
         self
     }
 
+    /// Runs the method as a view call that times out after `timeout`.
+    pub(crate) fn view_timeout(mut self, timeout: Duration) -> Self {
+        self.context.view_config =
+            Some(ViewConfig { max_gas_burnt: Gas::MAX, timeout: Some(timeout) });
+        self
+    }
+
     pub(crate) fn opaque_error(mut self) -> Self {
         self.opaque_error = true;
         self
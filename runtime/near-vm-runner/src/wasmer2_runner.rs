@@ -12,9 +12,14 @@ use near_vm_errors::{CompilationError, FunctionCallError, MethodResolveError, VM
 use near_vm_logic::gas_counter::FastGasCounter;
 use near_vm_logic::types::{PromiseResult, ProtocolVersion};
 use near_vm_logic::{External, MemoryLike, VMConfig, VMContext, VMLogic};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
 use std::hash::{Hash, Hasher};
 use std::mem::size_of;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_engine::{DeserializeError, Engine};
 use wasmer_engine_universal::{Universal, UniversalEngine, UniversalExecutableRef};
@@ -310,8 +315,14 @@ impl Wasmer2VM {
             offset_of!(FastGasCounter, opcode_cost),
             offset_of!(wasmer_types::FastGasCounter, opcode_cost)
         );
-        let gas = import.vmlogic.gas_counter_pointer() as *mut wasmer_types::FastGasCounter;
+        let fast_gas = import.vmlogic.gas_counter_pointer();
+        let gas = fast_gas as *mut wasmer_types::FastGasCounter;
         let entrypoint = get_entrypoint_index(&*artifact, method_name)?;
+        // SAFETY: the gas counter lives in `VMLogic`, which outlives the
+        // registration dropped at the end of this function.
+        let _deadline = import.vmlogic.deadline().map(|deadline| unsafe {
+            DEADLINE_TIMER.register(std::ptr::addr_of!((*fast_gas).gas_limit), deadline)
+        });
         unsafe {
             let instance = {
                 let _span = tracing::debug_span!(target: "vm", "run_method/instantiate").entered();
@@ -401,6 +412,94 @@ impl Wasmer2VM {
     }
 }
 
+/// Interrupts the compiled code of the executions at their deadlines.
+///
+/// The compiled code charges the gas of the Wasm instructions itself and only
+/// returns to the host on host function calls, so a loop without them never
+/// reaches `check_deadline` of the gas counter. At the deadline the timer drops
+/// the gas limit shared with the compiled code to zero, which makes the next gas
+/// check trap, and the trap is reported as `ExecutionTimeout`.
+///
+/// A single thread serves all the executions, started along with the timer.
+static DEADLINE_TIMER: Lazy<DeadlineTimer> = Lazy::new(|| {
+    thread::Builder::new()
+        .name("wasmer2-deadline".to_string())
+        .spawn(|| DEADLINE_TIMER.run())
+        .expect("failed to start the deadline timer thread");
+    DeadlineTimer {
+        deadlines: Mutex::new(BTreeMap::new()),
+        next_id: AtomicU64::new(0),
+        changed: Condvar::new(),
+    }
+});
+
+struct DeadlineTimer {
+    /// Gas limits of the registered executions, by their deadlines and ids.
+    deadlines: Mutex<BTreeMap<(Instant, u64), GasLimitPtr>>,
+    next_id: AtomicU64,
+    /// Notified when an execution is registered, as its deadline may be the
+    /// earliest one.
+    changed: Condvar,
+}
+
+struct GasLimitPtr(*const AtomicU64);
+
+// SAFETY: the pointer is only dereferenced by the timer thread while the
+// execution is registered, see `DeadlineTimer::register`.
+unsafe impl Send for GasLimitPtr {}
+
+/// Registration of an execution with the `DeadlineTimer`, removed when dropped.
+struct DeadlineRegistration {
+    timer: &'static DeadlineTimer,
+    key: (Instant, u64),
+}
+
+impl DeadlineTimer {
+    /// Makes the timer drop `gas_limit` to zero at `deadline`.
+    ///
+    /// # Safety
+    ///
+    /// `gas_limit` must stay valid until the returned registration is dropped.
+    unsafe fn register(
+        &'static self,
+        gas_limit: *const AtomicU64,
+        deadline: Instant,
+    ) -> DeadlineRegistration {
+        let key = (deadline, self.next_id.fetch_add(1, Ordering::Relaxed));
+        self.deadlines.lock().unwrap().insert(key, GasLimitPtr(gas_limit));
+        self.changed.notify_one();
+        DeadlineRegistration { timer: self, key }
+    }
+
+    fn run(&self) {
+        let mut deadlines = self.deadlines.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            while let Some(&key) = deadlines.keys().next() {
+                if key.0 > now {
+                    break;
+                }
+                let gas_limit = deadlines.remove(&key).unwrap();
+                // SAFETY: the execution is still registered, as the
+                // registrations are removed under the same lock.
+                unsafe { (*gas_limit.0).store(0, Ordering::Relaxed) };
+            }
+            deadlines = match deadlines.keys().next() {
+                Some(&(deadline, _)) => {
+                    self.changed.wait_timeout(deadlines, deadline - now).unwrap().0
+                }
+                None => self.changed.wait(deadlines).unwrap(),
+            };
+        }
+    }
+}
+
+impl Drop for DeadlineRegistration {
+    fn drop(&mut self) {
+        self.timer.deadlines.lock().unwrap().remove(&self.key);
+    }
+}
+
 impl wasmer_vm::Tunables for &Wasmer2VM {
     fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
         MemoryStyle::Static {
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::Context;
+use near_primitives::types::Gas;
+
+use crate::cost::Cost;
+use crate::cost_table::{CostMetadata, CostTable};
+
+/// The costs estimated so far, saved to a file after every estimation, so that
+/// an interrupted run can be resumed with the same file, skipping the costs
+/// already in it.
+///
+/// The file holds a cost table in the format of [`CostTable::to_json`].
+pub(crate) struct Checkpoint {
+    path: PathBuf,
+    table: Mutex<CostTable>,
+}
+
+impl Checkpoint {
+    /// Resumes from the checkpoint at `path`, or starts a new one if the file
+    /// doesn't exist yet.
+    pub(crate) fn open(path: &Path) -> anyhow::Result<Checkpoint> {
+        let table = if path.exists() {
+            let s = fs::read_to_string(path)
+                .with_context(|| format!("failed to read checkpoint: {}", path.display()))?;
+            CostTable::from_json(&s).map_err(|()| {
+                anyhow::format_err!("failed to parse checkpoint: {}", path.display())
+            })?
+        } else {
+            CostTable::default()
+        };
+        Ok(Checkpoint { path: path.to_path_buf(), table: Mutex::new(table) })
+    }
+
+    pub(crate) fn contains(&self, cost: Cost) -> bool {
+        self.table.lock().unwrap().get(cost).is_some()
+    }
+
+    /// Adds an estimated cost and saves the checkpoint. The file is replaced
+    /// with a rename, so it isn't left truncated if the run is interrupted
+    /// while writing it.
+    pub(crate) fn record(
+        &self,
+        cost: Cost,
+        gas: Gas,
+        metadata: CostMetadata,
+    ) -> anyhow::Result<()> {
+        let mut table = self.table.lock().unwrap();
+        table.add_with_metadata(cost, gas, metadata);
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, table.to_json().to_string())
+            .with_context(|| format!("failed to write checkpoint: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to write checkpoint: {}", self.path.display()))?;
+        Ok(())
+    }
+
+    /// The resumed costs along with the ones recorded since.
+    pub(crate) fn into_table(self) -> CostTable {
+        self.table.into_inner().unwrap()
+    }
+}
+
+#[test]
+fn test_checkpoint_resume() {
+    use crate::config::GasMetric;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("checkpoint.json");
+    let metadata = CostMetadata {
+        metric: GasMetric::ICount,
+        warmup_iters: 0,
+        iters: 2,
+        computed_in: Duration::from_secs(30),
+        uncertain: false,
+        reduced_iters: true,
+        min_gas: None,
+        max_gas: None,
+        stddev_gas: None,
+        peak_memory_bytes: None,
+    };
+
+    let checkpoint = Checkpoint::open(&path).unwrap();
+    assert!(!checkpoint.contains(Cost::ActionTransfer));
+    checkpoint.record(Cost::ActionTransfer, 115_123_062_500, metadata.clone()).unwrap();
+    let saved = checkpoint.into_table();
+
+    let checkpoint = Checkpoint::open(&path).unwrap();
+    assert!(checkpoint.contains(Cost::ActionTransfer));
+    assert!(!checkpoint.contains(Cost::ActionStake));
+    checkpoint.record(Cost::ActionStake, 141_715_687_500, metadata).unwrap();
+    let resumed = checkpoint.into_table();
+    assert_eq!(saved.get(Cost::ActionTransfer), resumed.get(Cost::ActionTransfer));
+    assert_eq!(Some(141_715_687_500), resumed.get(Cost::ActionStake));
+    assert_eq!(resumed.to_json(), Checkpoint::open(&path).unwrap().into_table().to_json());
+}
@@ -1,5 +1,6 @@
 use near_vm_runner::internal::VMKind;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use crate::rocksdb::RocksDBTestConfig;
 
@@ -38,4 +39,22 @@ pub struct Config {
     pub json_output: bool,
     /// Clear all OS caches between measured blocks.
     pub drop_os_cache: bool,
+    /// Standard deviation of the measurements, relative to their average,
+    /// above which a cost is flagged as uncertain.
+    pub max_relative_stddev: f64,
+    /// Number of the costs estimated concurrently. More than one makes sense
+    /// only with the `ICount` metric, which isn't affected by the contention.
+    pub jobs: usize,
+    /// When non-none, the estimated costs are saved to this file, and the
+    /// costs already in it are not estimated again.
+    pub checkpoint: Option<PathBuf>,
+    /// When non-none, costs which would take longer to estimate are estimated
+    /// with fewer iterations and marked as such.
+    pub max_time_per_cost: Option<Duration>,
+    /// When non-none, a sampling profile of every estimation is saved to this
+    /// directory.
+    pub profile_dir: Option<PathBuf>,
+    /// Clear the compiled contract cache before every measured block, so that
+    /// the first call of every contract in the block compiles it.
+    pub cold_contract_cache: bool,
 }
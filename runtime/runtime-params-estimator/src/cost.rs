@@ -79,6 +79,23 @@ pub enum Cost {
     /// a transaction. Subtract base costs and apply least-squares on the
     /// results to find the per-byte costs.
     ActionDeployContractPerByte,
+    /// Cost of deploying a contract per function it defines. Not a parameter
+    /// of the protocol, which charges deployments per byte, but it shows how
+    /// the cost of compiling the contract depends on its shape rather than on
+    /// its size.
+    ///
+    /// Estimation: Deploy generated contracts which vary the number of
+    /// functions, the number of imported host functions and the size of the
+    /// data segment, each on its own and then all together. Fit the costs,
+    /// after subtracting the receipt creation cost, with a linear model of the
+    /// three variables using least squares.
+    ActionDeployContractPerFunction,
+    /// Cost of deploying a contract per imported host function, see
+    /// `ActionDeployContractPerFunction`.
+    ActionDeployContractPerImport,
+    /// Cost of deploying a contract per byte of its data segments, see
+    /// `ActionDeployContractPerFunction`.
+    ActionDeployContractPerDataByte,
     /// Estimates `action_creation_config.function_call_cost`, which is the base
     /// cost for adding a `FunctionCallAction` to a receipt. It aims to account
     /// for all costs of calling a function that are already known on the caller
@@ -154,6 +171,17 @@ pub enum Cost {
     /// Subtract the base cost of creating a sir-receipt.
     /// (TODO[jakmeier] Consider different account states.
     ActionDeleteAccount,
+    /// Cost of deleting an account per contract data record it still has. Not
+    /// a parameter of the protocol: `delete_account_cost` has to cover the
+    /// deletion of an account with up to `MAX_ACCOUNT_DELETION_DATA_RECORDS`
+    /// records, so it should be at least `ActionDeleteAccount` plus that many
+    /// times this cost.
+    ///
+    /// Estimation: Measure transactions that delete accounts with no records,
+    /// with half the maximum number of records and with the maximum number of
+    /// records, written by the test contract in setup blocks. Fit the costs with
+    /// least squares to find the per-record cost.
+    ActionDeleteAccountPerDataRecord,
 
     /// Estimates `wasm_config.ext_costs.base` which is intended to be charged
     /// once on every host function call. However, this is currently
@@ -535,6 +563,27 @@ pub enum Cost {
     AltBn128G1SumBase,
     AltBn128G1SumElement,
 
+    /// Estimates `receipt_random_seed_base`, the cost charged once per call to
+    /// the `receipt_random_seed` host function, which hashes the random seed
+    /// of the receipt with a salt.
+    ///
+    /// Estimation: Like `Sha256Base`, execute a transaction with a single
+    /// function that calls `receipt_random_seed` with a 10 bytes salt 10'000
+    /// times. Divide the cost by 10'000.
+    ///
+    /// Note: Only estimated with the `nightly` feature, the host function is
+    /// not available on stable yet.
+    ReceiptRandomSeedBase,
+    /// Estimates `receipt_random_seed_byte`, the cost charged per byte of the
+    /// salt in calls to the `receipt_random_seed` host function.
+    ///
+    /// Estimation: Like `Sha256Byte`, execute a transaction with a single
+    /// function that calls `receipt_random_seed` with a 10kiB salt 10'000
+    /// times. Divide the cost by the total number of bytes of salt.
+    ///
+    /// Note: Only estimated with the `nightly` feature.
+    ReceiptRandomSeedByte,
+
     // Costs used only in estimator
     //
     /// Costs associated with applying an empty block. This overhead is not
@@ -618,6 +667,38 @@ pub enum Cost {
     /// Using the extra flags prefixed with `rdb-`, this can be used to measure
     /// the impact of various RocksDB settings on read performance.
     RocksDbReadValueByte,
+    /// Cost of writing a trie node of the largest possible size to the RocksDB
+    /// store of nearcore and flushing it to disk. Unlike
+    /// `TouchingTrieNodeWrite`, this covers the disk I/O of the write, which
+    /// is otherwise amortized over many blocks.
+    ///
+    /// Estimation: Write 1000 encoded extension nodes with 2kiB keys into the
+    /// state column of the testbed store in a single store update, then flush
+    /// the memtable to disk. Divide the cost by the number of nodes. The write
+    /// amplification, i.e. the bytes written to disk per byte of the nodes and
+    /// their keys, is reported with the cost.
+    RocksDbWriteTrieNode,
+    /// Cost of reading a trie node of the largest possible size from the
+    /// RocksDB store of nearcore when it is in neither the trie cache, nor the
+    /// RocksDB block cache, nor the OS page cache.
+    ///
+    /// Estimation: Read the nodes written by `RocksDbWriteTrieNode` by their
+    /// hashes through a `TrieCachingStorage` with an empty cache, after the
+    /// OS page cache has been dropped. Divide the cost by the number of nodes.
+    /// The read amplification is reported with the cost.
+    ///
+    /// Note: With `--metric time`, the nodes are likely read from the OS page
+    /// cache unless `--drop-os-cache` is passed, so the cost is flagged as
+    /// uncertain without it.
+    RocksDbReadTrieNodeCold,
+    /// Cost of reading a trie node of the largest possible size from the
+    /// RocksDB store of nearcore when it isn't in the trie cache but has been
+    /// read from the store before, so that it is in the RocksDB block cache or
+    /// at least in the OS page cache.
+    ///
+    /// Estimation: Like `RocksDbReadTrieNodeCold`, but read the nodes a second
+    /// time, through a new `TrieCachingStorage`.
+    RocksDbReadTrieNodeWarm,
     IoReadByte,
     IoWriteByte,
     CpuBenchmarkSha256,
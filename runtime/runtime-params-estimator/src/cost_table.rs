@@ -1,10 +1,14 @@
 use std::collections::BTreeMap;
 use std::fmt;
+use std::fmt::Write;
 use std::str::FromStr;
+use std::time::Duration;
 
 use near_primitives::types::Gas;
 use num_rational::Ratio;
+use serde_json::json;
 
+use crate::config::GasMetric;
 use crate::cost::Cost;
 
 /// For each [`Cost`], the price of a single unit in [`Gas`].
@@ -13,6 +17,37 @@ use crate::cost::Cost;
 #[derive(Default)]
 pub struct CostTable {
     map: BTreeMap<Cost, Gas>,
+    /// How the costs have been estimated. Missing for the costs parsed from
+    /// a `costs.txt` file.
+    metadata: BTreeMap<Cost, CostMetadata>,
+}
+
+/// Details of the estimation of a single [`Cost`].
+#[derive(Debug, Clone)]
+pub struct CostMetadata {
+    pub metric: GasMetric,
+    pub warmup_iters: usize,
+    pub iters: usize,
+    /// Wall-clock time the estimation took.
+    pub computed_in: Duration,
+    pub uncertain: bool,
+    /// Estimated with fewer (warm up) iterations than configured, to fit into
+    /// the time budget of a single cost. Such estimates are less reliable.
+    pub reduced_iters: bool,
+    /// Smallest, largest and standard deviation of the measurements the cost
+    /// has been averaged over, if known.
+    pub min_gas: Option<Gas>,
+    pub max_gas: Option<Gas>,
+    pub stddev_gas: Option<Gas>,
+    /// Peak growth of the heap per unit of the cost, in bytes, if measured
+    /// with the `memory_stats` feature.
+    pub peak_memory_bytes: Option<u64>,
+}
+
+/// The same costs estimated with different settings, labeled by the setting,
+/// see [`crate::run_vm_kinds`] and [`crate::run_contract_caches`].
+pub struct CostComparison {
+    tables: Vec<(String, CostTable)>,
 }
 
 #[derive(Default)]
@@ -25,6 +60,10 @@ impl CostTable {
         let prev = self.map.insert(cost, value);
         assert!(prev.is_none())
     }
+    pub(crate) fn add_with_metadata(&mut self, cost: Cost, value: Gas, metadata: CostMetadata) {
+        self.add(cost, value);
+        self.metadata.insert(cost, metadata);
+    }
     pub(crate) fn get(&self, cost: Cost) -> Option<Gas> {
         self.map.get(&cost).copied()
     }
@@ -37,6 +76,154 @@ impl CostTable {
         }
         res
     }
+
+    /// JSON array with one object per cost, in the order of [`Cost::all`].
+    /// Intended to be consumed by scripts, which should expect more fields to
+    /// be added.
+    pub fn to_json(&self) -> serde_json::Value {
+        let costs = Cost::all()
+            .filter_map(|cost| {
+                let gas = self.get(cost)?;
+                let metadata = self.metadata.get(&cost);
+                Some(json!({
+                    "name": cost.to_string(),
+                    "gas": gas,
+                    // `None` will be printed as `null`
+                    "metric": metadata.map(|m| metric_name(m.metric)),
+                    "warmup_iters": metadata.map(|m| m.warmup_iters),
+                    "iters": metadata.map(|m| m.iters),
+                    "computed_in_secs": metadata.map(|m| m.computed_in.as_secs_f64()),
+                    "uncertain": metadata.map(|m| m.uncertain),
+                    "reduced_iters": metadata.map(|m| m.reduced_iters),
+                    "min_gas": metadata.and_then(|m| m.min_gas),
+                    "max_gas": metadata.and_then(|m| m.max_gas),
+                    "stddev_gas": metadata.and_then(|m| m.stddev_gas),
+                    "peak_memory_bytes": metadata.and_then(|m| m.peak_memory_bytes),
+                }))
+            })
+            .collect();
+        serde_json::Value::Array(costs)
+    }
+
+    /// Parses the output of [`CostTable::to_json`], along with the metadata
+    /// of the costs which have it.
+    pub fn from_json(s: &str) -> Result<CostTable, ()> {
+        let costs: Vec<serde_json::Value> = serde_json::from_str(s).map_err(drop)?;
+        let mut res = CostTable::default();
+        for cost in costs {
+            let name = cost["name"].as_str().ok_or(())?;
+            let gas = cost["gas"].as_u64().ok_or(())?;
+            match metadata_from_json(&cost) {
+                Some(metadata) => res.add_with_metadata(name.parse()?, gas, metadata),
+                None => res.add(name.parse()?, gas),
+            }
+        }
+        Ok(res)
+    }
+
+    /// CSV with a header row and one row per cost, with the same columns as
+    /// [`CostTable::to_json`]. Unknown metadata is left empty.
+    pub fn to_csv(&self) -> String {
+        let mut res = "name,gas,metric,warmup_iters,iters,computed_in_secs,uncertain,\
+                       reduced_iters,min_gas,max_gas,stddev_gas,peak_memory_bytes\n"
+            .to_string();
+        for cost in Cost::all() {
+            let gas = match self.get(cost) {
+                Some(gas) => gas,
+                None => continue,
+            };
+            write!(res, "{},{}", cost, gas).unwrap();
+            match self.metadata.get(&cost) {
+                Some(m) => writeln!(
+                    res,
+                    ",{},{},{},{:.3},{},{},{},{},{},{}",
+                    metric_name(m.metric),
+                    m.warmup_iters,
+                    m.iters,
+                    m.computed_in.as_secs_f64(),
+                    m.uncertain,
+                    m.reduced_iters,
+                    csv_field(m.min_gas),
+                    csv_field(m.max_gas),
+                    csv_field(m.stddev_gas),
+                    csv_field(m.peak_memory_bytes),
+                )
+                .unwrap(),
+                None => writeln!(res, ",,,,,,,,,,").unwrap(),
+            }
+        }
+        res
+    }
+
+    /// The peak memory of every cost for which it has been measured, next to
+    /// its gas, as a table in the order of [`Cost::all`]. The memory per Tgas
+    /// compares costs of different sizes, so that the costs whose memory is
+    /// out of line with their gas stand out. Returns None if no cost has its
+    /// memory measured.
+    pub fn memory_table(&self) -> Option<String> {
+        let mut table = format!(
+            "{:<35} {:>25} {:>20} {:>20}\n",
+            "Cost", "Gas", "Peak memory (B)", "Memory per Tgas (B)"
+        );
+        let mut measured = false;
+        for cost in Cost::all() {
+            let (gas, peak_memory) = match (self.get(cost), self.metadata.get(&cost)) {
+                (Some(gas), Some(CostMetadata { peak_memory_bytes: Some(bytes), .. })) => {
+                    (gas, *bytes)
+                }
+                _ => continue,
+            };
+            let per_tgas = if gas > 0 {
+                format!("{:.0}", peak_memory as f64 * 1e12 / gas as f64)
+            } else {
+                "-".to_string()
+            };
+            writeln!(
+                table,
+                "{:<35} {:>25} {:>20} {:>20}",
+                cost.to_string(),
+                format_gas(gas),
+                format_gas(peak_memory),
+                per_tgas,
+            )
+            .unwrap();
+            measured = true;
+        }
+        measured.then_some(table)
+    }
+}
+
+fn csv_field(value: Option<u64>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn metric_name(metric: GasMetric) -> &'static str {
+    match metric {
+        GasMetric::ICount => "icount",
+        GasMetric::Time => "time",
+    }
+}
+
+/// Metadata of a cost in the output of [`CostTable::to_json`], if present.
+fn metadata_from_json(cost: &serde_json::Value) -> Option<CostMetadata> {
+    let metric = match cost["metric"].as_str()? {
+        "icount" => GasMetric::ICount,
+        "time" => GasMetric::Time,
+        _ => return None,
+    };
+    Some(CostMetadata {
+        metric,
+        warmup_iters: cost["warmup_iters"].as_u64()? as usize,
+        iters: cost["iters"].as_u64()? as usize,
+        computed_in: Duration::from_secs_f64(cost["computed_in_secs"].as_f64()?),
+        uncertain: cost["uncertain"].as_bool()?,
+        // Missing in the tables saved before the field was added.
+        reduced_iters: cost["reduced_iters"].as_bool().unwrap_or(false),
+        min_gas: cost["min_gas"].as_u64(),
+        max_gas: cost["max_gas"].as_u64(),
+        stddev_gas: cost["stddev_gas"].as_u64(),
+        peak_memory_bytes: cost["peak_memory_bytes"].as_u64(),
+    })
 }
 
 impl FromStr for CostTable {
@@ -73,6 +260,75 @@ impl fmt::Display for CostTable {
     }
 }
 
+impl CostComparison {
+    pub(crate) fn new(tables: Vec<(String, CostTable)>) -> Self {
+        Self { tables }
+    }
+}
+
+/// One column per setting, and the ratio of the most to the least expensive
+/// estimate of every cost.
+impl fmt::Display for CostComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<35}", "Cost")?;
+        for (label, _) in &self.tables {
+            write!(f, " {:>25}", label)?;
+        }
+        writeln!(f, " {:>9}", "Max/Min")?;
+        for cost in Cost::all() {
+            let gas: Vec<Option<Gas>> =
+                self.tables.iter().map(|(_, table)| table.get(cost)).collect();
+            if gas.iter().all(Option::is_none) {
+                continue;
+            }
+            write!(f, "{:<35}", cost.to_string())?;
+            for gas in &gas {
+                let gas = gas.map_or("-".to_string(), format_gas);
+                write!(f, " {:>25}", gas)?;
+            }
+            let known = gas.iter().flatten();
+            match (known.clone().min(), known.max()) {
+                (Some(&min), Some(&max)) if min > 0 => {
+                    writeln!(f, " {:>8.2}x", max as f64 / min as f64)?
+                }
+                _ => writeln!(f, " {:>9}", "-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CostTableDiff {
+    /// Relative change of every cost present in both tables, in percent, e.g.
+    /// `10.0` if the cost is 10% more expensive in the second table, as a
+    /// table in the order of [`Cost::all`]. The costs which changed by more
+    /// than `max_change_percent` in either direction are flagged as
+    /// `EXCEEDED` and returned along with the table.
+    pub fn relative_changes(&self, max_change_percent: f64) -> (String, Vec<Cost>) {
+        let mut table =
+            format!("{:<35} {:>25} {:>25} {:>9}\n", "Cost", "First", "Second", "Change");
+        let mut exceeded = Vec::new();
+        for (&cost, &(first, second)) in &self.map {
+            let change = (second as f64 / first as f64 - 1.0) * 100.0;
+            let exceeds = change.abs() > max_change_percent;
+            writeln!(
+                table,
+                "{:<35} {:>25} {:>25} {:>+8.1}%{}",
+                cost.to_string(),
+                format_gas(first),
+                format_gas(second),
+                change,
+                if exceeds { " EXCEEDED" } else { "" },
+            )
+            .unwrap();
+            if exceeds {
+                exceeded.push(cost);
+            }
+        }
+        (table, exceeded)
+    }
+}
+
 impl fmt::Display for CostTableDiff {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "{:<35} {:>25} {:>25} {:>13}", "Cost", "First", "Second", "Second/First")?;
@@ -113,3 +369,143 @@ fn test_separate_thousands() {
     assert_eq!(format_gas(1000).as_str(), "1_000");
     assert_eq!(format_gas(u64::MAX).as_str(), "18_446_744_073_709_551_615");
 }
+
+#[test]
+fn test_cost_table_json_and_csv() {
+    let mut table: CostTable =
+        "ActionReceiptCreation 4_322_000\nActionSirReceiptCreation 1_000".parse().unwrap();
+    table.add_with_metadata(
+        Cost::ActionTransfer,
+        115_123_062_500,
+        CostMetadata {
+            metric: GasMetric::ICount,
+            warmup_iters: 1,
+            iters: 10,
+            computed_in: Duration::from_millis(1500),
+            uncertain: true,
+            reduced_iters: false,
+            min_gas: Some(100_000_000_000),
+            max_gas: Some(130_000_000_000),
+            stddev_gas: Some(9_000_000_000),
+            peak_memory_bytes: Some(2_048),
+        },
+    );
+    assert_eq!(
+        json!([
+            {
+                "name": "ActionReceiptCreation",
+                "gas": 4_322_000,
+                "metric": null,
+                "warmup_iters": null,
+                "iters": null,
+                "computed_in_secs": null,
+                "uncertain": null,
+                "reduced_iters": null,
+                "min_gas": null,
+                "max_gas": null,
+                "stddev_gas": null,
+                "peak_memory_bytes": null,
+            },
+            {
+                "name": "ActionSirReceiptCreation",
+                "gas": 1_000,
+                "metric": null,
+                "warmup_iters": null,
+                "iters": null,
+                "computed_in_secs": null,
+                "uncertain": null,
+                "reduced_iters": null,
+                "min_gas": null,
+                "max_gas": null,
+                "stddev_gas": null,
+                "peak_memory_bytes": null,
+            },
+            {
+                "name": "ActionTransfer",
+                "gas": 115_123_062_500u64,
+                "metric": "icount",
+                "warmup_iters": 1,
+                "iters": 10,
+                "computed_in_secs": 1.5,
+                "uncertain": true,
+                "reduced_iters": false,
+                "min_gas": 100_000_000_000u64,
+                "max_gas": 130_000_000_000u64,
+                "stddev_gas": 9_000_000_000u64,
+                "peak_memory_bytes": 2_048,
+            },
+        ]),
+        table.to_json()
+    );
+    let parsed = CostTable::from_json(&table.to_json().to_string()).unwrap();
+    assert_eq!(table.map, parsed.map);
+    assert_eq!(table.to_json(), parsed.to_json());
+    assert!(CostTable::from_json("[{\"name\": \"NoSuchCost\", \"gas\": 1}]").is_err());
+    assert_eq!(
+        "name,gas,metric,warmup_iters,iters,computed_in_secs,uncertain,reduced_iters,\
+         min_gas,max_gas,stddev_gas,peak_memory_bytes\n\
+         ActionReceiptCreation,4322000,,,,,,,,,,\n\
+         ActionSirReceiptCreation,1000,,,,,,,,,,\n\
+         ActionTransfer,115123062500,icount,1,10,1.500,true,false,100000000000,130000000000,\
+         9000000000,2048\n",
+        table.to_csv()
+    );
+    assert_eq!(
+        format!(
+            "{:<35} {:>25} {:>20} {:>20}\n{:<35} {:>25} {:>20} {:>20}\n",
+            "Cost",
+            "Gas",
+            "Peak memory (B)",
+            "Memory per Tgas (B)",
+            "ActionTransfer",
+            "115_123_062_500",
+            "2_048",
+            "17790"
+        ),
+        table.memory_table().unwrap()
+    );
+    assert_eq!(None, CostTable::default().memory_table());
+}
+
+#[test]
+fn test_cost_table_relative_changes() {
+    let baseline: CostTable =
+        "ActionReceiptCreation 1_000\nActionSirReceiptCreation 1_000\nActionTransfer 1_000"
+            .parse()
+            .unwrap();
+    let current: CostTable =
+        "ActionReceiptCreation 1_500\nActionTransfer 900\nActionCreateAccount 1_000"
+            .parse()
+            .unwrap();
+    let (table, exceeded) = baseline.diff(&current).relative_changes(10.0);
+    assert_eq!(vec![Cost::ActionReceiptCreation], exceeded);
+    assert_eq!(
+        vec![
+            "ActionReceiptCreation                                   1_000                     1_500    +50.0% EXCEEDED",
+            "ActionTransfer                                          1_000                       900    -10.0%",
+        ],
+        table.lines().skip(1).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_cost_comparison() {
+    let wasmer0: CostTable = "WasmInstruction 1_000\nContractLoadingBase 2_000".parse().unwrap();
+    let wasmer2: CostTable = "WasmInstruction 500".parse().unwrap();
+    let comparison = CostComparison::new(vec![
+        ("Wasmer0".to_string(), wasmer0),
+        ("Wasmer2".to_string(), wasmer2),
+    ]);
+    assert_eq!(
+        vec![
+            vec!["Cost", "Wasmer0", "Wasmer2", "Max/Min"],
+            vec!["WasmInstruction", "1_000", "500", "2.00x"],
+            vec!["ContractLoadingBase", "2_000", "-", "1.00x"],
+        ],
+        comparison
+            .to_string()
+            .lines()
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    );
+}
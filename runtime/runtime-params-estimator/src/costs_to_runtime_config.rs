@@ -154,6 +154,9 @@ fn ext_costs_config(cost_table: &CostTable) -> anyhow::Result<ExtCostsConfig> {
         alt_bn128_g1_multiexp_element: get(Cost::AltBn128G1MultiexpElement)?,
         alt_bn128_pairing_check_base: get(Cost::AltBn128PairingCheckBase)?,
         alt_bn128_pairing_check_element: get(Cost::AltBn128PairingCheckElement)?,
+        // Only estimated with the `nightly` feature.
+        receipt_random_seed_base: cost_table.get(Cost::ReceiptRandomSeedBase).unwrap_or(0),
+        receipt_random_seed_byte: cost_table.get(Cost::ReceiptRandomSeedByte).unwrap_or(0),
     };
 
     Ok(res)
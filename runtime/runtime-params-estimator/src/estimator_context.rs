@@ -2,12 +2,13 @@ use near_primitives::shard_layout::ShardUId;
 use std::collections::HashMap;
 
 use near_primitives::transaction::SignedTransaction;
-use near_store::{TrieCache, TrieCachingStorage};
+use near_store::{Store, TrieCache, TrieCachingStorage};
 use near_vm_logic::ExtCosts;
 
 use crate::config::{Config, GasMetric};
 use crate::gas_cost::GasCost;
 use crate::testbed::RuntimeTestbed;
+use crate::trie::RocksDbTrieNodeCosts;
 use genesis_populate::get_account_id;
 
 use super::transaction_builder::TransactionBuilder;
@@ -24,6 +25,7 @@ pub(crate) struct CachedCosts {
     pub(crate) action_sir_receipt_creation: Option<GasCost>,
     pub(crate) action_add_function_access_key_base: Option<GasCost>,
     pub(crate) deploy_contract_base: Option<GasCost>,
+    pub(crate) deploy_contract_shape: Option<DeployContractShapeCosts>,
     pub(crate) noop_function_call_cost: Option<GasCost>,
     pub(crate) storage_read_base: Option<GasCost>,
     pub(crate) contract_loading_base_per_byte: Option<(GasCost, GasCost)>,
@@ -33,6 +35,17 @@ pub(crate) struct CachedCosts {
     pub(crate) apply_block: Option<GasCost>,
     pub(crate) touching_trie_node_read: Option<GasCost>,
     pub(crate) touching_trie_node_write: Option<GasCost>,
+    pub(crate) rocks_db_trie_node: Option<RocksDbTrieNodeCosts>,
+}
+
+/// Costs of deploying a contract per unit of its shape, estimated together for
+/// `Cost::ActionDeployContractPerFunction`, `Cost::ActionDeployContractPerImport`
+/// and `Cost::ActionDeployContractPerDataByte`.
+#[derive(Clone)]
+pub(crate) struct DeployContractShapeCosts {
+    pub per_function: GasCost,
+    pub per_import: GasCost,
+    pub per_data_byte: GasCost,
 }
 
 impl<'c> EstimatorContext<'c> {
@@ -82,34 +95,58 @@ impl<'c> Testbed<'c> {
         blocks: Vec<Vec<SignedTransaction>>,
         block_latency: usize,
     ) -> Vec<(GasCost, HashMap<ExtCosts, u64>)> {
-        let allow_failures = false;
-
         let mut res = Vec::with_capacity(blocks.len());
 
         for block in blocks {
-            node_runtime::with_ext_cost_counter(|cc| cc.clear());
-            let extra_blocks;
-            let gas_cost = {
-                self.clear_caches();
-                let start = GasCost::measure(self.config.metric);
-                self.inner.process_block(&block, allow_failures);
-                extra_blocks = self.inner.process_blocks_until_no_receipts(allow_failures);
-                start.elapsed()
-            };
-            assert_eq!(block_latency, extra_blocks);
-
-            let mut ext_costs: HashMap<ExtCosts, u64> = HashMap::new();
-            node_runtime::with_ext_cost_counter(|cc| {
-                for (c, v) in cc.drain() {
-                    ext_costs.insert(c, v);
-                }
-            });
-            res.push((gas_cost, ext_costs));
+            if self.config.cold_contract_cache {
+                self.inner.clear_compiled_contract_cache();
+            }
+            res.push(self.measure_block(&block, block_latency));
         }
 
         res
     }
 
+    /// Applies and measures a block like `measure_blocks`, but keeps the
+    /// contracts compiled by the previous blocks even with
+    /// `Config::cold_contract_cache`.
+    #[track_caller]
+    pub(crate) fn measure_block_with_warm_contract_cache(
+        &mut self,
+        block: Vec<SignedTransaction>,
+        block_latency: usize,
+    ) -> (GasCost, HashMap<ExtCosts, u64>) {
+        self.measure_block(&block, block_latency)
+    }
+
+    #[track_caller]
+    fn measure_block(
+        &mut self,
+        block: &[SignedTransaction],
+        block_latency: usize,
+    ) -> (GasCost, HashMap<ExtCosts, u64>) {
+        let allow_failures = false;
+
+        node_runtime::with_ext_cost_counter(|cc| cc.clear());
+        let extra_blocks;
+        let gas_cost = {
+            self.clear_caches();
+            let start = GasCost::measure(self.config.metric);
+            self.inner.process_block(block, allow_failures);
+            extra_blocks = self.inner.process_blocks_until_no_receipts(allow_failures);
+            start.elapsed()
+        };
+        assert_eq!(block_latency, extra_blocks);
+
+        let mut ext_costs: HashMap<ExtCosts, u64> = HashMap::new();
+        node_runtime::with_ext_cost_counter(|cc| {
+            for (c, v) in cc.drain() {
+                ext_costs.insert(c, v);
+            }
+        });
+        (gas_cost, ext_costs)
+    }
+
     pub(crate) fn process_block<'a>(
         &'a mut self,
         block: Vec<SignedTransaction>,
@@ -128,6 +165,10 @@ impl<'c> Testbed<'c> {
         caching_storage
     }
 
+    pub(crate) fn store(&mut self) -> Store {
+        self.inner.store()
+    }
+
     pub(crate) fn clear_caches(&mut self) {
         // Flush out writes hanging in memtable
         self.inner.flush_db_write_buffer();
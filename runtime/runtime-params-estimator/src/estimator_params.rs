@@ -4,6 +4,7 @@
 
 use near_primitives::{hash::CryptoHash, types::Gas};
 use num_rational::Ratio;
+use std::sync::atomic::{AtomicI64, Ordering};
 
 use crate::{config::GasMetric, gas_cost::GasCost};
 
@@ -41,15 +42,12 @@ fn exec_sha256(repeats: u64) -> i64 {
     result
 }
 
-#[used]
-static mut SINK: i64 = 0;
+static SINK: AtomicI64 = AtomicI64::new(0);
 
 fn measure_operation<F: FnOnce(u64) -> i64>(count: u64, metric: GasMetric, op: F) -> GasCost {
     let start = GasCost::measure(metric);
     let value = op(count);
     let result = start.elapsed();
-    unsafe {
-        SINK = value;
-    }
+    SINK.store(value, Ordering::Relaxed);
     result
 }
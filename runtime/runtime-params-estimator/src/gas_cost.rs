@@ -10,7 +10,7 @@ use serde_json::json;
 
 use crate::config::GasMetric;
 use crate::estimator_params::{GAS_IN_INSTR, GAS_IN_NS, IO_READ_BYTE_COST, IO_WRITE_BYTE_COST};
-use crate::least_squares::least_squares_method;
+use crate::least_squares::{least_squares_method, multi_least_squares_method};
 use crate::qemu::QemuMeasurement;
 
 /// Result of cost estimation.
@@ -37,11 +37,87 @@ pub(crate) struct GasCost {
     /// the output. `uncertain_message` can be called to display the reason and
     /// code location of where the uncertainty has been set.
     uncertain: Option<MeasurementUncertainty>,
+    /// Spread of the measurements the cost has been averaged over, if any.
+    spread: Option<GasSpread>,
+    /// I/O amplification of the measured storage operations, if measured.
+    amplification: Option<IoAmplification>,
+    /// Blocks rejected as outliers out of those measured, if any.
+    rejected_blocks: Option<RejectedBlocks>,
+    /// Peak growth of the heap of the estimating thread over the measurement,
+    /// in bytes per measured unit, if built with the `memory_stats` feature.
+    /// Like the gas, it is divided by the number of units in a measurement,
+    /// so it is the memory retained per unit, such as the state changes of
+    /// the transactions accumulated until their block is committed.
+    peak_memory: Option<Ratio<u64>>,
+}
+
+/// Minimum, maximum and standard deviation of a set of measurements, in gas
+/// per measured unit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct GasSpread {
+    pub min: Gas,
+    pub max: Gas,
+    pub stddev: Gas,
+}
+
+impl GasSpread {
+    pub(crate) fn from_samples(samples: &[Gas]) -> Option<GasSpread> {
+        let min = *samples.iter().min()?;
+        let max = *samples.iter().max()?;
+        let n = samples.len() as f64;
+        let mean = samples.iter().map(|&x| x as f64).sum::<f64>() / n;
+        let variance = samples.iter().map(|&x| (x as f64 - mean).powi(2)).sum::<f64>() / n;
+        Some(GasSpread { min, max, stddev: variance.sqrt().round() as Gas })
+    }
+}
+
+/// Bytes read from and written to the disk per byte of data read or written
+/// by the measured storage operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct IoAmplification {
+    pub read: Ratio<u64>,
+    pub write: Ratio<u64>,
+}
+
+impl IoAmplification {
+    pub(crate) fn new(disk_read: u64, disk_written: u64, data_bytes: u64) -> IoAmplification {
+        IoAmplification {
+            read: Ratio::new(disk_read, data_bytes),
+            write: Ratio::new(disk_written, data_bytes),
+        }
+    }
+}
+
+impl fmt::Display for IoAmplification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read x{:.2} write x{:.2}",
+            self.read.to_f64().unwrap(),
+            self.write.to_f64().unwrap()
+        )
+    }
+}
+
+/// Number of blocks rejected as outliers out of the measured blocks the cost
+/// has been averaged over.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RejectedBlocks {
+    pub rejected: usize,
+    pub measured: usize,
+}
+
+impl fmt::Display for RejectedBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OUTLIERS({}/{})", self.rejected, self.measured)
+    }
 }
 
 pub(crate) struct GasClock {
     start: Instant,
     metric: GasMetric,
+    /// Heap usage of the thread when the clock was started.
+    heap_start: Option<usize>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -59,6 +135,10 @@ impl GasCost {
             io_r_bytes: 0.into(),
             io_w_bytes: 0.into(),
             uncertain: None,
+            spread: None,
+            amplification: None,
+            rejected_blocks: None,
+            peak_memory: None,
         }
     }
 
@@ -67,7 +147,8 @@ impl GasCost {
         if let GasMetric::ICount = metric {
             QemuMeasurement::start_count_instructions();
         };
-        GasClock { start, metric }
+        let heap_start = heap::start();
+        GasClock { start, metric, heap_start }
     }
 
     /// Creates `GasCost` out of raw numeric value of gas. This is required mostly for
@@ -92,6 +173,40 @@ impl GasCost {
     pub(crate) fn set_uncertain(&mut self, reason: &'static str) {
         self.uncertain = Some(MeasurementUncertainty { reason, location: Location::caller() });
     }
+    pub(crate) fn spread(&self) -> Option<GasSpread> {
+        self.spread
+    }
+    /// Records the spread of the measurements, in gas per measured unit, the
+    /// cost has been averaged over. Flags the cost as uncertain if their
+    /// standard deviation exceeds `max_relative_stddev` of the average.
+    #[track_caller]
+    pub(crate) fn set_spread(&mut self, samples: &[Gas], max_relative_stddev: f64) {
+        self.spread = GasSpread::from_samples(samples);
+        if self.relative_stddev().map_or(false, |stddev| stddev > max_relative_stddev) {
+            self.set_uncertain("HIGH-STDDEV");
+        }
+    }
+    pub(crate) fn amplification(&self) -> Option<IoAmplification> {
+        self.amplification
+    }
+    pub(crate) fn set_amplification(&mut self, amplification: IoAmplification) {
+        self.amplification = Some(amplification);
+    }
+    pub(crate) fn rejected_blocks(&self) -> Option<RejectedBlocks> {
+        self.rejected_blocks
+    }
+    pub(crate) fn set_rejected_blocks(&mut self, rejected_blocks: RejectedBlocks) {
+        self.rejected_blocks = Some(rejected_blocks);
+    }
+    /// Peak growth of the heap per measured unit, in bytes.
+    pub(crate) fn peak_memory(&self) -> Option<u64> {
+        self.peak_memory.map(|bytes| bytes.round().to_integer())
+    }
+    /// Standard deviation of the measurements relative to the cost.
+    pub(crate) fn relative_stddev(&self) -> Option<f64> {
+        let spread = self.spread?;
+        Some(spread.stddev as f64 / self.to_gas().max(1) as f64)
+    }
     /// Performs least squares using a separate variable for each component of the gas cost.
     ///
     /// Least-squares linear regression sometimes to produces negative
@@ -133,6 +248,42 @@ impl GasCost {
         }
     }
 
+    /// Least-squares fit of a cost which depends linearly on several variables,
+    /// every element of `xs` holding the variables of one sample. Returns the
+    /// base cost followed by the cost per unit of every variable.
+    ///
+    /// Unlike `least_squares_method_gas_cost`, the fit is done on the gas
+    /// values. Negative parameters are clamped to zero and marked as uncertain.
+    #[track_caller]
+    pub(crate) fn multi_least_squares_method_gas_cost(
+        xs: &[Vec<u64>],
+        ys: &[Self],
+        verbose: bool,
+    ) -> Vec<Self> {
+        let metric = ys[0].metric;
+        let uncertain = ys.iter().find_map(|cost| cost.uncertain);
+        let gas: Vec<Gas> = ys.iter().map(GasCost::to_gas).collect();
+        if verbose {
+            eprintln!("Multi-variable least squares input:");
+            for (x, y) in xs.iter().zip(&gas) {
+                eprintln!("{:?} => {}", x, y);
+            }
+        }
+        multi_least_squares_method(xs, &gas)
+            .expect("samples don't determine all the parameters")
+            .into_iter()
+            .map(|param| {
+                let mut cost =
+                    GasCost::from_gas(Ratio::from_integer(param.max(0.0).round() as u64), metric);
+                cost.uncertain = uncertain;
+                if param < 0.0 {
+                    cost.set_uncertain("NEG-LEAST-SQUARES");
+                }
+                cost
+            })
+            .collect()
+    }
+
     /// Subtracts two gas costs from each other without panicking on an arithmetic underflow.
     /// If the given tolerance is breached, the result will be marked as uncertain.
     #[track_caller]
@@ -143,6 +294,13 @@ impl GasCost {
         if !tolerance.tolerates(&pos, &neg) {
             pos.set_uncertain("SUBTRACTION-UNDERFLOW");
         }
+        let rhs_gas = rhs.to_gas();
+        pos.spread = self.spread.map(|spread| GasSpread {
+            min: spread.min.saturating_sub(rhs_gas),
+            max: spread.max.saturating_sub(rhs_gas),
+            stddev: spread.stddev,
+        });
+        pos.rejected_blocks = self.rejected_blocks;
         pos.combine_uncertain(self);
         pos.combine_uncertain(rhs);
         pos
@@ -155,8 +313,14 @@ impl GasCost {
             instructions: saturating_sub(self.instructions, rhs.instructions),
             io_r_bytes: saturating_sub(self.io_r_bytes, rhs.io_r_bytes),
             io_w_bytes: saturating_sub(self.io_w_bytes, rhs.io_w_bytes),
+            peak_memory: self
+                .peak_memory
+                .map(|bytes| rhs.peak_memory.map_or(bytes, |rhs| saturating_sub(bytes, rhs))),
             metric: self.metric,
             uncertain: None,
+            spread: None,
+            amplification: None,
+            rejected_blocks: None,
         }
     }
 
@@ -181,6 +345,13 @@ impl GasCost {
                 "io_w_bytes": self.io_w_bytes.to_f64(),
                 // `None` will be printed as `null`
                 "uncertain_reason": self.uncertain.map(|u| u.reason),
+                "min_gas": self.spread.map(|s| s.min),
+                "max_gas": self.spread.map(|s| s.max),
+                "stddev_gas": self.spread.map(|s| s.stddev),
+                "read_amplification": self.amplification.map(|a| a.read.to_f64()),
+                "write_amplification": self.amplification.map(|a| a.write.to_f64()),
+                "rejected_blocks": self.rejected_blocks.map(|r| r.rejected),
+                "peak_memory_bytes": self.peak_memory(),
             }),
             GasMetric::Time => json!({
                 "gas": self.to_gas(),
@@ -188,6 +359,13 @@ impl GasCost {
                 "time_ns": self.time_ns.to_f64(),
                 "uncertain": self.uncertain.is_some(),
                 "uncertain_reason": self.uncertain.map(|u| u.reason),
+                "min_gas": self.spread.map(|s| s.min),
+                "max_gas": self.spread.map(|s| s.max),
+                "stddev_gas": self.spread.map(|s| s.stddev),
+                "read_amplification": self.amplification.map(|a| a.read.to_f64()),
+                "write_amplification": self.amplification.map(|a| a.write.to_f64()),
+                "rejected_blocks": self.rejected_blocks.map(|r| r.rejected),
+                "peak_memory_bytes": self.peak_memory(),
             }),
         }
     }
@@ -328,6 +506,10 @@ fn least_squares_method_gas_cost_pos_neg(
         io_w_bytes: neg_w_base,
         metric,
         uncertain,
+        spread: None,
+        amplification: None,
+        rejected_blocks: None,
+        peak_memory: None,
     };
     let neg_factor = GasCost {
         time_ns: neg_t_factor / GAS_IN_NS,
@@ -336,6 +518,10 @@ fn least_squares_method_gas_cost_pos_neg(
         io_w_bytes: neg_w_factor,
         metric,
         uncertain,
+        spread: None,
+        amplification: None,
+        rejected_blocks: None,
+        peak_memory: None,
     };
     let pos_base = GasCost {
         time_ns: pos_t_base / GAS_IN_NS,
@@ -344,6 +530,10 @@ fn least_squares_method_gas_cost_pos_neg(
         io_w_bytes: pos_w_base,
         metric,
         uncertain,
+        spread: None,
+        amplification: None,
+        rejected_blocks: None,
+        peak_memory: None,
     };
     let pos_factor = GasCost {
         time_ns: pos_t_factor / GAS_IN_NS,
@@ -352,6 +542,10 @@ fn least_squares_method_gas_cost_pos_neg(
         io_w_bytes: pos_w_factor,
         metric,
         uncertain,
+        spread: None,
+        amplification: None,
+        rejected_blocks: None,
+        peak_memory: None,
     };
 
     if neg_base.to_gas() == 0 && neg_factor.to_gas() == 0 {
@@ -389,11 +583,44 @@ impl GasClock {
             result.io_r_bytes = qemu_measurement.io_r_bytes.into();
             result.io_w_bytes = qemu_measurement.io_w_bytes.into();
         };
+        result.peak_memory = self.heap_start.map(|start| (heap::peak_since(start) as u64).into());
 
         result
     }
 }
 
+/// Heap usage of the current thread, as tracked by the allocator proxy which
+/// the estimator installs as the global allocator with the `memory_stats`
+/// feature. Without it, the heap isn't measured.
+#[cfg(feature = "memory_stats")]
+mod heap {
+    use near_rust_allocator_proxy::{
+        current_thread_memory_usage, current_thread_peak_memory_usage, reset_memory_usage_max,
+    };
+
+    /// Resets the peak heap usage of the thread, and returns the current one.
+    pub(super) fn start() -> Option<usize> {
+        reset_memory_usage_max();
+        Some(current_thread_memory_usage())
+    }
+
+    /// Bytes the heap of the thread has grown by at most since `start`.
+    pub(super) fn peak_since(start: usize) -> usize {
+        current_thread_peak_memory_usage().saturating_sub(start)
+    }
+}
+
+#[cfg(not(feature = "memory_stats"))]
+mod heap {
+    pub(super) fn start() -> Option<usize> {
+        None
+    }
+
+    pub(super) fn peak_since(_start: usize) -> usize {
+        0
+    }
+}
+
 impl fmt::Debug for GasCost {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.metric {
@@ -431,6 +658,13 @@ impl ops::Add for GasCost {
             io_w_bytes: self.io_w_bytes + rhs.io_w_bytes,
             metric: self.metric,
             uncertain: self.uncertain,
+            spread: None,
+            amplification: None,
+            rejected_blocks: None,
+            peak_memory: match (self.peak_memory, rhs.peak_memory) {
+                (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+                (lhs, rhs) => lhs.or(rhs),
+            },
         }
     }
 }
@@ -459,6 +693,12 @@ impl ops::Mul<u64> for GasCost {
             instructions: self.instructions * rhs,
             io_r_bytes: self.io_r_bytes * rhs,
             io_w_bytes: self.io_w_bytes * rhs,
+            peak_memory: self.peak_memory.map(|bytes| bytes * rhs),
+            spread: self.spread.map(|spread| GasSpread {
+                min: spread.min * rhs,
+                max: spread.max * rhs,
+                stddev: spread.stddev * rhs,
+            }),
             ..self
         }
     }
@@ -473,6 +713,12 @@ impl ops::Div<u64> for GasCost {
             instructions: self.instructions / rhs,
             io_r_bytes: self.io_r_bytes / rhs,
             io_w_bytes: self.io_w_bytes / rhs,
+            peak_memory: self.peak_memory.map(|bytes| bytes / rhs),
+            spread: self.spread.map(|spread| GasSpread {
+                min: spread.min / rhs,
+                max: spread.max / rhs,
+                stddev: spread.stddev / rhs,
+            }),
             ..self
         }
     }
@@ -514,7 +760,10 @@ impl GasCost {
 
 #[cfg(test)]
 mod tests {
-    use super::{least_squares_method_gas_cost_pos_neg, GasCost, LeastSquaresTolerance};
+    use super::{
+        least_squares_method_gas_cost_pos_neg, GasCost, GasSpread, LeastSquaresTolerance,
+        NonNegativeTolerance,
+    };
     use crate::{
         config::GasMetric,
         estimator_params::{GAS_IN_INSTR, GAS_IN_NS, IO_READ_BYTE_COST, IO_WRITE_BYTE_COST},
@@ -784,4 +1033,58 @@ mod tests {
             true,
         );
     }
+
+    #[test]
+    fn gas_cost_spread() {
+        assert_eq!(GasSpread::from_samples(&[]), None);
+        assert_eq!(
+            GasSpread::from_samples(&[90, 100, 110, 100]),
+            Some(GasSpread { min: 90, max: 110, stddev: 7 })
+        );
+
+        let mut cost = GasCost::new_time_based(100) * 4;
+        let gas = cost.to_gas();
+        cost.set_spread(&[gas - 100, gas, gas + 100], 0.1);
+        assert!(!cost.is_uncertain());
+        cost.set_spread(&[gas / 2, gas, gas * 3 / 2], 0.1);
+        assert!(cost.is_uncertain());
+
+        // The spread follows the cost through the arithmetic.
+        let half = cost.clone() / 2;
+        assert_eq!(half.spread().unwrap().max, gas * 3 / 4);
+        let overhead = GasCost::new_time_based(100);
+        let diff = cost.saturating_sub(&overhead, &NonNegativeTolerance::Strict);
+        assert_eq!(diff.spread().unwrap().min, gas / 2 - overhead.to_gas());
+        assert_eq!(diff.spread().unwrap().stddev, cost.spread().unwrap().stddev);
+    }
+
+    #[test]
+    fn gas_cost_peak_memory() {
+        let with_memory = |time_ns: u64, bytes: u64| {
+            let mut cost = GasCost::new_time_based(time_ns);
+            cost.peak_memory = Some(bytes.into());
+            cost
+        };
+        assert_eq!(GasCost::new_time_based(100).peak_memory(), None);
+
+        // The peak memory follows the gas through the arithmetic, like the
+        // total cost of blocks minus an overhead divided by the block size.
+        let blocks = with_memory(1000, 3000) + with_memory(1000, 5000);
+        assert_eq!(blocks.peak_memory(), Some(8000));
+        let per_unit = blocks / 4;
+        assert_eq!(per_unit.peak_memory(), Some(2000));
+        let overhead = with_memory(100, 500);
+        let diff = per_unit.saturating_sub(&overhead, &NonNegativeTolerance::Strict);
+        assert_eq!(diff.peak_memory(), Some(1500));
+        assert_eq!((diff * 3).peak_memory(), Some(4500));
+        // Unmeasured costs don't change the measured ones.
+        let unmeasured = GasCost::new_time_based(100);
+        assert_eq!((with_memory(100, 500) + unmeasured.clone()).peak_memory(), Some(500));
+        assert_eq!(
+            with_memory(200, 500)
+                .saturating_sub(&unmeasured, &NonNegativeTolerance::Strict)
+                .peak_memory(),
+            Some(500)
+        );
+    }
 }
@@ -37,6 +37,48 @@ pub(crate) fn least_squares_method(
     (a, b, errs)
 }
 
+/// Least-squares fit of `y = c_0 + c_1 * x_1 + ... + c_k * x_k`, where every
+/// element of `xs` holds `x_1, ..., x_k` of one sample. Returns `c_0, ..., c_k`,
+/// or `None` if the samples don't determine them, e.g. if one of the variables
+/// is never varied.
+pub(crate) fn multi_least_squares_method(xs: &[Vec<u64>], ys: &[u64]) -> Option<Vec<f64>> {
+    let k = xs.first()?.len() + 1;
+    // The normal equations `A^T A c = A^T y`, where `A` has a column of ones
+    // for `c_0`, as an augmented matrix.
+    let mut m = vec![vec![0f64; k + 1]; k];
+    for (x, &y) in xs.iter().zip(ys) {
+        let row: Vec<f64> = std::iter::once(1.0).chain(x.iter().map(|&x| x as f64)).collect();
+        for i in 0..k {
+            for j in 0..k {
+                m[i][j] += row[i] * row[j];
+            }
+            m[i][k] += row[i] * y as f64;
+        }
+    }
+    let eps = 1e-12 * m.iter().flat_map(|row| &row[..k]).fold(0f64, |max, v| max.max(v.abs()));
+
+    // Gauss-Jordan elimination with partial pivoting.
+    for col in 0..k {
+        let pivot = (col..k)
+            .max_by(|&a, &b| m[a][col].abs().partial_cmp(&m[b][col].abs()).unwrap())
+            .unwrap();
+        if m[pivot][col].abs() <= eps {
+            return None;
+        }
+        m.swap(col, pivot);
+        let pivot_row = m[col].clone();
+        for (i, row) in m.iter_mut().enumerate() {
+            if i != col {
+                let factor = row[col] / pivot_row[col];
+                for (x, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+    Some(m.iter().enumerate().map(|(i, row)| row[k] / row[i]).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use num_traits::ToPrimitive;
@@ -78,6 +120,21 @@ mod tests {
         check_least_squares_method(&xs, &ys, (a, b, &error));
     }
 
+    #[test]
+    fn test_multi_least_squares_method() {
+        // y = 1000 + 2 * x1 + 300 * x2, varying one variable at a time.
+        let xs = vec![vec![0, 0], vec![10, 0], vec![20, 0], vec![0, 5], vec![0, 10], vec![20, 10]];
+        let ys: Vec<u64> = xs.iter().map(|x| 1000 + 2 * x[0] + 300 * x[1]).collect();
+        let c = multi_least_squares_method(&xs, &ys).unwrap();
+        for (actual, expected) in c.iter().zip([1000.0, 2.0, 300.0]) {
+            assert!((actual - expected).abs() < 1e-6, "{c:?}");
+        }
+
+        // The second variable is never varied.
+        let xs = vec![vec![0, 1], vec![10, 1], vec![20, 1]];
+        assert_eq!(None, multi_least_squares_method(&xs, &[1, 2, 3]));
+    }
+
     #[test]
     fn test_large_numbers() {
         let xs = [1, 1000000, 4000000];
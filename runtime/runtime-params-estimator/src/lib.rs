@@ -46,13 +46,16 @@
 //! digging deeper.
 //!
 
+mod checkpoint;
 mod cost;
 mod cost_table;
 mod costs_to_runtime_config;
 mod estimator_context;
 mod gas_cost;
+mod profile;
 mod qemu;
 mod rocksdb;
+mod runtime_config_patch;
 mod transaction_builder;
 
 pub(crate) mod estimator_params;
@@ -73,53 +76,83 @@ mod gas_metering;
 mod trie;
 
 use std::convert::TryFrom;
-use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use estimator_params::sha256_cost;
 use gas_cost::{LeastSquaresTolerance, NonNegativeTolerance};
 use gas_metering::gas_metering_cost;
 use near_crypto::{KeyType, SecretKey};
-use near_primitives::account::{AccessKey, AccessKeyPermission, FunctionCallPermission};
+use near_primitives::account::{AccessKey, AccessKeyPermission, Account, FunctionCallPermission};
 use near_primitives::contract::ContractCode;
 use near_primitives::runtime::fees::RuntimeFeesConfig;
 use near_primitives::transaction::{
     Action, AddKeyAction, CreateAccountAction, DeleteAccountAction, DeleteKeyAction,
     DeployContractAction, SignedTransaction, StakeAction, TransferAction,
 };
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, Gas};
 use near_primitives::version::PROTOCOL_VERSION;
 use near_vm_logic::mocks::mock_external::MockedExternal;
 use near_vm_logic::{ExtCosts, VMConfig};
+use near_vm_runner::internal::VMKind;
 use near_vm_runner::MockCompiledContractCache;
 use rand::Rng;
 use serde_json::json;
 use utils::{
     aggregate_per_block_measurements, average_cost, fn_cost, fn_cost_count, fn_cost_in_contract,
-    fn_cost_with_setup, generate_data_only_contract, generate_fn_name, noop_function_call_cost,
-    read_resource, transaction_cost, transaction_cost_ext,
+    fn_cost_with_setup, generate_data_only_contract, generate_fn_name, generate_shaped_contract,
+    noop_function_call_cost, overhead_per_measured_block, read_resource, transaction_cost,
+    transaction_cost_ext,
 };
 use vm_estimator::{compile_single_contract_cost, compute_compile_cost_vm};
 
+use crate::checkpoint::Checkpoint;
 use crate::config::Config;
 use crate::cost_table::format_gas;
-use crate::estimator_context::EstimatorContext;
+use crate::estimator_context::{DeployContractShapeCosts, EstimatorContext};
 use crate::gas_cost::GasCost;
+use crate::profile::Profiler;
 use crate::rocksdb::{rocks_db_inserts_cost, rocks_db_read_cost};
 use crate::transaction_builder::TransactionBuilder;
 use crate::vm_estimator::create_context;
 
 pub use crate::cost::Cost;
-pub use crate::cost_table::CostTable;
+pub use crate::cost_table::{CostComparison, CostMetadata, CostTable};
 pub use crate::costs_to_runtime_config::costs_to_runtime_config;
 pub use crate::qemu::QemuCommandBuilder;
 pub use crate::rocksdb::RocksDBTestConfig;
+pub use crate::runtime_config_patch::{ParameterChange, RuntimeConfigPatch};
+
+type EstimationFn = fn(&mut EstimatorContext) -> GasCost;
+
+/// Costs estimated by running contracts directly with the VM of
+/// `Config::vm_kind`. The other costs run contracts through the runtime, which
+/// uses the VM of the protocol version.
+static VM_KIND_COSTS: &[Cost] = &[
+    Cost::WasmInstruction,
+    Cost::ContractCompileBase,
+    Cost::ContractCompileBytes,
+    Cost::ContractCompileBaseV2,
+    Cost::ContractCompileBytesV2,
+    Cost::ContractLoadingBase,
+    Cost::ContractLoadingPerByte,
+    Cost::GasMeteringBase,
+    Cost::GasMeteringOp,
+];
+
+/// Costs of calling contracts compared by [`run_contract_caches`] by default.
+/// Only `ActionFunctionCallBase` includes the compilation with a cold cache,
+/// the others show that it doesn't leak into them.
+static CONTRACT_CACHE_COSTS: &[Cost] =
+    &[Cost::ActionFunctionCallBase, Cost::ActionFunctionCallPerByte, Cost::HostFunctionCall];
 
-static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
+static ALL_COSTS: &[(Cost, EstimationFn)] = &[
     (Cost::ActionReceiptCreation, action_receipt_creation),
     (Cost::ActionSirReceiptCreation, action_sir_receipt_creation),
     (Cost::ActionTransfer, action_transfer),
     (Cost::ActionCreateAccount, action_create_account),
     (Cost::ActionDeleteAccount, action_delete_account),
+    (Cost::ActionDeleteAccountPerDataRecord, action_delete_account_per_data_record),
     (Cost::ActionAddFullAccessKey, action_add_full_access_key),
     (Cost::ActionAddFunctionAccessKeyBase, action_add_function_access_key_base),
     (Cost::ActionAddFunctionAccessKeyPerByte, action_add_function_access_key_per_byte),
@@ -127,6 +160,9 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::ActionStake, action_stake),
     (Cost::ActionDeployContractBase, action_deploy_contract_base),
     (Cost::ActionDeployContractPerByte, action_deploy_contract_per_byte),
+    (Cost::ActionDeployContractPerFunction, action_deploy_contract_per_function),
+    (Cost::ActionDeployContractPerImport, action_deploy_contract_per_import),
+    (Cost::ActionDeployContractPerDataByte, action_deploy_contract_per_data_byte),
     (Cost::ActionFunctionCallBase, action_function_call_base),
     (Cost::ActionFunctionCallPerByte, action_function_call_per_byte),
     (Cost::HostFunctionCall, host_function_call),
@@ -162,6 +198,10 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::AltBn128G1SumElement, alt_bn128g1_sum_element),
     (Cost::AltBn128PairingCheckBase, alt_bn128_pairing_check_base),
     (Cost::AltBn128PairingCheckElement, alt_bn128_pairing_check_element),
+    #[cfg(feature = "nightly")]
+    (Cost::ReceiptRandomSeedBase, receipt_random_seed_base),
+    #[cfg(feature = "nightly")]
+    (Cost::ReceiptRandomSeedByte, receipt_random_seed_byte),
     (Cost::StorageHasKeyBase, storage_has_key_base),
     (Cost::StorageHasKeyByte, storage_has_key_byte),
     (Cost::StorageReadBase, storage_read_base),
@@ -191,6 +231,9 @@ static ALL_COSTS: &[(Cost, fn(&mut EstimatorContext) -> GasCost)] = &[
     (Cost::GasMeteringOp, gas_metering_op),
     (Cost::RocksDbInsertValueByte, rocks_db_insert_value_byte),
     (Cost::RocksDbReadValueByte, rocks_db_read_value_byte),
+    (Cost::RocksDbWriteTrieNode, rocks_db_write_trie_node),
+    (Cost::RocksDbReadTrieNodeCold, rocks_db_read_trie_node_cold),
+    (Cost::RocksDbReadTrieNodeWarm, rocks_db_read_trie_node_warm),
     (Cost::CpuBenchmarkSha256, cpu_benchmark_sha256),
     (Cost::OneCPUInstruction, one_cpu_instruction),
     (Cost::OneNanosecond, one_nanosecond),
@@ -209,48 +252,265 @@ static REAL_CONTRACTS_SAMPLE: [(&str, &str); 4] = [
 ];
 
 pub fn run(config: Config) -> CostTable {
-    let mut ctx = EstimatorContext::new(&config);
+    let checkpoint = config.checkpoint.as_deref().map(|path| {
+        Arc::new(Checkpoint::open(path).expect("failed to resume from the checkpoint"))
+    });
+    let costs: Vec<_> = ALL_COSTS
+        .iter()
+        .copied()
+        .filter(|(cost, _)| match &config.costs_to_measure {
+            None => true,
+            Some(costs) => costs.contains(&format!("{:?}", cost)),
+        })
+        .filter(|(cost, _)| {
+            let done = checkpoint.as_ref().map_or(false, |checkpoint| checkpoint.contains(*cost));
+            if done {
+                eprintln!("{:<40} already estimated in the checkpoint", cost.to_string());
+            }
+            !done
+        })
+        .collect();
+
+    let estimations = if config.jobs > 1 {
+        run_parallel(Arc::new(config.clone()), costs, checkpoint.clone())
+    } else {
+        let mut ctx = EstimatorContext::new(&config);
+        costs
+            .into_iter()
+            .map(|(cost, f)| estimate(&mut ctx, cost, f, checkpoint.as_deref()))
+            .collect()
+    };
+    eprintln!();
+
+    if let Some(checkpoint) = checkpoint {
+        // The costs resumed from the checkpoint along with the estimated ones.
+        return Arc::try_unwrap(checkpoint)
+            .ok()
+            .expect("threads holding the checkpoint have been joined")
+            .into_table();
+    }
     let mut res = CostTable::default();
+    for (cost, gas, metadata) in estimations {
+        res.add_with_metadata(cost, gas, metadata);
+    }
+    res
+}
 
-    for (cost, f) in ALL_COSTS.iter().copied() {
-        let skip = match &ctx.config.costs_to_measure {
-            None => false,
-            Some(costs) => !costs.contains(&format!("{:?}", cost)),
-        };
-        if skip {
+/// Estimates the costs which depend on `Config::vm_kind` with every VM kind
+/// enabled in this build, one after another, to compare them side by side.
+///
+/// Every VM kind gets its own subdirectory of `Config::profile_dir` and its own
+/// checkpoint, named after `Config::checkpoint` with the VM kind added, e.g.
+/// `checkpoint.Wasmer2.json`.
+pub fn run_vm_kinds(config: Config) -> CostComparison {
+    let costs: Vec<String> = VM_KIND_COSTS
+        .iter()
+        .map(|cost| format!("{:?}", cost))
+        .filter(|cost| config.costs_to_measure.as_ref().map_or(true, |costs| costs.contains(cost)))
+        .collect();
+    let mut tables = Vec::new();
+    for vm_kind in [VMKind::Wasmer0, VMKind::Wasmer2, VMKind::Wasmtime] {
+        if vm_kind.runtime(VMConfig::test()).is_none() {
+            eprintln!("{:?} is not enabled in this build, skipping it", vm_kind);
             continue;
         }
+        eprintln!("Estimating with {:?}", vm_kind);
+        let config = Config {
+            vm_kind,
+            costs_to_measure: Some(costs.clone()),
+            checkpoint: config
+                .checkpoint
+                .as_ref()
+                .map(|path| path.with_extension(format!("{:?}.json", vm_kind))),
+            profile_dir: config.profile_dir.as_ref().map(|dir| dir.join(format!("{:?}", vm_kind))),
+            ..config.clone()
+        };
+        tables.push((format!("{:?}", vm_kind), run(config)));
+    }
+    CostComparison::new(tables)
+}
 
-        let start = Instant::now();
-        let measurement = f(&mut ctx);
-        let time = start.elapsed();
-        let name = cost.to_string();
-        let uncertain = if measurement.is_uncertain() { "UNCERTAIN " } else { "" };
-        let gas = measurement.to_gas();
-        res.add(cost, gas);
-
-        eprintln!(
-            "{:<40} {:>25} gas [{:>25}] {:<10}(computed in {:.2?}) {}",
-            name,
-            format_gas(gas),
-            format!("{:?}", measurement),
-            uncertain,
-            time,
-            measurement.uncertain_message().unwrap_or_default(),
-        );
+/// Estimates the costs of calling contracts with a warm and with a cold
+/// compiled contract cache, see `Config::cold_contract_cache`, one after
+/// another, to compare them side by side. Estimates `Config::costs_to_measure`
+/// if set, and `CONTRACT_CACHE_COSTS` otherwise.
+///
+/// Like with [`run_vm_kinds`], both get their own checkpoint and subdirectory
+/// of `Config::profile_dir`, e.g. `checkpoint.cold.json`.
+pub fn run_contract_caches(config: Config) -> CostComparison {
+    let costs = config
+        .costs_to_measure
+        .clone()
+        .unwrap_or_else(|| CONTRACT_CACHE_COSTS.iter().map(|cost| format!("{:?}", cost)).collect());
+    let mut tables = Vec::new();
+    for (label, cold_contract_cache) in [("warm", false), ("cold", true)] {
+        eprintln!("Estimating with a {} contract cache", label);
+        let config = Config {
+            cold_contract_cache,
+            costs_to_measure: Some(costs.clone()),
+            checkpoint: config
+                .checkpoint
+                .as_ref()
+                .map(|path| path.with_extension(format!("{}.json", label))),
+            profile_dir: config.profile_dir.as_ref().map(|dir| dir.join(label)),
+            ..config.clone()
+        };
+        tables.push((label.to_string(), run(config)));
+    }
+    CostComparison::new(tables)
+}
+
+/// Estimates the costs on `config.jobs` threads, which take the next cost to
+/// estimate from a shared queue. Every thread has its own context, so the
+/// testbeds it creates load the state dump into their own databases, and the
+/// base costs cached by one thread are estimated again by the others if they
+/// need them.
+fn run_parallel(
+    config: Arc<Config>,
+    costs: Vec<(Cost, EstimationFn)>,
+    checkpoint: Option<Arc<Checkpoint>>,
+) -> Vec<(Cost, Gas, CostMetadata)> {
+    let queue = Arc::new(Mutex::new(costs.into_iter()));
+    let threads: Vec<_> = (0..config.jobs)
+        .map(|_| {
+            let config = Arc::clone(&config);
+            let queue = Arc::clone(&queue);
+            let checkpoint = checkpoint.clone();
+            std::thread::spawn(move || {
+                let mut ctx = EstimatorContext::new(&config);
+                let mut res = Vec::new();
+                loop {
+                    let next = queue.lock().unwrap().next();
+                    match next {
+                        Some((cost, f)) => {
+                            res.push(estimate(&mut ctx, cost, f, checkpoint.as_deref()))
+                        }
+                        None => return res,
+                    }
+                }
+            })
+        })
+        .collect();
+    threads.into_iter().flat_map(|thread| thread.join().expect("estimation panicked")).collect()
+}
 
-        if config.json_output {
-            let json = json! ({
-                "name": name,
-                "result": measurement.to_json(),
-                "computed_in": time,
-            });
-            println!("{json}");
-        }
+/// Estimates a single cost, prints the result and records it in the checkpoint.
+/// The whole estimation, including the setup, is profiled if requested.
+fn estimate(
+    ctx: &mut EstimatorContext,
+    cost: Cost,
+    f: EstimationFn,
+    checkpoint: Option<&Checkpoint>,
+) -> (Cost, Gas, CostMetadata) {
+    let profiler =
+        ctx.config.profile_dir.as_deref().map(|dir| {
+            Profiler::start(dir, cost).expect("failed to start profiling the estimation")
+        });
+    let start = Instant::now();
+    let (measurement, warmup_iters, iters) = match ctx.config.max_time_per_cost {
+        Some(budget) => estimate_within_budget(ctx, f, budget),
+        None => (f(ctx), ctx.config.warmup_iters_per_block, ctx.config.iter_per_block),
+    };
+    let time = start.elapsed();
+    let profile = profiler.map(|profiler| match profiler.finish() {
+        Ok(path) => format!("profile: {} ", path.display()),
+        Err(err) => format!("profiling failed: {:#} ", err),
+    });
+    let reduced_iters =
+        warmup_iters < ctx.config.warmup_iters_per_block || iters < ctx.config.iter_per_block;
+    let name = cost.to_string();
+    let uncertain = if measurement.is_uncertain() { "UNCERTAIN " } else { "" };
+    let reduced = if reduced_iters { format!("REDUCED({}) ", iters) } else { String::new() };
+    let spread = match measurement.relative_stddev() {
+        Some(stddev) => format!("±{:.1}%", stddev * 100.),
+        None => String::new(),
+    };
+    let amplification = match measurement.amplification() {
+        Some(amplification) => format!("{} ", amplification),
+        None => String::new(),
+    };
+    let rejected = match measurement.rejected_blocks() {
+        Some(rejected) => format!("{} ", rejected),
+        None => String::new(),
+    };
+
+    eprintln!(
+        "{:<40} {:>25} gas [{:>25}] {:>7} {}{:<10}{}{}(computed in {:.2?}) {}{}",
+        name,
+        format_gas(measurement.to_gas()),
+        format!("{:?}", measurement),
+        spread,
+        amplification,
+        uncertain,
+        reduced,
+        rejected,
+        time,
+        profile.unwrap_or_default(),
+        measurement.uncertain_message().unwrap_or_default(),
+    );
+
+    if ctx.config.json_output {
+        let json = json! ({
+            "name": name,
+            "result": measurement.to_json(),
+            "computed_in": time,
+            "reduced_iters": reduced_iters,
+        });
+        println!("{json}");
     }
-    eprintln!();
 
-    res
+    let metadata = CostMetadata {
+        metric: ctx.config.metric,
+        warmup_iters,
+        iters,
+        computed_in: time,
+        uncertain: measurement.is_uncertain(),
+        reduced_iters,
+        min_gas: measurement.spread().map(|s| s.min),
+        max_gas: measurement.spread().map(|s| s.max),
+        stddev_gas: measurement.spread().map(|s| s.stddev),
+        peak_memory_bytes: measurement.peak_memory(),
+    };
+    if let Some(checkpoint) = checkpoint {
+        checkpoint
+            .record(cost, measurement.to_gas(), metadata.clone())
+            .expect("failed to save the checkpoint");
+    }
+    (cost, measurement.to_gas(), metadata)
+}
+
+/// Estimates a cost with fewer iterations if estimating it with all the
+/// configured ones would take longer than `budget`, and returns the
+/// measurement with the number of warm up and measured iterations used.
+///
+/// The time of the full estimation is extrapolated from a probe with a single
+/// iteration and no warm up. The probe and the reduced estimation get their own
+/// contexts, so that the base costs they cache aren't reused by the other
+/// estimations.
+fn estimate_within_budget(
+    ctx: &mut EstimatorContext,
+    f: EstimationFn,
+    budget: Duration,
+) -> (GasCost, usize, usize) {
+    let config = ctx.config;
+    let probe_config = Config { warmup_iters_per_block: 0, iter_per_block: 1, ..config.clone() };
+    let start = Instant::now();
+    let probe = f(&mut EstimatorContext::new(&probe_config));
+    let probe_time = start.elapsed();
+
+    let all_iters = config.warmup_iters_per_block + config.iter_per_block;
+    if probe_time * all_iters as u32 <= budget {
+        return (f(ctx), config.warmup_iters_per_block, config.iter_per_block);
+    }
+    // The warm up goes first, then the measured iterations.
+    let iters =
+        ((budget.as_secs_f64() / probe_time.as_secs_f64()) as usize).min(config.iter_per_block);
+    if iters <= 1 {
+        return (probe, 0, 1);
+    }
+    let reduced_config =
+        Config { warmup_iters_per_block: 0, iter_per_block: iters, ..config.clone() };
+    (f(&mut EstimatorContext::new(&reduced_config)), 0, iters)
 }
 
 fn action_receipt_creation(ctx: &mut EstimatorContext) -> GasCost {
@@ -353,6 +613,72 @@ fn action_delete_account(ctx: &mut EstimatorContext) -> GasCost {
     total_cost.saturating_sub(&base_cost, &NonNegativeTolerance::PER_MILLE)
 }
 
+fn action_delete_account_per_data_record(ctx: &mut EstimatorContext) -> GasCost {
+    let max_records = Account::MAX_ACCOUNT_DELETION_DATA_RECORDS;
+    let xs = [0, max_records / 2, max_records];
+    let ys: Vec<GasCost> =
+        xs.iter().map(|&num_records| delete_account_with_data_cost(ctx, num_records)).collect();
+
+    let (_base, per_record) = GasCost::least_squares_method_gas_cost(
+        &xs,
+        &ys,
+        &LeastSquaresTolerance::default().factor_rel_nn_tolerance(0.001),
+        ctx.config.debug,
+    );
+    per_record
+}
+
+/// Cost of deleting an account with `num_records` contract data records.
+fn delete_account_with_data_cost(ctx: &mut EstimatorContext, num_records: u64) -> GasCost {
+    let block_latency = 1;
+    let block_size = 10;
+    let measurement_overhead = overhead_per_measured_block(ctx, block_latency);
+
+    let mut testbed = ctx.testbed();
+    let n_blocks = testbed.config.warmup_iters_per_block + testbed.config.iter_per_block;
+    let tb = testbed.transaction_builder();
+    let accounts: Vec<AccountId> =
+        (0..n_blocks * block_size).map(|_| tb.random_unused_account()).collect();
+    // The records of an account are written in a setup block of their own,
+    // which is not measured.
+    let mut blocks = vec![];
+    for account in &accounts {
+        let setup_block = (0..num_records)
+            .map(|i| tb.account_insert_key(account.clone(), &i.to_le_bytes(), &i.to_le_bytes()))
+            .collect::<Vec<_>>();
+        if !setup_block.is_empty() {
+            blocks.push(setup_block);
+        }
+    }
+    let n_setup_blocks = blocks.len();
+    for block_accounts in accounts.chunks(block_size) {
+        let block = block_accounts
+            .iter()
+            .map(|account| {
+                let beneficiary_id = tb.random_unused_account();
+                let actions = vec![Action::DeleteAccount(DeleteAccountAction { beneficiary_id })];
+                tb.transaction_from_actions(account.clone(), account.clone(), actions)
+            })
+            .collect();
+        blocks.push(block);
+    }
+
+    let measurements = testbed.measure_blocks(blocks, block_latency);
+    let measurements = measurements
+        .into_iter()
+        .skip(n_setup_blocks + testbed.config.warmup_iters_per_block)
+        .collect::<Vec<_>>();
+    let (total_cost, _ext) = aggregate_per_block_measurements(
+        testbed.config,
+        block_size,
+        measurements,
+        Some(measurement_overhead),
+    );
+    let base_cost = action_sir_receipt_creation(ctx);
+
+    total_cost.saturating_sub(&base_cost, &NonNegativeTolerance::PER_MILLE)
+}
+
 fn action_add_full_access_key(ctx: &mut EstimatorContext) -> GasCost {
     let total_cost = {
         let mut make_transaction = |tb: &mut TransactionBuilder| -> SignedTransaction {
@@ -552,6 +878,58 @@ fn action_deploy_contract_per_byte(ctx: &mut EstimatorContext) -> GasCost {
     per_byte
 }
 
+fn action_deploy_contract_per_function(ctx: &mut EstimatorContext) -> GasCost {
+    deploy_contract_shape_costs(ctx).per_function
+}
+fn action_deploy_contract_per_import(ctx: &mut EstimatorContext) -> GasCost {
+    deploy_contract_shape_costs(ctx).per_import
+}
+fn action_deploy_contract_per_data_byte(ctx: &mut EstimatorContext) -> GasCost {
+    deploy_contract_shape_costs(ctx).per_data_byte
+}
+fn deploy_contract_shape_costs(ctx: &mut EstimatorContext) -> DeployContractShapeCosts {
+    if let Some(costs) = ctx.cached.deploy_contract_shape.clone() {
+        return costs;
+    }
+
+    // (functions, imports, data bytes) of the deployed contracts. Every
+    // variable is varied on its own, starting from the smallest contract, so
+    // that the fit doesn't attribute the cost of one to another, and then all
+    // of them together to cover their interaction.
+    let shapes = [
+        [1, 0, 0],
+        [250, 0, 0],
+        [500, 0, 0],
+        [1000, 0, 0],
+        [1, 250, 0],
+        [1, 500, 0],
+        [1, 1000, 0],
+        [1, 0, 100_000],
+        [1, 0, 200_000],
+        [1, 0, 400_000],
+        [500, 500, 200_000],
+        [1000, 1000, 400_000],
+    ];
+    let pivot_fn = "shape_pivot";
+    let vm_config = VMConfig::test();
+    let mut xs = vec![];
+    let mut ys = vec![];
+    for [functions, imports, data_size] in shapes {
+        let code = generate_shaped_contract(functions, imports, data_size, pivot_fn, &vm_config);
+        ys.push(deploy_contract_cost(ctx, code, Some(pivot_fn.as_bytes())));
+        xs.push(vec![functions as u64, imports as u64, data_size as u64]);
+    }
+    let params = GasCost::multi_least_squares_method_gas_cost(&xs, &ys, ctx.config.debug);
+    let costs = DeployContractShapeCosts {
+        per_function: params[1].clone(),
+        per_import: params[2].clone(),
+        per_data_byte: params[3].clone(),
+    };
+
+    ctx.cached.deploy_contract_shape = Some(costs.clone());
+    costs
+}
+
 /// Cost for deploying a specific contract.
 ///
 /// This function will run however many iterations of the transaction as has
@@ -939,6 +1317,20 @@ fn alt_bn128_pairing_check_element(ctx: &mut EstimatorContext) -> GasCost {
     )
 }
 
+#[cfg(feature = "nightly")]
+fn receipt_random_seed_base(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(ctx, "receipt_random_seed_10b_10k", ExtCosts::receipt_random_seed_base, 10_000)
+}
+#[cfg(feature = "nightly")]
+fn receipt_random_seed_byte(ctx: &mut EstimatorContext) -> GasCost {
+    fn_cost(
+        ctx,
+        "receipt_random_seed_10kib_10k",
+        ExtCosts::receipt_random_seed_byte,
+        10 * 1024 * 10_000,
+    )
+}
+
 fn storage_has_key_base(ctx: &mut EstimatorContext) -> GasCost {
     fn_cost_with_setup(
         ctx,
@@ -1139,6 +1531,29 @@ fn rocks_db_read_value_byte(ctx: &mut EstimatorContext) -> GasCost {
     rocks_db_read_cost(&ctx.config) / total_bytes
 }
 
+fn rocks_db_write_trie_node(ctx: &mut EstimatorContext) -> GasCost {
+    rocks_db_trie_node(ctx).write
+}
+
+fn rocks_db_read_trie_node_cold(ctx: &mut EstimatorContext) -> GasCost {
+    rocks_db_trie_node(ctx).cold_read
+}
+
+fn rocks_db_read_trie_node_warm(ctx: &mut EstimatorContext) -> GasCost {
+    rocks_db_trie_node(ctx).warm_read
+}
+
+fn rocks_db_trie_node(ctx: &mut EstimatorContext) -> trie::RocksDbTrieNodeCosts {
+    if let Some(cached) = ctx.cached.rocks_db_trie_node.clone() {
+        return cached;
+    }
+    let warmup_iters = ctx.config.warmup_iters_per_block;
+    let iters = ctx.config.iter_per_block;
+    let costs = trie::rocks_db_trie_node(&mut ctx.testbed(), warmup_iters, iters);
+    ctx.cached.rocks_db_trie_node = Some(costs.clone());
+    costs
+}
+
 fn gas_metering(ctx: &mut EstimatorContext) -> (GasCost, GasCost) {
     if let Some(cached) = ctx.cached.gas_metering_cost_base_per_op.clone() {
         return cached;
@@ -8,7 +8,7 @@ use near_primitives::version::PROTOCOL_VERSION;
 use near_vm_runner::internal::VMKind;
 use runtime_params_estimator::config::{Config, GasMetric};
 use runtime_params_estimator::{
-    costs_to_runtime_config, CostTable, QemuCommandBuilder, RocksDBTestConfig,
+    costs_to_runtime_config, CostTable, QemuCommandBuilder, RocksDBTestConfig, RuntimeConfigPatch,
 };
 use std::env;
 use std::fmt::Write;
@@ -19,6 +19,12 @@ use std::process::Command;
 use std::time;
 use tracing_subscriber::Layer;
 
+// Tracks the heap usage per thread, to measure the memory of every cost.
+#[cfg(feature = "memory_stats")]
+#[global_allocator]
+static ALLOC: near_rust_allocator_proxy::ProxyAllocator<std::alloc::System> =
+    near_rust_allocator_proxy::ProxyAllocator::new(std::alloc::System);
+
 #[derive(Parser)]
 struct CliArgs {
     /// Directory for config and data. If not set, a temporary directory is used
@@ -43,15 +49,39 @@ struct CliArgs {
     /// What metric to use.
     #[clap(long, default_value = "icount", possible_values = &["icount", "time"])]
     metric: String,
-    /// Which VM to test.
-    #[clap(long, possible_values = &["wasmer", "wasmer2", "wasmtime"])]
+    /// Which VM to test. With `all`, only the costs which depend on the VM are
+    /// estimated, with every VM enabled in the build, and compared side by side.
+    #[clap(long, possible_values = &["wasmer", "wasmer2", "wasmtime", "all"])]
     vm_kind: Option<String>,
+    /// Whether the compiled contract cache is kept between the measured blocks
+    /// (`warm`) or cleared before every block (`cold`), so that calling a
+    /// contract compiles it. With `both`, the costs of calling contracts are
+    /// estimated with each and compared side by side.
+    #[clap(long, default_value = "warm", possible_values = &["warm", "cold", "both"])]
+    contract_cache: String,
     /// Render existing `costs.txt` as `RuntimeConfig`.
     #[clap(long)]
     costs_file: Option<PathBuf>,
     /// Compare baseline `costs-file` with a different costs file.
     #[clap(long, requires("costs-file"))]
     compare_to: Option<PathBuf>,
+    /// Compare the estimated costs with a baseline cost table, e.g. one saved
+    /// by a previous run with `--format json`, and fail if any cost changed by
+    /// more than `max-change-percent`.
+    #[clap(long)]
+    compare_with: Option<PathBuf>,
+    /// Maximal relative change of a cost, in percent, tolerated by
+    /// `compare-with`.
+    #[clap(long, default_value = "10")]
+    max_change_percent: f64,
+    /// Propose changes of the current `RuntimeConfig` given the baseline
+    /// `costs-file`, as a JSON patch along with a summary of the changes.
+    #[clap(long, requires("costs-file"))]
+    runtime_config_patch: bool,
+    /// Minimal relative change of a parameter to be included in the
+    /// `runtime-config-patch`.
+    #[clap(long, default_value = "0.1")]
+    change_threshold: f64,
     /// Coma-separated lists of a subset of costs to estimate.
     #[clap(long)]
     costs: Option<String>,
@@ -66,6 +96,31 @@ struct CliArgs {
     /// Works only with enabled docker, because precise computations without it doesn't make sense.
     #[clap(long)]
     full: bool,
+    /// Standard deviation of the per-block measurements of a cost, relative to
+    /// their average, above which the cost is flagged as uncertain.
+    #[clap(long, default_value = "0.1")]
+    max_relative_stddev: f64,
+    /// Number of the costs to estimate concurrently, each in its own testbeds.
+    /// Requires `--metric icount`, as the contention would skew the time.
+    #[clap(long, default_value = "1")]
+    jobs: usize,
+    /// File to save the estimated costs to after every estimation. If it
+    /// already exists, the costs in it are not estimated again, which resumes
+    /// an interrupted run.
+    #[clap(long)]
+    checkpoint: Option<PathBuf>,
+    /// Time budget, in seconds, for the estimation of a single cost. Costs
+    /// which would take longer are estimated with fewer iterations and
+    /// marked as `REDUCED`.
+    #[clap(long)]
+    max_time_per_cost: Option<u64>,
+    /// Record a sampling profile of every estimation with `perf`, and save it
+    /// as folded stacks to `profile-dir`, to be rendered as a flamegraph.
+    #[clap(long)]
+    profile: bool,
+    /// Directory to save the profiles of `profile` to.
+    #[clap(long, default_value = "profiles")]
+    profile_dir: PathBuf,
     /// Drop OS cache before measurements for better IO accuracy. Requires sudo.
     #[clap(long)]
     drop_os_cache: bool,
@@ -76,6 +131,11 @@ struct CliArgs {
     /// object per estimation.
     #[clap(long)]
     json_output: bool,
+    /// Format of the cost table saved at the end of the run: human-readable
+    /// `text`, or `json` and `csv` with the metric, the iterations and the
+    /// measurement time of every cost, to be consumed by scripts.
+    #[clap(long, default_value = "text", possible_values = &["text", "json", "csv"])]
+    format: String,
     /// Prints hierarchical execution-timing information using the tracing-span-tree crate.
     #[clap(long)]
     tracing_span_tree: bool,
@@ -143,12 +203,16 @@ fn main() -> anyhow::Result<()> {
     }
 
     if cli_args.docker {
+        if cli_args.profile {
+            anyhow::bail!("`--profile` can't be combined with `--docker`, as QEMU has no `perf`");
+        }
         return main_docker(
             &state_dump_path,
             cli_args.full,
             cli_args.docker_shell,
             cli_args.json_output,
             cli_args.debug,
+            cli_args.jobs > 1,
         );
     }
 
@@ -161,6 +225,25 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if cli_args.runtime_config_patch {
+        let cost_table = read_costs_table(&cli_args.costs_file.unwrap())?;
+
+        let patch = RuntimeConfigPatch::from_costs(&cost_table, cli_args.change_threshold)?;
+
+        println!("Proposed RuntimeConfig changes:\n");
+        println!("{}", patch);
+
+        let str = serde_json::to_string_pretty(&patch.to_json())
+            .expect("Failed serializing the runtime config patch");
+
+        let output_path = state_dump_path.join("runtime_config_patch.json");
+        fs::write(&output_path, &str)
+            .with_context(|| format!("failed to write runtime config patch to file"))?;
+        println!("\nOutput saved to:\n\n    {}", output_path.display());
+
+        return Ok(());
+    }
+
     if let Some(path) = cli_args.costs_file {
         let cost_table = read_costs_table(&path)?;
 
@@ -219,11 +302,14 @@ fn main() -> anyhow::Result<()> {
         "time" => GasMetric::Time,
         other => unreachable!("Unknown metric {}", other),
     };
+    if cli_args.jobs > 1 && metric != GasMetric::ICount {
+        anyhow::bail!("`--jobs` greater than 1 requires `--metric icount`");
+    }
     let vm_kind = match cli_args.vm_kind.as_deref() {
         Some("wasmer") => VMKind::Wasmer0,
         Some("wasmer2") => VMKind::Wasmer2,
         Some("wasmtime") => VMKind::Wasmtime,
-        None => VMKind::for_protocol_version(PROTOCOL_VERSION),
+        Some("all") | None => VMKind::for_protocol_version(PROTOCOL_VERSION),
         Some(other) => unreachable!("Unknown vm_kind {}", other),
     };
     let costs_to_measure = cli_args.costs.map(|it| it.split(',').map(str::to_string).collect());
@@ -241,24 +327,99 @@ fn main() -> anyhow::Result<()> {
         debug: cli_args.debug,
         json_output: cli_args.json_output,
         drop_os_cache: cli_args.drop_os_cache,
+        max_relative_stddev: cli_args.max_relative_stddev,
+        jobs: cli_args.jobs,
+        checkpoint: cli_args.checkpoint,
+        max_time_per_cost: cli_args.max_time_per_cost.map(time::Duration::from_secs),
+        profile_dir: cli_args.profile.then(|| cli_args.profile_dir),
+        cold_contract_cache: cli_args.contract_cache == "cold",
     };
+    if cli_args.vm_kind.as_deref() == Some("all") {
+        if cli_args.format != "text" || cli_args.compare_with.is_some() {
+            anyhow::bail!("`--vm-kind all` supports neither `--format` nor `--compare-with`");
+        }
+        if cli_args.contract_cache == "both" {
+            anyhow::bail!("`--vm-kind all` can't be combined with `--contract-cache both`");
+        }
+        let comparison = runtime_params_estimator::run_vm_kinds(config);
+        eprintln!("\n{}", comparison);
+        save_output(&comparison.to_string(), "txt", start)?;
+        return Ok(());
+    }
+    if cli_args.contract_cache == "both" {
+        if cli_args.format != "text" || cli_args.compare_with.is_some() {
+            anyhow::bail!(
+                "`--contract-cache both` supports neither `--format` nor `--compare-with`"
+            );
+        }
+        let comparison = runtime_params_estimator::run_contract_caches(config);
+        eprintln!("\n{}", comparison);
+        save_output(&comparison.to_string(), "txt", start)?;
+        return Ok(());
+    }
     let cost_table = runtime_params_estimator::run(config);
 
+    let (output, extension) = match cli_args.format.as_str() {
+        "text" => (cost_table.to_string(), "txt"),
+        "json" => (
+            serde_json::to_string_pretty(&cost_table.to_json())
+                .expect("Failed serializing the cost table"),
+            "json",
+        ),
+        "csv" => (cost_table.to_csv(), "csv"),
+        other => unreachable!("Unknown format {}", other),
+    };
+    if let Some(memory_table) = cost_table.memory_table() {
+        eprintln!("\n{}", memory_table);
+    }
+    save_output(&output, extension, start)?;
+
+    if let Some(baseline) = cli_args.compare_with {
+        let baseline = read_costs_table(&baseline)?;
+        compare_with_baseline(&baseline, &cost_table, cli_args.max_change_percent)?;
+    }
+
+    Ok(())
+}
+
+/// Saves the output of the run to `costs-$timestamp$.$extension$` in the
+/// current directory.
+fn save_output(output: &str, extension: &str, start: time::Instant) -> anyhow::Result<()> {
     let output_path = {
         let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
         let commit =
             exec("git rev-parse --short HEAD").map(|hash| format!("-{}", hash)).unwrap_or_default();
-        let file_name = format!("costs-{}{}.txt", timestamp, commit);
+        let file_name = format!("costs-{}{}.{}", timestamp, commit, extension);
 
         env::current_dir()?.join(file_name)
     };
-    fs::write(&output_path, &cost_table.to_string())?;
+    fs::write(&output_path, output)?;
     eprintln!(
         "\nFinished in {:.2?}, output saved to:\n\n    {}",
         start.elapsed(),
         output_path.display()
     );
+    Ok(())
+}
 
+/// Prints the relative change of every cost from the baseline, and fails if
+/// any of them exceeds `max_change_percent` in either direction.
+fn compare_with_baseline(
+    baseline: &CostTable,
+    cost_table: &CostTable,
+    max_change_percent: f64,
+) -> anyhow::Result<()> {
+    let (table, exceeded) = baseline.diff(cost_table).relative_changes(max_change_percent);
+    println!("{}", table);
+    if !exceeded.is_empty() {
+        let exceeded: Vec<_> = exceeded.iter().map(|cost| cost.to_string()).collect();
+        anyhow::bail!(
+            "{} costs changed by more than {}% from the baseline: {}",
+            exceeded.len(),
+            max_change_percent,
+            exceeded.join(", ")
+        );
+    }
     Ok(())
 }
 
@@ -269,6 +430,7 @@ fn main_docker(
     debug_shell: bool,
     json_output: bool,
     debug: bool,
+    parallel: bool,
 ) -> anyhow::Result<()> {
     exec("docker --version").context("please install `docker`")?;
 
@@ -306,10 +468,13 @@ fn main_docker(
         buf.push_str(",nightly");
         #[cfg(feature = "nightly_protocol")]
         buf.push_str(",nightly_protocol");
+        #[cfg(feature = "memory_stats")]
+        buf.push_str(",memory_stats");
 
         buf.push_str(" --release;");
 
-        let mut qemu_cmd_builder = QemuCommandBuilder::default();
+        // Costs estimated concurrently need their own instruction counters.
+        let mut qemu_cmd_builder = QemuCommandBuilder::default().count_per_thread(parallel);
 
         if debug {
             qemu_cmd_builder = qemu_cmd_builder.plugin_log(true).print_on_every_close(true);
@@ -378,11 +543,16 @@ fn main_docker(
     Ok(())
 }
 
+/// Reads a cost table saved in the `text` or, if the file has the `.json`
+/// extension, in the `json` format.
 fn read_costs_table(path: &Path) -> anyhow::Result<CostTable> {
-    fs::read_to_string(&path)
-        .with_context(|| format!("failed to read costs file: {}", path.display()))?
-        .parse::<CostTable>()
-        .map_err(|()| anyhow::format_err!("failed to parse costs file: {}", path.display()))
+    let s = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read costs file: {}", path.display()))?;
+    let res = match path.extension() {
+        Some(extension) if extension == "json" => CostTable::from_json(&s),
+        _ => s.parse::<CostTable>(),
+    };
+    res.map_err(|()| anyhow::format_err!("failed to parse costs file: {}", path.display()))
 }
 
 fn exec(command: &str) -> anyhow::Result<String> {
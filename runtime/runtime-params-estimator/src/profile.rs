@@ -0,0 +1,140 @@
+//! Sampling profiles of the estimations, recorded with `perf`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use anyhow::Context;
+
+use crate::cost::Cost;
+
+/// Sampling frequency, in Hz.
+const FREQUENCY: u32 = 999;
+/// Time given to `perf` to attach to the thread before the estimation starts.
+const ATTACH_DELAY: Duration = Duration::from_millis(200);
+
+/// `perf record` sampling the stacks of the current thread while a cost is
+/// estimated. Only the estimating thread is sampled, so the profile isn't mixed
+/// with other estimations running concurrently or with background threads.
+pub(crate) struct Profiler {
+    perf: Child,
+    data_path: PathBuf,
+    folded_path: PathBuf,
+}
+
+impl Profiler {
+    /// Starts sampling the current thread for the estimation of `cost`. The
+    /// profile is saved to `dir` as `<cost>.folded`.
+    pub(crate) fn start(dir: &Path, cost: Cost) -> anyhow::Result<Profiler> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create profile directory: {}", dir.display()))?;
+        let data_path = dir.join(format!("{:?}.perf.data", cost));
+        let folded_path = dir.join(format!("{:?}.folded", cost));
+        let tid = unsafe { libc::syscall(libc::SYS_gettid) };
+        let perf = Command::new("perf")
+            .args(["record", "--quiet", "-g", "-F", &FREQUENCY.to_string()])
+            .arg("--tid")
+            .arg(tid.to_string())
+            .arg("-o")
+            .arg(&data_path)
+            .stdout(Stdio::null())
+            .spawn()
+            .context("failed to run `perf record`, is `perf` installed?")?;
+        std::thread::sleep(ATTACH_DELAY);
+        Ok(Profiler { perf, data_path, folded_path })
+    }
+
+    /// Stops sampling and saves the profile as folded stacks, which can be
+    /// rendered with `inferno-flamegraph` or `flamegraph.pl`. Returns the path
+    /// of the saved profile.
+    pub(crate) fn finish(mut self) -> anyhow::Result<PathBuf> {
+        // `perf record` writes the samples out when interrupted.
+        unsafe { libc::kill(self.perf.id() as libc::pid_t, libc::SIGINT) };
+        let status = self.perf.wait().context("failed to wait for `perf record`")?;
+        if !status.success() && !self.data_path.exists() {
+            anyhow::bail!("`perf record` failed: {}", status);
+        }
+        let output = Command::new("perf")
+            .arg("script")
+            .arg("-i")
+            .arg(&self.data_path)
+            .stderr(Stdio::null())
+            .output()
+            .context("failed to run `perf script`")?;
+        if !output.status.success() {
+            anyhow::bail!("`perf script` failed: {}", output.status);
+        }
+        let folded = fold_stacks(&String::from_utf8_lossy(&output.stdout));
+        fs::write(&self.folded_path, folded)
+            .with_context(|| format!("failed to write profile: {}", self.folded_path.display()))?;
+        let _ = fs::remove_file(&self.data_path);
+        Ok(self.folded_path)
+    }
+}
+
+/// Converts the samples printed by `perf script` to folded stacks: one line per
+/// distinct stack, with the frames from the outermost one separated by `;`,
+/// followed by the number of samples.
+fn fold_stacks(perf_script: &str) -> String {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    for sample in perf_script.split("\n\n") {
+        let mut lines = sample.lines().filter(|line| !line.trim().is_empty());
+        let command = match lines.next().and_then(|header| header.split_whitespace().next()) {
+            Some(command) => command,
+            None => continue,
+        };
+        // The frames are listed from the innermost one.
+        let mut frames: Vec<&str> = lines.map(frame_symbol).collect();
+        frames.push(command);
+        frames.reverse();
+        *counts.entry(frames.join(";")).or_default() += 1;
+    }
+    counts.into_iter().map(|(stack, count)| format!("{} {}\n", stack, count)).collect()
+}
+
+/// Extracts the symbol from a frame line of `perf script`, such as
+/// `    55d0c2a1b2c3 near_vm_runner::run+0x1c (/path/to/binary)`.
+fn frame_symbol(line: &str) -> &str {
+    let line = line.trim();
+    let line = match line.rfind(" (") {
+        Some(dso) => &line[..dso],
+        None => line,
+    };
+    // Skip the address.
+    let symbol = match line.find(' ') {
+        Some(space) => line[space..].trim_start(),
+        None => "[unknown]",
+    };
+    match symbol.rfind("+0x") {
+        Some(offset) => &symbol[..offset],
+        None => symbol,
+    }
+}
+
+#[test]
+fn test_fold_stacks() {
+    let perf_script = "\
+runtime-params- 4242 1234.000001:    1001001 cycles:u:
+\t    55d0c2a1b2c3 wasmer_vm::instance::Instance::invoke+0x1c (/bin/estimator)
+\t    55d0c2a1b000 runtime_params_estimator::wasm_instruction+0x200 (/bin/estimator)
+\t    55d0c2a1a000 main+0x10 (/bin/estimator)
+
+runtime-params- 4242 1234.001002:    1001001 cycles:u:
+\t    55d0c2a1b2c3 wasmer_vm::instance::Instance::invoke+0x20 (/bin/estimator)
+\t    55d0c2a1b000 runtime_params_estimator::wasm_instruction+0x200 (/bin/estimator)
+\t    55d0c2a1a000 main+0x10 (/bin/estimator)
+
+runtime-params- 4242 1234.002003:    1001001 cycles:u:
+\t    7f0000000000 [unknown] ([unknown])
+\t    55d0c2a1a000 main+0x10 (/bin/estimator)
+";
+    assert_eq!(
+        fold_stacks(perf_script),
+        "\
+runtime-params-;main;[unknown] 1
+runtime-params-;main;runtime_params_estimator::wasm_instruction;wasmer_vm::instance::Instance::invoke 2
+"
+    );
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use near_primitives::runtime::config_store::RuntimeConfigStore;
+use near_primitives::types::Gas;
+use near_primitives::version::PROTOCOL_VERSION;
+use node_runtime::config::RuntimeConfig;
+use serde_json::Value;
+
+use crate::cost_table::{format_gas, CostTable};
+use crate::costs_to_runtime_config::costs_to_runtime_config;
+
+/// Parts of [`RuntimeConfig`] which are not derived from the estimations, see
+/// [`costs_to_runtime_config`].
+const NOT_ESTIMATED: &[&str] =
+    &["storage_amount_per_byte", "limit_config", "account_creation_config"];
+
+/// Number of significant digits the proposed values are rounded up to.
+const SIGNIFICANT_DIGITS: u32 = 3;
+
+/// A change of a single parameter of [`RuntimeConfig`], identified by its path
+/// in the JSON representation of the config.
+#[derive(Debug, PartialEq)]
+pub struct ParameterChange {
+    pub path: Vec<String>,
+    pub old: Gas,
+    pub estimated: Gas,
+    pub new: Gas,
+}
+
+/// Proposed changes of the current [`RuntimeConfig`] given the results of an
+/// estimation run.
+///
+/// Only the parameters whose relative change exceeds the threshold are
+/// included. The estimated values are rounded up, so that a proposed
+/// parameter is never cheaper than its estimation.
+#[derive(Debug, Default)]
+pub struct RuntimeConfigPatch {
+    changes: Vec<ParameterChange>,
+}
+
+impl RuntimeConfigPatch {
+    /// Compares the costs against the [`RuntimeConfig`] of the latest protocol version.
+    pub fn from_costs(cost_table: &CostTable, threshold: f64) -> anyhow::Result<Self> {
+        let config_store = RuntimeConfigStore::new(None);
+        let current = config_store.get_config(PROTOCOL_VERSION);
+        let proposed = costs_to_runtime_config(cost_table)?;
+        Ok(Self::new(current, &proposed, threshold))
+    }
+
+    pub fn new(current: &RuntimeConfig, proposed: &RuntimeConfig, threshold: f64) -> Self {
+        let current = serde_json::to_value(current).expect("Failed serializing the runtime config");
+        let proposed =
+            serde_json::to_value(proposed).expect("Failed serializing the runtime config");
+        let mut res = RuntimeConfigPatch::default();
+        res.collect(&mut vec![], &current, &proposed, threshold);
+        res
+    }
+
+    fn collect(
+        &mut self,
+        path: &mut Vec<String>,
+        current: &Value,
+        proposed: &Value,
+        threshold: f64,
+    ) {
+        match (current, proposed) {
+            (Value::Object(current), Value::Object(proposed)) => {
+                for (key, proposed) in proposed {
+                    if NOT_ESTIMATED.contains(&key.as_str()) {
+                        continue;
+                    }
+                    if let Some(current) = current.get(key) {
+                        path.push(key.clone());
+                        self.collect(path, current, proposed, threshold);
+                        path.pop();
+                    }
+                }
+            }
+            (Value::Number(current), Value::Number(proposed)) => {
+                let (old, estimated) = match (current.as_u64(), proposed.as_u64()) {
+                    (Some(old), Some(estimated)) => (old, estimated),
+                    _ => return,
+                };
+                // Zero stands for a cost which isn't estimated (yet).
+                if estimated == 0 {
+                    return;
+                }
+                let new = round_up(estimated, SIGNIFICANT_DIGITS);
+                if relative_change(old, new).abs() > threshold {
+                    self.changes.push(ParameterChange { path: path.clone(), old, estimated, new });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn changes(&self) -> &[ParameterChange] {
+        &self.changes
+    }
+
+    /// JSON merge patch of the [`RuntimeConfig`], containing only the changed parameters.
+    pub fn to_json(&self) -> Value {
+        let mut res = Value::Object(Default::default());
+        for change in &self.changes {
+            let (last, parents) = change.path.split_last().unwrap();
+            let mut obj = &mut res;
+            for key in parents {
+                obj = obj
+                    .as_object_mut()
+                    .unwrap()
+                    .entry(key.clone())
+                    .or_insert_with(|| Value::Object(Default::default()));
+            }
+            obj.as_object_mut().unwrap().insert(last.clone(), change.new.into());
+        }
+        res
+    }
+}
+
+impl fmt::Display for RuntimeConfigPatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<60} {:>25} {:>25} {:>25} {:>8}",
+            "Parameter", "Current", "Estimated", "Proposed", "Change"
+        )?;
+
+        let mut biggest_change_first = self.changes.iter().collect::<Vec<_>>();
+        biggest_change_first.sort_by(|a, b| {
+            relative_change(b.old, b.new).abs().total_cmp(&relative_change(a.old, a.new).abs())
+        });
+
+        for change in biggest_change_first {
+            writeln!(
+                f,
+                "{:<60} {:>25} {:>25} {:>25} {:>+7.0}%",
+                change.path.join("."),
+                format_gas(change.old),
+                format_gas(change.estimated),
+                format_gas(change.new),
+                relative_change(change.old, change.new) * 100.,
+            )?
+        }
+        Ok(())
+    }
+}
+
+fn relative_change(old: Gas, new: Gas) -> f64 {
+    if old == 0 {
+        return f64::INFINITY;
+    }
+    (new as f64 - old as f64) / old as f64
+}
+
+/// Rounds `gas` up to the given number of significant digits.
+fn round_up(gas: Gas, digits: u32) -> Gas {
+    let mut unit = 1;
+    while gas / unit >= 10u64.pow(digits) {
+        unit *= 10;
+    }
+    let rounded = gas / unit * unit;
+    if rounded == gas {
+        gas
+    } else {
+        rounded.saturating_add(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_up() {
+        assert_eq!(round_up(0, 3), 0);
+        assert_eq!(round_up(999, 3), 999);
+        assert_eq!(round_up(1000, 3), 1000);
+        assert_eq!(round_up(1001, 3), 1010);
+        assert_eq!(round_up(2_319_861_500_000, 3), 2_320_000_000_000);
+        assert_eq!(round_up(2_310_000_000_000, 3), 2_310_000_000_000);
+    }
+
+    #[test]
+    fn test_runtime_config_patch() {
+        let current = RuntimeConfig::test();
+        let mut proposed = current.clone();
+        let ext_costs = &mut proposed.wasm_config.ext_costs;
+        // Below the threshold.
+        ext_costs.base = current.wasm_config.ext_costs.base * 105 / 100;
+        // Not estimated.
+        ext_costs.contract_loading_base = 0;
+        ext_costs.sha256_base = 2 * current.wasm_config.ext_costs.sha256_base + 1;
+        proposed.transaction_costs.action_receipt_creation_config.execution /= 2;
+        proposed.storage_amount_per_byte *= 2;
+
+        let patch = RuntimeConfigPatch::new(&current, &proposed, 0.1);
+        let sha256_base = current.wasm_config.ext_costs.sha256_base;
+        let execution = current.transaction_costs.action_receipt_creation_config.execution;
+        assert_eq!(
+            serde_json::json!({
+                "transaction_costs": {
+                    "action_receipt_creation_config": {
+                        "execution": round_up(execution / 2, SIGNIFICANT_DIGITS),
+                    },
+                },
+                "wasm_config": {
+                    "ext_costs": {
+                        "sha256_base": round_up(2 * sha256_base + 1, SIGNIFICANT_DIGITS),
+                    },
+                },
+            }),
+            patch.to_json()
+        );
+        assert_eq!(2, patch.changes().len());
+        assert!(patch.to_string().contains("wasm_config.ext_costs.sha256_base"));
+    }
+}
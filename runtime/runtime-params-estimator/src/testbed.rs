@@ -6,7 +6,7 @@ use near_primitives::test_utils::MockEpochInfoProvider;
 use near_primitives::transaction::{ExecutionStatus, SignedTransaction};
 use near_primitives::types::{Gas, MerkleHash};
 use near_primitives::version::PROTOCOL_VERSION;
-use near_store::{ShardTries, ShardUId, Store, StoreCompiledContractCache};
+use near_store::{DBCol, ShardTries, ShardUId, Store, StoreCompiledContractCache};
 use near_vm_logic::VMLimitConfig;
 use node_runtime::{ApplyState, Runtime};
 use std::path::Path;
@@ -146,4 +146,12 @@ impl RuntimeTestbed {
     pub fn store(&mut self) -> Store {
         self.tries.get_store()
     }
+
+    /// Removes the contracts compiled by the previous blocks, so that the next
+    /// block compiles the contracts it calls.
+    pub fn clear_compiled_contract_cache(&mut self) {
+        let mut store_update = self.tries.get_store().store_update();
+        store_update.delete_all(DBCol::CachedContractCode);
+        store_update.commit().unwrap();
+    }
 }
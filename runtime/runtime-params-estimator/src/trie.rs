@@ -1,9 +1,14 @@
+use crate::config::GasMetric;
 use crate::estimator_context::{EstimatorContext, Testbed};
-use crate::gas_cost::{GasCost, NonNegativeTolerance};
-use crate::utils::{aggregate_per_block_measurements, overhead_per_measured_block, percentiles};
-use near_primitives::hash::hash;
+use crate::gas_cost::{GasCost, IoAmplification, NonNegativeTolerance};
+use crate::utils::{
+    aggregate_per_block_measurements, average_cost, disk_io_bytes, overhead_per_measured_block,
+    percentiles,
+};
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::types::TrieCacheMode;
-use near_store::{TrieCachingStorage, TrieStorage};
+use near_store::{DBCol, TrieCachingStorage, TrieStorage};
 use near_vm_logic::ExtCosts;
 use std::iter;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -347,3 +352,122 @@ fn read_raw_nodes_from_storage(
         })
         .sum()
 }
+
+/// Costs per trie node of accessing the RocksDB store directly, see
+/// `Cost::RocksDbWriteTrieNode`, `Cost::RocksDbReadTrieNodeCold` and
+/// `Cost::RocksDbReadTrieNodeWarm`.
+#[derive(Clone)]
+pub(crate) struct RocksDbTrieNodeCosts {
+    pub write: GasCost,
+    pub cold_read: GasCost,
+    pub warm_read: GasCost,
+}
+
+/// A single measurement of the access to a batch of trie nodes in RocksDB.
+struct RocksDbMeasurement {
+    /// Cost per node.
+    cost: GasCost,
+    /// Bytes read from and written to the disk during the measurement, zero if
+    /// not available.
+    disk_io: (u64, u64),
+}
+
+pub(crate) fn rocks_db_trie_node(
+    testbed: &mut Testbed,
+    warmup_iters: usize,
+    iters: usize,
+) -> RocksDbTrieNodeCosts {
+    // Picked somewhat arbitrarily, large enough for the nodes not to fit in
+    // a single SST block, small enough to not trigger compactions.
+    let num_nodes = 1000;
+    let mut writes = vec![];
+    let mut cold_reads = vec![];
+    let mut warm_reads = vec![];
+    let mut data_bytes = 0;
+    for i in 0..(warmup_iters + iters) {
+        let (nodes_bytes, [write, cold_read, warm_read]) =
+            rocks_db_trie_node_ext(testbed, num_nodes);
+        if i >= warmup_iters {
+            data_bytes += nodes_bytes;
+            writes.push(write);
+            cold_reads.push(cold_read);
+            warm_reads.push(warm_read);
+        }
+    }
+    let config = testbed.config;
+    let average = |measurements: Vec<RocksDbMeasurement>| -> GasCost {
+        let costs: Vec<_> = measurements.iter().map(|m| m.cost.clone()).collect();
+        let mut cost = average_cost(config, &costs);
+        let (disk_read, disk_written) =
+            measurements.iter().fold((0, 0), |(r, w), m| (r + m.disk_io.0, w + m.disk_io.1));
+        cost.set_amplification(IoAmplification::new(disk_read, disk_written, data_bytes.max(1)));
+        cost
+    };
+    let mut cold_read = average(cold_reads);
+    if config.metric == GasMetric::Time && !config.drop_os_cache {
+        cold_read.set_uncertain("COLD-READ-WITHOUT-DROP-OS-CACHE");
+    }
+    RocksDbTrieNodeCosts { write: average(writes), cold_read, warm_read: average(warm_reads) }
+}
+
+/// Writes `num_nodes` new trie nodes of the largest possible size into the
+/// state column of the store and reads them back twice, first cold and then
+/// warm. Returns the number of bytes of the nodes and their keys, and the
+/// measurements of the write and of the two reads.
+fn rocks_db_trie_node_ext(
+    testbed: &mut Testbed,
+    num_nodes: usize,
+) -> (u64, [RocksDbMeasurement; 3]) {
+    // See `read_node_from_chunk_cache_ext` for why these are the largest nodes.
+    let value_len: usize = 2048;
+    let tb = testbed.transaction_builder();
+    let nodes: Vec<_> = (0..num_nodes)
+        .map(|_| near_store::estimator::encode_extension_node(tb.random_vec(value_len)))
+        .collect();
+    let hashes: Vec<CryptoHash> = nodes.iter().map(|node| hash(node)).collect();
+    let shard_uid = ShardUId::single_shard().to_bytes();
+    let keys: Vec<Vec<u8>> =
+        hashes.iter().map(|hash| [&shard_uid[..], hash.as_ref()].concat()).collect();
+    let nodes_bytes =
+        nodes.iter().zip(&keys).map(|(node, key)| (node.len() + key.len()) as u64).sum();
+
+    let metric = testbed.config.metric;
+    let store = testbed.store();
+    // Start from a flushed memtable and, if enabled, a dropped OS page cache.
+    testbed.clear_caches();
+    let write = measure_disk_io(metric, num_nodes, || {
+        let mut update = store.store_update();
+        for (key, node) in keys.iter().zip(&nodes) {
+            update.increment_refcount(DBCol::State, key, node);
+        }
+        update.commit().unwrap();
+        store.flush().unwrap();
+    });
+
+    // The nodes are in no cache right after the flush, except for the OS page
+    // cache, which is dropped here if enabled.
+    testbed.clear_caches();
+    let caching_storage = testbed.trie_caching_storage();
+    let cold_read = measure_disk_io(metric, num_nodes, || {
+        SINK.fetch_add(read_raw_nodes_from_storage(&caching_storage, &hashes), Ordering::SeqCst);
+    });
+    // A new `TrieCachingStorage` comes with an empty trie cache, so the nodes
+    // are read from RocksDB again.
+    let caching_storage = testbed.trie_caching_storage();
+    let warm_read = measure_disk_io(metric, num_nodes, || {
+        SINK.fetch_add(read_raw_nodes_from_storage(&caching_storage, &hashes), Ordering::SeqCst);
+    });
+    (nodes_bytes, [write, cold_read, warm_read])
+}
+
+fn measure_disk_io(metric: GasMetric, num_nodes: usize, f: impl FnOnce()) -> RocksDbMeasurement {
+    let io_before = disk_io_bytes();
+    let start = GasCost::measure(metric);
+    f();
+    let cost = start.elapsed() / num_nodes as u64;
+    let disk_io = match (io_before, disk_io_bytes()) {
+        (Some((r0, w0)), Some((r1, w1))) => (r1.saturating_sub(r0), w1.saturating_sub(w0)),
+        _ => (0, 0),
+    };
+    RocksDbMeasurement { cost, disk_io }
+}
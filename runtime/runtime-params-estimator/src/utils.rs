@@ -1,7 +1,7 @@
 use crate::apply_block_cost;
 use crate::config::Config;
 use crate::estimator_context::EstimatorContext;
-use crate::gas_cost::{GasCost, NonNegativeTolerance};
+use crate::gas_cost::{GasCost, NonNegativeTolerance, RejectedBlocks};
 use crate::transaction_builder::TransactionBuilder;
 use near_primitives::transaction::{
     Action, DeployContractAction, FunctionCallAction, SignedTransaction,
@@ -11,6 +11,7 @@ use rand::distributions::Alphanumeric;
 use rand::Rng;
 use rand_xorshift::XorShiftRng;
 use std::collections::HashMap;
+use std::fmt::Write;
 use std::iter;
 
 pub fn read_resource(path: &str) -> Vec<u8> {
@@ -30,6 +31,16 @@ pub fn clear_linux_page_cache() -> std::io::Result<()> {
     std::fs::write("/proc/sys/vm/drop_caches", b"1")
 }
 
+/// Bytes read from and written to the storage devices by this process so far,
+/// as accounted in `/proc/self/io` on Linux. None on other systems.
+pub(crate) fn disk_io_bytes() -> Option<(u64, u64)> {
+    let io = std::fs::read_to_string("/proc/self/io").ok()?;
+    let field = |name: &str| {
+        io.lines().find_map(|line| line.strip_prefix(name)?.trim().parse::<u64>().ok())
+    };
+    Some((field("read_bytes:")?, field("write_bytes:")?))
+}
+
 #[track_caller]
 pub(crate) fn transaction_cost(
     ctx: &mut EstimatorContext,
@@ -67,13 +78,67 @@ pub(crate) fn transaction_cost_ext(
     let measurements = testbed.measure_blocks(blocks, block_latency);
     let measurements =
         measurements.into_iter().skip(testbed.config.warmup_iters_per_block).collect::<Vec<_>>();
+    let measured = measurements.len();
+    let (measurements, rejected) = reject_outlier_blocks(measurements);
 
-    aggregate_per_block_measurements(
+    let (mut gas_cost, ext_costs) = aggregate_per_block_measurements(
         testbed.config,
         block_size,
         measurements,
         Some(measurement_overhead),
-    )
+    );
+    if rejected > 0 {
+        gas_cost.set_rejected_blocks(RejectedBlocks { rejected, measured });
+    }
+    (gas_cost, ext_costs)
+}
+
+/// Modified z-score above which a block is rejected as an outlier, the
+/// threshold recommended by Iglewicz and Hoaglin.
+const OUTLIER_MODIFIED_Z_SCORE: f64 = 3.5;
+
+/// Drops the blocks whose cost deviates from the median much more than the
+/// others do, such as blocks hit by a RocksDB compaction or an allocator
+/// pause, so that a single noisy block doesn't skew the average. The deviation
+/// is measured relative to the median absolute deviation (MAD), which unlike
+/// the standard deviation isn't inflated by the outliers themselves. Nothing
+/// is rejected if there are too few blocks, or if most of them cost exactly
+/// the same. Returns the kept measurements and the number of rejected ones.
+pub(crate) fn reject_outlier_blocks<T>(
+    measurements: Vec<(GasCost, T)>,
+) -> (Vec<(GasCost, T)>, usize) {
+    if measurements.len() < 3 {
+        return (measurements, 0);
+    }
+    let costs: Vec<f64> = measurements.iter().map(|(cost, _)| cost.to_gas() as f64).collect();
+    let median_cost = median(&costs);
+    let deviations: Vec<f64> = costs.iter().map(|cost| (cost - median_cost).abs()).collect();
+    let mad = median(&deviations);
+    if mad == 0.0 {
+        return (measurements, 0);
+    }
+    let n = measurements.len();
+    // 0.6745 is the 75th percentile of the standard normal distribution, which
+    // scales the MAD to the standard deviation for normally distributed costs.
+    let kept: Vec<_> = measurements
+        .into_iter()
+        .zip(deviations)
+        .filter(|(_, deviation)| 0.6745 * deviation / mad <= OUTLIER_MODIFIED_Z_SCORE)
+        .map(|(measurement, _)| measurement)
+        .collect();
+    let rejected = n - kept.len();
+    (kept, rejected)
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let mid = sorted.len() / 2;
+    if sorted.len() % 2 == 0 {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
 }
 
 /// Returns the total measurement overhead for a measured block.
@@ -214,6 +279,9 @@ pub(crate) fn fn_cost_with_setup(
 /// in the returned result. It does so by executing the method n+1 times in a
 /// single transaction, and subtract the cost of a transaction that calls the
 /// method once, before dividing by n.
+///
+/// With `Config::cold_contract_cache`, the result also includes compiling the
+/// contract, i.e. it is the cost of a call of a contract which isn't cached.
 pub(crate) fn fn_cost_in_contract(
     ctx: &mut EstimatorContext,
     method: &str,
@@ -226,7 +294,7 @@ pub(crate) fn fn_cost_in_contract(
 
     let mut chosen_accounts = {
         let tb = testbed.transaction_builder();
-        iter::repeat_with(|| tb.random_unused_account()).take(n_blocks + 1).collect::<Vec<_>>()
+        iter::repeat_with(|| tb.random_unused_account()).take(n_blocks + 2).collect::<Vec<_>>()
     };
     testbed.clear_caches();
 
@@ -263,13 +331,34 @@ pub(crate) fn fn_cost_in_contract(
     let mut measurements = testbed.measure_blocks(blocks, 0);
     measurements.drain(0..n_warmup_blocks);
 
+    // With a cold contract cache, every block compiles the contract once, on
+    // its first call, so the compilation cancels out when subtracting the base.
+    // Measure it as the difference between the base with a cold and with a
+    // warm cache, to add it to the cost of a single call.
+    let compilation = testbed.config.cold_contract_cache.then(|| {
+        let warm_account = chosen_accounts.pop().unwrap();
+        let warm_tx = testbed.transaction_builder().transaction_from_actions(
+            warm_account.clone(),
+            warm_account,
+            vec![function_call_action(method.to_string())],
+        );
+        let (warm_gas_cost, _warm_ext_costs) =
+            testbed.measure_block_with_warm_contract_cache(vec![warm_tx], 0);
+        measurements[0].0.saturating_sub(&warm_gas_cost, &NonNegativeTolerance::PER_MILLE)
+    });
+
     let (base_gas_cost, _base_ext_costs) = measurements.remove(0);
     // Do not subtract block overhead because we already subtract the base.
     let overhead = None;
     let block_size = 1;
     let (gas_cost, _ext_costs) =
         aggregate_per_block_measurements(ctx.config, block_size, measurements, overhead);
-    gas_cost.saturating_sub(&base_gas_cost, &NonNegativeTolerance::Strict) / (n_actions - 1) as u64
+    let per_action = gas_cost.saturating_sub(&base_gas_cost, &NonNegativeTolerance::Strict)
+        / (n_actions - 1) as u64;
+    match compilation {
+        Some(compilation) => per_action + compilation,
+        None => per_action,
+    }
 }
 
 fn function_call_action(method_name: String) -> Action {
@@ -288,11 +377,13 @@ pub(crate) fn aggregate_per_block_measurements(
     overhead: Option<GasCost>,
 ) -> (GasCost, HashMap<ExtCosts, u64>) {
     let mut block_costs = Vec::new();
+    let mut unit_costs = Vec::new();
     let mut total_ext_costs: HashMap<ExtCosts, u64> = HashMap::new();
     let mut total = GasCost::zero(config.metric);
     let mut n = 0;
     for (gas_cost, ext_cost) in measurements {
         block_costs.push(gas_cost.to_gas() as f64);
+        unit_costs.push(gas_cost.to_gas() / block_size as u64);
         total += gas_cost;
         n += block_size as u64;
         for (c, v) in ext_cost {
@@ -303,6 +394,7 @@ pub(crate) fn aggregate_per_block_measurements(
         *v /= n;
     }
     let mut gas_cost = total / n;
+    gas_cost.set_spread(&unit_costs, config.max_relative_stddev);
     if is_high_variance(&block_costs) {
         gas_cost.set_uncertain("HIGH-VARIANCE");
     }
@@ -315,6 +407,8 @@ pub(crate) fn aggregate_per_block_measurements(
 pub(crate) fn average_cost(config: &Config, measurements: &[GasCost]) -> GasCost {
     let total = measurements.iter().fold(GasCost::zero(config.metric), |acc, x| acc + x.clone());
     let mut avg = total / measurements.len() as u64;
+    let unit_costs = measurements.iter().map(GasCost::to_gas).collect::<Vec<_>>();
+    avg.set_spread(&unit_costs, config.max_relative_stddev);
     let scalar_costs = measurements.iter().map(|cost| cost.to_gas() as f64).collect::<Vec<_>>();
     if is_high_variance(&scalar_costs) {
         avg.set_uncertain("HIGH-VARIANCE");
@@ -387,9 +481,48 @@ pub(crate) fn generate_data_only_contract(data_size: usize, config: &VMConfig) -
     wasm
 }
 
+/// Generates a valid contract with `functions` functions, `imports` imported
+/// host functions and a data segment of `data_size` bytes. The first function
+/// is exported as `pivot_fn`, so that it can be renamed to get contracts with
+/// a different hash.
+pub(crate) fn generate_shaped_contract(
+    functions: usize,
+    imports: usize,
+    data_size: usize,
+    pivot_fn: &str,
+    config: &VMConfig,
+) -> Vec<u8> {
+    let mut wat_code = String::from("(module\n");
+    for _ in 0..imports {
+        wat_code.push_str("(import \"env\" \"input\" (func (param i64)))\n");
+    }
+    // The memory is replaced by the one imported from the host when the
+    // contract is prepared, so it is only needed for the data to fit.
+    let pages = data_size / (64 * 1024) + 1;
+    write!(wat_code, "(memory {pages})\n(func (export \"{pivot_fn}\"))\n").unwrap();
+    for i in 1..functions {
+        write!(
+            wat_code,
+            "(func (param i32) (result i32) local.get 0 i32.const {i} i32.mul i32.const 7 i32.add)\n"
+        )
+        .unwrap();
+    }
+    if data_size > 0 {
+        // Using pseudo-random stream with fixed seed to create deterministic, incompressable payload.
+        let prng: XorShiftRng = rand::SeedableRng::seed_from_u64(0xdeadbeef);
+        let payload = prng.sample_iter(&Alphanumeric).take(data_size).collect::<String>();
+        write!(wat_code, "(data (i32.const 0) \"{payload}\")\n").unwrap();
+    }
+    wat_code.push(')');
+    let wasm = wat::parse_str(wat_code).unwrap();
+    // Validate generated code is valid.
+    near_vm_runner::prepare::prepare_contract(&wasm, config).unwrap();
+    wasm
+}
+
 #[cfg(test)]
 mod test {
-    use super::percentiles;
+    use super::{percentiles, reject_outlier_blocks};
     use crate::{config::GasMetric, gas_cost::GasCost};
     use rand::prelude::SliceRandom;
 
@@ -417,4 +550,29 @@ mod test {
         one_to_one_o_one.shuffle(&mut rand::thread_rng());
         check_percentiles(&one_to_one_o_one, &[0.1, 0.5, 0.995], &[11, 51, 101]);
     }
+
+    #[test]
+    fn test_reject_outlier_blocks() {
+        let blocks = |gas_values: &[u64]| -> Vec<(GasCost, usize)> {
+            gas_values
+                .iter()
+                .enumerate()
+                .map(|(i, n)| (GasCost::from_gas((*n).into(), GasMetric::Time), i))
+                .collect()
+        };
+        let kept_indices = |gas_values: &[u64]| {
+            let (kept, rejected) = reject_outlier_blocks(blocks(gas_values));
+            (kept.into_iter().map(|(_, i)| i).collect::<Vec<_>>(), rejected)
+        };
+
+        // A single block hit by a pause is rejected, the noise of the others isn't.
+        assert_eq!(
+            (vec![0, 1, 2, 4, 5, 6], 1),
+            kept_indices(&[1000, 1020, 990, 5000, 1010, 980, 1000])
+        );
+        assert_eq!((vec![0, 1, 2, 3], 0), kept_indices(&[1000, 1100, 900, 1050]));
+        // Too few blocks, or identical blocks, give nothing to compare against.
+        assert_eq!((vec![0, 1], 0), kept_indices(&[1000, 5000]));
+        assert_eq!((vec![0, 1, 2, 3], 0), kept_indices(&[1000, 1000, 1000, 5000]));
+    }
 }
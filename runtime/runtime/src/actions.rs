@@ -13,9 +13,10 @@ use near_primitives::transaction::{
     Action, AddKeyAction, DeleteAccountAction, DeleteKeyAction, DeployContractAction,
     FunctionCallAction, StakeAction, TransferAction,
 };
+use near_primitives::trie_key::trie_key_parsers;
 use near_primitives::types::validator_stake::ValidatorStake;
 use near_primitives::types::{AccountId, BlockHeight, EpochInfoProvider, TrieCacheMode};
-use near_primitives::utils::create_random_seed;
+use near_primitives::utils::{create_random_seed, create_receipt_random_seed};
 use near_primitives::version::{
     is_implicit_account_creation_enabled, ProtocolFeature, ProtocolVersion,
     DELETE_KEY_STORAGE_USAGE_PROTOCOL_VERSION,
@@ -46,6 +47,7 @@ pub(crate) fn execute_function_call(
     promise_results: &[PromiseResult],
     function_call: &FunctionCallAction,
     action_hash: &CryptoHash,
+    receipt_id: &CryptoHash,
     config: &RuntimeConfig,
     is_last_action: bool,
     view_config: Option<ViewConfig>,
@@ -76,6 +78,7 @@ pub(crate) fn execute_function_call(
         *action_hash,
         apply_state.random_seed,
     );
+    let receipt_random_seed = create_receipt_random_seed(receipt_id, &apply_state.random_seed);
     let context = VMContext {
         current_account_id: runtime_ext.account_id().clone(),
         signer_account_id: action_receipt.signer_id.clone(),
@@ -94,6 +97,7 @@ pub(crate) fn execute_function_call(
         attached_deposit: function_call.deposit,
         prepaid_gas: function_call.gas,
         random_seed,
+        receipt_random_seed,
         view_config,
         output_data_receivers,
     };
@@ -164,6 +168,7 @@ pub(crate) fn action_function_call(
         promise_results,
         function_call,
         action_hash,
+        &receipt.receipt_id,
         config,
         is_last_action,
         None,
@@ -509,7 +514,15 @@ pub(crate) fn action_delete_account(
             debug_assert!(account_storage_usage > code_len);
             account_storage_usage = account_storage_usage.saturating_sub(code_len);
         }
-        if account_storage_usage > Account::MAX_ACCOUNT_DELETION_STORAGE_USAGE {
+        // The records are only counted for accounts within the storage usage limit, the
+        // other ones are refused without reading them.
+        let too_large = account_storage_usage > Account::MAX_ACCOUNT_DELETION_STORAGE_USAGE
+            || (checked_feature!(
+                "protocol_feature_delete_account_data_limit",
+                DeleteAccountDataRecordLimit,
+                current_protocol_version
+            ) && has_too_many_data_records(state_update, account_id)?);
+        if too_large {
             result.result = Err(ActionErrorKind::DeleteAccountWithLargeState {
                 account_id: account_id.clone(),
             }
@@ -530,6 +543,30 @@ pub(crate) fn action_delete_account(
     Ok(())
 }
 
+/// Whether the account has more than `MAX_ACCOUNT_DELETION_DATA_RECORDS` contract data
+/// records.  Only the records up to the limit are read, so the check itself is bounded.
+///
+/// The reads are not charged separately, and don't need to be: `remove_account` reads all
+/// the records of the deleted account without charging for them either, and the check
+/// only walks the same trie nodes once more, at most `MAX_ACCOUNT_DELETION_DATA_RECORDS + 1`
+/// records of them.  Both are paid for by `delete_account_cost`, which the estimator
+/// checks against `ActionDeleteAccount` plus the limit times
+/// `ActionDeleteAccountPerDataRecord`, measured with this check enabled.  The storage
+/// usage limit alone doesn't bound the records tightly enough: with
+/// `num_extra_bytes_record` of 40 bytes and short keys and values it still allows about
+/// 250 of them.
+fn has_too_many_data_records(
+    state_update: &TrieUpdate,
+    account_id: &AccountId,
+) -> Result<bool, StorageError> {
+    let limit = Account::MAX_ACCOUNT_DELETION_DATA_RECORDS as usize;
+    let records = state_update
+        .iter(&trie_key_parsers::get_raw_prefix_for_contract_data(account_id, &[]))?
+        .take(limit + 1)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(records.len() > limit)
+}
+
 pub(crate) fn action_delete_key(
     fee_config: &RuntimeFeesConfig,
     state_update: &mut TrieUpdate,
@@ -836,6 +873,7 @@ mod tests {
         code_hash: &CryptoHash,
         storage_usage: u64,
         state_update: &mut TrieUpdate,
+        protocol_version: ProtocolVersion,
     ) -> ActionResult {
         let mut account = Some(Account::new(100, 0, *code_hash, storage_usage));
         let mut actor_id = account_id.clone();
@@ -849,7 +887,7 @@ mod tests {
             &mut action_result,
             account_id,
             &DeleteAccountAction { beneficiary_id: "bob".parse().unwrap() },
-            ProtocolFeature::DeleteActionRestriction.protocol_version(),
+            protocol_version,
         );
         assert!(res.is_ok());
         action_result
@@ -865,6 +903,7 @@ mod tests {
             &CryptoHash::default(),
             Account::MAX_ACCOUNT_DELETION_STORAGE_USAGE + 1,
             &mut state_update,
+            ProtocolFeature::DeleteActionRestriction.protocol_version(),
         );
         assert_eq!(
             action_result.result,
@@ -886,7 +925,13 @@ mod tests {
         let empty_contract = [0; 10_000].to_vec();
         let contract_hash = hash(&empty_contract);
         state_update.set(trie_key, empty_contract);
-        test_delete_large_account(&account_id, &contract_hash, storage_usage, &mut state_update)
+        test_delete_large_account(
+            &account_id,
+            &contract_hash,
+            storage_usage,
+            &mut state_update,
+            ProtocolFeature::DeleteActionRestriction.protocol_version(),
+        )
     }
 
     #[test]
@@ -910,4 +955,41 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    #[cfg(feature = "protocol_feature_delete_account_data_limit")]
+    fn test_delete_account_with_many_data_records() {
+        let account_id = "alice".parse::<AccountId>().unwrap();
+        let delete_with_records = |num_records: u64, protocol_version| {
+            let tries = create_tries();
+            let mut state_update =
+                tries.new_trie_update(ShardUId::single_shard(), CryptoHash::default());
+            for i in 0..num_records {
+                let key = i.to_le_bytes().to_vec();
+                state_update
+                    .set(TrieKey::ContractData { account_id: account_id.clone(), key }, vec![]);
+            }
+            // The records are small enough to stay within the storage usage limit.
+            test_delete_large_account(
+                &account_id,
+                &CryptoHash::default(),
+                Account::MAX_ACCOUNT_DELETION_STORAGE_USAGE,
+                &mut state_update,
+                protocol_version,
+            )
+        };
+        let limit = Account::MAX_ACCOUNT_DELETION_DATA_RECORDS;
+        let feature_version = ProtocolFeature::DeleteAccountDataRecordLimit.protocol_version();
+        assert!(delete_with_records(limit, feature_version).result.is_ok());
+        assert!(delete_with_records(limit + 1, feature_version - 1).result.is_ok());
+        assert_eq!(
+            delete_with_records(limit + 1, feature_version).result,
+            Err(ActionError {
+                index: None,
+                kind: ActionErrorKind::DeleteAccountWithLargeState {
+                    account_id: "alice".parse().unwrap()
+                }
+            })
+        );
+    }
 }
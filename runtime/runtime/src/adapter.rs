@@ -7,7 +7,7 @@ use near_primitives::types::{
     AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, MerkleHash,
 };
 use near_primitives::version::ProtocolVersion;
-use near_primitives::views::ViewStateResult;
+use near_primitives::views::{AccessKeyPermissionFilter, ViewStateResult};
 
 /// Adapter for querying runtime.
 pub trait ViewRuntimeAdapter {
@@ -56,7 +56,13 @@ pub trait ViewRuntimeAdapter {
         shard_uid: &ShardUId,
         state_root: MerkleHash,
         account_id: &AccountId,
-    ) -> Result<Vec<(PublicKey, AccessKey)>, crate::state_viewer::errors::ViewAccessKeyError>;
+        permission: Option<&AccessKeyPermissionFilter>,
+        after_public_key: Option<&PublicKey>,
+        limit: Option<u32>,
+    ) -> Result<
+        (Vec<(PublicKey, AccessKey)>, Option<PublicKey>),
+        crate::state_viewer::errors::ViewAccessKeyError,
+    >;
 
     fn view_state(
         &self,
@@ -2,6 +2,7 @@ use std::cmp::max;
 use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use near_primitives::sandbox_state_patch::SandboxStatePatch;
 use tracing::debug;
@@ -112,6 +113,15 @@ pub struct ApplyStats {
     pub gas_deficit_amount: Balance,
 }
 
+/// Size of the state witness of a chunk: the storage proof of all the state read while
+/// applying it, see `Trie::record_proof_size`.
+#[derive(Debug, Default)]
+pub struct StateWitnessSize {
+    pub total: u64,
+    /// Part of `total` first read while processing the receipts to each receiver.
+    pub by_receiver: HashMap<AccountId, u64>,
+}
+
 pub struct ApplyResult {
     pub state_root: StateRoot,
     pub trie_changes: TrieChanges,
@@ -122,6 +132,11 @@ pub struct ApplyResult {
     pub stats: ApplyStats,
     pub processed_delayed_receipts: Vec<Receipt>,
     pub proof: Option<PartialStorage>,
+    /// Set if the size of the storage proof has been recorded, see `Trie::record_proof_size`.
+    pub state_witness_size: Option<StateWitnessSize>,
+    /// Wall-clock time it took to process each of the receipts, by the receiver of the receipt.
+    /// Depends on the node, so it must only be used for monitoring.
+    pub receipt_execution_times: Vec<(AccountId, Duration)>,
 }
 
 #[derive(Debug)]
@@ -1199,6 +1214,9 @@ impl Runtime {
         {
             let (trie_changes, state_changes) = state_update.finalize()?;
             let proof = trie.recorded_storage();
+            let state_witness_size = trie
+                .recorded_proof_size()
+                .map(|total| StateWitnessSize { total, by_receiver: HashMap::new() });
             return Ok(ApplyResult {
                 state_root: trie_changes.new_root,
                 trie_changes,
@@ -1209,6 +1227,8 @@ impl Runtime {
                 stats,
                 processed_delayed_receipts: vec![],
                 proof,
+                state_witness_size,
+                receipt_execution_times: vec![],
             });
         }
 
@@ -1243,6 +1263,8 @@ impl Runtime {
         let mut delayed_receipts_indices: DelayedReceiptIndices =
             get(&state_update, &TrieKey::DelayedReceiptIndices)?.unwrap_or_default();
         let initial_delayed_receipt_indices = delayed_receipts_indices.clone();
+        let mut witness_size_by_receiver: HashMap<AccountId, u64> = HashMap::new();
+        let mut receipt_execution_times = vec![];
 
         let mut process_receipt = |receipt: &Receipt,
                                    state_update: &mut TrieUpdate,
@@ -1258,6 +1280,8 @@ impl Runtime {
                 id = %receipt.receipt_id,
             )
             .entered();
+            let witness_size_before = state_update.trie.recorded_proof_size();
+            let started = Instant::now();
             let result = self.process_receipt(
                 state_update,
                 apply_state,
@@ -1267,7 +1291,14 @@ impl Runtime {
                 &mut stats,
                 epoch_info_provider,
             );
+            receipt_execution_times.push((receipt.receiver_id.clone(), started.elapsed()));
             tracing::debug!(target: "runtime", node_counter = ?state_update.trie.get_trie_nodes_count());
+            if let (Some(before), Some(after)) =
+                (witness_size_before, state_update.trie.recorded_proof_size())
+            {
+                *witness_size_by_receiver.entry(receipt.receiver_id.clone()).or_default() +=
+                    after - before;
+            }
             result?.into_iter().try_for_each(
                 |outcome_with_id: ExecutionOutcomeWithId| -> Result<(), RuntimeError> {
                     *total_gas_burnt =
@@ -1373,6 +1404,9 @@ impl Runtime {
 
         let state_root = trie_changes.new_root;
         let proof = trie.recorded_storage();
+        let state_witness_size = trie
+            .recorded_proof_size()
+            .map(|total| StateWitnessSize { total, by_receiver: witness_size_by_receiver });
         Ok(ApplyResult {
             state_root,
             trie_changes,
@@ -1383,6 +1417,8 @@ impl Runtime {
             stats,
             processed_delayed_receipts,
             proof,
+            state_witness_size,
+            receipt_execution_times,
         })
     }
 
@@ -1611,6 +1647,29 @@ mod tests {
             .unwrap();
     }
 
+    #[test]
+    fn test_apply_state_witness_size() {
+        let (runtime, tries, root, apply_state, _, epoch_info_provider) =
+            setup_runtime(to_yocto(1_000_000), 0, 10u64.pow(15));
+        let receipts = [Receipt::new_balance_refund(&alice_account(), to_yocto(1))];
+        let apply = |trie| {
+            runtime
+                .apply(trie, root, &None, &apply_state, &receipts, &[], &epoch_info_provider, None)
+                .unwrap()
+        };
+
+        let apply_result = apply(tries.get_trie_for_shard(ShardUId::single_shard()));
+        assert!(apply_result.state_witness_size.is_none());
+
+        let trie = tries.get_trie_for_shard(ShardUId::single_shard());
+        trie.record_proof_size();
+        let witness_size = apply(trie).state_witness_size.unwrap();
+        let receiver_size = witness_size.by_receiver[&alice_account()];
+        assert!(receiver_size > 0);
+        // Includes the reads of the delayed receipts indices and the final update of the trie.
+        assert!(witness_size.total > receiver_size);
+    }
+
     #[test]
     fn test_apply_check_balance_validation_rewards() {
         let initial_locked = to_yocto(500_000);
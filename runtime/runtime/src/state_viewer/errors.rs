@@ -52,6 +52,8 @@ pub enum CallFunctionError {
     InternalError { error_message: String },
     #[error("VM error occurred: #{error_message}")]
     VMError { error_message: String },
+    #[error("Function call was aborted after running for {elapsed:?}")]
+    ExecutionTimeout { elapsed: std::time::Duration },
 }
 
 impl From<ViewAccountError> for ViewContractCodeError {
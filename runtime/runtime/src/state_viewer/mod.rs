@@ -4,7 +4,7 @@ use near_crypto::{KeyType, PublicKey};
 use near_primitives::runtime::config_store::RuntimeConfigStore;
 use near_primitives::{
     account::{AccessKey, Account},
-    borsh::BorshDeserialize,
+    borsh::{BorshDeserialize, BorshSerialize},
     contract::ContractCode,
     hash::CryptoHash,
     receipt::ActionReceipt,
@@ -16,11 +16,13 @@ use near_primitives::{
     transaction::FunctionCallAction,
     trie_key::trie_key_parsers,
     types::{AccountId, EpochInfoProvider, Gas},
-    views::{StateItem, ViewApplyState, ViewStateResult},
+    views::{AccessKeyPermissionFilter, StateItem, ViewApplyState, ViewStateResult},
 };
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
+use near_store::{get_access_key, get_account, get_code, TrieUpdate, TrieUpdateIterator};
+use near_vm_errors::{FunctionCallError, HostError, VMError};
 use near_vm_logic::{ReturnData, ViewConfig};
-use std::{str, sync::Arc, time::Instant};
+use std::time::{Duration, Instant};
+use std::{str, sync::Arc};
 use tracing::debug;
 
 pub mod errors;
@@ -30,6 +32,8 @@ pub struct TrieViewer {
     state_size_limit: Option<u64>,
     /// Gas limit used when when handling call_function queries.
     max_gas_burnt_view: Gas,
+    /// Wall-clock time limit used when handling call_function queries. None is no limit
+    view_call_timeout: Option<Duration>,
 }
 
 impl Default for TrieViewer {
@@ -37,15 +41,19 @@ impl Default for TrieViewer {
         let config_store = RuntimeConfigStore::new(None);
         let latest_runtime_config = config_store.get_config(PROTOCOL_VERSION);
         let max_gas_burnt = latest_runtime_config.wasm_config.limit_config.max_gas_burnt;
-        Self { state_size_limit: None, max_gas_burnt_view: max_gas_burnt }
+        Self { state_size_limit: None, max_gas_burnt_view: max_gas_burnt, view_call_timeout: None }
     }
 }
 
 impl TrieViewer {
-    pub fn new(state_size_limit: Option<u64>, max_gas_burnt_view: Option<Gas>) -> Self {
+    pub fn new(
+        state_size_limit: Option<u64>,
+        max_gas_burnt_view: Option<Gas>,
+        view_call_timeout: Option<Duration>,
+    ) -> Self {
         let max_gas_burnt_view =
             max_gas_burnt_view.unwrap_or_else(|| TrieViewer::default().max_gas_burnt_view);
-        Self { state_size_limit, max_gas_burnt_view }
+        Self { state_size_limit, max_gas_burnt_view, view_call_timeout }
     }
 
     pub fn view_account(
@@ -84,34 +92,58 @@ impl TrieViewer {
         })
     }
 
+    /// Access keys of the account with the given permission, in the order of
+    /// their public keys in the trie, starting after `after_public_key` and
+    /// stopping after `limit` keys.  Also returns the cursor to continue from if
+    /// there are more keys.
     pub fn view_access_keys(
         &self,
         state_update: &TrieUpdate,
         account_id: &AccountId,
-    ) -> Result<Vec<(PublicKey, AccessKey)>, errors::ViewAccessKeyError> {
+        permission: Option<&AccessKeyPermissionFilter>,
+        after_public_key: Option<&PublicKey>,
+        limit: Option<u32>,
+    ) -> Result<(Vec<(PublicKey, AccessKey)>, Option<PublicKey>), errors::ViewAccessKeyError> {
         let prefix = trie_key_parsers::get_raw_prefix_for_access_keys(account_id);
         let raw_prefix: &[u8] = prefix.as_ref();
-        let access_keys =
-            state_update
-                .iter(&prefix)?
-                .map(|key| {
-                    let key = key?;
-                    let public_key = &key[raw_prefix.len()..];
-                    let access_key = near_store::get_access_key_raw(state_update, &key)?
-                        .ok_or_else(|| errors::ViewAccessKeyError::InternalError {
-                            error_message: "Unexpected missing key from iterator".to_string(),
-                        })?;
-                    PublicKey::try_from_slice(public_key)
-                        .map_err(|_| errors::ViewAccessKeyError::InternalError {
-                            error_message: format!(
-                                "Unexpected invalid public key {:?} received from store",
-                                public_key
-                            ),
-                        })
-                        .map(|key| (key, access_key))
-                })
-                .collect::<Result<Vec<_>, errors::ViewAccessKeyError>>();
-        access_keys
+        // Appending a zero byte gives the smallest key after the cursor.
+        let start = match after_public_key {
+            Some(public_key) => {
+                let mut start = public_key.try_to_vec().unwrap();
+                start.push(0);
+                start
+            }
+            None => vec![],
+        };
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        let mut access_keys = Vec::new();
+        for key in TrieUpdateIterator::new(state_update, &prefix, &start, None)? {
+            let key = key?;
+            let public_key = &key[raw_prefix.len()..];
+            let access_key =
+                near_store::get_access_key_raw(state_update, &key)?.ok_or_else(|| {
+                    errors::ViewAccessKeyError::InternalError {
+                        error_message: "Unexpected missing key from iterator".to_string(),
+                    }
+                })?;
+            if !permission.map_or(true, |permission| permission.matches(&access_key.permission)) {
+                continue;
+            }
+            if access_keys.len() == limit {
+                let next_public_key = access_keys.last().map(|(key, _)| key.clone());
+                return Ok((access_keys, next_public_key));
+            }
+            let public_key = PublicKey::try_from_slice(public_key).map_err(|_| {
+                errors::ViewAccessKeyError::InternalError {
+                    error_message: format!(
+                        "Unexpected invalid public key {:?} received from store",
+                        public_key
+                    ),
+                }
+            })?;
+            access_keys.push((public_key, access_key));
+        }
+        Ok((access_keys, None))
     }
 
     pub fn view_state(
@@ -234,9 +266,13 @@ impl TrieViewer {
             &[],
             &function_call,
             &empty_hash,
+            &empty_hash,
             config,
             true,
-            Some(ViewConfig { max_gas_burnt: self.max_gas_burnt_view }),
+            Some(ViewConfig {
+                max_gas_burnt: self.max_gas_burnt_view,
+                timeout: self.view_call_timeout,
+            }),
         )
         .outcome_error();
         let elapsed = now.elapsed();
@@ -248,7 +284,12 @@ impl TrieViewer {
             logs.extend(outcome.logs);
             let message = format!("wasm execution failed with error: {:?}", err);
             debug!(target: "runtime", "(exec time {}) {}", time_str, message);
-            Err(errors::CallFunctionError::VMError { error_message: message })
+            match err {
+                VMError::FunctionCallError(FunctionCallError::HostError(
+                    HostError::ExecutionTimeout,
+                )) => Err(errors::CallFunctionError::ExecutionTimeout { elapsed }),
+                _ => Err(errors::CallFunctionError::VMError { error_message: message }),
+            }
         } else {
             debug!(target: "runtime", "(exec time {}) result of execution: {:?}", time_str, outcome);
             logs.extend(outcome.logs);
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use near_primitives::hash::CryptoHash;
+
+use crate::fetch_chain::Range;
+use crate::latency::Latencies;
+
+// RangeProgress tracks how far the fetching of a Range got, so that an
+// interrupted run can be resumed from there. Blocks are fetched
+// concurrently, so the progress only advances over a prefix of the range
+// which has been fetched completely.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct RangeProgress {
+    pub range: Range,
+    // The last block of the range such that it and all the blocks before
+    // it have been fetched, or range.start_block_hash if there is none.
+    pub last_hash: CryptoHash,
+    pub last_height: u64,
+    // Number of the blocks counted towards range.block_limit so far.
+    pub blocks_count: u64,
+    // Whether the whole range has been fetched.
+    pub done: bool,
+    // Whether all the headers of the range have been fetched.
+    #[serde(skip)]
+    headers_done: bool,
+    // Blocks being fetched, by height, with whether they have been fetched.
+    #[serde(skip)]
+    pending: BTreeMap<u64, (CryptoHash, bool)>,
+}
+
+impl RangeProgress {
+    pub fn new(range: Range) -> RangeProgress {
+        return RangeProgress {
+            last_hash: range.start_block_hash,
+            range,
+            last_height: 0,
+            blocks_count: 0,
+            done: false,
+            headers_done: false,
+            pending: BTreeMap::new(),
+        };
+    }
+
+    // started() records that the block has been counted towards the
+    // block_limit and is being fetched.
+    pub fn started(&mut self, height: u64, hash: CryptoHash) {
+        self.pending.insert(height, (hash, false));
+    }
+
+    // fetched() records that the block has been fetched and advances the
+    // progress over the blocks fetched completely.
+    pub fn fetched(&mut self, height: u64) {
+        if let Some((_, fetched)) = self.pending.get_mut(&height) {
+            *fetched = true;
+        }
+        loop {
+            let (height, hash) = match self.pending.iter().next() {
+                Some((&height, &(hash, true))) => (height, hash),
+                _ => break,
+            };
+            self.pending.remove(&height);
+            self.last_hash = hash;
+            self.last_height = height;
+            self.blocks_count += 1;
+        }
+        self.done = self.headers_done && self.pending.is_empty();
+    }
+
+    // headers_done() records that no more blocks of the range will be
+    // started.
+    pub fn headers_done(&mut self) {
+        self.headers_done = true;
+        self.done = self.pending.is_empty();
+    }
+}
+
+// Checkpoint is the state of a run persisted periodically, so that the run
+// can be resumed after an interruption.
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct Checkpoint {
+    pub ranges: Vec<RangeProgress>,
+    // Latencies of the responses to the BlockHeadersRequests, per peer.
+    pub header_latencies: Latencies,
+}
+
+impl Checkpoint {
+    // load() reads the checkpoint from <path>, if it exists.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Checkpoint>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let checkpoint = serde_json::from_reader(std::io::BufReader::new(file))
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        return Ok(Some(checkpoint));
+    }
+
+    // save() writes the checkpoint to <path> atomically, so that an
+    // interruption while saving doesn't corrupt the previous checkpoint.
+    pub fn save(
+        path: &Path,
+        ranges: &[Arc<Mutex<RangeProgress>>],
+        header_latencies: &Mutex<Latencies>,
+    ) -> anyhow::Result<()> {
+        let checkpoint = Checkpoint {
+            ranges: ranges.iter().map(|r| r.lock().unwrap().clone()).collect(),
+            header_latencies: header_latencies.lock().unwrap().clone(),
+        };
+        let tmp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path)
+            .with_context(|| format!("failed to create {}", tmp_path.display()))?;
+        serde_json::to_writer(file, &checkpoint)?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        return Ok(());
+    }
+
+    // ranges() returns the progress of the <ranges>, resumed from the
+    // checkpoint. The checkpoint has to be of a run over the same ranges.
+    pub fn ranges(&self, ranges: &[Range]) -> anyhow::Result<Vec<RangeProgress>> {
+        if !self.ranges.iter().map(|p| &p.range).eq(ranges.iter()) {
+            anyhow::bail!("the checkpoint is of a run over different ranges");
+        }
+        return Ok(self.ranges.clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_range_progress() {
+        let hash = |i: u64| CryptoHash::hash_bytes(&i.to_le_bytes());
+        let range = Range { start_block_hash: hash(0), block_limit: 10 };
+        let mut p = RangeProgress::new(range.clone());
+        for height in 1..=3 {
+            p.started(height, hash(height));
+        }
+        // Only a prefix of the range fetched completely counts.
+        p.fetched(2);
+        assert_eq!((hash(0), 0, 0), (p.last_hash, p.last_height, p.blocks_count));
+        p.fetched(1);
+        assert_eq!((hash(2), 2, 2), (p.last_hash, p.last_height, p.blocks_count));
+        p.headers_done();
+        assert!(!p.done);
+        p.fetched(3);
+        assert_eq!((hash(3), 3, 3), (p.last_hash, p.last_height, p.blocks_count));
+        assert!(p.done);
+
+        let checkpoint = Checkpoint { ranges: vec![p.clone()], ..Default::default() };
+        let checkpoint: Checkpoint =
+            serde_json::from_str(&serde_json::to_string(&checkpoint).unwrap()).unwrap();
+        let resumed = checkpoint.ranges(&[range]).unwrap();
+        assert_eq!(
+            vec![(hash(3), 3, 3, true)],
+            resumed
+                .iter()
+                .map(|p| (p.last_hash, p.last_height, p.blocks_count, p.done))
+                .collect::<Vec<_>>()
+        );
+        assert!(checkpoint.ranges(&[]).is_err());
+    }
+}
@@ -1,16 +0,0 @@
-pub mod ctx;
-mod once;
-mod rate_limiter;
-pub mod scope;
-pub mod weak_map;
-
-#[cfg(test)]
-mod ctx_test;
-#[cfg(test)]
-mod scope_test;
-
-pub use ctx::{Ctx, CtxWithCancel};
-pub use once::Once;
-pub use rate_limiter::RateLimiter;
-pub use scope::Scope;
-pub use weak_map::WeakMap;
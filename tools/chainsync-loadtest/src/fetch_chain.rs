@@ -1,97 +1,374 @@
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
-use crate::concurrency::{Ctx, Scope};
+use crate::checkpoint::{Checkpoint, RangeProgress};
+use crate::latency;
 use crate::network;
-use anyhow::Context;
-use log::info;
+use anyhow::{anyhow, Context};
+use log::{info, warn};
+use near_async::{Ctx, Scope};
+use rand::seq::SliceRandom;
 use std::sync::atomic::Ordering;
 use tokio::time;
 
 use near_primitives::hash::CryptoHash;
+use near_primitives::syncing::get_num_state_parts;
+use near_primitives::types::ShardId;
 
-// run() fetches the chain (headers,blocks and chunks)
-// starting with block having hash = <start_block_hash> and
-// ending with the current tip of the chain (snapshotted once
-// at the start of the routine, so that the amount of work
-// is bounded).
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Fetch headers, blocks and chunks.
+    Full,
+    /// Fetch only the headers, to measure the header sync throughput
+    /// in isolation from the block download.
+    Headers,
+    /// Fetch the state header and parts of a shard, like state sync does.
+    StateSync,
+    /// Flood the peers given by --only-peers with requests, to see when
+    /// and how they throttle, ban or degrade.
+    Flood,
+}
+
+// log_stats() logs the network stats periodically.
+async fn log_stats(ctx: Ctx, network: Arc<network::Network>) -> anyhow::Result<()> {
+    let ctx = ctx.with_label("stats");
+    loop {
+        info!("stats = {:?}", network.stats);
+        ctx.wait(time::Duration::from_secs(2)).await?;
+    }
+}
+
+// save_checkpoints() saves the progress of the run periodically.
+async fn save_checkpoints(
+    ctx: Ctx,
+    path: PathBuf,
+    ranges: Vec<Arc<Mutex<RangeProgress>>>,
+    network: Arc<network::Network>,
+) -> anyhow::Result<()> {
+    let ctx = ctx.with_label("checkpoint");
+    loop {
+        ctx.wait(time::Duration::from_secs(10)).await?;
+        Checkpoint::save(&path, &ranges, &network.header_latencies)?;
+    }
+}
+
+// Range of the blocks to fetch: <block_limit> blocks starting with
+// the block having hash = <start_block_hash>.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub start_block_hash: CryptoHash,
+    pub block_limit: u64,
+}
+
+impl std::str::FromStr for Range {
+    type Err = anyhow::Error;
+
+    // Parses "<start_block_hash>:<block_limit>".
+    fn from_str(s: &str) -> anyhow::Result<Range> {
+        let (hash, limit) =
+            s.split_once(':').context("expected <start_block_hash>:<block_limit>")?;
+        return Ok(Range {
+            start_block_hash: hash.parse::<CryptoHash>().map_err(|x| anyhow!(x.to_string()))?,
+            block_limit: limit.parse().context("invalid block limit")?,
+        });
+    }
+}
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    /// Fetch the blocks of each range in order, as soon as their headers
+    /// are fetched.
+    Sequential,
+    /// Fetch the headers of all the ranges first, and then the blocks in
+    /// a random order, to stress the block caches of the peers like the
+    /// spikes of the syncing nodes do.
+    Random,
+}
+
+// A block to fetch: the progress of its range, its height and hash.
+type BlockToFetch = (Arc<Mutex<RangeProgress>>, u64, CryptoHash);
+
+// run() fetches the chain (headers,blocks and chunks) in the given
+// ranges, concurrently, continuing from where their progress got. Each
+// range ends after <block_limit> blocks or with the current tip of the
+// chain (snapshotted once at the start of the routine, so that the amount
+// of work is bounded). In Mode::Headers only the headers are fetched.
+// The progress is saved periodically to <checkpoint_path>, and removed
+// from there once the run completes.
 pub async fn run(
     ctx: Ctx,
     network: Arc<network::Network>,
-    start_block_hash: CryptoHash,
-    block_limit: u64,
+    ranges: Vec<RangeProgress>,
+    mode: Mode,
+    pattern: Pattern,
+    checkpoint_path: PathBuf,
 ) -> anyhow::Result<()> {
     info!("SYNC start");
     let peers = network.info(&ctx).await?;
     let target_height = peers.highest_height_peers[0].chain_info.height as i64;
     info!("SYNC target_height = {}", target_height);
 
+    let ranges: Vec<_> = ranges.into_iter().map(|r| Arc::new(Mutex::new(r))).collect();
     let start_time = time::Instant::now();
     let res = Scope::run(&ctx, {
         let network = network.clone();
+        let ranges = ranges.clone();
+        let checkpoint_path = checkpoint_path.clone();
         |ctx, s| async move {
             s.spawn_weak({
                 let network = network.clone();
-                |ctx| async move {
-                    let ctx = ctx.with_label("stats");
-                    loop {
-                        info!("stats = {:?}", network.stats);
-                        ctx.wait(time::Duration::from_secs(2)).await?;
-                    }
-                }
+                |ctx| log_stats(ctx, network)
+            });
+            s.spawn_weak({
+                let network = network.clone();
+                let ranges = ranges.clone();
+                |ctx| save_checkpoints(ctx, checkpoint_path, ranges, network)
             });
 
-            let mut last_hash = start_block_hash;
-            let mut last_height = 0;
-            let mut blocks_count = 0;
-            while last_height < target_height {
-                // Fetch the next batch of headers.
-                let mut headers = network.fetch_block_headers(&ctx, &last_hash).await?;
-                headers.sort_by_key(|h| h.height());
-                let last_header = headers.last().context("no headers")?;
-                last_hash = last_header.hash().clone();
-                last_height = last_header.height() as i64;
-                info!(
-                    "SYNC last_height = {}, {} headers left",
-                    last_height,
-                    target_height - last_height
-                );
-                for h in headers {
-                    blocks_count += 1;
-                    if blocks_count == block_limit {
-                        return anyhow::Ok(());
-                    }
-                    s.spawn({
-                        let network = network.clone();
-                        |ctx, s| async move {
-                            let block = network.fetch_block(&ctx, h.hash()).await?;
-                            for ch in block.chunks().iter() {
-                                let ch = ch.clone();
-                                let network = network.clone();
-                                s.spawn(|ctx, _s| async move {
-                                    network.fetch_chunk(&ctx, &ch).await?;
-                                    anyhow::Ok(())
-                                });
-                            }
-                            anyhow::Ok(())
+            // With Pattern::Random, the hashes of the blocks of all the
+            // ranges are collected first.
+            let hashes = Arc::new(Mutex::new(vec![]));
+            Scope::run(&ctx, {
+                let network = network.clone();
+                let hashes = hashes.clone();
+                |_ctx, s| async move {
+                    for progress in ranges {
+                        if progress.lock().unwrap().done {
+                            continue;
                         }
-                    });
+                        s.spawn({
+                            let network = network.clone();
+                            let hashes = match pattern {
+                                Pattern::Sequential => None,
+                                Pattern::Random => Some(hashes.clone()),
+                            };
+                            move |ctx, s| {
+                                fetch_range(ctx, s, network, progress, target_height, mode, hashes)
+                            }
+                        });
+                    }
+                    anyhow::Ok(())
                 }
+            })
+            .await?;
+            let mut hashes = std::mem::take(&mut *hashes.lock().unwrap());
+            hashes.shuffle(&mut rand::thread_rng());
+            for (progress, height, hash) in hashes {
+                s.spawn({
+                    let network = network.clone();
+                    move |ctx, _s| fetch_block(ctx, network, progress, height, hash)
+                });
             }
             anyhow::Ok(())
         }
     })
     .await;
+    // Keep the progress of an interrupted run, so that it can be resumed.
+    let checkpoint_res = match &res {
+        Ok(()) if checkpoint_path.exists() => std::fs::remove_file(&checkpoint_path)
+            .with_context(|| format!("failed to remove {}", checkpoint_path.display())),
+        Ok(()) => Ok(()),
+        Err(_) => Checkpoint::save(&checkpoint_path, &ranges, &network.header_latencies),
+    };
+    match checkpoint_res {
+        Ok(()) if res.is_err() => info!("progress saved to {}", checkpoint_path.display()),
+        Ok(()) => {}
+        Err(err) => warn!("checkpoint: {:#}", err),
+    }
     let stop_time = time::Instant::now();
     let total_time = stop_time - start_time;
     let t = total_time.as_secs_f64();
     let sent = network.stats.msgs_sent.load(Ordering::Relaxed);
     let headers = network.stats.header_done.load(Ordering::Relaxed);
+    let headers_fetched = network.stats.headers_fetched.load(Ordering::Relaxed);
     let blocks = network.stats.block_done.load(Ordering::Relaxed);
     let chunks = network.stats.chunk_done.load(Ordering::Relaxed);
     info!("running time: {:.2}s", t);
     info!("average QPS: {:.2}", (sent as f64) / t);
     info!("fetched {} header batches ({:.2} per second)", headers, headers as f64 / t);
+    info!("fetched {} headers ({:.2} per second)", headers_fetched, headers_fetched as f64 / t);
+    if mode == Mode::Headers {
+        for l in network.header_latencies.lock().unwrap().summary() {
+            info!(
+                "peer {}: {} responses, latency p50 = {:?}, p90 = {:?}, p99 = {:?}",
+                l.peer_id, l.responses, l.p50, l.p90, l.p99
+            );
+        }
+        return res;
+    }
     info!("fetched {} blocks ({:.2} per second)", blocks, blocks as f64 / t);
     info!("fetched {} chunks ({:.2} per second)", chunks, chunks as f64 / t);
+    info!(
+        "fetched {} chunk parts and {} receipt proofs, {} chunk responses missing some parts",
+        network.stats.chunk_parts_fetched.load(Ordering::Relaxed),
+        network.stats.chunk_receipts_fetched.load(Ordering::Relaxed),
+        network.stats.chunk_responses_incomplete.load(Ordering::Relaxed),
+    );
+    for (name, latencies) in
+        [("block", &network.block_latencies), ("chunk", &network.chunk_latencies)]
+    {
+        if let Some(l) = latency::summarize(&latencies.lock().unwrap()) {
+            info!("{} fetch latency p50 = {:?}, p90 = {:?}, p99 = {:?}", name, l.p50, l.p90, l.p99);
+        }
+    }
     return res;
 }
+
+// fetch_range() fetches the headers of the range and spawns the fetching of
+// the blocks as soon as their headers are fetched, unless <hashes> is set:
+// then it collects the blocks there instead. It starts after the last block
+// fetched according to <progress>.
+async fn fetch_range(
+    ctx: Ctx,
+    s: Arc<Scope>,
+    network: Arc<network::Network>,
+    progress: Arc<Mutex<RangeProgress>>,
+    target_height: i64,
+    mode: Mode,
+    hashes: Option<Arc<Mutex<Vec<BlockToFetch>>>>,
+) -> anyhow::Result<()> {
+    let (block_limit, mut last_hash, mut last_height, mut blocks_count) = {
+        let p = progress.lock().unwrap();
+        (p.range.block_limit, p.last_hash, p.last_height as i64, p.blocks_count)
+    };
+    while last_height < target_height {
+        // Fetch the next batch of headers.
+        let mut headers = network.fetch_block_headers(&ctx, &last_hash).await?;
+        headers.sort_by_key(|h| h.height());
+        let last_header = headers.last().context("no headers")?;
+        last_hash = last_header.hash().clone();
+        last_height = last_header.height() as i64;
+        info!("SYNC last_height = {}, {} headers left", last_height, target_height - last_height);
+        for h in headers {
+            blocks_count += 1;
+            if blocks_count == block_limit {
+                progress.lock().unwrap().headers_done();
+                return Ok(());
+            }
+            let (height, hash) = (h.height(), h.hash().clone());
+            progress.lock().unwrap().started(height, hash);
+            if mode == Mode::Headers {
+                progress.lock().unwrap().fetched(height);
+                continue;
+            }
+            match &hashes {
+                Some(hashes) => hashes.lock().unwrap().push((progress.clone(), height, hash)),
+                None => s.spawn({
+                    let network = network.clone();
+                    let progress = progress.clone();
+                    move |ctx, _s| fetch_block(ctx, network, progress, height, hash)
+                }),
+            }
+        }
+    }
+    progress.lock().unwrap().headers_done();
+    return Ok(());
+}
+
+// fetch_block() fetches the block with hash = <hash> and then all of its
+// chunks, with their receipts, and records the block as fetched in
+// <progress>.
+async fn fetch_block(
+    ctx: Ctx,
+    network: Arc<network::Network>,
+    progress: Arc<Mutex<RangeProgress>>,
+    height: u64,
+    hash: CryptoHash,
+) -> anyhow::Result<()> {
+    let block = network.fetch_block(&ctx, &hash).await?;
+    let num_shards = block.chunks().len() as u64;
+    let chunks: Vec<_> = block.chunks().iter().cloned().collect();
+    Scope::run(&ctx, |_ctx, s| async move {
+        for ch in chunks {
+            let network = network.clone();
+            s.spawn(move |ctx, _s| async move {
+                network.fetch_chunk(&ctx, &ch, num_shards).await?;
+                anyhow::Ok(())
+            });
+        }
+        anyhow::Ok(())
+    })
+    .await?;
+    progress.lock().unwrap().fetched(height);
+    return Ok(());
+}
+
+// run_state_sync() fetches the state header of the shard <shard_id>
+// as of the block with hash = <sync_hash>, and then its parts
+// (at most <part_limit> of them).
+pub async fn run_state_sync(
+    ctx: Ctx,
+    network: Arc<network::Network>,
+    sync_hash: CryptoHash,
+    shard_id: ShardId,
+    part_limit: u64,
+) -> anyhow::Result<()> {
+    info!("STATE SYNC start");
+    network.info(&ctx).await?;
+
+    let start_time = time::Instant::now();
+    let res = Scope::run(&ctx, {
+        let network = network.clone();
+        |ctx, s| async move {
+            s.spawn_weak({
+                let network = network.clone();
+                |ctx| log_stats(ctx, network)
+            });
+
+            let header = network.fetch_state_header(&ctx, shard_id, &sync_hash).await?;
+            let num_parts = get_num_state_parts(header.state_root_node().memory_usage);
+            info!("STATE SYNC num_parts = {}", num_parts);
+            for part_id in 0..num_parts.min(part_limit) {
+                s.spawn({
+                    let network = network.clone();
+                    move |ctx, _s| async move {
+                        network.fetch_state_part(&ctx, shard_id, &sync_hash, part_id).await?;
+                        anyhow::Ok(())
+                    }
+                });
+            }
+            anyhow::Ok(())
+        }
+    })
+    .await;
+    let t = (time::Instant::now() - start_time).as_secs_f64();
+    let sent = network.stats.msgs_sent.load(Ordering::Relaxed);
+    let parts = network.stats.state_part_done.load(Ordering::Relaxed);
+    let bytes = network.stats.state_part_bytes.load(Ordering::Relaxed);
+    let requests = network.stats.state_requests_sent.load(Ordering::Relaxed);
+    let ok = network.stats.state_responses_ok.load(Ordering::Relaxed);
+    let empty = network.stats.state_responses_empty.load(Ordering::Relaxed);
+    info!("running time: {:.2}s", t);
+    info!("average QPS: {:.2}", (sent as f64) / t);
+    info!(
+        "fetched {} state parts ({:.2} per second, {:.2} MB/s)",
+        parts,
+        parts as f64 / t,
+        bytes as f64 / t / 1e6
+    );
+    // A request fails if the peer responds without the data or doesn't
+    // respond at all.
+    info!(
+        "sent {} state requests, {} responses with data, {} empty responses, failure rate {:.2}%",
+        requests,
+        ok,
+        empty,
+        100. * requests.saturating_sub(ok) as f64 / requests.max(1) as f64
+    );
+    return res;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let hash = CryptoHash::hash_bytes(b"1");
+        let range: Range = format!("{}:1000", hash).parse().unwrap();
+        assert_eq!(Range { start_block_hash: hash, block_limit: 1000 }, range);
+        assert!(format!("{}", hash).parse::<Range>().is_err());
+        assert!(format!("{}:x", hash).parse::<Range>().is_err());
+        assert!("x:1000".parse::<Range>().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use near_async::{Ctx, Scope};
+use near_network::types::NetworkRequests;
+use near_network_primitives::types::AccountOrPeerIdOrHash;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::ShardId;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use tokio::time;
+
+use crate::network::Network;
+
+// Length of the windows the results of a flood are aggregated over.
+const WINDOW: time::Duration = time::Duration::from_secs(5);
+
+// The target is considered degraded once the fraction of the requests of
+// a window which got a response falls below DEGRADED_RATIO of the best
+// window so far.
+const DEGRADED_RATIO: f64 = 0.5;
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloodRequest {
+    /// BlockRequests for the block given by --start-block-hash.
+    Block,
+    /// StateRequestParts for the first part of the state of the shard given
+    /// by --shard-id, as of the block given by --sync-hash.
+    StatePart,
+}
+
+// FloodConfig describes the requests to flood the targets with.
+#[derive(Clone, Copy, Debug)]
+pub struct FloodConfig {
+    pub request: FloodRequest,
+    // The block to request, or the block to request the state as of.
+    pub hash: CryptoHash,
+    pub shard_id: ShardId,
+    // Number of the requests awaiting a response at any time.
+    pub concurrency: usize,
+    pub duration: time::Duration,
+}
+
+// FloodWindow summarizes the flood over a single WINDOW.
+#[derive(serde::Serialize, Debug, Clone, Default, PartialEq)]
+pub struct FloodWindow {
+    pub start_secs: f64,
+    pub requests_sent: u64,
+    pub responses: u64,
+    // State responses without the part, i.e. refused by the target.
+    pub empty_responses: u64,
+    pub timeouts: u64,
+    // Whether any of the targets was connected at the end of the window.
+    pub target_connected: bool,
+}
+
+impl FloodWindow {
+    fn response_rate(&self) -> f64 {
+        return self.responses as f64 / self.requests_sent.max(1) as f64;
+    }
+}
+
+// FloodReport describes when and how the targets reacted to the flood.
+#[derive(serde::Serialize, Debug, Default)]
+pub struct FloodReport {
+    pub windows: Vec<FloodWindow>,
+    // Start of the first window in which the response rate fell below
+    // DEGRADED_RATIO of the best window before it.
+    pub degraded_at_secs: Option<f64>,
+    // Start of the first window at the end of which none of the targets
+    // was connected, e.g. because they banned us.
+    pub disconnected_at_secs: Option<f64>,
+}
+
+impl FloodReport {
+    // add() records the window, and whether the targets degraded or
+    // disconnected in it for the first time.
+    fn add(&mut self, window: FloodWindow) {
+        let best_rate = self.windows.iter().map(FloodWindow::response_rate).fold(0., f64::max);
+        if self.degraded_at_secs.is_none()
+            && window.requests_sent > 0
+            && window.response_rate() < DEGRADED_RATIO * best_rate
+        {
+            warn!(
+                "FLOOD targets degraded at {:.0}s: response rate {:.2} (best {:.2})",
+                window.start_secs,
+                window.response_rate(),
+                best_rate
+            );
+            self.degraded_at_secs = Some(window.start_secs);
+        }
+        if self.disconnected_at_secs.is_none() && !window.target_connected {
+            warn!("FLOOD targets disconnected at {:.0}s", window.start_secs);
+            self.disconnected_at_secs = Some(window.start_secs);
+        }
+        self.windows.push(window);
+    }
+}
+
+// run() floods the peers selected by the peer filter with requests for
+// config.duration, from config.concurrency workers, each of which sends
+// a request as soon as it gets a response to the previous one (or the
+// response times out). The results are recorded in <report> every WINDOW.
+pub async fn run(
+    ctx: Ctx,
+    network: Arc<Network>,
+    config: FloodConfig,
+    report: Arc<Mutex<FloodReport>>,
+) -> anyhow::Result<()> {
+    info!("FLOOD start, {} workers for {:?}", config.concurrency, config.duration);
+    network.info(&ctx).await?;
+    let ctx = ctx.with_timeout(config.duration);
+    let start_time = time::Instant::now();
+    Scope::run(&ctx, {
+        let network = network.clone();
+        |ctx, s| async move {
+            s.spawn_weak({
+                let network = network.clone();
+                |ctx| monitor(ctx, network, start_time, report)
+            });
+            for _ in 0..config.concurrency {
+                s.spawn({
+                    let network = network.clone();
+                    move |ctx, _s| worker(ctx, network, config)
+                });
+            }
+            anyhow::Ok(())
+        }
+    })
+    .await?;
+    info!("FLOOD done");
+    return Ok(());
+}
+
+// worker() sends the requests one at a time to a random target, until ctx
+// expires. It pauses while no target is connected.
+async fn worker(ctx: Ctx, network: Arc<Network>, config: FloodConfig) -> anyhow::Result<()> {
+    while ctx.err().is_none() {
+        let peer = match network.info(&ctx).await {
+            Ok(_) => network.connected_peers().choose(&mut thread_rng()).cloned(),
+            // The flood is over.
+            Err(_) => return Ok(()),
+        };
+        let peer = match peer {
+            Some(peer) => peer,
+            None => continue,
+        };
+        let target = peer.peer_info.id.clone();
+        network.send(
+            &peer,
+            match config.request {
+                FloodRequest::Block => {
+                    NetworkRequests::BlockRequest { hash: config.hash, peer_id: target }
+                }
+                FloodRequest::StatePart => NetworkRequests::StateRequestPart {
+                    shard_id: config.shard_id,
+                    sync_hash: config.hash,
+                    part_id: 0,
+                    target: AccountOrPeerIdOrHash::PeerId(target),
+                },
+            },
+        );
+        match time::timeout(network.request_timeout, ctx.wrap(network.responses.notified())).await {
+            Ok(Ok(())) => network.stats.flood_responses.fetch_add(1, Ordering::Relaxed),
+            Ok(Err(_)) => return Ok(()),
+            Err(_) => network.stats.flood_timeouts.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+    return Ok(());
+}
+
+// monitor() records a FloodWindow every WINDOW.
+async fn monitor(
+    ctx: Ctx,
+    network: Arc<Network>,
+    start_time: time::Instant,
+    report: Arc<Mutex<FloodReport>>,
+) -> anyhow::Result<()> {
+    let load = |x: &std::sync::atomic::AtomicU64| x.load(Ordering::Relaxed);
+    let totals = || {
+        let stats = &network.stats;
+        return FloodWindow {
+            start_secs: 0.,
+            requests_sent: load(&stats.msgs_sent),
+            responses: load(&stats.flood_responses),
+            empty_responses: load(&stats.state_responses_empty),
+            timeouts: load(&stats.flood_timeouts),
+            target_connected: true,
+        };
+    };
+    let mut prev = totals();
+    let mut window_start = start_time;
+    loop {
+        // Record the last, partial window as well.
+        let done = ctx.wait_until(window_start + WINDOW).await.is_err();
+        let now = totals();
+        let window = FloodWindow {
+            start_secs: (window_start - start_time).as_secs_f64(),
+            requests_sent: now.requests_sent - prev.requests_sent,
+            responses: now.responses - prev.responses,
+            empty_responses: now.empty_responses - prev.empty_responses,
+            timeouts: now.timeouts - prev.timeouts,
+            target_connected: !network.connected_peers().is_empty(),
+        };
+        info!(
+            "FLOOD {:.0}s: {} requests sent, {} responses ({} empty), {} timeouts, connected = {}",
+            window.start_secs,
+            window.requests_sent,
+            window.responses,
+            window.empty_responses,
+            window.timeouts,
+            window.target_connected
+        );
+        report.lock().unwrap().add(window);
+        if done {
+            return Ok(());
+        }
+        prev = now;
+        window_start += WINDOW;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_flood_report() {
+        let window = |start_secs, requests_sent, responses, target_connected| FloodWindow {
+            start_secs,
+            requests_sent,
+            responses,
+            target_connected,
+            ..Default::default()
+        };
+        let mut report = FloodReport::default();
+        report.add(window(0., 100, 50, true));
+        report.add(window(5., 100, 90, true));
+        // Within DEGRADED_RATIO of the best window.
+        report.add(window(10., 100, 50, true));
+        assert_eq!((None, None), (report.degraded_at_secs, report.disconnected_at_secs));
+        report.add(window(15., 100, 40, true));
+        report.add(window(20., 0, 0, false));
+        report.add(window(25., 100, 0, false));
+        assert_eq!((Some(15.), Some(20.)), (report.degraded_at_secs, report.disconnected_at_secs));
+        assert_eq!(6, report.windows.len());
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
+use tokio::time;
+
+// PeerLatency summarizes the latencies of the responses of a single peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerLatency {
+    pub peer_id: PeerId,
+    pub responses: usize,
+    pub p50: time::Duration,
+    pub p90: time::Duration,
+    pub p99: time::Duration,
+}
+
+// Latencies measures the time between sending a request to a peer and
+// receiving the response from that peer. Requests are identified by the hash
+// they were sent for. If the same request is sent to the same peer more than
+// once, the latency is measured from the most recent send.
+#[derive(serde::Serialize, serde::Deserialize, Default, Clone)]
+pub struct Latencies {
+    #[serde(skip)]
+    sent: HashMap<(CryptoHash, PeerId), time::Instant>,
+    received: HashMap<PeerId, Vec<time::Duration>>,
+}
+
+impl Latencies {
+    pub fn sent(&mut self, hash: CryptoHash, peer_id: PeerId, now: time::Instant) {
+        self.sent.insert((hash, peer_id), now);
+    }
+
+    // received() records the latency of the response, unless no matching
+    // request has been sent (or the response is a duplicate).
+    pub fn received(&mut self, hash: CryptoHash, peer_id: PeerId, now: time::Instant) {
+        if let Some(sent) = self.sent.remove(&(hash, peer_id.clone())) {
+            self.received.entry(peer_id).or_default().push(now - sent);
+        }
+    }
+
+    // summary() returns the latency percentiles of every peer which responded
+    // at least once, the fastest (by median) peers first.
+    pub fn summary(&self) -> Vec<PeerLatency> {
+        let mut res: Vec<_> = self
+            .received
+            .iter()
+            .filter_map(|(peer_id, latencies)| {
+                let s = summarize(latencies)?;
+                Some(PeerLatency {
+                    peer_id: peer_id.clone(),
+                    responses: s.count,
+                    p50: s.p50,
+                    p90: s.p90,
+                    p99: s.p99,
+                })
+            })
+            .collect();
+        res.sort_by_key(|l| l.p50);
+        res
+    }
+}
+
+// Summary contains the percentiles of a set of latencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub p50: time::Duration,
+    pub p90: time::Duration,
+    pub p99: time::Duration,
+}
+
+// summarize() returns the percentiles of the latencies, or None if there
+// are no latencies.
+pub fn summarize(latencies: &[time::Duration]) -> Option<Summary> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort();
+    return Some(Summary {
+        count: sorted.len(),
+        p50: percentile(&sorted, 0.5),
+        p90: percentile(&sorted, 0.9),
+        p99: percentile(&sorted, 0.99),
+    });
+}
+
+// percentile() returns the nearest-rank percentile p (in [0,1]) of
+// a non-empty sorted slice.
+fn percentile(sorted: &[time::Duration], p: f64) -> time::Duration {
+    let rank = (p * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+
+    fn peer_id(seed: &str) -> PeerId {
+        PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed).public_key())
+    }
+
+    #[test]
+    fn test_percentile() {
+        let ms = |x| time::Duration::from_millis(x);
+        let sorted: Vec<_> = (1..=100).map(ms).collect();
+        assert_eq!(ms(50), percentile(&sorted, 0.5));
+        assert_eq!(ms(90), percentile(&sorted, 0.9));
+        assert_eq!(ms(99), percentile(&sorted, 0.99));
+        assert_eq!(ms(1), percentile(&sorted, 0.));
+        assert_eq!(ms(7), percentile(&[ms(7)], 0.99));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let ms = |x| time::Duration::from_millis(x);
+        assert_eq!(None, summarize(&[]));
+        let latencies: Vec<_> = (1..=100).rev().map(ms).collect();
+        assert_eq!(
+            Some(Summary { count: 100, p50: ms(50), p90: ms(90), p99: ms(99) }),
+            summarize(&latencies)
+        );
+    }
+
+    #[test]
+    fn test_latencies() {
+        let (a, b) = (peer_id("a"), peer_id("b"));
+        let (h1, h2) = (CryptoHash::hash_bytes(b"1"), CryptoHash::hash_bytes(b"2"));
+        let t0 = time::Instant::now();
+        let ms = |x| time::Duration::from_millis(x);
+        let mut l = Latencies::default();
+        l.sent(h1, a.clone(), t0);
+        l.sent(h1, b.clone(), t0);
+        l.sent(h2, a.clone(), t0 + ms(10));
+        l.received(h1, b.clone(), t0 + ms(5));
+        l.received(h1, a.clone(), t0 + ms(30));
+        l.received(h2, a.clone(), t0 + ms(30));
+        // Duplicated and unsolicited responses are ignored.
+        l.received(h1, b.clone(), t0 + ms(40));
+        l.received(h2, b.clone(), t0 + ms(40));
+        let summary = l.summary();
+        assert_eq!(2, summary.len());
+        assert_eq!(
+            (b, 1, ms(5)),
+            (summary[0].peer_id.clone(), summary[0].responses, summary[0].p50)
+        );
+        assert_eq!(
+            (a, 2, ms(20), ms(30)),
+            (summary[1].peer_id.clone(), summary[1].responses, summary[1].p50, summary[1].p99)
+        );
+    }
+}
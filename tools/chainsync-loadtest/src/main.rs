@@ -1,8 +1,12 @@
-mod concurrency;
+mod checkpoint;
 mod fetch_chain;
+mod flood;
+mod latency;
 mod network;
+mod peers;
+mod report;
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use actix::{Actor, Arbiter};
 use anyhow::{anyhow, Context};
@@ -10,9 +14,9 @@ use clap::Parser;
 use near_store::test_utils::create_test_store;
 use openssl_probe;
 
-use concurrency::{Ctx, Scope};
 use network::{FakeClientActor, Network};
 
+use near_async::{Ctx, Scope};
 use near_chain_configs::Genesis;
 use near_network::test_utils::NetworkRecipient;
 use near_network::PeerManagerActor;
@@ -21,19 +25,27 @@ use near_primitives::hash::CryptoHash;
 use nearcore::config;
 use nearcore::config::NearConfig;
 
-pub fn start_with_config(config: NearConfig, qps_limit: u32) -> anyhow::Result<Arc<Network>> {
+pub fn start_with_config(
+    config: NearConfig,
+    qps_limit: u32,
+    parts_per_chunk: u64,
+    peer_filter: peers::PeerFilter,
+    client_block_delay: tokio::time::Duration,
+) -> anyhow::Result<Arc<Network>> {
     config.network_config.verify().context("start_with_config")?;
     let store = create_test_store();
 
     let network_adapter = Arc::new(NetworkRecipient::default());
-    let network = Network::new(&config, network_adapter.clone(), qps_limit);
+    let network =
+        Network::new(&config, network_adapter.clone(), qps_limit, parts_per_chunk, peer_filter);
     let client_actor = FakeClientActor::start_in_arbiter(&Arbiter::new().handle(), {
         let network = network.clone();
-        move |_| FakeClientActor::new(network)
+        move |_| FakeClientActor::new(network, client_block_delay)
     });
 
     let network_actor = PeerManagerActor::start_in_arbiter(&Arbiter::new().handle(), move |_ctx| {
         PeerManagerActor::new(
+            near_network_primitives::time::Clock::real(),
             store,
             config.network_config,
             client_actor.clone().recipient(),
@@ -67,28 +79,146 @@ fn download_configs(chain_id: &str, dir: &std::path::Path) -> anyhow::Result<Nea
 struct Cmd {
     #[clap(long)]
     pub chain_id: String,
+    /// Required unless --ranges or --mode=state-sync is set.
     #[clap(long)]
-    pub start_block_hash: String,
+    pub start_block_hash: Option<String>,
     #[clap(long, default_value = "200")]
     pub qps_limit: u32,
     #[clap(long, default_value = "2000")]
     pub block_limit: u64,
+    /// Total number of the parts of a chunk, i.e. the number of
+    /// the block producer seats of the chain.
+    #[clap(long, default_value = "100")]
+    pub parts_per_chunk: u64,
+    /// Ranges of blocks to fetch concurrently instead of a single one,
+    /// e.g. "hash1:1000,hash2:1000".
+    #[clap(long, use_value_delimiter = true)]
+    pub ranges: Vec<fetch_chain::Range>,
+    #[clap(long, arg_enum, default_value = "full")]
+    pub mode: fetch_chain::Mode,
+    #[clap(long, arg_enum, default_value = "sequential")]
+    pub pattern: fetch_chain::Pattern,
+    /// Hash of the block to fetch the state as of, with --mode=state-sync.
+    #[clap(long)]
+    pub sync_hash: Option<String>,
+    #[clap(long, default_value = "0")]
+    pub shard_id: u64,
+    #[clap(long, default_value = "1000")]
+    pub part_limit: u64,
+    /// Path to write the JSON report of the results to.
+    #[clap(long)]
+    pub output: Option<std::path::PathBuf>,
+    /// Fail unless that many blocks (headers with --mode=headers, state parts
+    /// with --mode=state-sync) are fetched per second.
+    #[clap(long)]
+    pub min_throughput: Option<f64>,
+    /// Fail if a larger fraction of the requests doesn't complete a fetch.
+    #[clap(long)]
+    pub max_error_rate: Option<f64>,
+    /// Resume the run interrupted with CTRL+C from its checkpoint, instead
+    /// of starting from the beginning of the ranges.
+    #[clap(long)]
+    pub resume: bool,
+    /// Send the requests only to these peers, given by IP, peer id or
+    /// "<peer id>@<ip>:<port>" (to dial the peer directly).
+    #[clap(long, use_value_delimiter = true)]
+    pub only_peers: Vec<peers::PeerMatcher>,
+    /// Never send the requests to these peers, given by IP or peer id.
+    #[clap(long, use_value_delimiter = true)]
+    pub exclude_peers: Vec<peers::PeerMatcher>,
+    /// Delay the processing of every received block by that many
+    /// milliseconds, to simulate a slow client.
+    #[clap(long, default_value = "0")]
+    pub client_delay_ms: u64,
+    /// Requests to send with --mode=flood: BlockRequests for the block given
+    /// by --start-block-hash, or StateRequestParts as of --sync-hash.
+    #[clap(long, arg_enum, default_value = "block")]
+    pub flood_request: flood::FloodRequest,
+    /// Number of the requests awaiting a response at any time with
+    /// --mode=flood.
+    #[clap(long, default_value = "32")]
+    pub flood_concurrency: usize,
+    /// Duration of the flood with --mode=flood.
+    #[clap(long, default_value = "60")]
+    pub flood_duration_secs: u64,
 }
 
 impl Cmd {
     fn parse_and_run() -> anyhow::Result<()> {
         let cmd = Self::parse();
-        let start_block_hash =
-            cmd.start_block_hash.parse::<CryptoHash>().map_err(|x| anyhow!(x.to_string()))?;
+        let parse_hash =
+            |hash: &str| hash.parse::<CryptoHash>().map_err(|x| anyhow!(x.to_string()));
+        let mut ranges = cmd.ranges.clone();
+        if let Some(hash) = &cmd.start_block_hash {
+            if !ranges.is_empty() {
+                return Err(anyhow!("--start-block-hash and --ranges are mutually exclusive"));
+            }
+            ranges.push(fetch_chain::Range {
+                start_block_hash: parse_hash(hash)?,
+                block_limit: cmd.block_limit,
+            });
+        }
+        // The block to fetch the state as of, or to flood the targets with
+        // the requests for.
+        let hash = match cmd.mode {
+            fetch_chain::Mode::StateSync => parse_hash(
+                cmd.sync_hash.as_ref().context("--sync-hash is required with --mode=state-sync")?,
+            )?,
+            fetch_chain::Mode::Flood => {
+                // Flooding the peers gets us banned, so it is meant only
+                // for the nodes the operator of the run owns.
+                if cmd.only_peers.is_empty() {
+                    return Err(anyhow!("--mode=flood requires --only-peers"));
+                }
+                match cmd.flood_request {
+                    flood::FloodRequest::Block => {
+                        ranges
+                            .first()
+                            .context("--start-block-hash is required with --flood-request=block")?
+                            .start_block_hash
+                    }
+                    flood::FloodRequest::StatePart => parse_hash(
+                        cmd.sync_hash
+                            .as_ref()
+                            .context("--sync-hash is required with --flood-request=state-part")?,
+                    )?,
+                }
+            }
+            _ if ranges.is_empty() => {
+                return Err(anyhow!("--start-block-hash or --ranges is required"));
+            }
+            _ => CryptoHash::default(),
+        };
 
         let mut cache_dir = dirs::cache_dir().context("dirs::cache_dir() = None")?;
         cache_dir.push("near_configs");
         cache_dir.push(&cmd.chain_id);
 
+        // The progress of the runs is saved in the checkpoint file,
+        // see fetch_chain::run().
+        let checkpoint_path = cache_dir.join("checkpoint.json");
+        let checkpoint = if cmd.resume
+            && !matches!(cmd.mode, fetch_chain::Mode::StateSync | fetch_chain::Mode::Flood)
+        {
+            checkpoint::Checkpoint::load(&checkpoint_path)?
+        } else {
+            None
+        };
+        let progress = match &checkpoint {
+            Some(checkpoint) => {
+                info!("resuming from {}", checkpoint_path.display());
+                checkpoint.ranges(&ranges).context("--resume")?
+            }
+            None => ranges.into_iter().map(checkpoint::RangeProgress::new).collect(),
+        };
+
         info!("downloading configs for chain {}", cmd.chain_id);
         let home_dir = cache_dir.as_path();
-        let near_config =
+        let mut near_config =
             download_configs(&cmd.chain_id, home_dir).context("Failed to initialize configs")?;
+        let peer_filter =
+            peers::PeerFilter { only: cmd.only_peers.clone(), exclude: cmd.exclude_peers.clone() };
+        near_config.network_config.boot_nodes.extend(peer_filter.boot_nodes());
 
         info!("#boot nodes = {}", near_config.network_config.boot_nodes.len());
         // Dropping Runtime is blocking, while futures should never be blocking.
@@ -99,8 +229,17 @@ impl Cmd {
         let rt_ = Arc::new(tokio::runtime::Runtime::new()?);
         let rt = rt_;
         return actix::System::new().block_on(async move {
-            let network =
-                start_with_config(near_config, cmd.qps_limit).context("start_with_config")?;
+            let network = start_with_config(
+                near_config,
+                cmd.qps_limit,
+                cmd.parts_per_chunk,
+                peer_filter,
+                tokio::time::Duration::from_millis(cmd.client_delay_ms),
+            )
+            .context("start_with_config")?;
+            if let Some(checkpoint) = checkpoint {
+                *network.header_latencies.lock().unwrap() = checkpoint.header_latencies;
+            }
 
             // We execute the chain_sync on a totally separate set of system threads to minimize
             // the interaction with actix.
@@ -111,9 +250,87 @@ impl Cmd {
                         info!("Got CTRL+C, stopping...");
                         return Err(anyhow!("Got CTRL+C"));
                     });
-                    fetch_chain::run(ctx.clone(), network, start_block_hash, cmd.block_limit)
-                        .await?;
+                    network.info(&ctx).await?;
+                    let flood_report = Arc::new(Mutex::new(flood::FloodReport::default()));
+                    let start_time = tokio::time::Instant::now();
+                    let res = match cmd.mode {
+                        fetch_chain::Mode::StateSync => {
+                            fetch_chain::run_state_sync(
+                                ctx.clone(),
+                                network.clone(),
+                                hash,
+                                cmd.shard_id,
+                                cmd.part_limit,
+                            )
+                            .await
+                        }
+                        fetch_chain::Mode::Flood => {
+                            flood::run(
+                                ctx.clone(),
+                                network.clone(),
+                                flood::FloodConfig {
+                                    request: cmd.flood_request,
+                                    hash,
+                                    shard_id: cmd.shard_id,
+                                    concurrency: cmd.flood_concurrency,
+                                    duration: tokio::time::Duration::from_secs(
+                                        cmd.flood_duration_secs,
+                                    ),
+                                },
+                                flood_report.clone(),
+                            )
+                            .await
+                        }
+                        _ => {
+                            fetch_chain::run(
+                                ctx.clone(),
+                                network.clone(),
+                                progress,
+                                cmd.mode,
+                                cmd.pattern,
+                                checkpoint_path,
+                            )
+                            .await
+                        }
+                    };
+                    let mut report = report::Report::new(
+                        cmd.mode,
+                        &network.stats,
+                        &network.peer_stats.lock().unwrap(),
+                        start_time.elapsed(),
+                        &res,
+                    );
+                    if cmd.mode == fetch_chain::Mode::Flood {
+                        report.flood = Some(std::mem::take(&mut *flood_report.lock().unwrap()));
+                    }
+                    for p in &report.peers {
+                        info!(
+                            "peer {}: {} requests sent, {} responses",
+                            p.peer_id, p.requests_sent, p.responses
+                        );
+                    }
+                    if cmd.client_delay_ms > 0 {
+                        info!(
+                            "{} blocks delayed by {}ms, {} peers disconnected",
+                            report.client_blocks_delayed,
+                            cmd.client_delay_ms,
+                            report.peers_disconnected
+                        );
+                    }
+                    report.check(&report::Thresholds {
+                        min_throughput: cmd.min_throughput,
+                        max_error_rate: cmd.max_error_rate,
+                    });
+                    if let Some(path) = &cmd.output {
+                        let file = std::fs::File::create(path)
+                            .with_context(|| format!("failed to create {}", path.display()))?;
+                        serde_json::to_writer_pretty(file, &report)?;
+                    }
+                    res?;
                     info!("Fetch completed");
+                    if !report.passed() {
+                        return Err(anyhow!("thresholds not met: {}", report.failures.join(", ")));
+                    }
                     anyhow::Ok(())
                 })
                 .await
@@ -141,5 +358,6 @@ fn main() {
     openssl_probe::init_ssl_cert_env_vars();
     if let Err(e) = Cmd::parse_and_run() {
         error!("Cmd::parse_and_run(): {:#}", e);
+        std::process::exit(1);
     }
 }
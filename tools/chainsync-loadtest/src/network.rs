@@ -1,10 +1,13 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use crate::concurrency::{Ctx, Once, RateLimiter, Scope, WeakMap};
+use crate::latency::Latencies;
+use crate::peers::PeerFilter;
+use near_async::{Ctx, Once, RateLimiter, Scope, WeakMap};
 
 use near_network_primitives::types::{
-    AccountIdOrPeerTrackingShard, NetworkViewClientMessages, NetworkViewClientResponses,
-    PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
+    AccountIdOrPeerTrackingShard, AccountOrPeerIdOrHash, NetworkViewClientMessages,
+    NetworkViewClientResponses, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg,
+    PeerInfo,
 };
 
 use actix::{Actor, Context, Handler};
@@ -15,14 +18,18 @@ use near_network::types::{
 };
 use near_primitives::block::{Block, BlockHeader, GenesisId};
 use near_primitives::hash::CryptoHash;
+use near_primitives::network::PeerId;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
+use near_primitives::syncing::ShardStateSyncResponseHeader;
 use near_primitives::time::Clock;
+use near_primitives::types::ShardId;
 use nearcore::config::NearConfig;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
+use std::collections::HashMap;
 use std::future::Future;
 use std::sync::{Arc, Mutex};
-use tokio::sync::oneshot;
+use tokio::sync::{oneshot, Notify};
 use tokio::time;
 
 fn genesis_hash(chain_id: &str) -> CryptoHash {
@@ -45,10 +52,51 @@ pub struct Stats {
 
     pub header_start: AtomicU64,
     pub header_done: AtomicU64,
+    // Number of headers in the fetched header batches.
+    pub headers_fetched: AtomicU64,
     pub block_start: AtomicU64,
     pub block_done: AtomicU64,
     pub chunk_start: AtomicU64,
     pub chunk_done: AtomicU64,
+    // Distinct parts received of the chunks being fetched.
+    pub chunk_parts_fetched: AtomicU64,
+    // Distinct receipt proofs received with the chunks being fetched.
+    pub chunk_receipts_fetched: AtomicU64,
+    // PartialEncodedChunkResponses missing some of the requested parts.
+    pub chunk_responses_incomplete: AtomicU64,
+
+    pub state_header_start: AtomicU64,
+    pub state_header_done: AtomicU64,
+    pub state_part_start: AtomicU64,
+    pub state_part_done: AtomicU64,
+    // Total size of the fetched state parts.
+    pub state_part_bytes: AtomicU64,
+    // StateRequestHeader and StateRequestPart messages sent.
+    pub state_requests_sent: AtomicU64,
+    // State responses containing the requested header or part,
+    // including the duplicates.
+    pub state_responses_ok: AtomicU64,
+    // State responses without a header or a part, i.e. from
+    // peers which couldn't serve the request.
+    pub state_responses_empty: AtomicU64,
+
+    // Blocks whose processing FakeClientActor has delayed artificially.
+    pub client_blocks_delayed: AtomicU64,
+    // Peers which disconnected after having been connected.
+    pub peers_disconnected: AtomicU64,
+
+    // Responses to the requests sent by the flood workers, and the requests
+    // which got no response in time.
+    pub flood_responses: AtomicU64,
+    pub flood_timeouts: AtomicU64,
+}
+
+// PeerStats counts the requests sent to a single peer and its responses.
+// Only the responses with headers and blocks are attributed to peers.
+#[derive(Default, Debug, Clone)]
+pub struct PeerStats {
+    pub requests_sent: u64,
+    pub responses: u64,
 }
 
 // NetworkData contains the mutable private data of the Network struct.
@@ -66,17 +114,38 @@ pub struct Network {
     pub block_headers: Arc<WeakMap<CryptoHash, Once<Vec<BlockHeader>>>>,
     pub blocks: Arc<WeakMap<CryptoHash, Once<Block>>>,
     pub chunks: Arc<WeakMap<ChunkHash, Once<PartialEncodedChunkResponseMsg>>>,
+    pub state_headers: Arc<WeakMap<(ShardId, CryptoHash), Once<ShardStateSyncResponseHeader>>>,
+    // Sizes of the state parts.
+    pub state_parts: Arc<WeakMap<(ShardId, CryptoHash, u64), Once<u64>>>,
+    // Parts and receipts received so far of the chunks being fetched,
+    // until all the parts of a chunk are received.
+    chunk_parts: Mutex<HashMap<ChunkHash, PartialEncodedChunkResponseMsg>>,
+    // Latencies of the responses to the BlockHeadersRequests, per peer.
+    pub header_latencies: Mutex<Latencies>,
+    // Time from the start of a fetch until the block is received.
+    pub block_latencies: Mutex<Vec<time::Duration>>,
+    // Time from the start of a fetch until all the parts of the chunk
+    // are received.
+    pub chunk_latencies: Mutex<Vec<time::Duration>>,
+    pub peer_stats: Mutex<HashMap<PeerId, PeerStats>>,
+    // Notified of every received block and state response, for the flood
+    // workers which don't track the individual requests.
+    pub responses: Notify,
     data: Mutex<NetworkData>,
 
     chain_id: String,
-    // client_config.min_num_peers
+    // client_config.min_num_peers, or 1 if the peers to send the requests
+    // to are given explicitly.
     min_peers: usize,
+    // Selects the connected peers to send the requests to.
+    peer_filter: PeerFilter,
     // Currently it is equivalent to genesis_config.num_block_producer_seats,
     // (see https://cs.github.com/near/nearcore/blob/dae9553670de13c279d3ebd55f17da13d94fa691/nearcore/src/runtime/mod.rs#L1114).
     // AFAICT eventually it will change dynamically (I guess it will be provided in the Block).
+    // It has to be provided explicitly, because the genesis file is not downloaded.
     parts_per_chunk: u64,
 
-    request_timeout: tokio::time::Duration,
+    pub request_timeout: tokio::time::Duration,
     rate_limiter: RateLimiter,
 }
 
@@ -85,6 +154,8 @@ impl Network {
         config: &NearConfig,
         network_adapter: Arc<dyn PeerManagerAdapter>,
         qps_limit: u32,
+        parts_per_chunk: u64,
+        peer_filter: PeerFilter,
     ) -> Arc<Network> {
         Arc::new(Network {
             stats: Default::default(),
@@ -99,16 +170,32 @@ impl Network {
                     received_bytes_per_sec: 0,
                     known_producers: vec![],
                     peer_counter: 0,
+                    peer_scores: Default::default(),
+                    protocol_deprecation: None,
+                    advisories: vec![],
                 }),
                 info_futures: Default::default(),
             }),
             blocks: WeakMap::new(),
             block_headers: WeakMap::new(),
             chunks: WeakMap::new(),
+            state_headers: WeakMap::new(),
+            state_parts: WeakMap::new(),
+            chunk_parts: Default::default(),
+            header_latencies: Default::default(),
+            block_latencies: Default::default(),
+            chunk_latencies: Default::default(),
+            peer_stats: Default::default(),
+            responses: Notify::new(),
 
             chain_id: config.client_config.chain_id.clone(),
-            min_peers: config.client_config.min_num_peers,
-            parts_per_chunk: config.genesis.config.num_block_producer_seats,
+            min_peers: if peer_filter.only.is_empty() {
+                config.client_config.min_num_peers
+            } else {
+                1
+            },
+            peer_filter,
+            parts_per_chunk,
             rate_limiter: RateLimiter::new(
                 time::Duration::from_secs(1) / qps_limit,
                 qps_limit as u64,
@@ -119,7 +206,8 @@ impl Network {
 
     // keep_sending() sends periodically (every self.request_timeout)
     // a NetworkRequest produced by <new_req> in an infinite loop.
-    // The requests are distributed uniformly among all the available peers.
+    // The requests are distributed uniformly among all the available peers
+    // allowed by the peer filter.
     // - keep_sending() completes as soon as ctx expires.
     // - keep_sending() respects the global rate limits, so the actual frequency
     //   of the sends may be lower than expected.
@@ -133,29 +221,55 @@ impl Network {
         let ctx = ctx.with_label("keep_sending");
         async move {
             loop {
-                let mut peers = self_.info(&ctx).await?.connected_peers.clone();
+                let mut peers = self_.peers(&self_.info(&ctx).await?);
                 peers.shuffle(&mut thread_rng());
                 for peer in peers {
                     // TODO: rate limit per peer.
                     self_.rate_limiter.allow(&ctx).await?;
-                    self_
-                        .network_adapter
-                        .do_send(PeerManagerMessageRequest::NetworkRequests(new_req(peer.clone())));
-                    self_.stats.msgs_sent.fetch_add(1, Ordering::Relaxed);
+                    self_.send(&peer, new_req(peer.clone()));
                     ctx.wait(self_.request_timeout).await?;
                 }
             }
         }
     }
 
+    // send() sends the request to the peer right away, bypassing the rate
+    // limiter.
+    pub fn send(&self, peer: &FullPeerInfo, req: NetworkRequests) {
+        self.network_adapter.do_send(PeerManagerMessageRequest::NetworkRequests(req));
+        self.stats.msgs_sent.fetch_add(1, Ordering::Relaxed);
+        self.peer_stats
+            .lock()
+            .unwrap()
+            .entry(peer.peer_info.id.clone())
+            .or_default()
+            .requests_sent += 1;
+    }
+
+    // connected_peers() returns the connected peers allowed by the peer
+    // filter, according to the newest available NetworkInfo. Unlike info(),
+    // it doesn't block.
+    pub fn connected_peers(&self) -> Vec<FullPeerInfo> {
+        return self.peers(&self.data.lock().unwrap().info_);
+    }
+
+    // peers() returns the connected peers allowed by the peer filter.
+    fn peers(&self, info: &NetworkInfo) -> Vec<FullPeerInfo> {
+        return (info.connected_peers.iter())
+            .filter(|p| self.peer_filter.allows(&p.peer_info))
+            .cloned()
+            .collect();
+    }
+
     // info() fetches the state of the newest available NetworkInfo.
-    // It blocks if the number of connected peers is too small.
+    // It blocks if the number of connected peers allowed by the peer filter
+    // is too small.
     pub async fn info(self: &Arc<Self>, ctx: &Ctx) -> anyhow::Result<Arc<NetworkInfo>> {
         let ctx = ctx.clone();
         let (send, recv) = oneshot::channel();
         {
             let mut n = self.data.lock().unwrap();
-            if n.info_.num_connected_peers >= self.min_peers {
+            if self.peers(&n.info_).len() >= self.min_peers {
                 let _ = send.send(n.info_.clone());
             } else {
                 n.info_futures.push(send);
@@ -180,13 +294,26 @@ impl Network {
                 self_.stats.header_start.fetch_add(1, Ordering::Relaxed);
                 let recv = self_.block_headers.get_or_insert(&hash, || Once::new());
                 s.spawn_weak(|ctx| {
-                    self_.keep_sending(&ctx, move |peer| NetworkRequests::BlockHeadersRequest {
-                        hashes: vec![hash.clone()],
-                        peer_id: peer.peer_info.id,
+                    self_.keep_sending(&ctx, {
+                        let self_ = self_.clone();
+                        move |peer| {
+                            self_.header_latencies.lock().unwrap().sent(
+                                hash.clone(),
+                                peer.peer_info.id.clone(),
+                                time::Instant::now(),
+                            );
+                            NetworkRequests::BlockHeadersRequest {
+                                hashes: vec![hash.clone()],
+                                peer_id: peer.peer_info.id,
+                            }
+                        }
                     })
                 });
                 let res = ctx.wrap(recv.wait()).await;
                 self_.stats.header_done.fetch_add(1, Ordering::Relaxed);
+                if let Ok(headers) = &res {
+                    self_.stats.headers_fetched.fetch_add(headers.len() as u64, Ordering::Relaxed);
+                }
                 anyhow::Ok(res?)
             }
         })
@@ -204,6 +331,7 @@ impl Network {
             let hash = hash.clone();
             move |ctx, s| async move {
                 self_.stats.block_start.fetch_add(1, Ordering::Relaxed);
+                let start = time::Instant::now();
                 let recv = self_.blocks.get_or_insert(&hash, || Once::new());
                 s.spawn_weak(|ctx| {
                     self_.keep_sending(&ctx, move |peer| NetworkRequests::BlockRequest {
@@ -213,17 +341,23 @@ impl Network {
                 });
                 let res = ctx.wrap(recv.wait()).await;
                 self_.stats.block_done.fetch_add(1, Ordering::Relaxed);
+                if res.is_ok() {
+                    self_.block_latencies.lock().unwrap().push(start.elapsed());
+                }
                 anyhow::Ok(res?)
             }
         })
         .await
     }
 
-    // fetch_chunk fetches a chunk for the given chunk header.
+    // fetch_chunk fetches all the parts of a chunk for the given chunk header,
+    // together with the receipts to the <num_shards> shards. The parts and
+    // receipts may come in several responses, possibly from different peers.
     pub async fn fetch_chunk(
         self: &Arc<Self>,
         ctx: &Ctx,
         ch: &ShardChunkHeader,
+        num_shards: u64,
     ) -> anyhow::Result<PartialEncodedChunkResponseMsg> {
         Scope::run(ctx, {
             let self_ = self.clone();
@@ -232,6 +366,7 @@ impl Network {
                 let recv = self_.chunks.get_or_insert(&ch.chunk_hash(), || Once::new());
                 // TODO: consider converting wrapping these atomic counters into sth like a Span.
                 self_.stats.chunk_start.fetch_add(1, Ordering::Relaxed);
+                let start = time::Instant::now();
                 s.spawn_weak(|ctx| {
                     self_.keep_sending(&ctx, {
                         let ppc = self_.parts_per_chunk;
@@ -246,7 +381,7 @@ impl Network {
                             request: PartialEncodedChunkRequestMsg {
                                 chunk_hash: ch.chunk_hash(),
                                 part_ords: (0..ppc).collect(),
-                                tracking_shards: Default::default(),
+                                tracking_shards: (0..num_shards).collect(),
                             },
                             create_time: Clock::instant().into(),
                         }
@@ -254,50 +389,233 @@ impl Network {
                 });
                 let res = ctx.wrap(recv.wait()).await;
                 self_.stats.chunk_done.fetch_add(1, Ordering::Relaxed);
+                match res {
+                    Ok(_) => self_.chunk_latencies.lock().unwrap().push(start.elapsed()),
+                    // Drop the parts received so far.
+                    Err(_) => {
+                        self_.chunk_parts.lock().unwrap().remove(&ch.chunk_hash());
+                    }
+                }
+                anyhow::Ok(res?)
+            }
+        })
+        .await
+    }
+
+    // fetch_state_header() fetches the header of the state of the shard
+    // as of the block with hash <sync_hash>.
+    pub async fn fetch_state_header(
+        self: &Arc<Self>,
+        ctx: &Ctx,
+        shard_id: ShardId,
+        sync_hash: &CryptoHash,
+    ) -> anyhow::Result<ShardStateSyncResponseHeader> {
+        Scope::run(ctx, {
+            let self_ = self.clone();
+            let sync_hash = sync_hash.clone();
+            move |ctx, s| async move {
+                self_.stats.state_header_start.fetch_add(1, Ordering::Relaxed);
+                let recv =
+                    self_.state_headers.get_or_insert(&(shard_id, sync_hash), || Once::new());
+                s.spawn_weak(|ctx| {
+                    self_.keep_sending(&ctx, {
+                        let self_ = self_.clone();
+                        move |peer| {
+                            self_.stats.state_requests_sent.fetch_add(1, Ordering::Relaxed);
+                            NetworkRequests::StateRequestHeader {
+                                shard_id,
+                                sync_hash,
+                                target: AccountOrPeerIdOrHash::PeerId(peer.peer_info.id),
+                            }
+                        }
+                    })
+                });
+                let res = ctx.wrap(recv.wait()).await;
+                self_.stats.state_header_done.fetch_add(1, Ordering::Relaxed);
+                anyhow::Ok(res?)
+            }
+        })
+        .await
+    }
+
+    // fetch_state_part() fetches a part of the state of the shard as of
+    // the block with hash <sync_hash> and returns its size.
+    pub async fn fetch_state_part(
+        self: &Arc<Self>,
+        ctx: &Ctx,
+        shard_id: ShardId,
+        sync_hash: &CryptoHash,
+        part_id: u64,
+    ) -> anyhow::Result<u64> {
+        Scope::run(ctx, {
+            let self_ = self.clone();
+            let sync_hash = sync_hash.clone();
+            move |ctx, s| async move {
+                self_.stats.state_part_start.fetch_add(1, Ordering::Relaxed);
+                let recv = self_
+                    .state_parts
+                    .get_or_insert(&(shard_id, sync_hash, part_id), || Once::new());
+                s.spawn_weak(|ctx| {
+                    self_.keep_sending(&ctx, {
+                        let self_ = self_.clone();
+                        move |peer| {
+                            self_.stats.state_requests_sent.fetch_add(1, Ordering::Relaxed);
+                            NetworkRequests::StateRequestPart {
+                                shard_id,
+                                sync_hash,
+                                part_id,
+                                target: AccountOrPeerIdOrHash::PeerId(peer.peer_info.id),
+                            }
+                        }
+                    })
+                });
+                let res = ctx.wrap(recv.wait()).await;
+                self_.stats.state_part_done.fetch_add(1, Ordering::Relaxed);
+                if let Ok(size) = &res {
+                    self_.stats.state_part_bytes.fetch_add(*size, Ordering::Relaxed);
+                }
                 anyhow::Ok(res?)
             }
         })
         .await
     }
 
+    // received_from() counts a response from the peer.
+    fn received_from(&self, peer_id: PeerId) {
+        self.peer_stats.lock().unwrap().entry(peer_id).or_default().responses += 1;
+    }
+
     fn notify(&self, msg: NetworkClientMessages) {
         self.stats.msgs_recv.fetch_add(1, Ordering::Relaxed);
         match msg {
             NetworkClientMessages::NetworkInfo(info) => {
                 let mut n = self.data.lock().unwrap();
+                for peer_info in disconnected_peers(&n.info_.connected_peers, &info.connected_peers)
+                {
+                    info!("peer {} disconnected", peer_info.id);
+                    self.stats.peers_disconnected.fetch_add(1, Ordering::Relaxed);
+                }
                 n.info_ = Arc::new(info);
-                if n.info_.num_connected_peers < self.min_peers {
-                    info!("connected = {}/{}", n.info_.num_connected_peers, self.min_peers);
+                let peers = self.peers(&n.info_).len();
+                if peers < self.min_peers {
+                    info!("connected = {}/{}", peers, self.min_peers);
                     return;
                 }
                 for s in n.info_futures.split_off(0) {
                     s.send(n.info_.clone()).unwrap();
                 }
             }
-            NetworkClientMessages::Block(block, _, _) => {
-                self.blocks.get(&block.hash().clone()).map(|p| p.set(block));
+            NetworkClientMessages::Block(block, peer_id, _) => {
+                self.responses.notify_one();
+                if let Some(p) = self.blocks.get(&block.hash().clone()) {
+                    self.received_from(peer_id);
+                    let _ = p.set(block);
+                }
             }
-            NetworkClientMessages::BlockHeaders(headers, _) => {
+            NetworkClientMessages::BlockHeaders(headers, peer_id) => {
                 if let Some(h) = headers.iter().min_by_key(|h| h.height()) {
                     let hash = h.prev_hash().clone();
-                    self.block_headers.get(&hash).map(|p| p.set(headers));
+                    self.header_latencies.lock().unwrap().received(
+                        hash.clone(),
+                        peer_id.clone(),
+                        time::Instant::now(),
+                    );
+                    if let Some(p) = self.block_headers.get(&hash) {
+                        self.received_from(peer_id);
+                        let _ = p.set(headers);
+                    }
                 }
             }
             NetworkClientMessages::PartialEncodedChunkResponse(resp, _) => {
-                self.chunks.get(&resp.chunk_hash.clone()).map(|p| p.set(resp));
+                let once = match self.chunks.get(&resp.chunk_hash) {
+                    Some(once) => once,
+                    None => return,
+                };
+                if (resp.parts.len() as u64) < self.parts_per_chunk {
+                    self.stats.chunk_responses_incomplete.fetch_add(1, Ordering::Relaxed);
+                }
+                let mut chunk_parts = self.chunk_parts.lock().unwrap();
+                let chunk = chunk_parts.entry(resp.chunk_hash.clone()).or_insert_with(|| {
+                    PartialEncodedChunkResponseMsg {
+                        chunk_hash: resp.chunk_hash.clone(),
+                        parts: vec![],
+                        receipts: vec![],
+                    }
+                });
+                let (parts, receipts) = merge_chunk_response(chunk, resp);
+                self.stats.chunk_parts_fetched.fetch_add(parts, Ordering::Relaxed);
+                self.stats.chunk_receipts_fetched.fetch_add(receipts, Ordering::Relaxed);
+                if chunk.parts.len() as u64 >= self.parts_per_chunk {
+                    let chunk_hash = chunk.chunk_hash.clone();
+                    let _ = once.set(chunk_parts.remove(&chunk_hash).unwrap());
+                }
+            }
+            NetworkClientMessages::StateResponse(info) => {
+                self.responses.notify_one();
+                let (shard_id, sync_hash) = (info.shard_id(), info.sync_hash());
+                let resp = info.take_state_response();
+                if let Some(part_id) = resp.part_id() {
+                    self.stats.state_responses_ok.fetch_add(1, Ordering::Relaxed);
+                    let size = resp.part().as_ref().map_or(0, |(_, data)| data.len() as u64);
+                    self.state_parts.get(&(shard_id, sync_hash, part_id)).map(|p| p.set(size));
+                } else if let Some(header) = resp.take_header() {
+                    self.stats.state_responses_ok.fetch_add(1, Ordering::Relaxed);
+                    self.state_headers.get(&(shard_id, sync_hash)).map(|p| p.set(header));
+                } else {
+                    self.stats.state_responses_empty.fetch_add(1, Ordering::Relaxed);
+                }
             }
             _ => {}
         }
     }
 }
 
+// disconnected_peers() returns the peers among <prev> which are not among
+// the <connected> ones.
+fn disconnected_peers<'a>(
+    prev: &'a [FullPeerInfo],
+    connected: &[FullPeerInfo],
+) -> Vec<&'a PeerInfo> {
+    return (prev.iter())
+        .map(|p| &p.peer_info)
+        .filter(|p| connected.iter().all(|c| c.peer_info.id != p.id))
+        .collect();
+}
+
+// merge_chunk_response() adds to <chunk> the parts and receipts of <resp>
+// which it doesn't contain yet. Returns the numbers of the added parts and
+// receipts.
+fn merge_chunk_response(
+    chunk: &mut PartialEncodedChunkResponseMsg,
+    resp: PartialEncodedChunkResponseMsg,
+) -> (u64, u64) {
+    let (mut parts, mut receipts) = (0, 0);
+    for part in resp.parts {
+        if chunk.parts.iter().all(|p| p.part_ord != part.part_ord) {
+            chunk.parts.push(part);
+            parts += 1;
+        }
+    }
+    for receipt in resp.receipts {
+        if chunk.receipts.iter().all(|r| r.1.to_shard_id != receipt.1.to_shard_id) {
+            chunk.receipts.push(receipt);
+            receipts += 1;
+        }
+    }
+    return (parts, receipts);
+}
+
 pub struct FakeClientActor {
     network: Arc<Network>,
+    // Time the processing of every received block takes, to simulate a slow
+    // client. The actor blocks its thread meanwhile, so the messages from
+    // PeerManagerActor queue up the way they do in front of a busy client.
+    block_delay: time::Duration,
 }
 
 impl FakeClientActor {
-    pub fn new(network: Arc<Network>) -> Self {
-        FakeClientActor { network }
+    pub fn new(network: Arc<Network>, block_delay: time::Duration) -> Self {
+        FakeClientActor { network, block_delay }
     }
 }
 
@@ -328,8 +646,11 @@ impl Handler<NetworkViewClientMessages> for FakeClientActor {
                         hash: genesis_hash(&self.network.chain_id),
                     },
                     height: 0,
+                    header_height: 0,
                     tracked_shards: Default::default(),
                     archival: false,
+                    num_shards: None,
+                    protocol_upgrade: None,
                 }
             }
             NetworkViewClientMessages::AnnounceAccount(_) => {
@@ -346,7 +667,65 @@ impl Handler<NetworkViewClientMessages> for FakeClientActor {
 impl Handler<NetworkClientMessages> for FakeClientActor {
     type Result = NetworkClientResponses;
     fn handle(&mut self, msg: NetworkClientMessages, _ctx: &mut Context<Self>) -> Self::Result {
+        if let NetworkClientMessages::Block(..) = &msg {
+            if !self.block_delay.is_zero() {
+                std::thread::sleep(self.block_delay);
+                self.network.stats.client_blocks_delayed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
         self.network.notify(msg);
         return NetworkClientResponses::NoResponse;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_primitives::sharding::{PartialEncodedChunkPart, ReceiptProof, ShardProof};
+
+    #[test]
+    fn test_disconnected_peers() {
+        let peer = || FullPeerInfo {
+            peer_info: PeerInfo::random(),
+            chain_info: Default::default(),
+            partial_edge_info: Default::default(),
+        };
+        let (a, b, c) = (peer(), peer(), peer());
+        let prev = vec![a.clone(), b.clone()];
+        assert!(disconnected_peers(&prev, &[b.clone(), a.clone(), c.clone()]).is_empty());
+        assert_eq!(vec![&a.peer_info], disconnected_peers(&prev, &[b, c]));
+        assert_eq!(2, disconnected_peers(&prev, &[]).len());
+    }
+
+    #[test]
+    fn test_merge_chunk_response() {
+        let part = |part_ord| PartialEncodedChunkPart {
+            part_ord,
+            part: Box::new([]),
+            merkle_proof: vec![],
+        };
+        let receipt = |to_shard_id| {
+            ReceiptProof(vec![], ShardProof { from_shard_id: 0, to_shard_id, proof: vec![] })
+        };
+        let chunk_hash = ChunkHash(CryptoHash::hash_bytes(b"chunk"));
+        let mut chunk = PartialEncodedChunkResponseMsg {
+            chunk_hash: chunk_hash.clone(),
+            parts: vec![],
+            receipts: vec![],
+        };
+        let resp = PartialEncodedChunkResponseMsg {
+            chunk_hash: chunk_hash.clone(),
+            parts: vec![part(0), part(2)],
+            receipts: vec![receipt(0)],
+        };
+        assert_eq!((2, 1), merge_chunk_response(&mut chunk, resp));
+        let resp = PartialEncodedChunkResponseMsg {
+            chunk_hash,
+            parts: vec![part(1), part(2)],
+            receipts: vec![receipt(0), receipt(1)],
+        };
+        assert_eq!((1, 1), merge_chunk_response(&mut chunk, resp));
+        assert_eq!(3, chunk.parts.len());
+        assert_eq!(2, chunk.receipts.len());
+    }
+}
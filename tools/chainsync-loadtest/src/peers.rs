@@ -0,0 +1,99 @@
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use near_network_primitives::types::PeerInfo;
+
+// PeerMatcher matches the peers to send the requests to (or not to).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerMatcher {
+    // The peer with the given id. If the address of the peer is given as
+    // well, the peer is dialed directly, in addition to the boot nodes.
+    Id(PeerInfo),
+    // All the peers connected at the given IP.
+    Ip(IpAddr),
+}
+
+impl FromStr for PeerMatcher {
+    type Err = anyhow::Error;
+
+    // Parses "<ip>", "<peer id>" or "<peer id>@<ip>:<port>".
+    fn from_str(s: &str) -> anyhow::Result<PeerMatcher> {
+        if let Ok(ip) = s.parse::<IpAddr>() {
+            return Ok(PeerMatcher::Ip(ip));
+        }
+        let peer_info =
+            PeerInfo::from_str(s).map_err(|err| anyhow!("invalid peer {:?}: {}", s, err))?;
+        return Ok(PeerMatcher::Id(peer_info));
+    }
+}
+
+impl PeerMatcher {
+    fn matches(&self, peer: &PeerInfo) -> bool {
+        return match self {
+            PeerMatcher::Id(p) => p.id == peer.id,
+            PeerMatcher::Ip(ip) => peer.addr.map_or(false, |addr| addr.ip() == *ip),
+        };
+    }
+}
+
+// PeerFilter selects the connected peers which the requests are sent to.
+#[derive(Clone, Debug, Default)]
+pub struct PeerFilter {
+    // If not empty, only the matching peers are selected.
+    pub only: Vec<PeerMatcher>,
+    // The matching peers are never selected.
+    pub exclude: Vec<PeerMatcher>,
+}
+
+impl PeerFilter {
+    pub fn allows(&self, peer: &PeerInfo) -> bool {
+        return (self.only.is_empty() || self.only.iter().any(|m| m.matches(peer)))
+            && !self.exclude.iter().any(|m| m.matches(peer));
+    }
+
+    // boot_nodes() returns the selected peers given together with their
+    // addresses, to be dialed directly.
+    pub fn boot_nodes(&self) -> Vec<PeerInfo> {
+        return (self.only.iter())
+            .filter_map(|m| match m {
+                PeerMatcher::Id(p) if p.addr.is_some() => Some(p.clone()),
+                _ => None,
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+    use near_primitives::network::PeerId;
+
+    fn peer(seed: &str, addr: &str) -> PeerInfo {
+        let id = PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed).public_key());
+        return PeerInfo { id, addr: Some(addr.parse().unwrap()), account_id: None };
+    }
+
+    #[test]
+    fn test_peer_filter() {
+        let (a, b, c) =
+            (peer("a", "1.2.3.4:24567"), peer("b", "1.2.3.4:24568"), peer("c", "5.6.7.8:24567"));
+        let id = |p: &PeerInfo| p.id.to_string().parse::<PeerMatcher>().unwrap();
+        assert_eq!(PeerMatcher::Ip("1.2.3.4".parse().unwrap()), "1.2.3.4".parse().unwrap());
+        assert_eq!(PeerMatcher::Id(c.clone()), c.to_string().parse().unwrap());
+        assert!("1.2.3.4:24567".parse::<PeerMatcher>().is_err());
+
+        let filter = PeerFilter::default();
+        assert!([&a, &b, &c].iter().all(|p| filter.allows(p)));
+        assert!(filter.boot_nodes().is_empty());
+
+        let filter = PeerFilter { only: vec!["1.2.3.4".parse().unwrap()], exclude: vec![id(&b)] };
+        assert_eq!(vec![true, false, false], [&a, &b, &c].map(|p| filter.allows(p)));
+
+        let filter =
+            PeerFilter { only: vec![id(&a), c.to_string().parse().unwrap()], exclude: vec![] };
+        assert_eq!(vec![true, false, true], [&a, &b, &c].map(|p| filter.allows(p)));
+        assert_eq!(vec![c], filter.boot_nodes());
+    }
+}
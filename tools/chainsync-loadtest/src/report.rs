@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+
+use crate::fetch_chain::Mode;
+use crate::flood::FloodReport;
+use crate::network::{PeerStats, Stats};
+use near_primitives::network::PeerId;
+use tokio::time;
+
+// Thresholds which the results have to meet for the run to pass.
+#[derive(Debug, Default)]
+pub struct Thresholds {
+    pub min_throughput: Option<f64>,
+    pub max_error_rate: Option<f64>,
+}
+
+// PeerReport summarizes the requests sent to a single peer.
+#[derive(serde::Serialize, Debug)]
+pub struct PeerReport {
+    pub peer_id: String,
+    pub requests_sent: u64,
+    // Responses with headers and blocks, the other responses are not
+    // attributed to peers.
+    pub responses: u64,
+}
+
+// Report summarizes the results of a run, so that they can be checked
+// against the Thresholds and saved for later comparison.
+#[derive(serde::Serialize, Debug)]
+pub struct Report {
+    pub mode: String,
+    pub running_time_secs: f64,
+    pub requests_sent: u64,
+    pub header_batches: u64,
+    pub headers: u64,
+    pub blocks: u64,
+    pub chunks: u64,
+    pub chunk_parts: u64,
+    pub chunk_receipts: u64,
+    // Responses to the chunk requests which were missing some of the parts.
+    pub incomplete_chunk_responses: u64,
+    pub state_parts: u64,
+    // Items fetched per second: blocks in Mode::Full, headers in
+    // Mode::Headers, state parts in Mode::StateSync and responses in
+    // Mode::Flood.
+    pub throughput: f64,
+    // Fraction of the requests sent which didn't complete a fetch, either
+    // because the peer didn't respond (in time) or because another peer
+    // responded first.
+    pub error_rate: f64,
+    // Blocks whose processing has been delayed with --client-delay-ms.
+    pub client_blocks_delayed: u64,
+    // Peers which disconnected during the run.
+    pub peers_disconnected: u64,
+    // The error the run has been interrupted with, if any.
+    pub error: Option<String>,
+    // The thresholds which haven't been met.
+    pub failures: Vec<String>,
+    // Per peer results, the peers which got the most requests first.
+    pub peers: Vec<PeerReport>,
+    // How the targets reacted to the flood, in Mode::Flood.
+    pub flood: Option<FloodReport>,
+}
+
+impl Report {
+    pub fn new(
+        mode: Mode,
+        stats: &Stats,
+        peer_stats: &HashMap<PeerId, PeerStats>,
+        running_time: time::Duration,
+        res: &anyhow::Result<()>,
+    ) -> Report {
+        let t = running_time.as_secs_f64();
+        let load = |x: &std::sync::atomic::AtomicU64| x.load(Ordering::Relaxed);
+        let requests_sent = load(&stats.msgs_sent);
+        let headers = load(&stats.headers_fetched);
+        let blocks = load(&stats.block_done);
+        let state_parts = load(&stats.state_part_done);
+        let flood_responses = load(&stats.flood_responses);
+        let done = load(&stats.header_done)
+            + blocks
+            + load(&stats.chunk_done)
+            + load(&stats.state_header_done)
+            + state_parts
+            + flood_responses;
+        let mut peers: Vec<_> = (peer_stats.iter())
+            .map(|(peer_id, s)| PeerReport {
+                peer_id: peer_id.to_string(),
+                requests_sent: s.requests_sent,
+                responses: s.responses,
+            })
+            .collect();
+        peers.sort_by(|a, b| {
+            b.requests_sent.cmp(&a.requests_sent).then_with(|| a.peer_id.cmp(&b.peer_id))
+        });
+        let fetched = match mode {
+            Mode::Full => blocks,
+            Mode::Headers => headers,
+            Mode::StateSync => state_parts,
+            Mode::Flood => flood_responses,
+        };
+        Report {
+            mode: clap::ArgEnum::to_possible_value(&mode).unwrap().get_name().to_string(),
+            running_time_secs: t,
+            requests_sent,
+            header_batches: load(&stats.header_done),
+            headers,
+            blocks,
+            chunks: load(&stats.chunk_done),
+            chunk_parts: load(&stats.chunk_parts_fetched),
+            chunk_receipts: load(&stats.chunk_receipts_fetched),
+            incomplete_chunk_responses: load(&stats.chunk_responses_incomplete),
+            state_parts,
+            throughput: if t > 0. { fetched as f64 / t } else { 0. },
+            error_rate: if requests_sent > 0 {
+                requests_sent.saturating_sub(done) as f64 / requests_sent as f64
+            } else {
+                0.
+            },
+            client_blocks_delayed: load(&stats.client_blocks_delayed),
+            peers_disconnected: load(&stats.peers_disconnected),
+            error: res.as_ref().err().map(|err| format!("{:#}", err)),
+            failures: vec![],
+            peers,
+            flood: None,
+        }
+    }
+
+    // check() records the thresholds which the results don't meet.
+    pub fn check(&mut self, thresholds: &Thresholds) {
+        if let Some(min) = thresholds.min_throughput {
+            if self.throughput < min {
+                self.failures.push(format!("throughput {:.2}/s < {:.2}/s", self.throughput, min));
+            }
+        }
+        if let Some(max) = thresholds.max_error_rate {
+            if self.error_rate > max {
+                self.failures.push(format!("error rate {:.4} > {:.4}", self.error_rate, max));
+            }
+        }
+    }
+
+    // passed() tells whether the run has met the thresholds.
+    // A run which has been interrupted never passes.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.failures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_crypto::{KeyType, SecretKey};
+
+    #[test]
+    fn test_check() {
+        let stats = Stats::default();
+        stats.msgs_sent.store(10, Ordering::Relaxed);
+        stats.header_done.store(1, Ordering::Relaxed);
+        stats.block_done.store(4, Ordering::Relaxed);
+        stats.chunk_done.store(3, Ordering::Relaxed);
+        let peer_id = |seed| PeerId::new(SecretKey::from_seed(KeyType::ED25519, seed).public_key());
+        let peer_stats = HashMap::from([
+            (peer_id("a"), PeerStats { requests_sent: 3, responses: 1 }),
+            (peer_id("b"), PeerStats { requests_sent: 7, responses: 3 }),
+        ]);
+        let mut report =
+            Report::new(Mode::Full, &stats, &peer_stats, time::Duration::from_secs(2), &Ok(()));
+        assert_eq!("full", report.mode);
+        assert_eq!(
+            vec![(peer_id("b").to_string(), 7, 3), (peer_id("a").to_string(), 3, 1)],
+            report
+                .peers
+                .iter()
+                .map(|p| (p.peer_id.clone(), p.requests_sent, p.responses))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(2., report.throughput);
+        assert_eq!(0.2, report.error_rate);
+        report.check(&Thresholds::default());
+        assert!(report.passed());
+
+        report.check(&Thresholds { min_throughput: Some(2.), max_error_rate: Some(0.2) });
+        assert!(report.passed());
+        report.check(&Thresholds { min_throughput: Some(2.5), max_error_rate: Some(0.1) });
+        assert_eq!(2, report.failures.len());
+        assert!(!report.passed());
+
+        let report = Report::new(
+            Mode::StateSync,
+            &stats,
+            &HashMap::new(),
+            time::Duration::from_secs(2),
+            &Err(anyhow::anyhow!("Got CTRL+C")),
+        );
+        assert_eq!("state-sync", report.mode);
+        assert_eq!(0., report.throughput);
+        assert_eq!(Some("Got CTRL+C".to_string()), report.error);
+        assert!(!report.passed());
+    }
+}
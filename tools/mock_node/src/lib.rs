@@ -10,6 +10,8 @@ use near_network::types::{
     FullPeerInfo, NetworkClientMessages, NetworkInfo, NetworkRequests, NetworkResponses,
     PeerManagerMessageRequest, PeerManagerMessageResponse,
 };
+use near_network_primitives::chaos::{Chaos, ChaosAction, ChaosConfig};
+use near_network_primitives::time;
 use near_network_primitives::types::{
     PartialEdgeInfo, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerInfo,
 };
@@ -48,6 +50,9 @@ pub struct MockNetworkConfig {
     // How long we'll wait until sending replies to the client
     pub response_delay: Duration,
     pub incoming_requests: Option<MockIncomingRequestsConfig>,
+    /// Faults to inject into the messages sent to the client.
+    #[serde(default)]
+    pub chaos: Option<ChaosConfig>,
 }
 
 impl MockNetworkConfig {
@@ -71,7 +76,7 @@ fn default_delay() -> Duration {
 
 impl Default for MockNetworkConfig {
     fn default() -> Self {
-        Self { response_delay: default_delay(), incoming_requests: None }
+        Self { response_delay: default_delay(), incoming_requests: None, chaos: None }
     }
 }
 
@@ -199,6 +204,33 @@ pub struct MockPeerManagerActor {
     /// The simulated peers will stop producing new blocks at this height
     target_height: BlockHeight,
     incoming_requests: IncomingRequests,
+    /// Faults injected into the messages sent to the client.
+    chaos: Option<Chaos>,
+    /// Message held back by `ChaosAction::Reorder`.
+    chaos_held: Option<NetworkClientMessages>,
+}
+
+/// Copy of a message sent to the client, for `ChaosAction::Duplicate`. None for the
+/// messages which the mock network never sends.
+fn clone_client_message(msg: &NetworkClientMessages) -> Option<NetworkClientMessages> {
+    Some(match msg {
+        NetworkClientMessages::Block(block, peer_id, was_requested) => {
+            NetworkClientMessages::Block(block.clone(), peer_id.clone(), *was_requested)
+        }
+        NetworkClientMessages::BlockHeaders(headers, peer_id) => {
+            NetworkClientMessages::BlockHeaders(headers.clone(), peer_id.clone())
+        }
+        NetworkClientMessages::PartialEncodedChunkRequest(request, route_back) => {
+            NetworkClientMessages::PartialEncodedChunkRequest(request.clone(), *route_back)
+        }
+        NetworkClientMessages::PartialEncodedChunkResponse(response, received_time) => {
+            NetworkClientMessages::PartialEncodedChunkResponse(response.clone(), *received_time)
+        }
+        NetworkClientMessages::NetworkInfo(info) => {
+            NetworkClientMessages::NetworkInfo(info.clone())
+        }
+        _ => return None,
+    })
 }
 
 impl MockPeerManagerActor {
@@ -236,6 +268,9 @@ impl MockPeerManagerActor {
             received_bytes_per_sec: 0,
             known_producers: vec![],
             peer_counter: 0,
+            peer_scores: Default::default(),
+            protocol_deprecation: None,
+            advisories: vec![],
         };
         let incoming_requests = IncomingRequests::new(
             &network_config.incoming_requests,
@@ -251,6 +286,47 @@ impl MockPeerManagerActor {
             network_delay: network_config.response_delay,
             target_height,
             incoming_requests,
+            chaos: network_config
+                .chaos
+                .clone()
+                .map(|chaos| Chaos::new(chaos, time::Clock::real().now())),
+            chaos_held: None,
+        }
+    }
+
+    /// Sends `msg` to the client, injecting the faults of `MockNetworkConfig::chaos`.
+    fn send_to_client(
+        &mut self,
+        ctx: &mut Context<MockPeerManagerActor>,
+        msg: NetworkClientMessages,
+    ) {
+        let action = self
+            .chaos
+            .as_ref()
+            .and_then(|chaos| chaos.action(msg.as_ref(), time::Clock::real().now()));
+        match action {
+            None => {}
+            Some(ChaosAction::Drop) => return,
+            Some(ChaosAction::Delay(delay)) => {
+                run_later(ctx, delay, move |act, _ctx| {
+                    let _response = act.client_addr.do_send(msg);
+                });
+                return;
+            }
+            Some(ChaosAction::Reorder) if self.chaos_held.is_none() => {
+                self.chaos_held = Some(msg);
+                return;
+            }
+            Some(ChaosAction::Reorder) => {}
+            Some(ChaosAction::Duplicate) => {
+                if let Some(copy) = clone_client_message(&msg) {
+                    let _response = self.client_addr.do_send(copy);
+                }
+            }
+        }
+        let _response = self.client_addr.do_send(msg);
+        if let Some(held) = self.chaos_held.take() {
+            let _response = self.client_addr.do_send(held);
         }
     }
 
@@ -258,15 +334,15 @@ impl MockPeerManagerActor {
     /// When it is called, it increments peer heights by 1 and sends the block at that height
     /// to ClientActor. In a way, it simulates peers that broadcast new blocks
     fn update_peers(&mut self, ctx: &mut Context<MockPeerManagerActor>) {
-        let _response =
-            self.client_addr.do_send(NetworkClientMessages::NetworkInfo(self.network_info.clone()));
+        self.send_to_client(ctx, NetworkClientMessages::NetworkInfo(self.network_info.clone()));
+        let mut blocks = vec![];
         for peer in self.network_info.connected_peers.iter_mut() {
             let current_height = peer.chain_info.height;
             if current_height <= self.target_height {
                 if let Ok(block) =
                     self.chain_history_access.retrieve_block_by_height(current_height)
                 {
-                    let _response = self.client_addr.do_send(NetworkClientMessages::Block(
+                    blocks.push(NetworkClientMessages::Block(
                         block,
                         peer.peer_info.id.clone(),
                         false,
@@ -275,6 +351,9 @@ impl MockPeerManagerActor {
                 peer.chain_info.height = current_height + 1;
             }
         }
+        for block in blocks {
+            self.send_to_client(ctx, block);
+        }
         self.network_info.highest_height_peers = self.network_info.connected_peers.clone();
         near_performance_metrics::actix::run_later(
             ctx,
@@ -286,31 +365,30 @@ impl MockPeerManagerActor {
     }
 
     fn send_unrequested_block(&mut self, ctx: &mut Context<MockPeerManagerActor>) {
-        if let Some((interval, block)) = &self.incoming_requests.block {
-            let _response = self.client_addr.do_send(NetworkClientMessages::Block(
-                block.clone(),
-                self.network_info.connected_peers[0].peer_info.id.clone(),
-                false,
-            ));
-
-            run_later(ctx, *interval, move |act, ctx| {
+        if let Some((interval, block)) = self.incoming_requests.block.clone() {
+            let peer_id = self.network_info.connected_peers[0].peer_info.id.clone();
+            self.send_to_client(ctx, NetworkClientMessages::Block(block, peer_id, false));
+
+            run_later(ctx, interval, move |act, ctx| {
                 act.send_unrequested_block(ctx);
             });
         }
     }
 
     fn send_chunk_request(&mut self, ctx: &mut Context<MockPeerManagerActor>) {
-        if let Some((interval, request)) = &self.incoming_requests.chunk_request {
-            let _response =
-                self.client_addr.do_send(NetworkClientMessages::PartialEncodedChunkRequest(
-                    request.clone(),
+        if let Some((interval, request)) = self.incoming_requests.chunk_request.clone() {
+            self.send_to_client(
+                ctx,
+                NetworkClientMessages::PartialEncodedChunkRequest(
+                    request,
                     // this can just be nonsense since the PeerManager is mocked out anyway. If/when we update the mock node
                     // to exercise the PeerManager code as well, then this won't matter anyway since the mock code won't be
                     // responsible for it.
                     CryptoHash::default(),
-                ));
+                ),
+            );
 
-            run_later(ctx, *interval, move |act, ctx| {
+            run_later(ctx, interval, move |act, ctx| {
                 act.send_chunk_request(ctx);
             });
         }
@@ -339,31 +417,31 @@ impl Handler<PeerManagerMessageRequest> for MockPeerManagerActor {
         match msg {
             PeerManagerMessageRequest::NetworkRequests(request) => match request {
                 NetworkRequests::BlockRequest { hash, peer_id } => {
-                    run_later(ctx, self.network_delay, move |act, _ctx| {
+                    run_later(ctx, self.network_delay, move |act, ctx| {
                         let block = act.chain_history_access.retrieve_block(&hash).unwrap();
-                        let _response = act
-                            .client_addr
-                            .do_send(NetworkClientMessages::Block(block, peer_id, true));
+                        act.send_to_client(ctx, NetworkClientMessages::Block(block, peer_id, true));
                     });
                 }
                 NetworkRequests::BlockHeadersRequest { hashes, peer_id } => {
-                    run_later(ctx, self.network_delay, move |act, _ctx| {
+                    run_later(ctx, self.network_delay, move |act, ctx| {
                         let headers = act
                             .chain_history_access
                             .retrieve_block_headers(hashes.clone())
                             .unwrap();
-                        let _response = act
-                            .client_addr
-                            .do_send(NetworkClientMessages::BlockHeaders(headers, peer_id));
+                        act.send_to_client(
+                            ctx,
+                            NetworkClientMessages::BlockHeaders(headers, peer_id),
+                        );
                     });
                 }
                 NetworkRequests::PartialEncodedChunkRequest { request, .. } => {
-                    run_later(ctx, self.network_delay, move |act, _ctx| {
+                    run_later(ctx, self.network_delay, move |act, ctx| {
                         let response = act
                             .chain_history_access
                             .retrieve_partial_encoded_chunk(&request)
                             .unwrap();
-                        let _response = act.client_addr.do_send(
+                        act.send_to_client(
+                            ctx,
                             NetworkClientMessages::PartialEncodedChunkResponse(
                                 response,
                                 Clock::instant(),
@@ -10,6 +10,7 @@ use near_primitives::receipt::Receipt;
 use near_primitives::shard_layout;
 use near_primitives::sharding::{ChunkHash, ReceiptProof};
 use near_primitives::syncing::ReceiptProofResponse;
+use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{BlockHeight, ShardId};
 use near_primitives_core::hash::hash;
 use near_primitives_core::types::Gas;
@@ -70,12 +71,24 @@ fn get_incoming_receipts(
     Ok(collect_receipts_from_response(&responses))
 }
 
+/// Transactions to apply with a chunk. Anything else than the ones included in the chunk
+/// allows "what if" analyses, e.g. what would have happened if a transaction was left out.
+pub(crate) enum ChunkTransactions {
+    /// The transactions included in the chunk.
+    Included,
+    /// The transactions included in the chunk, except the ones with the given hashes.
+    Exclude(HashSet<CryptoHash>),
+    /// The given transactions instead of the ones included in the chunk.
+    Replace(Vec<SignedTransaction>),
+}
+
 // returns (apply_result, gas limit)
 pub(crate) fn apply_chunk(
     runtime: &dyn RuntimeAdapter,
     chain_store: &mut ChainStore,
     chunk_hash: ChunkHash,
     target_height: Option<u64>,
+    transactions: ChunkTransactions,
     rng: Option<StdRng>,
 ) -> anyhow::Result<(ApplyTransactionResult, Gas)> {
     let chunk = chain_store.get_chunk(&chunk_hash)?;
@@ -85,7 +98,16 @@ pub(crate) fn apply_chunk(
     let shard_id = chunk.shard_id();
     let prev_state_root = chunk.prev_state_root();
 
-    let transactions = chunk.transactions().clone();
+    let transactions = match transactions {
+        ChunkTransactions::Included => chunk.transactions().to_vec(),
+        ChunkTransactions::Exclude(hashes) => chunk
+            .transactions()
+            .iter()
+            .filter(|tx| !hashes.contains(&tx.get_hash()))
+            .cloned()
+            .collect(),
+        ChunkTransactions::Replace(transactions) => transactions,
+    };
     let prev_block =
         chain_store.get_block(&prev_block_hash).context("Failed getting chunk's prev block")?;
     let prev_height_included = prev_block.chunks()[shard_id as usize].height_included();
@@ -249,7 +271,8 @@ fn apply_tx_in_chunk(
     let mut results = Vec::new();
     for chunk_hash in chunk_hashes {
         println!("found tx in chunk {}. Equivalent command (which will run faster than apply_tx):\nview_state apply_chunk --chunk_hash {}\n", &chunk_hash.0, &chunk_hash.0);
-        let (apply_result, gas_limit) = apply_chunk(runtime, chain_store, chunk_hash, None, None)?;
+        let (apply_result, gas_limit) =
+            apply_chunk(runtime, chain_store, chunk_hash, None, ChunkTransactions::Included, None)?;
         println!(
             "resulting chunk extra:\n{:?}",
             crate::commands::resulting_chunk_extra(&apply_result, gas_limit)
@@ -378,8 +401,14 @@ fn apply_receipt_in_chunk(
         };
         println!("Applying chunk at height {} in shard {}. Equivalent command (which will run faster than apply_receipt):\nview_state apply_chunk --chunk_hash {}\n",
                  height, shard_id, chunk_hash.0);
-        let (apply_result, gas_limit) =
-            apply_chunk(runtime.clone(), chain_store, chunk_hash.clone(), None, None)?;
+        let (apply_result, gas_limit) = apply_chunk(
+            runtime.clone(),
+            chain_store,
+            chunk_hash.clone(),
+            None,
+            ChunkTransactions::Included,
+            None,
+        )?;
         let chunk_extra = crate::commands::resulting_chunk_extra(&apply_result, gas_limit);
         println!("resulting chunk extra:\n{:?}", chunk_extra);
         results.push(apply_result);
@@ -404,6 +433,7 @@ pub(crate) fn apply_receipt(
 
 #[cfg(test)]
 mod test {
+    use crate::apply_chunk::ChunkTransactions;
     use near_chain::{ChainGenesis, ChainStore, ChainStoreAccess, Provenance, RuntimeAdapter};
     use near_chain_configs::Genesis;
     use near_client::test_utils::TestEnv;
@@ -420,6 +450,7 @@ mod test {
     use nearcore::TrackedConfig;
     use rand::rngs::StdRng;
     use rand::SeedableRng;
+    use std::collections::HashSet;
     use std::path::Path;
     use std::sync::Arc;
 
@@ -508,10 +539,44 @@ mod test {
                         &mut chain_store,
                         chunk_hash.clone(),
                         None,
-                        Some(rng),
+                        ChunkTransactions::Included,
+                        Some(rng.clone()),
                     )
                     .unwrap();
                     assert_eq!(apply_result.new_root, new_root);
+
+                    // Excluding all the transactions is the same as replacing them with none.
+                    let tx_hashes: HashSet<_> = chain_store
+                        .get_chunk(chunk_hash)
+                        .unwrap()
+                        .transactions()
+                        .iter()
+                        .map(|tx| tx.get_hash())
+                        .collect();
+                    let (excluded, _) = crate::apply_chunk::apply_chunk(
+                        runtime.as_ref(),
+                        &mut chain_store,
+                        chunk_hash.clone(),
+                        None,
+                        ChunkTransactions::Exclude(tx_hashes.clone()),
+                        Some(rng.clone()),
+                    )
+                    .unwrap();
+                    let (replaced, _) = crate::apply_chunk::apply_chunk(
+                        runtime.as_ref(),
+                        &mut chain_store,
+                        chunk_hash.clone(),
+                        None,
+                        ChunkTransactions::Replace(vec![]),
+                        Some(rng),
+                    )
+                    .unwrap();
+                    assert_eq!(excluded.new_root, replaced.new_root);
+                    assert_eq!(
+                        apply_result.outcomes.len() - tx_hashes.len(),
+                        excluded.outcomes.len()
+                    );
+                    assert!(excluded.outcomes.iter().all(|o| !tx_hashes.contains(&o.id)));
                 }
             }
         }
@@ -1,12 +1,16 @@
+use crate::apply_chunk::ChunkTransactions;
 use crate::commands::*;
+use crate::epoch_analysis::{analyze_epochs, OutputFormat};
+use crate::epoch_diff::{epoch_snapshots, print_epoch_diff};
 use crate::epoch_info;
+use crate::replay_blocks::{record_blocks, replay_blocks};
 use crate::rocksdb_stats::get_rocksdb_stats;
 use clap::{Args, Parser, Subcommand};
 use near_chain_configs::GenesisValidationMode;
 use near_primitives::account::id::AccountId;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::ChunkHash;
-use near_primitives::types::{BlockHeight, ShardId};
+use near_primitives::types::{BlockHeight, EpochHeight, ShardId};
 use near_store::db::Mode;
 use near_store::Store;
 use nearcore::{load_config, NearConfig};
@@ -50,6 +54,19 @@ pub enum StateViewerSubCommand {
     /// Print `EpochInfo` of an epoch given by `--epoch_id` or by `--epoch_height`.
     #[clap(alias = "epoch_info")]
     EpochInfo(EpochInfoCmd),
+    /// Print per-epoch seat price, number of validators, Gini coefficient of their stakes
+    /// and stake churn for a range of epochs of the canonical chain, as CSV or JSON.
+    #[clap(alias = "epoch_analysis")]
+    EpochAnalysis(EpochAnalysisCmd),
+    /// Print the differences between the epoch infos, validator sets and protocol configs of
+    /// a range of epochs of this node and of the node at `--other-home`, e.g. to check that
+    /// a migration or a fork produces the same consensus state.
+    #[clap(alias = "epoch_diff")]
+    EpochDiff(EpochDiffCmd),
+    /// Print the RNG seeds and the settlements of epochs as JSON, which is everything needed
+    /// to reproduce the sampling of their block and chunk producers.
+    #[clap(alias = "epoch_sampling")]
+    EpochSampling(EpochSamplingCmd),
     /// Dump stats for the RocksDB storage.
     #[clap(name = "rocksdb-stats", alias = "rocksdb_stats")]
     RocksDBStats(RocksDBStatsCmd),
@@ -68,6 +85,14 @@ pub enum StateViewerSubCommand {
     /// even if it's not included in any block on disk
     #[clap(alias = "apply_receipt")]
     ApplyReceipt(ApplyReceiptCmd),
+    /// Write the blocks at a range of heights, with their chunks, to a file for
+    /// `replay-blocks`.
+    #[clap(alias = "record_blocks")]
+    RecordBlocks(RecordBlocksCmd),
+    /// Process the blocks recorded with `record-blocks`, without a network. The store must
+    /// contain only the genesis and be opened with `--readwrite`.
+    #[clap(alias = "replay_blocks")]
+    ReplayBlocks(ReplayBlocksCmd),
 }
 
 impl StateViewerSubCommand {
@@ -92,6 +117,11 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::DumpCode(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::DumpAccountStorage(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::EpochInfo(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::EpochAnalysis(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::EpochDiff(cmd) => {
+                cmd.run(home_dir, near_config, store, genesis_validation)
+            }
+            StateViewerSubCommand::EpochSampling(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::RocksDBStats(cmd) => cmd.run(&store_opener.get_path()),
             StateViewerSubCommand::Receipts(cmd) => cmd.run(near_config, store),
             StateViewerSubCommand::Chunks(cmd) => cmd.run(near_config, store),
@@ -99,6 +129,8 @@ impl StateViewerSubCommand {
             StateViewerSubCommand::ApplyChunk(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyTx(cmd) => cmd.run(home_dir, near_config, store),
             StateViewerSubCommand::ApplyReceipt(cmd) => cmd.run(home_dir, near_config, store),
+            StateViewerSubCommand::RecordBlocks(cmd) => cmd.run(near_config, store),
+            StateViewerSubCommand::ReplayBlocks(cmd) => cmd.run(home_dir, near_config, store),
         }
     }
 }
@@ -224,6 +256,49 @@ impl ReplayCmd {
     }
 }
 
+#[derive(Parser)]
+pub struct RecordBlocksCmd {
+    #[clap(long)]
+    start_index: BlockHeight,
+    #[clap(long)]
+    end_index: BlockHeight,
+    #[clap(long, parse(from_os_str))]
+    recording: PathBuf,
+}
+
+impl RecordBlocksCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        record_blocks(self.start_index, self.end_index, &self.recording, &near_config, store)
+            .unwrap_or_else(|e| panic!("Error recording blocks: {:#}", e));
+    }
+}
+
+#[derive(Parser)]
+pub struct ReplayBlocksCmd {
+    #[clap(long, parse(from_os_str))]
+    recording: PathBuf,
+    /// Stop before the first block above this height.
+    #[clap(long)]
+    stop_at_height: Option<BlockHeight>,
+    /// Stop on the first block which fails to be processed.
+    #[clap(long)]
+    stop_on_error: bool,
+}
+
+impl ReplayBlocksCmd {
+    pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
+        replay_blocks(
+            &self.recording,
+            self.stop_at_height,
+            self.stop_on_error,
+            home_dir,
+            &near_config,
+            store,
+        )
+        .unwrap_or_else(|e| panic!("Error replaying blocks: {:#}", e));
+    }
+}
+
 #[derive(Parser)]
 pub struct ApplyRangeCmd {
     #[clap(long)]
@@ -349,6 +424,107 @@ impl EpochInfoCmd {
     }
 }
 
+#[derive(Parser)]
+pub struct EpochAnalysisCmd {
+    /// Height of the first epoch to analyze.
+    #[clap(long, default_value = "0")]
+    start_epoch_height: EpochHeight,
+    /// Height of the last epoch to analyze. Defaults to the epoch of the head.
+    #[clap(long)]
+    end_epoch_height: Option<EpochHeight>,
+    #[clap(long, arg_enum, default_value = "csv")]
+    format: OutputFormat,
+    /// File to write the time series to, instead of stdout.
+    #[clap(long, parse(from_os_str))]
+    output: Option<PathBuf>,
+}
+
+impl EpochAnalysisCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        analyze_epochs(
+            self.start_epoch_height,
+            self.end_epoch_height,
+            self.format,
+            self.output.as_deref(),
+            &near_config,
+            store,
+        )
+        .unwrap_or_else(|e| panic!("Error analyzing epochs: {:#}", e));
+    }
+}
+
+#[derive(Parser)]
+pub struct EpochDiffCmd {
+    /// Home directory of the node to compare with. Its database is opened read-only.
+    #[clap(long, parse(from_os_str))]
+    other_home: PathBuf,
+    /// Height of the first epoch to compare.
+    #[clap(long, default_value = "0")]
+    start_epoch_height: EpochHeight,
+    /// Height of the last epoch to compare. Defaults to the epoch of the head of the chain
+    /// which is behind.
+    #[clap(long)]
+    end_epoch_height: Option<EpochHeight>,
+    /// Don't report the differences at this path and below it, e.g.
+    /// `protocol_config.chain_id`. Can be repeated.
+    #[clap(long)]
+    ignore: Vec<String>,
+    /// Print the differences as JSON.
+    #[clap(long)]
+    json: bool,
+}
+
+impl EpochDiffCmd {
+    pub fn run(
+        self,
+        home_dir: &Path,
+        near_config: NearConfig,
+        store: Store,
+        genesis_validation: GenesisValidationMode,
+    ) {
+        let other_near_config = load_config(&self.other_home, genesis_validation)
+            .unwrap_or_else(|e| panic!("Error loading config: {:#}", e));
+        let other_store =
+            near_store::Store::opener(&self.other_home, &other_near_config.config.store)
+                .mode(Mode::ReadOnly)
+                .open();
+        let snapshots = |home_dir: &Path, near_config: &NearConfig, store: Store| {
+            epoch_snapshots(
+                home_dir,
+                near_config,
+                store,
+                self.start_epoch_height,
+                self.end_epoch_height,
+            )
+            .unwrap_or_else(|e| panic!("Error reading epochs of {}: {:#}", home_dir.display(), e))
+        };
+        let left = snapshots(home_dir, &near_config, store);
+        let right = snapshots(&self.other_home, &other_near_config, other_store);
+        let differences =
+            print_epoch_diff(left, right, self.end_epoch_height.is_none(), &self.ignore, self.json)
+                .unwrap_or_else(|e| panic!("Error comparing epochs: {:#}", e));
+        if differences > 0 {
+            std::process::exit(1);
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct EpochSamplingCmd {
+    #[clap(subcommand)]
+    epoch_selection: epoch_info::EpochSelection,
+    /// Also print the block and chunk producers of every height of the epochs, reproduced
+    /// from the seeds and the stakes of the settlements.
+    #[clap(long)]
+    assignments: bool,
+}
+
+impl EpochSamplingCmd {
+    pub fn run(self, near_config: NearConfig, store: Store) {
+        print_epoch_sampling(self.epoch_selection, self.assignments, near_config, store);
+    }
+}
+
 #[derive(Parser)]
 pub struct RocksDBStatsCmd {
     /// Location of the dumped Rocks DB stats.
@@ -406,12 +582,30 @@ pub struct ApplyChunkCmd {
     chunk_hash: String,
     #[clap(long)]
     target_height: Option<u64>,
+    /// Hash of a transaction of the chunk to leave out. Can be repeated.
+    /// Allows checking what the result would be without the transaction.
+    #[clap(long)]
+    exclude_tx: Vec<String>,
+    /// JSON file with the transactions to apply instead of the ones in the chunk,
+    /// in the format written by dump_tx.
+    #[clap(long, parse(from_os_str), conflicts_with = "exclude_tx")]
+    transactions_file: Option<PathBuf>,
 }
 
 impl ApplyChunkCmd {
     pub fn run(self, home_dir: &Path, near_config: NearConfig, store: Store) {
         let hash = ChunkHash::from(CryptoHash::from_str(&self.chunk_hash).unwrap());
-        apply_chunk(home_dir, near_config, store, hash, self.target_height).unwrap()
+        let transactions = if let Some(path) = &self.transactions_file {
+            let file = std::fs::File::open(path).unwrap();
+            ChunkTransactions::Replace(serde_json::from_reader(file).unwrap())
+        } else if !self.exclude_tx.is_empty() {
+            ChunkTransactions::Exclude(
+                self.exclude_tx.iter().map(|hash| CryptoHash::from_str(hash).unwrap()).collect(),
+            )
+        } else {
+            ChunkTransactions::Included
+        };
+        apply_chunk(home_dir, near_config, store, hash, self.target_height, transactions).unwrap()
     }
 }
 
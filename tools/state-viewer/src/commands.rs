@@ -639,6 +639,28 @@ pub(crate) fn print_epoch_info(
     );
 }
 
+pub(crate) fn print_epoch_sampling(
+    epoch_selection: epoch_info::EpochSelection,
+    assignments: bool,
+    near_config: NearConfig,
+    store: Store,
+) {
+    let genesis_height = near_config.genesis.config.genesis_height;
+    let mut chain_store =
+        ChainStore::new(store.clone(), genesis_height, !near_config.client_config.archive);
+    let mut epoch_manager =
+        EpochManager::new_from_genesis_config(store.clone(), &near_config.genesis.config)
+            .expect("Failed to start Epoch Manager");
+
+    epoch_info::print_epoch_sampling(
+        epoch_selection,
+        assignments,
+        store,
+        &mut chain_store,
+        &mut epoch_manager,
+    );
+}
+
 pub(crate) fn get_receipt(receipt_id: CryptoHash, near_config: NearConfig, store: Store) {
     let chain_store = ChainStore::new(
         store,
@@ -759,6 +781,7 @@ pub(crate) fn apply_chunk(
     store: Store,
     chunk_hash: ChunkHash,
     target_height: Option<u64>,
+    transactions: apply_chunk::ChunkTransactions,
 ) -> anyhow::Result<()> {
     let runtime = NightshadeRuntime::from_config(home_dir, store.clone(), &near_config);
     let mut chain_store = ChainStore::new(
@@ -766,9 +789,22 @@ pub(crate) fn apply_chunk(
         near_config.genesis.config.genesis_height,
         !near_config.client_config.archive,
     );
-    let (apply_result, gas_limit) =
-        apply_chunk::apply_chunk(&runtime, &mut chain_store, chunk_hash, target_height, None)?;
+    let (apply_result, gas_limit) = apply_chunk::apply_chunk(
+        &runtime,
+        &mut chain_store,
+        chunk_hash,
+        target_height,
+        transactions,
+        None,
+    )?;
     println!("resulting chunk extra:\n{:?}", resulting_chunk_extra(&apply_result, gas_limit));
+    println!("outcomes:");
+    for outcome in &apply_result.outcomes {
+        println!(
+            "{} gas_burnt: {} status: {:?}",
+            outcome.id, outcome.outcome.gas_burnt, outcome.outcome.status
+        );
+    }
     Ok(())
 }
 
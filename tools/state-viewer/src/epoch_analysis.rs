@@ -0,0 +1,288 @@
+//! Time series of the decentralization metrics of the epochs of the canonical chain: seat
+//! price, number of validators, inequality of their stakes and churn of the stake between
+//! consecutive epochs.
+use near_chain::{ChainStore, ChainStoreAccess};
+use near_epoch_manager::EpochManager;
+use near_primitives::epoch_manager::epoch_info::EpochInfo;
+use near_primitives::hash::CryptoHash;
+use near_primitives::serialize::u128_dec_format;
+use near_primitives::types::{AccountId, Balance, EpochHeight, EpochId, ProtocolVersion};
+use near_store::Store;
+use nearcore::NearConfig;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug)]
+pub(crate) enum OutputFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(crate) struct EpochStats {
+    pub epoch_height: EpochHeight,
+    pub epoch_id: CryptoHash,
+    pub protocol_version: ProtocolVersion,
+    #[serde(with = "u128_dec_format")]
+    pub seat_price: Balance,
+    #[serde(with = "u128_dec_format")]
+    pub total_stake: Balance,
+    pub num_validators: usize,
+    /// Gini coefficient of the stakes of the validators: 0 if all of them have the same stake,
+    /// approaching 1 as the stake concentrates in a single validator.
+    pub stake_gini: f64,
+    /// Share of the stake which moved between the validators since the previous epoch: the
+    /// sum of the changes of the stakes of all the validators of either epoch divided by the
+    /// total stake of both epochs, between 0 (no change) and 1 (disjoint validator sets).
+    /// Not set for the first epoch of the series.
+    pub stake_churn: Option<f64>,
+    /// Number of validators which weren't validators in the previous epoch.
+    pub validators_joined: Option<usize>,
+    /// Number of validators of the previous epoch which aren't validators anymore.
+    pub validators_left: Option<usize>,
+}
+
+const CSV_HEADER: &str = "epoch_height,epoch_id,protocol_version,seat_price,total_stake,\
+num_validators,stake_gini,stake_churn,validators_joined,validators_left";
+
+impl EpochStats {
+    fn to_csv(&self) -> String {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map_or(String::new(), T::to_string)
+        }
+        format!(
+            "{},{},{},{},{},{},{},{},{},{}",
+            self.epoch_height,
+            self.epoch_id,
+            self.protocol_version,
+            self.seat_price,
+            self.total_stake,
+            self.num_validators,
+            self.stake_gini,
+            opt(&self.stake_churn),
+            opt(&self.validators_joined),
+            opt(&self.validators_left),
+        )
+    }
+}
+
+/// Computes the metrics of the epochs, given in the order of their heights.
+pub(crate) fn epoch_stats(epoch_infos: &[(EpochId, Arc<EpochInfo>)]) -> Vec<EpochStats> {
+    let mut prev_stakes: Option<HashMap<AccountId, Balance>> = None;
+    epoch_infos
+        .iter()
+        .map(|(epoch_id, epoch_info)| {
+            let stakes: HashMap<AccountId, Balance> = epoch_info
+                .validators_iter()
+                .map(|validator| (validator.account_id().clone(), validator.stake()))
+                .collect();
+            let total_stake = stakes.values().sum();
+            let (stake_churn, validators_joined, validators_left) = match &prev_stakes {
+                Some(prev) => (
+                    Some(stake_churn(prev, &stakes)),
+                    Some(stakes.keys().filter(|a| !prev.contains_key(*a)).count()),
+                    Some(prev.keys().filter(|a| !stakes.contains_key(*a)).count()),
+                ),
+                None => (None, None, None),
+            };
+            let stats = EpochStats {
+                epoch_height: epoch_info.epoch_height(),
+                epoch_id: epoch_id.0,
+                protocol_version: epoch_info.protocol_version(),
+                seat_price: epoch_info.seat_price(),
+                total_stake,
+                num_validators: stakes.len(),
+                stake_gini: gini(stakes.values().copied().collect()),
+                stake_churn,
+                validators_joined,
+                validators_left,
+            };
+            prev_stakes = Some(stakes);
+            stats
+        })
+        .collect()
+}
+
+fn gini(mut stakes: Vec<Balance>) -> f64 {
+    let total: f64 = stakes.iter().map(|&s| s as f64).sum();
+    if stakes.is_empty() || total == 0.0 {
+        return 0.0;
+    }
+    stakes.sort();
+    let n = stakes.len() as f64;
+    let weighted: f64 = stakes.iter().enumerate().map(|(i, &s)| (i + 1) as f64 * s as f64).sum();
+    2.0 * weighted / (n * total) - (n + 1.0) / n
+}
+
+fn stake_churn(prev: &HashMap<AccountId, Balance>, cur: &HashMap<AccountId, Balance>) -> f64 {
+    let total: f64 = prev.values().chain(cur.values()).map(|&s| s as f64).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let changed: f64 = prev
+        .iter()
+        .map(|(account_id, &stake)| (account_id, stake, cur.get(account_id).copied().unwrap_or(0)))
+        .chain(
+            cur.iter()
+                .filter(|(account_id, _)| !prev.contains_key(*account_id))
+                .map(|(account_id, &stake)| (account_id, 0, stake)),
+        )
+        .map(|(_, a, b)| a.abs_diff(b) as f64)
+        .sum();
+    changed / total
+}
+
+/// Walks the canonical chain back from the head and returns the epochs with heights in
+/// `start_height..=end_height`, ordered by height. Stops early at the epochs whose blocks have
+/// been garbage collected.
+pub(crate) fn canonical_epoch_infos(
+    start_height: EpochHeight,
+    end_height: Option<EpochHeight>,
+    chain_store: &ChainStore,
+    epoch_manager: &EpochManager,
+) -> anyhow::Result<Vec<(EpochId, Arc<EpochInfo>)>> {
+    let head = chain_store.head()?;
+    let mut block_info = epoch_manager.get_block_info(&head.last_block_hash)?;
+    let mut epoch_infos = vec![];
+    loop {
+        let epoch_id = block_info.epoch_id().clone();
+        let epoch_info = epoch_manager.get_epoch_info(&epoch_id)?;
+        let epoch_height = epoch_info.epoch_height();
+        if epoch_height < start_height {
+            break;
+        }
+        if end_height.map_or(true, |end_height| epoch_height <= end_height) {
+            epoch_infos.push((epoch_id, epoch_info));
+        }
+        let first_block_info = epoch_manager.get_block_info(block_info.epoch_first_block())?;
+        if first_block_info.prev_hash() == &CryptoHash::default() {
+            break;
+        }
+        block_info = match epoch_manager.get_block_info(first_block_info.prev_hash()) {
+            Ok(block_info) => block_info,
+            Err(err) => {
+                eprintln!("Epochs before #{} are not available: {}", epoch_height, err);
+                break;
+            }
+        };
+    }
+    epoch_infos.reverse();
+    Ok(epoch_infos)
+}
+
+/// Writes the metrics of the epochs with heights in `start_height..=end_height` to `output`,
+/// or to stdout.
+pub(crate) fn analyze_epochs(
+    start_height: EpochHeight,
+    end_height: Option<EpochHeight>,
+    format: OutputFormat,
+    output: Option<&Path>,
+    near_config: &NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    let chain_store = ChainStore::new(
+        store.clone(),
+        near_config.genesis.config.genesis_height,
+        !near_config.client_config.archive,
+    );
+    let epoch_manager = EpochManager::new_from_genesis_config(store, &near_config.genesis.config)?;
+    let epoch_infos =
+        canonical_epoch_infos(start_height, end_height, &chain_store, &epoch_manager)?;
+    let stats = epoch_stats(&epoch_infos);
+    match output {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path)?);
+            write_stats(&stats, format, &mut out)?;
+            out.flush()?;
+        }
+        None => write_stats(&stats, format, &mut io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+fn write_stats(stats: &[EpochStats], format: OutputFormat, out: &mut dyn Write) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for epoch_stats in stats {
+                writeln!(out, "{}", epoch_stats.to_csv())?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, stats)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_epoch_manager::test_utils::epoch_info;
+
+    fn make_epoch_info(epoch_height: EpochHeight, stakes: &[(&str, Balance)]) -> Arc<EpochInfo> {
+        let accounts: Vec<_> = stakes.iter().map(|(a, s)| (a.parse().unwrap(), *s)).collect();
+        let num_validators = accounts.len() as u64;
+        Arc::new(epoch_info(
+            epoch_height,
+            accounts,
+            (0..num_validators).collect(),
+            vec![(0..num_validators).collect()],
+            vec![],
+            vec![],
+            Default::default(),
+            vec![],
+            Default::default(),
+            0,
+        ))
+    }
+
+    #[test]
+    fn test_epoch_stats() {
+        let epoch_infos = vec![
+            (EpochId(CryptoHash::hash_bytes(b"1")), make_epoch_info(1, &[("a", 100), ("b", 100)])),
+            (EpochId(CryptoHash::hash_bytes(b"2")), make_epoch_info(2, &[("a", 100), ("c", 300)])),
+        ];
+        let stats = epoch_stats(&epoch_infos);
+        assert_eq!(2, stats.len());
+        assert_eq!(
+            (1, 200, 2),
+            (stats[0].epoch_height, stats[0].total_stake, stats[0].num_validators)
+        );
+        assert_eq!(0.0, stats[0].stake_gini);
+        assert_eq!(
+            (None, None, None),
+            (stats[0].stake_churn, stats[0].validators_joined, stats[0].validators_left)
+        );
+        assert_eq!(0.25, stats[1].stake_gini);
+        // b left with 100 and c joined with 300, out of 600 in total.
+        assert_eq!(Some(400.0 / 600.0), stats[1].stake_churn);
+        assert_eq!((Some(1), Some(1)), (stats[1].validators_joined, stats[1].validators_left));
+
+        let mut csv = vec![];
+        write_stats(&stats, OutputFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!(CSV_HEADER, lines[0]);
+        assert!(lines[1].ends_with(",2,0,,,"), "{}", lines[1]);
+        let mut json = vec![];
+        write_stats(&stats, OutputFormat::Json, &mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!("400", json[1]["total_stake"]);
+        assert_eq!(1, json[1]["validators_joined"]);
+    }
+
+    #[test]
+    fn test_gini() {
+        assert_eq!(0.0, gini(vec![]));
+        assert_eq!(0.0, gini(vec![5, 5, 5]));
+        assert_eq!(0.5, gini(vec![0, 10]));
+        assert!((gini(vec![0, 0, 0, 100]) - 0.75).abs() < 1e-9);
+    }
+}
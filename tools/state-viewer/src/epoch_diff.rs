@@ -0,0 +1,272 @@
+//! Differences between the epochs of the canonical chains of two nodes: their epoch infos,
+//! validator sets and protocol configs, compared epoch by epoch.  Used to check that a
+//! migration or a fork produces the same consensus state as the chain it started from.
+use crate::epoch_analysis::canonical_epoch_infos;
+use near_chain::{ChainStore, RuntimeAdapter};
+use near_chain_configs::ProtocolConfigView;
+use near_epoch_manager::EpochManager;
+use near_primitives::epoch_manager::epoch_info::EpochInfo;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::validator_stake::ValidatorStake;
+use near_primitives::types::{EpochHeight, EpochId};
+use near_store::Store;
+use nearcore::{NearConfig, NightshadeRuntime};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// A value which differs between the epochs of the same height of the two chains.
+#[derive(Serialize, Debug, PartialEq)]
+pub(crate) struct Difference {
+    pub epoch_height: EpochHeight,
+    /// Path of the value in the epoch, e.g. `validators["alice.near"]`.
+    pub path: String,
+    /// The value in the first chain, null if it's missing there.
+    pub left: Value,
+    /// The value in the second chain, null if it's missing there.
+    pub right: Value,
+}
+
+/// The epochs of the canonical chain of a node with heights in `start_height..=end_height`,
+/// as JSON values.
+pub(crate) fn epoch_snapshots(
+    home_dir: &Path,
+    near_config: &NearConfig,
+    store: Store,
+    start_height: EpochHeight,
+    end_height: Option<EpochHeight>,
+) -> anyhow::Result<BTreeMap<EpochHeight, Value>> {
+    let chain_store = ChainStore::new(
+        store.clone(),
+        near_config.genesis.config.genesis_height,
+        !near_config.client_config.archive,
+    );
+    let epoch_manager =
+        EpochManager::new_from_genesis_config(store.clone(), &near_config.genesis.config)?;
+    let runtime = NightshadeRuntime::from_config(home_dir, store, near_config);
+    canonical_epoch_infos(start_height, end_height, &chain_store, &epoch_manager)?
+        .iter()
+        .map(|(epoch_id, epoch_info)| {
+            Ok((epoch_info.epoch_height(), epoch_snapshot(epoch_id, epoch_info, &runtime)?))
+        })
+        .collect()
+}
+
+/// The consensus state of an epoch.  The validators, fishermen and kickouts are keyed by
+/// account, so that a changed stake shows up as a single difference rather than shifting
+/// every validator after it.
+fn epoch_snapshot(
+    epoch_id: &EpochId,
+    epoch_info: &EpochInfo,
+    runtime: &dyn RuntimeAdapter,
+) -> anyhow::Result<Value> {
+    let account_ids = |ids: &[u64]| -> Vec<String> {
+        ids.iter().map(|&id| epoch_info.validator_account_id(id).to_string()).collect()
+    };
+    let kickouts: Map<String, Value> = epoch_info
+        .validator_kickout()
+        .iter()
+        .map(|(account_id, reason)| (account_id.to_string(), json!(reason)))
+        .collect();
+    let protocol_config = ProtocolConfigView::from(runtime.get_protocol_config(epoch_id)?);
+    Ok(json!({
+        "epoch_id": epoch_id.0,
+        "protocol_version": epoch_info.protocol_version(),
+        "seat_price": epoch_info.seat_price().to_string(),
+        "minted_amount": epoch_info.minted_amount().to_string(),
+        "rng_seed": epoch_info.rng_seed().map(CryptoHash),
+        "validators": stakes(epoch_info.validators_iter()),
+        "fishermen": stakes(epoch_info.fishermen_iter()),
+        "block_producers_settlement": account_ids(epoch_info.block_producers_settlement()),
+        "chunk_producers_settlement": epoch_info
+            .chunk_producers_settlement()
+            .iter()
+            .map(|shard_settlement| account_ids(shard_settlement))
+            .collect::<Vec<_>>(),
+        "validator_kickout": kickouts,
+        "protocol_config": protocol_config,
+    }))
+}
+
+fn stakes(validators: impl Iterator<Item = ValidatorStake>) -> Map<String, Value> {
+    validators
+        .map(|validator| (validator.account_id().to_string(), json!(validator.stake().to_string())))
+        .collect()
+}
+
+/// Paths of the values of an epoch which map account ids to values, rather than fields.
+const ACCOUNT_MAPS: &[&str] = &["validators", "fishermen", "validator_kickout"];
+
+/// Compares two values recursively, adding a difference for every leaf value which differs,
+/// unless its path starts with one of `ignore`.
+fn diff_values(
+    epoch_height: EpochHeight,
+    path: &str,
+    left: &Value,
+    right: &Value,
+    ignore: &[String],
+    differences: &mut Vec<Difference>,
+) {
+    if ignore.iter().any(|prefix| path.starts_with(prefix.as_str())) {
+        return;
+    }
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            let keys: BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+            for key in keys {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else if ACCOUNT_MAPS.contains(&path) {
+                    format!("{path}[{key:?}]")
+                } else {
+                    format!("{path}.{key}")
+                };
+                let (left, right) = (left.get(key), right.get(key));
+                diff_values(
+                    epoch_height,
+                    &path,
+                    left.unwrap_or(&Value::Null),
+                    right.unwrap_or(&Value::Null),
+                    ignore,
+                    differences,
+                );
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for i in 0..left.len().max(right.len()) {
+                diff_values(
+                    epoch_height,
+                    &format!("{path}[{i}]"),
+                    left.get(i).unwrap_or(&Value::Null),
+                    right.get(i).unwrap_or(&Value::Null),
+                    ignore,
+                    differences,
+                );
+            }
+        }
+        (left, right) if left != right => differences.push(Difference {
+            epoch_height,
+            path: path.to_string(),
+            left: left.clone(),
+            right: right.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Compares the epochs of two chains with the same heights.  An epoch present in only one
+/// of them is a single difference at the empty path.
+fn diff_epochs(
+    left: &BTreeMap<EpochHeight, Value>,
+    right: &BTreeMap<EpochHeight, Value>,
+    ignore: &[String],
+) -> Vec<Difference> {
+    let heights: BTreeSet<&EpochHeight> = left.keys().chain(right.keys()).collect();
+    let mut differences = vec![];
+    for &epoch_height in heights {
+        match (left.get(&epoch_height), right.get(&epoch_height)) {
+            (Some(left), Some(right)) => {
+                diff_values(epoch_height, "", left, right, ignore, &mut differences)
+            }
+            (left, right) => differences.push(Difference {
+                epoch_height,
+                path: String::new(),
+                left: left.map_or(Value::Null, |_| json!("present")),
+                right: right.map_or(Value::Null, |_| json!("present")),
+            }),
+        }
+    }
+    differences
+}
+
+/// Prints the differences between the epochs of two chains, as text or as JSON.  With
+/// `up_to_common_head`, the epochs after the last epoch of the chain which is behind are
+/// not compared.  Returns the number of differences.
+pub(crate) fn print_epoch_diff(
+    mut left: BTreeMap<EpochHeight, Value>,
+    mut right: BTreeMap<EpochHeight, Value>,
+    up_to_common_head: bool,
+    ignore: &[String],
+    json: bool,
+) -> anyhow::Result<usize> {
+    if up_to_common_head {
+        let last_left = left.keys().next_back().copied();
+        let last_right = right.keys().next_back().copied();
+        if let Some(last) = last_left.into_iter().chain(last_right).min() {
+            left.retain(|&height, _| height <= last);
+            right.retain(|&height, _| height <= last);
+        }
+    }
+    let differences = diff_epochs(&left, &right, ignore);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&differences)?);
+    } else {
+        for difference in &differences {
+            println!(
+                "#{} {}: {} != {}",
+                difference.epoch_height, difference.path, difference.left, difference.right
+            );
+        }
+        println!(
+            "Compared {} epochs: {} differences",
+            left.len().max(right.len()),
+            differences.len()
+        );
+    }
+    Ok(differences.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_diff_epochs() {
+        let epoch = |stake: &str, chain_id: &str| {
+            json!({
+                "protocol_version": 55,
+                "validators": {"alice.near": "100", "bob.near": stake},
+                "block_producers_settlement": ["alice.near", "bob.near"],
+                "protocol_config": {"chain_id": chain_id},
+            })
+        };
+        let left = BTreeMap::from([(1, epoch("200", "mainnet")), (2, epoch("200", "mainnet"))]);
+        let right = BTreeMap::from([(1, epoch("200", "canary")), (2, epoch("300", "canary"))]);
+        let differences = diff_epochs(&left, &right, &[]);
+        assert_eq!(
+            vec![
+                ("protocol_config.chain_id".to_string(), json!("mainnet"), json!("canary")),
+                ("protocol_config.chain_id".to_string(), json!("mainnet"), json!("canary")),
+                (r#"validators["bob.near"]"#.to_string(), json!("200"), json!("300")),
+            ],
+            differences
+                .into_iter()
+                .map(|difference| (difference.path, difference.left, difference.right))
+                .collect::<Vec<_>>()
+        );
+
+        let ignore = vec!["protocol_config.chain_id".to_string()];
+        let mut right = right;
+        right.remove(&2);
+        right.get_mut(&1).unwrap()["block_producers_settlement"] = json!(["alice.near"]);
+        let differences = diff_epochs(&left, &right, &ignore);
+        assert_eq!(
+            vec![
+                Difference {
+                    epoch_height: 1,
+                    path: "block_producers_settlement[1]".to_string(),
+                    left: json!("bob.near"),
+                    right: Value::Null,
+                },
+                Difference {
+                    epoch_height: 2,
+                    path: String::new(),
+                    left: json!("present"),
+                    right: Value::Null,
+                },
+            ],
+            differences
+        );
+    }
+}
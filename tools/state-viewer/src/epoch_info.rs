@@ -5,10 +5,14 @@ use near_chain::{ChainStore, ChainStoreAccess, RuntimeAdapter};
 use near_epoch_manager::EpochManager;
 use near_primitives::account::id::AccountId;
 use near_primitives::epoch_manager::epoch_info::EpochInfo;
-use near_primitives::epoch_manager::AGGREGATOR_KEY;
+use near_primitives::epoch_manager::{sampling, AGGREGATOR_KEY};
 use near_primitives::hash::CryptoHash;
-use near_primitives::types::{BlockHeight, EpochHeight, EpochId, ProtocolVersion, ShardId};
+use near_primitives::serialize::u128_dec_format;
+use near_primitives::types::{
+    Balance, BlockHeight, EpochHeight, EpochId, ProtocolVersion, ShardId, ValidatorId,
+};
 use near_store::{DBCol, Store};
+use serde::Serialize;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -28,6 +32,8 @@ pub(crate) enum EpochSelection {
     BlockHeight { block_height: BlockHeight },
     /// Fetch all epochs with the given protocol version.
     ProtocolVersion { protocol_version: ProtocolVersion },
+    /// Fetch all epochs in which the given account was a validator or a proposal.
+    Validator { account_id: String },
 }
 
 pub(crate) fn print_epoch_info(
@@ -38,6 +44,13 @@ pub(crate) fn print_epoch_info(
     epoch_manager: &mut EpochManager,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
 ) {
+    // Selecting epochs by a validator implies displaying the info about that validator.
+    let validator_account_id = match (&epoch_selection, validator_account_id) {
+        (EpochSelection::Validator { account_id }, None) => {
+            Some(AccountId::from_str(account_id).unwrap())
+        }
+        (_, validator_account_id) => validator_account_id,
+    };
     let epoch_ids = get_epoch_ids(epoch_selection, store, chain_store, epoch_manager);
 
     let head_block_info =
@@ -67,6 +80,135 @@ pub(crate) fn print_epoch_info(
     println!("Found {} epochs", epoch_ids.len());
 }
 
+#[derive(Serialize)]
+struct SettlementEntry {
+    account_id: AccountId,
+    #[serde(with = "u128_dec_format")]
+    stake: Balance,
+}
+
+#[derive(Serialize)]
+struct HeightAssignment {
+    height: BlockHeight,
+    block_producer: AccountId,
+    /// Chunk producer of each shard.
+    chunk_producers: Vec<AccountId>,
+}
+
+/// Everything needed to reproduce the assignment of the producers of an epoch.
+#[derive(Serialize)]
+struct EpochSampling {
+    epoch_id: CryptoHash,
+    epoch_height: EpochHeight,
+    protocol_version: ProtocolVersion,
+    /// Not set for the epochs in which the producers are assigned round-robin over the
+    /// settlements.
+    rng_seed: Option<CryptoHash>,
+    block_producers_settlement: Vec<SettlementEntry>,
+    chunk_producers_settlement: Vec<Vec<SettlementEntry>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    assignments: Option<Vec<HeightAssignment>>,
+}
+
+/// Prints the RNG seeds and the settlements of the selected epochs as JSON and, if
+/// `assignments` is set, the producers of every height of the epochs reproduced with
+/// `near_primitives::epoch_manager::sampling` from the seeds and the stakes alone.
+pub(crate) fn print_epoch_sampling(
+    epoch_selection: EpochSelection,
+    assignments: bool,
+    store: Store,
+    chain_store: &mut ChainStore,
+    epoch_manager: &mut EpochManager,
+) {
+    let epoch_ids = get_epoch_ids(epoch_selection, store, chain_store, epoch_manager);
+    let head_block_info =
+        epoch_manager.get_block_info(&chain_store.head().unwrap().last_block_hash).unwrap();
+    let head_epoch_height =
+        epoch_manager.get_epoch_info(head_block_info.epoch_id()).unwrap().epoch_height();
+    let mut epoch_infos: Vec<(EpochId, Arc<EpochInfo>)> = epoch_ids
+        .iter()
+        .map(|epoch_id| (epoch_id.clone(), epoch_manager.get_epoch_info(&epoch_id).unwrap()))
+        .collect();
+    epoch_infos.sort_by_key(|(_, epoch_info)| epoch_info.epoch_height());
+
+    let settlement = |epoch_info: &EpochInfo, ids: &[ValidatorId]| -> Vec<SettlementEntry> {
+        ids.iter()
+            .map(|&id| {
+                let validator = epoch_info.get_validator(id);
+                SettlementEntry {
+                    account_id: validator.account_id().clone(),
+                    stake: validator.stake(),
+                }
+            })
+            .collect()
+    };
+    let samplings: Vec<EpochSampling> = epoch_infos
+        .iter()
+        .map(|(epoch_id, epoch_info)| {
+            let epoch_info = epoch_info.as_ref();
+            // Heights of the epochs ahead of the head aren't known yet.
+            let assignments =
+                (assignments && epoch_info.epoch_height() <= head_epoch_height).then(|| {
+                    get_block_height_range(epoch_info, chain_store, epoch_manager)
+                        .map(|height| reproduce_assignment(epoch_info, height))
+                        .collect()
+                });
+            EpochSampling {
+                epoch_id: epoch_id.0,
+                epoch_height: epoch_info.epoch_height(),
+                protocol_version: epoch_info.protocol_version(),
+                rng_seed: epoch_info.rng_seed().map(CryptoHash),
+                block_producers_settlement: settlement(
+                    epoch_info,
+                    epoch_info.block_producers_settlement(),
+                ),
+                chunk_producers_settlement: (epoch_info.chunk_producers_settlement().iter())
+                    .map(|shard_settlement| settlement(epoch_info, shard_settlement))
+                    .collect(),
+                assignments,
+            }
+        })
+        .collect();
+    println!("{}", serde_json::to_string_pretty(&samplings).unwrap());
+}
+
+/// Producers of the block and of the chunks at `height`, computed without the samplers of the
+/// `EpochInfo`.
+fn reproduce_assignment(epoch_info: &EpochInfo, height: BlockHeight) -> HeightAssignment {
+    let sample = |ids: &[ValidatorId], seed: Option<[u8; 32]>| -> ValidatorId {
+        let index = match seed {
+            Some(seed) => {
+                let stakes: Vec<Balance> =
+                    ids.iter().map(|&id| epoch_info.get_validator(id).stake()).collect();
+                sampling::sample_producer(&stakes, seed)
+            }
+            None => (height % ids.len() as u64) as usize,
+        };
+        ids[index]
+    };
+    let account_id = |id: ValidatorId| epoch_info.get_validator(id).account_id().clone();
+    let rng_seed = epoch_info.rng_seed();
+    let block_producer = sample(
+        epoch_info.block_producers_settlement(),
+        rng_seed.map(|rng_seed| sampling::block_producer_seed(&rng_seed, height)),
+    );
+    let chunk_producers = (epoch_info.chunk_producers_settlement().iter())
+        .enumerate()
+        .map(|(shard_id, shard_settlement)| {
+            let seed = rng_seed.map(|rng_seed| {
+                sampling::chunk_producer_seed(
+                    &rng_seed,
+                    height,
+                    shard_id as ShardId,
+                    epoch_info.protocol_version(),
+                )
+            });
+            account_id(sample(shard_settlement, seed))
+        })
+        .collect();
+    HeightAssignment { height, block_producer: account_id(block_producer), chunk_producers }
+}
+
 // Iterate over each epoch starting from the head. Find the requested epoch and its previous epoch
 // and use that to determine the block range corresponding to the epoch.
 fn get_block_height_range(
@@ -136,6 +278,17 @@ fn get_epoch_ids(
                 epoch_info.protocol_version() == protocol_version
             })
         }
+        EpochSelection::Validator { account_id } => {
+            // Fetch all epochs in which the account was a validator, or proposed to be one,
+            // which leaves a trace as a stake change or a kickout for not enough stake.
+            let account_id = AccountId::from_str(&account_id).unwrap();
+            iterate_and_filter(store, |epoch_info| {
+                epoch_info.account_is_validator(&account_id)
+                    || epoch_info.account_is_fisherman(&account_id)
+                    || epoch_info.stake_change().contains_key(&account_id)
+                    || epoch_info.validator_kickout().contains_key(&account_id)
+            })
+        }
     }
 }
 
@@ -170,6 +323,9 @@ fn display_epoch_info(
     runtime_adapter: Arc<dyn RuntimeAdapter>,
 ) {
     println!("{:?}: {:#?}", epoch_id, epoch_info);
+    if let Some(account_id) = validator_account_id {
+        display_validator_role(epoch_info, account_id);
+    }
     if epoch_info.epoch_height() >= *head_epoch_height {
         println!("Epoch information for this epoch is not yet available, skipping.");
         return;
@@ -186,6 +342,39 @@ fn display_epoch_info(
     }
 }
 
+fn display_validator_role(epoch_info: &EpochInfo, account_id: &AccountId) {
+    let role = if let Some(validator_id) = epoch_info.get_validator_id(account_id) {
+        if epoch_info.block_producers_settlement().contains(validator_id) {
+            "block producer"
+        } else if epoch_info
+            .chunk_producers_settlement()
+            .iter()
+            .any(|shard_producers| shard_producers.contains(validator_id))
+        {
+            "chunk producer"
+        } else {
+            "validator"
+        }
+    } else if epoch_info.account_is_fisherman(account_id) {
+        "fisherman"
+    } else {
+        "proposal"
+    };
+    let stake = epoch_info
+        .get_validator_by_account(account_id)
+        .or_else(|| epoch_info.get_fisherman_by_account(account_id))
+        .map_or(0, |validator_stake| validator_stake.stake());
+    println!(
+        "Validator {} in epoch #{}: role: {}, stake: {}, stake change: {:?}, reward: {:?}",
+        account_id,
+        epoch_info.epoch_height(),
+        role,
+        stake,
+        epoch_info.stake_change().get(account_id),
+        epoch_info.validator_reward().get(account_id),
+    );
+}
+
 fn display_validator_info(
     epoch_id: &EpochId,
     epoch_info: &EpochInfo,
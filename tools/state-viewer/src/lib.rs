@@ -4,7 +4,10 @@ mod apply_chain_range;
 mod apply_chunk;
 pub mod cli;
 mod commands;
+mod epoch_analysis;
+mod epoch_diff;
 mod epoch_info;
+mod replay_blocks;
 mod rocksdb_stats;
 mod state_dump;
 mod tx_dump;
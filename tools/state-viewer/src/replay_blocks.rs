@@ -0,0 +1,240 @@
+//! Deterministic replay of a recorded stream of blocks, for reproducing consensus bugs
+//! without a network.
+//!
+//! `record-blocks` dumps the blocks at a range of heights, including the forks, together
+//! with their chunks, as `RecordedBlock`s each preceded by the length of its borsh
+//! serialization as u32 little endian. `replay-blocks` feeds such a recording, in order, to
+//! a `Chain` on top of the store of the home directory, which must contain only the genesis.
+//! Blocks and chunks are fully validated and applied, as if they were received from peers.
+//! Once the replay stops, the resulting store can be inspected with the other commands of
+//! the state viewer.
+use borsh::{BorshDeserialize, BorshSerialize};
+use near_chain::test_utils::wait_for_block_in_processing;
+use near_chain::{
+    BlockProcessingArtifact, Chain, ChainGenesis, ChainStore, ChainStoreAccess,
+    DoomslugThresholdMode, Provenance,
+};
+use near_primitives::block::Block;
+use near_primitives::sharding::{PartialEncodedChunk, ShardChunk};
+use near_primitives::types::BlockHeight;
+use near_primitives::utils::MaybeValidated;
+use near_store::Store;
+use nearcore::{NearConfig, NightshadeRuntime};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecordedBlock {
+    pub block: Block,
+    /// Chunks included in the block which were available in the recorded store.
+    pub chunks: Vec<ShardChunk>,
+    /// Partial chunks of all the chunks included in the block.
+    pub partial_chunks: Vec<PartialEncodedChunk>,
+}
+
+/// Writes all the blocks at heights `start_height..=end_height` to `path`, ordered by height
+/// and then by hash.
+pub(crate) fn record_blocks(
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+    path: &Path,
+    near_config: &NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    let chain_store = ChainStore::new(
+        store,
+        near_config.genesis.config.genesis_height,
+        !near_config.client_config.archive,
+    );
+    let num_blocks = write_blocks(start_height, end_height, path, &chain_store)?;
+    println!("Recorded {} blocks to {}", num_blocks, path.display());
+    Ok(())
+}
+
+fn write_blocks(
+    start_height: BlockHeight,
+    end_height: BlockHeight,
+    path: &Path,
+    chain_store: &ChainStore,
+) -> anyhow::Result<usize> {
+    let mut file = BufWriter::new(File::create(path)?);
+    let mut num_blocks = 0;
+    for height in start_height..=end_height {
+        let mut hashes: Vec<_> = match chain_store.get_all_block_hashes_by_height(height) {
+            Ok(hashes) => hashes.values().flatten().cloned().collect(),
+            Err(_) => continue,
+        };
+        hashes.sort();
+        for hash in hashes {
+            let block = chain_store.get_block(&hash)?;
+            let mut chunks = vec![];
+            let mut partial_chunks = vec![];
+            for chunk_header in block.chunks().iter() {
+                if chunk_header.height_included() != height {
+                    continue;
+                }
+                let chunk_hash = chunk_header.chunk_hash();
+                partial_chunks.push(chain_store.get_partial_chunk(&chunk_hash)?.as_ref().clone());
+                if let Ok(chunk) = chain_store.get_chunk(&chunk_hash) {
+                    chunks.push(chunk.as_ref().clone());
+                }
+            }
+            let recorded = RecordedBlock { block, chunks, partial_chunks }.try_to_vec()?;
+            file.write_all(&(recorded.len() as u32).to_le_bytes())?;
+            file.write_all(&recorded)?;
+            num_blocks += 1;
+        }
+    }
+    file.flush()?;
+    Ok(num_blocks)
+}
+
+/// Reads all the blocks of a recording.
+pub fn read_blocks(path: &Path) -> io::Result<Vec<RecordedBlock>> {
+    let data = std::fs::read(path)?;
+    let mut blocks = vec![];
+    let mut rest = data.as_slice();
+    while !rest.is_empty() {
+        let len = u32::deserialize(&mut rest)? as usize;
+        if rest.len() < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated block"));
+        }
+        blocks.push(RecordedBlock::try_from_slice(&rest[..len])?);
+        rest = &rest[len..];
+    }
+    Ok(blocks)
+}
+
+/// Processes the recorded blocks on top of the store of the home directory, see
+/// `process_blocks`.
+pub(crate) fn replay_blocks(
+    path: &Path,
+    stop_at_height: Option<BlockHeight>,
+    stop_on_error: bool,
+    home_dir: &Path,
+    near_config: &NearConfig,
+    store: Store,
+) -> anyhow::Result<()> {
+    let blocks = read_blocks(path)?;
+    let runtime = NightshadeRuntime::from_config(home_dir, store, near_config);
+    let chain_genesis = ChainGenesis::new(&near_config.genesis);
+    let mut chain = Chain::new(
+        Arc::new(runtime),
+        &chain_genesis,
+        DoomslugThresholdMode::TwoThirds,
+        !near_config.client_config.archive,
+    )?;
+    let (num_processed, num_failed) =
+        process_blocks(&mut chain, blocks, stop_at_height, stop_on_error)?;
+    let head = chain.head()?;
+    println!(
+        "Processed {} blocks, {} failed. Head: {} at height {}",
+        num_processed, num_failed, head.last_block_hash, head.height
+    );
+    Ok(())
+}
+
+/// Processes the blocks in order and returns the number of the processed and of the failed
+/// blocks. Stops before the first block above `stop_at_height`, and on the first block which
+/// fails to be processed if `stop_on_error` is set, otherwise the failures are reported and
+/// the replay continues.
+fn process_blocks(
+    chain: &mut Chain,
+    blocks: Vec<RecordedBlock>,
+    stop_at_height: Option<BlockHeight>,
+    stop_on_error: bool,
+) -> anyhow::Result<(usize, usize)> {
+    let me = None;
+    let mut num_processed = 0;
+    let mut num_failed = 0;
+    for recorded in blocks {
+        let height = recorded.block.header().height();
+        if stop_at_height.map_or(false, |stop_at_height| height > stop_at_height) {
+            println!("Reached height {}, stopping", height);
+            break;
+        }
+        let hash = *recorded.block.hash();
+        let mut store_update = chain.mut_store().store_update();
+        for chunk in recorded.chunks {
+            store_update.save_chunk(chunk);
+        }
+        for partial_chunk in recorded.partial_chunks {
+            store_update.save_partial_chunk(partial_chunk);
+        }
+        store_update.commit()?;
+
+        let mut artifacts = BlockProcessingArtifact::default();
+        let result = chain
+            .start_process_block_async(
+                &me,
+                MaybeValidated::from(recorded.block),
+                Provenance::NONE,
+                &mut artifacts,
+                Arc::new(|_| {}),
+            )
+            .and_then(|()| {
+                wait_for_block_in_processing(chain, &hash).unwrap();
+                let (_, mut errors) =
+                    chain.postprocess_ready_blocks(&me, &mut artifacts, Arc::new(|_| {}));
+                errors.remove(&hash).map_or(Ok(()), Err)
+            });
+        match result {
+            Ok(()) => {
+                num_processed += 1;
+                println!("Processed block {} at height {}", hash, height);
+            }
+            Err(err) => {
+                num_failed += 1;
+                println!("Failed to process block {} at height {}: {}", hash, height, err);
+                if stop_on_error {
+                    break;
+                }
+            }
+        }
+    }
+    Ok((num_processed, num_failed))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use near_chain::RuntimeAdapter;
+    use near_chain_configs::Genesis;
+    use near_client::test_utils::TestEnv;
+    use near_store::test_utils::create_test_store;
+    use nearcore::config::GenesisExt;
+
+    #[test]
+    fn test_record_and_replay_blocks() {
+        let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+        let mut chain_genesis = ChainGenesis::test();
+        chain_genesis.gas_limit = genesis.config.gas_limit;
+        let runtime: Arc<dyn RuntimeAdapter> =
+            Arc::new(NightshadeRuntime::test(Path::new("."), create_test_store(), &genesis));
+        let mut env = TestEnv::builder(chain_genesis.clone())
+            .validator_seats(1)
+            .runtime_adapters(vec![runtime])
+            .build();
+        for height in 1..=10 {
+            env.produce_block(0, height);
+        }
+
+        let tmp_dir = tempfile::Builder::new().prefix("replay_blocks").tempdir().unwrap();
+        let path = tmp_dir.path().join("blocks");
+        assert_eq!(10, write_blocks(1, 10, &path, env.clients[0].chain.store()).unwrap());
+        let blocks = read_blocks(&path).unwrap();
+
+        let runtime =
+            Arc::new(NightshadeRuntime::test(Path::new("."), create_test_store(), &genesis));
+        let mut chain =
+            Chain::new(runtime, &chain_genesis, env.clients[0].chain.doomslug_threshold_mode, true)
+                .unwrap();
+        assert_eq!((7, 0), process_blocks(&mut chain, blocks.clone(), Some(7), false).unwrap());
+        assert_eq!(7, chain.head().unwrap().height);
+        // Blocks which are already processed fail, but don't stop the replay.
+        assert_eq!((3, 7), process_blocks(&mut chain, blocks, None, false).unwrap());
+        assert_eq!(env.clients[0].chain.head().unwrap(), chain.head().unwrap());
+    }
+}
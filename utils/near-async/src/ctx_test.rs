@@ -1,4 +1,4 @@
-use crate::concurrency::{ctx, Ctx};
+use crate::{ctx, Ctx};
 
 use futures::task;
 use std::future::Future;
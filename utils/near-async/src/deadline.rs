@@ -0,0 +1,34 @@
+use crate::{ctx, Ctx};
+use std::future::Future;
+use tokio::time;
+
+// deadline() executes f with a child context which gets cancelled at
+// <deadline>. Returns ctx::Error::Timeout if f doesn't complete by then, or
+// ctx::Error::Cancelled if the parent context gets cancelled first.
+// It replaces tokio::time::timeout() for the context-aware code: f gets
+// notified about the cancellation via its context, so it can shut down
+// the routines it has spawned.
+pub async fn deadline<F, T>(
+    ctx: &Ctx,
+    deadline: time::Instant,
+    f: impl FnOnce(Ctx) -> F,
+) -> Result<T, ctx::Error>
+where
+    F: Future<Output = T>,
+{
+    let ctx = ctx.with_deadline(deadline);
+    return ctx.wrap(f(ctx.clone())).await;
+}
+
+// timeout() is the same as deadline() but you provide a duration,
+// rather than an instant.
+pub async fn timeout<F, T>(
+    ctx: &Ctx,
+    timeout: time::Duration,
+    f: impl FnOnce(Ctx) -> F,
+) -> Result<T, ctx::Error>
+where
+    F: Future<Output = T>,
+{
+    return deadline(ctx, time::Instant::now() + timeout, f).await;
+}
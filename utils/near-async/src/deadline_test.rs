@@ -0,0 +1,23 @@
+use crate::{ctx, deadline, timeout, Ctx};
+use tokio::time;
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout() {
+    let ctx = Ctx::background();
+    let sec = time::Duration::from_secs(1);
+    let res = timeout(&ctx, 2 * sec, |ctx| async move { ctx.wait(sec).await.map(|()| 1) }).await;
+    assert_eq!(Ok(Ok(1)), res);
+    let res = timeout(&ctx, sec, |ctx| async move { ctx.wait(2 * sec).await }).await;
+    assert_eq!(Err(ctx::Error::Timeout), res);
+
+    // The deadline of the parent context applies as well.
+    let parent = ctx.with_timeout(sec);
+    let d = time::Instant::now() + 2 * sec;
+    let res = deadline(&parent, d, |ctx| async move { ctx.wait(2 * sec).await }).await;
+    assert_eq!(Err(ctx::Error::Timeout), res);
+
+    let parent = ctx.with_cancel();
+    parent.cancel();
+    let res = timeout(&parent, sec, |_ctx| std::future::pending::<()>()).await;
+    assert_eq!(Err(ctx::Error::Cancelled), res);
+}
@@ -0,0 +1,33 @@
+//! Utilities for the structured concurrency on top of tokio: contexts which
+//! broadcast cancellation and deadlines to the routines (see `Ctx`), scopes
+//! bounding the lifetime of the spawned routines (see `Scope`), and helpers
+//! built on top of them: rate limiting, bounded parallel map and deadlines.
+//!
+//! The chainsync loadtest tool is built on them. In nearcore itself they are
+//! only used by the network crate to resolve the boot nodes published in DNS:
+//! its sync and cancellation paths run on actix actors and haven't been
+//! ported.
+pub mod ctx;
+mod deadline;
+mod once;
+mod parallel;
+mod rate_limiter;
+pub mod scope;
+pub mod weak_map;
+
+#[cfg(test)]
+mod ctx_test;
+#[cfg(test)]
+mod deadline_test;
+#[cfg(test)]
+mod parallel_test;
+#[cfg(test)]
+mod scope_test;
+
+pub use ctx::{Ctx, CtxWithCancel};
+pub use deadline::{deadline, timeout};
+pub use once::Once;
+pub use parallel::parallel_map;
+pub use rate_limiter::RateLimiter;
+pub use scope::Scope;
+pub use weak_map::WeakMap;
@@ -0,0 +1,27 @@
+use crate::Ctx;
+use futures::{StreamExt, TryStreamExt};
+use std::future::Future;
+
+// parallel_map() calls f on each of the items, with at most <limit> calls
+// running concurrently, and returns the results in the order of the items.
+// It stops at the first error (the calls still running are dropped) or once
+// the context gets cancelled. Unlike Scope::spawn(), the calls are not
+// spawned, so they don't have to be 'static.
+pub async fn parallel_map<I, F, Fut, T>(
+    ctx: &Ctx,
+    limit: usize,
+    items: I,
+    mut f: F,
+) -> anyhow::Result<Vec<T>>
+where
+    I: IntoIterator,
+    F: FnMut(Ctx, I::Item) -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    if limit == 0 {
+        panic!("limit has to be non-zero");
+    }
+    let calls = futures::stream::iter(items).map(|item| f(ctx.clone(), item)).buffered(limit);
+    let res: anyhow::Result<Vec<T>> = ctx.wrap(calls.try_collect()).await?;
+    return res;
+}
@@ -0,0 +1,35 @@
+use crate::{parallel_map, Ctx};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::time;
+
+#[tokio::test(start_paused = true)]
+async fn test_parallel_map() {
+    let ctx = Ctx::background();
+    let running = AtomicUsize::new(0);
+    let max_running = AtomicUsize::new(0);
+    let res = parallel_map(&ctx, 3, (0..10).rev(), |ctx, i: u64| {
+        let (running, max_running) = (&running, &max_running);
+        async move {
+            let n = running.fetch_add(1, Ordering::SeqCst) + 1;
+            max_running.fetch_max(n, Ordering::SeqCst);
+            // The calls complete out of order.
+            ctx.wait(time::Duration::from_millis(i)).await?;
+            running.fetch_sub(1, Ordering::SeqCst);
+            anyhow::Ok(i * 2)
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!((0..10).rev().map(|i| i * 2).collect::<Vec<_>>(), res);
+    assert_eq!(3, max_running.load(Ordering::SeqCst));
+
+    // The first error is returned.
+    let res = parallel_map(&ctx, 3, 0..10, |_ctx, i| async move {
+        if i == 5 {
+            anyhow::bail!("failed at {}", i);
+        }
+        Ok(i)
+    })
+    .await;
+    assert_eq!("failed at 5", res.unwrap_err().to_string());
+}
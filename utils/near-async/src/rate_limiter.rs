@@ -1,4 +1,4 @@
-use crate::concurrency::Ctx;
+use crate::Ctx;
 use std::sync::Arc;
 use tokio::time;
 
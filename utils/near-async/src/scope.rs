@@ -1,4 +1,4 @@
-use crate::concurrency::{ctx, Ctx, CtxWithCancel};
+use crate::{ctx, Ctx, CtxWithCancel};
 use std::future::Future;
 use std::sync::{Arc, Mutex};
 
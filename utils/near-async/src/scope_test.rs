@@ -1,4 +1,4 @@
-use crate::concurrency::scope::WaitGroup;
+use crate::scope::WaitGroup;
 
 #[tokio::test]
 async fn test_wait_group() {
@@ -359,6 +359,11 @@ impl<I, T: AsyncWrite + Unpin, U: Encoder<I>, K: 'static + EncoderCallBack>
         self.inner.0.borrow().flags.contains(Flags::CLOSED)
     }
 
+    /// Returns the number of bytes waiting in the write buffer.
+    pub fn buffer_len(&self) -> usize {
+        self.inner.0.borrow().buffer.len()
+    }
+
     /// Sets the write buffer capacity.
     pub fn set_buffer_capacity(&mut self, low: usize, high: usize) {
         let mut inner = self.inner.0.borrow_mut();